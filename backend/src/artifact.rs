@@ -0,0 +1,197 @@
+use std::fmt::Write;
+
+use json::{object, JsonValue};
+use number::{AbstractNumberType, FieldElement};
+
+use crate::Publics;
+
+/// The version of the proof artifact format, increased on incompatible changes.
+pub const ARTIFACT_VERSION: u32 = 1;
+
+/// A proof together with everything needed to verify it, apart from the PIL file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofArtifact {
+    pub version: u32,
+    /// The name of the backend that created the proof.
+    pub backend: String,
+    /// The modulus of the field the PIL file was compiled in.
+    pub field: AbstractNumberType,
+    /// The fingerprint of the analyzed PIL file, see `Analyzed::fingerprint`.
+    pub fingerprint: u64,
+    /// The public values by name, in declaration order.
+    pub publics: Vec<(String, AbstractNumberType)>,
+    /// The proof as serialized by the backend.
+    pub proof: Vec<u8>,
+}
+
+impl ProofArtifact {
+    pub fn new<T: FieldElement>(
+        backend: &str,
+        fingerprint: u64,
+        publics: &Publics<T>,
+        proof: Vec<u8>,
+    ) -> Self {
+        ProofArtifact {
+            version: ARTIFACT_VERSION,
+            backend: backend.to_string(),
+            field: T::modulus(),
+            fingerprint,
+            publics: publics
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_integer()))
+                .collect(),
+            proof,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let publics = self
+            .publics
+            .iter()
+            .map(|(name, value)| object! { name: name.as_str(), value: value.to_string() })
+            .collect::<Vec<_>>();
+        object! {
+            version: self.version,
+            backend: self.backend.as_str(),
+            field: self.field.to_string(),
+            fingerprint: format!("{:016x}", self.fingerprint),
+            publics: publics,
+            proof: to_hex(&self.proof),
+        }
+        .pretty(2)
+    }
+
+    /// The JSON Schema document of the output of `to_json`.
+    pub fn json_schema() -> JsonValue {
+        let decimal = || object! { type: "string", description: "A decimal number." };
+        object! {
+            "$schema": pil_analyzer::json_schema::DIALECT,
+            title: "powdr proof artifact",
+            description: "A proof together with everything needed to verify it, apart from the PIL file.",
+            type: "object",
+            properties: {
+                version: { const: ARTIFACT_VERSION },
+                backend: { type: "string", description: "The name of the backend that created the proof." },
+                field: decimal(),
+                fingerprint: {
+                    type: "string",
+                    description: "The fingerprint of the analyzed PIL file, as 16 hexadecimal digits.",
+                },
+                publics: {
+                    description: "The public values by name, in declaration order.",
+                    type: "array",
+                    items: {
+                        type: "object",
+                        properties: { name: { type: "string" }, value: decimal() },
+                        required: ["name", "value"],
+                        additionalProperties: false,
+                    },
+                },
+                proof: {
+                    type: "string",
+                    description: "The proof as serialized by the backend, in hexadecimal.",
+                },
+            },
+            required: ["version", "backend", "field", "fingerprint", "publics", "proof"],
+            additionalProperties: false,
+        }
+    }
+
+    pub fn from_json(input: &str) -> Result<Self, String> {
+        let parsed = json::parse(input).map_err(|e| format!("Invalid proof artifact: {e}"))?;
+        let version = parsed["version"]
+            .as_u32()
+            .ok_or("Proof artifact without version.")?;
+        if version != ARTIFACT_VERSION {
+            return Err(format!(
+                "Unsupported proof artifact version {version}, expected {ARTIFACT_VERSION}."
+            ));
+        }
+        let string = |value: &JsonValue, what: &str| {
+            value
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| format!("Missing or invalid {what} in proof artifact."))
+        };
+        let number = |value: &JsonValue, what: &str| {
+            AbstractNumberType::parse_bytes(string(value, what)?.as_bytes(), 10)
+                .ok_or_else(|| format!("Invalid {what} in proof artifact."))
+        };
+        let fingerprint = u64::from_str_radix(&string(&parsed["fingerprint"], "fingerprint")?, 16)
+            .map_err(|_| "Invalid fingerprint in proof artifact.")?;
+        let publics = parsed["publics"]
+            .members()
+            .map(|public| {
+                Ok((
+                    string(&public["name"], "public name")?,
+                    number(&public["value"], "public value")?,
+                ))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(ProofArtifact {
+            version,
+            backend: string(&parsed["backend"], "backend")?,
+            field: number(&parsed["field"], "field")?,
+            fingerprint,
+            publics,
+            proof: from_hex(&string(&parsed["proof"], "proof")?)
+                .ok_or("Invalid proof in proof artifact.")?,
+        })
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut s, b| {
+        write!(s, "{b:02x}").unwrap();
+        s
+    })
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+    use pil_analyzer::analyze_string;
+
+    use super::*;
+
+    #[test]
+    fn json_round_trip() {
+        let analyzed = analyze_string::<GoldilocksField>(
+            r#"
+namespace Main(2);
+    col witness x;
+    public first = x(0);
+"#,
+        );
+        let publics =
+            Publics::from_witness(&analyzed, &[("Main.x", vec![7.into(), 8.into()])]).unwrap();
+        let artifact =
+            ProofArtifact::new("mock", analyzed.fingerprint(), &publics, vec![0, 1, 0xab]);
+        let json = artifact.to_json();
+        assert!(json.contains("\"proof\": \"0001ab\""), "{json}");
+        assert_eq!(ProofArtifact::from_json(&json).unwrap(), artifact);
+
+        let other_version = json.replace("\"version\": 1", "\"version\": 2");
+        assert!(ProofArtifact::from_json(&other_version)
+            .unwrap_err()
+            .contains("version 2"));
+        assert!(ProofArtifact::from_json(&json.replace("0001ab", "0001a")).is_err());
+        assert_eq!(
+            pil_analyzer::json_schema::validate(
+                &ProofArtifact::json_schema(),
+                &json::parse(&json).unwrap()
+            ),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use number::FieldElement;
+use pil_analyzer::{Analyzed, IdentityKind};
+
+/// The identity kinds a backend can prove, besides polynomial identities.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    pub plookup: bool,
+    /// Lookups that also constrain their multiplicity column.
+    pub logup: bool,
+    pub permutation: bool,
+    pub connect: bool,
+}
+
+impl Capabilities {
+    pub fn supports(&self, kind: IdentityKind) -> bool {
+        match kind {
+            IdentityKind::Polynomial => true,
+            IdentityKind::Plookup => self.plookup,
+            // Proving a LogUp identity as a plain lookup would leave the
+            // multiplicity column unconstrained.
+            IdentityKind::LogUp => self.logup,
+            IdentityKind::Permutation => self.permutation,
+            IdentityKind::Connect => self.connect,
+        }
+    }
+}
+
+/// Checks that the backend supports all identities of the PIL file and lists
+/// the ones it does not support otherwise. See `lower_identities` to rewrite
+/// identities for the backend first.
+pub fn check_capabilities<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    capabilities: Capabilities,
+) -> Result<(), String> {
+    let unsupported = analyzed
+        .identities
+        .iter()
+        .filter(|identity| !capabilities.supports(identity.kind))
+        .map(|identity| {
+            format!(
+                "{}:{}: {:?} identity {}",
+                identity.source.file,
+                identity.source.line,
+                identity.kind,
+                analyzed.expressions.display(identity)
+            )
+        })
+        .collect::<Vec<_>>();
+    if unsupported.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "The backend does not support these identities:\n{}",
+            unsupported.join("\n")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+    use pil_analyzer::analyze_string;
+
+    use super::*;
+
+    #[test]
+    fn unsupported_identities() {
+        let analyzed = analyze_string::<GoldilocksField>(
+            r#"
+namespace Main(4);
+    col fixed A = [1, 2, 3, 4];
+    col witness x, y;
+    { x } in { A };
+    { y } is { A };
+    col witness m;
+    { y } in { A } multiplicity m;
+"#,
+        );
+        let all = Capabilities {
+            plookup: true,
+            logup: true,
+            permutation: true,
+            connect: true,
+        };
+        assert!(check_capabilities(&analyzed, all).is_ok());
+        let error = check_capabilities(
+            &analyzed,
+            Capabilities {
+                permutation: false,
+                ..all
+            },
+        )
+        .unwrap_err();
+        assert!(error.contains("input:6: Permutation identity"), "{error}");
+        assert!(!error.contains("Plookup"), "{error}");
+        let error = check_capabilities(
+            &analyzed,
+            Capabilities {
+                logup: false,
+                ..all
+            },
+        )
+        .unwrap_err();
+        assert!(error.contains("input:8: LogUp identity"), "{error}");
+        assert!(!error.contains("Plookup"), "{error}");
+    }
+}
@@ -0,0 +1,346 @@
+//! Coverage of the constraints by a trace: on how many rows each identity
+//! is active, which rows of lookup tables are never looked up and which arms
+//! of the queries are never hit. Identities that are never active and table
+//! rows that are never used point to dead constraints or oversized tables.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use number::FieldElement;
+use pil_analyzer::{
+    Analyzed, BinaryOperator, Expression, ExpressionArena, FunctionValueDefinition, IdentityKind,
+};
+
+use crate::mock::{row_evaluator, Error};
+use crate::Columns;
+
+/// Lists at most this many unused table rows in the report.
+const LISTED_ROWS: usize = 10;
+
+pub struct Coverage<T> {
+    pub degree: usize,
+    /// In the order of `Analyzed::identities`.
+    pub identities: Vec<IdentityCoverage>,
+    /// The witness columns defined by a match expression (like the free inputs
+    /// of assembly programs), in source order.
+    pub queries: Vec<QueryCoverage<T>>,
+}
+
+pub struct IdentityCoverage {
+    /// The identity as in the PIL file.
+    pub identity: String,
+    /// File and line of the identity.
+    pub source: String,
+    /// The number of rows on which the selector is not zero. The selector of
+    /// a polynomial identity `a * b * c = 0` is `a * b` if it does not
+    /// reference the columns of `c`, identities without a selector are active
+    /// on all rows.
+    pub active_rows: usize,
+    /// For lookups, the rows of the table (the selected rows of the right
+    /// hand side) and those whose values are never looked up.
+    pub table: Option<TableCoverage>,
+}
+
+pub struct TableCoverage {
+    pub rows: usize,
+    pub unused_rows: Vec<usize>,
+}
+
+pub struct QueryCoverage<T> {
+    pub column: String,
+    /// The pattern of each arm (None for `_`) and the number of rows it matches.
+    pub arms: Vec<(Option<T>, usize)>,
+}
+
+impl<T: FieldElement> Coverage<T> {
+    pub fn inactive_identities(&self) -> impl Iterator<Item = &IdentityCoverage> {
+        self.identities.iter().filter(|i| i.active_rows == 0)
+    }
+
+    pub fn unhit_arms(&self) -> usize {
+        self.queries
+            .iter()
+            .map(|q| q.arms.iter().filter(|(_, hits)| *hits == 0).count())
+            .sum()
+    }
+}
+
+impl<T: FieldElement> fmt::Display for Coverage<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for identity in &self.identities {
+            writeln!(f, "{}: {}", identity.source, identity.identity)?;
+            write!(
+                f,
+                "    active on {} of {} rows",
+                identity.active_rows, self.degree
+            )?;
+            if identity.active_rows == 0 {
+                write!(f, " (never active)")?;
+            }
+            writeln!(f)?;
+            if let Some(table) = identity
+                .table
+                .as_ref()
+                .filter(|t| !t.unused_rows.is_empty())
+            {
+                let mut rows = table
+                    .unused_rows
+                    .iter()
+                    .take(LISTED_ROWS)
+                    .map(|row| row.to_string())
+                    .collect::<Vec<_>>();
+                if table.unused_rows.len() > LISTED_ROWS {
+                    rows.push("...".to_string());
+                }
+                writeln!(
+                    f,
+                    "    {} of {} table rows never looked up: {}",
+                    table.unused_rows.len(),
+                    table.rows,
+                    rows.join(", ")
+                )?;
+            }
+        }
+        for query in &self.queries {
+            let unhit = query
+                .arms
+                .iter()
+                .filter(|(_, hits)| *hits == 0)
+                .map(|(pattern, _)| match pattern {
+                    Some(n) => n.to_string(),
+                    None => "_".to_string(),
+                })
+                .collect::<Vec<_>>();
+            if unhit.is_empty() {
+                writeln!(f, "{}: all {} arms hit", query.column, query.arms.len())?;
+            } else {
+                writeln!(
+                    f,
+                    "{}: {} of {} arms never hit: {}",
+                    query.column,
+                    unhit.len(),
+                    query.arms.len(),
+                    unhit.join(", ")
+                )?;
+            }
+        }
+        write!(
+            f,
+            "{} of {} identities never active, {} query arms never hit.",
+            self.inactive_identities().count(),
+            self.identities.len(),
+            self.unhit_arms()
+        )
+    }
+}
+
+/// Evaluates the selectors of all identities and the scrutinees of all
+/// queries on all rows.
+pub fn coverage<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    fixed: &Columns<T>,
+    witness: &Columns<T>,
+) -> Result<Coverage<T>, Error<T>> {
+    let evaluator = row_evaluator(analyzed, fixed, witness)?;
+    let mut identities = vec![];
+    for identity in &analyzed.identities {
+        let (active_rows, table) = match identity.kind {
+            IdentityKind::Polynomial => match selector(
+                &analyzed.expressions,
+                identity.left.selector.as_ref().unwrap(),
+            ) {
+                Some(selector) => {
+                    let mut active_rows = 0;
+                    for row in 0..evaluator.degree {
+                        if !evaluator
+                            .evaluate(selector, row)
+                            .map_err(Error::Unsupported)?
+                            .is_zero()
+                        {
+                            active_rows += 1;
+                        }
+                    }
+                    (active_rows, None)
+                }
+                None => (evaluator.degree, None),
+            },
+            IdentityKind::Plookup | IdentityKind::LogUp => {
+                let left = evaluator
+                    .selected_tuples(&identity.left)
+                    .map_err(Error::Unsupported)?;
+                let looked_up = left.iter().map(|(_, tuple)| tuple).collect::<HashSet<_>>();
+                let right = evaluator
+                    .selected_tuples(&identity.right)
+                    .map_err(Error::Unsupported)?;
+                let unused_rows = right
+                    .iter()
+                    .filter(|(_, tuple)| !looked_up.contains(tuple))
+                    .map(|(row, _)| *row)
+                    .collect();
+                let table = TableCoverage {
+                    rows: right.len(),
+                    unused_rows,
+                };
+                (left.len(), Some(table))
+            }
+            IdentityKind::Permutation => (
+                evaluator
+                    .selected_tuples(&identity.left)
+                    .map_err(Error::Unsupported)?
+                    .len(),
+                None,
+            ),
+            IdentityKind::Connect => {
+                return Err(Error::Unsupported(format!(
+                    "connect identity at {}:{}",
+                    identity.source.file, identity.source.line
+                )))
+            }
+        };
+        identities.push(IdentityCoverage {
+            identity: analyzed.expressions.display(identity).to_string(),
+            source: format!("{}:{}", identity.source.file, identity.source.line),
+            active_rows,
+            table,
+        });
+    }
+
+    let mut queries = vec![];
+    for (poly, definition) in analyzed.committed_polys_in_source_order() {
+        let Some(FunctionValueDefinition::Query(Expression::MatchExpression(scrutinee, arms))) =
+            definition
+        else {
+            continue;
+        };
+        let mut hits = vec![0; arms.len()];
+        for row in 0..evaluator.degree {
+            let value = evaluator
+                .evaluate(&analyzed.expressions[*scrutinee], row)
+                .map_err(Error::Unsupported)?;
+            if let Some(arm) = arms
+                .iter()
+                .position(|(pattern, _)| *pattern == Some(value))
+                .or_else(|| arms.iter().position(|(pattern, _)| pattern.is_none()))
+            {
+                hits[arm] += 1;
+            }
+        }
+        queries.push(QueryCoverage {
+            column: poly.absolute_name.to_string(),
+            arms: arms.iter().map(|(pattern, _)| *pattern).zip(hits).collect(),
+        });
+    }
+
+    Ok(Coverage {
+        degree: evaluator.degree,
+        identities,
+        queries,
+    })
+}
+
+/// The selector of the polynomial identity `a * b * c = 0` is `a * b` if it
+/// does not reference the columns of `c`: `x * (1 - x) = 0` has none.
+fn selector<'a, T: FieldElement>(
+    expressions: &'a ExpressionArena<T>,
+    identity: &'a Expression<T>,
+) -> Option<&'a Expression<T>> {
+    match identity {
+        Expression::BinaryOperation(left, BinaryOperator::Sub, right) if matches!(expressions[*right], Expression::Number(n) if n.is_zero()) => {
+            selector(expressions, &expressions[*left])
+        }
+        Expression::BinaryOperation(selector, BinaryOperator::Mul, constraint) => {
+            let mut selector_columns = HashSet::new();
+            collect_references(expressions, &expressions[*selector], &mut selector_columns);
+            let mut constraint_columns = HashSet::new();
+            collect_references(
+                expressions,
+                &expressions[*constraint],
+                &mut constraint_columns,
+            );
+            selector_columns
+                .is_disjoint(&constraint_columns)
+                .then_some(&expressions[*selector])
+        }
+        _ => None,
+    }
+}
+
+fn collect_references<'a, T>(
+    expressions: &'a ExpressionArena<T>,
+    e: &'a Expression<T>,
+    names: &mut HashSet<&'a str>,
+) {
+    match e {
+        Expression::PolynomialReference(reference) => {
+            names.insert(&reference.name);
+        }
+        Expression::BinaryOperation(left, _, right) => {
+            collect_references(expressions, &expressions[*left], names);
+            collect_references(expressions, &expressions[*right], names);
+        }
+        Expression::UnaryOperation(_, e) => {
+            collect_references(expressions, &expressions[*e], names)
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+    use pil_analyzer::analyze_string;
+
+    use super::*;
+
+    fn column(name: &'static str, values: &[u64]) -> (&'static str, Vec<GoldilocksField>) {
+        (name, values.iter().map(|v| (*v).into()).collect())
+    }
+
+    #[test]
+    fn coverage_report() {
+        let pil = r#"
+namespace Main(4);
+    col fixed BYTE = [0, 1, 2, 3];
+    col witness sel, x, pc;
+    col witness y(i) query match pc { 0 => ("input", 0), 1 => ("input", 1), };
+    sel * (x - 1) = 0;
+    (1 - sel) * (1 - sel) * y = 0;
+    { x } in { BYTE };
+    x * (1 - x) = 0;
+"#;
+        let analyzed = analyze_string::<GoldilocksField>(pil);
+        let fixed = [column("Main.BYTE", &[0, 1, 2, 3])];
+        let witness = [
+            column("Main.sel", &[1, 1, 1, 1]),
+            column("Main.x", &[1, 1, 1, 1]),
+            column("Main.pc", &[0, 2, 2, 2]),
+            column("Main.y", &[0, 0, 0, 0]),
+        ];
+        let coverage = coverage(&analyzed, &fixed, &witness).unwrap();
+        let active_rows = coverage
+            .identities
+            .iter()
+            .map(|i| i.active_rows)
+            .collect::<Vec<_>>();
+        assert_eq!(active_rows, [4, 0, 4, 4]);
+        assert_eq!(
+            coverage.identities[2].table.as_ref().unwrap().unused_rows,
+            [0, 2, 3]
+        );
+        assert_eq!(coverage.unhit_arms(), 1);
+        assert_eq!(
+            coverage.to_string(),
+            "input:6: (Main.sel * (Main.x - 1)) = 0;
+    active on 4 of 4 rows
+input:7: (((1 - Main.sel) * (1 - Main.sel)) * Main.y) = 0;
+    active on 0 of 4 rows (never active)
+input:8: { Main.x } in { Main.BYTE };
+    active on 4 of 4 rows
+    3 of 4 table rows never looked up: 0, 2, 3
+input:9: (Main.x * (1 - Main.x)) = 0;
+    active on 4 of 4 rows
+Main.y: 1 of 2 arms never hit: 1
+1 of 4 identities never active, 1 query arms never hit."
+        );
+    }
+}
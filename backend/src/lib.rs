@@ -0,0 +1,82 @@
+//! The interface between powdr and proof systems.
+//!
+//! A backend receives the analyzed PIL file together with the values of the
+//! fixed and witness columns, as returned by the constant evaluator and the
+//! witness generator. Crates outside of powdr can implement `Backend` to use
+//! their own prover without changes to powdr itself.
+
+mod artifact;
+mod capabilities;
+mod coverage;
+mod lowering;
+mod mock;
+mod publics;
+mod reproducible;
+
+use std::{fmt, io};
+
+use number::FieldElement;
+use pil_analyzer::Analyzed;
+
+pub use artifact::{ProofArtifact, ARTIFACT_VERSION};
+pub use capabilities::{check_capabilities, Capabilities};
+pub use coverage::{coverage, Coverage, IdentityCoverage, QueryCoverage, TableCoverage};
+pub use lowering::{lower_identities, Lowered};
+pub use mock::{check, Failure, MockBackend};
+pub use publics::Publics;
+pub use reproducible::{is_reproducible, proof_rng, set_reproducible, timestamp};
+
+/// Column values by column name, in the order of the PIL file.
+pub type Columns<'a, T> = [(&'a str, Vec<T>)];
+
+pub trait Backend<T: FieldElement> {
+    /// Everything that only depends on the constraints and the fixed columns,
+    /// like proving and verification keys or commitment parameters.
+    type Setup;
+    type Proof;
+    type Error: fmt::Display;
+
+    /// Returns the name of the backend, used in log messages.
+    fn name(&self) -> &str;
+
+    /// Returns the identity kinds the backend can prove. By default only
+    /// polynomial identities, backends have to declare everything else.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    /// Creates the setup for the constraints and fixed columns.
+    fn setup(&self, analyzed: &Analyzed<T>, fixed: &Columns<T>)
+        -> Result<Self::Setup, Self::Error>;
+
+    /// Returns what the written setup for the constraints and fixed columns
+    /// depends on, as part of a file name. Setups read from files with the
+    /// same id are used in place of creating them, see `read_setup`.
+    fn setup_id(&self, analyzed: &Analyzed<T>, fixed: &Columns<T>) -> Result<String, Self::Error>;
+
+    /// Creates a proof that the witness columns satisfy the constraints.
+    fn prove(
+        &self,
+        setup: &Self::Setup,
+        analyzed: &Analyzed<T>,
+        fixed: &Columns<T>,
+        witness: &Columns<T>,
+    ) -> Result<Self::Proof, Self::Error>;
+
+    /// Verifies a proof for the given values of the public declarations,
+    /// in declaration order, see `Publics`.
+    fn verify(
+        &self,
+        setup: &Self::Setup,
+        analyzed: &Analyzed<T>,
+        fixed: &Columns<T>,
+        publics: &[T],
+        proof: &Self::Proof,
+    ) -> Result<(), Self::Error>;
+
+    fn write_setup(&self, setup: &Self::Setup, output: &mut dyn io::Write) -> io::Result<()>;
+    fn read_setup(&self, input: &mut dyn io::Read) -> io::Result<Self::Setup>;
+
+    fn write_proof(&self, proof: &Self::Proof, output: &mut dyn io::Write) -> io::Result<()>;
+    fn read_proof(&self, input: &mut dyn io::Read) -> io::Result<Self::Proof>;
+}
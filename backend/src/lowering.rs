@@ -0,0 +1,222 @@
+//! Rewrites identities that a backend does not support into ones it does.
+//!
+//! A permutation `[s] { a } is [t] { b }` becomes the LogUp identity
+//! `[s] { a } in [t] { b } multiplicity m` with a new witness column `m` and
+//! the polynomial identity `m = t`: Every selected row of the right hand side
+//! has to be looked up exactly once, which is the permutation. Connect
+//! identities cannot be lowered this way.
+
+use number::FieldElement;
+use pil_analyzer::{
+    row_evaluator::RowEvaluator, Analyzed, BinaryOperator, Expression, Identity, IdentityKind,
+    Polynomial, PolynomialReference, PolynomialType, SelectedExpressions, StatementIdentifier,
+    Symbol,
+};
+
+use crate::{check_capabilities, Capabilities, Columns};
+
+/// A PIL file with identities rewritten for a backend, together with the
+/// witness columns the rewriting added.
+pub struct Lowered<T> {
+    pub analyzed: Analyzed<T>,
+    /// The added witness columns and the expressions they equal on each row.
+    added_columns: Vec<(Symbol, Expression<T>)>,
+}
+
+impl<T: FieldElement> Lowered<T> {
+    /// Returns the witness columns of the original PIL file together with the
+    /// columns added by the rewriting.
+    pub fn witness<'a>(
+        &'a self,
+        original: &Analyzed<T>,
+        fixed: &'a Columns<T>,
+        witness: &'a Columns<T>,
+    ) -> Vec<(&'a str, Vec<T>)> {
+        let evaluator = RowEvaluator::new(original, fixed, witness);
+        let added = self.added_columns.iter().map(|(name, value)| {
+            let values = (0..evaluator.degree)
+                .map(|row| {
+                    evaluator
+                        .evaluate(value, row)
+                        .unwrap_or_else(|e| panic!("Cannot evaluate {e} in permutation."))
+                })
+                .collect();
+            (name.as_str(), values)
+        });
+        witness.iter().cloned().chain(added).collect()
+    }
+}
+
+/// Rewrites the permutations of the PIL file if the backend does not support
+/// them but supports LogUp identities. Returns `None` if the backend supports
+/// all identities as they are and an error listing the identities that cannot
+/// be rewritten.
+pub fn lower_identities<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    capabilities: Capabilities,
+) -> Result<Option<Lowered<T>>, String> {
+    if check_capabilities(analyzed, capabilities).is_ok() {
+        return Ok(None);
+    }
+    let mut lowered = Lowered {
+        analyzed: analyzed.clone(),
+        added_columns: vec![],
+    };
+    if !capabilities.permutation && capabilities.logup {
+        for index in 0..analyzed.identities.len() {
+            if analyzed.identities[index].kind == IdentityKind::Permutation {
+                lowered.lower_permutation(index);
+            }
+        }
+    }
+    check_capabilities(&lowered.analyzed, capabilities)?;
+    Ok(Some(lowered))
+}
+
+impl<T: FieldElement> Lowered<T> {
+    fn lower_permutation(&mut self, index: usize) {
+        let analyzed = &mut self.analyzed;
+        let identity = &analyzed.identities[index];
+        let name = Symbol::intern(&format!("Global.permutation_multiplicity_{}", identity.id));
+        let degree = analyzed
+            .definitions
+            .values()
+            .map(|(poly, _)| poly.degree)
+            .max()
+            .unwrap_or_default();
+        let poly = Polynomial {
+            id: analyzed.commitment_count() as u64,
+            source: identity.source.clone(),
+            absolute_name: name,
+            poly_type: PolynomialType::Committed,
+            degree,
+            length: None,
+        };
+        let multiplicity = PolynomialReference {
+            name,
+            index: None,
+            next: false,
+        };
+        let value = identity
+            .right
+            .selector
+            .clone()
+            .unwrap_or(Expression::Number(T::one()));
+        let constraint = Expression::BinaryOperation(
+            analyzed
+                .expressions
+                .add(Expression::PolynomialReference(multiplicity.clone())),
+            BinaryOperator::Sub,
+            analyzed.expressions.add(value.clone()),
+        );
+        let count = |kind| {
+            analyzed
+                .identities
+                .iter()
+                .filter(|identity| identity.kind == kind)
+                .count() as u64
+        };
+        let logup_id = count(IdentityKind::LogUp);
+        let constraint = Identity {
+            id: count(IdentityKind::Polynomial),
+            kind: IdentityKind::Polynomial,
+            source: identity.source.clone(),
+            left: SelectedExpressions {
+                selector: Some(constraint),
+                expressions: vec![],
+            },
+            right: SelectedExpressions::default(),
+            multiplicity: None,
+            origin: identity.origin.clone(),
+        };
+
+        let identity = &mut analyzed.identities[index];
+        identity.kind = IdentityKind::LogUp;
+        identity.id = logup_id;
+        identity.multiplicity = Some(multiplicity);
+
+        analyzed.definitions.insert(name, (poly, None));
+        analyzed
+            .source_order
+            .push(StatementIdentifier::Definition(name));
+        analyzed
+            .source_order
+            .push(StatementIdentifier::Identity(analyzed.identities.len()));
+        analyzed.identities.push(constraint);
+        self.added_columns.push((name, value));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+    use pil_analyzer::analyze_string;
+
+    use crate::check;
+
+    use super::*;
+
+    fn column(name: &'static str, values: &[u64]) -> (&'static str, Vec<GoldilocksField>) {
+        (name, values.iter().map(|v| (*v).into()).collect())
+    }
+
+    #[test]
+    fn permutation_to_logup() {
+        let analyzed = analyze_string::<GoldilocksField>(
+            r#"
+namespace Main(4);
+    col fixed A = [1, 2, 3, 4];
+    col witness x;
+    { x } is { A };
+"#,
+        );
+        let capabilities = Capabilities {
+            logup: true,
+            ..Default::default()
+        };
+        let lowered = lower_identities(&analyzed, capabilities).unwrap().unwrap();
+        assert_eq!(
+            lowered.analyzed.to_string(),
+            r#"namespace Main(4);
+    col fixed A = [1, 2, 3, 4];
+    col witness x;
+    { Main.x } in { Main.A } multiplicity Global.permutation_multiplicity_0;
+namespace Global(4);
+    col witness permutation_multiplicity_0;
+    Global.permutation_multiplicity_0 = 1;
+"#
+        );
+
+        let fixed = [column("Main.A", &[1, 2, 3, 4])];
+        let permuted = [column("Main.x", &[4, 3, 2, 1])];
+        let witness = lowered.witness(&analyzed, &fixed, &permuted);
+        assert!(check(&lowered.analyzed, &fixed, &witness)
+            .unwrap()
+            .is_empty());
+
+        let repeated = [column("Main.x", &[1, 1, 2, 3])];
+        let witness = lowered.witness(&analyzed, &fixed, &repeated);
+        let failures = check(&lowered.analyzed, &fixed, &witness).unwrap();
+        assert_eq!(failures.len(), 2);
+        assert_eq!(
+            failures[0].reason,
+            "(1) is looked up 2 times, but its multiplicities sum up to 1"
+        );
+    }
+
+    #[test]
+    fn nothing_to_lower() {
+        let analyzed = analyze_string::<GoldilocksField>(
+            "namespace Main(4);\ncol witness x;\ncol witness y;\n{ x } is { y };",
+        );
+        let capabilities = Capabilities {
+            permutation: true,
+            ..Default::default()
+        };
+        assert!(lower_identities(&analyzed, capabilities).unwrap().is_none());
+        let error = lower_identities(&analyzed, Capabilities::default())
+            .err()
+            .unwrap();
+        assert!(error.contains("input:4: Permutation identity"), "{error}");
+    }
+}
@@ -0,0 +1,467 @@
+//! A backend that does not create proofs, but checks all identities on every
+//! row of the trace and reports each violation together with the values of
+//! the cells involved. Connect identities are not supported.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt, io,
+};
+
+use number::FieldElement;
+use pil_analyzer::{row_evaluator::RowEvaluator, Analyzed, Identity, IdentityKind};
+
+use crate::{Backend, Capabilities, Columns};
+
+pub struct MockBackend;
+
+/// A violated identity.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Failure<T> {
+    /// The identity as in the PIL file.
+    pub identity: String,
+    /// File and line of the identity.
+    pub source: String,
+    /// The row on which the identity is violated. For lookups and permutations,
+    /// this is the row of the tuple that could not be matched.
+    pub row: usize,
+    pub reason: String,
+    /// The cells the violating expressions reference, with their values.
+    pub cells: Vec<(String, T)>,
+}
+
+impl<T: FieldElement> fmt::Display for Failure<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} row {}: {}\n    {}",
+            self.source, self.row, self.identity, self.reason
+        )?;
+        if !self.cells.is_empty() {
+            let cells = self
+                .cells
+                .iter()
+                .map(|(name, value)| format!("{name} = {value}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            write!(f, "\n    {cells}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum Error<T> {
+    /// The PIL file uses a feature the mock backend does not support.
+    Unsupported(String),
+    /// A column that the PIL file declares was not provided.
+    MissingColumn(String),
+    /// The trace violates the identities.
+    Failures(Vec<Failure<T>>),
+}
+
+impl<T: FieldElement> fmt::Display for Error<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Unsupported(what) => write!(f, "Not supported by the mock backend: {what}"),
+            Error::MissingColumn(name) => write!(f, "No values provided for column {name}."),
+            Error::Failures(failures) => {
+                write!(f, "{} identity violations:", failures.len())?;
+                for failure in failures {
+                    write!(f, "\n{failure}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<T: FieldElement> Backend<T> for MockBackend {
+    type Setup = ();
+    type Proof = ();
+    type Error = Error<T>;
+
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            plookup: true,
+            logup: true,
+            permutation: true,
+            connect: false,
+        }
+    }
+
+    fn setup(&self, _analyzed: &Analyzed<T>, _fixed: &Columns<T>) -> Result<(), Error<T>> {
+        Ok(())
+    }
+
+    /// Checks all identities and returns all violations as an error.
+    fn prove(
+        &self,
+        _setup: &(),
+        analyzed: &Analyzed<T>,
+        fixed: &Columns<T>,
+        witness: &Columns<T>,
+    ) -> Result<(), Error<T>> {
+        let failures = check(analyzed, fixed, witness)?;
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Failures(failures))
+        }
+    }
+
+    /// There is nothing to verify, `prove` only succeeds on valid traces.
+    fn verify(
+        &self,
+        _setup: &(),
+        _analyzed: &Analyzed<T>,
+        _fixed: &Columns<T>,
+        _publics: &[T],
+        _proof: &(),
+    ) -> Result<(), Error<T>> {
+        Ok(())
+    }
+
+    /// The setup is empty, it is the same for all constraints.
+    fn setup_id(&self, _analyzed: &Analyzed<T>, _fixed: &Columns<T>) -> Result<String, Error<T>> {
+        Ok("empty".to_string())
+    }
+
+    fn write_setup(&self, _setup: &(), _output: &mut dyn io::Write) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn read_setup(&self, _input: &mut dyn io::Read) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_proof(&self, _proof: &(), _output: &mut dyn io::Write) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn read_proof(&self, _input: &mut dyn io::Read) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Checks all identities on all rows and returns the violations.
+pub fn check<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    fixed: &Columns<T>,
+    witness: &Columns<T>,
+) -> Result<Vec<Failure<T>>, Error<T>> {
+    let evaluator = row_evaluator(analyzed, fixed, witness)?;
+    let mut failures = vec![];
+    for identity in &analyzed.identities {
+        match identity.kind {
+            IdentityKind::Polynomial => {
+                let expression = identity.left.selector.as_ref().unwrap();
+                for row in 0..evaluator.degree {
+                    let mut cells = vec![];
+                    let value = evaluator
+                        .evaluate_recording(expression, row, Some(&mut cells))
+                        .map_err(Error::Unsupported)?;
+                    if !value.is_zero() {
+                        failures.push(failure(
+                            analyzed,
+                            identity,
+                            row,
+                            format!("evaluates to {value}"),
+                            cells,
+                        ));
+                    }
+                }
+            }
+            IdentityKind::Plookup => {
+                let table = evaluator
+                    .selected_tuples(&identity.right)
+                    .map_err(Error::Unsupported)?
+                    .into_iter()
+                    .map(|(_, tuple)| tuple)
+                    .collect::<HashSet<_>>();
+                for (row, tuple, cells) in evaluator
+                    .selected_tuples_with_cells(&identity.left)
+                    .map_err(Error::Unsupported)?
+                {
+                    if !table.contains(&tuple) {
+                        failures.push(failure(
+                            analyzed,
+                            identity,
+                            row,
+                            format!("{} is not in the table", format_tuple(&tuple)),
+                            cells,
+                        ));
+                    }
+                }
+            }
+            IdentityKind::LogUp => {
+                let table = evaluator
+                    .selected_tuples(&identity.right)
+                    .map_err(Error::Unsupported)?;
+                let multiplicity = identity.multiplicity.as_ref().unwrap();
+                let multiplicities = evaluator
+                    .column(multiplicity.name.as_str())
+                    .ok_or_else(|| Error::MissingColumn(multiplicity.name.to_string()))?;
+                // The first row of each tuple in the table and the sum of its multiplicities.
+                let mut expected = HashMap::new();
+                for (row, tuple) in &table {
+                    let (_, sum) = expected.entry(tuple).or_insert((*row, T::zero()));
+                    *sum += multiplicities[*row];
+                }
+                let mut counts = HashMap::<_, T>::new();
+                for (row, tuple, cells) in evaluator
+                    .selected_tuples_with_cells(&identity.left)
+                    .map_err(Error::Unsupported)?
+                {
+                    if expected.contains_key(&tuple) {
+                        *counts.entry(tuple).or_default() += 1.into();
+                    } else {
+                        failures.push(failure(
+                            analyzed,
+                            identity,
+                            row,
+                            format!("{} is not in the table", format_tuple(&tuple)),
+                            cells,
+                        ));
+                    }
+                }
+                let mut wrong_multiplicities = expected
+                    .into_iter()
+                    .map(|(tuple, (row, sum))| {
+                        (
+                            row,
+                            tuple,
+                            sum,
+                            counts.get(tuple).copied().unwrap_or_default(),
+                        )
+                    })
+                    .filter(|(_, _, sum, count)| sum != count)
+                    .collect::<Vec<_>>();
+                wrong_multiplicities.sort_by_key(|(row, ..)| *row);
+                for (row, tuple, sum, count) in wrong_multiplicities {
+                    failures.push(failure(
+                        analyzed,
+                        identity,
+                        row,
+                        format!(
+                            "{} is looked up {count} times, but its multiplicities sum up to {sum}",
+                            format_tuple(tuple)
+                        ),
+                        vec![(multiplicity.name.to_string(), multiplicities[row])],
+                    ));
+                }
+            }
+            IdentityKind::Permutation => {
+                let mut right = HashMap::<_, VecDeque<_>>::new();
+                for (row, tuple, cells) in evaluator
+                    .selected_tuples_with_cells(&identity.right)
+                    .map_err(Error::Unsupported)?
+                {
+                    right.entry(tuple).or_default().push_back((row, cells));
+                }
+                for (row, tuple, cells) in evaluator
+                    .selected_tuples_with_cells(&identity.left)
+                    .map_err(Error::Unsupported)?
+                {
+                    if right
+                        .get_mut(&tuple)
+                        .and_then(|rows| rows.pop_front())
+                        .is_none()
+                    {
+                        failures.push(failure(
+                            analyzed,
+                            identity,
+                            row,
+                            format!(
+                                "{} of the left hand side has no match on the right hand side",
+                                format_tuple(&tuple)
+                            ),
+                            cells,
+                        ));
+                    }
+                }
+                let mut unmatched = right
+                    .into_iter()
+                    .flat_map(|(tuple, rows)| rows.into_iter().map(move |r| (tuple.clone(), r)))
+                    .collect::<Vec<_>>();
+                unmatched.sort_by_key(|(_, (row, _))| *row);
+                for (tuple, (row, cells)) in unmatched {
+                    failures.push(failure(
+                        analyzed,
+                        identity,
+                        row,
+                        format!(
+                            "{} of the right hand side has no match on the left hand side",
+                            format_tuple(&tuple)
+                        ),
+                        cells,
+                    ));
+                }
+            }
+            IdentityKind::Connect => {
+                return Err(Error::Unsupported(format!(
+                    "connect identity at {}:{}",
+                    identity.source.file, identity.source.line
+                )))
+            }
+        }
+    }
+    Ok(failures)
+}
+
+fn failure<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    identity: &Identity<T>,
+    row: usize,
+    reason: String,
+    cells: Vec<(String, T)>,
+) -> Failure<T> {
+    Failure {
+        identity: analyzed.expressions.display(identity).to_string(),
+        source: format!("{}:{}", identity.source.file, identity.source.line),
+        row,
+        reason,
+        cells,
+    }
+}
+
+/// Fails if a fixed or witness column of the PIL file is not provided.
+pub(crate) fn row_evaluator<'a, T: FieldElement>(
+    analyzed: &'a Analyzed<T>,
+    fixed: &'a Columns<T>,
+    witness: &'a Columns<T>,
+) -> Result<RowEvaluator<'a, T>, Error<T>> {
+    let evaluator = RowEvaluator::new(analyzed, fixed, witness);
+    match evaluator.missing_column() {
+        Some(name) => Err(Error::MissingColumn(name.to_string())),
+        None => Ok(evaluator),
+    }
+}
+
+fn format_tuple<T: FieldElement>(tuple: &[T]) -> String {
+    format!(
+        "({})",
+        tuple
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+    use pil_analyzer::analyze_string;
+
+    use super::*;
+
+    type Column = (&'static str, Vec<GoldilocksField>);
+
+    fn column(name: &'static str, values: &[u64]) -> Column {
+        (name, values.iter().map(|v| (*v).into()).collect())
+    }
+
+    fn check_pil(pil: &str, fixed: &[Column], witness: &[Column]) -> Vec<Failure<GoldilocksField>> {
+        check(&analyze_string(pil), fixed, witness).unwrap()
+    }
+
+    #[test]
+    fn polynomial_identity() {
+        let pil = r#"
+namespace Main(4);
+    col fixed FIRST = [1, 0, 0, 0];
+    col witness x;
+    (1 - FIRST') * (x' - x - 1) = 0;
+"#;
+        let fixed = [column("Main.FIRST", &[1, 0, 0, 0])];
+        assert!(check_pil(pil, &fixed, &[column("Main.x", &[0, 1, 2, 3])]).is_empty());
+        let failures = check_pil(pil, &fixed, &[column("Main.x", &[0, 1, 5, 6])]);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].row, 1);
+        assert_eq!(failures[0].source, "input:5");
+        assert_eq!(failures[0].reason, "evaluates to 3");
+        assert_eq!(
+            failures[0].cells,
+            vec![
+                ("Main.FIRST'".to_string(), 0.into()),
+                ("Main.x'".to_string(), 5.into()),
+                ("Main.x".to_string(), 1.into())
+            ]
+        );
+    }
+
+    #[test]
+    fn lookup() {
+        let pil = r#"
+namespace Main(4);
+    col fixed BIT = [0, 1, 0, 1];
+    col witness sel, x;
+    sel { x } in { BIT };
+"#;
+        let fixed = [column("Main.BIT", &[0, 1, 0, 1])];
+        let failures = check_pil(
+            pil,
+            &fixed,
+            &[
+                column("Main.sel", &[1, 0, 1, 1]),
+                column("Main.x", &[1, 7, 2, 0]),
+            ],
+        );
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].row, 2);
+        assert_eq!(failures[0].reason, "(2) is not in the table");
+    }
+
+    #[test]
+    fn log_up() {
+        let pil = r#"
+namespace Main(4);
+    col fixed BIT = [0, 1, 0, 1];
+    col witness x, m;
+    { x } in { BIT } multiplicity m;
+"#;
+        let fixed = [column("Main.BIT", &[0, 1, 0, 1])];
+        let x = column("Main.x", &[1, 1, 0, 1]);
+        assert!(check_pil(pil, &fixed, &[x.clone(), column("Main.m", &[1, 2, 0, 1])]).is_empty());
+        let failures = check_pil(pil, &fixed, &[x, column("Main.m", &[1, 2, 0, 0])]);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].row, 1);
+        assert_eq!(
+            failures[0].reason,
+            "(1) is looked up 3 times, but its multiplicities sum up to 2"
+        );
+    }
+
+    #[test]
+    fn permutation() {
+        let pil = r#"
+namespace Main(4);
+    col witness x, y;
+    { x } is { y };
+"#;
+        let x = column("Main.x", &[1, 2, 3, 3]);
+        assert!(check_pil(pil, &[], &[x.clone(), column("Main.y", &[3, 1, 3, 2])]).is_empty());
+        let failures = check_pil(pil, &[], &[x, column("Main.y", &[3, 1, 2, 2])]);
+        assert_eq!(failures.len(), 2);
+        assert_eq!((failures[0].row, failures[1].row), (3, 3));
+        assert!(failures[0].reason.contains("left hand side has no match"));
+        assert!(failures[1].reason.contains("right hand side has no match"));
+    }
+
+    #[test]
+    fn missing_column() {
+        let pil = r#"
+namespace Main(4);
+    col witness x;
+    x = 0;
+"#;
+        assert!(matches!(
+            check(&analyze_string::<GoldilocksField>(pil), &[], &[]),
+            Err(Error::MissingColumn(name)) if name == "Main.x"
+        ));
+    }
+}
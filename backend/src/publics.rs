@@ -0,0 +1,193 @@
+use std::io;
+
+use number::{AbstractNumberType, FieldElement};
+use pil_analyzer::Analyzed;
+
+use crate::Columns;
+
+/// The values of the public declarations of a PIL file, by name.
+///
+/// The order is the order of the `public` statements in the PIL file. Backends
+/// take the public inputs in this order, for example as the instance column
+/// in Halo2, and `publics.json` stores the values in this order as well.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Publics<T>(Vec<(String, T)>);
+
+impl<T: FieldElement> Publics<T> {
+    /// Reads the public values from the witness columns,
+    /// fails with the name of a column that has no values in `witness`.
+    pub fn from_witness(analyzed: &Analyzed<T>, witness: &Columns<T>) -> Result<Self, String> {
+        let mut declarations = analyzed.public_declarations.values().collect::<Vec<_>>();
+        declarations.sort_by_key(|decl| decl.id);
+        declarations
+            .into_iter()
+            .map(|decl| {
+                let column = &decl.polynomial.name;
+                witness
+                    .iter()
+                    .find(|(n, _)| n == column)
+                    .map(|(_, values)| (decl.name.clone(), values[decl.index as usize]))
+                    .ok_or_else(|| column.to_string())
+            })
+            .collect::<Result<_, _>>()
+            .map(Publics)
+    }
+
+    /// Checks that the names are the ones of the public declarations,
+    /// in declaration order.
+    pub fn from_named_values(
+        analyzed: &Analyzed<T>,
+        values: Vec<(String, T)>,
+    ) -> Result<Self, String> {
+        let mut declarations = analyzed.public_declarations.values().collect::<Vec<_>>();
+        declarations.sort_by_key(|decl| decl.id);
+        let expected = declarations.iter().map(|decl| decl.name.as_str());
+        if !expected
+            .clone()
+            .eq(values.iter().map(|(name, _)| name.as_str()))
+        {
+            return Err(format!(
+                "Expected the publics {}, but got {}.",
+                expected.collect::<Vec<_>>().join(", "),
+                values
+                    .iter()
+                    .map(|(name, _)| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        Ok(Publics(values))
+    }
+
+    /// Returns the value of the public with the given name.
+    pub fn get(&self, name: &str) -> Option<T> {
+        self.0.iter().find(|(n, _)| n == name).map(|(_, v)| *v)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, T)> {
+        self.0.iter().map(|(n, v)| (n.as_str(), *v))
+    }
+
+    /// Returns the values in declaration order.
+    pub fn values(&self) -> Vec<T> {
+        self.0.iter().map(|(_, v)| *v).collect()
+    }
+
+    /// Writes the values as a JSON array of decimal strings, like pil-stark.
+    pub fn write_json(&self, output: &mut dyn io::Write) -> io::Result<()> {
+        let values = self
+            .0
+            .iter()
+            .map(|(_, v)| json::JsonValue::from(v.to_integer().to_string()))
+            .collect::<Vec<_>>();
+        write!(output, "{}", json::JsonValue::from(values))
+    }
+
+    /// The JSON Schema document of the output of `write_json`. The format is
+    /// the one of pil-stark and has no version.
+    pub fn json_schema() -> json::JsonValue {
+        json::object! {
+            "$schema": pil_analyzer::json_schema::DIALECT,
+            title: "powdr public values",
+            description: "The values of the public declarations, in declaration order.",
+            type: "array",
+            items: { type: "string", description: "A decimal number." },
+        }
+    }
+
+    /// Reads values written by `write_json` and names them
+    /// using the public declarations of the PIL file.
+    pub fn read_json(analyzed: &Analyzed<T>, input: &str) -> Result<Self, String> {
+        let parsed = json::parse(input).map_err(|e| format!("Invalid publics: {e}"))?;
+        let mut declarations = analyzed.public_declarations.values().collect::<Vec<_>>();
+        declarations.sort_by_key(|decl| decl.id);
+        if parsed.len() != declarations.len() || !parsed.is_array() {
+            return Err(format!(
+                "Expected an array of {} public values.",
+                declarations.len()
+            ));
+        }
+        declarations
+            .into_iter()
+            .zip(parsed.members())
+            .map(|(decl, value)| {
+                let value = value
+                    .as_str()
+                    .map(str::to_string)
+                    .or_else(|| value.as_u64().map(|v| v.to_string()))
+                    .and_then(|v| AbstractNumberType::parse_bytes(v.as_bytes(), 10))
+                    .filter(|v| *v < T::modulus())
+                    .ok_or_else(|| format!("Invalid value for public {}: {value}", decl.name))?;
+                Ok((decl.name.clone(), T::from(value)))
+            })
+            .collect::<Result<_, _>>()
+            .map(Publics)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+    use pil_analyzer::analyze_string;
+
+    use super::*;
+
+    #[test]
+    fn named_values_in_declaration_order() {
+        let analyzed = analyze_string::<GoldilocksField>(
+            r#"
+namespace Main(4);
+    col witness x, y;
+    public last_y = y(3);
+    public first_x = x(0);
+"#,
+        );
+        let witness = [
+            ("Main.x", vec![5.into(), 6.into(), 7.into(), 8.into()]),
+            ("Main.y", vec![1.into(), 2.into(), 3.into(), 4.into()]),
+        ];
+        let publics = Publics::from_witness(&analyzed, &witness).unwrap();
+        assert_eq!(publics.values(), vec![4.into(), 5.into()]);
+        assert_eq!(publics.get("first_x"), Some(5.into()));
+        assert_eq!(publics.get("y"), None);
+
+        let mut json = vec![];
+        publics.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert_eq!(json, r#"["4","5"]"#);
+        assert_eq!(
+            pil_analyzer::json_schema::validate(
+                &Publics::<GoldilocksField>::json_schema(),
+                &json::parse(&json).unwrap()
+            ),
+            Ok(())
+        );
+        assert_eq!(Publics::read_json(&analyzed, &json).unwrap(), publics);
+        assert!(Publics::read_json(&analyzed, r#"["4"]"#).is_err());
+
+        assert_eq!(
+            Publics::from_witness(&analyzed, &witness[..1]),
+            Err("Main.y".to_string())
+        );
+    }
+
+    #[test]
+    fn round_trip_above_half_modulus() {
+        let analyzed = analyze_string::<GoldilocksField>(
+            r#"
+namespace Main(4);
+    col witness x;
+    public first_x = x(0);
+"#,
+        );
+        // p - 1, which is displayed as -1 in the default format.
+        let value = -GoldilocksField::from(1);
+        let publics =
+            Publics::from_named_values(&analyzed, vec![("first_x".to_string(), value)]).unwrap();
+        let mut json = vec![];
+        publics.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert_eq!(json, r#"["18446744069414584320"]"#);
+        assert_eq!(Publics::read_json(&analyzed, &json).unwrap(), publics);
+    }
+}
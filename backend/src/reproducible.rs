@@ -0,0 +1,63 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+static REPRODUCIBLE: AtomicBool = AtomicBool::new(false);
+
+/// The seed of the randomness of proofs in reproducible mode, "powdr" in ASCII.
+const SEED: u64 = 0x706f776472;
+
+/// Enables or disables reproducible mode for the rest of the process. In
+/// reproducible mode, all artifacts are byte-identical across runs and
+/// machines: Proofs use fixed instead of fresh randomness and written files
+/// get a fixed modification time, see `timestamp`.
+///
+/// Proofs created in reproducible mode are not zero-knowledge, they are only
+/// meant to be audited and compared.
+pub fn set_reproducible(reproducible: bool) {
+    REPRODUCIBLE.store(reproducible, Ordering::Relaxed);
+}
+
+pub fn is_reproducible() -> bool {
+    REPRODUCIBLE.load(Ordering::Relaxed)
+}
+
+/// Returns the random number generator backends should use for proofs:
+/// Seeded from the operating system, or with a fixed seed in reproducible
+/// mode. Backends pass `is_reproducible()` unless a caller chose the mode.
+pub fn proof_rng(reproducible: bool) -> StdRng {
+    if reproducible {
+        StdRng::seed_from_u64(SEED)
+    } else {
+        StdRng::from_entropy()
+    }
+}
+
+/// Returns the modification time of written files in reproducible mode:
+/// `SOURCE_DATE_EPOCH` (seconds since the Unix epoch) if it is set,
+/// or the Unix epoch.
+pub fn timestamp() -> Result<SystemTime, String> {
+    match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(seconds) => seconds
+            .trim()
+            .parse()
+            .map(|seconds| UNIX_EPOCH + Duration::from_secs(seconds))
+            .map_err(|_| format!("Invalid SOURCE_DATE_EPOCH: {seconds}")),
+        Err(_) => Ok(UNIX_EPOCH),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::RngCore;
+
+    use super::*;
+
+    #[test]
+    fn fixed_randomness() {
+        assert_eq!(proof_rng(true).next_u64(), proof_rng(true).next_u64());
+        assert_ne!(proof_rng(false).next_u64(), proof_rng(false).next_u64());
+    }
+}
@@ -0,0 +1,368 @@
+//! Shell completions and man pages, generated from the clap definitions.
+
+use std::{fmt, fmt::Write, str::FromStr};
+
+use clap::{Arg, Command};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => Err(format!("Unknown shell {s}, expected bash, zsh or fish.")),
+        }
+    }
+}
+
+impl fmt::Display for Shell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Shell::Bash => write!(f, "bash"),
+            Shell::Zsh => write!(f, "zsh"),
+            Shell::Fish => write!(f, "fish"),
+        }
+    }
+}
+
+/// Returns the completion script for the shell. Options and subcommands are
+/// completed, all other arguments are completed as file names.
+pub fn completions(mut cmd: Command, bin_name: &str, shell: Shell) -> String {
+    // Adds the help options and the global options to the subcommands.
+    cmd.build();
+    match shell {
+        Shell::Bash => bash(&cmd, bin_name),
+        Shell::Zsh => zsh(&cmd, bin_name),
+        Shell::Fish => fish(&cmd, bin_name),
+    }
+}
+
+/// Returns the man pages of the command and all subcommands by file name,
+/// like `powdr.1` and `powdr-prove.1`.
+pub fn man_pages(mut cmd: Command, bin_name: &str) -> Vec<(String, String)> {
+    cmd.build();
+    std::iter::once(man_page(&cmd, bin_name, None))
+        .chain(man_subcommands(&cmd).map(|sub| {
+            man_page(
+                sub,
+                &format!("{bin_name}-{}", sub.get_name()),
+                Some(bin_name),
+            )
+        }))
+        .collect()
+}
+
+fn subcommands(cmd: &Command) -> impl Iterator<Item = &Command> {
+    cmd.get_subcommands().filter(|sub| !sub.is_hide_set())
+}
+
+/// The subcommands that get a man page, the help subcommand only repeats
+/// what the man pages say.
+fn man_subcommands(cmd: &Command) -> impl Iterator<Item = &Command> {
+    subcommands(cmd).filter(|sub| sub.get_name() != "help")
+}
+
+fn options(cmd: &Command) -> impl Iterator<Item = &Arg> {
+    cmd.get_arguments()
+        .filter(|arg| !arg.is_positional() && !arg.is_hide_set())
+}
+
+fn takes_value(arg: &Arg) -> bool {
+    arg.get_action().takes_values()
+}
+
+fn value_name(arg: &Arg) -> String {
+    arg.get_value_names()
+        .and_then(|names| names.first())
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| arg.get_id().as_str().to_uppercase())
+}
+
+/// The first line of the help of the argument or command.
+fn summary(help: Option<&clap::builder::StyledStr>) -> String {
+    help.map(|help| help.to_string())
+        .unwrap_or_default()
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// The names of the options with their dashes, like `-o` and `--output-directory`.
+fn option_names(arg: &Arg) -> Vec<String> {
+    arg.get_short()
+        .map(|short| format!("-{short}"))
+        .into_iter()
+        .chain(arg.get_long().map(|long| format!("--{long}")))
+        .collect()
+}
+
+fn bash(cmd: &Command, bin_name: &str) -> String {
+    let function = format!("_{}", bin_name.replace('-', "_"));
+    let words = |cmd: &Command| {
+        options(cmd)
+            .flat_map(option_names)
+            .chain(subcommands(cmd).map(|sub| sub.get_name().to_string()))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let mut script = String::new();
+    writeln!(script, "{function}() {{").unwrap();
+    writeln!(script, "    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"").unwrap();
+    writeln!(script, "    local subcommand=\"\"").unwrap();
+    writeln!(script, "    local word").unwrap();
+    writeln!(
+        script,
+        "    for word in \"${{COMP_WORDS[@]:1:COMP_CWORD-1}}\"; do"
+    )
+    .unwrap();
+    writeln!(script, "        case \"$word\" in").unwrap();
+    for sub in subcommands(cmd) {
+        writeln!(script, "            {})", sub.get_name()).unwrap();
+        writeln!(script, "                subcommand=\"$word\"").unwrap();
+        writeln!(script, "                break").unwrap();
+        writeln!(script, "                ;;").unwrap();
+    }
+    writeln!(script, "        esac").unwrap();
+    writeln!(script, "    done").unwrap();
+    writeln!(script, "    local words").unwrap();
+    writeln!(script, "    case \"$subcommand\" in").unwrap();
+    writeln!(script, "        \"\")").unwrap();
+    writeln!(script, "            words=\"{}\"", words(cmd)).unwrap();
+    writeln!(script, "            ;;").unwrap();
+    for sub in subcommands(cmd) {
+        writeln!(script, "        {})", sub.get_name()).unwrap();
+        writeln!(script, "            words=\"{}\"", words(sub)).unwrap();
+        writeln!(script, "            ;;").unwrap();
+    }
+    writeln!(script, "    esac").unwrap();
+    writeln!(
+        script,
+        "    if [[ \"$cur\" == -* || -z \"$subcommand\" ]]; then"
+    )
+    .unwrap();
+    writeln!(
+        script,
+        "        COMPREPLY=($(compgen -W \"$words\" -- \"$cur\"))"
+    )
+    .unwrap();
+    writeln!(script, "    fi").unwrap();
+    writeln!(script, "}}").unwrap();
+    writeln!(script, "complete -F {function} -o default {bin_name}").unwrap();
+    script
+}
+
+fn zsh(cmd: &Command, bin_name: &str) -> String {
+    let escape = |s: &str| {
+        s.replace('\'', "'\\''")
+            .replace('[', "\\[")
+            .replace(']', "\\]")
+            .replace(':', "\\:")
+    };
+    let option_specs = |cmd: &Command| {
+        options(cmd)
+            .flat_map(|arg| {
+                let help = escape(&summary(arg.get_help()));
+                let value = if takes_value(arg) {
+                    format!(":{}:_files", value_name(arg).to_lowercase())
+                } else {
+                    String::new()
+                };
+                option_names(arg)
+                    .into_iter()
+                    .map(move |name| format!("'{name}[{help}]{value}'"))
+            })
+            .collect::<Vec<_>>()
+    };
+    let function = format!("_{}", bin_name.replace('-', "_"));
+    let mut script = String::new();
+    writeln!(script, "#compdef {bin_name}\n").unwrap();
+    writeln!(script, "{function}() {{").unwrap();
+    writeln!(script, "    local -a subcommands").unwrap();
+    writeln!(script, "    subcommands=(").unwrap();
+    for sub in subcommands(cmd) {
+        let about = summary(sub.get_about()).replace('\'', "'\\''");
+        writeln!(script, "        '{}:{about}'", sub.get_name()).unwrap();
+    }
+    writeln!(script, "    )").unwrap();
+    writeln!(script, "    local state").unwrap();
+    writeln!(script, "    _arguments -C \\").unwrap();
+    for spec in option_specs(cmd) {
+        writeln!(script, "        {spec} \\").unwrap();
+    }
+    writeln!(script, "        '1: :->subcommand' \\").unwrap();
+    writeln!(script, "        '*:: :->arguments'").unwrap();
+    writeln!(script, "    case $state in").unwrap();
+    writeln!(script, "        subcommand)").unwrap();
+    writeln!(script, "            _describe 'command' subcommands").unwrap();
+    writeln!(script, "            ;;").unwrap();
+    writeln!(script, "        arguments)").unwrap();
+    writeln!(script, "            case $words[1] in").unwrap();
+    for sub in subcommands(cmd) {
+        writeln!(script, "                {})", sub.get_name()).unwrap();
+        writeln!(script, "                    _arguments \\").unwrap();
+        for spec in option_specs(sub) {
+            writeln!(script, "                        {spec} \\").unwrap();
+        }
+        writeln!(script, "                        '*:file:_files'").unwrap();
+        writeln!(script, "                    ;;").unwrap();
+    }
+    writeln!(script, "            esac").unwrap();
+    writeln!(script, "            ;;").unwrap();
+    writeln!(script, "    esac").unwrap();
+    writeln!(script, "}}\n").unwrap();
+    writeln!(script, "{function} \"$@\"").unwrap();
+    script
+}
+
+fn fish(cmd: &Command, bin_name: &str) -> String {
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('\'', "\\'");
+    let complete_options = |script: &mut String, cmd: &Command, condition: &str| {
+        for arg in options(cmd) {
+            let mut line = format!("complete -c {bin_name} -n '{condition}'");
+            if let Some(short) = arg.get_short() {
+                write!(line, " -s {short}").unwrap();
+            }
+            if let Some(long) = arg.get_long() {
+                write!(line, " -l {long}").unwrap();
+            }
+            if takes_value(arg) {
+                line += " -r";
+            }
+            write!(line, " -d '{}'", escape(&summary(arg.get_help()))).unwrap();
+            writeln!(script, "{line}").unwrap();
+        }
+    };
+    let mut script = String::new();
+    complete_options(&mut script, cmd, "__fish_use_subcommand");
+    for sub in subcommands(cmd) {
+        writeln!(
+            script,
+            "complete -c {bin_name} -n '__fish_use_subcommand' -f -a {} -d '{}'",
+            sub.get_name(),
+            escape(&summary(sub.get_about()))
+        )
+        .unwrap();
+    }
+    for sub in subcommands(cmd) {
+        let condition = format!("__fish_seen_subcommand_from {}", sub.get_name());
+        complete_options(&mut script, sub, &condition);
+    }
+    script
+}
+
+/// Returns the file name and the contents of the man page of the command.
+/// `parent` is the name of the parent command of a subcommand.
+fn man_page(cmd: &Command, name: &str, parent: Option<&str>) -> (String, String) {
+    let escape = |s: &str| {
+        s.lines()
+            .map(|line| {
+                let line = line.replace('\\', "\\\\").replace('-', "\\-");
+                if line.starts_with('.') || line.starts_with('\'') {
+                    format!("\\&{line}")
+                } else {
+                    line
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let help = |help: Option<&clap::builder::StyledStr>| {
+        escape(&help.map(|help| help.to_string()).unwrap_or_default())
+    };
+    let mut page = String::new();
+    writeln!(page, ".TH {} 1", escape(&name.to_uppercase())).unwrap();
+    writeln!(page, ".SH NAME").unwrap();
+    writeln!(
+        page,
+        "{} \\- {}",
+        escape(name),
+        escape(&summary(cmd.get_about()))
+    )
+    .unwrap();
+    writeln!(page, ".SH SYNOPSIS").unwrap();
+    let invocation = match parent {
+        Some(parent) => format!("{parent} {}", cmd.get_name()),
+        None => name.to_string(),
+    };
+    let mut synopsis = format!("\\fB{}\\fR [\\fIOPTIONS\\fR]", escape(&invocation));
+    for arg in cmd.get_arguments().filter(|arg| arg.is_positional()) {
+        let value = format!("<{}>", value_name(arg));
+        if arg.is_required_set() {
+            write!(synopsis, " \\fI{value}\\fR").unwrap();
+        } else {
+            write!(synopsis, " [\\fI{value}\\fR]").unwrap();
+        }
+    }
+    if cmd.has_subcommands() {
+        synopsis += " \\fI<COMMAND>\\fR";
+    }
+    writeln!(page, "{synopsis}").unwrap();
+    writeln!(page, ".SH DESCRIPTION").unwrap();
+    writeln!(
+        page,
+        "{}",
+        help(cmd.get_long_about().or_else(|| cmd.get_about()))
+    )
+    .unwrap();
+    let positional = cmd
+        .get_arguments()
+        .filter(|arg| arg.is_positional())
+        .collect::<Vec<_>>();
+    if !positional.is_empty() {
+        writeln!(page, ".SH ARGUMENTS").unwrap();
+        for arg in positional {
+            writeln!(page, ".TP\n\\fI<{}>\\fR", escape(&value_name(arg))).unwrap();
+            writeln!(
+                page,
+                "{}",
+                help(arg.get_long_help().or_else(|| arg.get_help()))
+            )
+            .unwrap();
+        }
+    }
+    writeln!(page, ".SH OPTIONS").unwrap();
+    for arg in options(cmd) {
+        let mut names = option_names(arg)
+            .iter()
+            .map(|name| format!("\\fB{}\\fR", escape(name)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if takes_value(arg) {
+            write!(names, " \\fI<{}>\\fR", escape(&value_name(arg))).unwrap();
+        }
+        writeln!(page, ".TP\n{names}").unwrap();
+        writeln!(
+            page,
+            "{}",
+            help(arg.get_long_help().or_else(|| arg.get_help()))
+        )
+        .unwrap();
+    }
+    if cmd.has_subcommands() {
+        writeln!(page, ".SH COMMANDS").unwrap();
+        for sub in man_subcommands(cmd) {
+            writeln!(
+                page,
+                ".TP\n\\fB{}\\-{}\\fR(1)",
+                escape(name),
+                escape(sub.get_name())
+            )
+            .unwrap();
+            writeln!(page, "{}", help(sub.get_about())).unwrap();
+        }
+    }
+    if let Some(parent) = parent {
+        writeln!(page, ".SH SEE ALSO\n\\fB{}\\fR(1)", escape(parent)).unwrap();
+    }
+    (format!("{name}.1"), page)
+}
@@ -0,0 +1,104 @@
+//! Sets up the output of log messages and tracing spans.
+
+use std::{fmt, fs::File, io, io::BufWriter, path::Path, str::FromStr};
+
+use tracing::{Event, Subscriber};
+use tracing_flame::{FlameLayer, FlushGuard};
+use tracing_subscriber::{
+    filter::{EnvFilter, LevelFilter},
+    fmt::{
+        format::{FmtSpan, Writer},
+        FmtContext, FormatEvent, FormatFields,
+    },
+    layer::SubscriberExt,
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Only the messages, one per line.
+    Text,
+    /// One JSON object per line with the level, the fields, the enclosing
+    /// spans and, when a span closes, its duration.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Unknown log format {s}, expected text or json.")),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogFormat::Text => write!(f, "text"),
+            LogFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Writes log messages (from `log` and `tracing`) to stdout, filtered by
+/// `RUST_LOG` with a default level of info.
+/// If `flamegraph` is given, the spans are also recorded there as folded stacks
+/// (for `inferno-flamegraph` or `flamegraph.pl`), until the returned guard is dropped.
+pub fn init(format: LogFormat, flamegraph: Option<&Path>) -> Option<FlushGuard<BufWriter<File>>> {
+    let filter = EnvFilter::builder()
+        .with_default_directive(LevelFilter::INFO.into())
+        .from_env_lossy();
+    let text = (format == LogFormat::Text).then(|| {
+        tracing_subscriber::fmt::layer()
+            .with_writer(io::stdout)
+            .event_format(MessageOnly)
+    });
+    let json = (format == LogFormat::Json).then(|| {
+        tracing_subscriber::fmt::layer()
+            .with_writer(io::stdout)
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_span_events(FmtSpan::CLOSE)
+    });
+    let (flame, guard) = match flamegraph {
+        Some(path) => {
+            let (layer, guard) = FlameLayer::with_file(path).unwrap_or_else(|e| {
+                panic!("Could not create {}: {e}", path.display());
+            });
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(text)
+        .with(json)
+        .with(flame)
+        .init();
+    guard
+}
+
+/// Formats an event as its message and fields, without level, time or spans.
+struct MessageOnly;
+
+impl<S, N> FormatEvent<S, N> for MessageOnly
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
@@ -1,15 +1,84 @@
 //! The powdr CLI tool
 
-use clap::{Parser, Subcommand};
-use compiler::no_callback;
-use env_logger::{Builder, Target};
-use log::LevelFilter;
-use number::FieldElement;
-use std::{fs, io::Write, path::Path};
+use clap::{CommandFactory, Parser, Subcommand};
+use compiler::{
+    diagnostics, no_callback, with_field, Artifact, BackendType, CompileOptions, FieldType,
+    OutputLayout, ProjectConfig, ProofArtifact, ThreadConfig,
+};
+use completions::Shell;
+use logging::LogFormat;
+use number::{
+    format::{set_display_format, DisplayFormat},
+    AbstractNumberType, FieldElement, GoldilocksField,
+};
+use parser::LiteralOverflowPolicy;
+use std::{
+    collections::BTreeSet,
+    fs, panic,
+    path::{Path, PathBuf},
+};
+
+mod completions;
+mod logging;
+mod watch;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    /// How field elements are displayed in messages and logs:
+    /// signed, decimal, hex or abbreviated.
+    #[arg(long, global = true)]
+    #[arg(default_value_t = DisplayFormat::Signed)]
+    number_format: DisplayFormat,
+
+    /// What to do with number literals that do not fit into the field:
+    /// error, warn (and reduce) or wrap (reduce silently).
+    #[arg(long, global = true)]
+    #[arg(default_value_t = LiteralOverflowPolicy::Error)]
+    literal_overflow: LiteralOverflowPolicy,
+
+    /// How log messages are written: text or json (one object per line,
+    /// with the enclosing spans and the durations of the pipeline phases).
+    #[arg(long, global = true)]
+    #[arg(default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// The field to run the pipeline in: gl, bn254 or bb. Defaults to the
+    /// field the backend requires, or to Goldilocks.
+    #[arg(long, global = true)]
+    field: Option<FieldType>,
+
+    /// The number of threads for all parallel phases (8), for individual
+    /// phases (witgen=1,backend=16) or both (8,witgen=1). The phases are
+    /// fixed, witgen and backend. Overrides the POWDR_THREADS environment
+    /// variable, the default is one thread per CPU.
+    #[arg(long, global = true)]
+    threads: Option<ThreadConfig>,
+
+    /// Makes all written artifacts byte-identical across runs and machines,
+    /// for audits: Proofs use fixed randomness (and are not zero-knowledge),
+    /// paths of temporary and source directories are replaced in the
+    /// generated assembly and the modification time of written files is
+    /// SOURCE_DATE_EPOCH, or the Unix epoch if it is not set.
+    #[arg(long, global = true)]
+    reproducible: bool,
+
+    /// Defines a name for `#if NAME` in assembly and PIL files, can be
+    /// given several times.
+    #[arg(short = 'D', long = "define", value_name = "NAME", global = true)]
+    defines: Vec<String>,
+
+    /// Optimizes the assembly before it is compiled to PIL: Propagates
+    /// registers with constant values, folds arithmetic on constants and
+    /// removes writes that are overwritten before they are read.
+    #[arg(long, global = true)]
+    optimize: bool,
+
+    /// Encodes the instruction flags of assembly programs in a few fixed
+    /// columns instead of one fixed column per instruction.
+    #[arg(long, global = true)]
+    compress_rom: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -20,7 +89,49 @@ enum Commands {
     /// and finally to PIL and generates fixed and witness columns.
     /// Needs `rustup target add riscv32imc-unknown-none-elf`.
     Rust {
-        /// Input file (rust source file) or directory (containing a crate).
+        /// Input file (rust source file), directory or Cargo.toml (of a crate or workspace).
+        file: String,
+
+        /// Comma-separated list of free inputs (numbers).
+        #[arg(short, long)]
+        #[arg(default_value_t = String::new())]
+        inputs: String,
+
+        /// The package to build, if the crate is a workspace.
+        #[arg(long)]
+        package: Option<String>,
+
+        /// Comma-separated list of features of the crate to enable.
+        #[arg(long)]
+        #[arg(default_value_t = String::new())]
+        features: String,
+
+        /// Do not enable the default features of the crate.
+        #[arg(long)]
+        #[arg(default_value_t = false)]
+        no_default_features: bool,
+
+        /// Directory to cache the powdr assembly of crates in. Crates are
+        /// only rebuilt if their sources or the build options change.
+        #[arg(long)]
+        cache_dir: Option<String>,
+
+        /// Directory for  output files.
+        #[arg(short, long)]
+        #[arg(default_value_t = String::from("."))]
+        output_directory: String,
+
+        /// Force overwriting of files in output directory.
+        #[arg(short, long)]
+        #[arg(default_value_t = false)]
+        force: bool,
+    },
+
+    /// Compiles (freestanding) C or C++ code to riscv assembly using clang,
+    /// then to powdr assembly and finally to PIL and generates fixed and witness columns.
+    /// Sources can include "powdr.h" to access prover inputs.
+    C {
+        /// Input file (C/C++ source file) or directory (containing source files).
         file: String,
 
         /// Comma-separated list of free inputs (numbers).
@@ -71,6 +182,12 @@ enum Commands {
         #[arg(default_value_t = String::new())]
         inputs: String,
 
+        /// The number of the exported label that programs with `export`
+        /// start at, in the order of the `export` statements.
+        #[arg(long, value_name = "NUMBER")]
+        #[arg(default_value_t = 0)]
+        entry_point: u64,
+
         /// Output directory for PIL file, json file and fixed and witness column data.
         #[arg(short, long)]
         #[arg(default_value_t = String::from("."))]
@@ -80,6 +197,299 @@ enum Commands {
         #[arg(short, long)]
         #[arg(default_value_t = false)]
         force: bool,
+
+        /// Prepended to the names of all output files.
+        #[arg(long)]
+        #[arg(default_value_t = String::new())]
+        prefix: String,
+
+        /// Comma-separated list of the artifacts to write: pil, json,
+        /// constants, witness and proof. Defaults to all of them.
+        #[arg(long, value_parser = compiler::parse_artifacts)]
+        artifacts: Option<BTreeSet<Artifact>>,
+
+        /// Generate a proof with a given backend (halo2, estark or mock).
+        #[arg(long)]
+        prove_with: Option<BackendType>,
+
+        /// Watch the input file (and its includes) and run again when it changes.
+        #[arg(long)]
+        #[arg(default_value_t = false)]
+        watch: bool,
+    },
+
+    /// Executes an assembly file directly, without compiling it to PIL, and
+    /// prints the number of executed lines and the final values of the
+    /// registers. Instructions that access other machines are not supported.
+    Interpret {
+        /// Input file
+        file: String,
+
+        /// Comma-separated list of free inputs (numbers).
+        #[arg(short, long)]
+        #[arg(default_value_t = String::new())]
+        inputs: String,
+
+        /// The number of the exported label that programs with `export`
+        /// start at, in the order of the `export` statements.
+        #[arg(long, value_name = "NUMBER")]
+        #[arg(default_value_t = 0)]
+        entry_point: u64,
+
+        /// The number of lines after which the execution is aborted.
+        #[arg(long)]
+        #[arg(default_value_t = 1 << 20)]
+        max_steps: usize,
+    },
+
+    /// Compiles an assembly or PIL file, generates fixed and witness columns
+    /// and creates a proof, all in one step. Writes the proof artifact
+    /// (proof_artifact.json) and the public values (publics.json) to the
+    /// output directory, next to the PIL, json and column files.
+    /// Without an input file, the input file, field, degree, backend, output
+    /// directory and inputs are taken from powdr.toml in the current directory.
+    Prove {
+        /// Input file (.asm or .pil)
+        file: Option<String>,
+
+        /// The backend to prove with (halo2, estark or mock).
+        #[arg(short, long)]
+        backend: Option<BackendType>,
+
+        /// Comma-separated list of free inputs (numbers).
+        #[arg(short, long)]
+        #[arg(default_value_t = String::new())]
+        inputs: String,
+
+        /// The number of the exported label that programs with `export`
+        /// start at, in the order of the `export` statements.
+        #[arg(long, value_name = "NUMBER")]
+        #[arg(default_value_t = 0)]
+        entry_point: u64,
+
+        /// Output directory for the proof and all intermediate files.
+        /// Defaults to the current directory.
+        #[arg(short, long)]
+        output_directory: Option<String>,
+
+        /// The project configuration to use, even if an input file is given.
+        /// Options on the command line take precedence over the configuration.
+        #[arg(long)]
+        config: Option<String>,
+
+        /// Force overwriting of the PIL file generated from assembly.
+        #[arg(short, long)]
+        #[arg(default_value_t = false)]
+        force: bool,
+
+        /// Prepended to the names of all output files.
+        #[arg(long)]
+        #[arg(default_value_t = String::new())]
+        prefix: String,
+
+        /// Comma-separated list of the artifacts to write: pil, json,
+        /// constants, witness and proof. Defaults to all of them.
+        #[arg(long, value_parser = compiler::parse_artifacts)]
+        artifacts: Option<BTreeSet<Artifact>>,
+    },
+
+    /// Parses and analyzes the PIL file without generating any columns and
+    /// reports errors and warnings. Exits with 1 if there are errors.
+    Check {
+        /// Input file
+        file: String,
+
+        /// Print the diagnostics as a JSON array of objects with the fields
+        /// file, span, severity, code and message.
+        #[arg(long)]
+        #[arg(default_value_t = false)]
+        json: bool,
+
+        /// Watch the input file (and its includes) and run again when it changes.
+        #[arg(long)]
+        #[arg(default_value_t = false)]
+        watch: bool,
+    },
+
+    /// Runs the tests declared with `#[test] name(degree) { ... }` in a PIL
+    /// file: For each test, generates the witness with the statements of the
+    /// test and all namespaces at the degree of the test and checks the
+    /// identities. Exits with 1 if a test fails.
+    Test {
+        /// Input file
+        file: String,
+
+        /// Only run the tests whose names contain this string.
+        #[arg(default_value_t = String::new())]
+        filter: String,
+    },
+
+    /// Generates the witness of an assembly or PIL file with random answers
+    /// to the prover queries (for example the free inputs) and checks the
+    /// identities. Prints the first failing case after shrinking it and
+    /// exits with 1 if there is one.
+    Fuzz {
+        /// Input file (.asm or .pil)
+        file: String,
+
+        /// The number of random cases.
+        #[arg(long)]
+        #[arg(default_value_t = 100)]
+        runs: usize,
+
+        /// The seed of the random answers.
+        #[arg(long)]
+        #[arg(default_value_t = 0)]
+        seed: u64,
+    },
+
+    /// Compiles a PIL file with powdr and with pilcom (PILCOM environment
+    /// variable) and compares the JSON outputs, then checks the identities
+    /// with powdr and with the pil verifier of pilcom on the generated witness
+    /// and on randomly mutated witnesses. Prints the divergences and exits
+    /// with 1 if there are any.
+    Differential {
+        /// Input file
+        file: String,
+
+        /// Comma-separated list of free inputs (numbers).
+        #[arg(short, long)]
+        #[arg(default_value_t = String::new())]
+        inputs: String,
+
+        /// The number of witnesses with one randomly changed cell.
+        #[arg(long)]
+        #[arg(default_value_t = 10)]
+        mutations: usize,
+
+        /// The seed of the random mutations.
+        #[arg(long)]
+        #[arg(default_value_t = 0)]
+        seed: u64,
+    },
+
+    /// Loads a PIL file and starts an interactive session to evaluate
+    /// expressions over the fixed columns at given rows, look up definitions
+    /// and expand macros.
+    Repl {
+        /// Input file
+        file: String,
+    },
+
+    /// Prints selected columns of the witness generated for a PIL file
+    /// (commits.bin) as a table. Numbers are shown in the --number-format.
+    InspectWitness {
+        /// Input file
+        file: String,
+
+        /// Directory that contains commits.bin.
+        #[arg(short, long)]
+        #[arg(default_value_t = String::from("."))]
+        dir: String,
+
+        /// Comma-separated list of (witness or fixed) columns, with or without
+        /// namespace. Defaults to all witness columns.
+        #[arg(short, long)]
+        #[arg(default_value_t = String::new())]
+        columns: String,
+
+        /// A row (5) or range of rows (0..8) to print. Defaults to all rows.
+        #[arg(short, long)]
+        rows: Option<String>,
+
+        /// Marks the rows on which the identities at this line (or file:line) fail.
+        #[arg(long)]
+        identity: Option<String>,
+    },
+
+    /// Compares the witness generated for a PIL file (commits.bin) with a
+    /// snapshot file and prints the differing cells, or writes the snapshot
+    /// file if it does not exist. Exits with 1 if there are differences.
+    Snapshot {
+        /// Input file
+        file: String,
+
+        /// The snapshot file.
+        snapshot: String,
+
+        /// Directory that contains commits.bin.
+        #[arg(short, long)]
+        #[arg(default_value_t = String::from("."))]
+        dir: String,
+
+        /// Overwrite the snapshot file with the current witness.
+        #[arg(long)]
+        #[arg(default_value_t = false)]
+        update: bool,
+    },
+
+    /// Writes the trace of a PIL file (fixed columns and commits.bin) as a
+    /// self-contained HTML page, with the identities checked on every row
+    /// and links from the cells to the PIL source.
+    TraceHtml {
+        /// Input file
+        file: String,
+
+        /// Directory that contains commits.bin.
+        #[arg(short, long)]
+        #[arg(default_value_t = String::from("."))]
+        dir: String,
+
+        /// A row (5) or range of rows (0..8) to include. Defaults to all rows.
+        #[arg(short, long)]
+        rows: Option<String>,
+
+        /// The HTML file to write.
+        #[arg(short, long)]
+        #[arg(default_value_t = String::from("trace.html"))]
+        output: String,
+    },
+
+    /// Runs the pipeline on an assembly or PIL file several times and reports
+    /// the wall time and peak memory of every phase and the witness
+    /// generation speed in rows per second.
+    Bench {
+        /// Input file (.asm or .pil)
+        file: String,
+
+        /// Comma-separated list of free inputs (numbers).
+        #[arg(short, long)]
+        #[arg(default_value_t = String::new())]
+        inputs: String,
+
+        /// How often to run the pipeline.
+        #[arg(short = 'n', long)]
+        #[arg(default_value_t = 5)]
+        iterations: usize,
+
+        /// Also create proofs with the given backend (halo2 or mock).
+        #[arg(short, long)]
+        backend: Option<BackendType>,
+
+        /// Record the spans of all runs as folded stacks in this file,
+        /// to be rendered with inferno-flamegraph or flamegraph.pl.
+        #[arg(long)]
+        flamegraph: Option<String>,
+
+        /// Reset the peak memory before each phase (Linux only), to report
+        /// the peak of each phase instead of the peak of the process so far.
+        /// This writes to /proc/self/clear_refs, which also clears the
+        /// referenced and soft-dirty bits of the pages of the process.
+        #[arg(long)]
+        #[arg(default_value_t = false)]
+        reset_peak_memory: bool,
+    },
+
+    /// Formats PIL and powdr assembly files (.asm) in place.
+    Fmt {
+        /// Input files
+        #[arg(required = true)]
+        files: Vec<String>,
+
+        /// Do not write the files, exit with 1 if any of them is not formatted.
+        #[arg(long)]
+        #[arg(default_value_t = false)]
+        check: bool,
     },
 
     /// Parses and prints the PIL file on stdout.
@@ -88,6 +498,229 @@ enum Commands {
         file: String,
     },
 
+    /// Generates fixed and witness columns for the PIL file and exports the
+    /// constraints and the witness in the iden3 r1cs and wtns formats over
+    /// the BN254 scalar field, for use with snarkjs.
+    ExportR1cs {
+        /// Input file
+        file: String,
+        /// Output directory for the r1cs and wtns files.
+        #[arg(short, long)]
+        #[arg(default_value_t = String::from("."))]
+        output_directory: String,
+    },
+
+    /// Prints a Graphviz graph of the machines (namespaces) of an assembly or
+    /// PIL file and the lookups, permutations and connections between them,
+    /// for example for `dot -Tsvg`.
+    Dot {
+        /// Input file (.asm or .pil)
+        file: String,
+
+        /// Write the graph to this file instead of stdout.
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Prints the identities of an assembly or PIL file on the first rows as
+    /// an SMT-LIB script, to check properties of small instances with Z3 or
+    /// cvc5. The witness cells are named like `|Main.x@3|`.
+    ExportSmt {
+        /// Input file (.asm or .pil)
+        file: String,
+
+        /// Only export the identities that reference this namespace.
+        #[arg(short, long)]
+        namespace: Option<String>,
+
+        /// The number of rows, at most the degree.
+        #[arg(short, long)]
+        #[arg(default_value_t = 8)]
+        rows: u64,
+
+        /// Write the script to this file instead of stdout.
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Prints on how many rows each identity of a PIL file is active, which
+    /// rows of lookup tables are never looked up and which arms of the
+    /// queries are never hit by the witness (commits.bin).
+    Coverage {
+        /// Input file
+        file: String,
+
+        /// Directory that contains commits.bin.
+        #[arg(short, long)]
+        #[arg(default_value_t = String::from("."))]
+        dir: String,
+    },
+
+    /// Generates the witness of an assembly or PIL file and prints the
+    /// witness columns that might be under-constrained: columns that no
+    /// (active) identity references and columns whose values witness
+    /// generation could not derive. Exits with 1 if there are any.
+    Underconstrained {
+        /// Input file (.asm or .pil)
+        file: String,
+
+        /// Comma-separated list of free inputs (numbers).
+        #[arg(short, long)]
+        #[arg(default_value_t = String::new())]
+        inputs: String,
+    },
+
+    /// Generates the witness of an assembly or PIL file and records which
+    /// identity, query or machine determined the value of each cell. Writes
+    /// the log (one line per cell in the order of deduction) to a file and
+    /// prints what determined the given cells.
+    Audit {
+        /// Input file (.asm or .pil)
+        file: String,
+
+        /// Comma-separated list of free inputs (numbers).
+        #[arg(short, long)]
+        #[arg(default_value_t = String::new())]
+        inputs: String,
+
+        /// Write the log to this file.
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// A cell to explain, like `Main.x@7`. Can be given several times.
+        #[arg(short, long = "cell")]
+        cells: Vec<String>,
+    },
+
+    /// Generates the witness of an assembly or PIL file and writes the
+    /// responses to all prover queries to a replay file, also if witness
+    /// generation fails. See the replay command.
+    Record {
+        /// Input file (.asm or .pil)
+        file: String,
+
+        /// Comma-separated list of free inputs (numbers).
+        #[arg(short, long)]
+        #[arg(default_value_t = String::new())]
+        inputs: String,
+
+        /// The replay file to write.
+        replay: String,
+    },
+
+    /// Generates the witness of an assembly or PIL file with the query
+    /// responses of a replay file written by the record command, without
+    /// the inputs. Fails if witness generation makes other queries.
+    Replay {
+        /// Input file (.asm or .pil)
+        file: String,
+
+        /// The replay file.
+        replay: String,
+
+        /// Write the witness to commits.bin in this directory.
+        #[arg(short, long)]
+        output_directory: Option<String>,
+    },
+
+    /// Checks weakened variants of the identities of an assembly or PIL file
+    /// (one identity removed, lookups with fewer columns or without table
+    /// selector) against witnesses the identities reject and prints which
+    /// variants still reject all of them. Exits with 1 if any do.
+    Mutate {
+        /// Input file (.asm or .pil)
+        file: String,
+
+        /// Directory with a bad witness in commits.bin, can be given several
+        /// times.
+        #[arg(short, long = "witness", value_name = "DIR", required = true)]
+        witnesses: Vec<String>,
+    },
+
+    /// Generates the fixed columns and the witness of an assembly or PIL file
+    /// several times with different numbers of threads and prints the cells
+    /// that differ between the runs. Exits with 1 if there are any.
+    Determinism {
+        /// Input file (.asm or .pil)
+        file: String,
+
+        /// Comma-separated list of free inputs (numbers).
+        #[arg(short, long)]
+        #[arg(default_value_t = String::new())]
+        inputs: String,
+
+        /// Comma-separated list of the numbers of threads of the runs.
+        #[arg(long)]
+        #[arg(default_value_t = String::from("1,4,1"))]
+        thread_counts: String,
+    },
+
+    /// Prints the number of columns by type, the number of identities by
+    /// kind, the maximal identity degree and the estimated number of
+    /// committed cells of an assembly or PIL file, by namespace.
+    Stats {
+        /// Input file (.asm or .pil)
+        file: String,
+
+        /// Also generate the witness and print the memory used by the
+        /// columns, lookup indexes and solver state of each machine, and
+        /// the resident memory after each phase.
+        #[arg(long)]
+        #[arg(default_value_t = false)]
+        memory: bool,
+
+        /// Comma-separated list of free inputs (numbers), for --memory.
+        #[arg(short, long)]
+        #[arg(default_value_t = String::new())]
+        inputs: String,
+    },
+
+    /// Prints the completion script for bash, zsh or fish, or writes the man
+    /// pages of all commands to a directory.
+    Completions {
+        /// The shell to print the completion script for: bash, zsh or fish.
+        #[arg(required_unless_present = "man")]
+        shell: Option<Shell>,
+
+        /// Write the man pages to this directory instead.
+        #[arg(long, conflicts_with = "shell")]
+        man: Option<String>,
+
+        /// The name the binary is installed as, defaults to the name of the command.
+        #[arg(long)]
+        bin_name: Option<String>,
+    },
+
+    /// Prints the JSON Schema document of the JSON export of a PIL file
+    /// (json), the proof artifact (proof) or the public values (publics), or
+    /// writes all of them to a directory.
+    Schema {
+        /// The document to print: json, proof or publics.
+        #[arg(required_unless_present = "dir")]
+        document: Option<String>,
+
+        /// Write all documents to this directory instead, as
+        /// `<document>.schema.json`.
+        #[arg(long, conflicts_with = "document")]
+        dir: Option<String>,
+    },
+
+    /// Verifies a proof artifact (proof_artifact.json) created for the PIL file.
+    Verify {
+        /// The PIL file the proof was created for.
+        file: String,
+        /// The proof artifact.
+        proof: String,
+        /// The setup written when proving (<backend>_setup_<id>.bin).
+        /// Derived from the PIL file if not given.
+        #[arg(long)]
+        setup: Option<String>,
+        /// The expected public values (publics.json). If given, the public
+        /// values of the proof have to match them.
+        #[arg(long)]
+        publics: Option<String>,
+    },
+
     /// Compiles the PIL file to json and generates fixed and witness columns.
     Compile {
         /// Input file
@@ -96,41 +729,330 @@ enum Commands {
         #[arg(short, long)]
         #[arg(default_value_t = String::from("."))]
         output_directory: String,
+
+        /// Prepended to the names of all output files.
+        #[arg(long)]
+        #[arg(default_value_t = String::new())]
+        prefix: String,
+
+        /// Comma-separated list of the artifacts to write: pil, json,
+        /// constants, witness and proof. Defaults to all of them.
+        #[arg(long, value_parser = compiler::parse_artifacts)]
+        artifacts: Option<BTreeSet<Artifact>>,
+
+        /// Generate a proof with a given backend (halo2, estark or mock).
+        #[arg(long)]
+        prove_with: Option<BackendType>,
+
+        /// Watch the input file (and its includes) and run again when it changes.
+        #[arg(long)]
+        #[arg(default_value_t = false)]
+        watch: bool,
     },
 }
 
-fn split_inputs(inputs: &str) -> Vec<FieldElement> {
+fn split_inputs<T: FieldElement>(inputs: &str) -> Vec<T> {
     inputs
         .split(',')
         .map(|x| x.trim())
         .filter(|x| !x.is_empty())
         .map(|x| x.parse::<u64>().unwrap().into())
-        .collect::<Vec<FieldElement>>()
+        .collect()
+}
+
+/// Returns the modulus of the field to run in, see `compiler::select_field`,
+/// and exits if the backend does not support the selected field.
+fn field_modulus(field: Option<FieldType>, backend: Option<BackendType>) -> AbstractNumberType {
+    compiler::select_field(field, backend).unwrap_or_else(|err| {
+        log::error!("{err}");
+        std::process::exit(1);
+    })
+}
+
+fn output_layout(
+    output_directory: &str,
+    prefix: String,
+    artifacts: Option<BTreeSet<Artifact>>,
+) -> OutputLayout {
+    OutputLayout {
+        prefix,
+        artifacts: artifacts.unwrap_or_else(|| Artifact::ALL.into_iter().collect()),
+        ..OutputLayout::new(output_directory)
+    }
+}
+
+/// Prints the diagnostics for the PIL file.
+/// @returns false if there are errors.
+fn check<T: FieldElement>(file: &str, json: bool) -> bool {
+    let diagnostics = diagnostics::check::<T>(Path::new(file));
+    if json {
+        println!("{}", diagnostics::to_json(&diagnostics));
+    } else {
+        for diagnostic in &diagnostics {
+            eprintln!("{diagnostic}");
+        }
+    }
+    !diagnostics
+        .iter()
+        .any(|d| d.severity == diagnostics::Severity::Error)
+}
+
+/// Runs the tests of the PIL file and prints their results.
+/// @returns false if a test failed.
+fn run_tests<T: FieldElement>(file: &str, options: &CompileOptions, filter: &str) -> bool {
+    let outcomes = match compiler::run_tests::<T>(Path::new(file), options, filter) {
+        Ok(outcomes) => outcomes,
+        Err(e) => {
+            eprintln!("{e}");
+            return false;
+        }
+    };
+    println!("running {} tests", outcomes.len());
+    for outcome in &outcomes {
+        let result = if outcome.passed() { "ok" } else { "FAILED" };
+        println!("test {} ... {result}", outcome.test.name);
+    }
+    let failed = outcomes.iter().filter(|o| !o.passed()).collect::<Vec<_>>();
+    for outcome in &failed {
+        let test = &outcome.test;
+        println!(
+            "\n---- {} ({}:{}) ----",
+            test.name, test.source.file, test.source.line
+        );
+        match &outcome.failure {
+            Some(failure) => println!("{failure}"),
+            None => println!("The test should fail, but the witness satisfies all identities."),
+        }
+    }
+    println!(
+        "\ntest result: {}. {} passed; {} failed",
+        if failed.is_empty() { "ok" } else { "FAILED" },
+        outcomes.len() - failed.len(),
+        failed.len()
+    );
+    failed.is_empty()
+}
+
+/// Executes the assembly file and prints the result.
+/// @returns false if the execution failed.
+fn interpret<T: FieldElement>(
+    file: &str,
+    options: &CompileOptions,
+    inputs: &str,
+    entry_point: u64,
+    max_steps: usize,
+) -> bool {
+    let contents = fs::read_to_string(file).unwrap();
+    let execution = compiler::interpret::<T>(
+        Some(file),
+        &contents,
+        &options.defines,
+        entry_point,
+        &split_inputs::<T>(inputs),
+        &mut Default::default(),
+        max_steps,
+    )
+    .unwrap_or_else(|err| {
+        eprintln!("Error parsing .asm file:");
+        err.output_to_stderr();
+        std::process::exit(1);
+    });
+    println!("Executed {} lines.", execution.steps);
+    for (name, value) in &execution.registers {
+        println!("{name} = {value}");
+    }
+    match execution.error {
+        Some(error) => {
+            eprintln!("{error}");
+            false
+        }
+        None => true,
+    }
+}
+
+/// Fuzzes the file and prints the failing case, if any.
+/// @returns false if a case failed.
+fn fuzz<T: FieldElement>(
+    file: &str,
+    options: &CompileOptions,
+    config: &compiler::FuzzConfig,
+) -> bool {
+    match compiler::fuzz::<T>(Path::new(file), options, config) {
+        Ok(None) => {
+            println!("All {} cases passed.", config.runs);
+            true
+        }
+        Ok(Some(failure)) => {
+            println!("Case {} failed, shrunk to the answers:", failure.run);
+            for (query, answer) in &failure.answers {
+                println!("    {query} => {answer}");
+            }
+            println!("{}", failure.message);
+            false
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            false
+        }
+    }
+}
+
+/// Reads the project configuration from `path`, or from powdr.toml in the
+/// current directory if there is no path but the configuration is `required`.
+fn project_config(path: Option<&str>, required: bool) -> Option<ProjectConfig> {
+    let path = match path {
+        Some(path) => Path::new(path),
+        None if required => Path::new(compiler::PROJECT_FILE),
+        None => return None,
+    };
+    match ProjectConfig::read(path) {
+        Ok(config) => Some(config),
+        Err(err) => {
+            log::error!("{err}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Runs the whole pipeline from the assembly or PIL file to the proof.
+/// The inputs on the command line take precedence over the inputs of the
+/// project, whose degree is checked against the degree of the file.
+/// @returns false if a step failed.
+#[allow(clippy::too_many_arguments)]
+fn prove<T: FieldElement>(
+    file: &str,
+    options: &CompileOptions,
+    backend: BackendType,
+    inputs: &str,
+    entry_point: u64,
+    project: Option<&ProjectConfig>,
+    output: &OutputLayout,
+    force: bool,
+) -> Result<bool, String> {
+    let inputs = match project {
+        Some(project) if inputs.trim().is_empty() => project.inputs::<T>()?,
+        _ => split_inputs::<T>(inputs),
+    };
+    if let Some(degree) = project.and_then(|project| project.degree) {
+        let actual = compiler::stats::<T>(Path::new(file), options)?
+            .total()
+            .degree;
+        if actual != degree {
+            return Err(format!(
+                "{file} has degree {actual}, but the project declares degree {degree}."
+            ));
+        }
+    }
+    Ok(if file.ends_with(".asm") {
+        compiler::compile_asm(
+            file,
+            options,
+            inputs,
+            entry_point,
+            output,
+            force,
+            Some(backend),
+        )
+    } else {
+        compiler::compile_pil(
+            Path::new(file),
+            options,
+            output,
+            Some(compiler::inputs_query_callback(inputs, entry_point)),
+            Some(backend),
+        )
+    })
 }
 
 fn main() {
-    let mut builder = Builder::new();
-    builder
-        .filter_level(LevelFilter::Info)
-        .parse_default_env()
-        .target(Target::Stdout)
-        .format(|buf, record| writeln!(buf, "{}", record.args()))
-        .init();
-
-    let command = Cli::parse().command;
-    match command {
+    let cli = Cli::parse();
+    let flamegraph = match &cli.command {
+        Commands::Bench { flamegraph, .. } => flamegraph.as_deref().map(Path::new),
+        _ => None,
+    };
+    let _flamegraph_guard = logging::init(cli.log_format, flamegraph);
+    // Failures of witness generation that are reported as errors are not printed.
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if !compiler::is_catching_panic() {
+            default_hook(info)
+        }
+    }));
+    set_display_format(cli.number_format);
+    parser::set_literal_overflow_policy(cli.literal_overflow);
+    compiler::set_reproducible(cli.reproducible);
+    let options = CompileOptions {
+        defines: cli.defines.into_iter().collect(),
+        optimize: cli.optimize,
+        compress_rom: cli.compress_rom,
+    };
+    match cli
+        .threads
+        .map(Ok)
+        .or_else(|| ThreadConfig::from_env().transpose())
+    {
+        Some(Ok(threads)) => compiler::set_threads(threads),
+        Some(Err(err)) => {
+            log::error!("{err}");
+            std::process::exit(1);
+        }
+        None => {}
+    }
+    let field = cli.field;
+    match cli.command {
         Commands::Rust {
+            file,
+            inputs,
+            package,
+            features,
+            no_default_features,
+            cache_dir,
+            output_directory,
+            force,
+        } => {
+            if Path::new(&file).extension() == Some("rs".as_ref()) {
+                with_field!(field_modulus(field, None), T => riscv::compile_rust(
+                    &file,
+                    &options,
+                    split_inputs::<T>(&inputs),
+                    Path::new(&output_directory),
+                    force,
+                ));
+            } else {
+                let guest = riscv::GuestCrate {
+                    package,
+                    features: features
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|f| !f.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                    no_default_features,
+                    cache_dir: cache_dir.map(PathBuf::from),
+                    ..riscv::GuestCrate::new(&file)
+                };
+                with_field!(field_modulus(field, None), T => riscv::compile_guest(
+                    &guest,
+                    &options,
+                    split_inputs::<T>(&inputs),
+                    Path::new(&output_directory),
+                    force,
+                ));
+            }
+        }
+        Commands::C {
             file,
             inputs,
             output_directory,
             force,
         } => {
-            riscv::compile_rust(
+            with_field!(field_modulus(field, None), T => riscv::compile_c(
                 &file,
-                split_inputs(&inputs),
+                &options,
+                split_inputs::<T>(&inputs),
                 Path::new(&output_directory),
                 force,
-            );
+            ));
         }
         Commands::RiscvAsm {
             file,
@@ -138,43 +1060,669 @@ fn main() {
             output_directory,
             force,
         } => {
-            riscv::compile_riscv_asm(
+            with_field!(field_modulus(field, None), T => riscv::compile_riscv_asm(
                 &file,
                 &file,
-                split_inputs(&inputs),
+                &options,
+                split_inputs::<T>(&inputs),
                 Path::new(&output_directory),
                 force,
-            );
+            ));
         }
         Commands::Asm {
             file,
             inputs,
+            entry_point,
             output_directory,
             force,
+            prefix,
+            artifacts,
+            prove_with,
+            watch,
         } => {
-            compiler::compile_asm(
-                &file,
-                split_inputs(&inputs),
-                Path::new(&output_directory),
-                force,
-            );
+            let output = output_layout(&output_directory, prefix, artifacts);
+            let modulus = field_modulus(field, prove_with);
+            // In watch mode, later runs overwrite the files written by earlier ones.
+            let mut written = false;
+            let mut run = |_| {
+                let force = force || written;
+                written |= with_field!(modulus.clone(), T => compiler::compile_asm(
+                    &file,
+                    &options,
+                    split_inputs::<T>(&inputs),
+                    entry_point,
+                    &output,
+                    force,
+                    prove_with,
+                ));
+            };
+            if watch {
+                watch::watch(|| vec![PathBuf::from(&file)], run);
+            } else {
+                run(0);
+            }
+        }
+        Commands::Interpret {
+            file,
+            inputs,
+            entry_point,
+            max_steps,
+        } => {
+            let modulus = field_modulus(field, None);
+            if !with_field!(modulus, T => interpret::<T>(&file, &options, &inputs, entry_point, max_steps))
+            {
+                std::process::exit(1);
+            }
+        }
+        Commands::Prove {
+            file,
+            backend,
+            inputs,
+            entry_point,
+            output_directory,
+            config,
+            force,
+            prefix,
+            artifacts,
+        } => {
+            let project = project_config(config.as_deref(), file.is_none());
+            let project_path = |path: &PathBuf| path.to_string_lossy().to_string();
+            let file = file.or_else(|| project.as_ref()?.file.as_ref().map(project_path));
+            let backend = backend.or_else(|| project.as_ref()?.backend);
+            let (file, backend) = match (file, backend) {
+                (Some(file), Some(backend)) => (file, backend),
+                (None, _) => {
+                    log::error!("No input file given, neither on the command line nor in the project configuration.");
+                    std::process::exit(1);
+                }
+                (_, None) => {
+                    log::error!("No backend given, neither on the command line nor in the project configuration.");
+                    std::process::exit(1);
+                }
+            };
+            let field = field.or_else(|| project.as_ref()?.field);
+            let output_directory = output_directory
+                .or_else(|| {
+                    project
+                        .as_ref()?
+                        .output_directory
+                        .as_ref()
+                        .map(project_path)
+                })
+                .unwrap_or_else(|| ".".to_string());
+            let output = output_layout(&output_directory, prefix, artifacts);
+            let result = with_field!(field_modulus(field, Some(backend)), T => {
+                prove::<T>(&file, &options, backend, &inputs, entry_point, project.as_ref(), &output, force)
+            });
+            match result {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(1),
+                Err(err) => {
+                    log::error!("{err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Check { file, json, watch } => {
+            let modulus = field_modulus(field, None);
+            let check = || with_field!(modulus.clone(), T => check::<T>(&file, json));
+            if watch {
+                watch::watch(
+                    || compiler::included_files::<GoldilocksField>(Path::new(&file)),
+                    |_| {
+                        check();
+                    },
+                );
+            } else if !check() {
+                std::process::exit(1);
+            }
+        }
+        Commands::Test { file, filter } => {
+            if !with_field!(field_modulus(field, None), T => run_tests::<T>(&file, &options, &filter))
+            {
+                std::process::exit(1);
+            }
+        }
+        Commands::Fuzz { file, runs, seed } => {
+            let config = compiler::FuzzConfig {
+                runs,
+                seed,
+                ..Default::default()
+            };
+            if !with_field!(field_modulus(field, None), T => fuzz::<T>(&file, &options, &config)) {
+                std::process::exit(1);
+            }
+        }
+        Commands::Differential {
+            file,
+            inputs,
+            mutations,
+            seed,
+        } => {
+            let report = with_field!(field_modulus(field, None), T => {
+                compiler::differential_test::<T>(
+                    Path::new(&file),
+                    &options,
+                    split_inputs::<T>(&inputs),
+                    mutations,
+                    seed,
+                )
+            });
+            match report {
+                Ok(report) => {
+                    if let Some(err) = &report.pilcom_error {
+                        log::warn!("Pilcom could not compile {file}, the JSON export is not compared:\n{err}");
+                    }
+                    for divergence in &report.divergences {
+                        println!("{divergence}");
+                    }
+                    if !report.divergences.is_empty() {
+                        std::process::exit(1);
+                    }
+                    log::info!("No divergences from pilcom.");
+                }
+                Err(err) => {
+                    log::error!("{err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Repl { file } => {
+            let result = with_field!(field_modulus(field, None), T => compiler::repl::run::<T>(
+                Path::new(&file),
+                std::io::stdin().lock(),
+                &mut std::io::stdout(),
+            ));
+            if let Err(err) = result {
+                log::error!("{err}");
+                std::process::exit(1);
+            }
+        }
+        Commands::InspectWitness {
+            file,
+            dir,
+            columns,
+            rows,
+            identity,
+        } => {
+            let columns = columns
+                .split(',')
+                .map(|c| c.trim().to_string())
+                .filter(|c| !c.is_empty())
+                .collect::<Vec<_>>();
+            let result = rows
+                .map(|rows| compiler::parse_rows(&rows))
+                .transpose()
+                .and_then(|rows| {
+                    with_field!(field_modulus(field, None), T => compiler::inspect_witness::<T>(
+                        Path::new(&file),
+                        Path::new(&dir),
+                        &columns,
+                        rows,
+                        identity.as_deref(),
+                    ))
+                });
+            match result {
+                Ok(table) => print!("{table}"),
+                Err(err) => {
+                    log::error!("{err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Snapshot {
+            file,
+            snapshot,
+            dir,
+            update,
+        } => {
+            let result = with_field!(field_modulus(field, None), T => {
+                compiler::snapshot_witness::<T>(
+                    Path::new(&file),
+                    Path::new(&dir),
+                    Path::new(&snapshot),
+                    update,
+                )
+            });
+            match result {
+                Ok(differences) if differences.is_empty() => {}
+                Ok(differences) => {
+                    for difference in differences {
+                        println!("{difference}");
+                    }
+                    std::process::exit(1);
+                }
+                Err(err) => {
+                    log::error!("{err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::TraceHtml {
+            file,
+            dir,
+            rows,
+            output,
+        } => {
+            let result = rows
+                .map(|rows| compiler::parse_rows(&rows))
+                .transpose()
+                .and_then(|rows| {
+                    with_field!(field_modulus(field, None), T => compiler::export_trace_html::<T>(
+                        Path::new(&file),
+                        Path::new(&dir),
+                        rows,
+                    ))
+                })
+                .and_then(|html| {
+                    fs::write(&output, html).map_err(|e| format!("Could not write {output}: {e}"))
+                });
+            match result {
+                Ok(()) => log::info!("Wrote {output}."),
+                Err(err) => {
+                    log::error!("{err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Bench {
+            file,
+            inputs,
+            iterations,
+            backend,
+            flamegraph,
+            reset_peak_memory,
+        } => {
+            let result = with_field!(field_modulus(field, backend), T => compiler::bench(
+                Path::new(&file),
+                &options,
+                split_inputs::<T>(&inputs),
+                iterations,
+                backend,
+                reset_peak_memory,
+            ));
+            match result {
+                Ok(report) => {
+                    print!("{report}");
+                    if let Some(flamegraph) = flamegraph {
+                        log::info!("Wrote the folded stacks to {flamegraph}.");
+                    }
+                }
+                Err(err) => {
+                    log::error!("{err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Fmt { files, check } => {
+            let modulus = field_modulus(field, None);
+            let mut success = true;
+            for file in files {
+                let contents = fs::read_to_string(&file).unwrap();
+                let formatted = with_field!(modulus.clone(), T => if file.ends_with(".asm") {
+                    parser::format::format_asm::<T>(Some(&file), &contents)
+                } else {
+                    parser::format::format_pil::<T>(Some(&file), &contents)
+                });
+                match formatted {
+                    Ok(formatted) if formatted == contents => {}
+                    Ok(_) if check => {
+                        log::error!("{file} is not formatted.");
+                        success = false;
+                    }
+                    Ok(formatted) => {
+                        fs::write(&file, formatted).unwrap();
+                        log::info!("Formatted {file}.");
+                    }
+                    Err(err) => {
+                        err.output_to_stderr();
+                        success = false;
+                    }
+                }
+            }
+            if !success {
+                std::process::exit(1);
+            }
         }
         Commands::Reformat { file } => {
             let contents = fs::read_to_string(&file).unwrap();
-            match parser::parse(Some(&file), &contents) {
-                Ok(ast) => println!("{ast}"),
-                Err(err) => err.output_to_stderr(),
-            }
+            with_field!(field_modulus(field, None), T => {
+                match parser::parse::<T>(Some(&file), &contents) {
+                    Ok(ast) => println!("{ast}"),
+                    Err(err) => err.output_to_stderr(),
+                }
+            });
         }
-        Commands::Compile {
+        Commands::ExportR1cs {
             file,
             output_directory,
         } => {
-            compiler::compile_pil(
+            // R1CS files are read by circom-based tools, which work in BN254.
+            let modulus = field_modulus(field.or(Some(FieldType::Bn254)), None);
+            if !with_field!(modulus, T => compiler::export_r1cs(
                 Path::new(&file),
                 Path::new(&output_directory),
-                no_callback(),
-            );
+                no_callback::<T>(),
+            )) {
+                std::process::exit(1);
+            }
+        }
+        Commands::Dot { file, output } => {
+            let result = with_field!(field_modulus(field, None), T => {
+                compiler::export_dot::<T>(Path::new(&file), &options)
+            })
+            .and_then(|dot| match &output {
+                Some(output) => fs::write(output, dot)
+                    .map(|_| log::info!("Wrote {output}."))
+                    .map_err(|e| format!("Could not write {output}: {e}")),
+                None => {
+                    print!("{dot}");
+                    Ok(())
+                }
+            });
+            if let Err(err) = result {
+                log::error!("{err}");
+                std::process::exit(1);
+            }
+        }
+        Commands::ExportSmt {
+            file,
+            namespace,
+            rows,
+            output,
+        } => {
+            let result = with_field!(field_modulus(field, None), T => {
+                compiler::export_smt::<T>(Path::new(&file), &options, namespace.as_deref(), rows)
+            })
+            .and_then(|smt| match &output {
+                Some(output) => fs::write(output, smt)
+                    .map(|_| log::info!("Wrote {output}."))
+                    .map_err(|e| format!("Could not write {output}: {e}")),
+                None => {
+                    print!("{smt}");
+                    Ok(())
+                }
+            });
+            if let Err(err) = result {
+                log::error!("{err}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Coverage { file, dir } => {
+            let report = with_field!(field_modulus(field, None), T => {
+                compiler::coverage::<T>(Path::new(&file), Path::new(&dir)).map(|c| c.to_string())
+            });
+            match report {
+                Ok(report) => println!("{report}"),
+                Err(err) => {
+                    log::error!("{err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Underconstrained { file, inputs } => {
+            let columns = with_field!(field_modulus(field, None), T => {
+                compiler::underconstrained_columns::<T>(Path::new(&file), &options, split_inputs::<T>(&inputs))
+            });
+            match columns {
+                Ok(columns) => {
+                    for column in &columns {
+                        for reason in &column.reasons {
+                            println!("{}: {reason}", column.name);
+                        }
+                    }
+                    if !columns.is_empty() {
+                        std::process::exit(1);
+                    }
+                    log::info!("No under-constrained columns found.");
+                }
+                Err(err) => {
+                    log::error!("{err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Audit {
+            file,
+            inputs,
+            output,
+            cells,
+        } => {
+            let explanations = with_field!(field_modulus(field, None), T => {
+                compiler::audit_witness::<T>(
+                    Path::new(&file),
+                    &options,
+                    split_inputs::<T>(&inputs),
+                    output.as_deref().map(Path::new),
+                    &cells,
+                )
+            });
+            match explanations {
+                Ok(explanations) => {
+                    for explanation in explanations {
+                        println!("{explanation}");
+                    }
+                }
+                Err(err) => {
+                    log::error!("{err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Record {
+            file,
+            inputs,
+            replay,
+        } => {
+            let result = with_field!(field_modulus(field, None), T => {
+                compiler::record_queries::<T>(
+                    Path::new(&file),
+                    &options,
+                    split_inputs::<T>(&inputs),
+                    Path::new(&replay),
+                )
+            });
+            if let Err(err) = result {
+                log::error!("{err}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Replay {
+            file,
+            replay,
+            output_directory,
+        } => {
+            let result = with_field!(field_modulus(field, None), T => {
+                compiler::replay_queries::<T>(
+                    Path::new(&file),
+                    &options,
+                    Path::new(&replay),
+                    output_directory.as_deref().map(Path::new),
+                )
+            });
+            if let Err(err) = result {
+                log::error!("{err}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Mutate { file, witnesses } => {
+            let dirs = witnesses.iter().map(PathBuf::from).collect::<Vec<_>>();
+            let report = with_field!(field_modulus(field, None), T => {
+                compiler::mutation_test::<T>(Path::new(&file), &options, &dirs)
+            });
+            match report {
+                Ok(report) => {
+                    println!("{report}");
+                    if report.survived().next().is_some() {
+                        std::process::exit(1);
+                    }
+                }
+                Err(err) => {
+                    log::error!("{err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Determinism {
+            file,
+            inputs,
+            thread_counts,
+        } => {
+            let threads = match thread_counts
+                .split(',')
+                .map(|t| t.trim().parse::<usize>().ok().filter(|t| *t > 0))
+                .collect::<Option<Vec<_>>>()
+            {
+                Some(threads) => threads,
+                None => {
+                    log::error!("Invalid list of thread counts: {thread_counts}");
+                    std::process::exit(1);
+                }
+            };
+            let differences = with_field!(field_modulus(field, None), T => {
+                compiler::check_determinism::<T>(Path::new(&file), &options, split_inputs::<T>(&inputs), &threads)
+            });
+            match differences {
+                Ok(differences) if differences.is_empty() => {
+                    log::info!("All {} runs generated the same columns.", threads.len());
+                }
+                Ok(differences) => {
+                    for difference in differences {
+                        println!("{difference}");
+                    }
+                    std::process::exit(1);
+                }
+                Err(err) => {
+                    log::error!("{err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Stats {
+            file,
+            memory,
+            inputs,
+        } => {
+            let result = with_field!(field_modulus(field, None), T => {
+                compiler::stats::<T>(Path::new(&file), &options).and_then(|stats| {
+                    let report = memory
+                        .then(|| {
+                            compiler::memory_report::<T>(
+                                Path::new(&file),
+                                &options,
+                                split_inputs::<T>(&inputs),
+                            )
+                        })
+                        .transpose()?;
+                    Ok((stats, report))
+                })
+            });
+            match result {
+                Ok((stats, report)) => {
+                    print!("{stats}");
+                    if let Some(report) = report {
+                        print!("{report}");
+                    }
+                }
+                Err(err) => {
+                    log::error!("{err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Schema { document, dir } => {
+            let result = match (document, dir) {
+                (Some(document), _) => {
+                    compiler::json_schema(&document).map(|schema| println!("{}", schema.pretty(2)))
+                }
+                (None, Some(dir)) => {
+                    compiler::JSON_SCHEMA_DOCUMENTS
+                        .iter()
+                        .try_for_each(|document| {
+                            let path = Path::new(&dir).join(format!("{document}.schema.json"));
+                            fs::write(&path, compiler::json_schema(document)?.pretty(2))
+                                .map(|_| log::info!("Wrote {}.", path.display()))
+                                .map_err(|e| format!("Could not write {}: {e}", path.display()))
+                        })
+                }
+                (None, None) => unreachable!(),
+            };
+            if let Err(err) = result {
+                log::error!("{err}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Completions {
+            shell,
+            man,
+            bin_name,
+        } => {
+            let cmd = Cli::command();
+            let bin_name = bin_name.unwrap_or_else(|| cmd.get_name().to_string());
+            match (shell, man) {
+                (Some(shell), _) => print!("{}", completions::completions(cmd, &bin_name, shell)),
+                (None, Some(dir)) => {
+                    for (file, page) in completions::man_pages(cmd, &bin_name) {
+                        let path = Path::new(&dir).join(file);
+                        if let Err(err) = fs::write(&path, page) {
+                            log::error!("Could not write {}: {err}", path.display());
+                            std::process::exit(1);
+                        }
+                        log::info!("Wrote {}.", path.display());
+                    }
+                }
+                (None, None) => unreachable!(),
+            }
+        }
+        Commands::Verify {
+            file,
+            proof,
+            setup,
+            publics,
+        } => {
+            let artifact = fs::read_to_string(&proof)
+                .map_err(|e| format!("Could not read {proof}: {e}"))
+                .and_then(|contents| ProofArtifact::from_json(&contents));
+            let result = artifact.and_then(|artifact| {
+                let file = Path::new(&file);
+                let setup = setup.as_ref().map(Path::new);
+                let publics = publics.as_ref().map(Path::new);
+                if let Some(field) = field.filter(|field| field.modulus() != artifact.field) {
+                    return Err(format!("The proof was not created in the field {field}."));
+                }
+                with_field!(artifact.field.clone(), T => {
+                    compiler::verify_proof::<T>(file, &artifact, setup, publics).map(|_| ())
+                })
+            });
+            match result {
+                Ok(()) => log::info!("Proof accepted."),
+                Err(err) => {
+                    log::error!("{err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Compile {
+            file,
+            output_directory,
+            prefix,
+            artifacts,
+            prove_with,
+            watch,
+        } => {
+            let file = Path::new(&file);
+            let output = output_layout(&output_directory, prefix, artifacts);
+            let modulus = field_modulus(field, prove_with);
+            let run = |_| {
+                with_field!(modulus.clone(), T => {
+                    compiler::compile_pil(file, &options, &output, no_callback::<T>(), prove_with);
+                });
+            };
+            if watch {
+                watch::watch(|| compiler::included_files::<GoldilocksField>(file), run);
+            } else {
+                run(0);
+            }
         }
     }
 }
@@ -0,0 +1,60 @@
+//! Re-runs a command whenever one of its input files changes.
+
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Runs `run` once and then again every time one of the files returned by
+/// `files` is modified, forever. The files are determined again after every
+/// run, so that new includes are watched as well.
+/// Errors (panics) in `run` are reported and do not stop the watching.
+pub fn watch(files: impl Fn() -> Vec<PathBuf>, mut run: impl FnMut(usize)) -> ! {
+    let mut previous: Option<Duration> = None;
+    for iteration in 0.. {
+        let watched = files();
+        let modified = modification_times(&watched);
+
+        let start = Instant::now();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| run(iteration)));
+        let duration = start.elapsed();
+        let outcome = if result.is_ok() { "Finished" } else { "Failed" };
+        match previous {
+            Some(previous) => log::info!(
+                "{outcome} in {:.3}s ({:+.3}s compared to the previous run).",
+                duration.as_secs_f64(),
+                duration.as_secs_f64() - previous.as_secs_f64()
+            ),
+            None => log::info!("{outcome} in {:.3}s.", duration.as_secs_f64()),
+        }
+        previous = Some(duration);
+
+        log::info!("Watching {} files for changes...", watched.len());
+        let changed = loop {
+            thread::sleep(POLL_INTERVAL);
+            let current = modification_times(&watched);
+            if current != modified {
+                break watched
+                    .iter()
+                    .zip(current.iter().zip(&modified))
+                    .filter(|(_, (current, modified))| current != modified)
+                    .map(|(file, _)| file.display().to_string())
+                    .collect::<Vec<_>>();
+            }
+        };
+        log::info!("Changed: {}", changed.join(", "));
+        // Editors often write files in several steps, wait for them to finish.
+        thread::sleep(POLL_INTERVAL);
+    }
+    unreachable!()
+}
+
+fn modification_times(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| fs::metadata(file).and_then(|m| m.modified()).ok())
+        .collect()
+}
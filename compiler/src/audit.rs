@@ -0,0 +1,115 @@
+//! Answers "why does this cell have this value?" with the audit log of
+//! witness generation, see `executor::witgen::AuditLog`.
+
+use std::path::Path;
+
+use executor::constant_evaluator;
+use number::{DegreeType, FieldElement};
+use pil_analyzer::Symbol;
+
+use crate::panics::catch_panic;
+use crate::{analyze_file, inputs_query_callback, write_artifact, CompileOptions};
+
+/// Generates the witness of an assembly or PIL file for the inputs and
+/// records which identity, query or machine determined each cell. Writes the
+/// log to `output` if given, see `AuditLog::write`.
+/// @returns for each of the `cells`, which are given like `Main.x@7`, its
+/// value, its position in the order of deduction and what determined it.
+pub fn audit_witness<T: FieldElement>(
+    file: &Path,
+    options: &CompileOptions,
+    inputs: Vec<T>,
+    output: Option<&Path>,
+    cells: &[String],
+) -> Result<Vec<String>, String> {
+    let cells = cells
+        .iter()
+        .map(|cell| parse_cell(cell))
+        .collect::<Result<Vec<_>, _>>()?;
+    let analyzed = analyze_file::<T>(file, options)?;
+    let (fixed, degree) = constant_evaluator::generate(&analyzed)?;
+    let (_, log) = catch_panic(|| {
+        Ok(executor::witgen::generate_with_audit(
+            &analyzed,
+            degree,
+            &fixed,
+            Some(inputs_query_callback(inputs, 0)),
+        ))
+    })
+    .map_err(|e| format!("Witness generation failed: {e}"))?;
+
+    if let Some(output) = output {
+        let mut bytes = vec![];
+        log.write(&mut bytes)
+            .and_then(|_| write_artifact(output, &bytes))
+            .map_err(|e| format!("Could not write {}: {e}", output.display()))?;
+        log::info!("Wrote {}.", output.display());
+    }
+
+    cells
+        .into_iter()
+        .map(|(column, row)| {
+            if !Symbol::lookup(column).is_some_and(|name| analyzed.definitions.contains_key(&name))
+            {
+                return Err(format!("Unknown column {column}."));
+            }
+            let position = log
+                .deductions
+                .iter()
+                .rposition(|d| d.column == column && d.row == row)
+                .ok_or_else(|| {
+                    format!("No deduction for {column}@{row}, the degree is {degree}.")
+                })?;
+            let deduction = &log.deductions[position];
+            Ok(format!(
+                "{column}@{row} = {} (deduction {position} of {}): {}",
+                deduction.value,
+                log.deductions.len(),
+                log.format_cause(&deduction.cause)
+            ))
+        })
+        .collect()
+}
+
+/// Parses a cell like `Main.x@7` into the column and the row.
+fn parse_cell(cell: &str) -> Result<(&str, DegreeType), String> {
+    cell.rsplit_once('@')
+        .and_then(|(column, row)| Some((column, row.parse().ok()?)))
+        .ok_or_else(|| format!("Invalid cell {cell}, expected a column and a row like Main.x@7."))
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+
+    use super::*;
+
+    #[test]
+    fn fibonacci() {
+        let explanations = audit_witness::<GoldilocksField>(
+            Path::new("../test_data/pil/fibonacci.pil"),
+            &Default::default(),
+            vec![],
+            None,
+            &["Fibonacci.y@0".to_string(), "Fibonacci.y@3".to_string()],
+        )
+        .unwrap();
+        assert_eq!(
+            explanations,
+            [
+                "Fibonacci.y@0 = 1 (deduction 0 of 32): identity fibonacci.pil:12: \
+                 (Fibonacci.ISLAST * (Fibonacci.y' - 1)) = 0;",
+                "Fibonacci.y@3 = 5 (deduction 7 of 32): identity fibonacci.pil:16: \
+                 ((1 - Fibonacci.ISLAST) * (Fibonacci.y' - (Fibonacci.x + Fibonacci.y))) = 0;"
+            ]
+        );
+        assert!(audit_witness::<GoldilocksField>(
+            Path::new("../test_data/pil/fibonacci.pil"),
+            &Default::default(),
+            vec![],
+            None,
+            &["Fibonacci.y".to_string()],
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,287 @@
+//! Runs the phases of the pipeline several times and measures them.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use backend::{lower_identities, Backend, MockBackend};
+use executor::constant_evaluator;
+use executor::witgen::peak_resident_memory;
+use halo2::Halo2Backend;
+use number::{DegreeType, FieldElement};
+use pil_analyzer::Analyzed;
+
+use crate::threads::{in_phase, Phase};
+use crate::{inputs_query_callback, BackendType, CompileOptions};
+
+/// The measurements of one phase over all iterations.
+pub struct PhaseMeasurement {
+    pub name: &'static str,
+    pub durations: Vec<Duration>,
+    /// The peak resident memory of the process during the phase in bytes,
+    /// if the platform reports it. Without resetting the peak before each
+    /// phase, this is the peak of the process up to the end of the phase.
+    pub peak_memory: Option<u64>,
+}
+
+impl PhaseMeasurement {
+    pub fn min(&self) -> Duration {
+        self.durations.iter().copied().min().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> Duration {
+        self.durations.iter().copied().max().unwrap_or_default()
+    }
+
+    pub fn mean(&self) -> Duration {
+        self.durations.iter().sum::<Duration>() / self.durations.len().max(1) as u32
+    }
+}
+
+pub struct BenchReport {
+    pub phases: Vec<PhaseMeasurement>,
+    /// The number of rows of the trace.
+    pub degree: DegreeType,
+}
+
+impl BenchReport {
+    pub fn phase(&self, name: &str) -> Option<&PhaseMeasurement> {
+        self.phases.iter().find(|phase| phase.name == name)
+    }
+
+    /// The number of rows witness generation creates per second, on average.
+    pub fn witgen_rows_per_second(&self) -> Option<f64> {
+        self.phase("witgen")
+            .map(|witgen| self.degree as f64 / witgen.mean().as_secs_f64())
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let iterations = self.phases.first().map(|p| p.durations.len());
+        writeln!(
+            f,
+            "{} rows, {} iterations",
+            self.degree,
+            iterations.unwrap_or_default()
+        )?;
+        writeln!(
+            f,
+            "{:<10}{:>12}{:>12}{:>12}{:>14}",
+            "phase", "min", "mean", "max", "peak memory"
+        )?;
+        for phase in &self.phases {
+            let seconds = |d: Duration| format!("{:.4}s", d.as_secs_f64());
+            let memory = phase
+                .peak_memory
+                .map(|bytes| format!("{:.1} MiB", bytes as f64 / (1 << 20) as f64))
+                .unwrap_or_else(|| "n/a".to_string());
+            writeln!(
+                f,
+                "{:<10}{:>12}{:>12}{:>12}{:>14}",
+                phase.name,
+                seconds(phase.min()),
+                seconds(phase.mean()),
+                seconds(phase.max()),
+                memory
+            )?;
+        }
+        if let Some(rows_per_second) = self.witgen_rows_per_second() {
+            writeln!(f, "witgen: {rows_per_second:.0} rows per second")?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs the phase in a tracing span named after it, see `Measurements::measure`.
+macro_rules! measure {
+    ($measurements:expr, $name:literal, $phase:expr) => {
+        $measurements.measure($name, tracing::info_span!($name), $phase)
+    };
+}
+
+/// Runs the pipeline from the assembly or PIL file to the witness (and the
+/// proof, if a backend is given) `iterations` times and measures every phase.
+/// Every phase runs in a tracing span of the same name, with the configured
+/// number of threads, see `set_threads`.
+/// The eStark backend is not supported.
+/// The peak memory of a phase is the peak of the process up to the end of
+/// the phase, unless `reset_peak_memory` is set, see `PhaseMeasurement`.
+pub fn bench<T: FieldElement>(
+    file: &Path,
+    options: &CompileOptions,
+    inputs: Vec<T>,
+    iterations: usize,
+    backend: Option<BackendType>,
+    reset_peak_memory: bool,
+) -> Result<BenchReport, String> {
+    if let Some(modulus) = backend.and_then(|backend| backend.required_modulus()) {
+        if T::modulus() != modulus {
+            return Err(format!(
+                "The {} backend needs the field with modulus {modulus}.",
+                backend.unwrap()
+            ));
+        }
+    }
+    if backend == Some(BackendType::EStark) {
+        return Err("eStark proofs are created by pil-stark and cannot be benchmarked.".into());
+    }
+    let file_name = file.to_str().unwrap();
+    let contents =
+        fs::read_to_string(file).map_err(|e| format!("Could not read {file_name}: {e}"))?;
+
+    let mut measurements = Measurements {
+        phases: vec![],
+        reset_peak_memory,
+    };
+    let mut degree = 0;
+    for _ in 0..iterations {
+        let analyzed = if file_name.ends_with(".asm") {
+            let pil = measure!(measurements, "parse", || {
+                pilgen::compile::<T>(Some(file_name), &contents, options)
+                    .map(|pil| pil.to_string())
+                    .map_err(|e| e.message().to_string())
+            })?;
+            measure!(measurements, "analyze", || {
+                pil_analyzer::try_analyze_string::<T>(&pil).map_err(|e| e.to_string())
+            })?
+        } else {
+            measure!(measurements, "analyze", || {
+                pil_analyzer::try_analyze_with_defines::<T>(file, options.defines.clone())
+                    .map_err(|e| e.to_string())
+            })?
+        };
+        let (fixed, fixed_degree) = measure!(measurements, "fixed", || {
+            in_phase(Phase::Fixed, || constant_evaluator::generate(&analyzed))
+        })?;
+        degree = fixed_degree;
+        let witness = measure!(measurements, "witgen", || {
+            in_phase(Phase::Witgen, || {
+                executor::witgen::generate(
+                    &analyzed,
+                    degree,
+                    &fixed,
+                    Some(inputs_query_callback(inputs.clone(), 0)),
+                )
+            })
+        });
+        match backend {
+            Some(BackendType::Halo2) => measure!(measurements, "backend", || {
+                in_phase(Phase::Backend, || {
+                    prove(&Halo2Backend, &analyzed, &fixed, &witness)
+                })
+            })?,
+            Some(BackendType::Mock) => measure!(measurements, "backend", || {
+                in_phase(Phase::Backend, || {
+                    prove(&MockBackend, &analyzed, &fixed, &witness)
+                })
+            })?,
+            Some(BackendType::EStark) | None => {}
+        }
+    }
+    Ok(BenchReport {
+        phases: measurements.phases,
+        degree,
+    })
+}
+
+fn prove<T: FieldElement, B: Backend<T>>(
+    backend: &B,
+    analyzed: &Analyzed<T>,
+    fixed: &[(&str, Vec<T>)],
+    witness: &[(&str, Vec<T>)],
+) -> Result<(), String> {
+    let lowered = lower_identities(analyzed, backend.capabilities())?;
+    let (analyzed, witness) = match &lowered {
+        Some(lowered) => (
+            &lowered.analyzed,
+            Cow::Owned(lowered.witness(analyzed, fixed, witness)),
+        ),
+        None => (analyzed, Cow::Borrowed(witness)),
+    };
+    let setup = backend
+        .setup(analyzed, fixed)
+        .map_err(|e| format!("Setup failed: {e}"))?;
+    backend
+        .prove(&setup, analyzed, fixed, &witness)
+        .map_err(|e| format!("Proving failed: {e}"))?;
+    Ok(())
+}
+
+struct Measurements {
+    phases: Vec<PhaseMeasurement>,
+    /// Whether to reset the peak resident memory before each phase, see
+    /// `reset_peak_resident_memory`.
+    reset_peak_memory: bool,
+}
+
+impl Measurements {
+    /// Runs the phase in the span and adds its duration and memory
+    /// to the measurements of the phase.
+    fn measure<R>(
+        &mut self,
+        name: &'static str,
+        span: tracing::Span,
+        phase: impl FnOnce() -> R,
+    ) -> R {
+        if self.reset_peak_memory {
+            reset_peak_resident_memory();
+        }
+        let start = Instant::now();
+        let result = span.in_scope(phase);
+        let duration = start.elapsed();
+        let peak_memory = peak_resident_memory();
+
+        let index = match self.phases.iter().position(|p| p.name == name) {
+            Some(index) => index,
+            None => {
+                self.phases.push(PhaseMeasurement {
+                    name,
+                    durations: vec![],
+                    peak_memory: None,
+                });
+                self.phases.len() - 1
+            }
+        };
+        let measurement = &mut self.phases[index];
+        measurement.durations.push(duration);
+        measurement.peak_memory = measurement.peak_memory.max(peak_memory);
+        result
+    }
+}
+
+/// Resets the peak resident memory reported by Linux, if possible, by
+/// writing to `/proc/self/clear_refs`. This also clears the referenced and
+/// soft-dirty bits of the pages of the process, which other tools might use.
+fn reset_peak_resident_memory() {
+    let _ = fs::write("/proc/self/clear_refs", "5");
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+
+    use super::*;
+
+    #[test]
+    fn phases() {
+        let report = bench::<GoldilocksField>(
+            Path::new("../test_data/asm/simple_sum.asm"),
+            &Default::default(),
+            [16, 4, 1, 2, 8, 5].map(GoldilocksField::from).to_vec(),
+            2,
+            Some(BackendType::Mock),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            report.phases.iter().map(|p| p.name).collect::<Vec<_>>(),
+            ["parse", "analyze", "fixed", "witgen", "backend"]
+        );
+        assert!(report.phases.iter().all(|p| p.durations.len() == 2));
+        assert!(report.witgen_rows_per_second().unwrap() > 0.0);
+        assert!(report.to_string().contains("\nwitgen  "));
+    }
+}
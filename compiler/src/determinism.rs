@@ -0,0 +1,56 @@
+//! Checks that the fixed columns and the witness do not depend on the number
+//! of threads or on the iteration order of hash maps, which would make cached
+//! constants and proofs unusable.
+
+use std::path::Path;
+
+use executor::constant_evaluator;
+use number::FieldElement;
+
+use crate::panics::catch_panic;
+use crate::snapshot::WitnessSnapshot;
+use crate::threads::on_threads;
+use crate::{analyze_file, inputs_query_callback, CompileOptions};
+
+/// Generates the fixed columns and the witness of an assembly or PIL file
+/// once for every entry of `threads`, on a thread pool of that size, and
+/// compares the columns of every run with those of the first run. Every run
+/// creates new hash maps with new random keys, so different iteration orders
+/// are also tried with the same number of threads.
+/// @returns the differences from the first run, see `WitnessSnapshot::diff`.
+pub fn check_determinism<T: FieldElement>(
+    file: &Path,
+    options: &CompileOptions,
+    inputs: Vec<T>,
+    threads: &[usize],
+) -> Result<Vec<String>, String> {
+    let analyzed = analyze_file::<T>(file, options)?;
+    let mut first: Option<(usize, WitnessSnapshot<T>)> = None;
+    let mut differences = vec![];
+    for (run, &threads) in threads.iter().enumerate() {
+        let columns = catch_panic(|| {
+            on_threads(threads, || {
+                let (fixed, degree) = constant_evaluator::generate(&analyzed)?;
+                let witness = executor::witgen::generate(
+                    &analyzed,
+                    degree,
+                    &fixed,
+                    Some(inputs_query_callback(inputs.clone(), 0)),
+                );
+                Ok(WitnessSnapshot::new(&[fixed, witness].concat()))
+            })
+        })
+        .map_err(|e| format!("Run {run} on {threads} threads failed: {e}"))?;
+        match &first {
+            None => first = Some((threads, columns)),
+            Some((first_threads, expected)) => differences.extend(
+                expected.diff(&columns).into_iter().map(|difference| {
+                    format!(
+                        "Run {run} on {threads} threads (run 0 on {first_threads} threads): {difference}"
+                    )
+                }),
+            ),
+        }
+    }
+    Ok(differences)
+}
@@ -0,0 +1,147 @@
+//! Differential testing against pilcom: The same PIL file is compiled by
+//! powdr and by pilcom, and the identities are checked by the mock backend
+//! and by the pil verifier of pilcom on the same witnesses.
+
+use std::fs;
+use std::io::BufWriter;
+use std::path::Path;
+use std::process::Command;
+
+use executor::constant_evaluator;
+use number::random::seeded_rng;
+use number::FieldElement;
+use pil_analyzer::json_exporter;
+use rand::Rng;
+
+use crate::panics::catch_panic;
+use crate::verify::{pil_verifier, pilcom};
+use crate::{inputs_query_callback, write_polys_file, CompileOptions};
+
+pub struct DifferentialReport {
+    /// Why pilcom could not compile the PIL file, for example because the file
+    /// uses syntax only powdr supports. The JSON export is not compared then.
+    pub pilcom_error: Option<String>,
+    /// The differences between the JSON export of powdr and the output of
+    /// pilcom and the witnesses that only one of them accepts.
+    pub divergences: Vec<String>,
+}
+
+/// Compiles the PIL file with powdr and with pilcom (found through the PILCOM
+/// environment variable) and compares the JSON outputs. Then generates the
+/// fixed columns and the witness and checks the identities with the mock
+/// backend and with the pil verifier of pilcom (on the JSON export of powdr),
+/// first on the generated witness and then on `mutations` witnesses with one
+/// randomly changed cell each.
+/// Fails if pilcom is not found or the witness cannot be generated.
+pub fn differential_test<T: FieldElement>(
+    pil_file: &Path,
+    options: &CompileOptions,
+    inputs: Vec<T>,
+    mutations: usize,
+    seed: u64,
+) -> Result<DifferentialReport, String> {
+    let pilcom = pilcom()?;
+    let temp_dir = mktemp::Temp::new_dir().unwrap();
+    let mut divergences = vec![];
+
+    let analyzed = pil_analyzer::try_analyze_with_defines::<T>(pil_file, options.defines.clone())
+        .map_err(|e| e.to_string())?;
+    let json = json_exporter::export(&analyzed);
+    let pil_json = temp_dir.join("powdr.json");
+    json.write(&mut fs::File::create(&pil_json).unwrap())
+        .unwrap();
+
+    let pilcom_json = temp_dir.join("pilcom.json");
+    let pilcom_output = Command::new("node")
+        .args([
+            format!("{pilcom}/src/pil.js"),
+            pil_file.display().to_string(),
+            "-o".to_string(),
+            pilcom_json.display().to_string(),
+        ])
+        .output()
+        .map_err(|e| format!("Could not run pilcom: {e}"))?;
+    let pilcom_error = if pilcom_output.status.success() {
+        let contents = fs::read_to_string(&pilcom_json)
+            .map_err(|e| format!("Pilcom did not write {}: {e}", pilcom_json.display()))?;
+        let mut expected =
+            json::parse(&contents).map_err(|e| format!("Invalid json from pilcom: {e}"))?;
+        let mut json = json;
+        json_exporter::normalize(&mut json);
+        json_exporter::normalize(&mut expected);
+        divergences.extend(
+            json_exporter::differences(&json, &expected)
+                .into_iter()
+                .map(|difference| format!("JSON export (powdr and pilcom) at {difference}")),
+        );
+        None
+    } else {
+        Some(format!(
+            "{}{}",
+            String::from_utf8_lossy(&pilcom_output.stdout),
+            String::from_utf8_lossy(&pilcom_output.stderr)
+        ))
+    };
+
+    let (fixed, degree) = constant_evaluator::generate(&analyzed)?;
+    let witness = catch_panic(|| {
+        Ok(executor::witgen::generate(
+            &analyzed,
+            degree,
+            &fixed,
+            Some(inputs_query_callback(inputs, 0)),
+        ))
+    })
+    .map_err(|e| format!("Witness generation failed: {e}"))?;
+    let constants_file = temp_dir.join("constants.bin");
+    write_polys_file(
+        &mut BufWriter::new(fs::File::create(&constants_file).unwrap()),
+        degree,
+        &fixed,
+    );
+
+    let mut rng = seeded_rng(seed);
+    for mutation in 0..=mutations {
+        let mut witness = witness.clone();
+        let description = if mutation == 0 {
+            "the generated witness".to_string()
+        } else if witness.is_empty() || degree == 0 {
+            break;
+        } else {
+            let column = rng.gen_range(0..witness.len());
+            let (name, values) = &mut witness[column];
+            let row = rng.gen_range(0..values.len());
+            let offset = T::random(&mut rng);
+            values[row] += if offset.is_zero() { T::one() } else { offset };
+            format!(
+                "the witness with {name} changed to {} on row {row}",
+                values[row]
+            )
+        };
+        let commits_file = temp_dir.join("commits.bin");
+        write_polys_file(
+            &mut BufWriter::new(fs::File::create(&commits_file).unwrap()),
+            degree,
+            &witness,
+        );
+        let powdr_result = match backend::check(&analyzed, &fixed, &witness) {
+            Ok(failures) if failures.is_empty() => Ok(()),
+            Ok(failures) => Err(failures[0].to_string()),
+            Err(err) => return Err(err.to_string()),
+        };
+        let pilcom_result = pil_verifier(&pil_json, &constants_file, &commits_file);
+        match (powdr_result, pilcom_result) {
+            (Ok(()), Err(reason)) => divergences.push(format!(
+                "Only powdr accepts {description}, pilcom reports: {reason}"
+            )),
+            (Err(reason), Ok(())) => divergences.push(format!(
+                "Only pilcom accepts {description}, powdr reports: {reason}"
+            )),
+            _ => {}
+        }
+    }
+    Ok(DifferentialReport {
+        pilcom_error,
+        divergences,
+    })
+}
@@ -0,0 +1,171 @@
+//! Creates and verifies eSTARK proofs with pil-stark, which is found
+//! through the PILSTARK environment variable (path to the repository).
+
+use std::{path::Path, process::Command};
+
+use number::DegreeType;
+
+use crate::{set_timestamp, write_artifact, OutputLayout};
+
+/// Number of FRI queries, which gives about 128 bits of security
+/// for a blowup factor of two.
+const QUERIES: u32 = 128;
+
+/// Creates the stark structure and info, the constant tree and verification key,
+/// then the proof and the public values, and finally verifies the proof.
+/// All files are written to the output directory.
+pub fn prove(pil_json: &Path, degree: DegreeType, output: &OutputLayout) {
+    let pil_stark = std::env::var("PILSTARK").expect(
+        "Please set the PILSTARK environment variable to the path to the pil-stark repository.",
+    );
+    let file = |name: &str| output.path(name).to_string_lossy().to_string();
+    let pil = pil_json.to_string_lossy().to_string();
+
+    write_artifact(
+        &output.path("starkstruct.json"),
+        stark_struct(degree).as_bytes(),
+    )
+    .unwrap();
+    log::info!("Wrote {}.", file("starkstruct.json"));
+
+    let steps: [(&str, Vec<String>); 4] = [
+        (
+            "main_genstarkinfo.js",
+            vec![
+                "-p".into(),
+                pil.clone(),
+                "-s".into(),
+                file("starkstruct.json"),
+                "-i".into(),
+                file("starkinfo.json"),
+            ],
+        ),
+        (
+            "main_buildconsttree.js",
+            vec![
+                "-c".into(),
+                file("constants.bin"),
+                "-p".into(),
+                pil.clone(),
+                "-s".into(),
+                file("starkstruct.json"),
+                "-t".into(),
+                file("consttree.bin"),
+                "-v".into(),
+                file("verkey.json"),
+            ],
+        ),
+        (
+            "main_prover.js",
+            vec![
+                "-m".into(),
+                file("commits.bin"),
+                "-c".into(),
+                file("constants.bin"),
+                "-t".into(),
+                file("consttree.bin"),
+                "-p".into(),
+                pil.clone(),
+                "-s".into(),
+                file("starkinfo.json"),
+                "-o".into(),
+                file("proof.json"),
+                "-b".into(),
+                file("publics.json"),
+            ],
+        ),
+        (
+            "main_verifier.js",
+            vec![
+                "-p".into(),
+                pil,
+                "-s".into(),
+                file("starkinfo.json"),
+                "-v".into(),
+                file("verkey.json"),
+                "-o".into(),
+                file("proof.json"),
+                "-b".into(),
+                file("publics.json"),
+            ],
+        ),
+    ];
+    for (script, args) in steps {
+        run_node(&format!("{pil_stark}/src/{script}"), &args);
+    }
+    for name in [
+        "starkinfo.json",
+        "consttree.bin",
+        "verkey.json",
+        "proof.json",
+        "publics.json",
+    ] {
+        set_timestamp(&output.path(name));
+    }
+    log::info!(
+        "Wrote {} and {}, proof verified.",
+        file("proof.json"),
+        file("publics.json")
+    );
+}
+
+fn run_node(script: &str, args: &[String]) {
+    log::debug!("Running {script}...");
+    let output = Command::new("node")
+        .arg("--max-old-space-size=8000") // 8GB of memory
+        .arg(script)
+        .args(args)
+        .output()
+        .unwrap_or_else(|e| panic!("Failed to run {script}: {e}"));
+    if !output.status.success() {
+        panic!(
+            "{script} was unsuccessful.\nStdout: {}\nStderr: {}\n",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
+
+/// Returns the stark structure (FRI parameters) for the given degree.
+fn stark_struct(degree: DegreeType) -> String {
+    assert!(
+        degree.is_power_of_two(),
+        "The degree has to be a power of two for eSTARK proofs, but it is {degree}."
+    );
+    let bits = degree.trailing_zeros();
+    let bits_ext = bits + 1;
+    // Every FRI step folds by a factor of 16.
+    let mut steps = vec![bits_ext];
+    while *steps.last().unwrap() > 4 {
+        steps.push(steps.last().unwrap() - 4);
+    }
+    let steps = steps
+        .iter()
+        .map(|bits| format!("{{\"nBits\": {bits}}}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "{{\"nBits\": {bits}, \"nBitsExt\": {bits_ext}, \"nQueries\": {QUERIES}, \
+        \"verificationHashType\": \"GL\", \"steps\": [{steps}]}}"
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stark_struct_steps() {
+        assert_eq!(
+            stark_struct(1 << 10),
+            "{\"nBits\": 10, \"nBitsExt\": 11, \"nQueries\": 128, \
+            \"verificationHashType\": \"GL\", \"steps\": [{\"nBits\": 11}, {\"nBits\": 7}, {\"nBits\": 3}]}"
+        );
+    }
+
+    #[test]
+    #[should_panic = "power of two"]
+    fn non_power_of_two() {
+        stark_struct(1000);
+    }
+}
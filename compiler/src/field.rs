@@ -0,0 +1,128 @@
+//! Selection of the field the whole pipeline runs in.
+
+use std::fmt;
+use std::str::FromStr;
+
+use number::{AbstractNumberType, BabyBearField, Bn254Field, FieldElement, GoldilocksField};
+
+use crate::BackendType;
+
+/// A field that can be selected by name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldType {
+    Goldilocks,
+    Bn254,
+    BabyBear,
+}
+
+impl FieldType {
+    pub fn modulus(&self) -> AbstractNumberType {
+        match self {
+            FieldType::Goldilocks => GoldilocksField::modulus(),
+            FieldType::Bn254 => Bn254Field::modulus(),
+            FieldType::BabyBear => BabyBearField::modulus(),
+        }
+    }
+}
+
+impl FromStr for FieldType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gl" => Ok(FieldType::Goldilocks),
+            "bn254" => Ok(FieldType::Bn254),
+            "bb" => Ok(FieldType::BabyBear),
+            _ => Err(format!("Unknown field {s}, expected gl, bn254 or bb.")),
+        }
+    }
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldType::Goldilocks => write!(f, "gl"),
+            FieldType::Bn254 => write!(f, "bn254"),
+            FieldType::BabyBear => write!(f, "bb"),
+        }
+    }
+}
+
+/// Returns the modulus of the field to run the pipeline in: the selected
+/// field, which has to be supported by the backend, or the field the backend
+/// requires, or Goldilocks. Use `with_field!` to run code in that field.
+pub fn select_field(
+    field: Option<FieldType>,
+    backend: Option<BackendType>,
+) -> Result<AbstractNumberType, String> {
+    let required = backend.and_then(|backend| backend.required_modulus());
+    match (field, required) {
+        (Some(field), Some(required)) if field.modulus() != required => Err(format!(
+            "The {} backend does not support the field {field}.",
+            backend.unwrap()
+        )),
+        (Some(field), _) => Ok(field.modulus()),
+        (None, Some(required)) => Ok(required),
+        (None, None) => Ok(GoldilocksField::modulus()),
+    }
+}
+
+/// Evaluates `$body` with `$T` as the type of the field elements for the
+/// modulus, see `select_field`. Fields other than the named ones are run as
+/// `DynamicField` with the modulus set here.
+#[macro_export]
+macro_rules! with_field {
+    ($modulus:expr, $T:ident => $body:expr) => {{
+        let modulus = $modulus;
+        if modulus == <::number::GoldilocksField as ::number::FieldElement>::modulus() {
+            type $T = ::number::GoldilocksField;
+            $body
+        } else if modulus == <::number::Bn254Field as ::number::FieldElement>::modulus() {
+            type $T = ::number::Bn254Field;
+            $body
+        } else if modulus == <::number::BabyBearField as ::number::FieldElement>::modulus() {
+            type $T = ::number::BabyBearField;
+            $body
+        } else {
+            ::number::DynamicField::set_modulus(modulus);
+            type $T = ::number::DynamicField;
+            $body
+        }
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use number::DegreeType;
+
+    use super::*;
+
+    #[test]
+    fn selection() {
+        assert_eq!(
+            select_field(None, None).unwrap(),
+            GoldilocksField::modulus()
+        );
+        assert_eq!(
+            select_field(Some(FieldType::BabyBear), Some(BackendType::Mock)).unwrap(),
+            BabyBearField::modulus()
+        );
+        assert_eq!(
+            select_field(None, Some(BackendType::Halo2)).unwrap(),
+            halo2::modulus()
+        );
+        assert_eq!(
+            select_field(Some(FieldType::Bn254), Some(BackendType::EStark)).unwrap_err(),
+            "The estark backend does not support the field bn254."
+        );
+    }
+
+    #[test]
+    fn dispatch() {
+        let byte_len = |field: FieldType| with_field!(field.modulus(), T => T::byte_len());
+        assert_eq!(byte_len(FieldType::Goldilocks), 8);
+        assert_eq!(byte_len(FieldType::BabyBear), 4);
+        let degree: DegreeType = with_field!(halo2::modulus(), T => T::from(7).to_degree());
+        assert_eq!(degree, 7);
+    }
+}
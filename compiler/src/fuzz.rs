@@ -0,0 +1,241 @@
+//! Fuzzing of the witness generation with random answers to the prover
+//! queries, for example the free inputs of an assembly program.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use backend::{Backend, MockBackend};
+use executor::constant_evaluator;
+use executor::witgen::check_assertion;
+use number::random::{seeded_rng, SeededRng};
+use number::{DegreeType, FieldElement};
+use pil_analyzer::Analyzed;
+use rand::Rng;
+
+use crate::panics::catch_panic;
+use crate::{analyze_file, CompileOptions};
+
+/// How many random cases are tried and how failing cases are shrunk.
+#[derive(Clone, Debug)]
+pub struct FuzzConfig {
+    /// The number of random cases.
+    pub runs: usize,
+    /// The seed of the random answers, the same seed tries the same cases.
+    pub seed: u64,
+    /// The number of failing candidates that are tried while shrinking.
+    pub max_shrink_runs: usize,
+}
+
+impl Default for FuzzConfig {
+    fn default() -> Self {
+        FuzzConfig {
+            runs: 100,
+            seed: 0,
+            max_shrink_runs: 1000,
+        }
+    }
+}
+
+/// A case for which the witness could not be generated or violates the
+/// identities, after shrinking.
+#[derive(Debug)]
+pub struct FuzzFailure<T> {
+    /// The number of the random case that failed first, starting at zero.
+    pub run: usize,
+    /// The distinct queries in the order they were asked, with their answers.
+    pub answers: Vec<(String, T)>,
+    pub message: String,
+}
+
+/// Generates the witness of an assembly or PIL file for `config.runs` random
+/// cases and checks the identities with the mock backend.
+///
+/// A case is a sequence of answers: The n-th distinct query (as a string)
+/// is answered with the n-th value of the sequence and with zero if the
+/// sequence is shorter, repeated queries get the same answer. Assertion
+/// queries are checked and not answered from the case. The first
+/// failing case is shrunk by removing answers and replacing them by smaller
+/// values as long as it still fails.
+/// @returns the shrunk failing case, or None if all cases pass.
+pub fn fuzz<T: FieldElement>(
+    file: &Path,
+    options: &CompileOptions,
+    config: &FuzzConfig,
+) -> Result<Option<FuzzFailure<T>>, String> {
+    let analyzed = analyze_file::<T>(file, options)?;
+    let (fixed, degree) = constant_evaluator::generate(&analyzed)?;
+    let run_case = |case: &[T]| run_case(&analyzed, &fixed, degree, case);
+    let mut rng = seeded_rng(config.seed);
+    let log_level = log::max_level();
+    log::set_max_level(log::LevelFilter::Off);
+    let mut failure = None;
+    for run in 0..config.runs {
+        let case = (0..rng.gen_range(0..64))
+            .map(|_| random_answer(&mut rng))
+            .collect::<Vec<_>>();
+        if let (answers, Err(message)) = run_case(&case) {
+            let (answers, message) =
+                shrink(run_case, case, answers, message, config.max_shrink_runs);
+            failure = Some(FuzzFailure {
+                run,
+                answers,
+                message,
+            });
+            break;
+        }
+    }
+    log::set_max_level(log_level);
+    Ok(failure)
+}
+
+/// Zero, one, minus one and small values are more likely to reach edge
+/// cases than uniformly distributed field elements.
+fn random_answer<T: FieldElement>(rng: &mut SeededRng) -> T {
+    match rng.gen_range(0..6) {
+        0 => T::zero(),
+        1 => T::one(),
+        2 => -T::one(),
+        3 => T::from(rng.gen_range(0u64..256)),
+        4 => T::from(rng.gen::<u32>() as u64),
+        _ => T::random(rng),
+    }
+}
+
+/// Generates the witness and checks the identities.
+/// @returns the distinct queries with their answers and the reason of the
+/// failure, if any.
+fn run_case<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    fixed: &[(&str, Vec<T>)],
+    degree: DegreeType,
+    case: &[T],
+) -> (Vec<(String, T)>, Result<(), String>) {
+    let mut answers = BTreeMap::<String, T>::new();
+    let mut order = vec![];
+    let result = catch_panic(|| {
+        let callback = |query: &str| {
+            let (kind, args) = query.split_once(',').unwrap_or((query, ""));
+            if kind.trim() == "\"assert\"" {
+                check_assertion(args);
+                return Some(T::zero());
+            }
+            let next = case.get(answers.len()).cloned().unwrap_or_else(T::zero);
+            Some(*answers.entry(query.to_string()).or_insert_with(|| {
+                order.push(query.to_string());
+                next
+            }))
+        };
+        let witness = executor::witgen::generate(analyzed, degree, fixed, Some(callback));
+        Backend::prove(&MockBackend, &(), analyzed, fixed, &witness).map_err(|e| e.to_string())
+    });
+    let answers = order
+        .into_iter()
+        .map(|query| {
+            let answer = answers[&query];
+            (query, answer)
+        })
+        .collect();
+    (answers, result)
+}
+
+/// Tries smaller variants of the failing case and keeps those that still
+/// fail, until no variant fails or `max_runs` variants were tried.
+fn shrink<T: FieldElement>(
+    run_case: impl Fn(&[T]) -> (Vec<(String, T)>, Result<(), String>),
+    mut case: Vec<T>,
+    mut answers: Vec<(String, T)>,
+    mut message: String,
+    max_runs: usize,
+) -> (Vec<(String, T)>, String) {
+    // Answers that are never used by the failing run do not matter.
+    case.truncate(answers.len());
+    let mut runs = 0;
+    let mut shrunk = true;
+    while shrunk && runs < max_runs {
+        shrunk = false;
+        for candidate in smaller_cases(&case) {
+            if runs == max_runs {
+                break;
+            }
+            runs += 1;
+            if let (candidate_answers, Err(candidate_message)) = run_case(&candidate) {
+                case = candidate;
+                case.truncate(candidate_answers.len());
+                answers = candidate_answers;
+                message = candidate_message;
+                shrunk = true;
+                break;
+            }
+        }
+    }
+    (answers, message)
+}
+
+/// The variants of the case without one of the answers and with one answer
+/// replaced by zero, one, its absolute value or the half or the predecessor
+/// of its absolute value.
+fn smaller_cases<T: FieldElement>(case: &[T]) -> Vec<Vec<T>> {
+    let mut cases = vec![];
+    for i in (0..case.len()).rev() {
+        let mut candidate = case.to_vec();
+        candidate.remove(i);
+        cases.push(candidate);
+    }
+    for (i, value) in case.iter().enumerate() {
+        let absolute = if value.to_signed_integer() < 0.into() {
+            -*value
+        } else {
+            *value
+        };
+        let mut smaller = [
+            T::zero(),
+            T::one(),
+            absolute.integer_div(2.into()),
+            absolute - T::one(),
+        ]
+        .into_iter()
+        .filter(|s| s.to_integer() < absolute.to_integer())
+        .collect::<Vec<_>>();
+        if *value != absolute {
+            smaller.push(absolute);
+        }
+        smaller.dedup();
+        for smaller in smaller {
+            let mut candidate = case.to_vec();
+            candidate[i] = smaller;
+            cases.push(candidate);
+        }
+    }
+    cases
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+
+    use super::*;
+
+    #[test]
+    fn smaller() {
+        let case = [6, -3].map(GoldilocksField::from_i64);
+        let cases = smaller_cases(&case)
+            .iter()
+            .map(|c| c.iter().map(|v| v.to_signed_i64().unwrap()).collect())
+            .collect::<Vec<Vec<_>>>();
+        assert_eq!(
+            cases,
+            [
+                vec![6],
+                vec![-3],
+                vec![0, -3],
+                vec![1, -3],
+                vec![3, -3],
+                vec![5, -3],
+                vec![6, 0],
+                vec![6, 1],
+                vec![6, 2],
+                vec![6, 3]
+            ]
+        );
+    }
+}
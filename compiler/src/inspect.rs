@@ -0,0 +1,271 @@
+//! Prints selected columns of a generated witness as a table.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::BufReader;
+use std::ops::Range;
+use std::path::Path;
+
+use backend::Failure;
+use executor::constant_evaluator;
+use number::{DegreeType, FieldElement};
+use pil_analyzer::Analyzed;
+
+use crate::read_polys_file;
+
+/// Returns the values of the columns on the rows as a table, with the
+/// witness read from `commits.bin` in `dir` and the fixed columns generated
+/// from the PIL file. Columns can be given with or without namespace, the
+/// default are all witness columns. If `identity` (a line or `file:line`) is
+/// given, the rows on which the identities there fail are marked with `>`
+/// and the failures are listed below the table.
+pub fn inspect_witness<T: FieldElement>(
+    pil_file: &Path,
+    dir: &Path,
+    columns: &[String],
+    rows: Option<Range<DegreeType>>,
+    identity: Option<&str>,
+) -> Result<String, String> {
+    let analyzed = crate::analyze_pil::<T>(pil_file)?;
+    let (fixed, _) = constant_evaluator::generate(&analyzed)?;
+    let witness = read_witness(&analyzed, dir)?;
+    let degree = witness[0].1.len() as DegreeType;
+
+    let selected = if columns.is_empty() {
+        witness.iter().collect::<Vec<_>>()
+    } else {
+        columns
+            .iter()
+            .map(|name| {
+                fixed
+                    .iter()
+                    .chain(&witness)
+                    .find(|(column, _)| *column == name || column.ends_with(&format!(".{name}")))
+                    .ok_or_else(|| format!("Column {name} not found."))
+            })
+            .collect::<Result<_, _>>()?
+    };
+    let rows = rows.unwrap_or(0..degree);
+    if rows.end > degree {
+        return Err(format!(
+            "Row {} is out of range, the witness has {degree} rows.",
+            rows.end - 1
+        ));
+    }
+
+    let failures = match identity {
+        Some(identity) => failing_rows(&analyzed, &fixed, &witness, identity)?,
+        None => BTreeMap::new(),
+    };
+
+    let mut table = vec![std::iter::once("row".to_string())
+        .chain(selected.iter().map(|(name, _)| name.to_string()))
+        .collect::<Vec<_>>()];
+    table.extend(rows.clone().map(|row| {
+        std::iter::once(row.to_string())
+            .chain(
+                selected
+                    .iter()
+                    .map(|(_, values)| values[row as usize].to_string()),
+            )
+            .collect()
+    }));
+    let widths = (0..table[0].len())
+        .map(|i| table.iter().map(|line| line[i].len()).max().unwrap())
+        .collect::<Vec<_>>();
+    let mut output = table
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let failing = i > 0 && failures.contains_key(&(rows.start as usize + i - 1));
+            let cells = line
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{cell:>width$}"))
+                .collect::<Vec<_>>()
+                .join("  ");
+            format!("{}{cells}\n", if failing { "> " } else { "  " })
+        })
+        .collect::<String>();
+
+    if let Some(identity) = identity {
+        let shown = failures
+            .range(rows.start as usize..rows.end as usize)
+            .flat_map(|(_, failures)| failures)
+            .collect::<Vec<_>>();
+        if failures.is_empty() {
+            output += &format!("\nThe identities at {identity} hold on all rows.\n");
+        } else {
+            output += &format!(
+                "\nThe identities at {identity} fail on {} rows, {} of them shown:\n",
+                failures.len(),
+                shown.len()
+            );
+        }
+        for failure in shown {
+            output += &format!("{failure}\n");
+        }
+    }
+    Ok(output)
+}
+
+/// Reads the values of the witness columns of the PIL file from `commits.bin` in `dir`.
+pub(crate) fn read_witness<'a, T: FieldElement>(
+    analyzed: &'a Analyzed<T>,
+    dir: &Path,
+) -> Result<Vec<(&'a str, Vec<T>)>, String> {
+    let witness_columns = analyzed
+        .committed_polys_in_source_order()
+        .into_iter()
+        .map(|(poly, _)| poly.absolute_name.as_str())
+        .collect::<Vec<_>>();
+    let path = dir.join("commits.bin");
+    let file =
+        fs::File::open(&path).map_err(|e| format!("Could not open {}: {e}", path.display()))?;
+    read_polys_file(&mut BufReader::new(file), &witness_columns)
+        .map_err(|e| format!("Invalid {}: {e}", path.display()))
+}
+
+/// Checks the identities at the line (or `file:line`) and returns
+/// the failures by row.
+fn failing_rows<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    fixed: &[(&str, Vec<T>)],
+    witness: &[(&str, Vec<T>)],
+    identity: &str,
+) -> Result<BTreeMap<usize, Vec<Failure<T>>>, String> {
+    let matches = |source: &str| {
+        source == identity
+            || source
+                .rsplit_once(':')
+                .map(|(_, line)| line == identity)
+                .unwrap_or_default()
+    };
+    if !analyzed
+        .identities
+        .iter()
+        .any(|i| matches(&format!("{}:{}", i.source.file, i.source.line)))
+    {
+        return Err(format!("There is no identity at {identity}."));
+    }
+    let mut failures = BTreeMap::<_, Vec<_>>::new();
+    for failure in backend::check(analyzed, fixed, witness).map_err(|e| e.to_string())? {
+        if matches(&failure.source) {
+            failures.entry(failure.row).or_default().push(failure);
+        }
+    }
+    Ok(failures)
+}
+
+/// Parses a row (`5`) or a range of rows (`0..8`).
+pub fn parse_rows(rows: &str) -> Result<Range<DegreeType>, String> {
+    let parse = |row: &str| {
+        row.parse::<DegreeType>()
+            .map_err(|_| format!("Invalid row: {row}"))
+    };
+    match rows.split_once("..") {
+        Some((start, end)) => Ok(parse(start)?..parse(end)?),
+        None => parse(rows).map(|row| row..row + 1),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::BufWriter;
+
+    use number::GoldilocksField;
+
+    use crate::write_polys_file;
+
+    use super::*;
+
+    /// Writes a PIL file and a witness with the given columns to a temporary directory.
+    fn setup(pil: &str, witness: Vec<(&str, Vec<GoldilocksField>)>) -> mktemp::Temp {
+        let dir = mktemp::Temp::new_dir().unwrap();
+        fs::write(dir.join("input.pil"), pil).unwrap();
+        let degree = witness[0].1.len() as DegreeType;
+        write_polys_file(
+            &mut BufWriter::new(fs::File::create(dir.join("commits.bin")).unwrap()),
+            degree,
+            &witness,
+        );
+        dir
+    }
+
+    const PIL: &str = "namespace Main(4);
+    col fixed FIRST = [1, 0, 0, 0];
+    col witness x, y;
+    x' = x + y;
+    FIRST * (x - 1) = 0;
+";
+
+    fn values(values: &[i64]) -> Vec<GoldilocksField> {
+        values
+            .iter()
+            .map(|v| GoldilocksField::from_i64(*v))
+            .collect()
+    }
+
+    #[test]
+    fn table() {
+        let dir = setup(
+            PIL,
+            vec![
+                ("Main.x", values(&[1, 3, 10, 200])),
+                ("Main.y", values(&[2, -1, 190, 0])),
+            ],
+        );
+        let output = inspect_witness::<GoldilocksField>(
+            &dir.join("input.pil"),
+            &dir,
+            &["y".to_string(), "Main.FIRST".to_string()],
+            Some(parse_rows("1..4").unwrap()),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            output,
+            "  row  Main.y  Main.FIRST\n    1      -1           0\n    2     190           0\n    3       0           0\n"
+        );
+    }
+
+    #[test]
+    fn failing_identity() {
+        let dir = setup(
+            PIL,
+            vec![
+                ("Main.x", values(&[1, 3, 5, 7])),
+                ("Main.y", values(&[2, -1, 5, -6])),
+            ],
+        );
+        let output = inspect_witness::<GoldilocksField>(
+            &dir.join("input.pil"),
+            &dir,
+            &[],
+            Some(0..2),
+            Some("4"),
+        )
+        .unwrap();
+        let (table, failures) = output.split_once("\n\n").unwrap();
+        assert_eq!(
+            table,
+            "  row  Main.x  Main.y\n    0       1       2\n>   1       3      -1"
+        );
+        assert!(
+            failures.starts_with(
+                "The identities at 4 fail on 2 rows, 1 of them shown:\ninput.pil:4 row 1:"
+            ),
+            "{failures}"
+        );
+
+        let error = inspect_witness::<GoldilocksField>(
+            &dir.join("input.pil"),
+            &dir,
+            &[],
+            None,
+            Some("input.pil:3"),
+        )
+        .unwrap_err();
+        assert_eq!(error, "There is no identity at input.pil:3.");
+    }
+}
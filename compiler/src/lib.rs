@@ -1,166 +1,861 @@
 //! The main powdr lib, used to compile from assembly to PIL
 
+use std::borrow::Cow;
+use std::fmt;
 use std::fs;
-use std::io::{BufWriter, Write};
+use std::io::{self, BufReader, Read, Write};
 use std::path::Path;
+use std::str::FromStr;
 use std::time::Instant;
 
+mod audit;
+mod bench;
+mod determinism;
+mod differential;
+mod estark;
+mod field;
+mod fuzz;
+mod inspect;
+mod mutation;
+mod output;
+mod panics;
+mod pil_tests;
+mod project;
+pub mod repl;
+mod replay;
+mod snapshot;
+mod threads;
+mod trace_html;
+mod underconstrained;
 mod verify;
+pub use audit::audit_witness;
+use backend::{check_capabilities, lower_identities, MockBackend};
+pub use backend::{is_reproducible, set_reproducible, Backend, Coverage, ProofArtifact, Publics};
+pub use bench::{bench, BenchReport, PhaseMeasurement};
+pub use determinism::check_determinism;
+pub use differential::{differential_test, DifferentialReport};
+pub use executor::witgen::inputs_query_callback;
+pub use field::{select_field, FieldType};
+pub use fuzz::{fuzz, FuzzConfig, FuzzFailure};
+use halo2::Halo2Backend;
+pub use inspect::{inspect_witness, parse_rows};
+pub use mutation::{mutation_test, MutantOutcome, MutationReport};
+pub use output::{parse_artifacts, Artifact, OutputLayout};
+pub use panics::is_catching_panic;
+pub use pil_analyzer::diagnostics;
+pub use pil_analyzer::included_files;
 use pil_analyzer::json_exporter;
+use pil_analyzer::r1cs_exporter::{self, R1cs};
+pub use pil_tests::{run_tests, TestOutcome};
+pub use pilgen::{interpret, CompileOptions, Execution, InstructionStub};
+pub use project::{InputSource, ProjectConfig, PROJECT_FILE};
+pub use replay::{record_queries, replay_queries};
+pub use snapshot::{snapshot_witness, WitnessSnapshot};
+pub use threads::{set_threads, Phase, ThreadConfig};
+pub use trace_html::export_trace_html;
+pub use underconstrained::{underconstrained_columns, UnderconstrainedColumn};
 pub use verify::{compile_asm_string_temp, verify, verify_asm_string};
 
 use executor::constant_evaluator;
-use number::{DegreeType, FieldElement};
+use number::{AbstractNumberType, DegreeType, FieldElement, GoldilocksField};
 use parser::ast::PILFile;
+use threads::in_phase;
 
-pub fn no_callback() -> Option<fn(&str) -> Option<FieldElement>> {
+/// A backend that creates proofs from the fixed and witness columns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackendType {
+    Halo2,
+    /// eSTARK through the pil-stark javascript implementation.
+    EStark,
+    /// Checks all identities on the trace without creating a proof.
+    Mock,
+}
+
+impl BackendType {
+    /// Returns the modulus of the field the PIL file has to be compiled in,
+    /// if the backend only supports a single field.
+    pub fn required_modulus(&self) -> Option<AbstractNumberType> {
+        match self {
+            BackendType::Halo2 => Some(halo2::modulus()),
+            BackendType::EStark => Some(GoldilocksField::modulus()),
+            BackendType::Mock => None,
+        }
+    }
+}
+
+impl FromStr for BackendType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "halo2" => Ok(BackendType::Halo2),
+            "estark" => Ok(BackendType::EStark),
+            "mock" => Ok(BackendType::Mock),
+            _ => Err(format!(
+                "Unknown backend {s}, expected halo2, estark or mock."
+            )),
+        }
+    }
+}
+
+impl fmt::Display for BackendType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackendType::Halo2 => write!(f, "halo2"),
+            BackendType::EStark => write!(f, "estark"),
+            BackendType::Mock => write!(f, "mock"),
+        }
+    }
+}
+
+pub fn no_callback<T: FieldElement>() -> Option<fn(&str) -> Option<T>> {
     None
 }
 
 /// Compiles a .pil file to its json form and also tries to generate
 /// constants and committed polynomials.
+/// If a backend is given, also creates and verifies a proof.
 /// @returns true if all committed/witness and constant/fixed polynomials
 /// could be generated.
-pub fn compile_pil(
+pub fn compile_pil<T: FieldElement>(
     pil_file: &Path,
-    output_dir: &Path,
-    query_callback: Option<impl FnMut(&str) -> Option<FieldElement>>,
+    options: &CompileOptions,
+    output: &OutputLayout,
+    query_callback: Option<impl FnMut(&str) -> Option<T> + Send>,
+    prove_with: Option<BackendType>,
 ) -> bool {
+    let analyzed = tracing::info_span!("analyze")
+        .in_scope(|| pil_analyzer::analyze_with_defines(pil_file, options.defines.clone()));
     compile(
-        &pil_analyzer::analyze(pil_file),
+        &analyzed,
         pil_file.file_name().unwrap().to_str().unwrap(),
-        output_dir,
+        output,
         query_callback,
+        prove_with,
     )
 }
 
-pub fn compile_pil_ast(
-    pil: &PILFile,
+pub fn compile_pil_ast<T: FieldElement>(
+    pil: &PILFile<T>,
     file_name: &str,
-    output_dir: &Path,
-    query_callback: Option<impl FnMut(&str) -> Option<FieldElement>>,
+    output: &OutputLayout,
+    query_callback: Option<impl FnMut(&str) -> Option<T> + Send>,
+    prove_with: Option<BackendType>,
 ) -> bool {
     // TODO exporting this to string as a hack because the parser
     // is tied into the analyzer due to imports.
-    compile(
-        &pil_analyzer::analyze_string(&format!("{pil}")),
-        file_name,
-        output_dir,
-        query_callback,
-    )
+    let analyzed =
+        tracing::info_span!("analyze").in_scope(|| pil_analyzer::analyze_string(&format!("{pil}")));
+    compile(&analyzed, file_name, output, query_callback, prove_with)
+}
+
+/// Returns a Graphviz graph of the machines of an assembly or PIL file and
+/// the lookups, permutations and connections between them.
+pub fn export_dot<T: FieldElement>(
+    file: &Path,
+    options: &CompileOptions,
+) -> Result<String, String> {
+    Ok(pil_analyzer::dot_exporter::export(&analyze_file::<T>(
+        file, options,
+    )?))
+}
+
+/// Returns the identities of an assembly or PIL file that reference the
+/// namespace (all if it is None) on the first `rows` rows as an SMT-LIB
+/// script, see `pil_analyzer::smt_exporter`.
+pub fn export_smt<T: FieldElement>(
+    file: &Path,
+    options: &CompileOptions,
+    namespace: Option<&str>,
+    rows: DegreeType,
+) -> Result<String, String> {
+    let analyzed = analyze_file::<T>(file, options)?;
+    let (fixed, _) = constant_evaluator::generate(&analyzed)?;
+    pil_analyzer::smt_exporter::export(&analyzed, &fixed, namespace, rows)
+}
+
+/// The documents that `json_schema` returns.
+pub const JSON_SCHEMA_DOCUMENTS: [&str; 3] = ["json", "proof", "publics"];
+
+/// Returns the JSON Schema document of the JSON export of a PIL file
+/// (`json`), of the proof artifact (`proof`) or of the public values
+/// (`publics`), to validate the files written to the output directory.
+/// The schemas are written by hand next to the exporters, they are not
+/// derived from the Rust types, see `pil_analyzer::json_schema`.
+pub fn json_schema(document: &str) -> Result<json::JsonValue, String> {
+    match document {
+        "json" => Ok(json_exporter::schema()),
+        "proof" => Ok(ProofArtifact::json_schema()),
+        "publics" => Ok(Publics::<GoldilocksField>::json_schema()),
+        _ => Err(format!(
+            "Unknown document {document}, expected {}.",
+            JSON_SCHEMA_DOCUMENTS.join(", ")
+        )),
+    }
+}
+
+/// Returns the column and identity counts, the maximal identity degree and
+/// the committed cells of an assembly or PIL file, by namespace.
+pub fn stats<T: FieldElement>(
+    file: &Path,
+    options: &CompileOptions,
+) -> Result<pil_analyzer::stats::Stats, String> {
+    Ok(pil_analyzer::stats::stats(&analyze_file::<T>(
+        file, options,
+    )?))
+}
+
+/// Generates the fixed columns and the witness of an assembly or PIL file
+/// for the inputs and returns the memory used, see `MemoryReport`. The fixed
+/// columns are the first entry of the machines and their evaluation the
+/// first phase.
+pub fn memory_report<T: FieldElement>(
+    file: &Path,
+    options: &CompileOptions,
+    inputs: Vec<T>,
+) -> Result<executor::witgen::MemoryReport, String> {
+    let analyzed = analyze_file::<T>(file, options)?;
+    let (fixed, degree) = constant_evaluator::generate(&analyzed)?;
+    let fixed_memory = executor::witgen::MemoryUsage {
+        columns: fixed.iter().map(|(_, values)| values.len()).sum::<usize>()
+            * std::mem::size_of::<T>(),
+        ..Default::default()
+    };
+    let fixed_resident = executor::witgen::resident_memory();
+    let (_, mut report) = panics::catch_panic(|| {
+        Ok(executor::witgen::generate_with_memory_report(
+            &analyzed,
+            degree,
+            &fixed,
+            Some(inputs_query_callback(inputs, 0)),
+        ))
+    })
+    .map_err(|e| format!("Witness generation failed: {e}"))?;
+    report
+        .machines
+        .insert(0, ("fixed columns".to_string(), fixed_memory));
+    report.phases.insert(0, ("fixed", fixed_resident));
+    Ok(report)
+}
+
+/// Returns on how many rows the identities of the PIL file are active, which
+/// rows of lookup tables are never looked up and which arms of the queries
+/// are never hit, with the witness read from `commits.bin` in `dir`.
+pub fn coverage<T: FieldElement>(pil_file: &Path, dir: &Path) -> Result<Coverage<T>, String> {
+    let analyzed = analyze_pil::<T>(pil_file)?;
+    let (fixed, _) = constant_evaluator::generate(&analyzed)?;
+    let witness = inspect::read_witness(&analyzed, dir)?;
+    backend::coverage(&analyzed, &fixed, &witness).map_err(|e| e.to_string())
+}
+
+/// Analyzes a PIL file, or the PIL generated from an assembly file.
+fn analyze_file<T: FieldElement>(
+    file: &Path,
+    options: &CompileOptions,
+) -> Result<pil_analyzer::Analyzed<T>, String> {
+    let file_name = file.to_str().unwrap();
+    if !file_name.ends_with(".asm") {
+        return pil_analyzer::try_analyze_with_defines(file, options.defines.clone())
+            .map_err(|e| e.to_string());
+    }
+    let contents =
+        fs::read_to_string(file).map_err(|e| format!("Could not read {file_name}: {e}"))?;
+    let pil = pilgen::compile::<T>(Some(file_name), &contents, options)
+        .map_err(|e| format!("Error parsing {file_name}: {}", e.message()))?;
+    pil_analyzer::try_analyze_string(&format!("{pil}")).map_err(|e| e.to_string())
+}
+
+/// Analyzes a PIL file and returns the rendered error if it is invalid.
+pub(crate) fn analyze_pil<T: FieldElement>(
+    file: &Path,
+) -> Result<pil_analyzer::Analyzed<T>, String> {
+    pil_analyzer::try_analyze(file).map_err(|e| e.to_string())
+}
+
+/// Analyzes a .pil file, generates fixed and witness columns and writes
+/// the constraints and the witness as `<file>.r1cs` and `<file>.wtns`.
+/// @returns false if the constraints cannot be exported.
+pub fn export_r1cs<T: FieldElement>(
+    pil_file: &Path,
+    output_dir: &Path,
+    query_callback: Option<impl FnMut(&str) -> Option<T>>,
+) -> bool {
+    let analyzed = pil_analyzer::analyze(pil_file);
+    let (constants, degree) =
+        constant_evaluator::generate(&analyzed).unwrap_or_else(|e| panic!("{e}"));
+    let commits = executor::witgen::generate(&analyzed, degree, &constants, query_callback);
+    let r1cs = match r1cs_exporter::export(&analyzed, &constants, &commits) {
+        Ok(r1cs) => r1cs,
+        Err(err) => {
+            log::error!("{err}");
+            return false;
+        }
+    };
+    if !r1cs.is_satisfied() {
+        log::warn!("The witness does not satisfy the constraints.");
+    }
+    let file_name = pil_file.file_name().unwrap().to_str().unwrap();
+    for (extension, write) in [
+        ("r1cs", R1cs::write_r1cs as fn(&R1cs<T>, &mut Vec<u8>) -> _),
+        ("wtns", R1cs::write_wtns),
+    ] {
+        let path = output_dir.join(format!("{file_name}.{extension}"));
+        let mut bytes = vec![];
+        write(&r1cs, &mut bytes).unwrap();
+        write_artifact(&path, &bytes).unwrap();
+        log::info!("Wrote {}.", path.to_str().unwrap());
+    }
+    log::info!(
+        "{} wires, {} constraints.",
+        r1cs.wires.len(),
+        r1cs.constraints.len()
+    );
+    true
 }
 
 /// Compiles a .asm file, outputs the PIL on stdout and tries to generate
-/// fixed and witness columns.
-pub fn compile_asm(
+/// fixed and witness columns. Programs with `export` start at the exported
+/// label with the number `entry_point`.
+/// @returns false if the PIL file already exists and is not overwritten
+/// or if not all fixed and witness columns could be generated.
+pub fn compile_asm<T: FieldElement>(
     file_name: &str,
-    inputs: Vec<FieldElement>,
-    output_dir: &Path,
+    options: &CompileOptions,
+    inputs: Vec<T>,
+    entry_point: u64,
+    output: &OutputLayout,
     force_overwrite: bool,
-) {
+    prove_with: Option<BackendType>,
+) -> bool {
     let contents = fs::read_to_string(file_name).unwrap();
-    compile_asm_string(file_name, &contents, inputs, output_dir, force_overwrite)
+    compile_asm_string(
+        file_name,
+        &contents,
+        options,
+        inputs,
+        entry_point,
+        output,
+        force_overwrite,
+        prove_with,
+    )
 }
 
 /// Compiles the contents of a .asm file, outputs the PIL on stdout and tries to generate
-/// fixed and witness columns.
-pub fn compile_asm_string(
+/// fixed and witness columns, see `compile_asm`.
+#[allow(clippy::too_many_arguments)]
+pub fn compile_asm_string<T: FieldElement>(
     file_name: &str,
     contents: &str,
-    inputs: Vec<FieldElement>,
-    output_dir: &Path,
+    options: &CompileOptions,
+    inputs: Vec<T>,
+    entry_point: u64,
+    output: &OutputLayout,
     force_overwrite: bool,
-) {
-    let pil = pilgen::compile(Some(file_name), contents).unwrap_or_else(|err| {
-        eprintln!("Error parsing .asm file:");
-        err.output_to_stderr();
-        panic!();
-    });
-    let pil_file_name = output_dir.join(format!(
+    prove_with: Option<BackendType>,
+) -> bool {
+    let (pil, source_map, origins) =
+        tracing::info_span!("parse", file = file_name).in_scope(|| {
+            pilgen::compile_with_source_map::<T>(Some(file_name), contents, options).unwrap_or_else(
+                |err| {
+                    eprintln!("Error parsing .asm file:");
+                    err.output_to_stderr();
+                    panic!();
+                },
+            )
+        });
+    for warning in pilgen::check::<T>(Some(file_name), contents, &options.defines).unwrap() {
+        log::warn!("{file_name}: {warning}");
+    }
+    let pil_file_name = format!(
         "{}.pil",
         Path::new(file_name).file_stem().unwrap().to_str().unwrap()
-    ));
-    if pil_file_name.exists() && !force_overwrite {
-        eprint!(
-            "Target file {} already exists. Not overwriting.",
-            pil_file_name.to_str().unwrap()
+    );
+    if output.writes(Artifact::Pil) {
+        let pil_file = output.path(&pil_file_name);
+        if pil_file.exists() && !force_overwrite {
+            eprint!(
+                "Target file {} already exists. Not overwriting.",
+                pil_file.to_str().unwrap()
+            );
+            return false;
+        }
+        write_artifact(&pil_file, format!("{pil}").as_bytes()).unwrap();
+        log::info!("Wrote {}.", pil_file.to_str().unwrap());
+    }
+    if output.writes(Artifact::Pil) && !source_map.locations.is_empty() {
+        let source_map_file_name = output.path(&pil_file_name).with_extension("srcmap");
+        write_artifact(
+            &source_map_file_name,
+            source_map
+                .locations
+                .iter()
+                .map(|(pc, location)| format!("{pc} {location}\n"))
+                .collect::<String>()
+                .as_bytes(),
+        )
+        .unwrap();
+        log::info!(
+            "Wrote source map (pc to source location) to {}",
+            source_map_file_name.to_str().unwrap()
         );
-        return;
     }
-    fs::write(pil_file_name.clone(), format!("{pil}")).unwrap();
-
-    let query_callback = |query: &str| -> Option<FieldElement> {
-        let items = query.split(',').map(|s| s.trim()).collect::<Vec<_>>();
-        assert_eq!(items.len(), 2);
-        match items[0] {
-            "\"input\"" => {
-                let index = items[1].parse::<usize>().unwrap();
-                let value = inputs.get(index).cloned();
-                if let Some(value) = value {
-                    log::trace!("Input query: Index {index} -> {value}");
-                }
-                value
-            }
-            _ => None,
-        }
-    };
-    compile_pil_ast(
-        &pil,
-        pil_file_name.to_str().unwrap(),
-        output_dir,
-        Some(query_callback),
-    );
+
+    // TODO exporting this to string as a hack, see `compile_pil_ast`.
+    let mut analyzed =
+        tracing::info_span!("analyze").in_scope(|| pil_analyzer::analyze_string(&format!("{pil}")));
+    analyzed.set_identity_origins(&origins);
+    if let Some(pc) = &source_map.pc {
+        analyzed.set_pc_locations(
+            pc,
+            source_map
+                .locations
+                .iter()
+                .map(|(pc, location)| (*pc as DegreeType, location.to_string()))
+                .collect(),
+        );
+    }
+    compile(
+        &analyzed,
+        &pil_file_name,
+        output,
+        Some(inputs_query_callback(inputs, entry_point)),
+        prove_with,
+    )
 }
 
-fn compile(
-    analyzed: &pil_analyzer::Analyzed,
+fn compile<T: FieldElement>(
+    analyzed: &pil_analyzer::Analyzed<T>,
     file_name: &str,
-    output_dir: &Path,
-    query_callback: Option<impl FnMut(&str) -> Option<FieldElement>>,
+    output: &OutputLayout,
+    query_callback: Option<impl FnMut(&str) -> Option<T> + Send>,
+    prove_with: Option<BackendType>,
 ) -> bool {
     let mut success = true;
-    let start = Instant::now();
-    log::info!("Evaluating fixed columns...");
-    let (constants, degree) = constant_evaluator::generate(analyzed);
-    println!("Took {}", start.elapsed().as_secs_f32());
+    let json_file = output.json_file(file_name);
+    if output.writes(Artifact::Json) {
+        let json_out = json_exporter::export(analyzed);
+        write_artifact(&json_file, json_out.dump().as_bytes()).unwrap();
+        log::info!("Wrote {}.", json_file.to_str().unwrap());
+    }
+    let (constants, degree) = tracing::info_span!("fixed").in_scope(|| {
+        in_phase(Phase::Fixed, || {
+            load_or_generate_constants(analyzed, output)
+        })
+    });
     if analyzed.constant_count() == constants.len() {
-        write_polys_file(
-            &mut BufWriter::new(&mut fs::File::create(output_dir.join("constants.bin")).unwrap()),
-            degree,
-            &constants,
-        );
-        log::info!("Wrote constants.bin.");
+        if output.writes(Artifact::Constants) {
+            let mut bytes = vec![];
+            write_polys_file(&mut bytes, degree, &constants);
+            write_artifact(&output.constants_file(), &bytes).unwrap();
+            log::info!("Wrote {}.", output.constants_file().to_str().unwrap());
+        }
         log::info!("Deducing witness columns...");
-        let commits = executor::witgen::generate(analyzed, degree, &constants, query_callback);
-        write_polys_file(
-            &mut BufWriter::new(&mut fs::File::create(output_dir.join("commits.bin")).unwrap()),
-            degree,
-            &commits,
-        );
-        log::info!("Wrote commits.bin.");
+        let commits = tracing::info_span!("witgen", degree).in_scope(|| {
+            in_phase(Phase::Witgen, || {
+                executor::witgen::generate(analyzed, degree, &constants, query_callback)
+            })
+        });
+        if output.writes(Artifact::Witness) {
+            let mut bytes = vec![];
+            write_polys_file(&mut bytes, degree, &commits);
+            write_artifact(&output.witness_file(), &bytes).unwrap();
+            log::info!("Wrote {}.", output.witness_file().to_str().unwrap());
+        }
+        if let Some(backend) = prove_with {
+            prove(
+                analyzed, backend, &constants, &commits, degree, &json_file, output,
+            );
+        }
     } else {
         log::warn!("Not writing constants.bin because not all declared constants are defined (or there are none).");
         success = false;
     }
-    let json_out = json_exporter::export(analyzed);
-    let json_file = format!("{file_name}.json");
-    json_out
-        .write(&mut fs::File::create(output_dir.join(&json_file)).unwrap())
-        .unwrap();
-    log::info!("Wrote {json_file}.");
     success
 }
 
-fn write_polys_file(
+/// Creates a proof, writes it to the output directory (if the proof artifact
+/// is selected) and verifies it. The eSTARK backend expects the constants,
+/// the witness and the json form of the PIL file to be there already.
+fn prove<T: FieldElement>(
+    analyzed: &pil_analyzer::Analyzed<T>,
+    backend: BackendType,
+    constants: &[(&str, Vec<T>)],
+    commits: &[(&str, Vec<T>)],
+    degree: DegreeType,
+    pil_json: &Path,
+    output: &OutputLayout,
+) {
+    if let Some(modulus) = backend.required_modulus() {
+        assert!(
+            T::modulus() == modulus,
+            "The {backend} backend needs the field with modulus {modulus}."
+        );
+    }
+    let _span = tracing::info_span!("backend", %backend).entered();
+    log::info!("Creating proof with {backend}...");
+    let start = Instant::now();
+    in_phase(Phase::Backend, || match backend {
+        BackendType::Halo2 => {
+            prove_with_backend(&Halo2Backend, analyzed, constants, commits, output);
+        }
+        BackendType::EStark => {
+            for artifact in [Artifact::Json, Artifact::Constants, Artifact::Witness] {
+                assert!(
+                    output.writes(artifact),
+                    "The estark backend needs the {artifact} artifact to be written."
+                );
+            }
+            estark::prove(pil_json, degree, output)
+        }
+        BackendType::Mock => {
+            check_capabilities(analyzed, Backend::<T>::capabilities(&MockBackend))
+                .unwrap_or_else(|err| panic!("{err}"));
+            Backend::prove(&MockBackend, &(), analyzed, constants, commits)
+                .unwrap_or_else(|err| panic!("{err}"));
+            log::info!("All identities are satisfied.");
+        }
+    });
+    log::info!("Took {}", start.elapsed().as_secs_f32());
+}
+
+/// Creates a proof with the given backend and verifies it. Writes the proof
+/// artifact to `proof_artifact.json` and the public values to `publics.json`
+/// in the output directory, if the proof artifact is selected.
+/// @returns the public values.
+pub fn prove_with_backend<T: FieldElement, B: Backend<T>>(
+    backend: &B,
+    analyzed: &pil_analyzer::Analyzed<T>,
+    constants: &[(&str, Vec<T>)],
+    commits: &[(&str, Vec<T>)],
+    output: &OutputLayout,
+) -> Publics<T> {
+    let lowered =
+        lower_identities(analyzed, backend.capabilities()).unwrap_or_else(|err| panic!("{err}"));
+    // The constraints the backend proves, with the witness columns they need.
+    let (constraints, witness) = match &lowered {
+        Some(lowered) => (
+            &lowered.analyzed,
+            Cow::Owned(lowered.witness(analyzed, constants, commits)),
+        ),
+        None => (analyzed, Cow::Borrowed(commits)),
+    };
+    let setup = load_or_create_setup(backend, constraints, constants, output);
+    if backend::is_reproducible() {
+        log::warn!("Reproducible mode: The proof uses fixed randomness and is not zero-knowledge.");
+    }
+    let proof = backend
+        .prove(&setup, constraints, constants, &witness)
+        .unwrap_or_else(|err| panic!("{err}"));
+
+    let publics = Publics::from_witness(analyzed, commits)
+        .unwrap_or_else(|name| panic!("No values provided for column {name}."));
+    for (name, value) in publics.iter() {
+        log::info!("Public {name} = {value}");
+    }
+    if output.writes(Artifact::Proof) {
+        let mut publics_bytes = vec![];
+        publics.write_json(&mut publics_bytes).unwrap();
+        write_artifact(&output.publics_file(), &publics_bytes).unwrap();
+        log::info!("Wrote {}.", output.publics_file().to_str().unwrap());
+
+        let mut proof_bytes = vec![];
+        backend.write_proof(&proof, &mut proof_bytes).unwrap();
+        let artifact = ProofArtifact::new(
+            backend.name(),
+            analyzed.fingerprint(),
+            &publics,
+            proof_bytes,
+        );
+        write_artifact(&output.proof_file(), artifact.to_json().as_bytes()).unwrap();
+        log::info!("Wrote {}.", output.proof_file().to_str().unwrap());
+    }
+
+    backend
+        .verify(&setup, constraints, constants, &publics.values(), &proof)
+        .unwrap_or_else(|err| panic!("Proof verification failed: {err}"));
+    log::info!("Proof verified.");
+    publics
+}
+
+/// Verifies a proof artifact against a PIL file. The setup is read from
+/// `setup_file` if given, otherwise it is derived from the PIL file. If
+/// `publics_file` is given, the public values of the proof have to match the
+/// values in that file, as written by `Publics::write_json`.
+/// Fails with "Proof rejected: " and a description of the problem if the
+/// artifact does not belong to the PIL file or the proof is not valid.
+pub fn verify_proof<T: FieldElement>(
+    pil_file: &Path,
+    artifact: &ProofArtifact,
+    setup_file: Option<&Path>,
+    publics_file: Option<&Path>,
+) -> Result<Publics<T>, String> {
+    check_proof(pil_file, artifact, setup_file, publics_file)
+        .map_err(|err| format!("Proof rejected: {err}"))
+}
+
+fn check_proof<T: FieldElement>(
+    pil_file: &Path,
+    artifact: &ProofArtifact,
+    setup_file: Option<&Path>,
+    publics_file: Option<&Path>,
+) -> Result<Publics<T>, String> {
+    if artifact.field != T::modulus() {
+        return Err(format!(
+            "The proof is over the field with modulus {}, but the PIL file is compiled with modulus {}.",
+            artifact.field,
+            T::modulus()
+        ));
+    }
+    let analyzed = analyze_pil::<T>(pil_file)?;
+    if artifact.fingerprint != analyzed.fingerprint() {
+        return Err(format!(
+            "The proof was created for a different PIL file (fingerprint {:016x}, expected {:016x}).",
+            artifact.fingerprint,
+            analyzed.fingerprint()
+        ));
+    }
+    let publics = Publics::from_named_values(
+        &analyzed,
+        artifact
+            .publics
+            .iter()
+            .map(|(name, value)| (name.clone(), T::from(value.clone())))
+            .collect(),
+    )?;
+    if let Some(publics_file) = publics_file {
+        let contents = fs::read_to_string(publics_file)
+            .map_err(|e| format!("Could not read {}: {e}", publics_file.display()))?;
+        let expected = Publics::read_json(&analyzed, &contents)?;
+        let mismatches = publics
+            .iter()
+            .zip(expected.iter())
+            .filter(|((_, value), (_, expected))| value != expected)
+            .map(|((name, value), (_, expected))| {
+                format!("\n    public {name} is {value} in the proof, expected {expected}")
+            })
+            .collect::<String>();
+        if !mismatches.is_empty() {
+            return Err(format!(
+                "The public values do not match {}:{mismatches}",
+                publics_file.display()
+            ));
+        }
+    }
+    let (constants, _) = constant_evaluator::generate(&analyzed)?;
+    match artifact.backend.parse::<BackendType>()? {
+        BackendType::Halo2 => verify_with_backend(
+            &Halo2Backend,
+            &analyzed,
+            &constants,
+            setup_file,
+            &publics,
+            &artifact.proof,
+        ),
+        BackendType::EStark => Err("eStark proofs are verified by pil-stark.".to_string()),
+        BackendType::Mock => Err("The mock backend does not create proofs.".to_string()),
+    }?;
+    for (name, value) in publics.iter() {
+        log::info!("Public {name} = {value}");
+    }
+    Ok(publics)
+}
+
+fn verify_with_backend<T: FieldElement, B: Backend<T>>(
+    backend: &B,
+    analyzed: &pil_analyzer::Analyzed<T>,
+    constants: &[(&str, Vec<T>)],
+    setup_file: Option<&Path>,
+    publics: &Publics<T>,
+    proof: &[u8],
+) -> Result<(), String> {
+    let lowered = lower_identities(analyzed, backend.capabilities())?;
+    let analyzed = lowered
+        .as_ref()
+        .map_or(analyzed, |lowered| &lowered.analyzed);
+    let proof = backend
+        .read_proof(&mut &proof[..])
+        .map_err(|e| format!("Invalid proof: {e}"))?;
+    let setup = match setup_file {
+        Some(path) => {
+            let file = fs::File::open(path)
+                .map_err(|e| format!("Could not open {}: {e}", path.display()))?;
+            backend
+                .read_setup(&mut BufReader::new(file))
+                .map_err(|e| format!("Invalid setup in {}: {e}", path.display()))?
+        }
+        None => backend
+            .setup(analyzed, constants)
+            .map_err(|e| format!("Setup failed: {e}"))?,
+    };
+    backend
+        .verify(&setup, analyzed, constants, &publics.values(), &proof)
+        .map_err(|e| format!("{} verification failed: {e}", backend.name()))
+}
+
+/// Reads the public values of a proof from `publics.json` in the output directory.
+pub fn read_publics<T: FieldElement>(
+    analyzed: &pil_analyzer::Analyzed<T>,
+    output: &OutputLayout,
+) -> Publics<T> {
+    let path = output.publics_file();
+    let contents = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Could not read {}: {e}", path.display()));
+    Publics::read_json(analyzed, &contents).unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Reads the fixed columns from the output directory if they were generated
+/// for the same PIL file and degree before, otherwise generates them and
+/// writes them there (if the constants artifact is selected). The fixed
+/// columns do not depend on the inputs, so they can be reused across runs,
+/// for example the ROM of a RISC-V program.
+fn load_or_generate_constants<'a, T: FieldElement>(
+    analyzed: &'a pil_analyzer::Analyzed<T>,
+    output: &OutputLayout,
+) -> (Vec<(&'a str, Vec<T>)>, DegreeType) {
+    let defined = analyzed
+        .constant_polys_in_source_order()
+        .into_iter()
+        .filter(|(_, value)| value.is_some())
+        .map(|(poly, _)| poly)
+        .collect::<Vec<_>>();
+    let generate = || {
+        let start = Instant::now();
+        log::info!("Evaluating fixed columns...");
+        let result = constant_evaluator::generate(analyzed).unwrap_or_else(|e| panic!("{e}"));
+        log::info!("Took {}", start.elapsed().as_secs_f32());
+        result
+    };
+    if defined.is_empty() || analyzed.constant_count() != defined.len() {
+        return generate();
+    }
+    let degree = defined[0].degree;
+    let name = format!("constants_{:016x}_{degree}.bin", analyzed.fingerprint());
+    let path = output.path(&name);
+    if let Ok(file) = fs::File::open(&path) {
+        let columns = defined
+            .iter()
+            .map(|poly| poly.absolute_name.as_str())
+            .collect::<Vec<_>>();
+        match read_polys_file(&mut BufReader::new(file), &columns) {
+            Ok(constants) if constants[0].1.len() as DegreeType == degree => {
+                log::info!("Using fixed columns from {name}.");
+                return (constants, degree);
+            }
+            Ok(_) => log::warn!("Ignoring {name}, it has the wrong degree."),
+            Err(err) => log::warn!("Ignoring {name}: {err}"),
+        }
+    }
+    let (constants, degree) = generate();
+    if output.writes(Artifact::Constants) {
+        let mut bytes = vec![];
+        write_polys_file(&mut bytes, degree, &constants);
+        write_artifact(&path, &bytes).unwrap();
+        log::info!("Wrote {name}.");
+    }
+    (constants, degree)
+}
+
+/// Reads the setup of the backend from the output directory if a setup with
+/// the same id was written there before, otherwise creates it and writes it
+/// there (if the proof artifact is selected).
+/// The file is named after what the setup depends on, see `Backend::setup_id`.
+/// For Halo2, this is only the number of rows, because the file only contains
+/// the commitment parameters but not the keys, see `halo2::Halo2Setup`.
+fn load_or_create_setup<T: FieldElement, B: Backend<T>>(
+    backend: &B,
+    analyzed: &pil_analyzer::Analyzed<T>,
+    constants: &[(&str, Vec<T>)],
+    output: &OutputLayout,
+) -> B::Setup {
+    let id = backend
+        .setup_id(analyzed, constants)
+        .unwrap_or_else(|err| panic!("Setup with {} failed: {err}", backend.name()));
+    let name = format!("{}_setup_{id}.bin", backend.name());
+    let path = output.path(&name);
+    if let Ok(file) = fs::File::open(&path) {
+        log::info!("Using setup from {name}.");
+        return backend
+            .read_setup(&mut BufReader::new(file))
+            .unwrap_or_else(|err| panic!("Could not read {name}: {err}"));
+    }
+    let setup = backend
+        .setup(analyzed, constants)
+        .unwrap_or_else(|err| panic!("Setup with {} failed: {err}", backend.name()));
+    if !output.writes(Artifact::Proof) {
+        return setup;
+    }
+    let mut bytes = vec![];
+    backend.write_setup(&setup, &mut bytes).unwrap();
+    write_artifact(&path, &bytes).unwrap();
+    log::info!("Wrote {name}.");
+    setup
+}
+
+/// Writes an output file. In reproducible mode, its modification time is
+/// set to the timestamp of the build, see `set_reproducible`.
+pub fn write_artifact(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut file = fs::File::create(path)?;
+    file.write_all(bytes)?;
+    if backend::is_reproducible() {
+        file.set_modified(backend::timestamp().unwrap_or_else(|e| panic!("{e}")))?;
+    }
+    Ok(())
+}
+
+/// Sets the modification time of a file that was written by an external
+/// tool in reproducible mode, see `write_artifact`.
+pub(crate) fn set_timestamp(path: &Path) {
+    if !backend::is_reproducible() {
+        return;
+    }
+    let time = backend::timestamp().unwrap_or_else(|e| panic!("{e}"));
+    fs::File::options()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(time))
+        .unwrap_or_else(|e| {
+            panic!(
+                "Could not set the modification time of {}: {e}",
+                path.display()
+            )
+        });
+}
+
+fn write_polys_file<T: FieldElement>(
     file: &mut impl Write,
     degree: DegreeType,
-    polys: &Vec<(&str, Vec<FieldElement>)>,
+    polys: &Vec<(&str, Vec<T>)>,
 ) {
     for i in 0..degree as usize {
         for (_name, constant) in polys {
             let bytes = constant[i].to_bytes_le();
-            assert_eq!(bytes.len(), 8);
+            assert_eq!(bytes.len(), T::byte_len());
             file.write_all(&bytes).unwrap();
         }
     }
 }
+
+/// Reads the values of the columns from a file written by `write_polys_file`,
+/// for example `commits.bin`. The degree is derived from the size of the file.
+/// @returns the values in the order of `columns`.
+pub fn read_polys_file<'a, T: FieldElement>(
+    file: &mut impl Read,
+    columns: &[&'a str],
+) -> Result<Vec<(&'a str, Vec<T>)>, String> {
+    let mut bytes = vec![];
+    file.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+    let row_len = columns.len() * T::byte_len();
+    if row_len == 0 || !bytes.len().is_multiple_of(row_len) {
+        return Err(format!(
+            "Expected a multiple of {row_len} bytes for {} columns, but found {} bytes.",
+            columns.len(),
+            bytes.len()
+        ));
+    }
+    let degree = bytes.len() / row_len;
+    let mut values = vec![Vec::with_capacity(degree); columns.len()];
+    for (i, element) in bytes.chunks(T::byte_len()).enumerate() {
+        values[i % columns.len()].push(T::from_bytes_le(element).map_err(|e| e.to_string())?);
+    }
+    Ok(columns.iter().copied().zip(values).collect())
+}
@@ -0,0 +1,217 @@
+//! Mutation testing of the constraints: Weakened variants of the identities
+//! are checked against witnesses that the identities are known to reject. A
+//! mutant that rejects them as well shows a constraint that none of the
+//! witnesses depends on, either because it is redundant or because the
+//! witnesses do not test it.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use executor::constant_evaluator;
+use number::FieldElement;
+use pil_analyzer::{ExpressionArena, Identity, IdentityKind};
+
+use crate::inspect::read_witness;
+use crate::{analyze_file, CompileOptions};
+
+/// A weakened variant of the identities and the witnesses it accepts.
+#[derive(Debug)]
+pub struct MutantOutcome {
+    pub mutation: String,
+    /// The directories of the bad witnesses that the mutant accepts. The
+    /// mutant is killed if there is one, otherwise it survived.
+    pub accepts: Vec<PathBuf>,
+}
+
+pub struct MutationReport {
+    pub mutants: Vec<MutantOutcome>,
+}
+
+impl MutationReport {
+    pub fn killed(&self) -> usize {
+        self.mutants
+            .iter()
+            .filter(|m| !m.accepts.is_empty())
+            .count()
+    }
+
+    pub fn survived(&self) -> impl Iterator<Item = &MutantOutcome> {
+        self.mutants.iter().filter(|m| m.accepts.is_empty())
+    }
+}
+
+impl fmt::Display for MutationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for mutant in &self.mutants {
+            if mutant.accepts.is_empty() {
+                writeln!(f, "survived: {}", mutant.mutation)?;
+            } else {
+                let dirs = mutant
+                    .accepts
+                    .iter()
+                    .map(|dir| dir.display().to_string())
+                    .collect::<Vec<_>>();
+                writeln!(
+                    f,
+                    "killed: {} (accepts {})",
+                    mutant.mutation,
+                    dirs.join(", ")
+                )?;
+            }
+        }
+        write!(
+            f,
+            "{} of {} mutants killed.",
+            self.killed(),
+            self.mutants.len()
+        )
+    }
+}
+
+/// Checks every weakened variant of the identities of an assembly or PIL
+/// file against the bad witnesses in `commits.bin` in each of `dirs`, with
+/// the fixed columns generated from the file. The variants remove one
+/// identity, remove one pair of expressions from a lookup or permutation or
+/// remove the selector of a lookup table.
+/// Fails if the identities accept one of the bad witnesses.
+pub fn mutation_test<T: FieldElement>(
+    file: &Path,
+    options: &CompileOptions,
+    dirs: &[PathBuf],
+) -> Result<MutationReport, String> {
+    let analyzed = analyze_file::<T>(file, options)?;
+    // The mutants replace the identities of a second copy, the columns
+    // borrow their names from the first one.
+    let mut mutant = analyze_file::<T>(file, options)?;
+    let (fixed, _) = constant_evaluator::generate(&analyzed)?;
+    let witnesses = dirs
+        .iter()
+        .map(|dir| read_witness(&analyzed, dir))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let accepted_by = |mutant: &pil_analyzer::Analyzed<T>| {
+        dirs.iter()
+            .zip(&witnesses)
+            .filter_map(
+                |(dir, witness)| match backend::check(mutant, &fixed, witness) {
+                    Ok(failures) if failures.is_empty() => Some(Ok(dir.clone())),
+                    Ok(_) => None,
+                    Err(e) => Some(Err(e.to_string())),
+                },
+            )
+            .collect::<Result<Vec<_>, String>>()
+    };
+    if let Some(dir) = accepted_by(&mutant)?.first() {
+        return Err(format!(
+            "The identities accept the witness in {}.",
+            dir.display()
+        ));
+    }
+
+    let identities = std::mem::take(&mut mutant.identities);
+    let mut mutants = vec![];
+    for (mutation, mutated) in mutations(&mutant.expressions, &identities) {
+        mutant.identities = mutated;
+        mutants.push(MutantOutcome {
+            mutation,
+            accepts: accepted_by(&mutant)?,
+        });
+    }
+    Ok(MutationReport { mutants })
+}
+
+/// The weakened variants of the identities, with a description.
+fn mutations<T: FieldElement>(
+    expressions: &ExpressionArena<T>,
+    identities: &[Identity<T>],
+) -> Vec<(String, Vec<Identity<T>>)> {
+    let mut mutations = vec![];
+    for (i, identity) in identities.iter().enumerate() {
+        let location = format!("{}:{}", identity.source.file, identity.source.line);
+        let mut mutated = identities.to_vec();
+        mutated.remove(i);
+        mutations.push((
+            format!("remove {location}: {}", expressions.display(identity)),
+            mutated,
+        ));
+
+        if matches!(
+            identity.kind,
+            IdentityKind::Plookup | IdentityKind::Permutation
+        ) && identity.left.expressions.len() > 1
+        {
+            for j in 0..identity.left.expressions.len() {
+                let mut mutated = identities.to_vec();
+                mutated[i].left.expressions.remove(j);
+                mutated[i].right.expressions.remove(j);
+                mutations.push((
+                    format!(
+                        "remove pair {j} of {location}: {}",
+                        expressions.display(&mutated[i])
+                    ),
+                    mutated,
+                ));
+            }
+        }
+        if identity.kind == IdentityKind::Plookup && identity.right.selector.is_some() {
+            let mut mutated = identities.to_vec();
+            mutated[i].right.selector = None;
+            mutations.push((
+                format!(
+                    "remove the table selector of {location}: {}",
+                    expressions.display(&mutated[i])
+                ),
+                mutated,
+            ));
+        }
+    }
+    mutations
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+    use pil_analyzer::analyze_string;
+
+    use super::*;
+
+    #[test]
+    fn weakened_identities() {
+        let pil = r#"
+namespace Main(4);
+    col fixed A = [0, 1, 2, 3];
+    col fixed B = [1, 1, 0, 0];
+    col witness x, y;
+    x * (x - 1) = 0;
+    { x, y } in B { A, A };
+"#;
+        let analyzed = analyze_string::<GoldilocksField>(pil);
+        let mutations = mutations(&analyzed.expressions, &analyzed.identities)
+            .into_iter()
+            .map(|(mutation, identities)| (mutation, identities.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            mutations,
+            [
+                ("remove input:6: (Main.x * (Main.x - 1)) = 0;".to_string(), 1),
+                (
+                    "remove input:7: { Main.x, Main.y } in Main.B { Main.A, Main.A };".to_string(),
+                    1
+                ),
+                (
+                    "remove pair 0 of input:7: { Main.y } in Main.B { Main.A };".to_string(),
+                    2
+                ),
+                (
+                    "remove pair 1 of input:7: { Main.x } in Main.B { Main.A };".to_string(),
+                    2
+                ),
+                (
+                    "remove the table selector of input:7: { Main.x, Main.y } in { Main.A, Main.A };"
+                        .to_string(),
+                    2
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Where the pipeline writes its artifacts, under which names, and which
+//! of them are written at all.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// A file (or group of files) the pipeline can write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Artifact {
+    /// The PIL file generated from an assembly file and its source map.
+    Pil,
+    /// The JSON export of the analyzed PIL file, as read by pilcom and pil-stark.
+    Json,
+    /// The values of the fixed columns, and their cache for later runs.
+    Constants,
+    /// The values of the witness columns.
+    Witness,
+    /// The proof artifact, the public values and the setup of the backend.
+    Proof,
+}
+
+impl Artifact {
+    pub const ALL: [Artifact; 5] = [
+        Artifact::Pil,
+        Artifact::Json,
+        Artifact::Constants,
+        Artifact::Witness,
+        Artifact::Proof,
+    ];
+}
+
+impl FromStr for Artifact {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pil" => Ok(Artifact::Pil),
+            "json" => Ok(Artifact::Json),
+            "constants" => Ok(Artifact::Constants),
+            "witness" => Ok(Artifact::Witness),
+            "proof" => Ok(Artifact::Proof),
+            _ => Err(format!(
+                "Unknown artifact {s}, expected pil, json, constants, witness or proof."
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Artifact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Artifact::Pil => write!(f, "pil"),
+            Artifact::Json => write!(f, "json"),
+            Artifact::Constants => write!(f, "constants"),
+            Artifact::Witness => write!(f, "witness"),
+            Artifact::Proof => write!(f, "proof"),
+        }
+    }
+}
+
+/// Parses a comma-separated list of artifacts, like `pil,witness`.
+pub fn parse_artifacts(list: &str) -> Result<BTreeSet<Artifact>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|artifact| !artifact.is_empty())
+        .map(Artifact::from_str)
+        .collect()
+}
+
+/// The output directory, the names of the files in it and the artifacts
+/// that are written there.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputLayout {
+    pub dir: PathBuf,
+    /// Prepended to the name of every file, for example to keep the outputs
+    /// of several runs in the same directory.
+    pub prefix: String,
+    pub artifacts: BTreeSet<Artifact>,
+}
+
+impl OutputLayout {
+    /// Writes all artifacts to `dir` with the default names.
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        OutputLayout {
+            dir: dir.as_ref().to_path_buf(),
+            prefix: String::new(),
+            artifacts: Artifact::ALL.into_iter().collect(),
+        }
+    }
+
+    pub fn writes(&self, artifact: Artifact) -> bool {
+        self.artifacts.contains(&artifact)
+    }
+
+    /// Returns the path of the file with the given name in the output directory.
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}{name}", self.prefix))
+    }
+
+    /// The JSON export of the PIL file with the given (file) name.
+    pub fn json_file(&self, pil_file_name: &str) -> PathBuf {
+        self.path(&format!("{pil_file_name}.json"))
+    }
+
+    pub fn constants_file(&self) -> PathBuf {
+        self.path("constants.bin")
+    }
+
+    pub fn witness_file(&self) -> PathBuf {
+        self.path("commits.bin")
+    }
+
+    pub fn publics_file(&self) -> PathBuf {
+        self.path("publics.json")
+    }
+
+    pub fn proof_file(&self) -> PathBuf {
+        self.path("proof_artifact.json")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names() {
+        let layout = OutputLayout {
+            dir: PathBuf::from("out"),
+            prefix: "run1_".to_string(),
+            artifacts: parse_artifacts("witness, pil").unwrap(),
+        };
+        assert_eq!(
+            layout.path("simple_sum.pil"),
+            Path::new("out/run1_simple_sum.pil")
+        );
+        assert_eq!(
+            layout.json_file("simple_sum.pil"),
+            Path::new("out/run1_simple_sum.pil.json")
+        );
+        assert_eq!(layout.witness_file(), Path::new("out/run1_commits.bin"));
+        assert!(layout.writes(Artifact::Pil) && layout.writes(Artifact::Witness));
+        assert!(!layout.writes(Artifact::Constants));
+        assert_eq!(
+            parse_artifacts("pil,binary").unwrap_err(),
+            "Unknown artifact binary, expected pil, json, constants, witness or proof."
+        );
+    }
+}
@@ -0,0 +1,28 @@
+//! Turning panics of witness generation into errors.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of calls of `catch_panic` that are running.
+static CATCHING: AtomicUsize = AtomicUsize::new(0);
+
+/// Witness generation panics if it fails, so we catch the panic and return
+/// its message. The panic hook is not changed, see `is_catching_panic`.
+pub(crate) fn catch_panic<R>(f: impl FnOnce() -> Result<R, String>) -> Result<R, String> {
+    CATCHING.fetch_add(1, Ordering::SeqCst);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.fetch_sub(1, Ordering::SeqCst);
+    result.unwrap_or_else(|payload| {
+        Err(payload
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_else(|| "Witness generation failed.".to_string()))
+    })
+}
+
+/// Returns true while a panic is turned into an error, for panic hooks
+/// that do not print those panics.
+pub fn is_catching_panic() -> bool {
+    CATCHING.load(Ordering::SeqCst) > 0
+}
@@ -0,0 +1,64 @@
+//! Running the tests declared in PIL files with `#[test]`.
+
+use std::path::Path;
+
+use backend::{Backend, MockBackend};
+use executor::constant_evaluator;
+use number::FieldElement;
+use pil_analyzer::PilTest;
+
+use crate::panics::catch_panic;
+use crate::{inputs_query_callback, CompileOptions};
+
+/// The result of running a test declared with `#[test]`.
+pub struct TestOutcome {
+    pub test: PilTest,
+    /// Why the witness could not be generated or violates the identities.
+    pub failure: Option<String>,
+}
+
+impl TestOutcome {
+    /// Whether the test failed if and only if it is declared with
+    /// `#[should_fail]`.
+    pub fn passed(&self) -> bool {
+        self.failure.is_some() == self.test.should_fail
+    }
+}
+
+/// Runs the tests of the PIL file whose names contain `filter`: Generates
+/// the fixed columns and the witness at the degree of the test and checks
+/// the identities with the mock backend. Fails if the PIL file is invalid.
+pub fn run_tests<T: FieldElement>(
+    file: &Path,
+    options: &CompileOptions,
+    filter: &str,
+) -> Result<Vec<TestOutcome>, String> {
+    Ok(pil_analyzer::tests::<T>(file, options.defines.clone())
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|test| test.name.contains(filter))
+        .map(|test| {
+            let failure = run_test::<T>(file, options, &test).err();
+            TestOutcome { test, failure }
+        })
+        .collect())
+}
+
+fn run_test<T: FieldElement>(
+    file: &Path,
+    options: &CompileOptions,
+    test: &PilTest,
+) -> Result<(), String> {
+    catch_panic(|| {
+        let analyzed = pil_analyzer::analyze_test::<T>(file, options.defines.clone(), test)
+            .map_err(|e| e.to_string())?;
+        let (fixed, degree) = constant_evaluator::generate(&analyzed)?;
+        let witness = executor::witgen::generate(
+            &analyzed,
+            degree,
+            &fixed,
+            Some(inputs_query_callback(vec![], 0)),
+        );
+        Backend::prove(&MockBackend, &(), &analyzed, &fixed, &witness).map_err(|e| e.to_string())
+    })
+}
@@ -0,0 +1,374 @@
+//! The project configuration file `powdr.toml`, which declares how a project
+//! is built and proven, so that the setup can be committed next to the sources:
+//!
+//! ```toml
+//! file = "main.asm"
+//! field = "gl"
+//! degree = 1024
+//! backend = "halo2"
+//! coprocessors = []
+//! output_directory = "build"
+//!
+//! [inputs]
+//! values = [3, 2, 1]
+//! file = "inputs.txt"
+//! env = "MAIN_INPUTS"
+//! ```
+//!
+//! All keys are optional. Relative paths are relative to the directory of the
+//! configuration file. The prover inputs are the values, followed by the
+//! numbers in the file and the numbers in the environment variable, separated
+//! by commas or whitespace.
+//!
+//! Only the part of TOML needed for this is supported: tables, strings,
+//! integers and arrays.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use number::{DegreeType, FieldElement};
+
+use crate::{BackendType, FieldType};
+
+/// The name of the configuration file in the project directory.
+pub const PROJECT_FILE: &str = "powdr.toml";
+
+/// The coprocessors that can be enabled in the configuration.
+const COPROCESSORS: [&str; 0] = [];
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProjectConfig {
+    /// The directory of the configuration file.
+    pub dir: PathBuf,
+    /// The assembly or PIL file to prove.
+    pub file: Option<PathBuf>,
+    pub field: Option<FieldType>,
+    /// The degree the PIL file is expected to have.
+    pub degree: Option<DegreeType>,
+    pub backend: Option<BackendType>,
+    pub coprocessors: Vec<String>,
+    pub output_directory: Option<PathBuf>,
+    pub inputs: Vec<InputSource>,
+}
+
+/// Where prover inputs come from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InputSource {
+    Values(Vec<u64>),
+    File(PathBuf),
+    /// The name of an environment variable.
+    Env(String),
+}
+
+impl ProjectConfig {
+    /// Reads the configuration from the given file.
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        Self::parse(&contents, dir).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Parses the configuration, relative paths are relative to `dir`.
+    pub fn parse(contents: &str, dir: PathBuf) -> Result<Self, String> {
+        let mut config = ProjectConfig {
+            dir,
+            ..Default::default()
+        };
+        let mut values = None;
+        let mut input_file = None;
+        let mut env = None;
+        for (table, key, value) in parse_toml(contents)? {
+            match (table.as_str(), key.as_str()) {
+                ("", "file") => config.file = Some(config.dir.join(value.string(&key)?)),
+                ("", "field") => config.field = Some(value.string(&key)?.parse()?),
+                ("", "degree") => config.degree = Some(value.integer(&key)?),
+                ("", "backend") => config.backend = Some(value.string(&key)?.parse()?),
+                ("", "coprocessors") => {
+                    config.coprocessors = value
+                        .array(&key)?
+                        .iter()
+                        .map(|item| item.string(&key).map(str::to_string))
+                        .collect::<Result<_, _>>()?
+                }
+                ("", "output_directory") => {
+                    config.output_directory = Some(config.dir.join(value.string(&key)?))
+                }
+                ("inputs", "values") => {
+                    values = Some(
+                        value
+                            .array(&key)?
+                            .iter()
+                            .map(|item| item.integer(&key))
+                            .collect::<Result<_, _>>()?,
+                    )
+                }
+                ("inputs", "file") => input_file = Some(config.dir.join(value.string(&key)?)),
+                ("inputs", "env") => env = Some(value.string(&key)?.to_string()),
+                _ if table.is_empty() => return Err(format!("Unknown key {key}.")),
+                _ => return Err(format!("Unknown key {key} in [{table}].")),
+            }
+        }
+        config.inputs = values
+            .map(InputSource::Values)
+            .into_iter()
+            .chain(input_file.map(InputSource::File))
+            .chain(env.map(InputSource::Env))
+            .collect();
+        if let Some(unknown) = config
+            .coprocessors
+            .iter()
+            .find(|name| !COPROCESSORS.contains(&name.as_str()))
+        {
+            return Err(format!("Unknown coprocessor {unknown}."));
+        }
+        Ok(config)
+    }
+
+    /// Returns the prover inputs from all sources, in order.
+    pub fn inputs<T: FieldElement>(&self) -> Result<Vec<T>, String> {
+        let mut inputs = vec![];
+        for source in &self.inputs {
+            let (name, list) = match source {
+                InputSource::Values(values) => {
+                    inputs.extend(values.iter().map(|v| T::from(*v)));
+                    continue;
+                }
+                InputSource::File(path) => (
+                    path.display().to_string(),
+                    fs::read_to_string(path)
+                        .map_err(|e| format!("Could not read {}: {e}", path.display()))?,
+                ),
+                InputSource::Env(var) => (
+                    var.clone(),
+                    std::env::var(var)
+                        .map_err(|_| format!("The environment variable {var} is not set."))?,
+                ),
+            };
+            for item in list
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|item| !item.is_empty())
+            {
+                let value = item
+                    .parse::<u64>()
+                    .map_err(|_| format!("Invalid input {item} in {name}."))?;
+                inputs.push(value.into());
+            }
+        }
+        Ok(inputs)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Value {
+    String(String),
+    Integer(u64),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn string(&self, key: &str) -> Result<&str, String> {
+        match self {
+            Value::String(s) => Ok(s),
+            _ => Err(format!("Expected a string for {key}.")),
+        }
+    }
+
+    fn integer(&self, key: &str) -> Result<u64, String> {
+        match self {
+            Value::Integer(n) => Ok(*n),
+            _ => Err(format!("Expected a non-negative integer for {key}.")),
+        }
+    }
+
+    fn array(&self, key: &str) -> Result<&[Value], String> {
+        match self {
+            Value::Array(items) => Ok(items),
+            _ => Err(format!("Expected an array for {key}.")),
+        }
+    }
+}
+
+/// Returns the table, the key and the value of every assignment.
+fn parse_toml(contents: &str) -> Result<Vec<(String, String, Value)>, String> {
+    let mut assignments = vec![];
+    let mut table = String::new();
+    let mut lines = contents.lines().enumerate();
+    while let Some((index, line)) = lines.next() {
+        let error = |e: String| format!("Line {}: {e}", index + 1);
+        let mut line = strip_comment(line).trim().to_string();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            table = name.trim().to_string();
+            continue;
+        }
+        let (key, _) = line
+            .split_once('=')
+            .ok_or_else(|| error(format!("Expected key = value: {line}")))?;
+        let key = key.trim().to_string();
+        // Arrays can span several lines.
+        while unclosed_brackets(&line) > 0 {
+            let (_, next) = lines
+                .next()
+                .ok_or_else(|| error("Unclosed array.".to_string()))?;
+            line = format!("{line} {}", strip_comment(next).trim());
+        }
+        let value = line.split_once('=').unwrap().1.trim();
+        let (value, rest) = parse_value(value).map_err(error)?;
+        if !rest.trim().is_empty() {
+            return Err(error(format!("Unexpected {}", rest.trim())));
+        }
+        assignments.push((table.clone(), key, value));
+    }
+    Ok(assignments)
+}
+
+/// Removes a comment that is not inside a string.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn unclosed_brackets(line: &str) -> i32 {
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut depth = 0;
+    for c in line.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '[' if !in_string => depth += 1,
+            ']' if !in_string => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
+
+/// Parses a value at the start of `input` and returns it and the rest.
+fn parse_value(input: &str) -> Result<(Value, &str), String> {
+    let input = input.trim_start();
+    if let Some(rest) = input.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Ok((Value::String(value), &rest[i + 1..])),
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, 't')) => value.push('\t'),
+                    Some((_, c @ ('"' | '\\'))) => value.push(c),
+                    _ => return Err("Invalid escape sequence.".to_string()),
+                },
+                c => value.push(c),
+            }
+        }
+        Err("Unclosed string.".to_string())
+    } else if let Some(mut rest) = input.strip_prefix('[') {
+        let mut items = vec![];
+        loop {
+            rest = rest.trim_start();
+            if let Some(rest) = rest.strip_prefix(']') {
+                return Ok((Value::Array(items), rest));
+            }
+            let (item, after) = parse_value(rest)?;
+            items.push(item);
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after;
+            } else if !rest.starts_with(']') {
+                return Err("Expected , or ] in array.".to_string());
+            }
+        }
+    } else {
+        let end = input
+            .find(|c: char| c == ',' || c == ']' || c.is_whitespace())
+            .unwrap_or(input.len());
+        let (token, rest) = input.split_at(end);
+        let value = token
+            .replace('_', "")
+            .parse()
+            .map_err(|_| format!("Invalid value {token}."))?;
+        Ok((Value::Integer(value), rest))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let config = ProjectConfig::parse(
+            r#"
+# The main machine.
+file = "src/main.asm"
+field = "bb" # BabyBear
+degree = 1_024
+backend = "mock"
+coprocessors = []
+output_directory = "build"
+
+[inputs]
+values = [
+    3, 2, # first
+    1,
+]
+env = "POWDR_TEST_PROJECT_INPUTS"
+"#,
+            PathBuf::from("project"),
+        )
+        .unwrap();
+        assert_eq!(config.file, Some(PathBuf::from("project/src/main.asm")));
+        assert_eq!(config.field, Some(FieldType::BabyBear));
+        assert_eq!(config.degree, Some(1024));
+        assert_eq!(config.backend, Some(BackendType::Mock));
+        assert_eq!(
+            config.output_directory,
+            Some(PathBuf::from("project/build"))
+        );
+        std::env::set_var("POWDR_TEST_PROJECT_INPUTS", "7, 8 9");
+        assert_eq!(
+            config.inputs::<GoldilocksField>().unwrap(),
+            [3, 2, 1, 7, 8, 9].map(GoldilocksField::from)
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let error = |contents: &str| ProjectConfig::parse(contents, PathBuf::new()).unwrap_err();
+        assert_eq!(
+            error("degree = \"large\""),
+            "Expected a non-negative integer for degree."
+        );
+        assert_eq!(
+            error("[inputs]\nfiles = \"a\""),
+            "Unknown key files in [inputs]."
+        );
+        assert_eq!(
+            error("backend = \"plonky\""),
+            "Unknown backend plonky, expected halo2, estark or mock."
+        );
+        assert_eq!(
+            error("coprocessors = [\"keccak\"]"),
+            "Unknown coprocessor keccak."
+        );
+        assert_eq!(error("file = \"main.asm"), "Line 1: Unclosed string.");
+        assert_eq!(error("values = [1, 2"), "Line 1: Unclosed array.");
+    }
+}
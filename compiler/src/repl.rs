@@ -0,0 +1,297 @@
+//! An interactive session on an analyzed PIL file, to evaluate expressions
+//! over the fixed columns, look up definitions and expand macros.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use executor::constant_evaluator;
+use number::{DegreeType, FieldElement};
+use parser::ast;
+use pil_analyzer::pil_analyzer::ExpressionContext;
+use pil_analyzer::{Analyzed, FunctionValueDefinition, PolynomialType, Symbol};
+
+use crate::inspect::parse_rows;
+
+const HELP: &str = "\
+<expr>                evaluate the expression at the current row
+:at <rows> <expr>     evaluate the expression at a row (5) or rows (0..8)
+:row <row>            set the current row
+:def <name>           show the definition of a column, constant or macro
+:expand <expr>        show the expression and identities a macro call expands to
+:namespace <name>     set the namespace names are resolved in
+:help                 show this help
+:quit                 leave the REPL";
+
+pub struct Repl<'a, T> {
+    analyzed: &'a Analyzed<T>,
+    context: ExpressionContext<T>,
+    fixed: HashMap<&'a str, Vec<T>>,
+    degree: DegreeType,
+    row: DegreeType,
+}
+
+impl<'a, T: FieldElement> Repl<'a, T> {
+    /// Creates a session on the analyzed file and the analyzer state at its
+    /// end, see `pil_analyzer::analyze_with_context`, and generates the fixed columns.
+    pub fn new(analyzed: &'a Analyzed<T>, context: ExpressionContext<T>) -> Result<Self, String> {
+        let (fixed, degree) = constant_evaluator::generate(analyzed)?;
+        Ok(Repl {
+            analyzed,
+            context,
+            fixed: fixed.into_iter().collect(),
+            degree,
+            row: 0,
+        })
+    }
+
+    /// Executes one line of input and returns the output.
+    /// Errors in the command or in the analysis of the expression are returned as `Err`.
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let line = line.trim();
+        let (command, argument) = match line.strip_prefix(':') {
+            Some(command) => command
+                .split_once(char::is_whitespace)
+                .map(|(command, argument)| (command, argument.trim()))
+                .unwrap_or((command, "")),
+            None => return self.evaluate(line, self.row..self.row + 1),
+        };
+        match command {
+            "at" => {
+                let (rows, expr) = argument
+                    .split_once(char::is_whitespace)
+                    .ok_or("Expected rows and an expression.")?;
+                let rows = parse_rows(rows)?;
+                self.evaluate(expr, rows)
+            }
+            "row" => {
+                self.row = argument
+                    .parse()
+                    .map_err(|_| format!("Invalid row: {argument}"))?;
+                Ok(format!("Current row is {}.", self.row))
+            }
+            "def" => self.definition(argument),
+            "expand" => self.expand(argument),
+            "namespace" => {
+                self.context.set_namespace(argument);
+                Ok(format!("Current namespace is {argument}."))
+            }
+            "help" => Ok(HELP.to_string()),
+            _ => Err(format!("Unknown command :{command}, see :help.")),
+        }
+    }
+
+    fn evaluate(&mut self, expr: &str, rows: Range<DegreeType>) -> Result<String, String> {
+        let (expr, identities) = self
+            .context
+            .process_expression(&parse_expression(expr)?)
+            .map_err(|e| e.message().to_string())?;
+        if !identities.is_empty() {
+            return Err("The expression creates identities, use :expand to see them.".into());
+        }
+        if self.degree > 0 && rows.end > self.degree {
+            return Err(format!(
+                "Row {} is out of range, the degree is {}.",
+                rows.end - 1,
+                self.degree
+            ));
+        }
+        let values = rows
+            .clone()
+            .map(|row| {
+                constant_evaluator::evaluate_at_row(
+                    self.analyzed,
+                    self.context.expressions(),
+                    &self.fixed,
+                    &expr,
+                    row,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(if rows.end - rows.start == 1 {
+            values[0].to_string()
+        } else {
+            rows.zip(values)
+                .map(|(row, value)| format!("{row}: {value}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+    }
+
+    fn definition(&self, name: &str) -> Result<String, String> {
+        if name.starts_with('%') {
+            return self
+                .analyzed
+                .constants
+                .get(name)
+                .map(|value| format!("constant {name} = {value};"))
+                .ok_or_else(|| format!("Constant {name} not found."));
+        }
+        if let Some(definition) = self.context.macro_definition(name) {
+            let statement = ast::Statement::MacroDefinition(
+                0,
+                name.to_string(),
+                definition.parameters.clone(),
+                definition.identities.clone(),
+                definition.expression.clone(),
+            );
+            return Ok(format!("{statement} // {}", source(&definition.source)));
+        }
+        let name = self.context.namespaced(name);
+        let (poly, definition) = Symbol::lookup(&name)
+            .and_then(|name| self.analyzed.definitions.get(&name))
+            .ok_or_else(|| format!("{name} not found."))?;
+        let definition = match (poly.poly_type, definition) {
+            (PolynomialType::Intermediate, Some(FunctionValueDefinition::Mapping(e))) => {
+                format!(" = {}", self.analyzed.expressions.display(e))
+            }
+            (_, Some(definition)) => self.analyzed.expressions.display(definition).to_string(),
+            (_, None) => String::new(),
+        };
+        let kind = match poly.poly_type {
+            PolynomialType::Committed => "witness ",
+            PolynomialType::Constant => "fixed ",
+            PolynomialType::Intermediate => "",
+        };
+        Ok(format!(
+            "col {kind}{name}{definition}; // {}",
+            source(&poly.source)
+        ))
+    }
+
+    fn expand(&mut self, expr: &str) -> Result<String, String> {
+        let (expr, identities) = match parse_expression(expr)? {
+            ast::Expression::FunctionCall(name, arguments)
+                if self.context.macro_definition(&name).is_some() =>
+            {
+                self.context.expand_macro(&name, &arguments)
+            }
+            expr => self
+                .context
+                .process_expression(&expr)
+                .map(|(expr, identities)| (Some(expr), identities)),
+        }
+        .map_err(|e| e.message().to_string())?;
+        let expressions = self.context.expressions();
+        Ok(identities
+            .iter()
+            .map(|identity| expressions.display(identity).to_string())
+            .chain(expr.map(|expr| expressions.display(&expr).to_string()))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+/// Runs a session on the PIL file, reading commands from `input`
+/// until it ends or `:quit` is entered. Fails if the PIL file is invalid or
+/// the fixed columns cannot be generated.
+pub fn run<T: FieldElement>(
+    file: &Path,
+    input: impl BufRead,
+    output: &mut impl Write,
+) -> Result<(), String> {
+    let (analyzed, context) =
+        pil_analyzer::analyze_with_context::<T>(file).map_err(|e| e.to_string())?;
+    let mut repl = Repl::new(&analyzed, context)?;
+    writeln!(output, "Loaded {}, type :help for help.", file.display()).unwrap();
+    write!(output, "> ").unwrap();
+    output.flush().unwrap();
+    for line in input.lines() {
+        let line = line.unwrap();
+        match line.trim() {
+            ":quit" | ":q" => break,
+            "" => {}
+            line => match repl.execute(line) {
+                Ok(result) => writeln!(output, "{result}").unwrap(),
+                Err(e) => writeln!(output, "Error: {e}").unwrap(),
+            },
+        }
+        write!(output, "> ").unwrap();
+        output.flush().unwrap();
+    }
+    Ok(())
+}
+
+fn parse_expression<T: FieldElement>(input: &str) -> Result<ast::Expression<T>, String> {
+    parser::parse_expression(None, input).map_err(|e| e.message().to_string())
+}
+
+fn source(source: &pil_analyzer::SourceRef) -> String {
+    format!("{}:{}", source.file, source.line)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use number::GoldilocksField;
+
+    use super::*;
+
+    fn run_commands(pil: &str, commands: &str) -> String {
+        let dir = mktemp::Temp::new_dir().unwrap();
+        let file = dir.join("input.pil");
+        fs::write(&file, pil).unwrap();
+        let mut output = vec![];
+        run::<GoldilocksField>(&file, commands.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        // Skip the line with the (temporary) file name.
+        output.split_once('\n').unwrap().1.to_string()
+    }
+
+    const PIL: &str = r#"constant %N = 8;
+namespace Main(%N);
+    macro is_zero(x) { x = 0; };
+    macro double(x) { 2 * x };
+    col fixed ROW(i) { i };
+    col fixed ODD(i) { i % 2 };
+    col witness w;
+    col twice = double(ROW);
+    is_zero(w * ODD);
+"#;
+
+    #[test]
+    fn evaluate() {
+        let output = run_commands(
+            PIL,
+            "ROW + 1\n:row 3\ntwice + ODD'\n:at 6..8 ROW'\n:at 1 w\n",
+        );
+        assert_eq!(
+            output,
+            "> 1\n> Current row is 3.\n> 6\n> 6: 7\n7: 0\n> Error: Main.w is not a fixed or intermediate column.\n> "
+        );
+    }
+
+    #[test]
+    fn definitions_and_macros() {
+        let output = run_commands(
+            PIL,
+            ":def twice\n:def %N\n:def double\n:expand is_zero(ROW - 1)\n:expand double(ODD) + 1\n:quit\nROW\n",
+        );
+        assert_eq!(
+            output,
+            "> col Main.twice = (2 * Main.ROW); // input.pil:8\n\
+            > constant %N = 8;\n\
+            > macro double(x) { (2 * x) }; // input.pil:4\n\
+            > (Main.ROW - 1) = 0;\n\
+            > ((2 * Main.ODD) + 1)\n\
+            > "
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let output = run_commands(PIL, "ROW +\n:at 8 ROW\n:frobnicate\nUNKNOWN\n");
+        assert!(output.starts_with("> Error: Unrecognized EOF"), "{output}");
+        assert!(
+            output.ends_with(
+                "> Error: Row 8 is out of range, the degree is 8.\n\
+                > Error: Unknown command :frobnicate, see :help.\n\
+                > Error: Column Main.UNKNOWN not found.\n\
+                > "
+            ),
+            "{output}"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! Records the responses to the prover queries of witness generation and
+//! runs witness generation again from the recording, see
+//! `executor::witgen::QueryLog`.
+
+use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+
+use executor::constant_evaluator;
+use executor::witgen::{recording_callback, replay_callback, QueryLog};
+use number::FieldElement;
+
+use crate::panics::catch_panic;
+use crate::{
+    analyze_file, inputs_query_callback, write_artifact, write_polys_file, CompileOptions,
+};
+
+/// Generates the witness of an assembly or PIL file for the inputs and
+/// writes the queries and their responses to `replay_file`. The file is
+/// also written if witness generation fails, to reproduce the failure.
+pub fn record_queries<T: FieldElement>(
+    file: &Path,
+    options: &CompileOptions,
+    inputs: Vec<T>,
+    replay_file: &Path,
+) -> Result<(), String> {
+    let analyzed = analyze_file::<T>(file, options)?;
+    let (fixed, degree) = constant_evaluator::generate(&analyzed)?;
+    let log = RefCell::new(QueryLog::default());
+    let result = catch_panic(|| {
+        executor::witgen::generate(
+            &analyzed,
+            degree,
+            &fixed,
+            Some(recording_callback(inputs_query_callback(inputs, 0), &log)),
+        );
+        Ok(())
+    });
+    let log = log.into_inner();
+    fs::write(replay_file, log.to_string())
+        .map_err(|e| format!("Could not write {}: {e}", replay_file.display()))?;
+    log::info!(
+        "Wrote {} query responses to {}.",
+        log.responses.len(),
+        replay_file.display()
+    );
+    result.map_err(|e| format!("Witness generation failed: {e}"))
+}
+
+/// Generates the witness of an assembly or PIL file with the query responses
+/// of `replay_file` instead of inputs, and writes it to `commits.bin` in
+/// `output_dir` if given.
+pub fn replay_queries<T: FieldElement>(
+    file: &Path,
+    options: &CompileOptions,
+    replay_file: &Path,
+    output_dir: Option<&Path>,
+) -> Result<(), String> {
+    let contents = fs::read_to_string(replay_file)
+        .map_err(|e| format!("Could not read {}: {e}", replay_file.display()))?;
+    let log = QueryLog::<T>::parse(&contents)
+        .map_err(|e| format!("Invalid {}: {e}", replay_file.display()))?;
+    let analyzed = analyze_file::<T>(file, options)?;
+    let (fixed, degree) = constant_evaluator::generate(&analyzed)?;
+    let commits = catch_panic(|| {
+        Ok(executor::witgen::generate(
+            &analyzed,
+            degree,
+            &fixed,
+            Some(replay_callback(log)),
+        ))
+    })
+    .map_err(|e| format!("Witness generation failed: {e}"))?;
+    if let Some(output_dir) = output_dir {
+        let witness_file = output_dir.join("commits.bin");
+        let mut bytes = vec![];
+        write_polys_file(&mut bytes, degree, &commits);
+        write_artifact(&witness_file, &bytes)
+            .map_err(|e| format!("Could not write {}: {e}", witness_file.display()))?;
+        log::info!("Wrote {}.", witness_file.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+
+    use super::*;
+
+    #[test]
+    fn record_and_replay() {
+        let dir = mktemp::Temp::new_dir().unwrap();
+        let source = dir.join("sum.pil");
+        fs::write(
+            &source,
+            r#"
+namespace Main(4);
+    col witness input(i) query ("input", i);
+    col witness sum;
+    col fixed FIRST = [1, 0, 0, 0];
+    col fixed LAST = [0, 0, 0, 1];
+    (1 - LAST) * (sum' - sum - input') = 0;
+    FIRST * (sum - input) = 0;
+"#,
+        )
+        .unwrap();
+        let replay_file = dir.join("queries.txt");
+        let inputs = [1, 2, 3, 4].map(GoldilocksField::from).to_vec();
+        record_queries(&source, &Default::default(), inputs, &replay_file).unwrap();
+        replay_queries::<GoldilocksField>(&source, &Default::default(), &replay_file, Some(&dir))
+            .unwrap();
+        let witness = fs::read(dir.join("commits.bin")).unwrap();
+        let sums = witness
+            .chunks(8)
+            .skip(1)
+            .step_by(2)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(sums, [1, 3, 6, 10]);
+
+        // Without the responses, the queries cannot be answered.
+        let log = fs::read_to_string(&replay_file).unwrap();
+        let truncated = log.lines().take(3).collect::<Vec<_>>().join("\n");
+        fs::write(&replay_file, truncated).unwrap();
+        let error =
+            replay_queries::<GoldilocksField>(&source, &Default::default(), &replay_file, None)
+                .unwrap_err();
+        assert!(error.contains("is not in the recording"), "{error}");
+    }
+}
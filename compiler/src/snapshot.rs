@@ -0,0 +1,216 @@
+//! Snapshots of generated witnesses, to check that changes to the witness
+//! generation or the frontends do not change the traces.
+//!
+//! The snapshot is a text file with a header line and one line per column:
+//! the name of the column and its values, where a value repeated on
+//! consecutive rows is written once with the number of repetitions:
+//! `Main.pc: 0 1 2 3*1021`.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use number::{AbstractNumberType, FieldElement};
+
+use crate::inspect::read_witness;
+
+const HEADER: &str = "# powdr witness snapshot, modulus ";
+
+/// The differences listed by `WitnessSnapshot::diff`, the others are counted.
+const LISTED_DIFFERENCES: usize = 100;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct WitnessSnapshot<T> {
+    pub columns: Vec<(String, Vec<T>)>,
+}
+
+impl<T: FieldElement> WitnessSnapshot<T> {
+    pub fn new(columns: &[(&str, Vec<T>)]) -> Self {
+        WitnessSnapshot {
+            columns: columns
+                .iter()
+                .map(|(name, values)| (name.to_string(), values.clone()))
+                .collect(),
+        }
+    }
+
+    /// Reads a snapshot in the format written by `Display`.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut lines = input.lines();
+        let modulus = lines
+            .next()
+            .and_then(|line| line.strip_prefix(HEADER))
+            .ok_or_else(|| "Not a witness snapshot.".to_string())?;
+        if modulus != T::modulus().to_string() {
+            return Err(format!(
+                "The snapshot is in the field with modulus {modulus}, expected {}.",
+                T::modulus()
+            ));
+        }
+        let columns = lines
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                let error = |message: &str| format!("Line {}: {message}", i + 2);
+                let (name, values) = line
+                    .split_once(':')
+                    .ok_or_else(|| error("Expected a column name followed by ':'."))?;
+                let mut column = vec![];
+                for value in values.split_whitespace() {
+                    let (value, repetitions) = value.split_once('*').unwrap_or((value, "1"));
+                    let value = value
+                        .parse::<AbstractNumberType>()
+                        .map_err(|_| error(&format!("Invalid value {value}.")))?;
+                    let repetitions = repetitions
+                        .parse::<usize>()
+                        .map_err(|_| error(&format!("Invalid repetitions {repetitions}.")))?;
+                    column.extend(std::iter::repeat_n(T::from(value), repetitions));
+                }
+                Ok((name.trim().to_string(), column))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(WitnessSnapshot { columns })
+    }
+
+    /// Returns the differences of `actual` from the snapshot: missing and
+    /// additional columns, columns with a different number of rows and the
+    /// cells with different values.
+    pub fn diff(&self, actual: &WitnessSnapshot<T>) -> Vec<String> {
+        let mut differences = vec![];
+        for (name, _) in &self.columns {
+            if !actual.columns.iter().any(|(n, _)| n == name) {
+                differences.push(format!("Column {name} is missing."));
+            }
+        }
+        for (name, values) in &actual.columns {
+            let Some((_, expected)) = self.columns.iter().find(|(n, _)| n == name) else {
+                differences.push(format!("Column {name} is not in the snapshot."));
+                continue;
+            };
+            if expected.len() != values.len() {
+                differences.push(format!(
+                    "Column {name} has {} rows, expected {}.",
+                    values.len(),
+                    expected.len()
+                ));
+                continue;
+            }
+            differences.extend(
+                expected
+                    .iter()
+                    .zip(values)
+                    .enumerate()
+                    .filter(|(_, (expected, value))| expected != value)
+                    .map(|(row, (expected, value))| {
+                        format!("{name} row {row}: {value}, expected {expected}")
+                    }),
+            );
+        }
+        if differences.len() > LISTED_DIFFERENCES {
+            let more = differences.len() - LISTED_DIFFERENCES;
+            differences.truncate(LISTED_DIFFERENCES);
+            differences.push(format!("... and {more} more differences."));
+        }
+        differences
+    }
+}
+
+impl<T: FieldElement> fmt::Display for WitnessSnapshot<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{HEADER}{}", T::modulus())?;
+        for (name, values) in &self.columns {
+            write!(f, "{name}:")?;
+            let mut values = values.iter().peekable();
+            while let Some(value) = values.next() {
+                let mut repetitions = 1;
+                while values.next_if_eq(&value).is_some() {
+                    repetitions += 1;
+                }
+                write!(f, " {}", value.to_integer())?;
+                if repetitions > 1 {
+                    write!(f, "*{repetitions}")?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares the witness of the PIL file in `commits.bin` in `dir` with the
+/// snapshot file, or writes the snapshot file if it does not exist or
+/// `update` is set.
+/// @returns the differences from the snapshot, see `WitnessSnapshot::diff`.
+pub fn snapshot_witness<T: FieldElement>(
+    pil_file: &Path,
+    dir: &Path,
+    snapshot_file: &Path,
+    update: bool,
+) -> Result<Vec<String>, String> {
+    let analyzed = crate::analyze_pil::<T>(pil_file)?;
+    let actual = WitnessSnapshot::new(&read_witness(&analyzed, dir)?);
+    if update || !snapshot_file.exists() {
+        fs::write(snapshot_file, actual.to_string())
+            .map_err(|e| format!("Could not write {}: {e}", snapshot_file.display()))?;
+        log::info!("Wrote {}.", snapshot_file.display());
+        return Ok(vec![]);
+    }
+    let contents = fs::read_to_string(snapshot_file)
+        .map_err(|e| format!("Could not read {}: {e}", snapshot_file.display()))?;
+    let expected = WitnessSnapshot::parse(&contents)
+        .map_err(|e| format!("Invalid {}: {e}", snapshot_file.display()))?;
+    Ok(expected.diff(&actual))
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+
+    use super::*;
+
+    fn snapshot(columns: &[(&str, &[i64])]) -> WitnessSnapshot<GoldilocksField> {
+        WitnessSnapshot::new(
+            &columns
+                .iter()
+                .map(|(name, values)| {
+                    let values = values.iter().map(|v| GoldilocksField::from_i64(*v));
+                    (*name, values.collect())
+                })
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn write_and_parse() {
+        let witness = snapshot(&[
+            ("Main.pc", &[0, 1, 2, 2, 2]),
+            ("Main.x", &[-1, -1, 0, 7, 7]),
+        ]);
+        let text = witness.to_string();
+        assert_eq!(
+            text,
+            "# powdr witness snapshot, modulus 18446744069414584321
+Main.pc: 0 1 2*3
+Main.x: 18446744069414584320*2 0 7*2
+"
+        );
+        assert_eq!(WitnessSnapshot::parse(&text).unwrap(), witness);
+        assert!(WitnessSnapshot::<GoldilocksField>::parse("Main.pc: 1").is_err());
+    }
+
+    #[test]
+    fn differences() {
+        let expected = snapshot(&[("A", &[1, 2, 3]), ("B", &[0]), ("C", &[0, 0])]);
+        let actual = snapshot(&[("A", &[1, 5, 3]), ("C", &[0]), ("D", &[1])]);
+        assert_eq!(
+            expected.diff(&actual),
+            [
+                "Column B is missing.",
+                "A row 1: 5, expected 2",
+                "Column C has 1 rows, expected 2.",
+                "Column D is not in the snapshot."
+            ]
+        );
+        assert!(expected.diff(&expected).is_empty());
+    }
+}
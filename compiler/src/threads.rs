@@ -0,0 +1,194 @@
+//! The number of threads the parallel phases of the pipeline use.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+/// A phase of the pipeline that can run on several threads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    /// Evaluation of the fixed columns.
+    Fixed,
+    /// Witness generation.
+    Witgen,
+    /// Setup and proof creation in the backend.
+    Backend,
+}
+
+impl FromStr for Phase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fixed" => Ok(Phase::Fixed),
+            "witgen" => Ok(Phase::Witgen),
+            "backend" => Ok(Phase::Backend),
+            _ => Err(format!(
+                "Unknown phase {s}, expected fixed, witgen or backend."
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Phase::Fixed => write!(f, "fixed"),
+            Phase::Witgen => write!(f, "witgen"),
+            Phase::Backend => write!(f, "backend"),
+        }
+    }
+}
+
+/// The number of threads for all phases and for individual phases.
+/// Written as `8`, `witgen=1,backend=16` or `8,witgen=1`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ThreadConfig {
+    /// The size of the global thread pool, the default is one thread per CPU.
+    pub default: Option<usize>,
+    pub phases: BTreeMap<Phase, usize>,
+}
+
+impl ThreadConfig {
+    /// Reads the configuration from the `POWDR_THREADS` environment variable.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        match std::env::var("POWDR_THREADS") {
+            Ok(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|e| format!("Invalid POWDR_THREADS: {e}")),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// The number of threads for the phase, if it is configured.
+    pub fn threads(&self, phase: Phase) -> Option<usize> {
+        self.phases.get(&phase).copied().or(self.default)
+    }
+}
+
+impl FromStr for ThreadConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |threads: &str| match threads.parse::<usize>() {
+            Ok(threads) if threads > 0 => Ok(threads),
+            _ => Err(format!("Invalid number of threads: {threads}")),
+        };
+        let mut config = ThreadConfig::default();
+        for item in s.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            match item.split_once('=') {
+                Some((phase, threads)) => {
+                    config
+                        .phases
+                        .insert(phase.trim().parse()?, parse(threads.trim())?);
+                }
+                None => config.default = Some(parse(item)?),
+            }
+        }
+        Ok(config)
+    }
+}
+
+impl fmt::Display for ThreadConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items = self
+            .default
+            .iter()
+            .map(|threads| threads.to_string())
+            .chain(
+                self.phases
+                    .iter()
+                    .map(|(phase, threads)| format!("{phase}={threads}")),
+            )
+            .collect::<Vec<_>>();
+        write!(f, "{}", items.join(","))
+    }
+}
+
+static THREADS: RwLock<Option<ThreadConfig>> = RwLock::new(None);
+
+/// Sets the number of threads for the rest of the process. The size of the
+/// global thread pool can only be set before it is first used, later calls
+/// only change the pools of the phases.
+pub fn set_threads(config: ThreadConfig) {
+    if let Some(threads) = config.default {
+        if rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .is_err()
+        {
+            log::debug!("The global thread pool is already running, using per-phase pools.");
+        }
+    }
+    *THREADS.write().unwrap() = Some(config);
+}
+
+/// Runs the phase on a thread pool of the configured size, or on the global
+/// thread pool if no size is configured. The current tracing span is kept.
+pub(crate) fn in_phase<R: Send>(phase: Phase, f: impl FnOnce() -> R + Send) -> R {
+    let threads = THREADS
+        .read()
+        .unwrap()
+        .as_ref()
+        .and_then(|config| config.threads(phase));
+    match threads {
+        Some(threads) => {
+            log::debug!("Running {phase} on {threads} threads.");
+            on_threads(threads, f)
+        }
+        None => f(),
+    }
+}
+
+/// Runs `f` on a thread pool of the given size, unless the current pool
+/// already has this size. The current tracing span is kept.
+pub(crate) fn on_threads<R: Send>(threads: usize, f: impl FnOnce() -> R + Send) -> R {
+    if threads == rayon::current_num_threads() {
+        return f();
+    }
+    let span = tracing::Span::current();
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .unwrap_or_else(|e| panic!("Could not create the thread pool: {e}"))
+        .install(|| span.in_scope(f))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let config: ThreadConfig = " 8, witgen=1,backend = 16".parse().unwrap();
+        assert_eq!(config.default, Some(8));
+        assert_eq!(config.threads(Phase::Fixed), Some(8));
+        assert_eq!(config.threads(Phase::Witgen), Some(1));
+        assert_eq!(config.threads(Phase::Backend), Some(16));
+        assert_eq!(config.to_string(), "8,witgen=1,backend=16");
+        assert_eq!(
+            "fixed=2"
+                .parse::<ThreadConfig>()
+                .unwrap()
+                .threads(Phase::Witgen),
+            None
+        );
+        assert_eq!(
+            "0".parse::<ThreadConfig>().unwrap_err(),
+            "Invalid number of threads: 0"
+        );
+        assert_eq!(
+            "proof=2".parse::<ThreadConfig>().unwrap_err(),
+            "Unknown phase proof, expected fixed, witgen or backend."
+        );
+    }
+
+    #[test]
+    fn phase_pool() {
+        set_threads("fixed=3".parse().unwrap());
+        assert_eq!(in_phase(Phase::Fixed, rayon::current_num_threads), 3);
+        *THREADS.write().unwrap() = None;
+    }
+}
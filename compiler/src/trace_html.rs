@@ -0,0 +1,246 @@
+//! Exports the trace of a PIL file as a self-contained HTML page: the columns
+//! as a table, the identities checked on every row and the PIL sources, with
+//! links from the cells to the declarations of their columns.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use executor::constant_evaluator;
+use number::{DegreeType, FieldElement};
+use pil_analyzer::{SourceRef, StatementIdentifier, Symbol};
+
+use crate::inspect::read_witness;
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 1em; }
+.trace { max-height: 70vh; overflow: auto; border: 1px solid #ccc; }
+table { border-collapse: collapse; font-family: monospace; }
+th, td { padding: 2px 8px; border: 1px solid #ddd; text-align: right; white-space: nowrap; }
+th { position: sticky; top: 0; background: #eee; }
+th.fixed { background: #e0e8f8; }
+th.identity { background: #f0f0e0; }
+td a { color: inherit; text-decoration: none; }
+td.pass { background: #d8f5d8; text-align: center; }
+td.fail { background: #f8c8c8; text-align: center; }
+pre .line { display: block; }
+pre .line:target { background: #fff3a0; }
+pre .number { display: inline-block; width: 4em; color: #999; user-select: none; }
+";
+
+/// Returns the HTML page for the trace of the PIL file, with the witness read
+/// from `commits.bin` in `dir` and the fixed columns generated from the PIL file.
+/// Only the given rows are included, the default are all rows.
+pub fn export_trace_html<T: FieldElement>(
+    pil_file: &Path,
+    dir: &Path,
+    rows: Option<Range<DegreeType>>,
+) -> Result<String, String> {
+    let analyzed = crate::analyze_pil::<T>(pil_file)?;
+    let (fixed, _) = constant_evaluator::generate(&analyzed)?;
+    let witness = read_witness(&analyzed, dir)?;
+    let degree = witness[0].1.len() as DegreeType;
+    let rows = rows.unwrap_or(0..degree);
+    if rows.end > degree {
+        return Err(format!(
+            "Row {} is out of range, the witness has {degree} rows.",
+            rows.end - 1
+        ));
+    }
+
+    let identities = analyzed
+        .source_order
+        .iter()
+        .filter_map(|statement| match statement {
+            StatementIdentifier::Identity(i) => Some(&analyzed.identities[*i]),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let (failures, check_error) = match backend::check(&analyzed, &fixed, &witness) {
+        Ok(failures) => (failures, None),
+        Err(e) => (vec![], Some(e.to_string())),
+    };
+    // The failure messages by identity and row.
+    let mut failed = HashMap::<_, Vec<_>>::new();
+    for failure in &failures {
+        let identity = identities
+            .iter()
+            .position(|i| {
+                source(&i.source) == failure.source
+                    && analyzed.expressions.display(*i).to_string() == failure.identity
+            })
+            .unwrap();
+        failed
+            .entry((identity, failure.row))
+            .or_default()
+            .push(failure.reason.as_str());
+    }
+
+    let file_name = pil_file.file_name().unwrap().to_str().unwrap();
+    let mut html = String::new();
+    writeln!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+        <title>Trace of {0}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+        <h1>Trace of {0}</h1>",
+        escape(file_name)
+    )
+    .unwrap();
+    let failing_rows = failures.iter().map(|f| f.row).collect::<HashSet<_>>();
+    writeln!(
+        html,
+        "<p>{degree} rows (showing {}..{}), {} fixed and {} witness columns, \
+        {} identities, {} rows with violations.</p>",
+        rows.start,
+        rows.end,
+        fixed.len(),
+        witness.len(),
+        identities.len(),
+        failing_rows.len()
+    )
+    .unwrap();
+    if let Some(error) = &check_error {
+        writeln!(
+            html,
+            "<p>The identities could not be checked: {}</p>",
+            escape(error)
+        )
+        .unwrap();
+    }
+
+    // The table with a column for every fixed and witness column and every identity.
+    let columns = fixed
+        .iter()
+        .map(|column| (column, "fixed"))
+        .chain(witness.iter().map(|column| (column, "witness")))
+        .map(|((name, values), class)| {
+            let (poly, _) = &analyzed.definitions[&Symbol::intern(name)];
+            (name, values, class, anchor(&poly.source))
+        })
+        .collect::<Vec<_>>();
+    html += "<div class=\"trace\">\n<table>\n<thead><tr><th>row</th>";
+    for (name, _, class, anchor) in &columns {
+        write!(
+            html,
+            "<th class=\"{class}\"><a href=\"#{anchor}\">{}</a></th>",
+            escape(name)
+        )
+        .unwrap();
+    }
+    if check_error.is_none() {
+        for identity in &identities {
+            write!(
+                html,
+                "<th class=\"identity\" title=\"{}\"><a href=\"#{}\">{}</a></th>",
+                escape(&analyzed.expressions.display(*identity).to_string()),
+                anchor(&identity.source),
+                escape(&source(&identity.source))
+            )
+            .unwrap();
+        }
+    }
+    html += "</tr></thead>\n<tbody>\n";
+    for row in rows.clone() {
+        write!(html, "<tr><td>{row}</td>").unwrap();
+        for (_, values, _, anchor) in &columns {
+            write!(
+                html,
+                "<td><a href=\"#{anchor}\">{}</a></td>",
+                values[row as usize]
+            )
+            .unwrap();
+        }
+        if check_error.is_none() {
+            for (i, identity) in identities.iter().enumerate() {
+                match failed.get(&(i, row as usize)) {
+                    Some(reasons) => write!(
+                        html,
+                        "<td class=\"fail\" title=\"{}\"><a href=\"#{}\">&#x2717;</a></td>",
+                        escape(&reasons.join("\n")),
+                        anchor(&identity.source)
+                    ),
+                    None => write!(html, "<td class=\"pass\">&#x2713;</td>"),
+                }
+                .unwrap();
+            }
+        }
+        html += "</tr>\n";
+    }
+    html += "</tbody>\n</table>\n</div>\n";
+
+    // The sources, with an anchor on every line.
+    for file in pil_analyzer::included_files::<T>(pil_file) {
+        let name = file.file_name().unwrap().to_str().unwrap();
+        let contents = fs::read_to_string(&file)
+            .map_err(|e| format!("Could not read {}: {e}", file.display()))?;
+        writeln!(html, "<h2>{}</h2>\n<pre>", escape(name)).unwrap();
+        for (i, line) in contents.lines().enumerate() {
+            let line_ref = SourceRef {
+                file: name.to_string(),
+                line: i + 1,
+            };
+            writeln!(
+                html,
+                "<span class=\"line\" id=\"{}\"><span class=\"number\">{}</span>{}</span>",
+                anchor(&line_ref),
+                i + 1,
+                escape(line)
+            )
+            .unwrap();
+        }
+        html += "</pre>\n";
+    }
+    html += "</body>\n</html>\n";
+    Ok(html)
+}
+
+fn source(source: &SourceRef) -> String {
+    format!("{}:{}", source.file, source.line)
+}
+
+fn anchor(source: &SourceRef) -> String {
+    escape(&format!("{}-{}", source.file, source.line))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::BufWriter;
+
+    use number::GoldilocksField;
+
+    use crate::write_polys_file;
+
+    use super::*;
+
+    #[test]
+    fn failing_row() {
+        let dir = mktemp::Temp::new_dir().unwrap();
+        fs::write(
+            dir.join("input.pil"),
+            "namespace Main(4);\n    col fixed FIRST = [1, 0, 0, 0];\n    col witness x;\n    FIRST * (x - 1) = 0;\n    x' = x + 1;\n",
+        )
+        .unwrap();
+        let x = [1, 2, 3, 0].map(GoldilocksField::from).to_vec();
+        write_polys_file(
+            &mut BufWriter::new(fs::File::create(dir.join("commits.bin")).unwrap()),
+            4,
+            &vec![("Main.x", x)],
+        );
+        let html =
+            export_trace_html::<GoldilocksField>(&dir.join("input.pil"), &dir, None).unwrap();
+        assert!(html.contains("4 rows (showing 0..4), 1 fixed and 1 witness columns, 2 identities, 1 rows with violations."));
+        // The first identity holds on all rows, the second one fails on row 2.
+        assert!(html.contains("<tr><td>0</td><td><a href=\"#input.pil-2\">1</a></td><td><a href=\"#input.pil-3\">1</a></td><td class=\"pass\">&#x2713;</td><td class=\"pass\">&#x2713;</td></tr>"), "{html}");
+        assert!(html.contains("<tr><td>2</td><td><a href=\"#input.pil-2\">0</a></td><td><a href=\"#input.pil-3\">3</a></td><td class=\"pass\">&#x2713;</td><td class=\"fail\" title=\"evaluates to -4\"><a href=\"#input.pil-5\">&#x2717;</a></td></tr>"), "{html}");
+        assert!(html.contains("<span class=\"line\" id=\"input.pil-5\"><span class=\"number\">5</span>    x' = x + 1;</span>"));
+    }
+}
@@ -0,0 +1,141 @@
+//! Heuristics for witness columns that are not constrained enough, which
+//! frequently indicates a soundness problem: A dishonest prover could choose
+//! other values for them.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use executor::constant_evaluator;
+use number::FieldElement;
+use pil_analyzer::{Analyzed, Expression, FunctionValueDefinition, PolynomialType, Symbol};
+
+use crate::panics::catch_panic;
+use crate::{analyze_file, inputs_query_callback, CompileOptions};
+
+/// A witness column and why it might be under-constrained.
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnderconstrainedColumn {
+    pub name: String,
+    pub reasons: Vec<String>,
+}
+
+/// Generates the witness of an assembly or PIL file for the inputs and
+/// reports the witness columns that
+/// - are not referenced by any identity,
+/// - are only referenced by identities that are never active, see
+///   `backend::coverage`, or
+/// - have values that witness generation could not derive on some rows and
+///   chose instead. Columns with queries are free inputs and not reported.
+///
+/// References through intermediate columns count as references.
+pub fn underconstrained_columns<T: FieldElement>(
+    file: &Path,
+    options: &CompileOptions,
+    inputs: Vec<T>,
+) -> Result<Vec<UnderconstrainedColumn>, String> {
+    let analyzed = analyze_file::<T>(file, options)?;
+    let (fixed, degree) = constant_evaluator::generate(&analyzed)?;
+    let (witness, defaulted) = catch_panic(|| {
+        Ok(executor::witgen::generate_with_defaults(
+            &analyzed,
+            degree,
+            &fixed,
+            Some(inputs_query_callback(inputs, 0)),
+        ))
+    })
+    .map_err(|e| format!("Witness generation failed: {e}"))?;
+    let coverage = backend::coverage(&analyzed, &fixed, &witness).map_err(|e| e.to_string())?;
+
+    // The identities (with their source) that reference each column.
+    let mut referencing = HashMap::<Symbol, Vec<(String, bool)>>::new();
+    for (identity, identity_coverage) in analyzed.identities.iter().zip(&coverage.identities) {
+        let mut names = HashSet::new();
+        for e in [&identity.left, &identity.right]
+            .into_iter()
+            .flat_map(|selected| selected.selector.iter().chain(&selected.expressions))
+        {
+            collect_columns(&analyzed, e, &mut names);
+        }
+        if let Some(multiplicity) = &identity.multiplicity {
+            names.insert(multiplicity.name);
+        }
+        for name in names {
+            referencing.entry(name).or_default().push((
+                identity_coverage.source.clone(),
+                identity_coverage.active_rows > 0,
+            ));
+        }
+    }
+
+    let mut columns = vec![];
+    for (poly, definition) in analyzed.committed_polys_in_source_order() {
+        let name = poly.absolute_name;
+        let mut reasons = vec![];
+        match referencing.get(&name) {
+            None => reasons.push("not referenced by any identity".to_string()),
+            Some(identities) if identities.iter().all(|(_, active)| !active) => {
+                let sources = identities
+                    .iter()
+                    .map(|(source, _)| source.as_str())
+                    .collect::<Vec<_>>();
+                reasons.push(format!(
+                    "only referenced by identities that are never active: {}",
+                    sources.join(", ")
+                ));
+            }
+            Some(_) => {}
+        }
+        if !matches!(definition, Some(FunctionValueDefinition::Query(_))) {
+            if let Some(column) = defaulted.iter().find(|c| c.name == name) {
+                reasons.push(format!(
+                    "witness generation could not derive the value on {} of {degree} rows (first on row {}) and chose 0",
+                    column.rows, column.first_row
+                ));
+            }
+        }
+        if !reasons.is_empty() {
+            columns.push(UnderconstrainedColumn {
+                name: name.to_string(),
+                reasons,
+            });
+        }
+    }
+    Ok(columns)
+}
+
+/// Adds the names of the fixed and witness columns referenced by the
+/// expression, with the intermediate columns replaced by their definitions.
+fn collect_columns<T>(analyzed: &Analyzed<T>, e: &Expression<T>, names: &mut HashSet<Symbol>) {
+    match e {
+        Expression::PolynomialReference(reference) => {
+            match analyzed.definitions.get(&reference.name) {
+                Some((poly, Some(FunctionValueDefinition::Mapping(definition))))
+                    if poly.poly_type == PolynomialType::Intermediate =>
+                {
+                    if names.insert(reference.name) {
+                        collect_columns(analyzed, definition, names);
+                    }
+                }
+                _ => {
+                    names.insert(reference.name);
+                }
+            }
+        }
+        Expression::BinaryOperation(left, _, right) => {
+            collect_columns(analyzed, &analyzed.expressions[*left], names);
+            collect_columns(analyzed, &analyzed.expressions[*right], names);
+        }
+        Expression::UnaryOperation(_, e) => {
+            collect_columns(analyzed, &analyzed.expressions[*e], names)
+        }
+        Expression::FunctionCall(_, args) | Expression::Tuple(args) => args
+            .iter()
+            .for_each(|e| collect_columns(analyzed, e, names)),
+        Expression::MatchExpression(scrutinee, arms) => {
+            collect_columns(analyzed, &analyzed.expressions[*scrutinee], names);
+            arms.iter()
+                .for_each(|(_, e)| collect_columns(analyzed, e, names));
+        }
+        _ => {}
+    }
+}
@@ -1,8 +1,9 @@
 use compiler::verify_asm_string;
-use number::FieldElement;
+use number::{FieldElement, GoldilocksField};
 use std::fs;
+use std::path::Path;
 
-fn verify_asm(file_name: &str, inputs: Vec<FieldElement>) {
+fn verify_asm(file_name: &str, inputs: Vec<GoldilocksField>) {
     let contents = fs::read_to_string(format!("../test_data/asm/{file_name}")).unwrap();
     verify_asm_string(file_name, &contents, inputs)
 }
@@ -50,3 +51,149 @@ fn functional_instructions() {
 fn full_pil_constant() {
     verify_asm("full_pil_constant.asm", Default::default());
 }
+
+#[test]
+fn call_return() {
+    verify_asm(
+        "call_return.asm",
+        [5, 20].iter().map(|&x| x.into()).collect(),
+    );
+}
+
+#[test]
+fn multi_return() {
+    verify_asm(
+        "multi_return.asm",
+        [1000, 300, 1300, 700, 232, 3]
+            .iter()
+            .map(|&x| x.into())
+            .collect(),
+    );
+}
+
+#[test]
+fn register_defaults() {
+    verify_asm(
+        "register_defaults.asm",
+        [5].iter().map(|&x| x.into()).collect(),
+    );
+}
+
+#[test]
+fn read_only_registers() {
+    verify_asm(
+        "read_only_registers.asm",
+        [5].iter().map(|&x| x.into()).collect(),
+    );
+}
+
+#[test]
+fn jump_table() {
+    verify_asm(
+        "jump_table.asm",
+        [2, 30].iter().map(|&x| x.into()).collect(),
+    );
+}
+
+#[test]
+fn halt() {
+    verify_asm("halt.asm", [3, 4, 7].iter().map(|&x| x.into()).collect());
+}
+
+#[test]
+fn entry_points() {
+    verify_asm(
+        "entry_points.asm",
+        [5, 6].iter().map(|&x| x.into()).collect(),
+    );
+}
+
+#[test]
+fn local_labels() {
+    verify_asm(
+        "local_labels.asm",
+        [3, 4, 12, 9].iter().map(|&x| x.into()).collect(),
+    );
+}
+
+#[test]
+fn inline_pil_flags() {
+    verify_asm(
+        "inline_pil_flags.asm",
+        [200].iter().map(|&x| x.into()).collect(),
+    );
+}
+
+#[test]
+fn conditional() {
+    verify_asm(
+        "conditional.asm",
+        [3, 4, 7].iter().map(|&x| x.into()).collect(),
+    );
+}
+
+#[test]
+fn assert_eq() {
+    verify_asm(
+        "assert_eq.asm",
+        [3, 4, 7].iter().map(|&x| x.into()).collect(),
+    );
+}
+
+#[test]
+#[should_panic(expected = "Assertion failed at assert_eq.asm:13: sum mismatch")]
+fn assert_eq_violated() {
+    verify_asm(
+        "assert_eq.asm",
+        [3, 4, 8].iter().map(|&x| x.into()).collect(),
+    );
+}
+
+#[test]
+fn fuzz_assert_eq() {
+    let failure = compiler::fuzz::<GoldilocksField>(
+        Path::new("../test_data/asm/assert_eq.asm"),
+        &Default::default(),
+        &Default::default(),
+    )
+    .unwrap()
+    .unwrap();
+    assert!(failure.message.contains("sum mismatch"));
+    let answers = failure
+        .answers
+        .iter()
+        .map(|(query, answer)| (query.as_str(), answer.to_degree()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        answers,
+        [
+            ("\"input\", 0", 1),
+            ("\"input\", 1", 0),
+            ("\"input\", 2", 0)
+        ]
+    );
+}
+
+#[test]
+fn constant_propagation() {
+    verify_asm(
+        "constant_propagation.asm",
+        [4, 20].iter().map(|&x| x.into()).collect(),
+    );
+}
+
+#[test]
+fn machines() {
+    verify_asm(
+        "machines.asm",
+        [200, 15].iter().map(|&x| x.into()).collect(),
+    );
+}
+
+#[test]
+fn macros() {
+    verify_asm(
+        "macros.asm",
+        [3, 8, 1, 2, 3, 4, 10].iter().map(|&x| x.into()).collect(),
+    );
+}
@@ -0,0 +1,55 @@
+//! Tests with the Halo2 backend. They are in their own test binary because
+//! the modulus of `DynamicField` can only be set once per process.
+
+use number::DynamicField;
+use std::path::Path;
+
+#[test]
+fn test_fibonacci_halo2_publics() {
+    DynamicField::set_modulus(halo2::modulus());
+    let input_file = Path::new("../test_data/pil/fibonacci.pil");
+    let temp_dir = mktemp::Temp::new_dir().unwrap();
+    assert!(compiler::compile_pil(
+        input_file,
+        &Default::default(),
+        &compiler::OutputLayout::new(&temp_dir),
+        compiler::no_callback::<DynamicField>(),
+        Some(compiler::BackendType::Halo2),
+    ));
+    let publics = compiler::read_publics(
+        &pil_analyzer::analyze(input_file),
+        &compiler::OutputLayout::new(&temp_dir),
+    );
+    assert_eq!(publics.get("out"), Some(DynamicField::from(1597)));
+
+    let artifact = std::fs::read_to_string(temp_dir.join("proof_artifact.json")).unwrap();
+    let mut artifact = compiler::ProofArtifact::from_json(&artifact).unwrap();
+    compiler::verify_proof::<DynamicField>(input_file, &artifact, None, None).unwrap();
+    let setup_file = std::fs::read_dir(&temp_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.to_str().unwrap().contains("halo2_setup_"))
+        .unwrap();
+    let publics_file = temp_dir.join("publics.json");
+    compiler::verify_proof::<DynamicField>(
+        input_file,
+        &artifact,
+        Some(&setup_file),
+        Some(&publics_file),
+    )
+    .unwrap();
+
+    artifact.publics[0].1 += 1u32;
+    assert!(
+        compiler::verify_proof::<DynamicField>(input_file, &artifact, None, None)
+            .unwrap_err()
+            .starts_with("Proof rejected")
+    );
+    let err =
+        compiler::verify_proof::<DynamicField>(input_file, &artifact, None, Some(&publics_file))
+            .unwrap_err();
+    assert!(
+        err.contains("public out is 1598 in the proof, expected 1597"),
+        "{err}"
+    );
+}
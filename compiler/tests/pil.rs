@@ -1,7 +1,7 @@
-use number::FieldElement;
+use number::GoldilocksField;
 use std::path::Path;
 
-pub fn verify_pil(file_name: &str, query_callback: Option<fn(&str) -> Option<FieldElement>>) {
+pub fn verify_pil(file_name: &str, query_callback: Option<fn(&str) -> Option<GoldilocksField>>) {
     let input_file = Path::new(&format!("../test_data/pil/{file_name}"))
         .canonicalize()
         .unwrap();
@@ -9,8 +9,10 @@ pub fn verify_pil(file_name: &str, query_callback: Option<fn(&str) -> Option<Fie
     let temp_dir = mktemp::Temp::new_dir().unwrap();
     assert!(compiler::compile_pil(
         &input_file,
-        &temp_dir,
+        &Default::default(),
+        &compiler::OutputLayout::new(&temp_dir),
         query_callback,
+        None,
     ));
     compiler::verify(file_name, &temp_dir);
 }
@@ -20,6 +22,65 @@ fn test_fibonacci() {
     verify_pil("fibonacci.pil", None);
 }
 
+#[test]
+fn test_fibonacci_differential() {
+    let report = compiler::differential_test::<GoldilocksField>(
+        Path::new("../test_data/pil/fibonacci.pil"),
+        &Default::default(),
+        vec![],
+        5,
+        0,
+    )
+    .unwrap();
+    assert!(
+        report.divergences.is_empty(),
+        "{}",
+        report.divergences.join("\n")
+    );
+}
+
+#[test]
+fn test_fibonacci_determinism() {
+    let differences = compiler::check_determinism::<GoldilocksField>(
+        Path::new("../test_data/pil/fibonacci.pil"),
+        &Default::default(),
+        vec![],
+        &[1, 4, 1],
+    )
+    .unwrap();
+    assert!(differences.is_empty(), "{}", differences.join("\n"));
+}
+
+#[test]
+fn test_fixed_column_cache() {
+    let input_file = Path::new("../test_data/pil/fibonacci.pil");
+    let temp_dir = mktemp::Temp::new_dir().unwrap();
+    let output = compiler::OutputLayout::new(&temp_dir);
+    let compile = || {
+        assert!(compiler::compile_pil(
+            input_file,
+            &Default::default(),
+            &output,
+            compiler::no_callback::<GoldilocksField>(),
+            None,
+        ));
+        std::fs::read(temp_dir.join("constants.bin")).unwrap()
+    };
+    let constants = compile();
+    let cache_file = std::fs::read_dir(&temp_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.to_str().unwrap().contains("constants_"))
+        .unwrap();
+    assert_eq!(std::fs::read(&cache_file).unwrap(), constants);
+
+    // The second run takes the fixed columns from the cache.
+    let mut cached = constants;
+    cached[0] ^= 1;
+    std::fs::write(&cache_file, &cached).unwrap();
+    assert_eq!(compile(), cached);
+}
+
 #[test]
 fn test_fibonacci_macro() {
     verify_pil("fib_macro.pil", None);
@@ -68,3 +129,47 @@ fn test_pair_lookup() {
 fn test_block_lookup_or() {
     verify_pil("block_lookup_or.pil", None);
 }
+
+#[test]
+fn test_pil_tests() {
+    let outcomes = compiler::run_tests::<GoldilocksField>(
+        Path::new("../test_data/pil/tests.pil"),
+        &Default::default(),
+        "",
+    )
+    .unwrap();
+    assert_eq!(
+        outcomes
+            .iter()
+            .map(|o| (o.test.name.as_str(), o.passed()))
+            .collect::<Vec<_>>(),
+        [
+            ("decomposes", true),
+            ("too_large", true),
+            ("zero_or_one", true),
+            ("two", true)
+        ]
+    );
+}
+
+#[test]
+fn test_underconstrained_columns() {
+    let columns = compiler::underconstrained_columns::<GoldilocksField>(
+        Path::new("../test_data/pil/conditional.pil"),
+        &Default::default(),
+        vec![],
+    )
+    .unwrap();
+    let unchecked = columns
+        .iter()
+        .find(|c| c.name == "Conditional.unchecked")
+        .unwrap();
+    assert_eq!(unchecked.reasons[0], "not referenced by any identity");
+    assert!(compiler::underconstrained_columns::<GoldilocksField>(
+        Path::new("../test_data/pil/fibonacci.pil"),
+        &Default::default(),
+        vec![]
+    )
+    .unwrap()
+    .is_empty());
+}
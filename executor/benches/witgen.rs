@@ -0,0 +1,34 @@
+//! Compares fixed column evaluation and witness generation across field implementations.
+//! Run with `cargo bench -p executor`.
+
+use std::path::Path;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use executor::{constant_evaluator, witgen};
+use number::{FieldElement, GoldilocksField, GoldilocksMontgomeryField};
+
+const FILE: &str = "../test_data/pil/block_lookup_or.pil";
+
+fn bench_field<T: FieldElement>(c: &mut Criterion, name: &str) {
+    let analyzed = pil_analyzer::analyze::<T>(Path::new(FILE));
+    let mut group = c.benchmark_group("block_lookup_or");
+    group.sample_size(10);
+
+    group.bench_function(BenchmarkId::new("constants", name), |b| {
+        b.iter(|| constant_evaluator::generate(&analyzed).unwrap())
+    });
+
+    let (constants, degree) = constant_evaluator::generate(&analyzed).unwrap();
+    group.bench_function(BenchmarkId::new("witgen", name), |b| {
+        b.iter(|| witgen::generate(&analyzed, degree, &constants, None::<fn(&str) -> Option<T>>))
+    });
+    group.finish();
+}
+
+fn fields(c: &mut Criterion) {
+    bench_field::<GoldilocksField>(c, "goldilocks");
+    bench_field::<GoldilocksMontgomeryField>(c, "goldilocks_montgomery");
+}
+
+criterion_group!(benches, fields);
+criterion_main!(benches);
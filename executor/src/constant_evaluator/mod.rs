@@ -1,23 +1,40 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::ControlFlow;
 
-use number::{DegreeType, FieldElement};
-use pil_analyzer::{Analyzed, BinaryOperator, Expression, FunctionValueDefinition, UnaryOperator};
+use number::{batch, DegreeType, FieldElement};
+use pil_analyzer::util::previsit_expression;
+use pil_analyzer::{
+    Analyzed, BinaryOperator, Expression, ExpressionArena, FunctionValueDefinition,
+    PolynomialReference, PolynomialType, Symbol, UnaryOperator,
+};
+#[cfg(feature = "parallel")]
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 
+/// The values of fixed columns by name.
+type Columns<'a, T> = Vec<(&'a str, Vec<T>)>;
+
 /// Generates the constant polynomial values for all constant polynomials
 /// that are defined (and not just declared).
 /// @returns the values (in source order) and the degree of the polynomials.
-pub fn generate(analyzed: &Analyzed) -> (Vec<(&str, Vec<FieldElement>)>, DegreeType) {
+pub fn generate<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+) -> Result<(Columns<'_, T>, DegreeType), String> {
     let mut degree = None;
     let mut other_constants = HashMap::new();
     for (poly, value) in analyzed.constant_polys_in_source_order() {
         if let Some(value) = value {
             if let Some(degree) = degree {
-                assert!(degree == poly.degree);
+                if degree != poly.degree {
+                    return Err(format!(
+                        "The degree of {} is {}, but the degree of the other fixed columns is {degree}.",
+                        poly.absolute_name, poly.degree
+                    ));
+                }
             } else {
                 degree = Some(poly.degree);
             }
-            let values = generate_values(analyzed, poly.degree, value, &other_constants);
+            let values = generate_values(analyzed, poly.degree, value, &other_constants)
+                .map_err(|e| format!("{e} (in the definition of {})", poly.absolute_name))?;
             other_constants.insert(&poly.absolute_name, values);
         }
     }
@@ -27,131 +44,411 @@ pub fn generate(analyzed: &Analyzed) -> (Vec<(&str, Vec<FieldElement>)>, DegreeT
             values.push((poly.absolute_name.as_str(), std::mem::take(v)));
         };
     }
-    (values, degree.unwrap_or_default())
+    Ok((values, degree.unwrap_or_default()))
 }
 
-fn generate_values(
-    analyzed: &Analyzed,
-    degree: DegreeType,
-    body: &FunctionValueDefinition,
-    other_constants: &HashMap<&str, Vec<FieldElement>>,
-) -> Vec<FieldElement> {
-    match body {
-        FunctionValueDefinition::Mapping(body) => (0..degree)
-            .into_par_iter()
-            .map(|i| {
-                Evaluator {
+/// Evaluates an expression at the given row, where references to fixed columns
+/// are their values at that row (or the next one). References to intermediate
+/// columns are evaluated through their definition.
+/// The operands of the expression are in `expressions`, which has to contain
+/// those of the analyzed file.
+pub fn evaluate_at_row<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    expressions: &ExpressionArena<T>,
+    fixed: &HashMap<&str, Vec<T>>,
+    expr: &Expression<T>,
+    row: DegreeType,
+) -> Result<T, String> {
+    Evaluator {
+        analyzed,
+        expressions,
+        variables: &[],
+        other_constants: fixed,
+        row: Some(row),
+    }
+    .evaluate(expr)
+}
+
+/// Evaluates the definition of the fixed column at the rows, for example
+/// `0..16` or `[7]`, without generating the other fixed columns, except for
+/// those that the definition calls.
+/// @returns the values in the order of the rows.
+pub fn evaluate_column<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    name: &str,
+    rows: impl IntoIterator<Item = DegreeType>,
+) -> Result<Vec<T>, String> {
+    let (poly, definition) = Symbol::lookup(name)
+        .and_then(|name| analyzed.definitions.get(&name))
+        .ok_or_else(|| format!("Column {name} not found."))?;
+    if poly.poly_type != PolynomialType::Constant {
+        return Err(format!("{name} is not a fixed column."));
+    }
+    let definition = definition
+        .as_ref()
+        .ok_or_else(|| format!("Fixed column {name} is not defined."))?;
+
+    // The columns called by the definition, directly or through other
+    // columns, are generated in source order.
+    let mut called = BTreeSet::new();
+    collect_called_columns(analyzed, definition, &mut called);
+    let mut other_constants = HashMap::new();
+    for (poly, value) in analyzed.constant_polys_in_source_order() {
+        if let (true, Some(value)) = (called.contains(poly.absolute_name.as_str()), value) {
+            let values = generate_values(analyzed, poly.degree, value, &other_constants)?;
+            other_constants.insert(poly.absolute_name.as_str(), values);
+        }
+    }
+
+    let array_values = match definition {
+        FunctionValueDefinition::Array(_) => Some(generate_values(
+            analyzed,
+            poly.degree,
+            definition,
+            &other_constants,
+        )?),
+        _ => None,
+    };
+    rows.into_iter()
+        .map(|row| {
+            if row >= poly.degree {
+                return Err(format!(
+                    "Row {row} is out of range, the degree of {name} is {}.",
+                    poly.degree
+                ));
+            }
+            match (&array_values, definition) {
+                (Some(values), _) => Ok(values[row as usize]),
+                (None, FunctionValueDefinition::Mapping(body)) => Evaluator {
                     analyzed,
-                    variables: &[i.into()],
-                    other_constants,
+                    expressions: &analyzed.expressions,
+                    variables: &[row.into()],
+                    other_constants: &other_constants,
+                    row: None,
+                }
+                .evaluate(body),
+                _ => Err(format!("{name} is not defined by an expression.")),
+            }
+        })
+        .collect()
+}
+
+/// Adds the names of the fixed columns that the definition calls, directly
+/// or through the definitions of the called columns.
+fn collect_called_columns<'a, T>(
+    analyzed: &'a Analyzed<T>,
+    definition: &'a FunctionValueDefinition<T>,
+    called: &mut BTreeSet<&'a str>,
+) {
+    let expressions = match definition {
+        FunctionValueDefinition::Mapping(body) => vec![body],
+        FunctionValueDefinition::Array(items) => items.iter().flat_map(|i| &i.values).collect(),
+        FunctionValueDefinition::Query(_) => vec![],
+    };
+    for expression in expressions {
+        let _: ControlFlow<()> = previsit_expression(&analyzed.expressions, expression, &mut |e| {
+            if let Expression::FunctionCall(name, _) = e {
+                if called.insert(name.as_str()) {
+                    if let Some((_, Some(definition))) =
+                        Symbol::lookup(name).and_then(|name| analyzed.definitions.get(&name))
+                    {
+                        collect_called_columns(analyzed, definition, called);
+                    }
                 }
-                .evaluate(body)
-            })
-            .collect(),
+            }
+            ControlFlow::Continue(())
+        });
+    }
+}
+
+fn generate_values<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    degree: DegreeType,
+    body: &FunctionValueDefinition<T>,
+    other_constants: &HashMap<&str, Vec<T>>,
+) -> Result<Vec<T>, String> {
+    match body {
+        FunctionValueDefinition::Mapping(body) => {
+            match evaluate_column_wise(analyzed, degree, body) {
+                Some(ColumnValues::Column(values)) => Ok(values),
+                Some(ColumnValues::Scalar(value)) => Ok(vec![value; degree as usize]),
+                None => rows(degree)
+                    .map(|i| {
+                        Evaluator {
+                            analyzed,
+                            expressions: &analyzed.expressions,
+                            variables: &[i.into()],
+                            other_constants,
+                            row: None,
+                        }
+                        .evaluate(body)
+                    })
+                    .collect(),
+            }
+        }
         FunctionValueDefinition::Array(values) => {
             let evaluator = Evaluator {
                 analyzed,
+                expressions: &analyzed.expressions,
                 variables: &[],
                 other_constants,
+                row: None,
             };
-            let values: Vec<_> = values
-                .iter()
-                .flat_map(|elements| {
-                    assert!(elements.repetitions >= 1);
-                    let mut items = elements
-                        .values
-                        .iter()
-                        .map(|v| evaluator.evaluate(v))
-                        .collect::<Vec<_>>();
-                    let original_len = items.len();
-                    for _ in 1..elements.repetitions {
-                        items.extend(&items[..original_len].to_vec());
-                    }
-                    items
-                })
-                .collect();
-            assert_eq!(values.len(), degree as usize);
-            values
+            let mut result = vec![];
+            for elements in values {
+                assert!(elements.repetitions >= 1);
+                let items = elements
+                    .values
+                    .iter()
+                    .map(|v| evaluator.evaluate(v))
+                    .collect::<Result<Vec<_>, _>>()?;
+                for _ in 0..elements.repetitions {
+                    result.extend(&items);
+                }
+            }
+            if result.len() != degree as usize {
+                return Err(format!(
+                    "The array has {} elements, but the degree is {degree}.",
+                    result.len()
+                ));
+            }
+            Ok(result)
+        }
+        FunctionValueDefinition::Query(_) => Err("Query used for fixed column.".to_string()),
+    }
+}
+
+/// The row indices, iterated over in parallel with the `parallel` feature.
+#[cfg(feature = "parallel")]
+fn rows(degree: DegreeType) -> impl ParallelIterator<Item = DegreeType> {
+    (0..degree).into_par_iter()
+}
+
+#[cfg(not(feature = "parallel"))]
+fn rows(degree: DegreeType) -> impl Iterator<Item = DegreeType> {
+    0..degree
+}
+
+enum ColumnValues<T> {
+    Scalar(T),
+    Column(Vec<T>),
+}
+
+/// Evaluates a fixed column definition for all rows at once using batch operations.
+/// Only supports additions, subtractions and multiplications of the row index,
+/// constants and numbers, returns None for anything else.
+fn evaluate_column_wise<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    degree: DegreeType,
+    expr: &Expression<T>,
+) -> Option<ColumnValues<T>> {
+    Some(match expr {
+        Expression::Constant(name) => ColumnValues::Scalar(analyzed.constants[name]),
+        Expression::Number(n) => ColumnValues::Scalar(*n),
+        Expression::LocalVariableReference(0) => {
+            ColumnValues::Column((0..degree).map(T::from).collect())
+        }
+        Expression::UnaryOperation(UnaryOperator::Plus, expr) => {
+            evaluate_column_wise(analyzed, degree, &analyzed.expressions[*expr])?
+        }
+        Expression::UnaryOperation(UnaryOperator::Minus, expr) => {
+            match evaluate_column_wise(analyzed, degree, &analyzed.expressions[*expr])? {
+                ColumnValues::Scalar(v) => ColumnValues::Scalar(-v),
+                ColumnValues::Column(mut values) => {
+                    batch::neg_assign(&mut values);
+                    ColumnValues::Column(values)
+                }
+            }
+        }
+        Expression::BinaryOperation(
+            left,
+            op @ (BinaryOperator::Add | BinaryOperator::Sub | BinaryOperator::Mul),
+            right,
+        ) => {
+            let left = evaluate_column_wise(analyzed, degree, &analyzed.expressions[*left])?;
+            let right = evaluate_column_wise(analyzed, degree, &analyzed.expressions[*right])?;
+            combine_column_wise(left, op, right)
+        }
+        _ => return None,
+    })
+}
+
+fn combine_column_wise<T: FieldElement>(
+    left: ColumnValues<T>,
+    op: &BinaryOperator,
+    right: ColumnValues<T>,
+) -> ColumnValues<T> {
+    use ColumnValues::*;
+    match (left, op, right) {
+        (Scalar(l), BinaryOperator::Add, Scalar(r)) => Scalar(l + r),
+        (Scalar(l), BinaryOperator::Sub, Scalar(r)) => Scalar(l - r),
+        (Scalar(l), BinaryOperator::Mul, Scalar(r)) => Scalar(l * r),
+        (Column(mut l), BinaryOperator::Add, Column(r)) => {
+            batch::add_assign(&mut l, &r);
+            Column(l)
+        }
+        (Column(mut l), BinaryOperator::Sub, Column(r)) => {
+            batch::sub_assign(&mut l, &r);
+            Column(l)
+        }
+        (Column(mut l), BinaryOperator::Mul, Column(r)) => {
+            batch::mul_assign(&mut l, &r);
+            Column(l)
+        }
+        (Column(mut c), BinaryOperator::Add, Scalar(s))
+        | (Scalar(s), BinaryOperator::Add, Column(mut c)) => {
+            batch::add_scalar_assign(&mut c, s);
+            Column(c)
         }
-        FunctionValueDefinition::Query(_) => panic!("Query used for fixed column."),
+        (Column(mut c), BinaryOperator::Mul, Scalar(s))
+        | (Scalar(s), BinaryOperator::Mul, Column(mut c)) => {
+            batch::mul_scalar_assign(&mut c, s);
+            Column(c)
+        }
+        (Column(mut c), BinaryOperator::Sub, Scalar(s)) => {
+            batch::add_scalar_assign(&mut c, -s);
+            Column(c)
+        }
+        (Scalar(s), BinaryOperator::Sub, Column(mut c)) => {
+            batch::neg_assign(&mut c);
+            batch::add_scalar_assign(&mut c, s);
+            Column(c)
+        }
+        _ => unreachable!(),
     }
 }
 
-struct Evaluator<'a> {
-    analyzed: &'a Analyzed,
-    other_constants: &'a HashMap<&'a str, Vec<FieldElement>>,
-    variables: &'a [FieldElement],
+struct Evaluator<'a, T> {
+    analyzed: &'a Analyzed<T>,
+    /// The operands of the evaluated expressions.
+    expressions: &'a ExpressionArena<T>,
+    other_constants: &'a HashMap<&'a str, Vec<T>>,
+    variables: &'a [T],
+    /// The row column references are evaluated at, if they are allowed.
+    row: Option<DegreeType>,
 }
 
-impl<'a> Evaluator<'a> {
-    fn evaluate(&self, expr: &Expression) -> FieldElement {
+impl<'a, T: FieldElement> Evaluator<'a, T> {
+    fn evaluate(&self, expr: &Expression<T>) -> Result<T, String> {
         match expr {
-            Expression::Constant(name) => self.analyzed.constants[name],
-            Expression::PolynomialReference(_) => todo!(),
-            Expression::LocalVariableReference(i) => self.variables[*i as usize],
-            Expression::PublicReference(_) => todo!(),
-            Expression::Number(n) => *n,
-            Expression::String(_) => panic!(),
-            Expression::Tuple(_) => panic!(),
-            Expression::BinaryOperation(left, op, right) => {
-                self.evaluate_binary_operation(left, op, right)
+            Expression::Constant(name) => Ok(self.analyzed.constants[name]),
+            Expression::PolynomialReference(poly) => self.evaluate_reference(poly),
+            Expression::LocalVariableReference(i) => Ok(self.variables[*i as usize]),
+            Expression::Number(n) => Ok(*n),
+            Expression::PublicReference(_) | Expression::String(_) | Expression::Tuple(_) => {
+                Err(format!(
+                    "Cannot evaluate {} in a fixed column.",
+                    self.expressions.display(expr)
+                ))
+            }
+            Expression::BinaryOperation(left, op, right) => self.evaluate_binary_operation(
+                &self.expressions[*left],
+                op,
+                &self.expressions[*right],
+            ),
+            Expression::UnaryOperation(op, expr) => {
+                self.evaluate_unary_operation(op, &self.expressions[*expr])
             }
-            Expression::UnaryOperation(op, expr) => self.evaluate_unary_operation(op, expr),
             Expression::FunctionCall(name, args) => {
-                let arg_values = args.iter().map(|a| self.evaluate(a)).collect::<Vec<_>>();
-                assert!(arg_values.len() == 1);
-                let values = &self.other_constants[name.as_str()];
-                values[arg_values[0].to_degree() as usize % values.len()]
+                let [arg] = &args[..] else {
+                    return Err(format!(
+                        "{name} is called with {} arguments instead of one.",
+                        args.len()
+                    ));
+                };
+                let values = self
+                    .other_constants
+                    .get(name.as_str())
+                    .ok_or_else(|| format!("{name} is not a fixed column with values."))?;
+                let index = self
+                    .evaluate(arg)?
+                    .try_to_u64()
+                    .map_err(|e| e.with_context(format!("argument of {name}")).to_string())?;
+                Ok(values[index as usize % values.len()])
             }
             Expression::MatchExpression(scrutinee, arms) => {
-                let v = self.evaluate(scrutinee);
+                let v = self.evaluate(&self.expressions[*scrutinee])?;
                 arms.iter()
                     .find(|(n, _)| n.is_none() || n.as_ref() == Some(&v))
                     .map(|(_, e)| self.evaluate(e))
-                    .expect("No arm matched the value {v}")
+                    .unwrap_or_else(|| Err(format!("No arm matched the value {v}.")))
+            }
+        }
+    }
+
+    fn evaluate_reference(&self, poly: &PolynomialReference) -> Result<T, String> {
+        let row = self.row.ok_or_else(|| {
+            format!("Cannot reference {poly} in the definition of a fixed column.")
+        })?;
+        if poly.index.is_some() {
+            return Err(format!("Array elements are not supported: {poly}"));
+        }
+        let row = row + poly.next as DegreeType;
+        if let Some(values) = self.other_constants.get(poly.name.as_str()) {
+            return Ok(values[row as usize % values.len()]);
+        }
+        match self.analyzed.definitions.get(&poly.name) {
+            Some((p, Some(FunctionValueDefinition::Mapping(e))))
+                if p.poly_type == PolynomialType::Intermediate =>
+            {
+                Evaluator {
+                    row: Some(row),
+                    ..*self
+                }
+                .evaluate(e)
             }
+            Some(_) => Err(format!(
+                "{} is not a fixed or intermediate column.",
+                poly.name
+            )),
+            None => Err(format!("Column {} not found.", poly.name)),
         }
     }
 
     fn evaluate_binary_operation(
         &self,
-        left: &Expression,
+        left: &Expression<T>,
         op: &BinaryOperator,
-        right: &Expression,
-    ) -> FieldElement {
-        let left = self.evaluate(left);
-        let right = self.evaluate(right);
-        match op {
+        right: &Expression<T>,
+    ) -> Result<T, String> {
+        let left = self.evaluate(left)?;
+        let right = self.evaluate(right)?;
+        Ok(match op {
             BinaryOperator::Add => left + right,
             BinaryOperator::Sub => left - right,
             BinaryOperator::Mul => left * right,
             BinaryOperator::Div => left.integer_div(right),
             BinaryOperator::Pow => left.pow(right.to_integer()),
-            BinaryOperator::Mod => (left.to_integer() % right.to_integer()).into(),
-            BinaryOperator::BinaryAnd => (left.to_integer() & right.to_integer()).into(),
-            BinaryOperator::BinaryXor => (left.to_integer() ^ right.to_integer()).into(),
-            BinaryOperator::BinaryOr => (left.to_integer() | right.to_integer()).into(),
-            BinaryOperator::ShiftLeft => (left.to_integer() << right.to_integer()).into(),
-            BinaryOperator::ShiftRight => (left.to_integer() >> right.to_integer()).into(),
-        }
+            BinaryOperator::Mod => left.integer_mod(right),
+            BinaryOperator::BinaryAnd => left.bitand(right),
+            BinaryOperator::BinaryXor => left.bitxor(right),
+            BinaryOperator::BinaryOr => left.bitor(right),
+            BinaryOperator::ShiftLeft => left.try_shl(right).map_err(|e| e.to_string())?,
+            BinaryOperator::ShiftRight => left.try_shr(right).map_err(|e| e.to_string())?,
+        })
     }
 
-    fn evaluate_unary_operation(&self, op: &UnaryOperator, expr: &Expression) -> FieldElement {
-        let v = self.evaluate(expr);
-        match op {
+    fn evaluate_unary_operation(
+        &self,
+        op: &UnaryOperator,
+        expr: &Expression<T>,
+    ) -> Result<T, String> {
+        let v = self.evaluate(expr)?;
+        Ok(match op {
             UnaryOperator::Plus => v,
             UnaryOperator::Minus => -v,
-        }
+        })
     }
 }
 
 #[cfg(test)]
 mod test {
+    use number::{Bn254Field, GoldilocksField};
     use pil_analyzer::analyze_string;
 
     use super::*;
 
-    fn convert(input: Vec<i32>) -> Vec<FieldElement> {
+    fn convert(input: Vec<i32>) -> Vec<GoldilocksField> {
         input.into_iter().map(|x| x.into()).collect()
     }
 
@@ -165,8 +462,8 @@ mod test {
                 _ => 0,
             } };
         "#;
-        let analyzed = analyze_string(src);
-        let (constants, degree) = generate(&analyzed);
+        let analyzed = analyze_string::<GoldilocksField>(src);
+        let (constants, degree) = generate(&analyzed).unwrap();
         assert_eq!(degree, 8);
         assert_eq!(
             constants,
@@ -181,8 +478,8 @@ mod test {
             namespace F(%N);
             pol constant EVEN(i) { 2 * (i - 1) };
         "#;
-        let analyzed = analyze_string(src);
-        let (constants, degree) = generate(&analyzed);
+        let analyzed = analyze_string::<GoldilocksField>(src);
+        let (constants, degree) = generate(&analyzed).unwrap();
         assert_eq!(degree, 8);
         assert_eq!(
             constants,
@@ -197,8 +494,8 @@ mod test {
             namespace F(%N);
             pol constant X(i) { i ^ (i + 17) | 3 };
         "#;
-        let analyzed = analyze_string(src);
-        let (constants, degree) = generate(&analyzed);
+        let analyzed = analyze_string::<GoldilocksField>(src);
+        let (constants, degree) = generate(&analyzed).unwrap();
         assert_eq!(degree, 8);
         assert_eq!(
             constants,
@@ -218,8 +515,8 @@ mod test {
                 _ => 4,
             } + 1 };
         "#;
-        let analyzed = analyze_string(src);
-        let (constants, degree) = generate(&analyzed);
+        let analyzed = analyze_string::<GoldilocksField>(src);
+        let (constants, degree) = generate(&analyzed).unwrap();
         assert_eq!(degree, 8);
         assert_eq!(
             constants,
@@ -235,8 +532,8 @@ mod test {
             macro minus_one(X) { X - 1 };
             pol constant EVEN(i) { 2 * minus_one(i) };
         "#;
-        let analyzed = analyze_string(src);
-        let (constants, degree) = generate(&analyzed);
+        let analyzed = analyze_string::<GoldilocksField>(src);
+        let (constants, degree) = generate(&analyzed).unwrap();
         assert_eq!(degree, 8);
         assert_eq!(
             constants,
@@ -256,8 +553,8 @@ mod test {
             macro ite(C, T, F) { is_one(C) * T + is_zero(C) * F };
             pol constant TEN(i) { ite(is_equal(i, 10), 1, 0) };
         "#;
-        let analyzed = analyze_string(src);
-        let (constants, degree) = generate(&analyzed);
+        let analyzed = analyze_string::<GoldilocksField>(src);
+        let (constants, degree) = generate(&analyzed).unwrap();
         assert_eq!(degree, 12);
         assert_eq!(
             constants,
@@ -278,8 +575,8 @@ mod test {
             col fixed half_nibble(i) { i & 0x7 };
             col fixed doubled_half_nibble(i) { half_nibble(i / 2) };
         "#;
-        let analyzed = analyze_string(src);
-        let (constants, degree) = generate(&analyzed);
+        let analyzed = analyze_string::<GoldilocksField>(src);
+        let (constants, degree) = generate(&analyzed).unwrap();
         assert_eq!(degree, 10);
         assert_eq!(constants.len(), 4);
         assert_eq!(
@@ -318,8 +615,8 @@ mod test {
             col fixed empty = [] + [0]*;
             col fixed ref_other = [%N-1, alt(1), 8] + [0]*;
         "#;
-        let analyzed = analyze_string(src);
-        let (constants, degree) = generate(&analyzed);
+        let analyzed = analyze_string::<GoldilocksField>(src);
+        let (constants, degree) = generate(&analyzed).unwrap();
         assert_eq!(degree, 10);
         assert_eq!(constants.len(), 3);
         assert_eq!(
@@ -343,8 +640,8 @@ mod test {
             namespace F(%N);
             col fixed arr = [0, 1, 2]* + [7];
         "#;
-        let analyzed = analyze_string(src);
-        let (constants, degree) = generate(&analyzed);
+        let analyzed = analyze_string::<GoldilocksField>(src);
+        let (constants, degree) = generate(&analyzed).unwrap();
         assert_eq!(degree, 10);
         assert_eq!(constants.len(), 1);
         assert_eq!(
@@ -352,4 +649,84 @@ mod test {
             ("F.arr", convert([0i32, 1, 2, 0, 1, 2, 0, 1, 2, 7].to_vec()))
         );
     }
+
+    #[test]
+    pub fn large_values_bn254() {
+        let src = r#"
+            constant %N = 4;
+            namespace F(%N);
+            pol constant X(i) { 2**100 * i + 0x10000000000000000 };
+        "#;
+        let analyzed = analyze_string::<Bn254Field>(src);
+        let (constants, _) = generate(&analyzed).unwrap();
+        let two_to_the_100 = Bn254Field::from(2).pow(100u32.into());
+        let two_to_the_64 = Bn254Field::from(2).pow(64u32.into());
+        assert_eq!(
+            constants[0].1,
+            (0..4)
+                .map(|i| two_to_the_100 * Bn254Field::from(i) + two_to_the_64)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    pub fn evaluate_single_columns() {
+        let src = r#"
+            constant %N = 10;
+            namespace F(%N);
+            col fixed seq(i) { i };
+            col fixed doub(i) { seq((2 * i) % %N) + 1 };
+            col fixed BITS = [0, 1]*;
+            col witness w;
+        "#;
+        let analyzed = analyze_string::<GoldilocksField>(src);
+        assert_eq!(
+            evaluate_column(&analyzed, "F.doub", 3..7),
+            Ok(convert(vec![7, 9, 1, 3]))
+        );
+        assert_eq!(
+            evaluate_column(&analyzed, "F.BITS", [9, 0]),
+            Ok(convert(vec![1, 0]))
+        );
+        assert_eq!(
+            evaluate_column::<GoldilocksField>(&analyzed, "F.seq", [10]),
+            Err("Row 10 is out of range, the degree of F.seq is 10.".to_string())
+        );
+        assert_eq!(
+            evaluate_column::<GoldilocksField>(&analyzed, "F.w", [0]),
+            Err("F.w is not a fixed column.".to_string())
+        );
+    }
+
+    #[test]
+    pub fn column_wise_matches_row_wise() {
+        let src = r#"
+            constant %N = 16;
+            namespace F(%N);
+            pol constant A(i) { -(i - 3) * (i + %N) - 7 * i * i + 2 };
+            pol constant B(i) { (-(i - 3) * (i + %N) - 7 * i * i + 2) + 0 * (i & 0) };
+        "#;
+        let analyzed = analyze_string::<GoldilocksField>(src);
+        let (constants, _) = generate(&analyzed).unwrap();
+        assert_eq!(constants[0].1, constants[1].1);
+        assert_eq!(
+            constants[0].1,
+            (0..16)
+                .map(|i: i32| (-(i - 3) * (i + 16) - 7 * i * i + 2).into())
+                .collect::<Vec<GoldilocksField>>()
+        );
+    }
+
+    #[test]
+    pub fn no_matching_arm() {
+        let src = r#"
+            namespace F(4);
+            col fixed A(i) { match i { 0 => 1, 2 => 3, } };
+        "#;
+        let analyzed = analyze_string::<GoldilocksField>(src);
+        assert_eq!(
+            generate(&analyzed).err().unwrap(),
+            "No arm matched the value 1. (in the definition of F.A)"
+        );
+    }
 }
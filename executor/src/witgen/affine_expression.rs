@@ -1,5 +1,6 @@
 use std::collections::BTreeMap;
 
+use num_traits::Zero;
 use number::AbstractNumberType;
 // TODO this should probably rather be a finite field element.
 use number::FieldElement;
@@ -11,15 +12,15 @@ use super::{EvalError::*, EvalResult, EvalValue, IncompleteCause};
 
 /// An expression affine in the committed polynomials (or symbolic variables in general).
 #[derive(Debug, Clone)]
-pub struct AffineExpression {
-    pub coefficients: BTreeMap<usize, FieldElement>,
-    pub offset: FieldElement,
+pub struct AffineExpression<T> {
+    pub coefficients: BTreeMap<usize, T>,
+    pub offset: T,
 }
 
-pub type AffineResult = Result<AffineExpression, IncompleteCause>;
+pub type AffineResult<T> = Result<AffineExpression<T>, IncompleteCause>;
 
-impl From<FieldElement> for AffineExpression {
-    fn from(value: FieldElement) -> Self {
+impl<T: FieldElement> From<T> for AffineExpression<T> {
+    fn from(value: T) -> Self {
         AffineExpression {
             coefficients: Default::default(),
             offset: value,
@@ -27,17 +28,8 @@ impl From<FieldElement> for AffineExpression {
     }
 }
 
-impl From<u32> for AffineExpression {
-    fn from(value: u32) -> Self {
-        AffineExpression {
-            coefficients: Default::default(),
-            offset: value.into(),
-        }
-    }
-}
-
-impl AffineExpression {
-    pub fn from_variable_id(var_id: usize) -> AffineExpression {
+impl<T: FieldElement> AffineExpression<T> {
+    pub fn from_variable_id(var_id: usize) -> AffineExpression<T> {
         AffineExpression {
             coefficients: BTreeMap::from([(var_id, 1.into())]),
             offset: 0.into(),
@@ -48,7 +40,7 @@ impl AffineExpression {
         self.nonzero_coefficients().next().is_none()
     }
 
-    pub fn constant_value(&self) -> Option<FieldElement> {
+    pub fn constant_value(&self) -> Option<T> {
         if self.is_constant() {
             Some(self.offset)
         } else {
@@ -61,13 +53,13 @@ impl AffineExpression {
     }
 
     /// @returns an iterator of the nonzero coefficients and their variable IDs (but not the offset).
-    pub fn nonzero_coefficients(&self) -> impl Iterator<Item = (usize, &FieldElement)> {
+    pub fn nonzero_coefficients(&self) -> impl Iterator<Item = (usize, &T)> {
         self.coefficients
             .iter()
             .filter_map(|(i, c)| (!c.is_zero()).then_some((*i, c)))
     }
 
-    pub fn mul(mut self, factor: FieldElement) -> AffineExpression {
+    pub fn mul(mut self, factor: T) -> AffineExpression<T> {
         for f in self.coefficients.values_mut() {
             *f = *f * factor;
         }
@@ -79,7 +71,7 @@ impl AffineExpression {
     /// returns the index of the variable and the assignment that evaluates the
     /// affine expression to zero.
     /// Returns an error if the constraint is unsat
-    pub fn solve(&self) -> Result<EvalValue, ()> {
+    pub fn solve(&self) -> Result<EvalValue<T>, ()> {
         let mut nonzero = self.nonzero_coefficients();
         let first = nonzero.next();
         let second = nonzero.next();
@@ -119,7 +111,7 @@ impl AffineExpression {
     pub fn solve_with_bit_constraints(
         &self,
         known_constraints: &impl BitConstraintSet,
-    ) -> EvalResult {
+    ) -> EvalResult<T> {
         // Try to solve directly.
         match self.solve() {
             Ok(value) if value.is_complete() => return Ok(value),
@@ -162,7 +154,7 @@ impl AffineExpression {
     fn try_transfer_constraints(
         &self,
         known_constraints: &impl BitConstraintSet,
-    ) -> Option<EvalValue> {
+    ) -> Option<EvalValue<T>> {
         // We need the form X = a * Y + b * Z + ...
         // where X is unconstrained and all others are bit-constrained.
         let mut unconstrained = self
@@ -211,7 +203,7 @@ impl AffineExpression {
     fn try_solve_through_constraints(
         &self,
         known_constraints: &impl BitConstraintSet,
-    ) -> EvalResult {
+    ) -> EvalResult<T> {
         let parts = self
             .nonzero_coefficients()
             .map(|(i, coeff)| {
@@ -238,27 +230,28 @@ impl AffineExpression {
         }
 
         // Check if they are mutually exclusive and compute assignments.
-        let mut covered_bits: AbstractNumberType = 0;
+        let mut covered_bits = AbstractNumberType::zero();
         let mut assignments = EvalValue::complete(vec![]);
         let mut offset = (-self.offset).to_integer();
         for (i, coeff, constraint) in parts {
             let constraint = constraint.clone().unwrap();
             let mask = constraint.mask();
-            if mask & covered_bits != 0 {
+            if !(mask & &covered_bits).is_zero() {
                 return Ok(EvalValue::incomplete(
                     IncompleteCause::OverlappingBitConstraints,
                 ));
             } else {
                 covered_bits |= mask;
             }
+            let masked_offset = &offset & mask;
             assignments.combine(EvalValue::complete(vec![(
                 i,
-                Constraint::Assignment(((offset & mask) / coeff.to_integer()).into()),
+                Constraint::Assignment((&masked_offset / coeff.to_integer()).into()),
             )]));
-            offset &= !mask;
+            offset ^= masked_offset;
         }
 
-        if offset != 0 {
+        if !offset.is_zero() {
             // We were not able to cover all of the offset, so this equation cannot be solved.
             Err(ConflictingBitConstraints)
         } else {
@@ -284,14 +277,14 @@ impl AffineExpression {
     }
 }
 
-impl PartialEq for AffineExpression {
+impl<T: FieldElement> PartialEq for AffineExpression<T> {
     fn eq(&self, other: &Self) -> bool {
         self.offset == other.offset && self.nonzero_coefficients().eq(other.nonzero_coefficients())
     }
 }
 
-impl std::ops::Add for AffineExpression {
-    type Output = AffineExpression;
+impl<T: FieldElement> std::ops::Add for AffineExpression<T> {
+    type Output = AffineExpression<T>;
 
     fn add(self, rhs: Self) -> Self::Output {
         let mut coefficients = rhs.coefficients;
@@ -308,8 +301,8 @@ impl std::ops::Add for AffineExpression {
     }
 }
 
-impl std::ops::Neg for AffineExpression {
-    type Output = AffineExpression;
+impl<T: FieldElement> std::ops::Neg for AffineExpression<T> {
+    type Output = AffineExpression<T>;
 
     fn neg(mut self) -> Self::Output {
         self.coefficients.values_mut().for_each(|v| *v = -*v);
@@ -318,8 +311,8 @@ impl std::ops::Neg for AffineExpression {
     }
 }
 
-impl std::ops::Sub for AffineExpression {
-    type Output = AffineExpression;
+impl<T: FieldElement> std::ops::Sub for AffineExpression<T> {
+    type Output = AffineExpression<T>;
 
     fn sub(self, rhs: Self) -> Self::Output {
         self + -rhs
@@ -332,18 +325,18 @@ mod test {
 
     use super::*;
     use crate::witgen::{bit_constraints::BitConstraint, EvalError};
-    use number::FieldElement;
+    use number::GoldilocksField;
 
-    fn convert<T>(input: Vec<T>) -> BTreeMap<usize, FieldElement>
+    fn convert<U>(input: Vec<U>) -> BTreeMap<usize, GoldilocksField>
     where
-        T: Into<FieldElement> + Copy,
+        U: Into<GoldilocksField> + Copy,
     {
         input.iter().map(|x| (*x).into()).enumerate().collect()
     }
 
     #[test]
     pub fn test_affine_neg() {
-        let a = AffineExpression {
+        let a = AffineExpression::<GoldilocksField> {
             coefficients: convert(vec![1, 0, 2]),
             offset: 9.into(),
         };
@@ -351,18 +344,18 @@ mod test {
             -a,
             AffineExpression {
                 coefficients: convert(vec![
-                    FieldElement::from(0) - FieldElement::from(1u64),
+                    GoldilocksField::from(0) - GoldilocksField::from(1u64),
                     0.into(),
-                    FieldElement::from(0) - FieldElement::from(2u64),
+                    GoldilocksField::from(0) - GoldilocksField::from(2u64),
                 ]),
-                offset: FieldElement::from(0) - FieldElement::from(9u64),
+                offset: GoldilocksField::from(0) - GoldilocksField::from(9u64),
             },
         );
     }
 
     #[test]
     pub fn test_affine_add() {
-        let a = AffineExpression {
+        let a = AffineExpression::<GoldilocksField> {
             coefficients: convert(vec![1, 2]),
             offset: 3.into(),
         };
@@ -389,7 +382,7 @@ mod test {
 
     #[test]
     pub fn derive_constraints() {
-        let expr = AffineExpression::from_variable_id(1)
+        let expr = AffineExpression::<GoldilocksField>::from_variable_id(1)
             - AffineExpression::from_variable_id(2).mul(16.into())
             - AffineExpression::from_variable_id(3);
         let known_constraints = TestBitConstraints(
@@ -424,7 +417,7 @@ mod test {
         );
 
         // Replace factor 16 by 32.
-        let expr = AffineExpression::from_variable_id(1)
+        let expr = AffineExpression::<GoldilocksField>::from_variable_id(1)
             - AffineExpression::from_variable_id(2).mul(32.into())
             - AffineExpression::from_variable_id(3);
         assert_eq!(
@@ -432,14 +425,14 @@ mod test {
             EvalValue::incomplete_with_constraints(
                 vec![(
                     1,
-                    Constraint::BitConstraint(BitConstraint::from_mask(0x1fef))
+                    Constraint::BitConstraint(BitConstraint::from_mask(0x1fefu32))
                 )],
                 IncompleteCause::NotConcrete
             )
         );
 
         // Replace factor 16 by 8.
-        let expr = AffineExpression::from_variable_id(1)
+        let expr = AffineExpression::<GoldilocksField>::from_variable_id(1)
             - AffineExpression::from_variable_id(2).mul(8.into())
             - AffineExpression::from_variable_id(3);
         assert_eq!(
@@ -453,7 +446,7 @@ mod test {
     #[test]
     pub fn solve_through_constraints_success() {
         let value = 0x1504u32;
-        let expr = AffineExpression::from(value)
+        let expr = AffineExpression::from(GoldilocksField::from(value))
             - AffineExpression::from_variable_id(2).mul(256.into())
             - AffineExpression::from_variable_id(3);
         let known_constraints = TestBitConstraints(
@@ -477,7 +470,7 @@ mod test {
     #[test]
     pub fn solve_through_constraints_conflict() {
         let value = 0x1554u32;
-        let expr = AffineExpression::from(value)
+        let expr = AffineExpression::from(GoldilocksField::from(value))
             - AffineExpression::from_variable_id(2).mul(256.into())
             - AffineExpression::from_variable_id(3);
         let known_constraints = TestBitConstraints(
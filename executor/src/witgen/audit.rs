@@ -0,0 +1,165 @@
+//! The record of how witness generation determined the value of each cell,
+//! to answer "why is this cell 7?" when reviewing a machine.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::io;
+
+use number::{DegreeType, FieldElement};
+use pil_analyzer::{ExpressionArena, Identity};
+
+/// What determined the value of a cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cause<'a, T> {
+    /// The identity, evaluated on the row and the row before.
+    Identity(&'a Identity<T>),
+    /// The answer to the query of the column.
+    Query,
+    /// The value `period` rows before, because the rows started to repeat.
+    Repetition(usize),
+    /// Nothing determined the value and zero was chosen.
+    Default,
+    /// The machine with this name computed the column.
+    Machine(String),
+    /// The number of lookups of the row of the table of the identity.
+    Multiplicity(&'a Identity<T>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deduction<'a, T> {
+    pub row: DegreeType,
+    pub column: &'a str,
+    pub value: T,
+    pub cause: Cause<'a, T>,
+}
+
+/// The deductions of all cells of the witness, in the order in which
+/// witness generation made them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditLog<'a, T> {
+    pub deductions: Vec<Deduction<'a, T>>,
+    /// The operands of the expressions of the identities.
+    pub expressions: &'a ExpressionArena<T>,
+}
+
+impl<'a, T: FieldElement> AuditLog<'a, T> {
+    /// Returns the deduction of the cell, which is the last one if the value
+    /// was replaced.
+    pub fn cell(&self, column: &str, row: DegreeType) -> Option<&Deduction<'a, T>> {
+        self.deductions
+            .iter()
+            .rev()
+            .find(|d| d.column == column && d.row == row)
+    }
+
+    /// Writes the log as text: First the identities that determined values,
+    /// numbered, then one line per deduction with the row, the column, the
+    /// value and the cause, in the order of deduction:
+    /// ```text
+    /// identity 0 = fibonacci.pil:9: Main.y' = (Main.x + Main.y)
+    /// 1 Main.y 2 identity 0
+    /// ```
+    pub fn write(&self, output: &mut dyn io::Write) -> io::Result<()> {
+        let mut numbers = HashMap::new();
+        for deduction in &self.deductions {
+            if let Cause::Identity(identity) | Cause::Multiplicity(identity) = &deduction.cause {
+                let number = numbers.len();
+                if let Entry::Vacant(entry) = numbers.entry(*identity as *const Identity<T>) {
+                    entry.insert(number);
+                    writeln!(
+                        output,
+                        "identity {number} = {}:{}: {}",
+                        identity.source.file,
+                        identity.source.line,
+                        self.expressions.display(*identity)
+                    )?;
+                }
+            }
+        }
+        for Deduction {
+            row,
+            column,
+            value,
+            cause,
+        } in &self.deductions
+        {
+            write!(output, "{row} {column} {value} ")?;
+            match cause {
+                Cause::Identity(identity) => {
+                    writeln!(output, "identity {}", numbers[&(*identity as *const _)])
+                }
+                Cause::Multiplicity(identity) => {
+                    writeln!(output, "multiplicity {}", numbers[&(*identity as *const _)])
+                }
+                cause => writeln!(output, "{}", self.format_cause(cause)),
+            }?;
+        }
+        Ok(())
+    }
+
+    /// Formats the cause with the location and the text of its identity,
+    /// if any.
+    pub fn format_cause(&self, cause: &Cause<'a, T>) -> String {
+        match cause {
+            Cause::Identity(identity) => format!(
+                "identity {}:{}: {}",
+                identity.source.file,
+                identity.source.line,
+                self.expressions.display(*identity)
+            ),
+            Cause::Query => "query".to_string(),
+            Cause::Repetition(period) => format!("repetition {period}"),
+            Cause::Default => "default".to_string(),
+            Cause::Machine(name) => format!("machine {name}"),
+            Cause::Multiplicity(identity) => format!(
+                "multiplicity {}:{}: {}",
+                identity.source.file,
+                identity.source.line,
+                self.expressions.display(*identity)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+    use pil_analyzer::analyze_string;
+
+    use crate::constant_evaluator;
+    use crate::witgen::{generate_with_audit, inputs_query_callback};
+
+    #[test]
+    fn write_log() {
+        let analyzed = analyze_string::<GoldilocksField>(
+            r#"
+namespace Main(2);
+    col fixed FIRST = [1, 0];
+    col witness input(i) query ("input", i);
+    col witness double, free;
+    double = 2 * input;
+"#,
+        );
+        let (fixed, degree) = constant_evaluator::generate(&analyzed).unwrap();
+        let (_, log) = generate_with_audit(
+            &analyzed,
+            degree,
+            &fixed,
+            Some(inputs_query_callback(vec![3.into(), 4.into()], 0)),
+        );
+        let mut output = vec![];
+        log.write(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "identity 0 = input:6: Main.double = (2 * Main.input);
+0 Main.input 3 query
+0 Main.double 6 identity 0
+0 Main.free 0 default
+1 Main.input 4 query
+1 Main.double 8 identity 0
+1 Main.free 0 default
+"
+        );
+        assert_eq!(log.cell("Main.double", 1).unwrap().value, 8.into());
+    }
+}
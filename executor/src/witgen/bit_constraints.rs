@@ -1,9 +1,10 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{BTreeMap, HashSet};
 use std::fmt::{Display, Formatter};
 
 use crate::witgen::util::{contains_next_ref, WitnessColumnNamer};
+use num_traits::{One, Zero};
 use number::{AbstractNumberType, FieldElement};
-use pil_analyzer::{BinaryOperator, Expression, Identity, IdentityKind};
+use pil_analyzer::{BinaryOperator, Expression, Identity, IdentityKind, Symbol};
 
 use super::expression_evaluator::ExpressionEvaluator;
 use super::symbolic_evaluator::SymbolicEvaluator;
@@ -23,19 +24,19 @@ impl BitConstraint {
     pub fn from_max_bit(max_bit: u64) -> Self {
         assert!(max_bit < 1024);
         BitConstraint {
-            mask: (1 << (max_bit + 1)) - 1,
+            mask: (AbstractNumberType::one() << (max_bit + 1)) - 1u32,
         }
     }
 
-    pub fn from_mask(mask: AbstractNumberType) -> Self {
-        BitConstraint { mask }
+    pub fn from_mask<M: Into<AbstractNumberType>>(mask: M) -> Self {
+        BitConstraint { mask: mask.into() }
     }
 
     /// The bit constraint of the sum of two expressions.
     pub fn try_combine_sum(&self, other: &BitConstraint) -> Option<BitConstraint> {
-        if self.mask & other.mask == 0 {
+        if (&self.mask & &other.mask).is_zero() {
             Some(BitConstraint {
-                mask: self.mask | other.mask,
+                mask: &self.mask | &other.mask,
             })
         } else {
             None
@@ -45,31 +46,27 @@ impl BitConstraint {
     /// Returns the conjunction of this constraint and the other.
     pub fn conjunction(self, other: &BitConstraint) -> BitConstraint {
         BitConstraint {
-            mask: self.mask & other.mask,
+            mask: self.mask & &other.mask,
         }
     }
 
     /// The bit constraint of an integer multiple of an expression.
-    /// TODO this assumes goldilocks
-    pub fn multiple(&self, factor: FieldElement) -> Option<BitConstraint> {
-        if factor.to_integer() * self.mask >= FieldElement::modulus() {
+    /// Only multiples by powers of two are supported and the result
+    /// has to stay below the modulus, which can easily be violated
+    /// in small fields.
+    pub fn multiple<T: FieldElement>(&self, factor: T) -> Option<BitConstraint> {
+        let factor = factor.to_integer();
+        if &factor * &self.mask >= T::modulus() || factor.count_ones() != 1 {
             None
         } else {
-            // TODO use binary logarithm
-            (0..64).find_map(|i| {
-                if factor == (1u64 << i).into() {
-                    Some(BitConstraint {
-                        mask: self.mask << i,
-                    })
-                } else {
-                    None
-                }
+            Some(BitConstraint {
+                mask: &self.mask << factor.trailing_zeros().unwrap(),
             })
         }
     }
 
-    pub fn mask(&self) -> AbstractNumberType {
-        self.mask
+    pub fn mask(&self) -> &AbstractNumberType {
+        &self.mask
     }
 }
 
@@ -93,14 +90,14 @@ pub trait BitConstraintSet {
 }
 
 pub struct SimpleBitConstraintSet<'a, Namer: WitnessColumnNamer> {
-    bit_constraints: &'a BTreeMap<&'a str, BitConstraint>,
+    bit_constraints: &'a BTreeMap<Symbol, BitConstraint>,
     names: &'a Namer,
 }
 
 impl<'a, Namer: WitnessColumnNamer> BitConstraintSet for SimpleBitConstraintSet<'a, Namer> {
     fn bit_constraint(&self, id: usize) -> Option<BitConstraint> {
         self.bit_constraints
-            .get(self.names.name(id).as_str())
+            .get(&Symbol::intern(&self.names.name(id)))
             .cloned()
     }
 }
@@ -109,15 +106,15 @@ impl<'a, Namer: WitnessColumnNamer> BitConstraintSet for SimpleBitConstraintSet<
 /// Removes identities that only serve to create bit constraints from
 /// the identities vector.
 /// TODO at some point, we should check that they still hold.
-pub fn determine_global_constraints<'a>(
-    fixed_data: &'a FixedData,
-    identities: Vec<&'a Identity>,
-) -> (BTreeMap<&'a str, BitConstraint>, Vec<&'a Identity>) {
+pub fn determine_global_constraints<'a, T: FieldElement>(
+    fixed_data: &'a FixedData<T>,
+    identities: Vec<&'a Identity<T>>,
+) -> (BTreeMap<Symbol, BitConstraint>, Vec<&'a Identity<T>>) {
     let mut known_constraints = BTreeMap::new();
     // For these columns, we know that they are not only constrained to those bits
     // but also have one row for each possible value.
     // It allows us to completely remove some lookups.
-    let mut full_span = BTreeSet::new();
+    let mut full_span = HashSet::new();
     for (&name, &values) in &fixed_data.fixed_cols {
         if let Some((cons, full)) = process_fixed_column(values) {
             assert!(known_constraints.insert(name, cons).is_none());
@@ -147,7 +144,7 @@ pub fn determine_global_constraints<'a>(
     }
     log::debug!("Determined the following identities to be purely bit/range constraints:");
     for id in removed_identities {
-        log::debug!("  {id}");
+        log::debug!("  {}", fixed_data.expressions.display(id));
     }
 
     (known_constraints, retained_identities)
@@ -156,18 +153,18 @@ pub fn determine_global_constraints<'a>(
 /// Analyzes a fixed column and checks if its values correspond exactly
 /// to a certain bit pattern.
 /// TODO do this on the symbolic definition instead of the values.
-fn process_fixed_column(fixed: &[FieldElement]) -> Option<(BitConstraint, bool)> {
+fn process_fixed_column<T: FieldElement>(fixed: &[T]) -> Option<(BitConstraint, bool)> {
     if let Some(bit) = smallest_period_candidate(fixed) {
-        let mask: AbstractNumberType = (1 << bit) - 1;
+        let mask = (AbstractNumberType::one() << bit) - 1u32;
         if fixed
             .iter()
             .enumerate()
-            .all(|(i, v)| v.to_integer() == i as AbstractNumberType & mask)
+            .all(|(i, v)| v.to_integer() == AbstractNumberType::from(i) & &mask)
         {
             return Some((BitConstraint::from_mask(mask), true));
         }
     }
-    let mut mask = 0;
+    let mut mask = AbstractNumberType::zero();
     for v in fixed.iter() {
         mask |= v.to_integer();
     }
@@ -179,12 +176,12 @@ fn process_fixed_column(fixed: &[FieldElement]) -> Option<(BitConstraint, bool)>
 /// and identities. Note that these constraints hold globally, i.e. for all rows.
 /// If the returned flag is true, the identity can be removed, because it contains
 /// no further information than the bit constraint.
-fn propagate_constraints<'a>(
-    fixed_data: &'a FixedData,
-    mut known_constraints: BTreeMap<&'a str, BitConstraint>,
-    identity: &'a Identity,
-    full_span: &BTreeSet<&'a str>,
-) -> (BTreeMap<&'a str, BitConstraint>, bool) {
+fn propagate_constraints<'a, T: FieldElement>(
+    fixed_data: &'a FixedData<T>,
+    mut known_constraints: BTreeMap<Symbol, BitConstraint>,
+    identity: &'a Identity<T>,
+    full_span: &HashSet<Symbol>,
+) -> (BTreeMap<Symbol, BitConstraint>, bool) {
     let mut remove = false;
     match identity.kind {
         IdentityKind::Polynomial => {
@@ -206,7 +203,10 @@ fn propagate_constraints<'a>(
                     .or_insert(c);
             }
         }
-        IdentityKind::Plookup | IdentityKind::Permutation | IdentityKind::Connect => {
+        IdentityKind::Plookup
+        | IdentityKind::LogUp
+        | IdentityKind::Permutation
+        | IdentityKind::Connect => {
             if identity.left.selector.is_some() || identity.right.selector.is_some() {
                 return (known_constraints, false);
             }
@@ -217,7 +217,7 @@ fn propagate_constraints<'a>(
                 .zip(identity.right.expressions.iter())
             {
                 if let (Some(left), Some(right)) = (is_simple_poly(left), is_simple_poly(right)) {
-                    if let Some(constraint) = known_constraints.get(right).cloned() {
+                    if let Some(constraint) = known_constraints.get(&right).cloned() {
                         known_constraints
                             .entry(left)
                             .and_modify(|existing| {
@@ -227,11 +227,13 @@ fn propagate_constraints<'a>(
                     }
                 }
             }
-            if identity.kind == IdentityKind::Plookup && identity.right.expressions.len() == 1 {
+            if matches!(identity.kind, IdentityKind::Plookup | IdentityKind::LogUp)
+                && identity.right.expressions.len() == 1
+            {
                 // We can only remove the lookup if the RHS is a fixed polynomial that
                 // provides all values in the span.
                 if let Some(name) = is_simple_poly(&identity.right.expressions[0]) {
-                    if full_span.contains(name) {
+                    if full_span.contains(&name) {
                         remove = true;
                     }
                 }
@@ -243,22 +245,25 @@ fn propagate_constraints<'a>(
 }
 
 /// Tries to find "X * (1 - X) = 0"
-fn is_binary_constraint<'a>(fixed_data: &'a FixedData, expr: &Expression) -> Option<&'a str> {
+fn is_binary_constraint<T: FieldElement>(
+    fixed_data: &FixedData<T>,
+    expr: &Expression<T>,
+) -> Option<Symbol> {
     // TODO Write a proper pattern matching engine.
     if let Expression::BinaryOperation(left, BinaryOperator::Sub, right) = expr {
-        if let Expression::Number(n) = right.as_ref() {
+        if let Expression::Number(n) = &fixed_data.expressions[*right] {
             if *n == 0.into() {
-                return is_binary_constraint(fixed_data, left.as_ref());
+                return is_binary_constraint(fixed_data, &fixed_data.expressions[*left]);
             }
         }
     } else if let Expression::BinaryOperation(left, BinaryOperator::Mul, right) = expr {
         let symbolic_ev = SymbolicEvaluator::new(fixed_data);
-        let left_root = ExpressionEvaluator::new(symbolic_ev.clone())
-            .evaluate(left)
+        let left_root = ExpressionEvaluator::new(fixed_data.expressions, symbolic_ev.clone())
+            .evaluate(&fixed_data.expressions[*left])
             .ok()
             .and_then(|l| l.solve().ok())?;
-        let right_root = ExpressionEvaluator::new(symbolic_ev.clone())
-            .evaluate(right)
+        let right_root = ExpressionEvaluator::new(fixed_data.expressions, symbolic_ev.clone())
+            .evaluate(&fixed_data.expressions[*right])
             .ok()
             .and_then(|r| r.solve().ok())?;
         if let ([(id1, Constraint::Assignment(value1))], [(id2, Constraint::Assignment(value2))]) =
@@ -266,7 +271,7 @@ fn is_binary_constraint<'a>(fixed_data: &'a FixedData, expr: &Expression) -> Opt
         {
             let poly1 = symbolic_ev.poly_from_id(*id1);
             let poly2 = symbolic_ev.poly_from_id(*id2);
-            if poly1 != poly2 || !fixed_data.witness_ids.contains_key(poly1.0) {
+            if poly1 != poly2 || !fixed_data.witness_ids.contains_key(&poly1.0) {
                 return None;
             }
             if (*value1 == 0.into() && *value2 == 1.into())
@@ -280,17 +285,17 @@ fn is_binary_constraint<'a>(fixed_data: &'a FixedData, expr: &Expression) -> Opt
 }
 
 /// Tries to transfer constraints in a linear expression.
-fn try_transfer_constraints<'a>(
-    fixed_data: &'a FixedData,
-    expr: &'a Expression,
-    known_constraints: &BTreeMap<&str, BitConstraint>,
-) -> Option<(&'a str, BitConstraint)> {
-    if contains_next_ref(expr) {
+fn try_transfer_constraints<'a, T: FieldElement>(
+    fixed_data: &'a FixedData<T>,
+    expr: &'a Expression<T>,
+    known_constraints: &BTreeMap<Symbol, BitConstraint>,
+) -> Option<(Symbol, BitConstraint)> {
+    if contains_next_ref(expr, fixed_data) {
         return None;
     }
 
     let symbolic_ev = SymbolicEvaluator::new(fixed_data);
-    let aff_expr = ExpressionEvaluator::new(symbolic_ev.clone())
+    let aff_expr = ExpressionEvaluator::new(fixed_data.expressions, symbolic_ev.clone())
         .evaluate(expr)
         .ok()?;
 
@@ -312,7 +317,7 @@ fn try_transfer_constraints<'a>(
     })
 }
 
-fn smallest_period_candidate(fixed: &[FieldElement]) -> Option<u64> {
+fn smallest_period_candidate<T: FieldElement>(fixed: &[T]) -> Option<u64> {
     if fixed.first() != Some(&0.into()) {
         return None;
     }
@@ -323,6 +328,9 @@ fn smallest_period_candidate(fixed: &[FieldElement]) -> Option<u64> {
 mod test {
     use std::collections::BTreeMap;
 
+    use number::{BabyBearField, GoldilocksField};
+    use pil_analyzer::Symbol;
+
     use crate::witgen::bit_constraints::{propagate_constraints, BitConstraint};
     use crate::witgen::{FixedData, WitnessColumn};
 
@@ -330,10 +338,13 @@ mod test {
 
     #[test]
     fn all_zeros() {
-        let fixed = [0, 0, 0, 0].iter().map(|v| (*v).into()).collect::<Vec<_>>();
+        let fixed = [0, 0, 0, 0]
+            .iter()
+            .map(|v| GoldilocksField::from(*v))
+            .collect::<Vec<_>>();
         assert_eq!(
             process_fixed_column(&fixed),
-            Some((BitConstraint::from_mask(0), false))
+            Some((BitConstraint::from_mask(0u32), false))
         );
     }
 
@@ -341,11 +352,11 @@ mod test {
     fn zero_one() {
         let fixed = [0, 1, 0, 1, 0]
             .iter()
-            .map(|v| (*v).into())
+            .map(|v| GoldilocksField::from(*v))
             .collect::<Vec<_>>();
         assert_eq!(
             process_fixed_column(&fixed),
-            Some((BitConstraint::from_mask(1), true))
+            Some((BitConstraint::from_mask(1u32), true))
         );
     }
 
@@ -353,11 +364,11 @@ mod test {
     fn zero_one_two_three() {
         let fixed = [0, 1, 2, 3, 0]
             .iter()
-            .map(|v| (*v).into())
+            .map(|v| GoldilocksField::from(*v))
             .collect::<Vec<_>>();
         assert_eq!(
             process_fixed_column(&fixed),
-            Some((BitConstraint::from_mask(3), true))
+            Some((BitConstraint::from_mask(3u32), true))
         );
     }
 
@@ -365,11 +376,11 @@ mod test {
     fn various_with_bit_mask() {
         let fixed = [0, 6, 0x0100, 0x1100, 2]
             .iter()
-            .map(|v| (*v).into())
+            .map(|v| GoldilocksField::from(*v))
             .collect::<Vec<_>>();
         assert_eq!(
             process_fixed_column(&fixed),
-            Some((BitConstraint::from_mask(0x1106), false))
+            Some((BitConstraint::from_mask(0x1106u32), false))
         );
     }
 
@@ -391,12 +402,13 @@ namespace Global(2**20);
     { D } in { BYTE };
     { D } in { SHIFTED };
 ";
-        let analyzed = pil_analyzer::analyze_string(pil_source);
-        let (constants, degree) = crate::constant_evaluator::generate(&analyzed);
+        let analyzed = pil_analyzer::analyze_string::<GoldilocksField>(pil_source);
+        let (constants, degree) = crate::constant_evaluator::generate(&analyzed).unwrap();
         let mut known_constraints = constants
             .iter()
             .filter_map(|(name, values)| {
-                process_fixed_column(values).map(|(constraint, _full)| (*name, constraint))
+                process_fixed_column(values)
+                    .map(|(constraint, _full)| (Symbol::intern(name), constraint))
             })
             .collect::<BTreeMap<_, _>>();
         assert_eq!(
@@ -404,13 +416,14 @@ namespace Global(2**20);
             vec![
                 ("Global.BYTE", BitConstraint::from_max_bit(7)),
                 ("Global.BYTE2", BitConstraint::from_max_bit(15)),
-                ("Global.SHIFTED", BitConstraint::from_mask(0xff0)),
+                ("Global.SHIFTED", BitConstraint::from_mask(0xff0u32)),
             ]
             .into_iter()
+            .map(|(name, constraint)| (Symbol::intern(name), constraint))
             .collect()
         );
         // TODO write some test code to generate FixedData directly from `analyzed`
-        let witness_cols: Vec<WitnessColumn> = analyzed
+        let witness_cols: Vec<WitnessColumn<GoldilocksField>> = analyzed
             .committed_polys_in_source_order()
             .iter()
             .enumerate()
@@ -424,9 +437,14 @@ namespace Global(2**20);
         let fixed_data = FixedData::new(
             degree,
             &analyzed.constants,
+            &analyzed.expressions,
+            &analyzed.sources,
             constants.iter().map(|(n, v)| (*n, v)).collect(),
             &witness_cols,
-            witness_cols.iter().map(|w| (w.name, w.id)).collect(),
+            witness_cols
+                .iter()
+                .map(|w| (w.name.as_str(), w.id))
+                .collect(),
         );
         for identity in &analyzed.identities {
             (known_constraints, _) = propagate_constraints(
@@ -441,13 +459,14 @@ namespace Global(2**20);
             vec![
                 ("Global.A", BitConstraint::from_max_bit(0)),
                 ("Global.B", BitConstraint::from_max_bit(7)),
-                ("Global.C", BitConstraint::from_mask(0x2ff)),
-                ("Global.D", BitConstraint::from_mask(0xf0)),
+                ("Global.C", BitConstraint::from_mask(0x2ffu32)),
+                ("Global.D", BitConstraint::from_mask(0xf0u32)),
                 ("Global.BYTE", BitConstraint::from_max_bit(7)),
                 ("Global.BYTE2", BitConstraint::from_max_bit(15)),
-                ("Global.SHIFTED", BitConstraint::from_mask(0xff0)),
+                ("Global.SHIFTED", BitConstraint::from_mask(0xff0u32)),
             ]
             .into_iter()
+            .map(|(name, constraint)| (Symbol::intern(name), constraint))
             .collect()
         );
     }
@@ -455,23 +474,37 @@ namespace Global(2**20);
     #[test]
     fn combinations() {
         let a = BitConstraint::from_max_bit(7);
-        assert_eq!(a, BitConstraint::from_mask(0xff));
-        let b = a.multiple(256.into()).unwrap();
-        assert_eq!(b, BitConstraint::from_mask(0xff00));
+        assert_eq!(a, BitConstraint::from_mask(0xffu32));
+        let b = a.multiple(GoldilocksField::from(256)).unwrap();
+        assert_eq!(b, BitConstraint::from_mask(0xff00u32));
         assert_eq!(
             b.try_combine_sum(&a).unwrap(),
-            BitConstraint::from_mask(0xffff)
+            BitConstraint::from_mask(0xffffu32)
         );
     }
 
     #[test]
     fn weird_combinations() {
-        let a = BitConstraint::from_mask(0xf00f);
-        let b = a.multiple(256.into()).unwrap();
-        assert_eq!(b, BitConstraint::from_mask(0xf00f00));
+        let a = BitConstraint::from_mask(0xf00fu32);
+        let b = a.multiple(GoldilocksField::from(256)).unwrap();
+        assert_eq!(b, BitConstraint::from_mask(0xf00f00u32));
         assert_eq!(
             b.try_combine_sum(&a).unwrap(),
-            BitConstraint::from_mask(0xf0ff0f)
+            BitConstraint::from_mask(0xf0ff0fu32)
+        );
+    }
+
+    #[test]
+    fn multiple_in_small_field() {
+        let a = BitConstraint::from_max_bit(7);
+        let b = a.multiple(BabyBearField::from(1 << 16)).unwrap();
+        assert_eq!(b, BitConstraint::from_mask(0xff0000u32));
+        // Would exceed the modulus.
+        assert_eq!(a.multiple(BabyBearField::from(1 << 24)), None);
+        // 2**31 reduces to 2**27 - 1, which is not a power of two.
+        assert_eq!(
+            BitConstraint::from_mask(1u32).multiple(BabyBearField::from(1u64 << 31)),
+            None
         );
     }
 }
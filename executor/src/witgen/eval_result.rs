@@ -58,7 +58,7 @@ impl IncompleteCause {
     }
 }
 
-pub type Constraints = Vec<(usize, Constraint)>;
+pub type Constraints<T> = Vec<(usize, Constraint<T>)>;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum EvalStatus {
@@ -85,12 +85,12 @@ impl EvalStatus {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct EvalValue {
-    pub constraints: Constraints,
+pub struct EvalValue<T> {
+    pub constraints: Constraints<T>,
     pub status: EvalStatus,
 }
 
-impl EvalValue {
+impl<T: FieldElement> EvalValue<T> {
     pub fn is_complete(&self) -> bool {
         self.status == EvalStatus::Complete
     }
@@ -104,18 +104,18 @@ impl EvalValue {
     }
 
     pub fn incomplete_with_constraints(
-        constraints: impl IntoIterator<Item = (usize, Constraint)>,
+        constraints: impl IntoIterator<Item = (usize, Constraint<T>)>,
         cause: IncompleteCause,
     ) -> Self {
         Self::new(constraints, EvalStatus::Incomplete(cause))
     }
 
-    pub fn complete(constraints: impl IntoIterator<Item = (usize, Constraint)>) -> Self {
+    pub fn complete(constraints: impl IntoIterator<Item = (usize, Constraint<T>)>) -> Self {
         Self::new(constraints, EvalStatus::Complete)
     }
 
     fn new(
-        constraints: impl IntoIterator<Item = (usize, Constraint)>,
+        constraints: impl IntoIterator<Item = (usize, Constraint<T>)>,
         complete: EvalStatus,
     ) -> Self {
         Self {
@@ -132,7 +132,7 @@ impl EvalValue {
 
 /// Result of evaluating an expression / lookup.
 /// New assignments or constraints for witness columns identified by an ID.
-pub type EvalResult = Result<EvalValue, EvalError>;
+pub type EvalResult<T> = Result<EvalValue<T>, EvalError>;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum EvalError {
@@ -191,12 +191,12 @@ impl fmt::Display for EvalError {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Constraint {
-    Assignment(FieldElement),
+pub enum Constraint<T> {
+    Assignment(T),
     BitConstraint(BitConstraint),
 }
 
-impl fmt::Display for Constraint {
+impl<T: FieldElement> fmt::Display for Constraint<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Constraint::Assignment(a) => write!(f, " = {a}"),
@@ -1,53 +1,62 @@
 use number::FieldElement;
-use pil_analyzer::{BinaryOperator, Expression, UnaryOperator};
+use pil_analyzer::{BinaryOperator, Expression, ExpressionArena, Symbol, UnaryOperator};
 
 use super::{
     affine_expression::{AffineExpression, AffineResult},
     IncompleteCause,
 };
 
-pub trait SymbolicVariables {
+pub trait SymbolicVariables<T> {
     /// Acutal constant, not fixed polynomial
-    fn constant(&self, name: &str) -> AffineResult;
+    fn constant(&self, name: &str) -> AffineResult<T>;
     /// Value of a polynomial (fixed or witness).
-    fn value(&self, name: &str, next: bool) -> AffineResult;
-    fn format(&self, expr: AffineExpression) -> String;
+    fn value(&self, name: Symbol, next: bool) -> AffineResult<T>;
+    fn format(&self, expr: AffineExpression<T>) -> String;
 }
 
-pub struct ExpressionEvaluator<SV: SymbolicVariables> {
+pub struct ExpressionEvaluator<'a, T, SV: SymbolicVariables<T>> {
+    /// The operands of the evaluated expressions.
+    expressions: &'a ExpressionArena<T>,
     variables: SV,
 }
 
-impl<SV: SymbolicVariables> ExpressionEvaluator<SV> {
-    pub fn new(variables: SV) -> Self {
-        Self { variables }
+impl<'a, T: FieldElement, SV: SymbolicVariables<T>> ExpressionEvaluator<'a, T, SV> {
+    pub fn new(expressions: &'a ExpressionArena<T>, variables: SV) -> Self {
+        Self {
+            expressions,
+            variables,
+        }
     }
     /// Tries to evaluate the expression to an expression affine in the witness polynomials,
     /// taking current values of polynomials into account.
     /// @returns an expression affine in the witness polynomials
-    pub fn evaluate(&self, expr: &Expression) -> AffineResult {
+    pub fn evaluate(&self, expr: &Expression<T>) -> AffineResult<T> {
         // @TODO if we iterate on processing the constraints in the same row,
         // we could store the simplified values.
         match expr {
             Expression::Constant(name) => self.variables.constant(name),
-            Expression::PolynomialReference(poly) => self.variables.value(&poly.name, poly.next),
+            Expression::PolynomialReference(poly) => self.variables.value(poly.name, poly.next),
             Expression::Number(n) => Ok((*n).into()),
-            Expression::BinaryOperation(left, op, right) => {
-                self.evaluate_binary_operation(left, op, right)
+            Expression::BinaryOperation(left, op, right) => self.evaluate_binary_operation(
+                &self.expressions[*left],
+                op,
+                &self.expressions[*right],
+            ),
+            Expression::UnaryOperation(op, expr) => {
+                self.evaluate_unary_operation(op, &self.expressions[*expr])
             }
-            Expression::UnaryOperation(op, expr) => self.evaluate_unary_operation(op, expr),
             e => Err(IncompleteCause::ExpressionEvaluationUnimplemented(
-                e.to_string(),
+                self.expressions.display(e).to_string(),
             )),
         }
     }
 
     fn evaluate_binary_operation(
         &self,
-        left: &Expression,
+        left: &Expression<T>,
         op: &BinaryOperator,
-        right: &Expression,
-    ) -> AffineResult {
+        right: &Expression<T>,
+    ) -> AffineResult<T> {
         match (self.evaluate(left), op, self.evaluate(right)) {
             // Special case for multiplication: It is enough for one to be known zero.
             (Ok(zero), BinaryOperator::Mul, _) | (_, BinaryOperator::Mul, Ok(zero))
@@ -71,7 +80,7 @@ impl<SV: SymbolicVariables> ExpressionEvaluator<SV> {
                     if let (Some(l), Some(r)) = (left.constant_value(), right.constant_value()) {
                         // TODO Maybe warn about division by zero here.
                         if l == 0.into() {
-                            Ok(0.into())
+                            Ok(T::zero().into())
                         } else {
                             // TODO We have to do division in the proper field.
                             Ok((l / r).into())
@@ -96,22 +105,16 @@ impl<SV: SymbolicVariables> ExpressionEvaluator<SV> {
                     if let (Some(left), Some(right)) =
                         (left.constant_value(), right.constant_value())
                     {
-                        let result: FieldElement = match op {
-                            BinaryOperator::Mod => (left.to_integer() % right.to_integer()).into(),
-                            BinaryOperator::BinaryAnd => {
-                                (left.to_integer() & right.to_integer()).into()
-                            }
-                            BinaryOperator::BinaryXor => {
-                                (left.to_integer() ^ right.to_integer()).into()
-                            }
-                            BinaryOperator::BinaryOr => {
-                                (left.to_integer() | right.to_integer()).into()
-                            }
+                        let result: T = match op {
+                            BinaryOperator::Mod => left.integer_mod(right),
+                            BinaryOperator::BinaryAnd => left.bitand(right),
+                            BinaryOperator::BinaryXor => left.bitxor(right),
+                            BinaryOperator::BinaryOr => left.bitor(right),
                             BinaryOperator::ShiftLeft => {
-                                (left.to_integer() << right.to_integer()).into()
+                                left.try_shl(right).unwrap_or_else(|e| panic!("{e}"))
                             }
                             BinaryOperator::ShiftRight => {
-                                (left.to_integer() >> right.to_integer()).into()
+                                left.try_shr(right).unwrap_or_else(|e| panic!("{e}"))
                             }
                             _ => panic!(),
                         };
@@ -126,7 +129,11 @@ impl<SV: SymbolicVariables> ExpressionEvaluator<SV> {
         }
     }
 
-    fn evaluate_unary_operation(&self, op: &UnaryOperator, expr: &Expression) -> AffineResult {
+    fn evaluate_unary_operation(
+        &self,
+        op: &UnaryOperator,
+        expr: &Expression<T>,
+    ) -> AffineResult<T> {
         self.evaluate(expr).map(|v| match op {
             UnaryOperator::Plus => v,
             UnaryOperator::Minus => -v,
@@ -1,28 +1,30 @@
 use super::affine_expression::{AffineExpression, AffineResult};
+use number::FieldElement;
+use pil_analyzer::Symbol;
 
 use super::expression_evaluator::SymbolicVariables;
 use super::FixedData;
 
 /// Evaluates only fixed columns on a specific row.
-pub struct FixedEvaluator<'a> {
-    fixed_data: &'a FixedData<'a>,
+pub struct FixedEvaluator<'a, T> {
+    fixed_data: &'a FixedData<'a, T>,
     row: usize,
 }
 
-impl<'a> FixedEvaluator<'a> {
-    pub fn new(fixed_data: &'a FixedData<'a>, row: usize) -> Self {
+impl<'a, T: FieldElement> FixedEvaluator<'a, T> {
+    pub fn new(fixed_data: &'a FixedData<'a, T>, row: usize) -> Self {
         FixedEvaluator { fixed_data, row }
     }
 }
 
-impl<'a> SymbolicVariables for FixedEvaluator<'a> {
-    fn constant(&self, name: &str) -> AffineResult {
+impl<'a, T: FieldElement> SymbolicVariables<T> for FixedEvaluator<'a, T> {
+    fn constant(&self, name: &str) -> AffineResult<T> {
         Ok(self.fixed_data.constants[name].into())
     }
 
-    fn value(&self, name: &str, next: bool) -> AffineResult {
+    fn value(&self, name: Symbol, next: bool) -> AffineResult<T> {
         // TODO arrays
-        if let Some(col_data) = self.fixed_data.fixed_cols.get(name) {
+        if let Some(col_data) = self.fixed_data.fixed_cols.get(&name) {
             let degree = col_data.len();
             let row = if next {
                 (self.row + 1) % degree
@@ -35,7 +37,7 @@ impl<'a> SymbolicVariables for FixedEvaluator<'a> {
         }
     }
 
-    fn format(&self, expr: AffineExpression) -> String {
+    fn format(&self, expr: AffineExpression<T>) -> String {
         expr.format(self.fixed_data)
     }
 }
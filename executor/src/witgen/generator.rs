@@ -1,37 +1,44 @@
+use parser_util::diagnostics::Diagnostic;
 use parser_util::lines::indent;
-use pil_analyzer::{Expression, Identity, IdentityKind};
+use pil_analyzer::{Expression, Identity, IdentityKind, Symbol};
 use std::collections::{BTreeMap, HashMap};
-use std::time::Instant;
 // TODO should use finite field instead of abstract number
 use number::{DegreeType, FieldElement};
 
 use super::affine_expression::{AffineExpression, AffineResult};
+use super::audit::Cause;
 use super::bit_constraints::{BitConstraint, BitConstraintSet};
 
 use super::expression_evaluator::ExpressionEvaluator;
 use super::machines::{FixedLookup, Machine};
+use super::memory::MemoryUsage;
 use super::symbolic_witness_evaluator::{SymoblicWitnessEvaluator, WitnessColumnEvaluator};
 use super::util::{contains_next_witness_ref, WitnessColumnNamer};
 use super::{Constraint, EvalResult, EvalValue, FixedData, IncompleteCause, WitnessColumn};
 
-pub struct Generator<'a, QueryCallback> {
-    fixed_data: &'a FixedData<'a>,
-    fixed_lookup: &'a mut FixedLookup,
-    identities: &'a [&'a Identity],
-    machines: Vec<Box<dyn Machine>>,
+pub struct Generator<'a, T, QueryCallback> {
+    fixed_data: &'a FixedData<'a, T>,
+    fixed_lookup: &'a mut FixedLookup<T>,
+    identities: &'a [&'a Identity<T>],
+    machines: Vec<Box<dyn Machine<T>>>,
     query_callback: Option<QueryCallback>,
-    global_bit_constraints: BTreeMap<&'a str, BitConstraint>,
+    global_bit_constraints: BTreeMap<Symbol, BitConstraint>,
     /// Values of the witness polynomials
-    current: Vec<Option<FieldElement>>,
+    current: Vec<Option<T>>,
     /// Values of the witness polynomials in the next row
-    next: Vec<Option<FieldElement>>,
+    next: Vec<Option<T>>,
     /// Bit constraints on the witness polynomials in the next row.
     next_bit_constraints: Vec<Option<BitConstraint>>,
     next_row: DegreeType,
     failure_reasons: Vec<String>,
+    /// The witness columns whose values were assigned on the current row and
+    /// what determined them, if recorded, see `record_causes`.
+    causes: Option<Vec<(usize, Cause<'a, T>)>>,
     progress: bool,
     last_report: DegreeType,
-    last_report_time: Instant,
+    /// The time of the last progress report, there is no clock on wasm32.
+    #[cfg(not(target_arch = "wasm32"))]
+    last_report_time: std::time::Instant,
 }
 
 #[derive(PartialEq, Eq, Clone, Copy)]
@@ -42,16 +49,16 @@ enum EvaluationRow {
     Next,
 }
 
-impl<'a, QueryCallback> Generator<'a, QueryCallback>
+impl<'a, T: FieldElement, QueryCallback> Generator<'a, T, QueryCallback>
 where
-    QueryCallback: FnMut(&str) -> Option<FieldElement>,
+    QueryCallback: FnMut(&str) -> Option<T>,
 {
     pub fn new(
-        fixed_data: &'a FixedData<'a>,
-        fixed_lookup: &'a mut FixedLookup,
-        identities: &'a [&'a Identity],
-        global_bit_constraints: BTreeMap<&'a str, BitConstraint>,
-        machines: Vec<Box<dyn Machine>>,
+        fixed_data: &'a FixedData<'a, T>,
+        fixed_lookup: &'a mut FixedLookup<T>,
+        identities: &'a [&'a Identity<T>],
+        global_bit_constraints: BTreeMap<Symbol, BitConstraint>,
+        machines: Vec<Box<dyn Machine<T>>>,
         query_callback: Option<QueryCallback>,
     ) -> Self {
         let witness_cols_len = fixed_data.witness_cols.len();
@@ -68,14 +75,34 @@ where
             next_bit_constraints: vec![None; witness_cols_len],
             next_row: 0,
             failure_reasons: vec![],
+            causes: None,
             progress: true,
             last_report: 0,
-            last_report_time: Instant::now(),
+            #[cfg(not(target_arch = "wasm32"))]
+            last_report_time: std::time::Instant::now(),
         }
     }
 
-    pub fn compute_next_row(&mut self, next_row: DegreeType) -> Vec<FieldElement> {
+    /// Records from now on which identity or query determined the values
+    /// derived by `compute_next_row`, see `take_causes`.
+    pub fn record_causes(&mut self) {
+        self.causes = Some(vec![]);
+    }
+
+    /// Returns the witness columns whose values the last call of
+    /// `compute_next_row` derived and what determined them, in the order of
+    /// deduction.
+    pub fn take_causes(&mut self) -> Vec<(usize, Cause<'a, T>)> {
+        self.causes.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Derives the values of the next row from the identities and queries.
+    /// @returns None for the values that could not be derived.
+    pub fn compute_next_row(&mut self, next_row: DegreeType) -> Vec<Option<T>> {
         self.set_next_row_and_log(next_row);
+        if let Some(causes) = &mut self.causes {
+            causes.clear();
+        }
 
         // TODO maybe better to generate a dependency graph than looping multiple times.
         // TODO at least we could cache the affine expressions between loops.
@@ -98,13 +125,32 @@ where
                     IdentityKind::Polynomial => {
                         self.process_polynomial_identity(identity.left.selector.as_ref().unwrap())
                     }
-                    IdentityKind::Plookup | IdentityKind::Permutation => {
+                    IdentityKind::Plookup | IdentityKind::LogUp | IdentityKind::Permutation => {
                         self.process_plookup(identity)
                     }
                     kind => {
                         unimplemented!("Identity of kind {kind:?} is not supported in the executor")
                     }
                 };
+                let result = result.map_err(|reason| {
+                    let source = &identity.source;
+                    let mut diagnostic = Diagnostic::error(reason.to_string()).with_line(
+                        &source.file,
+                        source.line,
+                        "this identity",
+                    );
+                    if let Some(contents) = self.fixed_data.sources.get(&source.file) {
+                        diagnostic = diagnostic.with_source(&source.file, contents.clone());
+                    }
+                    match &identity.origin {
+                        Some(origin) => {
+                            diagnostic.with_note(format!("The constraint is from {origin}."))
+                        }
+                        None => diagnostic,
+                    }
+                    .to_string()
+                    .into()
+                });
 
                 if result.is_err() {
                     identity_failed = true;
@@ -117,7 +163,7 @@ where
                     _ => {}
                 };
 
-                self.handle_eval_result(result);
+                self.handle_eval_result(result, Cause::Identity(identity));
             }
 
             if self.query_callback.is_some() {
@@ -126,7 +172,7 @@ where
                     // if the value would be different.
                     if !self.has_known_next_value(column.id) && column.query.is_some() {
                         let result = self.process_witness_query(&column);
-                        self.handle_eval_result(result)
+                        self.handle_eval_result(result, Cause::Query)
                     }
                 }
             }
@@ -141,8 +187,17 @@ where
         // Identity check failure on the first row is not fatal. We will proceed with
         // "unknown", report zero and re-check the wrap-around against the zero values at the end.
         if identity_failed && next_row != 0 {
-            eprintln!(
-                "\nError: Row {next_row}: Identity check failed or unable to derive values for witness polynomials: {}\n",
+            let mut message = match self.fixed_data.pc_location(&self.next) {
+                Some(location) => format!("Constraint failed at {location}.\n"),
+                None => match self.fixed_data.pc_location(&self.current) {
+                    Some(location) => {
+                        format!("Constraint failed in the row after {location}.\n")
+                    }
+                    None => String::new(),
+                },
+            };
+            message += &format!(
+                "Row {next_row}: Identity check failed or unable to derive values for witness polynomials: {}\n",
                 self.next
                     .iter()
                     .enumerate()
@@ -154,24 +209,22 @@ where
                     .collect::<Vec<String>>()
                     .join(", ")
             );
-            eprintln!("Reasons:\n{}\n", self.failure_reasons.join("\n\n"));
-            eprintln!("Known bit constraints:");
-            eprintln!("Global:");
+            message += &format!("Reasons:\n{}\n\n", self.failure_reasons.join("\n\n"));
+            message += "Known bit constraints:\nGlobal:\n";
             for (name, cons) in &self.global_bit_constraints {
-                eprintln!("  {name}: {cons}");
+                message += &format!("  {name}: {cons}\n");
             }
-            eprintln!("For this row:");
+            message += "For this row:\n";
             for (id, cons) in self.next_bit_constraints.iter().enumerate() {
                 if let Some(cons) = cons {
-                    eprintln!("  {}: {cons}", self.fixed_data.witness_cols[id].name);
+                    message += &format!("  {}: {cons}\n", self.fixed_data.witness_cols[id].name);
                 }
             }
-            eprintln!();
-            eprintln!(
-                "Current values (known nonzero first, then zero, then unknown):\n{}",
+            message += &format!(
+                "\nCurrent values (known nonzero first, then zero, then unknown):\n{}",
                 indent(&self.format_next_values().join("\n"), "    ")
             );
-            panic!();
+            panic!("{message}");
         } else {
             log::trace!(
                 "===== Row {next_row}:\n{}",
@@ -180,19 +233,14 @@ where
             std::mem::swap(&mut self.next, &mut self.current);
             self.next = vec![None; self.current.len()];
             self.next_bit_constraints = vec![None; self.current.len()];
-            // TODO check a bit better that "None" values do not
-            // violate constraints.
-            self.current
-                .iter()
-                .map(|v| (*v).unwrap_or_default())
-                .collect()
+            self.current.clone()
         }
     }
 
     /// Verifies the proposed values for the next row.
     /// TODO this is bad for machines because we might introduce rows in the machine that are then
     /// not used.
-    pub fn propose_next_row(&mut self, next_row: DegreeType, values: &[FieldElement]) -> bool {
+    pub fn propose_next_row(&mut self, next_row: DegreeType, values: &[T]) -> bool {
         self.set_next_row_and_log(next_row);
         self.next = values.iter().cloned().map(Some).collect();
 
@@ -201,7 +249,9 @@ where
                 IdentityKind::Polynomial => {
                     self.process_polynomial_identity(identity.left.selector.as_ref().unwrap())
                 }
-                IdentityKind::Plookup | IdentityKind::Permutation => self.process_plookup(identity),
+                IdentityKind::Plookup | IdentityKind::LogUp | IdentityKind::Permutation => {
+                    self.process_plookup(identity)
+                }
                 kind => {
                     unimplemented!("Identity of kind {kind:?} is not supported in the executor")
                 }
@@ -218,24 +268,54 @@ where
         true
     }
 
-    pub fn machine_witness_col_values(&mut self) -> HashMap<String, Vec<FieldElement>> {
-        let mut result: HashMap<_, _> = Default::default();
-        for m in &mut self.machines {
-            result.extend(m.witness_col_values(self.fixed_data));
-        }
-        result
+    /// Returns the estimated memory of the main machine without its columns,
+    /// which the generator does not keep, of the fixed lookup and of the
+    /// other machines.
+    pub fn memory_usage(&self) -> Vec<(String, MemoryUsage)> {
+        let main = MemoryUsage {
+            solver: (self.current.len() + self.next.len()) * std::mem::size_of::<Option<T>>()
+                + self.next_bit_constraints.len() * std::mem::size_of::<Option<BitConstraint>>(),
+            ..Default::default()
+        };
+        [
+            ("main machine".to_string(), main),
+            ("fixed lookup".to_string(), self.fixed_lookup.memory_usage()),
+        ]
+        .into_iter()
+        .chain(self.machines.iter().map(|m| (m.name(), m.memory_usage())))
+        .collect()
+    }
+
+    /// Returns the names of the machines and the values of their columns.
+    pub fn machine_witness_col_values(&mut self) -> Vec<(String, HashMap<String, Vec<T>>)> {
+        self.machines
+            .iter_mut()
+            .map(|m| {
+                let _span = tracing::info_span!("machine", name = %m.name()).entered();
+                (m.name(), m.witness_col_values(self.fixed_data))
+            })
+            .collect()
     }
 
     fn set_next_row_and_log(&mut self, next_row: DegreeType) {
         if next_row >= self.last_report + 1000 {
-            let duration = self.last_report_time.elapsed();
-            self.last_report_time = Instant::now();
-
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                let duration = self.last_report_time.elapsed();
+                self.last_report_time = std::time::Instant::now();
+
+                log::info!(
+                    "{next_row} of {} rows ({} %, {} rows per second)",
+                    self.fixed_data.degree,
+                    next_row * 100 / self.fixed_data.degree,
+                    1000000 / duration.as_millis()
+                );
+            }
+            #[cfg(target_arch = "wasm32")]
             log::info!(
-                "{next_row} of {} rows ({} %, {} rows per second)",
+                "{next_row} of {} rows ({} %)",
                 self.fixed_data.degree,
                 next_row * 100 / self.fixed_data.degree,
-                1000000 / duration.as_millis()
             );
             self.last_report = next_row;
         }
@@ -259,7 +339,7 @@ where
             .map(|(i, v)| {
                 format!(
                     "{} = {}",
-                    AffineExpression::from_variable_id(i).format(self.fixed_data),
+                    AffineExpression::<T>::from_variable_id(i).format(self.fixed_data),
                     v.as_ref()
                         .map(ToString::to_string)
                         .unwrap_or_else(|| "<unknown>".to_string())
@@ -268,7 +348,7 @@ where
             .collect()
     }
 
-    fn process_witness_query(&mut self, column: &&WitnessColumn) -> EvalResult {
+    fn process_witness_query(&mut self, column: &&WitnessColumn<T>) -> EvalResult<T> {
         let query = match self.interpolate_query(column.query.unwrap()) {
             Ok(query) => query,
             Err(incomplete) => return Ok(EvalValue::incomplete(incomplete)),
@@ -286,7 +366,7 @@ where
         }
     }
 
-    fn interpolate_query(&self, query: &Expression) -> Result<String, IncompleteCause> {
+    fn interpolate_query(&self, query: &Expression<T>) -> Result<String, IncompleteCause> {
         if let Ok(v) = self.evaluate(query, EvaluationRow::Next) {
             if v.is_constant() {
                 return Ok(v.format(self.fixed_data));
@@ -307,17 +387,22 @@ where
                 "\"{}\"",
                 s.replace('\\', "\\\\").replace('"', "\\\"")
             )),
-            Expression::MatchExpression(scrutinee, arms) => {
-                self.interpolate_match_expression_for_query(scrutinee.as_ref(), arms)
-            }
-            query => unimplemented!("Cannot handle / evaluate {query}"),
+            Expression::MatchExpression(scrutinee, arms) => self
+                .interpolate_match_expression_for_query(
+                    &self.fixed_data.expressions[*scrutinee],
+                    arms,
+                ),
+            query => unimplemented!(
+                "Cannot handle / evaluate {}",
+                self.fixed_data.expressions.display(query)
+            ),
         }
     }
 
     fn interpolate_match_expression_for_query(
         &self,
-        scrutinee: &Expression,
-        arms: &[(Option<FieldElement>, Expression)],
+        scrutinee: &Expression<T>,
+        arms: &[(Option<T>, Expression<T>)],
     ) -> Result<String, IncompleteCause> {
         let v = self
             .evaluate(scrutinee, EvaluationRow::Next)?
@@ -330,7 +415,7 @@ where
         self.interpolate_query(expr)
     }
 
-    fn process_polynomial_identity(&self, identity: &Expression) -> EvalResult {
+    fn process_polynomial_identity(&self, identity: &Expression<T>) -> EvalResult<T> {
         // If there is no "next" reference in the expression,
         // we just evaluate it directly on the "next" row.
         let row = if contains_next_witness_ref(identity, self.fixed_data) {
@@ -351,7 +436,7 @@ where
         }
     }
 
-    fn process_plookup(&mut self, identity: &Identity) -> EvalResult {
+    fn process_plookup(&mut self, identity: &Identity<T>) -> EvalResult<T> {
         if let Some(left_selector) = &identity.left.selector {
             let value = match self.evaluate(left_selector, EvaluationRow::Next) {
                 Ok(value) => value,
@@ -393,6 +478,7 @@ where
         }
 
         for m in &mut self.machines {
+            let _span = tracing::trace_span!("machine", name = %m.name()).entered();
             // TODO also consider the reasons above.
             if let Some(result) = m.process_plookup(
                 self.fixed_data,
@@ -405,10 +491,13 @@ where
             }
         }
 
-        unimplemented!("No executor machine matched identity `{identity}`")
+        unimplemented!(
+            "No executor machine matched identity `{}`",
+            self.fixed_data.expressions.display(identity)
+        )
     }
 
-    fn handle_eval_result(&mut self, result: EvalResult) {
+    fn handle_eval_result(&mut self, result: EvalResult<T>, cause: Cause<'a, T>) {
         match result {
             Ok(constraints) => {
                 if !constraints.is_empty() {
@@ -417,6 +506,11 @@ where
                 for (id, c) in constraints.constraints {
                     match c {
                         Constraint::Assignment(value) => {
+                            if let Some(causes) = &mut self.causes {
+                                if self.next[id].is_none() {
+                                    causes.push((id, cause.clone()));
+                                }
+                            }
                             self.next[id] = Some(value);
                         }
                         Constraint::BitConstraint(cons) => {
@@ -438,27 +532,30 @@ where
     /// Tries to evaluate the expression to an expression affine in the witness polynomials,
     /// taking current values of polynomials into account.
     /// @returns an expression affine in the witness polynomials
-    fn evaluate(&self, expr: &Expression, evaluate_row: EvaluationRow) -> AffineResult {
+    fn evaluate(&self, expr: &Expression<T>, evaluate_row: EvaluationRow) -> AffineResult<T> {
         let degree = self.fixed_data.degree;
         let fixed_row = match evaluate_row {
             EvaluationRow::Current => (self.next_row + degree - 1) % degree,
             EvaluationRow::Next => self.next_row,
         };
 
-        ExpressionEvaluator::new(SymoblicWitnessEvaluator::new(
-            self.fixed_data,
-            fixed_row,
-            EvaluationData {
-                fixed_data: self.fixed_data,
-                current_witnesses: &self.current,
-                next_witnesses: &self.next,
-                evaluate_row,
-            },
-        ))
+        ExpressionEvaluator::new(
+            self.fixed_data.expressions,
+            SymoblicWitnessEvaluator::new(
+                self.fixed_data,
+                fixed_row,
+                EvaluationData {
+                    fixed_data: self.fixed_data,
+                    current_witnesses: &self.current,
+                    next_witnesses: &self.next,
+                    evaluate_row,
+                },
+            ),
+        )
         .evaluate(expr)
     }
 
-    fn bit_constraint_set(&'a self) -> WitnessBitConstraintSet<'a> {
+    fn bit_constraint_set(&'a self) -> WitnessBitConstraintSet<'a, T> {
         WitnessBitConstraintSet {
             fixed_data: self.fixed_data,
             global_bit_constraints: &self.global_bit_constraints,
@@ -467,36 +564,36 @@ where
     }
 }
 
-struct WitnessBitConstraintSet<'a> {
-    fixed_data: &'a FixedData<'a>,
+struct WitnessBitConstraintSet<'a, T> {
+    fixed_data: &'a FixedData<'a, T>,
     /// Global constraints on witness and fixed polynomials.
-    global_bit_constraints: &'a BTreeMap<&'a str, BitConstraint>,
+    global_bit_constraints: &'a BTreeMap<Symbol, BitConstraint>,
     /// Bit constraints on the witness polynomials in the next row.
     next_bit_constraints: &'a Vec<Option<BitConstraint>>,
 }
 
-impl<'a> BitConstraintSet for WitnessBitConstraintSet<'a> {
+impl<'a, T: FieldElement> BitConstraintSet for WitnessBitConstraintSet<'a, T> {
     fn bit_constraint(&self, id: usize) -> Option<BitConstraint> {
         let name = self.fixed_data.witness_cols[id].name;
         self.global_bit_constraints
-            .get(name)
+            .get(&name)
             .or_else(|| self.next_bit_constraints[id].as_ref())
             .cloned()
     }
 }
 
-struct EvaluationData<'a> {
-    pub fixed_data: &'a FixedData<'a>,
+struct EvaluationData<'a, T> {
+    pub fixed_data: &'a FixedData<'a, T>,
     /// Values of the witness polynomials in the current / last row
-    pub current_witnesses: &'a Vec<Option<FieldElement>>,
+    pub current_witnesses: &'a Vec<Option<T>>,
     /// Values of the witness polynomials in the next row
-    pub next_witnesses: &'a Vec<Option<FieldElement>>,
+    pub next_witnesses: &'a Vec<Option<T>>,
     pub evaluate_row: EvaluationRow,
 }
 
-impl<'a> WitnessColumnEvaluator for EvaluationData<'a> {
-    fn value(&self, name: &str, next: bool) -> AffineResult {
-        let id = self.fixed_data.witness_ids[name];
+impl<'a, T: FieldElement> WitnessColumnEvaluator<T> for EvaluationData<'a, T> {
+    fn value(&self, name: Symbol, next: bool) -> AffineResult<T> {
+        let id = self.fixed_data.witness_ids[&name];
         match (next, self.evaluate_row) {
             (false, EvaluationRow::Current) => {
                 // All values in the "current" row should usually be known.
@@ -524,8 +621,48 @@ impl<'a> WitnessColumnEvaluator for EvaluationData<'a> {
     }
 }
 
-impl<'a> WitnessColumnNamer for EvaluationData<'a> {
+impl<'a, T: FieldElement> WitnessColumnNamer for EvaluationData<'a, T> {
     fn name(&self, i: usize) -> String {
         self.fixed_data.name(i)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+    use pil_analyzer::analyze_string;
+
+    use crate::constant_evaluator;
+    use crate::witgen::generate;
+
+    #[test]
+    #[should_panic(expected = "Row 1: Identity check failed")]
+    fn failure_report_in_panic() {
+        let analyzed = analyze_string::<GoldilocksField>(
+            "namespace Main(4);\ncol fixed FIRST = [1, 0, 0, 0];\ncol witness x;\nx' = x + 1;\nx = 0;",
+        );
+        let (fixed, degree) = constant_evaluator::generate(&analyzed).unwrap();
+        generate(
+            &analyzed,
+            degree,
+            &fixed,
+            None::<fn(&str) -> Option<GoldilocksField>>,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Constraint failed at main.rs:42.")]
+    fn failure_at_source_location() {
+        let mut analyzed = analyze_string::<GoldilocksField>(
+            "namespace Main(4);\ncol fixed FIRST = [1, 0, 0, 0];\ncol witness pc;\npc' = pc + 1;\npc = 0;",
+        );
+        analyzed.set_pc_locations("Main.pc", [(1, "main.rs:42".to_string())].into());
+        let (fixed, degree) = constant_evaluator::generate(&analyzed).unwrap();
+        generate(
+            &analyzed,
+            degree,
+            &fixed,
+            None::<fn(&str) -> Option<GoldilocksField>>,
+        );
+    }
+}
@@ -9,23 +9,24 @@ use crate::witgen::{
     bit_constraints::{BitConstraint, BitConstraintSet},
     expression_evaluator::ExpressionEvaluator,
     machines::Machine,
+    memory::MemoryUsage,
     symbolic_witness_evaluator::{SymoblicWitnessEvaluator, WitnessColumnEvaluator},
     util::{is_simple_poly, WitnessColumnNamer},
     Constraint, EvalError,
 };
 use number::{DegreeType, FieldElement};
-use pil_analyzer::{Expression, Identity, IdentityKind, SelectedExpressions};
+use pil_analyzer::{Expression, Identity, IdentityKind, SelectedExpressions, Symbol};
 
 /// A machine that produces multiple rows (one block) per query.
 /// TODO we do not actually "detect" the machine yet, we just check if
 /// the lookup has a binary selector that is 1 every k rows for some k > 1
-pub struct BlockMachine {
+pub struct BlockMachine<T> {
     /// Block size, the period of the selector.
     block_size: usize,
-    selector: String,
-    identities: Vec<Identity>,
+    selector: Symbol,
+    identities: Vec<Identity<T>>,
     /// One column of values for each witness.
-    data: HashMap<usize, Vec<Option<FieldElement>>>,
+    data: HashMap<usize, Vec<Option<T>>>,
     /// Current row in the machine
     row: DegreeType,
     /// Bit constraints, are deleted outside the current block.
@@ -41,13 +42,13 @@ pub struct BlockMachine {
     processing_sequence_cache: ProcessingSequenceCache,
 }
 
-impl BlockMachine {
+impl<T: FieldElement> BlockMachine<T> {
     pub fn try_new(
-        fixed_data: &FixedData,
-        connecting_identities: &[&Identity],
-        identities: &[&Identity],
+        fixed_data: &FixedData<T>,
+        connecting_identities: &[&Identity<T>],
+        identities: &[&Identity<T>],
         witness_names: &HashSet<&str>,
-        global_bit_constraints: &BTreeMap<&str, BitConstraint>,
+        global_bit_constraints: &BTreeMap<Symbol, BitConstraint>,
     ) -> Option<Box<Self>> {
         for id in connecting_identities {
             if let Some(sel) = &id.right.selector {
@@ -59,7 +60,7 @@ impl BlockMachine {
                         identities: identities.iter().map(|&i| i.clone()).collect(),
                         data: witness_names
                             .iter()
-                            .map(|n| (fixed_data.witness_ids[n], vec![]))
+                            .map(|n| (fixed_data.witness_ids[&Symbol::intern(n)], vec![]))
                             .collect(),
                         row: 0,
                         bit_constraints: Default::default(),
@@ -94,13 +95,13 @@ impl BlockMachine {
 /// for some k >= 2
 /// TODO we could make this more generic and only detect the period
 /// but not enforce the offset.
-fn is_boolean_periodic_selector(
-    expr: &Expression,
-    fixed_data: &FixedData,
-) -> Option<(String, usize)> {
+fn is_boolean_periodic_selector<T: FieldElement>(
+    expr: &Expression<T>,
+    fixed_data: &FixedData<T>,
+) -> Option<(Symbol, usize)> {
     let poly = is_simple_poly(expr)?;
 
-    let values = fixed_data.fixed_cols.get(poly)?;
+    let values = fixed_data.fixed_cols.get(&poly)?;
 
     let period = 1 + values.iter().position(|v| *v == 1.into())?;
     if period == 1 {
@@ -117,20 +118,24 @@ fn is_boolean_periodic_selector(
             };
             *v == expected
         })
-        .then_some((poly.to_string(), period))
+        .then_some((poly, period))
 }
 
-impl Machine for BlockMachine {
+impl<T: FieldElement> Machine<T> for BlockMachine<T> {
+    fn name(&self) -> String {
+        format!("block machine ({})", self.selector)
+    }
+
     fn process_plookup(
         &mut self,
-        fixed_data: &FixedData,
-        fixed_lookup: &mut FixedLookup,
+        fixed_data: &FixedData<T>,
+        fixed_lookup: &mut FixedLookup<T>,
         kind: IdentityKind,
-        left: &[AffineResult],
-        right: &SelectedExpressions,
-    ) -> Option<EvalResult> {
+        left: &[AffineResult<T>],
+        right: &SelectedExpressions<T>,
+    ) -> Option<EvalResult<T>> {
         if is_simple_poly(right.selector.as_ref()?)? != self.selector
-            || kind != IdentityKind::Plookup
+            || !matches!(kind, IdentityKind::Plookup | IdentityKind::LogUp)
         {
             return None;
         }
@@ -148,7 +153,31 @@ impl Machine for BlockMachine {
         })
     }
 
-    fn witness_col_values(&mut self, fixed_data: &FixedData) -> HashMap<String, Vec<FieldElement>> {
+    fn memory_usage(&self) -> MemoryUsage {
+        let cached_steps = self
+            .processing_sequence_cache
+            .cache
+            .iter()
+            .map(|(key, steps)| {
+                key.known_columns.len() + steps.len() * std::mem::size_of::<SequenceStep>()
+            })
+            .sum::<usize>();
+        let bit_constraints = self
+            .bit_constraints
+            .values()
+            .map(HashMap::len)
+            .sum::<usize>()
+            + self.global_bit_constraints.len();
+        MemoryUsage {
+            columns: self.data.values().map(Vec::len).sum::<usize>()
+                * std::mem::size_of::<Option<T>>(),
+            indexes: 0,
+            solver: cached_steps
+                + bit_constraints * std::mem::size_of::<(DegreeType, BitConstraint)>(),
+        }
+    }
+
+    fn witness_col_values(&mut self, fixed_data: &FixedData<T>) -> HashMap<String, Vec<T>> {
         std::mem::take(&mut self.data)
             .into_iter()
             .map(|(id, values)| {
@@ -164,7 +193,7 @@ impl Machine for BlockMachine {
     }
 }
 
-impl BlockMachine {
+impl<T: FieldElement> BlockMachine<T> {
     /// Extends the data with a new block.
     fn append_new_block(&mut self, max_len: DegreeType) -> Result<(), EvalError> {
         if self.rows() + self.block_size as DegreeType >= max_len {
@@ -182,11 +211,11 @@ impl BlockMachine {
 
     fn process_plookup_internal(
         &mut self,
-        fixed_data: &FixedData,
-        fixed_lookup: &mut FixedLookup,
-        left: &[AffineResult],
-        right: &SelectedExpressions,
-    ) -> EvalResult {
+        fixed_data: &FixedData<T>,
+        fixed_lookup: &mut FixedLookup<T>,
+        left: &[AffineResult<T>],
+        right: &SelectedExpressions<T>,
+    ) -> EvalResult<T> {
         // First check if we already store the value.
         if left
             .iter()
@@ -271,7 +300,7 @@ impl BlockMachine {
         }
     }
 
-    fn handle_eval_result(&mut self, value: EvalValue) -> EvalValue {
+    fn handle_eval_result(&mut self, value: EvalValue<T>) -> EvalValue<T> {
         EvalValue {
             constraints: value
                 .constraints
@@ -309,12 +338,12 @@ impl BlockMachine {
     /// an identity in the vector of identities.
     fn process_identity(
         &self,
-        fixed_data: &FixedData,
-        fixed_lookup: &mut FixedLookup,
-        left: &[AffineResult],
-        right: &SelectedExpressions,
+        fixed_data: &FixedData<T>,
+        fixed_lookup: &mut FixedLookup<T>,
+        left: &[AffineResult<T>],
+        right: &SelectedExpressions<T>,
         identity: IdentityInSequence,
-    ) -> EvalResult {
+    ) -> EvalResult<T> {
         match identity {
             IdentityInSequence::Internal(index) => {
                 let id = &self.identities[index];
@@ -323,7 +352,7 @@ impl BlockMachine {
                         fixed_data,
                         id.left.selector.as_ref().unwrap(),
                     ),
-                    IdentityKind::Plookup | IdentityKind::Permutation => {
+                    IdentityKind::Plookup | IdentityKind::LogUp | IdentityKind::Permutation => {
                         self.process_plookup(fixed_data, fixed_lookup, id)
                     }
                     _ => Err("Unsupported lookup type".to_string().into()),
@@ -337,10 +366,10 @@ impl BlockMachine {
     /// on the acutal query row (the last one of the block).
     fn process_outer_query(
         &self,
-        fixed_data: &FixedData,
-        left: &[AffineResult],
-        right: &SelectedExpressions,
-    ) -> EvalResult {
+        fixed_data: &FixedData<T>,
+        left: &[AffineResult<T>],
+        right: &SelectedExpressions<T>,
+    ) -> EvalResult<T> {
         assert!(self.row as usize % self.block_size == self.block_size - 1);
         let mut results = EvalValue::complete(vec![]);
 
@@ -369,9 +398,9 @@ impl BlockMachine {
     /// Process a polynomial identity internal no the machine.
     fn process_polynomial_identity(
         &self,
-        fixed_data: &FixedData,
-        identity: &Expression,
-    ) -> EvalResult {
+        fixed_data: &FixedData<T>,
+        identity: &Expression<T>,
+    ) -> EvalResult<T> {
         let evaluated = match self.evaluate(fixed_data, identity) {
             Ok(evaluated) => evaluated,
             Err(cause) => return Ok(EvalValue::incomplete(cause)),
@@ -396,10 +425,10 @@ impl BlockMachine {
     /// Process a plookup internal to the machine against a set of fixed columns.
     fn process_plookup(
         &self,
-        fixed_data: &FixedData,
-        fixed_lookup: &mut FixedLookup,
-        identity: &Identity,
-    ) -> EvalResult {
+        fixed_data: &FixedData<T>,
+        fixed_lookup: &mut FixedLookup<T>,
+        identity: &Identity<T>,
+    ) -> EvalResult<T> {
         if identity.left.selector.is_some() || identity.right.selector.is_some() {
             unimplemented!("Selectors not yet implemented.");
         }
@@ -420,16 +449,19 @@ impl BlockMachine {
         }
     }
 
-    fn evaluate(&self, fixed_data: &FixedData, expression: &Expression) -> AffineResult {
-        ExpressionEvaluator::new(SymoblicWitnessEvaluator::new(
-            fixed_data,
-            self.row,
-            WitnessData {
+    fn evaluate(&self, fixed_data: &FixedData<T>, expression: &Expression<T>) -> AffineResult<T> {
+        ExpressionEvaluator::new(
+            fixed_data.expressions,
+            SymoblicWitnessEvaluator::new(
                 fixed_data,
-                data: &self.data,
-                row: self.row,
-            },
-        ))
+                self.row,
+                WitnessData {
+                    fixed_data,
+                    data: &self.data,
+                    row: self.row,
+                },
+            ),
+        )
         .evaluate(expression)
     }
 
@@ -440,7 +472,7 @@ impl BlockMachine {
     }
 }
 
-impl BitConstraintSet for BlockMachine {
+impl<T: FieldElement> BitConstraintSet for BlockMachine<T> {
     fn bit_constraint(&self, id: usize) -> Option<BitConstraint> {
         let (poly, next) = self.extract_next(id);
         self.global_bit_constraints.get(&poly).cloned().or_else(|| {
@@ -451,15 +483,15 @@ impl BitConstraintSet for BlockMachine {
 }
 
 #[derive(Clone)]
-struct WitnessData<'a> {
-    pub fixed_data: &'a FixedData<'a>,
-    pub data: &'a HashMap<usize, Vec<Option<FieldElement>>>,
+struct WitnessData<'a, T> {
+    pub fixed_data: &'a FixedData<'a, T>,
+    pub data: &'a HashMap<usize, Vec<Option<T>>>,
     pub row: DegreeType,
 }
 
-impl<'a> WitnessColumnEvaluator for WitnessData<'a> {
-    fn value(&self, name: &str, next: bool) -> AffineResult {
-        let id = self.fixed_data.witness_ids[name];
+impl<'a, T: FieldElement> WitnessColumnEvaluator<T> for WitnessData<'a, T> {
+    fn value(&self, name: Symbol, next: bool) -> AffineResult<T> {
+        let id = self.fixed_data.witness_ids[&name];
         let row = if next {
             (self.row + 1) % self.fixed_data.degree
         } else {
@@ -478,7 +510,7 @@ impl<'a> WitnessColumnEvaluator for WitnessData<'a> {
     }
 }
 
-impl<'a> WitnessColumnNamer for WitnessData<'a> {
+impl<'a, T: FieldElement> WitnessColumnNamer for WitnessData<'a, T> {
     fn name(&self, i: usize) -> String {
         let (id, next) = extract_next(self.fixed_data.witness_cols.len(), i);
         self.fixed_data.name(id) + if next { "\'" } else { "" }
@@ -518,8 +550,8 @@ struct SequenceCacheKey {
     known_columns: Vec<bool>,
 }
 
-impl From<&[AffineResult]> for SequenceCacheKey {
-    fn from(value: &[AffineResult]) -> Self {
+impl<T: FieldElement> From<&[AffineResult<T>]> for SequenceCacheKey {
+    fn from(value: &[AffineResult<T>]) -> Self {
         SequenceCacheKey {
             known_columns: value
                 .iter()
@@ -543,7 +575,10 @@ impl ProcessingSequenceCache {
         }
     }
 
-    pub fn get_processing_sequence(&self, left: &[AffineResult]) -> Vec<SequenceStep> {
+    pub fn get_processing_sequence<T: FieldElement>(
+        &self,
+        left: &[AffineResult<T>],
+    ) -> Vec<SequenceStep> {
         self.cache.get(&left.into()).cloned().unwrap_or_else(|| {
             let block_size = self.block_size as i64;
             (-1..=block_size)
@@ -566,9 +601,9 @@ impl ProcessingSequenceCache {
         })
     }
 
-    pub fn report_processing_sequence(
+    pub fn report_processing_sequence<T: FieldElement>(
         &mut self,
-        left: &[AffineResult],
+        left: &[AffineResult<T>],
         sequence: Vec<SequenceStep>,
     ) {
         self.cache.entry(left.into()).or_insert(sequence);
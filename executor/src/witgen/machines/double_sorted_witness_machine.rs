@@ -5,34 +5,35 @@ use itertools::{Either, Itertools};
 
 use super::{FixedLookup, Machine};
 use crate::witgen::affine_expression::AffineResult;
+use crate::witgen::memory::MemoryUsage;
 use crate::witgen::{EvalError, EvalResult, FixedData};
 use crate::witgen::{EvalValue, IncompleteCause};
 use number::FieldElement;
-use pil_analyzer::PolynomialReference;
-use pil_analyzer::{Expression, Identity, IdentityKind, SelectedExpressions};
+use pil_analyzer::{Expression, Identity, IdentityKind, SelectedExpressions, Symbol};
 
 /// TODO make this generic
-
-#[derive(Default)]
-pub struct DoubleSortedWitnesses {
+pub struct DoubleSortedWitnesses<T> {
     //key_col: String,
     /// Position of the witness columns in the data.
     /// The key column has a position of usize::max
     //witness_positions: HashMap<String, usize>,
     /// (addr, step) -> value
-    trace: BTreeMap<(FieldElement, FieldElement), Operation>,
-    data: BTreeMap<FieldElement, FieldElement>,
+    trace: BTreeMap<(T, T), Operation<T>>,
+    data: BTreeMap<T, T>,
+    /// The selectors of the reads and writes, `Assembly.m_is_read` and `Assembly.m_is_write`.
+    is_read: Symbol,
+    is_write: Symbol,
 }
 
-struct Operation {
+struct Operation<T> {
     pub is_write: bool,
-    pub value: FieldElement,
+    pub value: T,
 }
 
-impl DoubleSortedWitnesses {
+impl<T: FieldElement> DoubleSortedWitnesses<T> {
     pub fn try_new(
-        _fixed_data: &FixedData,
-        _identities: &[&Identity],
+        _fixed_data: &FixedData<T>,
+        _identities: &[&Identity<T>],
         witness_names: &HashSet<&str>,
     ) -> Option<Box<Self>> {
         // TODO check the identities.
@@ -52,43 +53,55 @@ impl DoubleSortedWitnesses {
             .next()
             .is_none()
         {
-            Some(Box::default())
+            Some(Box::new(DoubleSortedWitnesses {
+                trace: Default::default(),
+                data: Default::default(),
+                is_read: Symbol::intern("Assembly.m_is_read"),
+                is_write: Symbol::intern("Assembly.m_is_write"),
+            }))
         } else {
             None
         }
     }
 }
 
-impl Machine for DoubleSortedWitnesses {
+impl<T: FieldElement> Machine<T> for DoubleSortedWitnesses<T> {
+    fn name(&self) -> String {
+        "double sorted witnesses (Assembly.m_addr, Assembly.m_step)".to_string()
+    }
+
     fn process_plookup(
         &mut self,
-        fixed_data: &FixedData,
-        _fixed_lookup: &mut FixedLookup,
+        fixed_data: &FixedData<T>,
+        _fixed_lookup: &mut FixedLookup<T>,
         kind: IdentityKind,
-        left: &[AffineResult],
-        right: &SelectedExpressions,
-    ) -> Option<EvalResult> {
-        if kind != IdentityKind::Permutation
-            || (right.selector
-                != Some(Expression::PolynomialReference(PolynomialReference {
-                    name: "Assembly.m_is_read".to_owned(),
-                    index: None,
-                    next: false,
-                }))
-                && right.selector
-                    != Some(Expression::PolynomialReference(PolynomialReference {
-                        name: "Assembly.m_is_write".to_owned(),
-                        index: None,
-                        next: false,
-                    })))
-        {
+        left: &[AffineResult<T>],
+        right: &SelectedExpressions<T>,
+    ) -> Option<EvalResult<T>> {
+        let is_selector = match &right.selector {
+            Some(Expression::PolynomialReference(p)) => {
+                p.index.is_none() && !p.next && (p.name == self.is_read || p.name == self.is_write)
+            }
+            _ => false,
+        };
+        if kind != IdentityKind::Permutation || !is_selector {
             return None;
         }
 
         Some(self.process_plookup_internal(fixed_data, left, right))
     }
 
-    fn witness_col_values(&mut self, fixed_data: &FixedData) -> HashMap<String, Vec<FieldElement>> {
+    /// The trace of the memory operations becomes the columns, the current
+    /// values of the memory are the solver state.
+    fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            columns: self.trace.len() * std::mem::size_of::<((T, T), Operation<T>)>(),
+            indexes: 0,
+            solver: self.data.len() * std::mem::size_of::<(T, T)>(),
+        }
+    }
+
+    fn witness_col_values(&mut self, fixed_data: &FixedData<T>) -> HashMap<String, Vec<T>> {
         let mut addr = vec![];
         let mut step = vec![];
         let mut value = vec![];
@@ -116,7 +129,7 @@ impl Machine for DoubleSortedWitnesses {
         }
         while addr.len() < fixed_data.degree as usize {
             addr.push(*addr.last().unwrap());
-            step.push(*step.last().unwrap() + FieldElement::from(1));
+            step.push(*step.last().unwrap() + T::from(1));
             value.push(*value.last().unwrap());
             op.push(0.into());
             is_write.push(0.into());
@@ -146,13 +159,13 @@ impl Machine for DoubleSortedWitnesses {
     }
 }
 
-impl DoubleSortedWitnesses {
+impl<T: FieldElement> DoubleSortedWitnesses<T> {
     fn process_plookup_internal(
         &mut self,
-        fixed_data: &FixedData,
-        left: &[AffineResult],
-        right: &SelectedExpressions,
-    ) -> EvalResult {
+        fixed_data: &FixedData<T>,
+        left: &[AffineResult<T>],
+        right: &SelectedExpressions<T>,
+    ) -> EvalResult<T> {
         // We blindly assume the lookup is of the form
         // OP { ADDR, STEP, X } is m_is_write { m_addr, m_step, m_value }
         // or
@@ -174,21 +187,21 @@ impl DoubleSortedWitnesses {
         }
 
         let is_write = match &right.selector {
-            Some(Expression::PolynomialReference(p)) => p.name == "Assembly.m_is_write",
+            Some(Expression::PolynomialReference(p)) => p.name == self.is_write,
             _ => panic!(),
         };
         let addr = left[0].constant_value().ok_or_else(|| {
             format!(
                 "Address must be known: {} = {}",
                 left[0].format(fixed_data),
-                right.expressions[0]
+                fixed_data.expressions.display(&right.expressions[0])
             )
         })?;
         let step = left[1].constant_value().ok_or_else(|| {
             format!(
                 "Step must be known: {} = {}",
                 left[1].format(fixed_data),
-                right.expressions[1]
+                fixed_data.expressions.display(&right.expressions[1])
             )
         })?;
 
@@ -197,7 +210,10 @@ impl DoubleSortedWitnesses {
             addr.to_integer(),
             left[2].format(fixed_data)
         );
-        if addr.clone().to_integer() % 4 != 0 {
+        let addr_value = addr
+            .try_to_u64()
+            .map_err(|e| EvalError::Generic(e.with_context("memory address").to_string()))?;
+        if addr_value % 4 != 0 {
             panic!("UNALIGNED");
         }
 
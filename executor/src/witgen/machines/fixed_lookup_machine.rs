@@ -3,15 +3,16 @@ use std::mem;
 use std::num::NonZeroUsize;
 
 use number::FieldElement;
-use pil_analyzer::{Identity, IdentityKind, SelectedExpressions};
+use pil_analyzer::{Identity, IdentityKind, SelectedExpressions, Symbol};
 
 use crate::witgen::affine_expression::AffineResult;
+use crate::witgen::memory::MemoryUsage;
 use crate::witgen::util::is_simple_poly;
 use crate::witgen::{util::contains_witness_ref, EvalResult, FixedData};
 use crate::witgen::{EvalError, EvalValue, IncompleteCause};
 
-type Application = (Vec<String>, Vec<String>);
-type Index = BTreeMap<Vec<FieldElement>, IndexValue>;
+type Application = (Vec<Symbol>, Vec<Symbol>);
+type Index<T> = BTreeMap<Vec<T>, IndexValue>;
 
 struct IndexValue(Option<NonZeroUsize>);
 
@@ -33,20 +34,30 @@ impl IndexValue {
 /// - `(V, None)` if there exists two different rows where `INPUT_COLS == V` match but `OUTPUT_COLS` differ. TODO: store bitmasks of all possible outputs instead.
 /// - `(V, Some(row)` if the value of `OUTPUT_COLS` is unique when `INPUT_COLS == V`, and `row` is the first row where `INPUT_COLS ==V`
 #[derive(Default)]
-pub struct IndexedColumns {
-    indices: HashMap<Application, Index>,
+pub struct IndexedColumns<T> {
+    indices: HashMap<Application, Index<T>>,
 }
 
-impl IndexedColumns {
+impl<T: FieldElement> IndexedColumns<T> {
+    /// The estimated size of all indices, see the log message of `ensure_index`.
+    fn size(&self) -> usize {
+        self.indices
+            .iter()
+            .map(|((inputs, _), index)| {
+                index.len() * (inputs.len() * mem::size_of::<T>() + mem::size_of::<IndexValue>())
+            })
+            .sum()
+    }
+
     /// get the row at which the assignment is satisfied uniquely
     fn get_match(
         &mut self,
-        fixed_data: &FixedData,
-        mut assignment: Vec<(String, FieldElement)>,
-        mut output_fixed_columns: Vec<String>,
+        fixed_data: &FixedData<T>,
+        mut assignment: Vec<(Symbol, T)>,
+        mut output_fixed_columns: Vec<Symbol>,
     ) -> Option<&IndexValue> {
         // sort in order to have a single index for [X, Y] and for [Y, X]
-        assignment.sort_by(|(name0, _), (name1, _)| name0.cmp(name1));
+        assignment.sort_by_key(|(name, _)| *name);
         let (input_fixed_columns, values): (Vec<_>, Vec<_>) = assignment.into_iter().unzip();
         // sort the output as well
         output_fixed_columns.sort();
@@ -65,11 +76,7 @@ impl IndexedColumns {
 
     /// Create an index for a set of columns to be queried, if does not exist already
     /// `input_fixed_columns` is assumed to be sorted
-    fn ensure_index(
-        &mut self,
-        fixed_data: &FixedData,
-        sorted_fixed_columns: &(Vec<String>, Vec<String>),
-    ) {
+    fn ensure_index(&mut self, fixed_data: &FixedData<T>, sorted_fixed_columns: &Application) {
         // we do not use the Entry API here because we want to clone `sorted_input_fixed_columns` only on index creation
         if self.indices.get(sorted_fixed_columns).is_some() {
             return;
@@ -80,26 +87,26 @@ impl IndexedColumns {
         // create index for this lookup
         log::trace!(
             "Generating index for lookup in columns (in: {}, out: {})",
-            sorted_input_fixed_columns.join(", "),
-            sorted_output_fixed_columns.join(", ")
+            join(sorted_input_fixed_columns),
+            join(sorted_output_fixed_columns)
         );
 
         // get all values for the columns to be indexed
         let input_column_values = sorted_input_fixed_columns
             .iter()
-            .map(|name| fixed_data.fixed_cols.get(name.as_str()).unwrap())
+            .map(|name| fixed_data.fixed_cols.get(name).unwrap())
             .collect::<Vec<_>>();
 
         let output_column_values = sorted_output_fixed_columns
             .iter()
-            .map(|name| fixed_data.fixed_cols.get(name.as_str()).unwrap())
+            .map(|name| fixed_data.fixed_cols.get(name).unwrap())
             .collect::<Vec<_>>();
 
-        let index: BTreeMap<Vec<FieldElement>, IndexValue> = (0..fixed_data.degree as usize)
+        let index: BTreeMap<Vec<T>, IndexValue> = (0..fixed_data.degree as usize)
             .fold(
                 (
-                    BTreeMap::<Vec<FieldElement>, IndexValue>::default(),
-                    HashSet::<(Vec<FieldElement>, Vec<FieldElement>)>::default(),
+                    BTreeMap::<Vec<T>, IndexValue>::default(),
+                    HashSet::<(Vec<T>, Vec<T>)>::default(),
                 ),
                 |(mut acc, mut set), row| {
                     let input: Vec<_> = input_column_values
@@ -135,9 +142,9 @@ impl IndexedColumns {
             "Done creating index. Size (as flat list): entries * (num_inputs * input_size + row_pointer_size) = {} * ({} * {} bytes + {} bytes) = {} bytes",
             index.len(),
             input_column_values.len(),
-            mem::size_of::<FieldElement>(),
+            mem::size_of::<T>(),
             mem::size_of::<IndexValue>(),
-            index.len() * (input_column_values.len() * mem::size_of::<FieldElement>() + mem::size_of::<IndexValue>())
+            index.len() * (input_column_values.len() * mem::size_of::<T>() + mem::size_of::<IndexValue>())
         );
         self.indices.insert(
             (
@@ -149,16 +156,24 @@ impl IndexedColumns {
     }
 }
 
+fn join(columns: &[Symbol]) -> String {
+    columns
+        .iter()
+        .map(|c| c.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 /// Machine to perform a lookup in fixed columns only.
 #[derive(Default)]
-pub struct FixedLookup {
-    indices: IndexedColumns,
+pub struct FixedLookup<T> {
+    indices: IndexedColumns<T>,
 }
 
-impl FixedLookup {
+impl<T: FieldElement> FixedLookup<T> {
     pub fn try_new(
-        _fixed_data: &FixedData,
-        identities: &[&Identity],
+        _fixed_data: &FixedData<T>,
+        identities: &[&Identity<T>],
         witness_names: &HashSet<&str>,
     ) -> Option<Box<Self>> {
         if identities.is_empty() && witness_names.is_empty() {
@@ -168,15 +183,22 @@ impl FixedLookup {
         }
     }
 
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            indexes: self.indices.size(),
+            ..Default::default()
+        }
+    }
+
     pub fn process_plookup(
         &mut self,
-        fixed_data: &FixedData,
+        fixed_data: &FixedData<T>,
         kind: IdentityKind,
-        left: &[AffineResult],
-        right: &SelectedExpressions,
-    ) -> Option<EvalResult> {
+        left: &[AffineResult<T>],
+        right: &SelectedExpressions<T>,
+    ) -> Option<EvalResult<T>> {
         // This is a matching machine if it is a plookup and the RHS is fully constant.
-        if kind != IdentityKind::Plookup
+        if !matches!(kind, IdentityKind::Plookup | IdentityKind::LogUp)
             || right.selector.is_some()
             || right
                 .expressions
@@ -190,7 +212,7 @@ impl FixedLookup {
         let right: Vec<_> = right
             .expressions
             .iter()
-            .map(|right_key| is_simple_poly(right_key).map(From::from))
+            .map(is_simple_poly)
             .collect::<Option<_>>()?;
 
         Some(self.process_plookup_internal(fixed_data, left, right))
@@ -198,10 +220,10 @@ impl FixedLookup {
 
     fn process_plookup_internal(
         &mut self,
-        fixed_data: &FixedData,
-        left: &[AffineResult],
-        right: Vec<String>,
-    ) -> EvalResult {
+        fixed_data: &FixedData<T>,
+        left: &[AffineResult<T>],
+        right: Vec<Symbol>,
+    ) -> EvalResult<T> {
         // split the fixed columns depending on whether their associated lookup variable is constant or not. Preserve the value of the constant arguments.
         // {1, 2, x} in {A, B, C} -> [[(A, 1), (B, 2)], [C, x]]
 
@@ -214,7 +236,7 @@ impl FixedLookup {
                 .as_ref()
                 .ok()
                 .and_then(|l| l.constant_value())
-                .map(|v| (r.clone(), v))
+                .map(|v| (r, v))
                 .ok_or((l, r))
             {
                 Ok(assignment) => {
@@ -246,7 +268,7 @@ impl FixedLookup {
         let output = output_columns.iter().map(|column| {
             &fixed_data
                 .fixed_cols
-                .get(&column.as_ref())
+                .get(column)
                 .as_ref()
                 .unwrap_or_else(|| panic!("Uknown column {column}"))[row]
         });
@@ -9,36 +9,51 @@ use super::FixedData;
 use super::Machine;
 use crate::witgen::bit_constraints::BitConstraint;
 use crate::witgen::WitnessColumn;
+use number::FieldElement;
 use pil_analyzer::IdentityKind;
-use pil_analyzer::{Expression, Identity, SelectedExpressions};
+use pil_analyzer::{Expression, ExpressionArena, Identity, SelectedExpressions, Symbol};
+
+pub struct ExtractionOutput<'a, T> {
+    pub fixed_lookup: FixedLookup<T>,
+    pub machines: Vec<Box<dyn Machine<T>>>,
+    pub base_identities: Vec<&'a Identity<T>>,
+}
 
 /// Finds machines in the witness columns and identities
 /// and returns a list of machines and the identities
 /// that are not "internal" to the machines.
-pub fn split_out_machines<'a>(
-    fixed: &'a FixedData<'a>,
-    identities: Vec<&'a Identity>,
-    witness_cols: &'a [WitnessColumn],
-    global_bit_constraints: &BTreeMap<&'a str, BitConstraint>,
-) -> (FixedLookup, Vec<Box<dyn Machine>>, Vec<&'a Identity>) {
+pub fn split_out_machines<'a, T: FieldElement>(
+    fixed: &'a FixedData<'a, T>,
+    identities: Vec<&'a Identity<T>>,
+    witness_cols: &'a [WitnessColumn<T>],
+    global_bit_constraints: &BTreeMap<Symbol, BitConstraint>,
+) -> ExtractionOutput<'a, T> {
     let fixed_lookup = FixedLookup::try_new(fixed, &[], &Default::default()).unwrap();
 
-    let mut machines: Vec<Box<dyn Machine>> = vec![];
+    let mut machines: Vec<Box<dyn Machine<T>>> = vec![];
 
-    let all_witnesses = witness_cols.iter().map(|c| c.name).collect::<HashSet<_>>();
+    let all_witnesses = witness_cols
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect::<HashSet<_>>();
     let mut remaining_witnesses = all_witnesses.clone();
     let mut base_identities = identities.clone();
     for id in &identities {
         // Extract all witness columns in the RHS of the lookup.
-        let lookup_witnesses = &refs_in_selected_expressions(&id.right) & (&remaining_witnesses);
+        let lookup_witnesses =
+            &refs_in_selected_expressions(fixed.expressions, &id.right) & (&remaining_witnesses);
         if lookup_witnesses.is_empty() {
             continue;
         }
 
         // Recursively extend the set to all witnesses connected through identities that preserve
         // a fixed row relation.
-        let machine_witnesses =
-            all_row_connected_witnesses(lookup_witnesses, &remaining_witnesses, &identities);
+        let machine_witnesses = all_row_connected_witnesses(
+            fixed.expressions,
+            lookup_witnesses,
+            &remaining_witnesses,
+            &identities,
+        );
 
         // Split identities into those that only concern the machine
         // witnesses and those that concern any other witness.
@@ -46,7 +61,7 @@ pub fn split_out_machines<'a>(
             base_identities.iter().cloned().partition(|i| {
                 // The identity has at least one machine witness, but
                 // all referenced witnesses are machine witnesses.
-                let all_refs = &refs_in_identity(i) & (&all_witnesses);
+                let all_refs = &refs_in_identity(fixed.expressions, i) & (&all_witnesses);
                 !all_refs.is_empty() && all_refs.is_subset(&machine_witnesses)
             });
         base_identities = remaining_identities;
@@ -56,7 +71,7 @@ pub fn split_out_machines<'a>(
             .iter()
             .cloned()
             .filter(|i| {
-                refs_in_identity(i)
+                refs_in_identity(fixed.expressions, i)
                     .intersection(&machine_witnesses)
                     .next()
                     .is_some()
@@ -72,7 +87,7 @@ pub fn split_out_machines<'a>(
                 .join(", "),
             machine_identities
                 .iter()
-                .map(|id| id.to_string())
+                .map(|id| fixed.expressions.display(*id).to_string())
                 .collect::<Vec<_>>()
                 .join("\n")
         );
@@ -110,16 +125,21 @@ pub fn split_out_machines<'a>(
             log::warn!("Will try to continue as is, but this probably requires a specialized machine implementation.");
         }
     }
-    (*fixed_lookup, machines, base_identities)
+    ExtractionOutput {
+        fixed_lookup: *fixed_lookup,
+        machines,
+        base_identities,
+    }
 }
 
 /// Extends a set of witnesses to the full set of row-connected witnesses.
 /// Two witnesses are row-connected if they are part of a polynomial identity
 /// or part of the same side of a lookup.
-fn all_row_connected_witnesses<'a>(
+fn all_row_connected_witnesses<'a, T: FieldElement>(
+    expressions: &ExpressionArena<T>,
     mut witnesses: HashSet<&'a str>,
     all_witnesses: &HashSet<&'a str>,
-    identities: &'a [&'a Identity],
+    identities: &'a [&'a Identity<T>],
 ) -> HashSet<&'a str> {
     loop {
         let count = witnesses.len();
@@ -127,16 +147,21 @@ fn all_row_connected_witnesses<'a>(
             match i.kind {
                 IdentityKind::Polynomial => {
                     // Any current witness in the identity adds all other witnesses.
-                    let in_identity = &refs_in_identity(i) & all_witnesses;
+                    let in_identity = &refs_in_identity(expressions, i) & all_witnesses;
                     if in_identity.intersection(&witnesses).next().is_some() {
                         witnesses.extend(in_identity);
                     }
                 }
-                IdentityKind::Plookup | IdentityKind::Permutation | IdentityKind::Connect => {
+                IdentityKind::Plookup
+                | IdentityKind::LogUp
+                | IdentityKind::Permutation
+                | IdentityKind::Connect => {
                     // If we already have witnesses on the LHS, include the LHS,
                     // and vice-versa, but not across the "sides".
-                    let in_lhs = &refs_in_selected_expressions(&i.left) & all_witnesses;
-                    let in_rhs = &refs_in_selected_expressions(&i.right) & all_witnesses;
+                    let in_lhs =
+                        &refs_in_selected_expressions(expressions, &i.left) & all_witnesses;
+                    let in_rhs =
+                        &refs_in_selected_expressions(expressions, &i.right) & all_witnesses;
                     if in_lhs.intersection(&witnesses).next().is_some() {
                         witnesses.extend(in_lhs);
                     } else if in_rhs.intersection(&witnesses).next().is_some() {
@@ -152,35 +177,48 @@ fn all_row_connected_witnesses<'a>(
 }
 
 /// Extracts all references to names from an identity.
-pub fn refs_in_identity(identity: &Identity) -> HashSet<&str> {
-    &refs_in_selected_expressions(&identity.left) | &refs_in_selected_expressions(&identity.right)
+pub fn refs_in_identity<T: FieldElement>(
+    expressions: &ExpressionArena<T>,
+    identity: &Identity<T>,
+) -> HashSet<&'static str> {
+    &refs_in_selected_expressions(expressions, &identity.left)
+        | &refs_in_selected_expressions(expressions, &identity.right)
 }
 
 /// Extracts all references to names from selected expressions.
-pub fn refs_in_selected_expressions(selexpr: &SelectedExpressions) -> HashSet<&str> {
+pub fn refs_in_selected_expressions<T: FieldElement>(
+    expressions: &ExpressionArena<T>,
+    selexpr: &SelectedExpressions<T>,
+) -> HashSet<&'static str> {
     selexpr
         .expressions
         .iter()
         .chain(selexpr.selector.iter())
-        .map(refs_in_expression)
+        .map(|e| refs_in_expression(expressions, e))
         .reduce(|l, r| &l | &r)
         .unwrap_or_default()
 }
 
 /// Extracts all references to names from an expression
-pub fn refs_in_expression(expr: &Expression) -> HashSet<&str> {
+pub fn refs_in_expression<T: FieldElement>(
+    expressions: &ExpressionArena<T>,
+    expr: &Expression<T>,
+) -> HashSet<&'static str> {
     match expr {
         Expression::Constant(_) => todo!(),
         Expression::PolynomialReference(p) => [p.name.as_str()].into(),
-        Expression::Tuple(items) => refs_in_expressions(items),
-        Expression::BinaryOperation(l, _, r) => &refs_in_expression(l) | &refs_in_expression(r),
-        Expression::UnaryOperation(_, e) => refs_in_expression(e),
-        Expression::FunctionCall(_, args) => refs_in_expressions(args),
+        Expression::Tuple(items) => refs_in_expressions(expressions, items),
+        Expression::BinaryOperation(l, _, r) => {
+            &refs_in_expression(expressions, &expressions[*l])
+                | &refs_in_expression(expressions, &expressions[*r])
+        }
+        Expression::UnaryOperation(_, e) => refs_in_expression(expressions, &expressions[*e]),
+        Expression::FunctionCall(_, args) => refs_in_expressions(expressions, args),
         Expression::MatchExpression(scrutinee, arms) => {
-            &refs_in_expression(scrutinee)
+            &refs_in_expression(expressions, &expressions[*scrutinee])
                 | &arms
                     .iter()
-                    .map(|(_, e)| refs_in_expression(e))
+                    .map(|(_, e)| refs_in_expression(expressions, e))
                     .reduce(|a, b| &a | &b)
                     .unwrap_or_default()
         }
@@ -192,10 +230,13 @@ pub fn refs_in_expression(expr: &Expression) -> HashSet<&str> {
 }
 
 /// Extracts all references to names from expressions.
-pub fn refs_in_expressions(exprs: &[Expression]) -> HashSet<&str> {
+pub fn refs_in_expressions<T: FieldElement>(
+    expressions: &ExpressionArena<T>,
+    exprs: &[Expression<T>],
+) -> HashSet<&'static str> {
     exprs
         .iter()
-        .map(refs_in_expression)
+        .map(|e| refs_in_expression(expressions, e))
         .reduce(|l, r| &l | &r)
         .unwrap_or_default()
 }
@@ -1,11 +1,11 @@
 use std::collections::HashMap;
 
-use number::FieldElement;
 use pil_analyzer::{IdentityKind, SelectedExpressions};
 
 pub use self::fixed_lookup_machine::FixedLookup;
 
 use super::affine_expression::AffineResult;
+use super::memory::MemoryUsage;
 use super::EvalResult;
 use super::FixedData;
 
@@ -17,13 +17,13 @@ mod sorted_witness_machine;
 
 /// A machine is a set of witness columns and identities where the columns
 /// are used on the right-hand-side of lookups. It can process plookups.
-pub trait Machine {
+pub trait Machine<T> {
     // /// Tries to construct a new machine with the given subset of
     // /// witness columns and identities. If the identities do not
     // /// fit the pattern of the machine type, it can return None.
     // fn try_new(
-    //     fixed_data: &'a FixedData<'a>,
-    //     identities: Vec<&'a Identity>,
+    //     fixed_data: &'a FixedData<'a, T>,
+    //     identities: Vec<&'a Identity<T>>,
     //     witness_names: HashSet<&'a str>,
     // ) -> Option<Box<Self>>;
 
@@ -34,13 +34,19 @@ pub trait Machine {
     /// If this is not the right machine for the query, return `None`.
     fn process_plookup(
         &mut self,
-        fixed_data: &FixedData,
-        fixed_lookup: &mut FixedLookup,
+        fixed_data: &FixedData<T>,
+        fixed_lookup: &mut FixedLookup<T>,
         kind: IdentityKind,
-        left: &[AffineResult],
-        right: &SelectedExpressions,
-    ) -> Option<EvalResult>;
+        left: &[AffineResult<T>],
+        right: &SelectedExpressions<T>,
+    ) -> Option<EvalResult<T>>;
+
+    /// Returns a name for the machine, used in log messages and tracing spans.
+    fn name(&self) -> String;
 
     /// Returns the final values of the witness columns.
-    fn witness_col_values(&mut self, fixed_data: &FixedData) -> HashMap<String, Vec<FieldElement>>;
+    fn witness_col_values(&mut self, fixed_data: &FixedData<T>) -> HashMap<String, Vec<T>>;
+
+    /// Returns the estimated memory of the machine, see `MemoryUsage`.
+    fn memory_usage(&self) -> MemoryUsage;
 }
@@ -7,13 +7,14 @@ use super::fixed_lookup_machine::FixedLookup;
 use super::Machine;
 use super::{EvalResult, FixedData};
 use crate::witgen::affine_expression::AffineResult;
+use crate::witgen::memory::MemoryUsage;
 use crate::witgen::EvalValue;
 use crate::witgen::{
     expression_evaluator::ExpressionEvaluator, fixed_evaluator::FixedEvaluator,
     symbolic_evaluator::SymbolicEvaluator,
 };
 use number::FieldElement;
-use pil_analyzer::{self, Expression, Identity, IdentityKind, SelectedExpressions};
+use pil_analyzer::{self, Expression, Identity, IdentityKind, SelectedExpressions, Symbol};
 
 /// A machine that can support a lookup in a set of columns that are sorted
 /// by one specific column and values in that column have to be unique.
@@ -22,18 +23,18 @@ use pil_analyzer::{self, Expression, Identity, IdentityKind, SelectedExpressions
 /// Where
 ///  - NOTLAST is zero only on the last row
 ///  - POSITIVE has all values from 1 to half of the field size.
-pub struct SortedWitnesses {
-    key_col: String,
+pub struct SortedWitnesses<T> {
+    key_col: Symbol,
     /// Position of the witness columns in the data.
     /// The key column has a position of usize::max
-    witness_positions: HashMap<String, usize>,
-    data: BTreeMap<FieldElement, Vec<Option<FieldElement>>>,
+    witness_positions: HashMap<Symbol, usize>,
+    data: BTreeMap<T, Vec<Option<T>>>,
 }
 
-impl SortedWitnesses {
+impl<T: FieldElement> SortedWitnesses<T> {
     pub fn try_new(
-        fixed_data: &FixedData,
-        identities: &[&Identity],
+        fixed_data: &FixedData<T>,
+        identities: &[&Identity<T>],
         witness_names: &HashSet<&str>,
     ) -> Option<Box<Self>> {
         if identities.len() != 1 {
@@ -46,7 +47,7 @@ impl SortedWitnesses {
                     if w == key_col {
                         None
                     } else {
-                        Some(w.to_string())
+                        Some(Symbol::intern(w))
                     }
                 })
                 .enumerate()
@@ -54,7 +55,7 @@ impl SortedWitnesses {
                 .collect();
 
             Box::new(SortedWitnesses {
-                key_col: key_col.to_string(),
+                key_col,
                 witness_positions,
                 data: Default::default(),
             })
@@ -62,9 +63,9 @@ impl SortedWitnesses {
     }
 }
 
-fn check_identity<'a>(fixed_data: &'a FixedData, id: &Identity) -> Option<&'a str> {
+fn check_identity<T: FieldElement>(fixed_data: &FixedData<T>, id: &Identity<T>) -> Option<Symbol> {
     // Looking for NOTLAST { A' - A } in { POSITIVE }
-    if id.kind != IdentityKind::Plookup
+    if !matches!(id.kind, IdentityKind::Plookup | IdentityKind::LogUp)
         || id.right.selector.is_some()
         || id.left.expressions.len() != 1
     {
@@ -81,7 +82,8 @@ fn check_identity<'a>(fixed_data: &'a FixedData, id: &Identity) -> Option<&'a st
     // of evaluating it.
     let degree = fixed_data.degree as usize;
     for row in 0..(degree) {
-        let ev = ExpressionEvaluator::new(FixedEvaluator::new(fixed_data, row));
+        let ev =
+            ExpressionEvaluator::new(fixed_data.expressions, FixedEvaluator::new(fixed_data, row));
         let nl = ev.evaluate(notlast).ok()?.constant_value()?;
         if (row == degree - 1 && nl != 0.into()) || (row < degree - 1 && nl != 1.into()) {
             return None;
@@ -96,18 +98,24 @@ fn check_identity<'a>(fixed_data: &'a FixedData, id: &Identity) -> Option<&'a st
 
 /// Checks that the identity has a constraint of the form `a' - a` as the first expression
 /// on the left hand side and returns the name of the witness column.
-fn check_constraint<'a>(fixed_data: &'a FixedData, constraint: &Expression) -> Option<&'a str> {
+fn check_constraint<T: FieldElement>(
+    fixed_data: &FixedData<T>,
+    constraint: &Expression<T>,
+) -> Option<Symbol> {
     let symbolic_ev = SymbolicEvaluator::new(fixed_data);
-    let sort_constraint = match ExpressionEvaluator::new(symbolic_ev.clone()).evaluate(constraint) {
-        Ok(c) => c,
-        Err(_) => return None,
-    };
+    let sort_constraint =
+        match ExpressionEvaluator::new(fixed_data.expressions, symbolic_ev.clone())
+            .evaluate(constraint)
+        {
+            Ok(c) => c,
+            Err(_) => return None,
+        };
     let key_column_id = match sort_constraint.nonzero_variables().as_slice() {
         [key, _] => *key,
         _ => return None,
     };
     let (poly, next) = symbolic_ev.poly_from_id(key_column_id);
-    if next || fixed_data.witness_ids.get(poly).is_none() {
+    if next || fixed_data.witness_ids.get(&poly).is_none() {
         // Either next-witness or fixed column.
         return None;
     }
@@ -120,16 +128,21 @@ fn check_constraint<'a>(fixed_data: &'a FixedData, constraint: &Expression) -> O
     Some(poly)
 }
 
-impl Machine for SortedWitnesses {
+impl<T: FieldElement> Machine<T> for SortedWitnesses<T> {
+    fn name(&self) -> String {
+        format!("sorted witnesses ({})", self.key_col)
+    }
+
     fn process_plookup(
         &mut self,
-        fixed_data: &FixedData,
-        _fixed_lookup: &mut FixedLookup,
+        fixed_data: &FixedData<T>,
+        _fixed_lookup: &mut FixedLookup<T>,
         kind: IdentityKind,
-        left: &[AffineResult],
-        right: &SelectedExpressions,
-    ) -> Option<EvalResult> {
-        if kind != IdentityKind::Plookup || right.selector.is_some() {
+        left: &[AffineResult<T>],
+        right: &SelectedExpressions<T>,
+    ) -> Option<EvalResult<T>> {
+        if !matches!(kind, IdentityKind::Plookup | IdentityKind::LogUp) || right.selector.is_some()
+        {
             return None;
         }
         let rhs = right
@@ -139,7 +152,7 @@ impl Machine for SortedWitnesses {
                 pil_analyzer::Expression::PolynomialReference(p) => {
                     assert!(!p.next);
                     if p.name == self.key_col || self.witness_positions.contains_key(&p.name) {
-                        Some(&p.name)
+                        Some(p.name)
                     } else {
                         None
                     }
@@ -154,7 +167,18 @@ impl Machine for SortedWitnesses {
 
         Some(self.process_plookup_internal(fixed_data, left, right, rhs))
     }
-    fn witness_col_values(&mut self, fixed_data: &FixedData) -> HashMap<String, Vec<FieldElement>> {
+    fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            columns: self
+                .data
+                .values()
+                .map(|row| std::mem::size_of::<T>() + row.len() * std::mem::size_of::<Option<T>>())
+                .sum(),
+            ..Default::default()
+        }
+    }
+
+    fn witness_col_values(&mut self, fixed_data: &FixedData<T>) -> HashMap<String, Vec<T>> {
         let mut result = HashMap::new();
 
         let (mut keys, mut values): (Vec<_>, Vec<_>) =
@@ -165,7 +189,7 @@ impl Machine for SortedWitnesses {
             last_key += 1u64.into();
             keys.push(last_key);
         }
-        result.insert(self.key_col.clone(), keys);
+        result.insert(self.key_col.to_string(), keys);
 
         for (col_name, &i) in &self.witness_positions {
             let mut col_values = values
@@ -173,21 +197,21 @@ impl Machine for SortedWitnesses {
                 .map(|row| std::mem::take(&mut row[i]).unwrap_or_default())
                 .collect::<Vec<_>>();
             col_values.resize(fixed_data.degree as usize, 0.into());
-            result.insert(col_name.clone(), col_values);
+            result.insert(col_name.to_string(), col_values);
         }
 
         result
     }
 }
 
-impl SortedWitnesses {
+impl<T: FieldElement> SortedWitnesses<T> {
     fn process_plookup_internal(
         &mut self,
-        fixed_data: &FixedData,
-        left: &[AffineResult],
-        right: &SelectedExpressions,
-        rhs: Vec<&String>,
-    ) -> EvalResult {
+        fixed_data: &FixedData<T>,
+        left: &[AffineResult<T>],
+        right: &SelectedExpressions<T>,
+        rhs: Vec<Symbol>,
+    ) -> EvalResult<T> {
         // Fail if the LHS has an error.
         let (left, errors): (Vec<_>, Vec<_>) = left.iter().partition_map(|x| match x {
             Ok(x) => Either::Left(x),
@@ -203,13 +227,15 @@ impl SortedWitnesses {
             ));
         }
 
-        let key_index = rhs.iter().position(|&x| x == &self.key_col).unwrap();
+        let key_index = rhs.iter().position(|&x| x == self.key_col).unwrap();
 
         let key_value = left[key_index].constant_value().ok_or_else(|| {
             format!(
                 "Value of unique key must be known: {} = {}",
                 left[key_index].format(fixed_data),
-                right.expressions[key_index]
+                fixed_data
+                    .expressions
+                    .display(&right.expressions[key_index])
             )
         })?;
 
@@ -219,7 +245,7 @@ impl SortedWitnesses {
             .entry(key_value)
             .or_insert_with(|| vec![None; self.witness_positions.len()]);
         for (&l, &r) in left.iter().zip(rhs.iter()).skip(1) {
-            let stored_value = &mut stored_values[self.witness_positions[r]];
+            let stored_value = &mut stored_values[self.witness_positions[&r]];
             match stored_value {
                 // There is a stored value
                 Some(v) => {
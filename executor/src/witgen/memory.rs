@@ -0,0 +1,204 @@
+//! The memory used by witness generation, by machine and by phase, to plan
+//! degrees against the available memory.
+//!
+//! The memory of the machines is estimated from the number of elements of
+//! their data structures, without the overhead of the allocator and of the
+//! maps. The memory of the phases is the resident memory of the process, as
+//! reported by Linux.
+
+use std::fmt;
+use std::fs;
+
+/// The estimated memory of a machine in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// The values of the witness columns.
+    pub columns: usize,
+    /// The indexes of the fixed columns for lookups.
+    pub indexes: usize,
+    /// Everything else that is kept between rows: the values of the current
+    /// and the next row, bit constraints, caches and the state of memory
+    /// machines.
+    pub solver: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.columns + self.indexes + self.solver
+    }
+
+    fn add(&mut self, other: &MemoryUsage) {
+        self.columns += other.columns;
+        self.indexes += other.indexes;
+        self.solver += other.solver;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryReport {
+    /// The estimated memory of the main machine, the fixed lookup and the
+    /// other machines after all rows were processed, which is when their
+    /// data structures are the largest.
+    pub machines: Vec<(String, MemoryUsage)>,
+    /// The resident memory of the process at the end of each phase of
+    /// witness generation, if the platform reports it.
+    pub phases: Vec<(&'static str, Option<u64>)>,
+    /// The peak resident memory of the process, if the platform reports it.
+    pub peak_resident: Option<u64>,
+}
+
+impl MemoryReport {
+    /// The sum of the estimates of all machines.
+    pub fn total(&self) -> MemoryUsage {
+        let mut total = MemoryUsage::default();
+        for (_, usage) in &self.machines {
+            total.add(usage);
+        }
+        total
+    }
+
+    /// Logs the peak resident memory and the estimated total, and the
+    /// machines and phases on the debug level.
+    pub fn log(&self) {
+        for (name, usage) in &self.machines {
+            log::debug!(
+                "Memory of {name}: {} (columns {}, indexes {}, solver {})",
+                mebibytes(usage.total()),
+                mebibytes(usage.columns),
+                mebibytes(usage.indexes),
+                mebibytes(usage.solver)
+            );
+        }
+        for (phase, resident) in &self.phases {
+            log::debug!(
+                "Resident memory after {phase}: {}",
+                optional_mebibytes(*resident)
+            );
+        }
+        log::info!(
+            "Witness generation used {} of estimated memory, peak resident memory {}.",
+            mebibytes(self.total().total()),
+            optional_mebibytes(self.peak_resident)
+        );
+    }
+}
+
+impl fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<50}{:>12}{:>12}{:>12}{:>12}",
+            "machine", "columns", "indexes", "solver", "total"
+        )?;
+        let mut write_usage = |name: &str, usage: &MemoryUsage| {
+            writeln!(
+                f,
+                "{name:<50}{:>12}{:>12}{:>12}{:>12}",
+                mebibytes(usage.columns),
+                mebibytes(usage.indexes),
+                mebibytes(usage.solver),
+                mebibytes(usage.total())
+            )
+        };
+        for (name, usage) in &self.machines {
+            write_usage(name, usage)?;
+        }
+        write_usage("total", &self.total())?;
+        for (phase, resident) in &self.phases {
+            writeln!(
+                f,
+                "resident memory after {phase}: {}",
+                optional_mebibytes(*resident)
+            )?;
+        }
+        writeln!(
+            f,
+            "peak resident memory: {}",
+            optional_mebibytes(self.peak_resident)
+        )
+    }
+}
+
+fn mebibytes(bytes: usize) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1 << 20) as f64)
+}
+
+fn optional_mebibytes(bytes: Option<u64>) -> String {
+    bytes
+        .map(|bytes| mebibytes(bytes as usize))
+        .unwrap_or_else(|| "n/a".to_string())
+}
+
+/// Returns the peak resident memory in bytes, as reported by Linux.
+pub fn peak_resident_memory() -> Option<u64> {
+    process_status("VmHWM:")
+}
+
+/// Returns the current resident memory in bytes, as reported by Linux.
+pub fn resident_memory() -> Option<u64> {
+    process_status("VmRSS:")
+}
+
+fn process_status(key: &str) -> Option<u64> {
+    fs::read_to_string("/proc/self/status")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix(key))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(|kilobytes| kilobytes * 1024)
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+    use pil_analyzer::analyze_string;
+
+    use crate::constant_evaluator;
+    use crate::witgen::generate_with_memory_report;
+
+    #[test]
+    fn machines() {
+        let analyzed = analyze_string::<GoldilocksField>(
+            r#"
+namespace Main(8);
+    col fixed BYTE(i) { i };
+    col fixed DOUBLE(i) { 2 * i };
+    col witness x, y;
+    x' = x + 1;
+    { x, y } in { BYTE, DOUBLE };
+"#,
+        );
+        let (fixed, degree) = constant_evaluator::generate(&analyzed).unwrap();
+        let (_, report) = generate_with_memory_report(
+            &analyzed,
+            degree,
+            &fixed,
+            None::<fn(&str) -> Option<GoldilocksField>>,
+        );
+        assert_eq!(
+            report
+                .machines
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            ["main machine", "fixed lookup"]
+        );
+        let main = report.machines[0].1;
+        assert_eq!(main.columns, 2 * 8 * std::mem::size_of::<GoldilocksField>());
+        assert!(main.solver > 0);
+        assert!(report.machines[1].1.indexes > 0);
+        assert_eq!(
+            report
+                .phases
+                .iter()
+                .map(|(phase, _)| *phase)
+                .collect::<Vec<_>>(),
+            ["setup", "rows", "machines", "multiplicities"]
+        );
+        assert!(report.to_string().contains("\ntotal  "));
+    }
+}
@@ -1,33 +1,161 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 
 use number::{DegreeType, FieldElement};
-use pil_analyzer::{Analyzed, Expression, FunctionValueDefinition};
+use pil_analyzer::{
+    Analyzed, Expression, ExpressionArena, FunctionValueDefinition, Identity, PcLocations, Symbol,
+};
 
+pub use self::audit::{AuditLog, Cause, Deduction};
 pub use self::eval_result::{
     Constraint, Constraints, EvalError, EvalResult, EvalStatus, EvalValue, IncompleteCause,
 };
+use self::machines::machine_extractor::ExtractionOutput;
+pub use self::memory::{peak_resident_memory, resident_memory, MemoryReport, MemoryUsage};
+pub use self::query_log::{recording_callback, replay_callback, QueryLog};
 use self::util::WitnessColumnNamer;
 
 mod affine_expression;
+mod audit;
 mod bit_constraints;
 mod eval_result;
 mod expression_evaluator;
 pub mod fixed_evaluator;
 mod generator;
 mod machines;
+mod memory;
+mod multiplicities;
+mod query_log;
 pub mod symbolic_evaluator;
 mod symbolic_witness_evaluator;
 mod util;
 
+/// Returns a query callback that answers `("input", i)` queries
+/// with the i-th element of `inputs`, `"entry_point"` queries with `entry_point`,
+/// the number of the exported label that programs with `export` start at,
+/// and checks `("assert", difference, message)` queries of assertions,
+/// see `check_assertion`.
+pub fn inputs_query_callback<T: FieldElement>(
+    inputs: Vec<T>,
+    entry_point: u64,
+) -> impl FnMut(&str) -> Option<T> {
+    move |query: &str| -> Option<T> {
+        let (kind, args) = query.split_once(',').unwrap_or((query, ""));
+        match kind.trim() {
+            "\"entry_point\"" => Some(entry_point.into()),
+            "\"input\"" => {
+                let index = args.trim().parse::<usize>().unwrap();
+                let value = inputs.get(index).cloned();
+                if let Some(value) = value {
+                    log::trace!("Input query: Index {index} -> {value}");
+                }
+                value
+            }
+            "\"assert\"" => {
+                check_assertion(args);
+                Some(0.into())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Panics with the message of an assertion if the difference of its values
+/// is not zero. `args` is the rest of the query after `"assert",`.
+pub fn check_assertion(args: &str) {
+    let (difference, message) = args
+        .split_once(',')
+        .unwrap_or_else(|| panic!("Invalid assertion query: {args}"));
+    let difference = difference.trim();
+    if difference != "0" {
+        let message = message.trim();
+        let message = message
+            .strip_prefix('"')
+            .and_then(|m| m.strip_suffix('"'))
+            .unwrap_or(message)
+            .replace("\\\"", "\"")
+            .replace("\\\\", "\\");
+        panic!("Assertion failed at {message} (the difference is {difference})");
+    }
+}
+
+/// A witness column whose value could not be derived from the identities
+/// and queries on some rows, where witness generation chose zero instead.
+#[derive(Debug, PartialEq, Eq)]
+pub struct DefaultedColumn<'a> {
+    pub name: &'a str,
+    /// The number of rows with a chosen value.
+    pub rows: usize,
+    pub first_row: DegreeType,
+}
+
 /// Generates the committed polynomial values
 /// @returns the values (in source order) and the degree of the polynomials.
-pub fn generate<'a>(
-    analyzed: &'a Analyzed,
+pub fn generate<'a, T: FieldElement>(
+    analyzed: &'a Analyzed<T>,
+    degree: DegreeType,
+    fixed_cols: &[(&str, Vec<T>)],
+    query_callback: Option<impl FnMut(&str) -> Option<T>>,
+) -> Vec<(&'a str, Vec<T>)> {
+    generate_with_defaults(analyzed, degree, fixed_cols, query_callback).0
+}
+
+/// Generates the committed polynomial values like `generate` and also
+/// returns the columns whose values were chosen instead of derived on some
+/// rows. Columns computed by machines and multiplicity columns are derived.
+pub fn generate_with_defaults<'a, T: FieldElement>(
+    analyzed: &'a Analyzed<T>,
+    degree: DegreeType,
+    fixed_cols: &[(&str, Vec<T>)],
+    query_callback: Option<impl FnMut(&str) -> Option<T>>,
+) -> (Vec<(&'a str, Vec<T>)>, Vec<DefaultedColumn<'a>>) {
+    let output = generate_internal(analyzed, degree, fixed_cols, query_callback, false);
+    (output.values, output.defaulted)
+}
+
+/// Generates the committed polynomial values like `generate` and also
+/// returns which identity, query or machine determined the value of each
+/// cell, in the order of deduction. Recording the causes makes witness
+/// generation slower.
+pub fn generate_with_audit<'a, T: FieldElement>(
+    analyzed: &'a Analyzed<T>,
+    degree: DegreeType,
+    fixed_cols: &[(&str, Vec<T>)],
+    query_callback: Option<impl FnMut(&str) -> Option<T>>,
+) -> (Vec<(&'a str, Vec<T>)>, AuditLog<'a, T>) {
+    let output = generate_internal(analyzed, degree, fixed_cols, query_callback, true);
+    (output.values, output.audit.unwrap())
+}
+
+/// Generates the committed polynomial values like `generate` and also
+/// returns the memory used by the machines and the phases of witness
+/// generation, which `generate` only logs.
+pub fn generate_with_memory_report<'a, T: FieldElement>(
+    analyzed: &'a Analyzed<T>,
+    degree: DegreeType,
+    fixed_cols: &[(&str, Vec<T>)],
+    query_callback: Option<impl FnMut(&str) -> Option<T>>,
+) -> (Vec<(&'a str, Vec<T>)>, MemoryReport) {
+    let output = generate_internal(analyzed, degree, fixed_cols, query_callback, false);
+    (output.values, output.memory)
+}
+
+struct WitgenOutput<'a, T> {
+    /// The names and the values of the witness columns.
+    values: Vec<(&'a str, Vec<T>)>,
+    defaulted: Vec<DefaultedColumn<'a>>,
+    audit: Option<AuditLog<'a, T>>,
+    memory: MemoryReport,
+}
+
+fn generate_internal<'a, T: FieldElement>(
+    analyzed: &'a Analyzed<T>,
     degree: DegreeType,
-    fixed_cols: &[(&str, Vec<FieldElement>)],
-    query_callback: Option<impl FnMut(&str) -> Option<FieldElement>>,
-) -> Vec<(&'a str, Vec<FieldElement>)> {
-    let witness_cols: Vec<WitnessColumn> = analyzed
+    fixed_cols: &[(&str, Vec<T>)],
+    query_callback: Option<impl FnMut(&str) -> Option<T>>,
+    audit: bool,
+) -> WitgenOutput<'a, T> {
+    let witness_cols: Vec<WitnessColumn<T>> = analyzed
         .committed_polys_in_source_order()
         .iter()
         .enumerate()
@@ -41,13 +169,23 @@ pub fn generate<'a>(
     let fixed = FixedData::new(
         degree,
         &analyzed.constants,
+        &analyzed.expressions,
+        &analyzed.sources,
         fixed_cols.iter().map(|(n, v)| (*n, v)).collect(),
         &witness_cols,
-        witness_cols.iter().map(|w| (w.name, w.id)).collect(),
-    );
+        witness_cols
+            .iter()
+            .map(|w| (w.name.as_str(), w.id))
+            .collect(),
+    )
+    .with_pc_locations(analyzed.pc_locations.as_ref());
     let (global_bit_constraints, identities) =
         bit_constraints::determine_global_constraints(&fixed, analyzed.identities.iter().collect());
-    let (mut fixed_lookup, machines, identities) = machines::machine_extractor::split_out_machines(
+    let ExtractionOutput {
+        mut fixed_lookup,
+        machines,
+        base_identities: identities,
+    } = machines::machine_extractor::split_out_machines(
         &fixed,
         identities,
         &witness_cols,
@@ -61,9 +199,31 @@ pub fn generate<'a>(
         machines,
         query_callback,
     );
+    let mut phases = vec![("setup", memory::resident_memory())];
+    // The generator refers to the identities it was given, the log to the
+    // identities of the analyzed file.
+    let identities_by_address = analyzed
+        .identities
+        .iter()
+        .map(|identity| (identity as *const Identity<T>, identity))
+        .collect::<HashMap<_, _>>();
+    let mut deductions = audit.then(|| {
+        generator.record_causes();
+        vec![]
+    });
 
-    let mut values: Vec<(&str, Vec<FieldElement>)> =
-        witness_cols.iter().map(|p| (p.name, Vec::new())).collect();
+    let mut values: Vec<(&str, Vec<T>)> = witness_cols
+        .iter()
+        .map(|p| (p.name.as_str(), Vec::new()))
+        .collect();
+    let mut defaulted = witness_cols
+        .iter()
+        .map(|p| DefaultedColumn {
+            name: p.name.as_str(),
+            rows: 0,
+            first_row: 0,
+        })
+        .collect::<Vec<_>>();
     // Are we in an infinite loop and can just re-use the old values?
     let mut looping_period = None;
     for row in 0..degree as DegreeType {
@@ -81,6 +241,16 @@ pub fn generate<'a>(
                 .map(|(_, v)| v[v.len() - period])
                 .collect::<Vec<_>>();
             if generator.propose_next_row(row, &values) {
+                if let Some(deductions) = &mut deductions {
+                    deductions.extend(witness_cols.iter().zip(&values).map(|(column, value)| {
+                        Deduction {
+                            row,
+                            column: column.name.as_str(),
+                            value: *value,
+                            cause: Cause::Repetition(period),
+                        }
+                    }));
+                }
                 row_values = Some(values);
             } else {
                 log::info!("Using loop failed. Trying to generate regularly again.");
@@ -88,28 +258,144 @@ pub fn generate<'a>(
             }
         }
         if row_values.is_none() {
-            row_values = Some(generator.compute_next_row(row));
+            let derived = generator.compute_next_row(row);
+            if let Some(deductions) = &mut deductions {
+                let causes = generator.take_causes();
+                let derived_causes = causes.into_iter().map(|(id, cause)| {
+                    (
+                        id,
+                        match cause {
+                            Cause::Identity(identity) => {
+                                Cause::Identity(identities_by_address[&(identity as *const _)])
+                            }
+                            Cause::Query => Cause::Query,
+                            _ => unreachable!("The generator only records identities and queries."),
+                        },
+                    )
+                });
+                let defaulted_causes = derived
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, v)| v.is_none())
+                    .map(|(id, _)| (id, Cause::Default));
+                deductions.extend(derived_causes.chain(defaulted_causes).map(|(id, cause)| {
+                    Deduction {
+                        row,
+                        column: witness_cols[id].name.as_str(),
+                        value: derived[id].unwrap_or_default(),
+                        cause,
+                    }
+                }));
+            }
+            for (column, _) in defaulted
+                .iter_mut()
+                .zip(&derived)
+                .filter(|(_, v)| v.is_none())
+            {
+                if column.rows == 0 {
+                    column.first_row = row;
+                }
+                column.rows += 1;
+            }
+            // TODO check a bit better that "None" values do not
+            // violate constraints.
+            row_values = Some(derived.into_iter().map(Option::unwrap_or_default).collect());
         };
         for (col, v) in row_values.unwrap().into_iter().enumerate() {
             values[col].1.push(v);
         }
     }
-    for (col, v) in generator.compute_next_row(0).into_iter().enumerate() {
+    for (col, v) in generator
+        .compute_next_row(0)
+        .into_iter()
+        .map(Option::unwrap_or_default)
+        .enumerate()
+    {
         if v != values[col].1[0] {
-            eprintln!("Wrap-around value for column {} does not match: {} (wrap-around) vs. {} (first row).",
+            log::warn!("Wrap-around value for column {} does not match: {} (wrap-around) vs. {} (first row).",
             witness_cols[col].name, v, values[col].1[0]);
         }
     }
-    for (name, data) in generator.machine_witness_col_values() {
-        let (_, col) = values.iter_mut().find(|(n, _)| *n == name).unwrap();
-        *col = data;
+    phases.push(("rows", memory::resident_memory()));
+    let mut machines_memory = generator.memory_usage();
+    machines_memory[0].1.columns =
+        values.iter().map(|(_, v)| v.len()).sum::<usize>() * std::mem::size_of::<T>();
+    let mut machine_columns = vec![];
+    for (machine, columns) in generator.machine_witness_col_values() {
+        for (name, data) in columns {
+            let (name, col) = values.iter_mut().find(|(n, _)| *n == name).unwrap();
+            *col = data;
+            defaulted.retain(|c| c.name != *name);
+            machine_columns.push((machine.clone(), *name));
+        }
+    }
+    phases.push(("machines", memory::resident_memory()));
+    multiplicities::generate(analyzed, fixed_cols, &mut values);
+    phases.push(("multiplicities", memory::resident_memory()));
+    for identity in &analyzed.identities {
+        if let Some(multiplicity) = &identity.multiplicity {
+            defaulted.retain(|c| c.name != multiplicity.name);
+        }
+    }
+    defaulted.retain(|c| c.rows > 0);
+    let audit =
+        deductions.map(|mut deductions| {
+            // The values of the machine and multiplicity columns replace the
+            // ones of the main machine.
+            let replaced =
+                machine_columns
+                    .iter()
+                    .map(|(_, name)| *name)
+                    .chain(analyzed.identities.iter().filter_map(|identity| {
+                        identity.multiplicity.as_ref().map(|m| m.name.as_str())
+                    }))
+                    .collect::<Vec<_>>();
+            deductions.retain(|d| !replaced.contains(&d.column));
+            for (machine, name) in &machine_columns {
+                let (_, column) = values.iter().find(|(n, _)| n == name).unwrap();
+                deductions.extend(column.iter().enumerate().map(|(row, value)| Deduction {
+                    row: row as DegreeType,
+                    column: name,
+                    value: *value,
+                    cause: Cause::Machine(machine.clone()),
+                }));
+            }
+            for identity in &analyzed.identities {
+                if let Some(multiplicity) = &identity.multiplicity {
+                    let (name, column) = values
+                        .iter()
+                        .find(|(n, _)| *n == multiplicity.name)
+                        .unwrap();
+                    deductions.extend(column.iter().enumerate().map(|(row, value)| Deduction {
+                        row: row as DegreeType,
+                        column: name,
+                        value: *value,
+                        cause: Cause::Multiplicity(identity),
+                    }));
+                }
+            }
+            AuditLog {
+                deductions,
+                expressions: &analyzed.expressions,
+            }
+        });
+    let memory = MemoryReport {
+        machines: machines_memory,
+        phases,
+        peak_resident: memory::peak_resident_memory(),
+    };
+    memory.log();
+    WitgenOutput {
+        values,
+        defaulted,
+        audit,
+        memory,
     }
-    values
 }
 
 /// Checks if the last rows are repeating and returns the period.
 /// Only checks for periods of 1, 2, 3 and 4.
-fn rows_are_repeating(values: &[(&str, Vec<FieldElement>)]) -> Option<usize> {
+fn rows_are_repeating<T: FieldElement>(values: &[(&str, Vec<T>)]) -> Option<usize> {
     if values.is_empty() {
         return Some(1);
     } else if values[0].1.len() < 4 {
@@ -124,59 +410,99 @@ fn rows_are_repeating(values: &[(&str, Vec<FieldElement>)]) -> Option<usize> {
 }
 
 /// Data that is fixed for witness generation.
-pub struct FixedData<'a> {
+pub struct FixedData<'a, T> {
     degree: DegreeType,
-    constants: &'a HashMap<String, FieldElement>,
-    fixed_cols: HashMap<&'a str, &'a Vec<FieldElement>>,
-    witness_cols: &'a Vec<WitnessColumn<'a>>,
-    witness_ids: HashMap<&'a str, usize>,
+    constants: &'a HashMap<String, T>,
+    /// The operands of the expressions of the identities.
+    expressions: &'a ExpressionArena<T>,
+    /// The contents of the PIL files, to show the identities in errors.
+    sources: &'a BTreeMap<String, Arc<str>>,
+    /// The source locations of the values of the pc, to show where in the
+    /// program a row fails.
+    pc_locations: Option<&'a PcLocations>,
+    fixed_cols: HashMap<Symbol, &'a Vec<T>>,
+    witness_cols: &'a Vec<WitnessColumn<'a, T>>,
+    witness_ids: HashMap<Symbol, usize>,
 }
 
-impl<'a> FixedData<'a> {
+impl<'a, T: FieldElement> FixedData<'a, T> {
     pub fn new(
         degree: DegreeType,
-        constants: &'a HashMap<String, FieldElement>,
-        fixed_cols: HashMap<&'a str, &'a Vec<FieldElement>>,
-        witness_cols: &'a Vec<WitnessColumn<'a>>,
+        constants: &'a HashMap<String, T>,
+        expressions: &'a ExpressionArena<T>,
+        sources: &'a BTreeMap<String, Arc<str>>,
+        fixed_cols: HashMap<&'a str, &'a Vec<T>>,
+        witness_cols: &'a Vec<WitnessColumn<'a, T>>,
         witness_ids: HashMap<&'a str, usize>,
     ) -> Self {
         FixedData {
             degree,
             constants,
-            fixed_cols,
+            expressions,
+            sources,
+            pc_locations: None,
+            fixed_cols: fixed_cols
+                .into_iter()
+                .map(|(name, values)| (Symbol::intern(name), values))
+                .collect(),
             witness_cols,
-            witness_ids,
+            witness_ids: witness_ids
+                .into_iter()
+                .map(|(name, id)| (Symbol::intern(name), id))
+                .collect(),
+        }
+    }
+
+    pub fn with_pc_locations(self, pc_locations: Option<&'a PcLocations>) -> Self {
+        FixedData {
+            pc_locations,
+            ..self
         }
     }
 
-    fn witness_cols(&self) -> impl Iterator<Item = &WitnessColumn> {
+    /// Returns the source location of the value of the pc in the row, if the
+    /// PIL was generated from a program with a source map.
+    fn pc_location(&self, row: &[Option<T>]) -> Option<&'a str> {
+        let pc_locations = self.pc_locations?;
+        let pc = row[*self.witness_ids.get(&pc_locations.pc)?]?;
+        pc_locations
+            .locations
+            .get(&pc.try_to_u64().ok()?)
+            .map(|location| location.as_str())
+    }
+
+    fn witness_cols(&self) -> impl Iterator<Item = &WitnessColumn<T>> {
         self.witness_cols.iter()
     }
 }
 
-impl<'a> WitnessColumnNamer for FixedData<'a> {
+impl<'a, T: FieldElement> WitnessColumnNamer for FixedData<'a, T> {
     fn name(&self, i: usize) -> String {
         self.witness_cols[i].name.to_string()
     }
 }
 
-pub struct WitnessColumn<'a> {
+pub struct WitnessColumn<'a, T> {
     id: usize,
-    name: &'a str,
-    query: Option<&'a Expression>,
+    name: Symbol,
+    query: Option<&'a Expression<T>>,
 }
 
-impl<'a> WitnessColumn<'a> {
+impl<'a, T: FieldElement> WitnessColumn<'a, T> {
     pub fn new(
         id: usize,
         name: &'a str,
-        value: &'a Option<FunctionValueDefinition>,
-    ) -> WitnessColumn<'a> {
+        value: &'a Option<FunctionValueDefinition<T>>,
+    ) -> WitnessColumn<'a, T> {
         let query = if let Some(FunctionValueDefinition::Query(query)) = value {
             Some(query)
         } else {
             None
         };
-        WitnessColumn { id, name, query }
+        WitnessColumn {
+            id,
+            name: Symbol::intern(name),
+            query,
+        }
     }
 }
@@ -0,0 +1,89 @@
+//! Computes the multiplicity columns of LogUp identities, once all other
+//! witness columns are known: For every left hand side tuple, the multiplicity
+//! of the first matching row of the right hand side is incremented.
+
+use std::collections::HashMap;
+
+use number::FieldElement;
+use pil_analyzer::{row_evaluator::RowEvaluator, Analyzed, IdentityKind};
+
+pub fn generate<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    fixed: &[(&str, Vec<T>)],
+    witness: &mut [(&str, Vec<T>)],
+) {
+    let mut multiplicities: HashMap<String, Vec<T>> = HashMap::new();
+    {
+        let evaluator = RowEvaluator::new(analyzed, fixed, witness);
+        let selected_tuples = |selected| {
+            evaluator
+                .selected_tuples(selected)
+                .unwrap_or_else(|e| panic!("Cannot evaluate {e} in LogUp identity."))
+        };
+        for identity in analyzed
+            .identities
+            .iter()
+            .filter(|identity| identity.kind == IdentityKind::LogUp)
+        {
+            let mut first_rows = HashMap::new();
+            for (row, tuple) in selected_tuples(&identity.right) {
+                first_rows.entry(tuple).or_insert(row);
+            }
+            let name = &identity.multiplicity.as_ref().unwrap().name;
+            let values = multiplicities
+                .entry(name.to_string())
+                .or_insert_with(|| vec![T::zero(); evaluator.degree]);
+            for (_, tuple) in selected_tuples(&identity.left) {
+                // Tuples that are not in the table violate the identity anyway.
+                if let Some(row) = first_rows.get(&tuple) {
+                    values[*row] += T::one();
+                }
+            }
+        }
+    }
+    for (name, values) in multiplicities {
+        let (_, column) = witness
+            .iter_mut()
+            .find(|(n, _)| *n == name)
+            .unwrap_or_else(|| panic!("The multiplicity column {name} is not a witness column."));
+        *column = values;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+    use pil_analyzer::analyze_string;
+
+    use crate::{constant_evaluator, witgen};
+
+    #[test]
+    fn multiplicities() {
+        let analyzed = analyze_string::<GoldilocksField>(
+            r#"
+namespace Main(8);
+    col fixed BYTE(i) { i % 4 };
+    col fixed INPUT(i) { (i * 3) % 4 };
+    col fixed FIRST = [1] + [0]*;
+    col witness x, m;
+    x = INPUT;
+    (1 - FIRST) { x } in { BYTE } multiplicity m;
+"#,
+        );
+        let (fixed, degree) = constant_evaluator::generate(&analyzed).unwrap();
+        let witness = witgen::generate(
+            &analyzed,
+            degree,
+            &fixed,
+            None::<fn(&str) -> Option<GoldilocksField>>,
+        );
+        // Rows 1 to 7 look up 3, 2, 1, 0, 3, 2, 1,
+        // which are first in the table on rows 3, 2, 1 and 0.
+        let multiplicities = &witness.iter().find(|(n, _)| *n == "Main.m").unwrap().1;
+        let expected = [1, 2, 2, 2, 0, 0, 0, 0];
+        assert_eq!(
+            multiplicities,
+            &expected.iter().map(|v| (*v).into()).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Recording of the responses to the prover queries of a witness generation
+//! run, to run witness generation again without the inputs and callbacks of
+//! the prover, for example to reproduce a bug.
+//!
+//! The log is a text file with a header line and one line per query, in the
+//! order of the queries: the response (`-` if there was none) followed by
+//! the query, like `3 "input", 0`.
+
+use std::cell::RefCell;
+use std::fmt;
+
+use number::{AbstractNumberType, FieldElement};
+
+use super::check_assertion;
+
+const HEADER: &str = "# powdr query responses, modulus ";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryLog<T> {
+    /// The queries and the responses, in the order of the queries.
+    pub responses: Vec<(String, Option<T>)>,
+}
+
+impl<T> Default for QueryLog<T> {
+    fn default() -> Self {
+        QueryLog { responses: vec![] }
+    }
+}
+
+impl<T: FieldElement> QueryLog<T> {
+    /// Reads a log in the format written by `Display`.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut lines = input.lines();
+        let modulus = lines
+            .next()
+            .and_then(|line| line.strip_prefix(HEADER))
+            .ok_or_else(|| "Not a query log.".to_string())?;
+        if modulus != T::modulus().to_string() {
+            return Err(format!(
+                "The queries are in the field with modulus {modulus}, expected {}.",
+                T::modulus()
+            ));
+        }
+        let responses = lines
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                let error = |message: &str| format!("Line {}: {message}", i + 2);
+                let (response, query) = line
+                    .split_once(' ')
+                    .ok_or_else(|| error("Expected a response followed by the query."))?;
+                let response = match response {
+                    "-" => None,
+                    value => Some(T::from(
+                        value
+                            .parse::<AbstractNumberType>()
+                            .map_err(|_| error(&format!("Invalid response {value}.")))?,
+                    )),
+                };
+                Ok((query.to_string(), response))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(QueryLog { responses })
+    }
+}
+
+impl<T: FieldElement> fmt::Display for QueryLog<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{HEADER}{}", T::modulus())?;
+        for (query, response) in &self.responses {
+            match response {
+                Some(value) => writeln!(f, "{} {query}", value.to_integer()),
+                None => writeln!(f, "- {query}"),
+            }?;
+        }
+        Ok(())
+    }
+}
+
+/// Wraps the query callback so that every query and its response is
+/// appended to the log.
+pub fn recording_callback<'a, T: FieldElement>(
+    mut callback: impl FnMut(&str) -> Option<T> + 'a,
+    log: &'a RefCell<QueryLog<T>>,
+) -> impl FnMut(&str) -> Option<T> + 'a {
+    move |query: &str| {
+        let response = callback(query);
+        log.borrow_mut()
+            .responses
+            .push((query.to_string(), response));
+        response
+    }
+}
+
+/// Returns a query callback that answers the queries with the responses of
+/// the log, in order. Assertions are checked again, so that a run that
+/// failed an assertion fails in the same way.
+/// Panics if a query is not the recorded one, which means that witness
+/// generation is not deterministic or the file changed since the recording.
+pub fn replay_callback<T: FieldElement>(log: QueryLog<T>) -> impl FnMut(&str) -> Option<T> {
+    let mut responses = log.responses.into_iter().enumerate();
+    move |query: &str| {
+        if let Some(("\"assert\"", args)) = query
+            .split_once(',')
+            .map(|(kind, args)| (kind.trim(), args))
+        {
+            check_assertion(args);
+        }
+        match responses.next() {
+            Some((_, (recorded, response))) if recorded == query => response,
+            Some((i, (recorded, _))) => {
+                panic!("Query {i} is {query}, but the recorded query is {recorded}.")
+            }
+            None => panic!("The query {query} is not in the recording, it ends earlier."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+
+    use super::*;
+    use crate::witgen::inputs_query_callback;
+
+    #[test]
+    fn record_and_replay() {
+        let log = RefCell::new(QueryLog::<GoldilocksField>::default());
+        let mut callback = recording_callback(inputs_query_callback(vec![7.into()], 0), &log);
+        assert_eq!(callback("\"input\", 0"), Some(7.into()));
+        assert_eq!(callback("\"input\", 1"), None);
+        drop(callback);
+        let written = log.into_inner().to_string();
+        assert_eq!(
+            written,
+            "# powdr query responses, modulus 18446744069414584321\n7 \"input\", 0\n- \"input\", 1\n"
+        );
+
+        let parsed = QueryLog::<GoldilocksField>::parse(&written).unwrap();
+        let mut replay = replay_callback(parsed.clone());
+        assert_eq!(replay("\"input\", 0"), Some(7.into()));
+        assert_eq!(replay("\"input\", 1"), None);
+
+        let mut replay = replay_callback(parsed);
+        let result = std::panic::catch_unwind(move || replay("\"input\", 1"));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(QueryLog::<GoldilocksField>::parse("3 \"input\", 0").is_err());
+        assert_eq!(
+            QueryLog::<GoldilocksField>::parse(
+                "# powdr query responses, modulus 18446744069414584321\nx \"input\", 0"
+            ),
+            Err("Line 2: Invalid response x.".to_string())
+        );
+    }
+}
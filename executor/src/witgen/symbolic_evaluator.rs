@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 
 use super::affine_expression::{AffineExpression, AffineResult};
+use number::FieldElement;
+use pil_analyzer::Symbol;
 
 use super::expression_evaluator::SymbolicVariables;
 use super::util::WitnessColumnNamer;
@@ -15,14 +17,14 @@ use super::FixedData;
 /// The only IDs are allocated in the following order:
 /// witness columns, next witness columns, fixed columns, next fixed columns.
 #[derive(Clone)]
-pub struct SymbolicEvaluator<'a> {
-    fixed_data: &'a FixedData<'a>,
-    fixed_ids: HashMap<&'a str, usize>,
-    fixed_names: Vec<&'a str>,
+pub struct SymbolicEvaluator<'a, T> {
+    fixed_data: &'a FixedData<'a, T>,
+    fixed_ids: HashMap<Symbol, usize>,
+    fixed_names: Vec<Symbol>,
 }
 
-impl<'a> SymbolicEvaluator<'a> {
-    pub fn new(fixed_data: &'a FixedData<'a>) -> Self {
+impl<'a, T: FieldElement> SymbolicEvaluator<'a, T> {
+    pub fn new(fixed_data: &'a FixedData<'a, T>) -> Self {
         let mut fixed_names = fixed_data.fixed_cols.keys().cloned().collect::<Vec<_>>();
         fixed_names.sort();
         let fixed_ids = fixed_names
@@ -37,7 +39,7 @@ impl<'a> SymbolicEvaluator<'a> {
         }
     }
 
-    pub fn poly_from_id(&self, id: usize) -> (&'a str, bool) {
+    pub fn poly_from_id(&self, id: usize) -> (Symbol, bool) {
         let witness_count = self.fixed_data.witness_ids.len();
         if id < 2 * witness_count {
             (
@@ -54,30 +56,30 @@ impl<'a> SymbolicEvaluator<'a> {
         }
     }
 
-    pub fn id_for_fixed_poly(&self, name: &str, next: bool) -> usize {
+    pub fn id_for_fixed_poly(&self, name: Symbol, next: bool) -> usize {
         let witness_count = self.fixed_data.witness_ids.len();
         let fixed_count = self.fixed_ids.len();
 
         let id = self
             .fixed_ids
-            .get(name)
+            .get(&name)
             .unwrap_or_else(|| panic!("fixed poly {name} not found"));
         2 * witness_count + id + if next { fixed_count } else { 0 }
     }
-    pub fn id_for_witness_poly(&self, name: &str, next: bool) -> usize {
+    pub fn id_for_witness_poly(&self, name: Symbol, next: bool) -> usize {
         let witness_count = self.fixed_data.witness_ids.len();
-        self.fixed_data.witness_ids[name] + if next { witness_count } else { 0 }
+        self.fixed_data.witness_ids[&name] + if next { witness_count } else { 0 }
     }
 }
 
-impl<'a> SymbolicVariables for SymbolicEvaluator<'a> {
-    fn constant(&self, name: &str) -> AffineResult {
+impl<'a, T: FieldElement> SymbolicVariables<T> for SymbolicEvaluator<'a, T> {
+    fn constant(&self, name: &str) -> AffineResult<T> {
         Ok(self.fixed_data.constants[name].into())
     }
 
-    fn value(&self, name: &str, next: bool) -> AffineResult {
+    fn value(&self, name: Symbol, next: bool) -> AffineResult<T> {
         // TODO arrays
-        if self.fixed_data.witness_ids.get(name).is_some() {
+        if self.fixed_data.witness_ids.contains_key(&name) {
             Ok(AffineExpression::from_variable_id(
                 self.id_for_witness_poly(name, next),
             ))
@@ -88,12 +90,12 @@ impl<'a> SymbolicVariables for SymbolicEvaluator<'a> {
         }
     }
 
-    fn format(&self, expr: AffineExpression) -> String {
+    fn format(&self, expr: AffineExpression<T>) -> String {
         expr.format(self)
     }
 }
 
-impl<'a> WitnessColumnNamer for SymbolicEvaluator<'a> {
+impl<'a, T: FieldElement> WitnessColumnNamer for SymbolicEvaluator<'a, T> {
     fn name(&self, id: usize) -> String {
         let (name, next) = self.poly_from_id(id);
         if next {
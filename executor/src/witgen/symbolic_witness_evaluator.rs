@@ -1,4 +1,5 @@
-use number::DegreeType;
+use number::{DegreeType, FieldElement};
+use pil_analyzer::Symbol;
 
 use super::{
     affine_expression::{AffineExpression, AffineResult},
@@ -7,30 +8,30 @@ use super::{
     FixedData,
 };
 
-pub trait WitnessColumnEvaluator {
+pub trait WitnessColumnEvaluator<T> {
     /// Returns a symbolic or concrete value for the given witness column and next flag.
     /// This function defines the mapping to IDs.
     /// It should be used together with a matching reverse mapping in WitnessColumnNamer.
-    fn value(&self, name: &str, next: bool) -> AffineResult;
+    fn value(&self, name: Symbol, next: bool) -> AffineResult<T>;
 }
 
 /// An evaluator (to be used together with ExpressionEvaluator) that performs concrete
 /// evaluation of all fixed columns but falls back to a generic WitnessColumnEvaluator
 /// to evaluate the witness columns either symbolically or concretely.
-pub struct SymoblicWitnessEvaluator<'a, WA: WitnessColumnEvaluator + WitnessColumnNamer> {
-    fixed_data: &'a FixedData<'a>,
+pub struct SymoblicWitnessEvaluator<'a, T, WA: WitnessColumnEvaluator<T> + WitnessColumnNamer> {
+    fixed_data: &'a FixedData<'a, T>,
     row: DegreeType,
     witness_access: WA,
 }
 
-impl<'a, WA> SymoblicWitnessEvaluator<'a, WA>
+impl<'a, T: FieldElement, WA> SymoblicWitnessEvaluator<'a, T, WA>
 where
-    WA: WitnessColumnEvaluator + WitnessColumnNamer,
+    WA: WitnessColumnEvaluator<T> + WitnessColumnNamer,
 {
     /// Constructs a new SymbolicWitnessEvaluator
     /// @param row the row on which to evaluate plain fixed
     ///            columns ("next columns" - f' - are evaluated on row + 1).
-    pub fn new(fixed_data: &'a FixedData<'a>, row: DegreeType, witness_access: WA) -> Self {
+    pub fn new(fixed_data: &'a FixedData<'a, T>, row: DegreeType, witness_access: WA) -> Self {
         Self {
             fixed_data,
             row,
@@ -39,24 +40,24 @@ where
     }
 }
 
-impl<'a, WA> SymbolicVariables for SymoblicWitnessEvaluator<'a, WA>
+impl<'a, T: FieldElement, WA> SymbolicVariables<T> for SymoblicWitnessEvaluator<'a, T, WA>
 where
-    WA: WitnessColumnEvaluator + WitnessColumnNamer,
+    WA: WitnessColumnEvaluator<T> + WitnessColumnNamer,
 {
-    fn constant(&self, name: &str) -> AffineResult {
+    fn constant(&self, name: &str) -> AffineResult<T> {
         Ok(self.fixed_data.constants[name].into())
     }
 
-    fn value(&self, name: &str, next: bool) -> AffineResult {
+    fn value(&self, name: Symbol, next: bool) -> AffineResult<T> {
         // TODO arrays
-        if self.fixed_data.witness_ids.contains_key(name) {
+        if self.fixed_data.witness_ids.contains_key(&name) {
             self.witness_access.value(name, next)
         } else {
             // Constant polynomial (or something else)
             let values = self
                 .fixed_data
                 .fixed_cols
-                .get(name)
+                .get(&name)
                 .unwrap_or_else(|| panic!("unknown col: {name}"));
             let row = if next {
                 let degree = values.len() as DegreeType;
@@ -68,7 +69,7 @@ where
         }
     }
 
-    fn format(&self, expr: AffineExpression) -> String {
+    fn format(&self, expr: AffineExpression<T>) -> String {
         expr.format(&self.witness_access)
     }
 }
@@ -1,4 +1,5 @@
-use pil_analyzer::{util::expr_any, Expression, PolynomialReference};
+use number::FieldElement;
+use pil_analyzer::{util::expr_any, Expression, PolynomialReference, Symbol};
 
 use super::FixedData;
 
@@ -8,29 +9,33 @@ pub trait WitnessColumnNamer {
 
 /// @returns true if the expression contains a reference to a next value of a
 /// (witness or fixed) column
-pub fn contains_next_ref(expr: &Expression) -> bool {
-    expr_any(expr, |e| match e {
+pub fn contains_next_ref<T: FieldElement>(expr: &Expression<T>, fixed_data: &FixedData<T>) -> bool {
+    expr_any(fixed_data.expressions, expr, |e| match e {
         Expression::PolynomialReference(poly) => poly.next,
         _ => false,
     })
 }
 
 /// @returns true if the expression contains a reference to a next value of a witness column.
-pub fn contains_next_witness_ref(expr: &Expression, fixed_data: &FixedData) -> bool {
-    expr_any(expr, |e| match e {
+pub fn contains_next_witness_ref<T: FieldElement>(
+    expr: &Expression<T>,
+    fixed_data: &FixedData<T>,
+) -> bool {
+    expr_any(fixed_data.expressions, expr, |e| match e {
         Expression::PolynomialReference(poly) => {
-            poly.next && fixed_data.witness_ids.contains_key(poly.name.as_str())
+            poly.next && fixed_data.witness_ids.contains_key(&poly.name)
         }
         _ => false,
     })
 }
 
 /// @returns true if the expression contains a reference to a witness column.
-pub fn contains_witness_ref(expr: &Expression, fixed_data: &FixedData) -> bool {
-    expr_any(expr, |e| match e {
-        Expression::PolynomialReference(poly) => {
-            fixed_data.witness_ids.contains_key(poly.name.as_str())
-        }
+pub fn contains_witness_ref<T: FieldElement>(
+    expr: &Expression<T>,
+    fixed_data: &FixedData<T>,
+) -> bool {
+    expr_any(fixed_data.expressions, expr, |e| match e {
+        Expression::PolynomialReference(poly) => fixed_data.witness_ids.contains_key(&poly.name),
         _ => false,
     })
 }
@@ -40,14 +45,14 @@ pub fn contains_witness_ref(expr: &Expression, fixed_data: &FixedData) -> bool {
 /// - not part of a polynomial array
 /// - not shifted with `'`
 /// and return the polynomial's name if so
-pub fn is_simple_poly(expr: &Expression) -> Option<&str> {
+pub fn is_simple_poly<T: FieldElement>(expr: &Expression<T>) -> Option<Symbol> {
     if let Expression::PolynomialReference(PolynomialReference {
         name,
         index: None,
         next: false,
     }) = expr
     {
-        Some(name)
+        Some(*name)
     } else {
         None
     }
@@ -0,0 +1,66 @@
+/*
+ * C API of powdr to analyze PIL and generate witnesses, built as
+ * `libpowdr_ffi` by `cargo build -p ffi --release`.
+ *
+ * All functions except `powdr_witness_name` and `powdr_last_error` return
+ * a status. On failure, `powdr_last_error` returns a message for it.
+ * Field elements are little-endian byte strings of `element_size` bytes,
+ * see `powdr_witness_size`.
+ */
+
+#ifndef POWDR_H
+#define POWDR_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum PowdrStatus {
+    POWDR_OK = 0,
+    /* A null pointer, a string that is not UTF-8 or an unknown field. */
+    POWDR_INVALID_ARGUMENT = 1,
+    /* The PIL source could not be analyzed. */
+    POWDR_ANALYSIS_FAILED = 2,
+    /* The witness could not be generated. */
+    POWDR_WITGEN_FAILED = 3,
+    /* The buffer cannot hold the witness, see `powdr_witness_size`. */
+    POWDR_BUFFER_TOO_SMALL = 4,
+} PowdrStatus;
+
+typedef struct PowdrAnalyzed PowdrAnalyzed;
+
+/* Analyzes the PIL source code in the field "gl", "bn254" or "bb" and stores
+ * the result in `*out`, to be released with `powdr_analyzed_free`. */
+PowdrStatus powdr_analyze(const char *source, const char *field, PowdrAnalyzed **out);
+
+/* Releases analyzed PIL. Null is ignored. */
+void powdr_analyzed_free(PowdrAnalyzed *analyzed);
+
+/* Stores the number of witness columns, the number of rows and the number of
+ * bytes per element in the pointers that are not null. The buffer for
+ * `powdr_generate_witness` needs the product of the three in bytes. */
+PowdrStatus powdr_witness_size(const PowdrAnalyzed *analyzed, size_t *columns, uint64_t *rows,
+                               size_t *element_size);
+
+/* Returns the name of the witness column with the given index in source order,
+ * or null. The string is valid until `analyzed` is released. */
+const char *powdr_witness_name(const PowdrAnalyzed *analyzed, size_t index);
+
+/* Generates the witness, answering ("input", i) queries with inputs[i], and
+ * writes it to `buffer`: the witness columns in source order, each one row
+ * after row. */
+PowdrStatus powdr_generate_witness(const PowdrAnalyzed *analyzed, const uint64_t *inputs,
+                                   size_t inputs_len, uint8_t *buffer, size_t buffer_len);
+
+/* Returns the message of the last error on this thread, or null. The string
+ * is valid until the next failing call on this thread. */
+const char *powdr_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* POWDR_H */
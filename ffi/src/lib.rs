@@ -0,0 +1,403 @@
+//! A C API to embed the PIL analyzer and witness generation in programs
+//! written in other languages, see `include/powdr.h`.
+//!
+//! All functions return a `PowdrStatus`. On failure, `powdr_last_error`
+//! returns a message describing the error, panics in the pipeline are
+//! caught and reported as errors. Field elements are passed as
+//! little-endian byte strings of a fixed size per field, see
+//! `powdr_witness_size`.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use number::{BabyBearField, Bn254Field, DegreeType, FieldElement, GoldilocksField};
+use pil_analyzer::Analyzed;
+
+/// The result of a call, `POWDR_OK` on success.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowdrStatus {
+    Ok = 0,
+    /// A null pointer, a string that is not UTF-8 or an unknown field.
+    InvalidArgument = 1,
+    /// The PIL source could not be analyzed.
+    AnalysisFailed = 2,
+    /// The witness could not be generated.
+    WitgenFailed = 3,
+    /// The buffer cannot hold the values, see `powdr_witness_size`.
+    BufferTooSmall = 4,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// An error with the status code to return for it.
+struct Error(PowdrStatus, String);
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', "")).unwrap();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+/// Runs `f`, stores the message of an error or panic for `powdr_last_error`
+/// and returns the status of the error, or `panic_status` for a panic.
+fn run(panic_status: PowdrStatus, f: impl FnOnce() -> Result<(), Error>) -> PowdrStatus {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => PowdrStatus::Ok,
+        Ok(Err(Error(status, message))) => {
+            set_last_error(message);
+            status
+        }
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| "Unknown error.".to_string());
+            set_last_error(message);
+            panic_status
+        }
+    }
+}
+
+fn invalid_argument(message: &str) -> Error {
+    Error(PowdrStatus::InvalidArgument, message.to_string())
+}
+
+/// Returns the string behind the pointer.
+///
+/// # Safety
+/// The pointer has to be null or point to a nul-terminated string.
+unsafe fn to_str<'a>(s: *const c_char, name: &str) -> Result<&'a str, Error> {
+    if s.is_null() {
+        return Err(invalid_argument(&format!("{name} is null.")));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| invalid_argument(&format!("{name} is not valid UTF-8.")))
+}
+
+/// Analyzed PIL in one of the supported fields.
+enum AnyAnalyzed {
+    Goldilocks(Analyzed<GoldilocksField>),
+    Bn254(Analyzed<Bn254Field>),
+    BabyBear(Analyzed<BabyBearField>),
+}
+
+/// Runs the body with `$analyzed` bound to the analyzed PIL and `$T` to its field.
+macro_rules! with_analyzed {
+    ($any:expr, $analyzed:ident, $T:ident => $body:expr) => {
+        match $any {
+            AnyAnalyzed::Goldilocks($analyzed) => {
+                type $T = GoldilocksField;
+                $body
+            }
+            AnyAnalyzed::Bn254($analyzed) => {
+                type $T = Bn254Field;
+                $body
+            }
+            AnyAnalyzed::BabyBear($analyzed) => {
+                type $T = BabyBearField;
+                $body
+            }
+        }
+    };
+}
+
+/// Analyzed PIL, created by `powdr_analyze` and released by `powdr_analyzed_free`.
+pub struct PowdrAnalyzed {
+    analyzed: AnyAnalyzed,
+    degree: DegreeType,
+    element_size: usize,
+    /// The names of the witness columns in source order.
+    witness_names: Vec<CString>,
+}
+
+impl PowdrAnalyzed {
+    fn new(analyzed: AnyAnalyzed) -> Self {
+        with_analyzed!(&analyzed, pil, T => {
+            let degree = pil
+                .definitions
+                .values()
+                .map(|(poly, _)| poly.degree)
+                .max()
+                .unwrap_or_default();
+            let witness_names = pil
+                .committed_polys_in_source_order()
+                .iter()
+                .map(|(poly, _)| CString::new(poly.absolute_name.as_str()).unwrap())
+                .collect();
+            PowdrAnalyzed {
+                degree,
+                element_size: T::byte_len(),
+                witness_names,
+                analyzed,
+            }
+        })
+    }
+
+    /// The size of the witness in bytes.
+    fn witness_size(&self) -> usize {
+        self.witness_names.len() * self.degree as usize * self.element_size
+    }
+}
+
+/// Analyzes the PIL source code in the field `field` ("gl", "bn254" or "bb")
+/// and stores the result in `*out`, to be released with `powdr_analyzed_free`.
+///
+/// # Safety
+/// `source` and `field` have to be nul-terminated strings and `out` has to be
+/// a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn powdr_analyze(
+    source: *const c_char,
+    field: *const c_char,
+    out: *mut *mut PowdrAnalyzed,
+) -> PowdrStatus {
+    run(PowdrStatus::AnalysisFailed, || {
+        let source = to_str(source, "source")?;
+        let field = to_str(field, "field")?;
+        if out.is_null() {
+            return Err(invalid_argument("out is null."));
+        }
+        let analysis_failed = |e| Error(PowdrStatus::AnalysisFailed, format!("{e}"));
+        let analyzed = match field {
+            "gl" => AnyAnalyzed::Goldilocks(
+                pil_analyzer::try_analyze_string(source).map_err(analysis_failed)?,
+            ),
+            "bn254" => AnyAnalyzed::Bn254(
+                pil_analyzer::try_analyze_string(source).map_err(analysis_failed)?,
+            ),
+            "bb" => AnyAnalyzed::BabyBear(
+                pil_analyzer::try_analyze_string(source).map_err(analysis_failed)?,
+            ),
+            field => {
+                return Err(invalid_argument(&format!(
+                    "Unknown field {field}, expected gl, bn254 or bb."
+                )))
+            }
+        };
+        *out = Box::into_raw(Box::new(PowdrAnalyzed::new(analyzed)));
+        Ok(())
+    })
+}
+
+/// Releases analyzed PIL returned by `powdr_analyze`. Null is ignored.
+///
+/// # Safety
+/// `analyzed` has to be null or returned by `powdr_analyze` and not yet released.
+#[no_mangle]
+pub unsafe extern "C" fn powdr_analyzed_free(analyzed: *mut PowdrAnalyzed) {
+    if !analyzed.is_null() {
+        drop(Box::from_raw(analyzed));
+    }
+}
+
+/// Stores the number of witness columns, the number of rows and the number
+/// of bytes per element in the pointers that are not null. The buffer for
+/// `powdr_generate_witness` needs the product of the three in bytes.
+///
+/// # Safety
+/// `analyzed` has to be returned by `powdr_analyze` and the other pointers
+/// have to be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn powdr_witness_size(
+    analyzed: *const PowdrAnalyzed,
+    columns: *mut usize,
+    rows: *mut u64,
+    element_size: *mut usize,
+) -> PowdrStatus {
+    run(PowdrStatus::InvalidArgument, || {
+        let analyzed = analyzed
+            .as_ref()
+            .ok_or_else(|| invalid_argument("analyzed is null."))?;
+        if let Some(columns) = columns.as_mut() {
+            *columns = analyzed.witness_names.len();
+        }
+        if let Some(rows) = rows.as_mut() {
+            *rows = analyzed.degree;
+        }
+        if let Some(element_size) = element_size.as_mut() {
+            *element_size = analyzed.element_size;
+        }
+        Ok(())
+    })
+}
+
+/// Returns the name of the witness column with the given index in source
+/// order, or null if there is no such column. The string is valid until
+/// `analyzed` is released.
+///
+/// # Safety
+/// `analyzed` has to be null or returned by `powdr_analyze`.
+#[no_mangle]
+pub unsafe extern "C" fn powdr_witness_name(
+    analyzed: *const PowdrAnalyzed,
+    index: usize,
+) -> *const c_char {
+    analyzed
+        .as_ref()
+        .and_then(|analyzed| analyzed.witness_names.get(index))
+        .map_or(ptr::null(), |name| name.as_ptr())
+}
+
+/// Generates the witness, answering `("input", i)` queries with `inputs[i]`,
+/// and writes it to `buffer`: the witness columns in source order, each one
+/// row after row, with the elements in little endian.
+///
+/// # Safety
+/// `analyzed` has to be returned by `powdr_analyze`, `inputs` has to point to
+/// `inputs_len` values (or be null if it is zero) and `buffer` to
+/// `buffer_len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn powdr_generate_witness(
+    analyzed: *const PowdrAnalyzed,
+    inputs: *const u64,
+    inputs_len: usize,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> PowdrStatus {
+    run(PowdrStatus::WitgenFailed, || {
+        let analyzed = analyzed
+            .as_ref()
+            .ok_or_else(|| invalid_argument("analyzed is null."))?;
+        let inputs = match inputs_len {
+            0 => &[][..],
+            _ if inputs.is_null() => return Err(invalid_argument("inputs is null.")),
+            _ => slice::from_raw_parts(inputs, inputs_len),
+        };
+        if buffer.is_null() {
+            return Err(invalid_argument("buffer is null."));
+        }
+        let size = analyzed.witness_size();
+        if buffer_len < size {
+            return Err(Error(
+                PowdrStatus::BufferTooSmall,
+                format!("The witness needs {size} bytes, but the buffer has {buffer_len}."),
+            ));
+        }
+        let buffer = slice::from_raw_parts_mut(buffer, size);
+        buffer.fill(0);
+        with_analyzed!(&analyzed.analyzed, pil, T => {
+            let (fixed, degree) = executor::constant_evaluator::generate(pil)
+                .map_err(|e| Error(PowdrStatus::WitgenFailed, e))?;
+            let witness = executor::witgen::generate(
+                pil,
+                degree,
+                &fixed,
+                Some(executor::witgen::inputs_query_callback(
+                    inputs.iter().map(|i| T::from(*i)).collect(),
+                    0,
+                )),
+            );
+            let column_size = analyzed.degree as usize * analyzed.element_size;
+            for ((_, values), column) in witness.iter().zip(buffer.chunks_exact_mut(column_size)) {
+                for (value, element) in values.iter().zip(column.chunks_exact_mut(analyzed.element_size)) {
+                    let bytes = value.to_bytes_le();
+                    element[..bytes.len()].copy_from_slice(&bytes);
+                }
+            }
+        });
+        Ok(())
+    })
+}
+
+/// Returns the message of the last error on this thread, or null. The string
+/// is valid until the next failing call on this thread.
+#[no_mangle]
+pub extern "C" fn powdr_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SOURCE: &str = "namespace Fib(4);
+col fixed ISLAST(i) { match i { 3 => 1, _ => 0 } };
+col witness x, y;
+(1 - ISLAST) * (x' - y) = 0;
+(1 - ISLAST) * (y' - (x + y)) = 0;
+ISLAST * (x' - 1) = 0;
+ISLAST * (y' - 1) = 0;
+col witness input(i) query (\"input\", i);\0";
+
+    fn analyze(source: &str, field: &str) -> (PowdrStatus, *mut PowdrAnalyzed) {
+        let field = CString::new(field).unwrap();
+        let mut analyzed = ptr::null_mut();
+        let status = unsafe {
+            powdr_analyze(
+                source.as_ptr() as *const c_char,
+                field.as_ptr(),
+                &mut analyzed,
+            )
+        };
+        (status, analyzed)
+    }
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(powdr_last_error()) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn generate_witness() {
+        let (status, analyzed) = analyze(SOURCE, "bb");
+        assert_eq!(status, PowdrStatus::Ok);
+        let (mut columns, mut rows, mut element_size) = (0, 0, 0);
+        unsafe {
+            let status = powdr_witness_size(analyzed, &mut columns, &mut rows, &mut element_size);
+            assert_eq!(status, PowdrStatus::Ok);
+            assert_eq!((columns, rows, element_size), (3, 4, 4));
+            let name = CStr::from_ptr(powdr_witness_name(analyzed, 1));
+            assert_eq!(name.to_str().unwrap(), "Fib.y");
+            assert!(powdr_witness_name(analyzed, 3).is_null());
+
+            let mut buffer = vec![0xffu8; 48];
+            let inputs = [7, 8, 9, 10];
+            let status = powdr_generate_witness(
+                analyzed,
+                inputs.as_ptr(),
+                inputs.len(),
+                buffer.as_mut_ptr(),
+                buffer.len(),
+            );
+            assert_eq!(status, PowdrStatus::Ok);
+            let values = buffer
+                .chunks(4)
+                .map(|e| u32::from_le_bytes(e.try_into().unwrap()))
+                .collect::<Vec<_>>();
+            assert_eq!(values, [1, 1, 2, 3, 1, 2, 3, 5, 7, 8, 9, 10]);
+
+            let status = powdr_generate_witness(analyzed, ptr::null(), 0, buffer.as_mut_ptr(), 47);
+            assert_eq!(status, PowdrStatus::BufferTooSmall);
+            assert_eq!(
+                last_error(),
+                "The witness needs 48 bytes, but the buffer has 47."
+            );
+            powdr_analyzed_free(analyzed);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let (status, analyzed) = analyze(SOURCE, "m61");
+        assert_eq!(status, PowdrStatus::InvalidArgument);
+        assert!(analyzed.is_null());
+        assert_eq!(last_error(), "Unknown field m61, expected gl, bn254 or bb.");
+
+        let (status, analyzed) = analyze("namespace Fib(4);\ncol witness in;\0", "gl");
+        assert_eq!(status, PowdrStatus::AnalysisFailed);
+        assert!(analyzed.is_null());
+        assert!(!last_error().is_empty());
+
+        let status = unsafe { powdr_analyze(ptr::null(), ptr::null(), ptr::null_mut()) };
+        assert_eq!(status, PowdrStatus::InvalidArgument);
+        assert_eq!(last_error(), "source is null.");
+    }
+}
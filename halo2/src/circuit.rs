@@ -0,0 +1,223 @@
+//! The Halo2 circuit for a `Shape`.
+//!
+//! Every PIL column becomes a Halo2 column. PIL constraints are cyclic, so
+//! row `degree` is a copy of row 0 and constraints are only enabled on rows
+//! `0..degree`, which lets next references on the last row wrap around.
+
+use std::{cell::RefCell, rc::Rc};
+
+use halo2_proofs::{
+    circuit::{Cell, Layouter, SimpleFloorPlanner},
+    pasta::Fp,
+    plonk::{
+        self, Advice, Circuit, Column, ConstraintSystem, Expression, Fixed, Instance, TableColumn,
+        VirtualCells,
+    },
+    poly::Rotation,
+};
+
+use crate::shape::{Expr, Shape};
+
+thread_local! {
+    /// Halo2 configures circuits without access to the circuit itself,
+    /// so the shape is passed through here, see `with_shape`.
+    static SHAPE: RefCell<Option<Rc<Shape>>> = const { RefCell::new(None) };
+}
+
+/// Runs `f` with `shape` available to `PowdrCircuit::configure`.
+pub(crate) fn with_shape<R>(shape: &Rc<Shape>, f: impl FnOnce() -> R) -> R {
+    SHAPE.with(|s| *s.borrow_mut() = Some(shape.clone()));
+    let result = f();
+    SHAPE.with(|s| *s.borrow_mut() = None);
+    result
+}
+
+#[derive(Clone)]
+pub(crate) struct PowdrCircuit {
+    pub shape: Rc<Shape>,
+    /// The advice column values, None during key generation.
+    pub witness: Option<Rc<Vec<Vec<Fp>>>>,
+}
+
+#[derive(Clone)]
+pub(crate) struct Config {
+    /// One on rows `0..degree`, zero on the wrap-around row.
+    enable: Column<Fixed>,
+    fixed: Vec<Column<Fixed>>,
+    advice: Vec<Column<Advice>>,
+    instance: Column<Instance>,
+    tables: Vec<Vec<TableColumn>>,
+}
+
+impl Circuit<Fp> for PowdrCircuit {
+    type Config = Config;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            shape: self.shape.clone(),
+            witness: None,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Config {
+        let shape = SHAPE.with(|s| {
+            s.borrow()
+                .clone()
+                .expect("The circuit shape has to be set before configuring.")
+        });
+        let config = Config {
+            enable: meta.fixed_column(),
+            fixed: shape.fixed.iter().map(|_| meta.fixed_column()).collect(),
+            advice: shape
+                .advice_names
+                .iter()
+                .map(|_| meta.advice_column())
+                .collect(),
+            instance: meta.instance_column(),
+            tables: shape
+                .lookups
+                .iter()
+                .map(|lookup| {
+                    lookup
+                        .table
+                        .iter()
+                        .map(|_| meta.lookup_table_column())
+                        .collect()
+                })
+                .collect(),
+        };
+        for column in &config.advice {
+            meta.enable_equality(*column);
+        }
+        meta.enable_equality(config.instance);
+
+        if !shape.constraints.is_empty() {
+            meta.create_gate("identities", |meta| {
+                let enable = meta.query_fixed(config.enable, Rotation::cur());
+                shape
+                    .constraints
+                    .iter()
+                    .map(|(_, e)| enable.clone() * to_halo2(e, &config, meta))
+                    .collect::<Vec<_>>()
+            });
+        }
+        for (lookup, table) in shape.lookups.iter().zip(&config.tables) {
+            meta.lookup(|meta| {
+                let selector = meta.query_fixed(config.enable, Rotation::cur())
+                    * to_halo2(&lookup.selector, &config, meta);
+                // On inactive rows, the input is all zeros, which is added to the table.
+                std::iter::once(selector.clone())
+                    .chain(
+                        lookup
+                            .inputs
+                            .iter()
+                            .map(|e| selector.clone() * to_halo2(e, &config, meta)),
+                    )
+                    .zip(table.iter().copied())
+                    .collect()
+            });
+        }
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), plonk::Error> {
+        let shape = &self.shape;
+        let degree = shape.degree;
+        let public_cells = layouter.assign_region(
+            || "main",
+            |mut region| {
+                for row in 0..=degree {
+                    let enable = if row < degree { Fp::one() } else { Fp::zero() };
+                    region.assign_fixed(|| "enable", config.enable, row, || Ok(enable))?;
+                    for ((column, values), name) in config
+                        .fixed
+                        .iter()
+                        .zip(&shape.fixed)
+                        .zip(&shape.fixed_names)
+                    {
+                        region.assign_fixed(
+                            || name.as_str(),
+                            *column,
+                            row,
+                            || Ok(values[row % degree]),
+                        )?;
+                    }
+                }
+                let mut public_cells: Vec<Option<Cell>> = vec![None; shape.publics.len()];
+                for (index, (column, name)) in
+                    config.advice.iter().zip(&shape.advice_names).enumerate()
+                {
+                    let mut first = None;
+                    for row in 0..=degree {
+                        let cell = region.assign_advice(
+                            || name.as_str(),
+                            *column,
+                            row,
+                            || {
+                                self.witness
+                                    .as_ref()
+                                    .map(|w| w[index][row % degree])
+                                    .ok_or(plonk::Error::Synthesis)
+                            },
+                        )?;
+                        match first {
+                            None => first = Some(cell.cell()),
+                            Some(first) if row == degree => {
+                                region.constrain_equal(first, cell.cell())?
+                            }
+                            _ => {}
+                        }
+                        for (public, cell_slot) in shape.publics.iter().zip(&mut public_cells) {
+                            if *public == (index, row) {
+                                *cell_slot = Some(cell.cell());
+                            }
+                        }
+                    }
+                }
+                Ok(public_cells)
+            },
+        )?;
+        for (i, cell) in public_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell.unwrap(), config.instance, i)?;
+        }
+
+        for (lookup, columns) in shape.lookups.iter().zip(&config.tables) {
+            layouter.assign_table(
+                || "lookup table",
+                |mut table| {
+                    for (column, values) in columns.iter().zip(&lookup.table) {
+                        for (row, value) in values.iter().enumerate() {
+                            table.assign_cell(|| "table", *column, row, || Ok(*value))?;
+                        }
+                        table.assign_cell(|| "zero", *column, degree, || Ok(Fp::zero()))?;
+                    }
+                    Ok(())
+                },
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn to_halo2(expr: &Expr, config: &Config, meta: &mut VirtualCells<'_, Fp>) -> Expression<Fp> {
+    let rotation = |next: bool| {
+        if next {
+            Rotation::next()
+        } else {
+            Rotation::cur()
+        }
+    };
+    match expr {
+        Expr::Constant(c) => Expression::Constant(*c),
+        Expr::Fixed(index, next) => meta.query_fixed(config.fixed[*index], rotation(*next)),
+        Expr::Advice(index, next) => meta.query_advice(config.advice[*index], rotation(*next)),
+        Expr::Sum(left, right) => to_halo2(left, config, meta) + to_halo2(right, config, meta),
+        Expr::Product(left, right) => to_halo2(left, config, meta) * to_halo2(right, config, meta),
+        Expr::Negated(inner) => -to_halo2(inner, config, meta),
+    }
+}
@@ -0,0 +1,75 @@
+//! Proves and verifies analyzed PIL files with Halo2 (IPA commitments over
+//! the Pasta curves). The PIL has to be compiled in the Pasta `Fp` field,
+//! see `modulus`.
+
+mod circuit;
+mod prover;
+mod shape;
+
+use std::fmt;
+
+use ff::PrimeField;
+use halo2_proofs::{pasta::Fp, plonk};
+use number::{AbstractNumberType, FieldElement};
+
+pub use prover::{mock_prove, prove, publics, verify, Halo2Backend, Halo2Setup};
+
+/// Returns the modulus of the field the Halo2 backend works in.
+pub fn modulus() -> AbstractNumberType {
+    AbstractNumberType::from_bytes_le(&(-Fp::one()).to_repr()) + 1u32
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The PIL file was compiled in a different field.
+    FieldMismatch(AbstractNumberType),
+    /// The PIL file uses a feature the backend does not support.
+    Unsupported(String),
+    /// A column that the PIL file declares was not provided.
+    MissingColumn(String),
+    /// The witness does not satisfy the constraints, found by the mock prover.
+    ConstraintsNotSatisfied(Vec<String>),
+    /// An error reported by Halo2, including failed verification.
+    Halo2(plonk::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::FieldMismatch(modulus) => write!(
+                f,
+                "The Halo2 backend needs the field with modulus {}, but the modulus is {modulus}.",
+                crate::modulus()
+            ),
+            Error::Unsupported(what) => write!(f, "Not supported by the Halo2 backend: {what}"),
+            Error::MissingColumn(name) => write!(f, "No values provided for column {name}."),
+            Error::ConstraintsNotSatisfied(failures) => {
+                write!(f, "Constraints not satisfied:\n{}", failures.join("\n"))
+            }
+            Error::Halo2(error) => write!(f, "Halo2 error: {error:?}"),
+        }
+    }
+}
+
+impl From<plonk::Error> for Error {
+    fn from(error: plonk::Error) -> Self {
+        Error::Halo2(error)
+    }
+}
+
+/// Converts a field element to the Halo2 field.
+/// The caller has to make sure the moduli are the same.
+fn to_fp<T: FieldElement>(value: &T) -> Fp {
+    let mut bytes = [0u8; 32];
+    let value_bytes = value.to_integer().to_bytes_le();
+    bytes[..value_bytes.len()].copy_from_slice(&value_bytes);
+    Fp::from_repr(bytes).unwrap()
+}
+
+fn check_field<T: FieldElement>() -> Result<(), Error> {
+    if T::modulus() == modulus() {
+        Ok(())
+    } else {
+        Err(Error::FieldMismatch(T::modulus()))
+    }
+}
@@ -0,0 +1,451 @@
+use std::{cell::OnceCell, io, rc::Rc};
+
+use backend::{is_reproducible, proof_rng, Backend, Capabilities, Columns, Publics};
+use halo2_proofs::{
+    dev::MockProver,
+    pasta::{EqAffine, Fp},
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, ConstraintSystem, ProvingKey,
+        SingleVerifier, VerifyingKey,
+    },
+    poly::commitment::Params,
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+};
+use number::FieldElement;
+use pil_analyzer::Analyzed;
+
+use crate::{
+    check_field,
+    circuit::{with_shape, PowdrCircuit},
+    shape::Shape,
+    to_fp, Error,
+};
+
+/// The Halo2 backend, see `Halo2Setup` for its setup.
+pub struct Halo2Backend;
+
+/// The commitment parameters and the keys derived from them and the circuit.
+/// halo2_proofs 0.1 cannot serialize keys, so only the parameters are written
+/// to setup files. They only depend on the number of rows of the circuit, so
+/// the setup id is its logarithm k. The keys are derived in every process
+/// when they are first needed and reused for all proofs and verifications
+/// with the same setup, which therefore has to be used with the constraints
+/// and fixed columns it was created for.
+pub struct Halo2Setup {
+    params: Params<EqAffine>,
+    verifying_key: OnceCell<VerifyingKey<EqAffine>>,
+    proving_key: OnceCell<ProvingKey<EqAffine>>,
+}
+
+impl Halo2Setup {
+    fn new(params: Params<EqAffine>) -> Self {
+        Halo2Setup {
+            params,
+            verifying_key: OnceCell::new(),
+            proving_key: OnceCell::new(),
+        }
+    }
+
+    /// Needs to be called in `with_shape` for the circuit.
+    fn verifying_key(&self, circuit: &PowdrCircuit) -> Result<&VerifyingKey<EqAffine>, Error> {
+        if let Some(vk) = self.verifying_key.get() {
+            return Ok(vk);
+        }
+        let vk = keygen_vk(&self.params, &circuit.without_witnesses())?;
+        Ok(self.verifying_key.get_or_init(|| vk))
+    }
+
+    /// Needs to be called in `with_shape` for the circuit.
+    fn proving_key(&self, circuit: &PowdrCircuit) -> Result<&ProvingKey<EqAffine>, Error> {
+        if let Some(pk) = self.proving_key.get() {
+            return Ok(pk);
+        }
+        let vk = self.verifying_key(circuit)?.clone();
+        let pk = keygen_pk(&self.params, vk, &circuit.without_witnesses())?;
+        Ok(self.proving_key.get_or_init(|| pk))
+    }
+
+    /// Creates a proof with fixed randomness if `reproducible` is set,
+    /// see `backend::set_reproducible`.
+    fn prove<T: FieldElement>(
+        &self,
+        analyzed: &Analyzed<T>,
+        fixed: &Columns<T>,
+        witness: &Columns<T>,
+        reproducible: bool,
+    ) -> Result<Vec<u8>, Error> {
+        let circuit = circuit(analyzed, fixed, Some(witness))?;
+        let instance = publics(analyzed, witness)?
+            .iter()
+            .map(to_fp)
+            .collect::<Vec<_>>();
+        with_shape(&circuit.shape, || {
+            let pk = self.proving_key(&circuit)?;
+            let mut transcript = Blake2bWrite::<_, EqAffine, Challenge255<_>>::init(vec![]);
+            create_proof(
+                &self.params,
+                pk,
+                std::slice::from_ref(&circuit),
+                &[&[&instance]],
+                proof_rng(reproducible),
+                &mut transcript,
+            )?;
+            Ok(transcript.finalize())
+        })
+    }
+}
+
+impl<T: FieldElement> Backend<T> for Halo2Backend {
+    type Setup = Halo2Setup;
+    type Proof = Vec<u8>;
+    type Error = Error;
+
+    fn name(&self) -> &str {
+        "halo2"
+    }
+
+    /// Lookups are only supported into fixed columns.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            plookup: true,
+            logup: false,
+            permutation: false,
+            connect: false,
+        }
+    }
+
+    fn setup(&self, analyzed: &Analyzed<T>, fixed: &Columns<T>) -> Result<Halo2Setup, Error> {
+        let circuit = circuit(analyzed, fixed, None)?;
+        Ok(Halo2Setup::new(with_shape(&circuit.shape, || {
+            Params::new(k(&circuit.shape))
+        })))
+    }
+
+    fn setup_id(&self, analyzed: &Analyzed<T>, fixed: &Columns<T>) -> Result<String, Error> {
+        let circuit = circuit(analyzed, fixed, None)?;
+        Ok(format!(
+            "k{}",
+            with_shape(&circuit.shape, || k(&circuit.shape))
+        ))
+    }
+
+    fn prove(
+        &self,
+        setup: &Halo2Setup,
+        analyzed: &Analyzed<T>,
+        fixed: &Columns<T>,
+        witness: &Columns<T>,
+    ) -> Result<Vec<u8>, Error> {
+        setup.prove(analyzed, fixed, witness, is_reproducible())
+    }
+
+    fn verify(
+        &self,
+        setup: &Halo2Setup,
+        analyzed: &Analyzed<T>,
+        fixed: &Columns<T>,
+        publics: &[T],
+        proof: &Vec<u8>,
+    ) -> Result<(), Error> {
+        let circuit = circuit(analyzed, fixed, None)?;
+        let instance = publics.iter().map(to_fp).collect::<Vec<_>>();
+        with_shape(&circuit.shape, || {
+            let vk = setup.verifying_key(&circuit)?;
+            let mut transcript = Blake2bRead::<_, EqAffine, Challenge255<_>>::init(&proof[..]);
+            verify_proof(
+                &setup.params,
+                vk,
+                SingleVerifier::new(&setup.params),
+                &[&[&instance]],
+                &mut transcript,
+            )?;
+            Ok(())
+        })
+    }
+
+    fn write_setup(&self, setup: &Halo2Setup, mut output: &mut dyn io::Write) -> io::Result<()> {
+        setup.params.write(&mut output)
+    }
+
+    fn read_setup(&self, mut input: &mut dyn io::Read) -> io::Result<Halo2Setup> {
+        Params::read(&mut input).map(Halo2Setup::new)
+    }
+
+    fn write_proof(&self, proof: &Vec<u8>, output: &mut dyn io::Write) -> io::Result<()> {
+        output.write_all(proof)
+    }
+
+    fn read_proof(&self, input: &mut dyn io::Read) -> io::Result<Vec<u8>> {
+        let mut proof = vec![];
+        input.read_to_end(&mut proof)?;
+        Ok(proof)
+    }
+}
+
+/// Creates a proof that the witness columns satisfy the constraints.
+/// The columns are given by name, as returned by the constant evaluator
+/// and the witness generator.
+pub fn prove<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    fixed: &Columns<T>,
+    witness: &Columns<T>,
+) -> Result<Vec<u8>, Error> {
+    let setup = Halo2Backend.setup(analyzed, fixed)?;
+    Halo2Backend.prove(&setup, analyzed, fixed, witness)
+}
+
+/// Verifies a proof created by `prove` for the given public values.
+pub fn verify<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    fixed: &Columns<T>,
+    publics: &[T],
+    proof: &[u8],
+) -> Result<(), Error> {
+    let setup = Halo2Backend.setup(analyzed, fixed)?;
+    Halo2Backend.verify(&setup, analyzed, fixed, publics, &proof.to_vec())
+}
+
+/// Checks all constraints using the Halo2 mock prover, which is much faster
+/// than creating a proof and reports the failing constraints.
+pub fn mock_prove<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    fixed: &Columns<T>,
+    witness: &Columns<T>,
+) -> Result<(), Error> {
+    let circuit = circuit(analyzed, fixed, Some(witness))?;
+    let instance = publics(analyzed, witness)?
+        .iter()
+        .map(to_fp)
+        .collect::<Vec<_>>();
+    with_shape(&circuit.shape, || {
+        MockProver::run(k(&circuit.shape), &circuit, vec![instance])?
+            .verify()
+            .map_err(|failures| {
+                Error::ConstraintsNotSatisfied(failures.iter().map(|f| f.to_string()).collect())
+            })
+    })
+}
+
+/// Returns the values of the public declarations, in declaration order.
+pub fn publics<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    witness: &Columns<T>,
+) -> Result<Vec<T>, Error> {
+    Publics::from_witness(analyzed, witness)
+        .map(|publics| publics.values())
+        .map_err(Error::MissingColumn)
+}
+
+fn circuit<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    fixed: &Columns<T>,
+    witness: Option<&Columns<T>>,
+) -> Result<PowdrCircuit, Error> {
+    check_field::<T>()?;
+    let shape = Shape::new(analyzed, fixed)?;
+    let witness = witness
+        .map(|witness| {
+            shape
+                .advice_names
+                .iter()
+                .map(|name| {
+                    witness
+                        .iter()
+                        .find(|(n, _)| name == n)
+                        .map(|(_, values)| values.iter().map(to_fp).collect::<Vec<Fp>>())
+                        .ok_or_else(|| Error::MissingColumn(name.to_string()))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()?;
+    Ok(PowdrCircuit {
+        shape: Rc::new(shape),
+        witness: witness.map(Rc::new),
+    })
+}
+
+/// Returns the logarithm of the number of rows of the circuit: The rows of the
+/// PIL file, the wrap-around row and the rows Halo2 needs for blinding.
+fn k(shape: &Rc<Shape>) -> u32 {
+    let mut cs = ConstraintSystem::default();
+    PowdrCircuit::configure(&mut cs);
+    let rows = (shape.degree + 2 + cs.blinding_factors()).max(cs.minimum_rows());
+    rows.next_power_of_two().trailing_zeros()
+}
+
+#[cfg(test)]
+mod test {
+    use executor::{constant_evaluator, witgen};
+    use number::DynamicField;
+    use pil_analyzer::analyze_string;
+
+    use super::*;
+
+    fn analyze(pil: &str) -> Analyzed<DynamicField> {
+        DynamicField::set_modulus(crate::modulus());
+        analyze_string(pil)
+    }
+
+    type Columns<'a> = Vec<(&'a str, Vec<DynamicField>)>;
+
+    /// Returns the fixed and witness columns.
+    fn columns(analyzed: &Analyzed<DynamicField>) -> (Columns<'_>, Columns<'_>) {
+        let (fixed, degree) = constant_evaluator::generate(analyzed).unwrap();
+        let witness = witgen::generate(
+            analyzed,
+            degree,
+            &fixed,
+            None::<fn(&str) -> Option<DynamicField>>,
+        );
+        (fixed, witness)
+    }
+
+    const FIBONACCI: &str = r#"
+constant %N = 16;
+namespace Fibonacci(%N);
+    col fixed ISLAST(i) { match i { %N - 1 => 1, _ => 0 } };
+    col witness x, y;
+    ISLAST * (y' - 1) = 0;
+    ISLAST * (x' - 1) = 0;
+    (1-ISLAST) * (x' - y) = 0;
+    (1-ISLAST) * (y' - (x + y)) = 0;
+    public out = y(%N-1);
+"#;
+
+    #[test]
+    fn fibonacci() {
+        let analyzed = analyze(FIBONACCI);
+        let (fixed, witness) = columns(&analyzed);
+        mock_prove(&analyzed, &fixed, &witness).unwrap();
+
+        let publics = publics(&analyzed, &witness).unwrap();
+        assert_eq!(publics, vec![DynamicField::from(1597)]);
+        let proof = prove(&analyzed, &fixed, &witness).unwrap();
+        verify(&analyzed, &fixed, &publics, &proof).unwrap();
+        assert!(verify(&analyzed, &fixed, &[DynamicField::from(1598)], &proof).is_err());
+    }
+
+    #[test]
+    fn serialized_setup() {
+        let analyzed = analyze(FIBONACCI);
+        let (fixed, witness) = columns(&analyzed);
+        let setup = Halo2Backend.setup(&analyzed, &fixed).unwrap();
+        let mut setup_bytes = vec![];
+        Backend::<DynamicField>::write_setup(&Halo2Backend, &setup, &mut setup_bytes).unwrap();
+        let setup =
+            Backend::<DynamicField>::read_setup(&Halo2Backend, &mut &setup_bytes[..]).unwrap();
+        assert!(setup.proving_key.get().is_none());
+        let proof = Halo2Backend
+            .prove(&setup, &analyzed, &fixed, &witness)
+            .unwrap();
+        // The keys are derived once and reused for further proofs.
+        assert!(setup.proving_key.get().is_some());
+        let publics = publics(&analyzed, &witness).unwrap();
+        Halo2Backend
+            .verify(&setup, &analyzed, &fixed, &publics, &proof)
+            .unwrap();
+        let proof = Halo2Backend
+            .prove(&setup, &analyzed, &fixed, &witness)
+            .unwrap();
+        Halo2Backend
+            .verify(&setup, &analyzed, &fixed, &publics, &proof)
+            .unwrap();
+
+        // The parameters only depend on the number of rows.
+        let other = analyze(&FIBONACCI.replace("(x + y)", "(x + 2 * y)"));
+        let (other_fixed, _) = constant_evaluator::generate(&other).unwrap();
+        assert_eq!(
+            Halo2Backend.setup_id(&analyzed, &fixed).unwrap(),
+            Halo2Backend.setup_id(&other, &other_fixed).unwrap()
+        );
+    }
+
+    #[test]
+    fn reproducible_proof() {
+        let analyzed = analyze(FIBONACCI);
+        let (fixed, witness) = columns(&analyzed);
+        let setup = Halo2Backend.setup(&analyzed, &fixed).unwrap();
+        let proofs = [(); 2].map(|_| setup.prove(&analyzed, &fixed, &witness, true).unwrap());
+        assert_eq!(proofs[0], proofs[1]);
+        assert_ne!(
+            proofs[0],
+            setup.prove(&analyzed, &fixed, &witness, false).unwrap()
+        );
+    }
+
+    #[test]
+    fn wrong_witness() {
+        let analyzed = analyze(FIBONACCI);
+        let (fixed, mut witness) = columns(&analyzed);
+        witness[1].1[3] += DynamicField::from(1);
+        assert!(matches!(
+            mock_prove(&analyzed, &fixed, &witness),
+            Err(Error::ConstraintsNotSatisfied(_))
+        ));
+    }
+
+    #[test]
+    fn lookup() {
+        let analyzed = analyze(
+            r#"
+namespace Main(16);
+    col fixed BYTE(i) { i * 16 };
+    col fixed FIRST = [1] + [0]*;
+    col witness x;
+    x' = (x + 16) * (1 - FIRST');
+    { x } in { BYTE };
+"#,
+        );
+        let (fixed, witness) = columns(&analyzed);
+        mock_prove(&analyzed, &fixed, &witness).unwrap();
+        let proof = prove(&analyzed, &fixed, &witness).unwrap();
+        verify(&analyzed, &fixed, &[], &proof).unwrap();
+
+        let mut wrong_witness = witness.clone();
+        wrong_witness[0].1[2] += DynamicField::from(1);
+        assert!(mock_prove(&analyzed, &fixed, &wrong_witness).is_err());
+    }
+
+    #[test]
+    fn power() {
+        let analyzed = analyze(
+            r#"
+namespace Main(8);
+    col fixed A(i) { i + 1 };
+    col witness x;
+    x = A ** 7;
+"#,
+        );
+        let (fixed, mut witness) = columns(&analyzed);
+        mock_prove(&analyzed, &fixed, &witness).unwrap();
+        witness[0].1[2] += DynamicField::from(1);
+        assert!(mock_prove(&analyzed, &fixed, &witness).is_err());
+
+        let analyzed = analyze(
+            r#"
+namespace Main(8);
+    col fixed A(i) { i + 1 };
+    col witness x;
+    x = A ** 1000;
+"#,
+        );
+        let (fixed, _) = constant_evaluator::generate(&analyzed).unwrap();
+        let error = verify(&analyzed, &fixed, &[], &[]).unwrap_err();
+        assert!(error.to_string().contains("exponent 1000"), "{error}");
+    }
+
+    #[test]
+    fn unsupported() {
+        let analyzed = analyze(
+            r#"
+namespace Main(4);
+    col fixed A = [1, 2, 3, 4];
+    col witness x;
+    { x } is { A };
+"#,
+        );
+        let (fixed, _) = constant_evaluator::generate(&analyzed).unwrap();
+        let error = verify(&analyzed, &fixed, &[], &[]).unwrap_err();
+        assert!(matches!(error, Error::Unsupported(_)), "{error}");
+    }
+}
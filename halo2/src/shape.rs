@@ -0,0 +1,319 @@
+//! Lowers an analyzed PIL file to the parts of a Halo2 circuit:
+//! Columns, polynomial constraints, lookups into fixed tables and publics.
+
+use std::collections::HashMap;
+
+use halo2_proofs::pasta::Fp;
+use number::FieldElement;
+use pil_analyzer::{
+    Analyzed, BinaryOperator, Expression, FunctionValueDefinition, IdentityKind, PolynomialType,
+    SelectedExpressions, Symbol, UnaryOperator,
+};
+
+use crate::{to_fp, Error};
+
+/// The largest exponent of a power in a constraint. The degree of the
+/// constraint, and with it the size of the evaluation domain of the
+/// circuit, grows linearly with the exponent.
+const MAX_EXPONENT: u64 = 32;
+
+/// A constraint expression over the columns of the circuit.
+#[derive(Clone, Debug)]
+pub(crate) enum Expr {
+    Constant(Fp),
+    /// A fixed column, on the current (false) or next (true) row.
+    Fixed(usize, bool),
+    /// An advice (witness) column, on the current (false) or next (true) row.
+    Advice(usize, bool),
+    Sum(Box<Expr>, Box<Expr>),
+    Product(Box<Expr>, Box<Expr>),
+    Negated(Box<Expr>),
+}
+
+impl Expr {
+    /// Evaluates the expression on a row of the fixed columns,
+    /// returns None if it references advice columns.
+    fn evaluate_fixed(&self, fixed: &[Vec<Fp>], row: usize) -> Option<Fp> {
+        Some(match self {
+            Expr::Constant(c) => *c,
+            Expr::Fixed(index, next) => {
+                let values = &fixed[*index];
+                values[(row + *next as usize) % values.len()]
+            }
+            Expr::Advice(..) => return None,
+            Expr::Sum(left, right) => {
+                left.evaluate_fixed(fixed, row)? + right.evaluate_fixed(fixed, row)?
+            }
+            Expr::Product(left, right) => {
+                left.evaluate_fixed(fixed, row)? * right.evaluate_fixed(fixed, row)?
+            }
+            Expr::Negated(inner) => -inner.evaluate_fixed(fixed, row)?,
+        })
+    }
+}
+
+/// A lookup of tuples of expressions into a table computed from the fixed columns.
+/// The first table column is the selector of the right hand side.
+pub(crate) struct Lookup {
+    pub selector: Expr,
+    pub inputs: Vec<Expr>,
+    pub table: Vec<Vec<Fp>>,
+}
+
+/// Everything about the circuit that does not depend on the witness.
+pub(crate) struct Shape {
+    pub degree: usize,
+    pub fixed_names: Vec<Symbol>,
+    pub fixed: Vec<Vec<Fp>>,
+    pub advice_names: Vec<Symbol>,
+    /// Polynomial identities with their source locations.
+    pub constraints: Vec<(String, Expr)>,
+    pub lookups: Vec<Lookup>,
+    /// The advice column and row of each public, in declaration order.
+    pub publics: Vec<(usize, usize)>,
+}
+
+impl Shape {
+    pub fn new<T: FieldElement>(
+        analyzed: &Analyzed<T>,
+        fixed: &[(&str, Vec<T>)],
+    ) -> Result<Self, Error> {
+        let fixed_names = column_names(analyzed, PolynomialType::Constant)?;
+        let advice_names = column_names(analyzed, PolynomialType::Committed)?;
+        let fixed = fixed_names
+            .iter()
+            .map(|name| {
+                fixed
+                    .iter()
+                    .find(|(n, _)| name == n)
+                    .map(|(_, values)| values.iter().map(to_fp).collect::<Vec<_>>())
+                    .ok_or_else(|| Error::MissingColumn(name.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let degree = analyzed
+            .definitions
+            .values()
+            .map(|(poly, _)| poly.degree as usize)
+            .max()
+            .unwrap_or_default();
+
+        let lowering = Lowering {
+            analyzed,
+            fixed_indices: index_by_name(&fixed_names),
+            advice_indices: index_by_name(&advice_names),
+        };
+        let mut constraints = vec![];
+        let mut lookups = vec![];
+        for identity in &analyzed.identities {
+            let source = format!("{}:{}", identity.source.file, identity.source.line);
+            match identity.kind {
+                IdentityKind::Polynomial => constraints.push((
+                    source,
+                    lowering.lower(identity.left.selector.as_ref().unwrap())?,
+                )),
+                IdentityKind::Plookup => {
+                    let (selector, inputs) = lowering.lower_selected(&identity.left)?;
+                    let (table_selector, table_expressions) =
+                        lowering.lower_selected(&identity.right)?;
+                    let table = std::iter::once(table_selector)
+                        .chain(table_expressions)
+                        .map(|e| {
+                            (0..degree)
+                                .map(|row| e.evaluate_fixed(&fixed, row))
+                                .collect::<Option<Vec<_>>>()
+                        })
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(|| {
+                            Error::Unsupported(format!("lookup into witness columns at {source}"))
+                        })?;
+                    lookups.push(Lookup {
+                        selector,
+                        inputs,
+                        table,
+                    });
+                }
+                // The multiplicity of a LogUp identity cannot be constrained
+                // without verifier challenges.
+                IdentityKind::LogUp | IdentityKind::Permutation | IdentityKind::Connect => {
+                    return Err(Error::Unsupported(format!(
+                        "{:?} identity at {source}",
+                        identity.kind
+                    )))
+                }
+            }
+        }
+
+        let mut public_declarations = analyzed.public_declarations.values().collect::<Vec<_>>();
+        public_declarations.sort_by_key(|decl| decl.id);
+        let publics = public_declarations
+            .into_iter()
+            .map(|decl| {
+                let column = lowering
+                    .advice_indices
+                    .get(&decl.polynomial.name)
+                    .filter(|_| decl.polynomial.index.is_none() && !decl.polynomial.next)
+                    .ok_or_else(|| {
+                        Error::Unsupported(format!(
+                            "public {} not referencing a witness column",
+                            decl.name
+                        ))
+                    })?;
+                Ok::<_, Error>((*column, decl.index as usize))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Shape {
+            degree,
+            fixed_names,
+            fixed,
+            advice_names,
+            constraints,
+            lookups,
+            publics,
+        })
+    }
+}
+
+fn column_names<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    poly_type: PolynomialType,
+) -> Result<Vec<Symbol>, Error> {
+    analyzed
+        .definitions_in_source_order(poly_type)
+        .into_iter()
+        .map(|(poly, _)| {
+            if poly.is_array() {
+                Err(Error::Unsupported(format!(
+                    "array column {}",
+                    poly.absolute_name
+                )))
+            } else {
+                Ok(poly.absolute_name)
+            }
+        })
+        .collect()
+}
+
+fn index_by_name(names: &[Symbol]) -> HashMap<Symbol, usize> {
+    names.iter().enumerate().map(|(i, n)| (*n, i)).collect()
+}
+
+struct Lowering<'a, T> {
+    analyzed: &'a Analyzed<T>,
+    fixed_indices: HashMap<Symbol, usize>,
+    advice_indices: HashMap<Symbol, usize>,
+}
+
+impl<'a, T: FieldElement> Lowering<'a, T> {
+    /// Lowers the selector (1 if missing) and the expressions.
+    fn lower_selected(
+        &self,
+        selected: &SelectedExpressions<T>,
+    ) -> Result<(Expr, Vec<Expr>), Error> {
+        let selector = match &selected.selector {
+            Some(selector) => self.lower(selector)?,
+            None => Expr::Constant(Fp::one()),
+        };
+        let expressions = selected
+            .expressions
+            .iter()
+            .map(|e| self.lower(e))
+            .collect::<Result<_, _>>()?;
+        Ok((selector, expressions))
+    }
+
+    fn lower(&self, expr: &Expression<T>) -> Result<Expr, Error> {
+        let expressions = &self.analyzed.expressions;
+        Ok(match expr {
+            Expression::Number(n) => Expr::Constant(to_fp(n)),
+            Expression::Constant(name) => Expr::Constant(to_fp(&self.analyzed.constants[name])),
+            Expression::PolynomialReference(reference) if reference.index.is_none() => {
+                let name = reference.name;
+                if let Some(index) = self.fixed_indices.get(&name) {
+                    Expr::Fixed(*index, reference.next)
+                } else if let Some(index) = self.advice_indices.get(&name) {
+                    Expr::Advice(*index, reference.next)
+                } else if let Some((_, Some(FunctionValueDefinition::Mapping(definition)))) =
+                    self.analyzed.definitions.get(&name)
+                {
+                    if reference.next {
+                        return Err(Error::Unsupported(format!(
+                            "next reference to intermediate column {name}"
+                        )));
+                    }
+                    self.lower(definition)?
+                } else {
+                    return Err(Error::Unsupported(format!("reference to {name}")));
+                }
+            }
+            Expression::BinaryOperation(left, op, right) => {
+                let left = Box::new(self.lower(&expressions[*left])?);
+                let right = &expressions[*right];
+                match op {
+                    BinaryOperator::Add => Expr::Sum(left, Box::new(self.lower(right)?)),
+                    BinaryOperator::Sub => {
+                        Expr::Sum(left, Box::new(Expr::Negated(Box::new(self.lower(right)?))))
+                    }
+                    BinaryOperator::Mul => Expr::Product(left, Box::new(self.lower(right)?)),
+                    BinaryOperator::Pow => {
+                        // Square-and-multiply, which keeps the depth of the
+                        // product logarithmic in the exponent.
+                        let mut exponent = self.constant_exponent(right)?;
+                        let (mut result, mut base) = (None, *left);
+                        loop {
+                            if exponent & 1 == 1 {
+                                result = Some(match result {
+                                    Some(result) => {
+                                        Expr::Product(Box::new(result), Box::new(base.clone()))
+                                    }
+                                    None => base.clone(),
+                                });
+                            }
+                            exponent >>= 1;
+                            if exponent == 0 {
+                                break result.unwrap();
+                            }
+                            base = Expr::Product(Box::new(base.clone()), Box::new(base));
+                        }
+                    }
+                    _ => return Err(Error::Unsupported(format!("operator {op} in constraint"))),
+                }
+            }
+            Expression::UnaryOperation(UnaryOperator::Minus, inner) => {
+                Expr::Negated(Box::new(self.lower(&expressions[*inner])?))
+            }
+            Expression::UnaryOperation(UnaryOperator::Plus, inner) => {
+                self.lower(&expressions[*inner])?
+            }
+            _ => {
+                return Err(Error::Unsupported(format!(
+                    "expression {} in constraint",
+                    expressions.display(expr)
+                )))
+            }
+        })
+    }
+
+    fn constant_exponent(&self, exponent: &Expression<T>) -> Result<u64, Error> {
+        let value = match exponent {
+            Expression::Number(n) => Some(*n),
+            Expression::Constant(name) => Some(self.analyzed.constants[name]),
+            _ => None,
+        };
+        let exponent = value
+            .and_then(|v| v.try_to_u64().ok())
+            .filter(|e| *e > 0)
+            .ok_or_else(|| {
+                Error::Unsupported(format!(
+                    "exponent {} in constraint",
+                    self.analyzed.expressions.display(exponent)
+                ))
+            })?;
+        if exponent > MAX_EXPONENT {
+            return Err(Error::Unsupported(format!(
+                "exponent {exponent} in constraint, the maximum is {MAX_EXPONENT}"
+            )));
+        }
+        Ok(exponent)
+    }
+}
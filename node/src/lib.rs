@@ -0,0 +1,260 @@
+//! Node.js bindings to compile powdr assembly and generate fixed and witness
+//! columns in-process. Build the `powdr` npm package with `npm run build` in
+//! this directory, then:
+//!
+//! ```js
+//! const powdr = require("powdr");
+//! const pil = await powdr.compileAsm(asmSource);
+//! const { degree, elementSize, witness } = await powdr.generate(pil, { inputs: ["7"] });
+//! ```
+//!
+//! The work runs on the libuv thread pool and the functions return promises,
+//! which are rejected on errors. The values of a column are returned as a
+//! `Buffer` of little-endian field elements of `elementSize` bytes each.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use napi::bindgen_prelude::{AsyncTask, Buffer};
+use napi::{Env, Error, Result, Task};
+use napi_derive::napi;
+use number::{AbstractNumberType, FieldElement};
+
+/// Runs the body with `$T` set to the field with the given name.
+macro_rules! with_field {
+    ($field:expr, $T:ident => $body:expr) => {
+        match $field {
+            "gl" => {
+                type $T = ::number::GoldilocksField;
+                $body
+            }
+            "bn254" => {
+                type $T = ::number::Bn254Field;
+                $body
+            }
+            "bb" => {
+                type $T = ::number::BabyBearField;
+                $body
+            }
+            field => Err(format!("Unknown field {field}, expected gl, bn254 or bb.")),
+        }
+    };
+}
+
+/// Runs `f` and turns its error or a panic into a JavaScript error.
+fn run<R>(f: impl FnOnce() -> std::result::Result<R, String>) -> Result<R> {
+    let result = catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|panic| {
+        Err(panic
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_else(|| "Unknown error.".to_string()))
+    });
+    result.map_err(Error::from_reason)
+}
+
+/// Compiles powdr assembly to PIL.
+fn compile_asm_to_pil<T: FieldElement>(source: &str) -> std::result::Result<String, String> {
+    pilgen::compile::<T>(None, source, &Default::default())
+        .map(|pil| pil.to_string())
+        .map_err(|e| e.message().to_string())
+}
+
+/// The columns with their values encoded as little-endian bytes.
+#[derive(Debug)]
+pub struct GeneratedColumns {
+    degree: u64,
+    element_size: usize,
+    fixed: Vec<(String, Vec<u8>)>,
+    witness: Vec<(String, Vec<u8>)>,
+}
+
+fn generate_in_field<T: FieldElement>(
+    source: &str,
+    asm: bool,
+    inputs: &[String],
+) -> std::result::Result<GeneratedColumns, String> {
+    let inputs = inputs
+        .iter()
+        .map(|i| {
+            i.parse::<AbstractNumberType>()
+                .map(T::from)
+                .map_err(|_| format!("Invalid input {i}, expected a decimal number."))
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let pil = if asm {
+        compile_asm_to_pil::<T>(source)?
+    } else {
+        source.to_string()
+    };
+    let analyzed = pil_analyzer::try_analyze_string::<T>(&pil).map_err(|e| e.to_string())?;
+    let (fixed, degree) = executor::constant_evaluator::generate(&analyzed)?;
+    let witness = executor::witgen::generate(
+        &analyzed,
+        degree,
+        &fixed,
+        Some(executor::witgen::inputs_query_callback(inputs, 0)),
+    );
+    let encode = |columns: &[(&str, Vec<T>)]| {
+        columns
+            .iter()
+            .map(|(name, values)| {
+                let bytes = values
+                    .iter()
+                    .flat_map(|v| {
+                        let mut bytes = v.to_bytes_le();
+                        bytes.resize(T::byte_len(), 0);
+                        bytes
+                    })
+                    .collect();
+                (name.to_string(), bytes)
+            })
+            .collect()
+    };
+    Ok(GeneratedColumns {
+        degree,
+        element_size: T::byte_len(),
+        fixed: encode(&fixed),
+        witness: encode(&witness),
+    })
+}
+
+pub struct CompileAsmTask {
+    source: String,
+    field: String,
+}
+
+impl Task for CompileAsmTask {
+    type Output = String;
+    type JsValue = String;
+
+    fn compute(&mut self) -> Result<String> {
+        run(|| with_field!(self.field.as_str(), T => compile_asm_to_pil::<T>(&self.source)))
+    }
+
+    fn resolve(&mut self, _env: Env, output: String) -> Result<String> {
+        Ok(output)
+    }
+}
+
+/// Compiles powdr assembly to PIL in the field `field` ("gl" by default,
+/// "bn254" or "bb").
+#[napi(ts_return_type = "Promise<string>")]
+pub fn compile_asm(source: String, field: Option<String>) -> AsyncTask<CompileAsmTask> {
+    AsyncTask::new(CompileAsmTask {
+        source,
+        field: field.unwrap_or_else(|| "gl".to_string()),
+    })
+}
+
+#[napi(object)]
+pub struct GenerateOptions {
+    /// The field, "gl" by default, "bn254" or "bb".
+    pub field: Option<String>,
+    /// Whether the source is powdr assembly instead of PIL.
+    pub asm: Option<bool>,
+    /// The answers to `("input", i)` queries, as decimal numbers.
+    pub inputs: Option<Vec<String>>,
+}
+
+#[napi(object)]
+pub struct Column {
+    pub name: String,
+    /// The values row after row, `elementSize` bytes each in little endian.
+    pub values: Buffer,
+}
+
+#[napi(object)]
+pub struct Columns {
+    pub degree: i64,
+    pub element_size: u32,
+    /// The fixed columns in source order.
+    pub fixed: Vec<Column>,
+    /// The witness columns in source order.
+    pub witness: Vec<Column>,
+}
+
+pub struct GenerateTask {
+    source: String,
+    options: GenerateOptions,
+}
+
+impl Task for GenerateTask {
+    type Output = GeneratedColumns;
+    type JsValue = Columns;
+
+    fn compute(&mut self) -> Result<GeneratedColumns> {
+        let field = self.options.field.as_deref().unwrap_or("gl");
+        let asm = self.options.asm.unwrap_or_default();
+        let inputs = self.options.inputs.as_deref().unwrap_or_default();
+        run(|| with_field!(field, T => generate_in_field::<T>(&self.source, asm, inputs)))
+    }
+
+    fn resolve(&mut self, _env: Env, output: GeneratedColumns) -> Result<Columns> {
+        let columns = |columns: Vec<(String, Vec<u8>)>| {
+            columns
+                .into_iter()
+                .map(|(name, values)| Column {
+                    name,
+                    values: values.into(),
+                })
+                .collect()
+        };
+        Ok(Columns {
+            degree: output.degree as i64,
+            element_size: output.element_size as u32,
+            fixed: columns(output.fixed),
+            witness: columns(output.witness),
+        })
+    }
+}
+
+/// Generates the fixed and witness columns of the PIL (or assembly) source.
+#[napi(ts_return_type = "Promise<Columns>")]
+pub fn generate(source: String, options: Option<GenerateOptions>) -> AsyncTask<GenerateTask> {
+    AsyncTask::new(GenerateTask {
+        source,
+        options: options.unwrap_or(GenerateOptions {
+            field: None,
+            asm: None,
+            inputs: None,
+        }),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SUM: &str = include_str!("../../test_data/asm/simple_sum.asm");
+
+    fn column(columns: &[(String, Vec<u8>)], name: &str) -> Vec<u64> {
+        let (_, values) = columns.iter().find(|(n, _)| n == name).unwrap();
+        values
+            .chunks(8)
+            .map(|e| u64::from_le_bytes(e.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn generate_from_asm() {
+        let inputs = ["16", "4", "1", "2", "8", "5"].map(String::from);
+        let generated =
+            run(|| with_field!("gl", T => generate_in_field::<T>(SUM, true, &inputs))).unwrap();
+        assert_eq!(generated.element_size, 8);
+        let cnt = column(&generated.witness, "Assembly.CNT");
+        assert_eq!(cnt.len() as u64, generated.degree);
+        assert_eq!(cnt[..3], [0, 4, 4]);
+    }
+
+    #[test]
+    fn errors() {
+        let error = run(|| with_field!("m61", T => compile_asm_to_pil::<T>(SUM))).unwrap_err();
+        assert_eq!(error.reason, "Unknown field m61, expected gl, bn254 or bb.");
+        let error = run(|| with_field!("gl", T => compile_asm_to_pil::<T>("reg pc"))).unwrap_err();
+        assert!(!error.reason.is_empty());
+        let error =
+            run(|| with_field!("bb", T => generate_in_field::<T>(SUM, true, &["x".to_string()])))
+                .unwrap_err();
+        assert_eq!(error.reason, "Invalid input x, expected a decimal number.");
+    }
+}
@@ -0,0 +1,72 @@
+//! Throughput of the basic operations of the field implementations.
+//! Run with `cargo bench -p number`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use number::{
+    batch,
+    ntt::{self, NttField},
+    BabyBearField, Bn254Field, FieldElement, GoldilocksField, GoldilocksMontgomeryField,
+    Mersenne31Field,
+};
+
+const COUNT: u64 = 1024;
+
+fn bench_field<T: FieldElement>(c: &mut Criterion, name: &str) {
+    let values = (1..=COUNT)
+        .map(|i| T::from(i.wrapping_mul(0x9e37_79b9_7f4a_7c15)))
+        .collect::<Vec<_>>();
+    let mut group = c.benchmark_group("arithmetic");
+
+    group.bench_function(BenchmarkId::new("mul", name), |b| {
+        b.iter(|| values.iter().fold(T::one(), |acc, v| black_box(acc * *v)))
+    });
+    group.bench_function(BenchmarkId::new("add", name), |b| {
+        b.iter(|| values.iter().fold(T::zero(), |acc, v| black_box(acc + *v)))
+    });
+    group.bench_function(BenchmarkId::new("mul_add", name), |b| {
+        b.iter(|| {
+            values
+                .iter()
+                .fold(T::zero(), |acc, v| black_box(acc * *v + *v))
+        })
+    });
+    group.bench_function(BenchmarkId::new("inverse", name), |b| {
+        b.iter(|| values.iter().map(|v| T::one() / *v).collect::<Vec<_>>())
+    });
+    group.bench_function(BenchmarkId::new("invert_many", name), |b| {
+        b.iter(|| {
+            let mut inverses = values.clone();
+            batch::invert_many(&mut inverses);
+            inverses
+        })
+    });
+    group.finish();
+}
+
+fn bench_ntt<T: NttField>(c: &mut Criterion, name: &str) {
+    let values = (1..=COUNT).map(T::from).collect::<Vec<_>>();
+    let mut group = c.benchmark_group("ntt");
+    group.bench_function(BenchmarkId::new("ntt", name), |b| {
+        b.iter(|| {
+            let mut evaluations = values.clone();
+            ntt::ntt(&mut evaluations);
+            evaluations
+        })
+    });
+    group.finish();
+}
+
+fn fields(c: &mut Criterion) {
+    bench_field::<GoldilocksField>(c, "goldilocks");
+    bench_field::<GoldilocksMontgomeryField>(c, "goldilocks_montgomery");
+    bench_field::<BabyBearField>(c, "babybear");
+    bench_field::<Mersenne31Field>(c, "mersenne31");
+    bench_field::<Bn254Field>(c, "bn254");
+
+    bench_ntt::<GoldilocksField>(c, "goldilocks");
+    bench_ntt::<BabyBearField>(c, "babybear");
+    bench_ntt::<Bn254Field>(c, "bn254");
+}
+
+criterion_group!(benches, fields);
+criterion_main!(benches);
@@ -0,0 +1,65 @@
+//! Conversions between powdr field elements and arkworks field elements.
+//! Only available with the `ark` feature.
+
+use ark_ff::{BigInteger, PrimeField};
+
+use crate::{AbstractNumberType, Bn254Field, FieldElement};
+
+/// Converts a field element to the arkworks element with the same canonical
+/// representative. Panics if the two fields have different moduli.
+pub fn to_ark<T: FieldElement, F: PrimeField>(value: T) -> F {
+    assert_same_modulus::<T, F>();
+    F::from_le_bytes_mod_order(&value.to_bytes_le())
+}
+
+/// Converts an arkworks field element to the field element with the same
+/// canonical representative. Panics if the two fields have different moduli.
+pub fn from_ark<T: FieldElement, F: PrimeField>(value: F) -> T {
+    assert_same_modulus::<T, F>();
+    AbstractNumberType::from_bytes_le(&value.into_bigint().to_bytes_le()).into()
+}
+
+fn assert_same_modulus<T: FieldElement, F: PrimeField>() {
+    let ark_modulus = AbstractNumberType::from_bytes_le(&F::MODULUS.to_bytes_le());
+    assert_eq!(
+        ark_modulus,
+        T::modulus(),
+        "Cannot convert between fields with different moduli."
+    );
+}
+
+impl From<ark_bn254::Fr> for Bn254Field {
+    fn from(value: ark_bn254::Fr) -> Self {
+        from_ark(value)
+    }
+}
+
+impl From<Bn254Field> for ark_bn254::Fr {
+    fn from(value: Bn254Field) -> Self {
+        to_ark(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::GoldilocksField;
+
+    use super::*;
+
+    #[test]
+    fn bn254() {
+        let value = Bn254Field::from(-7) * Bn254Field::from(1u64 << 40);
+        let ark_value: ark_bn254::Fr = value.into();
+        assert_eq!(
+            ark_value,
+            -ark_bn254::Fr::from(7u64) * ark_bn254::Fr::from(1u64 << 40)
+        );
+        assert_eq!(Bn254Field::from(ark_value), value);
+    }
+
+    #[test]
+    #[should_panic = "different moduli"]
+    fn different_moduli() {
+        let _: ark_bn254::Fr = to_ark(GoldilocksField::from(1));
+    }
+}
@@ -0,0 +1,59 @@
+use crate::macros::impl_small_field;
+
+/// An element of the BabyBear field with modulus 2**31 - 2**27 + 1.
+///
+/// Note that 32 bit machine words do not fit into this field and have
+/// to be split into smaller limbs.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, PartialOrd, Ord, Hash)]
+pub struct BabyBearField {
+    value: u32,
+}
+
+impl BabyBearField {
+    fn reduce(value: u64) -> u32 {
+        (value % Self::P as u64) as u32
+    }
+}
+
+impl_small_field!(BabyBearField, 0x7800_0001);
+
+#[cfg(test)]
+mod test {
+    use crate::FieldElement;
+
+    use super::*;
+
+    #[test]
+    fn arithmetic() {
+        let max = BabyBearField::from(-1);
+        assert_eq!(max.to_degree(), 0x7800_0000);
+        assert_eq!(max * max, BabyBearField::one());
+        assert_eq!(max + max, BabyBearField::from(-2));
+        assert_eq!(BabyBearField::from(0x7800_0001u32), BabyBearField::zero());
+        assert_eq!(
+            BabyBearField::from(1u64 << 32),
+            BabyBearField::from(0x0fff_fffeu32)
+        );
+        let a = BabyBearField::from(123456789);
+        assert_eq!(a / a, BabyBearField::one());
+        assert_eq!(a * (BabyBearField::one() / a), BabyBearField::one());
+    }
+
+    #[test]
+    fn pow() {
+        let two = BabyBearField::from(2);
+        assert_eq!(two.pow(10u32.into()), BabyBearField::from(1024));
+        assert_eq!(two.pow(0x7800_0000u32.into()), BabyBearField::one());
+        assert_eq!(BabyBearField::zero().pow(0u32.into()), BabyBearField::one());
+        assert_eq!(
+            BabyBearField::zero().pow(5u32.into()),
+            BabyBearField::zero()
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn div_by_zero() {
+        let _ = BabyBearField::from(1) / BabyBearField::from(0);
+    }
+}
@@ -0,0 +1,153 @@
+//! Element-wise operations on slices of field elements.
+//!
+//! The slices are processed in fixed-size chunks so that the compiler
+//! can unroll and vectorize the inner loops.
+
+use crate::FieldElement;
+
+/// Number of elements processed together.
+const LANES: usize = 8;
+
+fn zip_with<T: FieldElement>(a: &mut [T], b: &[T], op: impl Fn(T, T) -> T) {
+    assert_eq!(a.len(), b.len(), "Slices have different lengths.");
+    let mut a_chunks = a.chunks_exact_mut(LANES);
+    let mut b_chunks = b.chunks_exact(LANES);
+    for (a, b) in (&mut a_chunks).zip(&mut b_chunks) {
+        for i in 0..LANES {
+            a[i] = op(a[i], b[i]);
+        }
+    }
+    for (a, b) in a_chunks
+        .into_remainder()
+        .iter_mut()
+        .zip(b_chunks.remainder())
+    {
+        *a = op(*a, *b);
+    }
+}
+
+fn map<T: FieldElement>(a: &mut [T], op: impl Fn(T) -> T) {
+    let mut chunks = a.chunks_exact_mut(LANES);
+    for a in &mut chunks {
+        for x in a.iter_mut() {
+            *x = op(*x);
+        }
+    }
+    for x in chunks.into_remainder() {
+        *x = op(*x);
+    }
+}
+
+/// Computes `a[i] += b[i]` for all `i`.
+pub fn add_assign<T: FieldElement>(a: &mut [T], b: &[T]) {
+    zip_with(a, b, |x, y| x + y)
+}
+
+/// Computes `a[i] -= b[i]` for all `i`.
+pub fn sub_assign<T: FieldElement>(a: &mut [T], b: &[T]) {
+    zip_with(a, b, |x, y| x - y)
+}
+
+/// Computes `a[i] *= b[i]` for all `i`.
+pub fn mul_assign<T: FieldElement>(a: &mut [T], b: &[T]) {
+    zip_with(a, b, |x, y| x * y)
+}
+
+/// Computes `a[i] += s` for all `i`.
+pub fn add_scalar_assign<T: FieldElement>(a: &mut [T], s: T) {
+    map(a, |x| x + s)
+}
+
+/// Computes `a[i] *= s` for all `i`.
+pub fn mul_scalar_assign<T: FieldElement>(a: &mut [T], s: T) {
+    map(a, |x| x * s)
+}
+
+/// Computes `a[i] = -a[i]` for all `i`.
+pub fn neg_assign<T: FieldElement>(a: &mut [T]) {
+    map(a, |x| -x)
+}
+
+/// Replaces every element by its inverse, using a single field inversion
+/// (Montgomery's trick). Panics if any element is zero.
+pub fn invert_many<T: FieldElement>(values: &mut [T]) {
+    // prefix_products[i] is the product of all elements before i.
+    let mut prefix_products = Vec::with_capacity(values.len());
+    let mut product = T::one();
+    for v in values.iter() {
+        assert!(!v.is_zero(), "Division by zero");
+        prefix_products.push(product);
+        product = product * *v;
+    }
+    // Invariant: inverse is the inverse of the product of all elements up to i.
+    let mut inverse = T::one() / product;
+    for (v, prefix_product) in values.iter_mut().zip(prefix_products).rev() {
+        let next_inverse = inverse * *v;
+        *v = inverse * prefix_product;
+        inverse = next_inverse;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{random::seeded_rng, GoldilocksField};
+
+    use super::*;
+
+    fn values(len: u64, seed: u64) -> Vec<GoldilocksField> {
+        let mut rng = seeded_rng(seed);
+        (0..len)
+            .map(|_| GoldilocksField::random(&mut rng))
+            .collect()
+    }
+
+    #[test]
+    fn matches_scalar_operations() {
+        // Lengths with and without remainder.
+        for len in [0, 3, 8, 21] {
+            let (a, b) = (values(len, 1), values(len, 7));
+            let s = GoldilocksField::from(-3);
+
+            let mut result = a.clone();
+            add_assign(&mut result, &b);
+            mul_assign(&mut result, &b);
+            sub_assign(&mut result, &a);
+            add_scalar_assign(&mut result, s);
+            mul_scalar_assign(&mut result, s);
+            neg_assign(&mut result);
+
+            let expected = a
+                .iter()
+                .zip(&b)
+                .map(|(x, y)| -((((*x + *y) * *y - *x) + s) * s))
+                .collect::<Vec<_>>();
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn invert() {
+        for len in [0, 1, 21] {
+            let values = values(len, 1);
+            let mut inverses = values.clone();
+            invert_many(&mut inverses);
+            let expected = values
+                .iter()
+                .map(|v| GoldilocksField::one() / *v)
+                .collect::<Vec<_>>();
+            assert_eq!(inverses, expected);
+        }
+    }
+
+    #[test]
+    #[should_panic = "Division by zero"]
+    fn invert_zero() {
+        invert_many(&mut [GoldilocksField::from(2), GoldilocksField::zero()]);
+    }
+
+    #[test]
+    #[should_panic = "different lengths"]
+    fn different_lengths() {
+        add_assign(&mut values(3, 0), &values(4, 0));
+    }
+}
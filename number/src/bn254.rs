@@ -0,0 +1,183 @@
+use std::{fmt, ops::AddAssign};
+
+use ark_ff::{
+    fields::{Field, Fp256, MontBackend, MontConfig},
+    BigInteger, PrimeField, Zero,
+};
+
+use crate::{format::format_element, macros::impl_ref_ops, AbstractNumberType, FieldElement};
+
+#[derive(MontConfig)]
+#[modulus = "21888242871839275222246405745257275088548364400416034343698204186575808495617"]
+#[generator = "5"]
+pub struct Bn254BaseFieldConfig;
+pub type Bn254BaseField = Fp256<MontBackend<Bn254BaseFieldConfig, 4>>;
+
+/// An element of the scalar field of the BN254 (alt_bn128) curve,
+/// the native field of SNARK verifiers on Ethereum.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, PartialOrd, Ord, Hash)]
+pub struct Bn254Field {
+    value: Bn254BaseField,
+}
+
+impl FieldElement for Bn254Field {
+    fn to_integer(&self) -> AbstractNumberType {
+        self.value.into()
+    }
+
+    fn modulus() -> AbstractNumberType {
+        Bn254BaseField::MODULUS.into()
+    }
+
+    fn zero() -> Self {
+        Self::from(0)
+    }
+
+    fn one() -> Self {
+        Self::from(1)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.value.is_zero()
+    }
+
+    fn pow(self, exponent: AbstractNumberType) -> Self {
+        Self {
+            value: self.value.pow(exponent.to_u64_digits()),
+        }
+    }
+
+    fn integer_div(self, other: Self) -> Self {
+        (self.to_integer() / other.to_integer()).into()
+    }
+
+    fn to_bytes_le(&self) -> Vec<u8> {
+        self.value.into_bigint().to_bytes_le()
+    }
+}
+
+macro_rules! impl_from_via_base_field {
+    ($($source:ty),*) => {
+        $(
+            impl From<$source> for Bn254Field {
+                fn from(value: $source) -> Self {
+                    Self {
+                        value: value.into(),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_from_via_base_field!(AbstractNumberType, u64, u32, i32, bool);
+
+impl std::ops::Add for Bn254Field {
+    type Output = Bn254Field;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Bn254Field {
+            value: self.value + rhs.value,
+        }
+    }
+}
+
+impl AddAssign for Bn254Field {
+    fn add_assign(&mut self, rhs: Self) {
+        self.value.add_assign(rhs.value);
+    }
+}
+
+impl std::ops::Sub for Bn254Field {
+    type Output = Bn254Field;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Bn254Field {
+            value: self.value - rhs.value,
+        }
+    }
+}
+
+impl std::ops::Mul for Bn254Field {
+    type Output = Bn254Field;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Bn254Field {
+            value: self.value * rhs.value,
+        }
+    }
+}
+
+impl std::ops::Div for Bn254Field {
+    type Output = Bn254Field;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        Bn254Field {
+            value: self.value / rhs.value,
+        }
+    }
+}
+
+impl_ref_ops!(Bn254Field);
+
+impl std::ops::Neg for Bn254Field {
+    type Output = Bn254Field;
+
+    fn neg(self) -> Self::Output {
+        Self { value: -self.value }
+    }
+}
+
+impl fmt::Display for Bn254Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        format_element(self, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn modulus() {
+        assert_eq!(
+            Bn254Field::modulus().to_string(),
+            "21888242871839275222246405745257275088548364400416034343698204186575808495617"
+        );
+        assert_eq!(Bn254Field::from(-1) + Bn254Field::one(), Bn254Field::zero());
+    }
+
+    #[test]
+    fn large_values() {
+        let two_to_the_200 = Bn254Field::from(2).pow(200u32.into());
+        assert_eq!(
+            two_to_the_200.to_integer(),
+            AbstractNumberType::from(1u32) << 200
+        );
+        assert_eq!(
+            Bn254Field::from(AbstractNumberType::from(1u32) << 200),
+            two_to_the_200
+        );
+        assert_eq!(two_to_the_200 / two_to_the_200, Bn254Field::one());
+        assert_eq!(Bn254Field::from(-7).to_string(), "-7");
+        assert_eq!(Bn254Field::from(-7).to_bytes_le().len(), 32);
+    }
+
+    #[test]
+    fn signed() {
+        assert_eq!(
+            Bn254Field::from_i64(i64::MIN).to_signed_i64(),
+            Some(i64::MIN)
+        );
+        assert_eq!(Bn254Field::from(-4).to_signed_i64(), Some(-4));
+        let large = Bn254Field::from(AbstractNumberType::from(1u32) << 100);
+        assert_eq!(large.to_signed_i64(), None);
+        assert_eq!((-large).to_string(), format!("-{}", large));
+    }
+
+    #[test]
+    #[should_panic]
+    fn div_by_zero() {
+        let _ = Bn254Field::from(1) / Bn254Field::from(0);
+    }
+}
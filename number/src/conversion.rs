@@ -0,0 +1,80 @@
+use std::fmt;
+
+use crate::AbstractNumberType;
+
+/// An error when converting a field element to a machine integer
+/// whose range does not contain the element's canonical representative.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConversionError {
+    pub value: AbstractNumberType,
+    /// The name of the target type.
+    pub target: &'static str,
+    /// What the value was used for, if known.
+    pub context: Option<String>,
+}
+
+impl ConversionError {
+    pub fn new(value: AbstractNumberType, target: &'static str) -> Self {
+        Self {
+            value,
+            target,
+            context: None,
+        }
+    }
+
+    /// Adds a description of what the value was used for.
+    pub fn with_context(self, context: impl Into<String>) -> Self {
+        Self {
+            context: Some(context.into()),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Value {} does not fit into {}", self.value, self.target)?;
+        match &self.context {
+            Some(context) => write!(f, " (used as {context})."),
+            None => write!(f, "."),
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+#[cfg(test)]
+mod test {
+    use crate::{BabyBearField, Bn254Field, FieldElement, GoldilocksField};
+
+    use super::*;
+
+    #[test]
+    fn checked_conversions() {
+        assert_eq!(
+            GoldilocksField::from(-1).try_to_u64(),
+            Ok(0xffff_ffff_0000_0000)
+        );
+        assert_eq!(BabyBearField::from(-1).try_to_u32(), Ok(0x7800_0000));
+        let large = Bn254Field::from(AbstractNumberType::from(1u32) << 64);
+        let error = large.try_to_u64().unwrap_err();
+        assert_eq!(error.value, AbstractNumberType::from(1u32) << 64);
+        assert_eq!(
+            error.with_context("degree").to_string(),
+            "Value 18446744073709551616 does not fit into u64 (used as degree)."
+        );
+        assert_eq!(
+            GoldilocksField::from(1u64 << 32)
+                .try_to_u32()
+                .unwrap_err()
+                .to_string(),
+            "Value 4294967296 does not fit into u32."
+        );
+    }
+
+    #[test]
+    #[should_panic = "does not fit into u64"]
+    fn degree_too_large() {
+        Bn254Field::from(-1).to_degree();
+    }
+}
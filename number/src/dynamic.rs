@@ -0,0 +1,242 @@
+use std::{cmp::Ordering, fmt, ops::AddAssign, sync::OnceLock};
+
+use num_traits::{One, Zero};
+
+use crate::{format::format_element, macros::impl_ref_ops, AbstractNumberType, FieldElement};
+
+/// Number of 64 bit limbs of a `DynamicField` element.
+const LIMBS: usize = 4;
+
+static MODULUS: OnceLock<AbstractNumberType> = OnceLock::new();
+
+/// An element of a prime field whose modulus is chosen at runtime
+/// through `DynamicField::set_modulus`, which has to be called before
+/// any element is created. Moduli of up to 256 bits are supported.
+///
+/// All arithmetic goes through arbitrary precision integers, so this
+/// is meant for experiments with custom fields, not for production use.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Hash)]
+pub struct DynamicField {
+    /// The canonical representative in little endian limbs.
+    value: [u64; LIMBS],
+}
+
+impl DynamicField {
+    /// Sets the modulus for all elements of this type.
+    /// The modulus has to be prime and can only be set once per process,
+    /// setting the same modulus again is allowed.
+    pub fn set_modulus(modulus: AbstractNumberType) {
+        assert!(
+            modulus > AbstractNumberType::one(),
+            "Invalid modulus: {modulus}"
+        );
+        assert!(
+            modulus.bits() <= (LIMBS * 64) as u64,
+            "Modulus {modulus} is too large, at most {} bits are supported.",
+            LIMBS * 64
+        );
+        let current = MODULUS.get_or_init(|| modulus.clone());
+        assert!(
+            *current == modulus,
+            "Modulus already set to {current}, cannot change it to {modulus}."
+        );
+    }
+
+    fn modulus_ref() -> &'static AbstractNumberType {
+        MODULUS
+            .get()
+            .expect("The modulus of DynamicField has not been set.")
+    }
+
+    /// Creates an element from an arbitrary integer, reducing it modulo the modulus.
+    fn reduce(value: AbstractNumberType) -> Self {
+        let mut limbs = [0; LIMBS];
+        for (limb, digit) in limbs
+            .iter_mut()
+            .zip((value % Self::modulus_ref()).iter_u64_digits())
+        {
+            *limb = digit;
+        }
+        Self { value: limbs }
+    }
+}
+
+impl FieldElement for DynamicField {
+    fn to_integer(&self) -> AbstractNumberType {
+        self.value
+            .iter()
+            .rev()
+            .fold(AbstractNumberType::zero(), |acc, limb| (acc << 64) + *limb)
+    }
+
+    fn modulus() -> AbstractNumberType {
+        Self::modulus_ref().clone()
+    }
+
+    fn zero() -> Self {
+        Self::default()
+    }
+
+    fn one() -> Self {
+        Self::reduce(AbstractNumberType::one())
+    }
+
+    fn is_zero(&self) -> bool {
+        self.value.iter().all(|limb| *limb == 0)
+    }
+
+    fn pow(self, exponent: AbstractNumberType) -> Self {
+        Self::reduce(self.to_integer().modpow(&exponent, Self::modulus_ref()))
+    }
+
+    fn integer_div(self, other: Self) -> Self {
+        Self::reduce(self.to_integer() / other.to_integer())
+    }
+
+    fn to_bytes_le(&self) -> Vec<u8> {
+        let len = (Self::modulus_ref().bits() as usize).div_ceil(8);
+        let mut bytes = self.to_integer().to_bytes_le();
+        bytes.resize(len, 0);
+        bytes
+    }
+}
+
+impl From<AbstractNumberType> for DynamicField {
+    fn from(value: AbstractNumberType) -> Self {
+        Self::reduce(value)
+    }
+}
+
+impl From<u64> for DynamicField {
+    fn from(value: u64) -> Self {
+        Self::reduce(value.into())
+    }
+}
+
+impl From<u32> for DynamicField {
+    fn from(value: u32) -> Self {
+        Self::reduce(value.into())
+    }
+}
+
+impl From<i32> for DynamicField {
+    fn from(value: i32) -> Self {
+        let abs = Self::from(value.unsigned_abs());
+        if value < 0 {
+            -abs
+        } else {
+            abs
+        }
+    }
+}
+
+impl From<bool> for DynamicField {
+    fn from(value: bool) -> Self {
+        Self::reduce((value as u32).into())
+    }
+}
+
+impl PartialOrd for DynamicField {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DynamicField {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value.iter().rev().cmp(other.value.iter().rev())
+    }
+}
+
+impl std::ops::Add for DynamicField {
+    type Output = DynamicField;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::reduce(self.to_integer() + rhs.to_integer())
+    }
+}
+
+impl AddAssign for DynamicField {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl std::ops::Sub for DynamicField {
+    type Output = DynamicField;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::reduce(self.to_integer() + Self::modulus_ref() - rhs.to_integer())
+    }
+}
+
+impl std::ops::Mul for DynamicField {
+    type Output = DynamicField;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::reduce(self.to_integer() * rhs.to_integer())
+    }
+}
+
+impl std::ops::Div for DynamicField {
+    type Output = DynamicField;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        assert!(!rhs.is_zero(), "Division by zero");
+        // Fermat's little theorem, requires the modulus to be prime.
+        let modulus = Self::modulus_ref();
+        let inverse = rhs.to_integer().modpow(&(modulus - 2u32), modulus);
+        Self::reduce(self.to_integer() * inverse)
+    }
+}
+
+impl_ref_ops!(DynamicField);
+
+impl std::ops::Neg for DynamicField {
+    type Output = DynamicField;
+
+    fn neg(self) -> Self::Output {
+        Self::zero() - self
+    }
+}
+
+impl fmt::Display for DynamicField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        format_element(self, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// All tests share the process-wide modulus, 2**127 - 1.
+    fn setup() {
+        DynamicField::set_modulus((AbstractNumberType::one() << 127) - 1u32);
+    }
+
+    #[test]
+    fn arithmetic() {
+        setup();
+        let max = DynamicField::from(-1);
+        assert_eq!(max.to_integer(), (AbstractNumberType::one() << 127) - 2u32);
+        assert_eq!(max + DynamicField::one(), DynamicField::zero());
+        assert_eq!(max * max, DynamicField::one());
+        assert_eq!(DynamicField::from(-7).to_string(), "-7");
+        assert!(DynamicField::from(3) < DynamicField::from(1u64 << 63) * DynamicField::from(4));
+        let a = DynamicField::from(0x1234_5678_9abc_def0u64);
+        assert_eq!(a / a, DynamicField::one());
+        assert_eq!(
+            DynamicField::from(2).pow(127u32.into()),
+            DynamicField::one()
+        );
+        assert_eq!(a.to_bytes_le().len(), 16);
+    }
+
+    #[test]
+    #[should_panic = "cannot change it"]
+    fn change_modulus() {
+        setup();
+        DynamicField::set_modulus(101u32.into());
+    }
+}
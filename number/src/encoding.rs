@@ -0,0 +1,93 @@
+use std::fmt;
+
+use crate::AbstractNumberType;
+
+/// An error when decoding a field element from its canonical encoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input does not have the length of the encoding of this field.
+    Length { expected: usize, actual: usize },
+    /// The encoded value is not smaller than the modulus.
+    NotCanonical(AbstractNumberType),
+    /// The input contains characters that are not hexadecimal digits.
+    InvalidHex(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Length { expected, actual } => {
+                write!(f, "Expected {expected} bytes but got {actual}.")
+            }
+            DecodeError::NotCanonical(value) => {
+                write!(f, "Value {value} is not smaller than the modulus.")
+            }
+            DecodeError::InvalidHex(input) => write!(f, "Invalid hex string: {input}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        BabyBearField, Bn254Field, FieldElement, GoldilocksField, GoldilocksMontgomeryField,
+        Mersenne31Field,
+    };
+
+    use super::*;
+
+    fn roundtrip<T: FieldElement>(byte_len: usize) {
+        assert_eq!(T::byte_len(), byte_len);
+        for value in [T::zero(), T::one(), T::from(-1), T::from(0x1234_5678u32)] {
+            let bytes = value.to_bytes_le();
+            assert_eq!(bytes.len(), byte_len);
+            assert_eq!(T::from_bytes_le(&bytes), Ok(value));
+            let hex = value.to_hex();
+            assert_eq!(hex.len(), 2 + 2 * byte_len);
+            assert_eq!(T::from_hex(&hex), Ok(value));
+        }
+        // The modulus itself is the smallest non-canonical value.
+        let mut modulus = T::modulus().to_bytes_le();
+        modulus.resize(byte_len, 0);
+        assert_eq!(
+            T::from_bytes_le(&modulus),
+            Err(DecodeError::NotCanonical(T::modulus()))
+        );
+        assert_eq!(
+            T::from_bytes_le(&[0; 3]),
+            Err(DecodeError::Length {
+                expected: byte_len,
+                actual: 3
+            })
+        );
+    }
+
+    #[test]
+    fn roundtrips() {
+        roundtrip::<GoldilocksField>(8);
+        roundtrip::<GoldilocksMontgomeryField>(8);
+        roundtrip::<BabyBearField>(4);
+        roundtrip::<Mersenne31Field>(4);
+        roundtrip::<Bn254Field>(32);
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(GoldilocksField::from(-1).to_hex(), "0xffffffff00000000");
+        assert_eq!(BabyBearField::from(10).to_hex(), "0x0000000a");
+        assert_eq!(BabyBearField::from_hex("0000000A"), Ok(10.into()));
+        assert!(matches!(
+            BabyBearField::from_hex("0x0000_00a"),
+            Err(DecodeError::InvalidHex(_))
+        ));
+        assert_eq!(
+            BabyBearField::from_hex("0xa"),
+            Err(DecodeError::Length {
+                expected: 4,
+                actual: 1
+            })
+        );
+    }
+}
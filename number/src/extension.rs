@@ -0,0 +1,344 @@
+use std::{
+    fmt,
+    ops::{Add, Div, Mul, Neg, Sub},
+};
+
+use crate::{BabyBearField, Bn254Field, FieldElement, GoldilocksField, Mersenne31Field};
+
+/// A base field that quadratic and cubic extensions can be built over.
+pub trait ExtensionBase: FieldElement {
+    /// An element `W` that is neither a square nor a cube,
+    /// which makes `X**2 - W` and `X**3 - W` irreducible.
+    fn non_residue() -> Self;
+}
+
+// A generator of the multiplicative group is never a square and,
+// since three divides the group order in all these fields, never a cube.
+
+impl ExtensionBase for GoldilocksField {
+    fn non_residue() -> Self {
+        7.into()
+    }
+}
+
+impl ExtensionBase for BabyBearField {
+    fn non_residue() -> Self {
+        31.into()
+    }
+}
+
+impl ExtensionBase for Mersenne31Field {
+    fn non_residue() -> Self {
+        7.into()
+    }
+}
+
+impl ExtensionBase for Bn254Field {
+    fn non_residue() -> Self {
+        5.into()
+    }
+}
+
+/// An element `c0 + c1 * X` of the extension field `T[X] / (X**2 - W)`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Hash)]
+pub struct QuadraticExtension<T> {
+    coefficients: [T; 2],
+}
+
+/// An element `c0 + c1 * X + c2 * X**2` of the extension field `T[X] / (X**3 - W)`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Hash)]
+pub struct CubicExtension<T> {
+    coefficients: [T; 3],
+}
+
+impl<T: ExtensionBase> QuadraticExtension<T> {
+    pub fn new(coefficients: [T; 2]) -> Self {
+        Self { coefficients }
+    }
+
+    /// The coefficients, starting with the constant one.
+    pub fn coefficients(&self) -> [T; 2] {
+        self.coefficients
+    }
+
+    pub fn zero() -> Self {
+        T::zero().into()
+    }
+
+    pub fn one() -> Self {
+        T::one().into()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coefficients.iter().all(|c| c.is_zero())
+    }
+
+    /// Returns the base field element if this element is in the base field.
+    pub fn to_base(&self) -> Option<T> {
+        self.coefficients[1]
+            .is_zero()
+            .then_some(self.coefficients[0])
+    }
+
+    pub fn pow(self, exponent: u64) -> Self {
+        pow(self, Self::one(), exponent)
+    }
+
+    pub fn inverse(self) -> Self {
+        assert!(!self.is_zero(), "Division by zero");
+        let [a0, a1] = self.coefficients;
+        // (a0 + a1 X) * (a0 - a1 X) = a0**2 - W a1**2, which is in the base field.
+        let norm = a0 * a0 - T::non_residue() * a1 * a1;
+        let norm_inverse = T::one() / norm;
+        Self::new([a0 * norm_inverse, -a1 * norm_inverse])
+    }
+}
+
+impl<T: ExtensionBase> CubicExtension<T> {
+    pub fn new(coefficients: [T; 3]) -> Self {
+        Self { coefficients }
+    }
+
+    /// The coefficients, starting with the constant one.
+    pub fn coefficients(&self) -> [T; 3] {
+        self.coefficients
+    }
+
+    pub fn zero() -> Self {
+        T::zero().into()
+    }
+
+    pub fn one() -> Self {
+        T::one().into()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coefficients.iter().all(|c| c.is_zero())
+    }
+
+    /// Returns the base field element if this element is in the base field.
+    pub fn to_base(&self) -> Option<T> {
+        (self.coefficients[1].is_zero() && self.coefficients[2].is_zero())
+            .then_some(self.coefficients[0])
+    }
+
+    pub fn pow(self, exponent: u64) -> Self {
+        pow(self, Self::one(), exponent)
+    }
+
+    pub fn inverse(self) -> Self {
+        assert!(!self.is_zero(), "Division by zero");
+        let [a0, a1, a2] = self.coefficients;
+        let w = T::non_residue();
+        // The adjugate, multiplied with the element it gives its norm.
+        let t0 = a0 * a0 - w * a1 * a2;
+        let t1 = w * a2 * a2 - a0 * a1;
+        let t2 = a1 * a1 - a0 * a2;
+        let norm = a0 * t0 + w * (a2 * t1 + a1 * t2);
+        let norm_inverse = T::one() / norm;
+        Self::new([t0 * norm_inverse, t1 * norm_inverse, t2 * norm_inverse])
+    }
+}
+
+fn pow<E: Copy + Mul<Output = E>>(mut base: E, one: E, mut exponent: u64) -> E {
+    let mut result = one;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result * base;
+        }
+        base = base * base;
+        exponent >>= 1;
+    }
+    result
+}
+
+impl<T: ExtensionBase> From<T> for QuadraticExtension<T> {
+    fn from(value: T) -> Self {
+        Self::new([value, T::zero()])
+    }
+}
+
+impl<T: ExtensionBase> From<T> for CubicExtension<T> {
+    fn from(value: T) -> Self {
+        Self::new([value, T::zero(), T::zero()])
+    }
+}
+
+impl<T: ExtensionBase> Add for QuadraticExtension<T> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let [a0, a1] = self.coefficients;
+        let [b0, b1] = rhs.coefficients;
+        Self::new([a0 + b0, a1 + b1])
+    }
+}
+
+impl<T: ExtensionBase> Sub for QuadraticExtension<T> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        let [a0, a1] = self.coefficients;
+        let [b0, b1] = rhs.coefficients;
+        Self::new([a0 - b0, a1 - b1])
+    }
+}
+
+impl<T: ExtensionBase> Mul for QuadraticExtension<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let [a0, a1] = self.coefficients;
+        let [b0, b1] = rhs.coefficients;
+        Self::new([a0 * b0 + T::non_residue() * a1 * b1, a0 * b1 + a1 * b0])
+    }
+}
+
+impl<T: ExtensionBase> Div for QuadraticExtension<T> {
+    type Output = Self;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: Self) -> Self {
+        self * rhs.inverse()
+    }
+}
+
+impl<T: ExtensionBase> Neg for QuadraticExtension<T> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        let [a0, a1] = self.coefficients;
+        Self::new([-a0, -a1])
+    }
+}
+
+impl<T: ExtensionBase> Add for CubicExtension<T> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let [a0, a1, a2] = self.coefficients;
+        let [b0, b1, b2] = rhs.coefficients;
+        Self::new([a0 + b0, a1 + b1, a2 + b2])
+    }
+}
+
+impl<T: ExtensionBase> Sub for CubicExtension<T> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        let [a0, a1, a2] = self.coefficients;
+        let [b0, b1, b2] = rhs.coefficients;
+        Self::new([a0 - b0, a1 - b1, a2 - b2])
+    }
+}
+
+impl<T: ExtensionBase> Mul for CubicExtension<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let [a0, a1, a2] = self.coefficients;
+        let [b0, b1, b2] = rhs.coefficients;
+        let w = T::non_residue();
+        Self::new([
+            a0 * b0 + w * (a1 * b2 + a2 * b1),
+            a0 * b1 + a1 * b0 + w * a2 * b2,
+            a0 * b2 + a1 * b1 + a2 * b0,
+        ])
+    }
+}
+
+impl<T: ExtensionBase> Div for CubicExtension<T> {
+    type Output = Self;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: Self) -> Self {
+        self * rhs.inverse()
+    }
+}
+
+impl<T: ExtensionBase> Neg for CubicExtension<T> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        let [a0, a1, a2] = self.coefficients;
+        Self::new([-a0, -a1, -a2])
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for QuadraticExtension<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a0, a1] = &self.coefficients;
+        write!(f, "({a0}, {a1})")
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for CubicExtension<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a0, a1, a2] = &self.coefficients;
+        write!(f, "({a0}, {a1}, {a2})")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use num_traits::One;
+
+    use crate::AbstractNumberType;
+
+    use super::*;
+
+    fn check_non_residue<T: ExtensionBase>() {
+        let w = T::non_residue();
+        let order = T::modulus() - AbstractNumberType::one();
+        assert_eq!(w.pow(&order / 2u32), -T::one(), "Non-residue is a square.");
+        assert_eq!(&order % 3u32, AbstractNumberType::from(0u32));
+        assert_ne!(w.pow(&order / 3u32), T::one(), "Non-residue is a cube.");
+    }
+
+    #[test]
+    fn non_residues() {
+        check_non_residue::<GoldilocksField>();
+        check_non_residue::<BabyBearField>();
+        check_non_residue::<Mersenne31Field>();
+        check_non_residue::<Bn254Field>();
+    }
+
+    #[test]
+    fn quadratic() {
+        type E = QuadraticExtension<GoldilocksField>;
+        let a = E::new([3.into(), 5.into()]);
+        let b = E::new([(-2).into(), 11.into()]);
+        // X**2 = W
+        let x = E::new([0.into(), 1.into()]);
+        assert_eq!(x * x, E::from(GoldilocksField::non_residue()));
+        assert_eq!(a * b / b, a);
+        assert_eq!(a * a.inverse(), E::one());
+        assert_eq!((a + b) * a, a * a + b * a);
+        assert_eq!(a - a, E::zero());
+        assert_eq!((-a + a).to_base(), Some(0.into()));
+        assert_eq!(a.pow(3), a * a * a);
+        assert_eq!(a.to_string(), "(3, 5)");
+    }
+
+    #[test]
+    fn cubic() {
+        type E = CubicExtension<BabyBearField>;
+        let a = E::new([3.into(), 5.into(), (-7).into()]);
+        let b = E::new([(-2).into(), 11.into(), 13.into()]);
+        // X**3 = W
+        let x = E::new([0.into(), 1.into(), 0.into()]);
+        assert_eq!(x * x * x, E::from(BabyBearField::non_residue()));
+        assert_eq!(a * b / b, a);
+        assert_eq!(a * a.inverse(), E::one());
+        assert_eq!((a + b) * a, a * a + b * a);
+        assert_eq!(a.pow(5), a * a * a * a * a);
+        assert_eq!(E::from(BabyBearField::from(4)).to_base(), Some(4.into()));
+        assert_eq!(a.to_base(), None);
+    }
+
+    #[test]
+    #[should_panic = "Division by zero"]
+    fn division_by_zero() {
+        let _ = CubicExtension::<GoldilocksField>::one() / CubicExtension::zero();
+    }
+}
@@ -0,0 +1,103 @@
+//! Conversions between powdr field elements and field elements
+//! implementing `ff::PrimeField`, as used by the zcash and halo2 ecosystems.
+//! Only available with the `ff` feature.
+
+use ff::PrimeField;
+use num_traits::Num;
+
+use crate::{AbstractNumberType, FieldElement};
+
+/// Converts a field element to the `ff` element with the same canonical
+/// representative. Panics if the two fields have different moduli.
+pub fn to_ff<T: FieldElement, F: PrimeField>(value: T) -> F {
+    assert_same_modulus::<T, F>();
+    let mut bytes = value.to_bytes_le();
+    let mut repr = F::Repr::default();
+    bytes.resize(repr.as_ref().len(), 0);
+    if !is_little_endian::<F>() {
+        bytes.reverse();
+    }
+    repr.as_mut().copy_from_slice(&bytes);
+    F::from_repr(repr).unwrap()
+}
+
+/// Converts an `ff` field element to the field element with the same
+/// canonical representative. Panics if the two fields have different moduli.
+pub fn from_ff<T: FieldElement, F: PrimeField>(value: F) -> T {
+    assert_same_modulus::<T, F>();
+    let mut bytes = value.to_repr().as_ref().to_vec();
+    if !is_little_endian::<F>() {
+        bytes.reverse();
+    }
+    AbstractNumberType::from_bytes_le(&bytes).into()
+}
+
+/// The byte order of `PrimeField::Repr` is implementation-specific,
+/// so we detect it from the representation of one.
+fn is_little_endian<F: PrimeField>() -> bool {
+    F::ONE.to_repr().as_ref()[0] == 1
+}
+
+fn assert_same_modulus<T: FieldElement, F: PrimeField>() {
+    let ff_modulus =
+        AbstractNumberType::from_str_radix(F::MODULUS.trim_start_matches("0x"), 16).unwrap();
+    assert_eq!(
+        ff_modulus,
+        T::modulus(),
+        "Cannot convert between fields with different moduli."
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use ff::Field;
+
+    use crate::{Bn254Field, GoldilocksField};
+
+    use super::*;
+
+    // The derive macro defines module-level constants, so each field gets its own module.
+    mod little_endian {
+        use ff::PrimeField;
+
+        #[derive(PrimeField)]
+        #[PrimeFieldModulus = "21888242871839275222246405745257275088548364400416034343698204186575808495617"]
+        #[PrimeFieldGenerator = "5"]
+        #[PrimeFieldReprEndianness = "little"]
+        pub struct Fr([u64; 4]);
+    }
+
+    mod big_endian {
+        use ff::PrimeField;
+
+        #[derive(PrimeField)]
+        #[PrimeFieldModulus = "21888242871839275222246405745257275088548364400416034343698204186575808495617"]
+        #[PrimeFieldGenerator = "5"]
+        #[PrimeFieldReprEndianness = "big"]
+        pub struct Fr([u64; 4]);
+    }
+
+    use big_endian::Fr as FrBigEndian;
+    use little_endian::Fr;
+
+    #[test]
+    fn bn254() {
+        let value = Bn254Field::from(-7) * Bn254Field::from(1u64 << 40);
+        let expected = -Fr::from(7) * Fr::from(1 << 40);
+        assert_eq!(to_ff::<_, Fr>(value), expected);
+        assert_eq!(from_ff::<Bn254Field, _>(expected), value);
+        let big_endian = to_ff::<_, FrBigEndian>(value);
+        assert_eq!(
+            big_endian,
+            -FrBigEndian::from(7) * FrBigEndian::from(1 << 40)
+        );
+        assert_eq!(from_ff::<Bn254Field, _>(big_endian), value);
+        assert_eq!(from_ff::<Bn254Field, _>(Fr::ONE.double()), 2.into());
+    }
+
+    #[test]
+    #[should_panic = "different moduli"]
+    fn different_moduli() {
+        let _: Fr = to_ff(GoldilocksField::from(1));
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    fmt,
+    str::FromStr,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use crate::FieldElement;
+
+/// How field elements are displayed, e.g. in error messages and logs.
+///
+/// The setting is process-wide, see `set_display_format`. Output that is
+/// parsed again, like PIL files or JSON exports, does not depend on it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DisplayFormat {
+    /// Values above half the modulus are displayed as negative numbers.
+    #[default]
+    Signed,
+    /// The canonical representative in decimal.
+    Decimal,
+    /// The canonical representative in hexadecimal.
+    Hex,
+    /// Like `Signed`, but only the leading and trailing digits of long numbers are shown.
+    Abbreviated,
+}
+
+/// Numbers with more digits than this are abbreviated.
+const ABBREVIATION_THRESHOLD: usize = 16;
+
+static DISPLAY_FORMAT: AtomicU8 = AtomicU8::new(DisplayFormat::Signed as u8);
+
+/// Sets the format used by the `Display` implementations of all field elements.
+pub fn set_display_format(format: DisplayFormat) {
+    DISPLAY_FORMAT.store(format as u8, Ordering::Relaxed);
+}
+
+pub fn display_format() -> DisplayFormat {
+    match DISPLAY_FORMAT.load(Ordering::Relaxed) {
+        0 => DisplayFormat::Signed,
+        1 => DisplayFormat::Decimal,
+        2 => DisplayFormat::Hex,
+        3 => DisplayFormat::Abbreviated,
+        _ => unreachable!(),
+    }
+}
+
+impl FromStr for DisplayFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "signed" => Ok(DisplayFormat::Signed),
+            "decimal" => Ok(DisplayFormat::Decimal),
+            "hex" => Ok(DisplayFormat::Hex),
+            "abbreviated" => Ok(DisplayFormat::Abbreviated),
+            _ => Err(format!(
+                "Unknown number format {s}, expected one of signed, decimal, hex, abbreviated."
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DisplayFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DisplayFormat::Signed => "signed",
+            DisplayFormat::Decimal => "decimal",
+            DisplayFormat::Hex => "hex",
+            DisplayFormat::Abbreviated => "abbreviated",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Formats a field element according to the current display format.
+/// This is the `Display` implementation of all field elements.
+pub(crate) fn format_element<T: FieldElement>(
+    value: &T,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    write_element(value, display_format(), f)
+}
+
+fn write_element<T: FieldElement>(
+    value: &T,
+    format: DisplayFormat,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    match format {
+        DisplayFormat::Signed => write_signed(value, f),
+        DisplayFormat::Decimal => write!(f, "{}", value.to_integer()),
+        DisplayFormat::Hex => write!(f, "{:#x}", value.to_integer()),
+        DisplayFormat::Abbreviated => {
+            let signed = value.to_signed_integer().to_string();
+            let (sign, digits) = match signed.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", signed.as_str()),
+            };
+            if digits.len() > ABBREVIATION_THRESHOLD {
+                let (start, end) = (&digits[..6], &digits[digits.len() - 4..]);
+                write!(f, "{sign}{start}...{end}")
+            } else {
+                write!(f, "{signed}")
+            }
+        }
+    }
+}
+
+fn write_signed<T: FieldElement>(value: &T, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match value.to_signed_i64() {
+        Some(v) => write!(f, "{v}"),
+        None => write!(f, "{}", value.to_signed_integer()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{AbstractNumberType, Bn254Field, GoldilocksField};
+
+    use super::*;
+
+    /// Displays a value in a given format without touching the process-wide
+    /// setting, which would interfere with other tests.
+    struct Formatted<T>(T, DisplayFormat);
+
+    impl<T: FieldElement> fmt::Display for Formatted<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write_element(&self.0, self.1, f)
+        }
+    }
+
+    #[test]
+    fn formats() {
+        let small = GoldilocksField::from(-3);
+        let large = Bn254Field::from(AbstractNumberType::from(1u32) << 100);
+        let expectations = [
+            (
+                DisplayFormat::Signed,
+                "-3",
+                "1267650600228229401496703205376",
+            ),
+            (
+                DisplayFormat::Decimal,
+                "18446744069414584318",
+                "1267650600228229401496703205376",
+            ),
+            (
+                DisplayFormat::Hex,
+                "0xfffffffefffffffe",
+                "0x10000000000000000000000000",
+            ),
+            (DisplayFormat::Abbreviated, "-3", "126765...5376"),
+        ];
+        for (format, small_expected, large_expected) in expectations {
+            assert_eq!(Formatted(small, format).to_string(), small_expected);
+            assert_eq!(Formatted(large, format).to_string(), large_expected);
+            assert_eq!(format.to_string().parse(), Ok(format));
+        }
+        assert_eq!(
+            Formatted(-large, DisplayFormat::Abbreviated).to_string(),
+            "-126765...5376"
+        );
+        assert!("octal".parse::<DisplayFormat>().is_err());
+    }
+}
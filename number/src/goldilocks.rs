@@ -0,0 +1,346 @@
+use std::{fmt, ops::AddAssign};
+
+use num_traits::ToPrimitive;
+
+use crate::{
+    format::format_element, macros::impl_ref_ops, AbstractNumberType, ConversionError, DecodeError,
+    FieldElement,
+};
+
+/// The modulus 2**64 - 2**32 + 1.
+const P: u64 = 0xffff_ffff_0000_0001;
+
+/// 2**64 mod P, i.e. 2**32 - 1.
+const EPSILON: u64 = 0xffff_ffff;
+
+/// An element of the Goldilocks field with modulus 2**64 - 2**32 + 1.
+///
+/// Elements are stored in canonical form. Products are reduced using
+/// 2**64 = 2**32 - 1 and 2**96 = -1 (mod P), which only needs a few
+/// additions and subtractions instead of a division.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, PartialOrd, Ord, Hash)]
+pub struct GoldilocksField {
+    value: u64,
+}
+
+impl GoldilocksField {
+    /// Creates an element from a value that is already known to be smaller than the modulus.
+    const fn from_canonical(value: u64) -> Self {
+        Self { value }
+    }
+
+    fn reduce64(value: u64) -> Self {
+        Self::from_canonical(if value >= P { value - P } else { value })
+    }
+
+    /// Reduces a 128 bit value modulo P.
+    fn reduce128(value: u128) -> Self {
+        let low = value as u64;
+        let high = (value >> 64) as u64;
+        let high_high = high >> 32;
+        let high_low = high & EPSILON;
+
+        // low - high_high * 2**96 = low + high_high
+        let (mut t0, borrow) = low.overflowing_sub(high_high);
+        if borrow {
+            // Adding P is the same as subtracting EPSILON modulo 2**64.
+            t0 = t0.wrapping_sub(EPSILON);
+        }
+        // high_low * 2**64 = high_low * EPSILON, fits into 64 bits.
+        let t1 = high_low * EPSILON;
+        let (mut result, carry) = t0.overflowing_add(t1);
+        if carry {
+            result = result.wrapping_add(EPSILON);
+        }
+        Self::reduce64(result)
+    }
+
+    fn pow_u64(self, mut exponent: u64) -> Self {
+        let mut base = self;
+        let mut result = Self::one();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exponent >>= 1;
+        }
+        result
+    }
+
+    fn inverse(self) -> Self {
+        assert!(!self.is_zero(), "Division by zero");
+        self.pow_u64(P - 2)
+    }
+}
+
+impl FieldElement for GoldilocksField {
+    fn try_to_u64(&self) -> Result<u64, ConversionError> {
+        Ok(self.value)
+    }
+
+    fn to_integer(&self) -> AbstractNumberType {
+        self.value.into()
+    }
+
+    fn modulus() -> AbstractNumberType {
+        P.into()
+    }
+
+    fn zero() -> Self {
+        Self::from_canonical(0)
+    }
+
+    fn one() -> Self {
+        Self::from_canonical(1)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.value == 0
+    }
+
+    fn pow(self, exponent: AbstractNumberType) -> Self {
+        // x**(a + b * 2**64) = x**a * (x**(2**64))**b
+        let mut base = self;
+        let mut result = Self::one();
+        for digit in exponent.to_u64_digits() {
+            result = result * base.pow_u64(digit);
+            base = (0..64).fold(base, |b, _| b * b);
+        }
+        result
+    }
+
+    fn random<R: rand::Rng + ?Sized>(rng: &mut R) -> Self {
+        loop {
+            let value = rng.next_u64();
+            if value < P {
+                return Self::from_canonical(value);
+            }
+        }
+    }
+
+    fn integer_div(self, other: Self) -> Self {
+        Self::from_canonical(self.value / other.value)
+    }
+
+    fn to_bytes_le(&self) -> Vec<u8> {
+        self.value.to_le_bytes().to_vec()
+    }
+
+    fn to_signed_i64(&self) -> Option<i64> {
+        Some(if self.value > (P - 1) / 2 {
+            -((P - self.value) as i64)
+        } else {
+            self.value as i64
+        })
+    }
+
+    fn byte_len() -> usize {
+        8
+    }
+
+    fn from_bytes_le(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let bytes: [u8; 8] = bytes.try_into().map_err(|_| DecodeError::Length {
+            expected: 8,
+            actual: bytes.len(),
+        })?;
+        let value = u64::from_le_bytes(bytes);
+        if value >= P {
+            return Err(DecodeError::NotCanonical(value.into()));
+        }
+        Ok(Self::from_canonical(value))
+    }
+}
+
+impl From<AbstractNumberType> for GoldilocksField {
+    fn from(value: AbstractNumberType) -> Self {
+        Self::from_canonical((value % P).to_u64().unwrap())
+    }
+}
+
+impl From<u128> for GoldilocksField {
+    fn from(value: u128) -> Self {
+        Self::reduce128(value)
+    }
+}
+
+impl From<u64> for GoldilocksField {
+    fn from(value: u64) -> Self {
+        Self::reduce64(value)
+    }
+}
+
+impl From<u32> for GoldilocksField {
+    fn from(value: u32) -> Self {
+        Self::from_canonical(value as u64)
+    }
+}
+
+impl From<i32> for GoldilocksField {
+    fn from(value: i32) -> Self {
+        let abs = Self::from_canonical(value.unsigned_abs() as u64);
+        if value < 0 {
+            -abs
+        } else {
+            abs
+        }
+    }
+}
+
+impl From<bool> for GoldilocksField {
+    fn from(value: bool) -> Self {
+        Self::from_canonical(value as u64)
+    }
+}
+
+impl std::ops::Add for GoldilocksField {
+    type Output = GoldilocksField;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let (sum, carry) = self.value.overflowing_add(rhs.value);
+        if carry {
+            // Both summands are smaller than P, so this does not overflow again.
+            Self::reduce64(sum + EPSILON)
+        } else {
+            Self::reduce64(sum)
+        }
+    }
+}
+
+impl AddAssign for GoldilocksField {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl std::ops::Sub for GoldilocksField {
+    type Output = GoldilocksField;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        let (difference, borrow) = self.value.overflowing_sub(rhs.value);
+        if borrow {
+            Self::from_canonical(difference.wrapping_sub(EPSILON))
+        } else {
+            Self::from_canonical(difference)
+        }
+    }
+}
+
+impl std::ops::Mul for GoldilocksField {
+    type Output = GoldilocksField;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::reduce128(self.value as u128 * rhs.value as u128)
+    }
+}
+
+impl std::ops::Div for GoldilocksField {
+    type Output = GoldilocksField;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: Self) -> Self::Output {
+        self * rhs.inverse()
+    }
+}
+
+impl_ref_ops!(GoldilocksField);
+
+impl std::ops::Neg for GoldilocksField {
+    type Output = GoldilocksField;
+
+    fn neg(self) -> Self::Output {
+        if self.is_zero() {
+            self
+        } else {
+            Self::from_canonical(P - self.value)
+        }
+    }
+}
+
+impl fmt::Display for GoldilocksField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        format_element(self, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn reference_mul(a: u64, b: u64) -> u64 {
+        ((a as u128 * b as u128) % P as u128) as u64
+    }
+
+    #[test]
+    #[should_panic]
+    fn integer_div_by_zero() {
+        let _ = GoldilocksField::from(1).to_integer() / GoldilocksField::from(0).to_integer();
+    }
+
+    #[test]
+    #[should_panic]
+    fn div_by_zero() {
+        let _ = GoldilocksField::from(1) / GoldilocksField::from(0);
+    }
+
+    #[test]
+    fn reduction() {
+        let values = [
+            0,
+            1,
+            2,
+            EPSILON,
+            EPSILON + 1,
+            P / 2,
+            P - 2,
+            P - 1,
+            0x1234_5678_9abc_def0,
+        ];
+        for a in values {
+            for b in values {
+                let product = GoldilocksField::from(a) * GoldilocksField::from(b);
+                assert_eq!(product.to_degree(), reference_mul(a, b));
+            }
+        }
+        assert_eq!(GoldilocksField::from(u64::MAX).to_degree(), EPSILON - 1);
+        assert_eq!(
+            GoldilocksField::from(u128::MAX).to_degree(),
+            (u128::MAX % P as u128) as u64
+        );
+    }
+
+    #[test]
+    fn add_sub_neg() {
+        let max = GoldilocksField::from(P - 1);
+        assert_eq!(max + max, GoldilocksField::from(P - 2));
+        assert_eq!(max + GoldilocksField::one(), GoldilocksField::zero());
+        assert_eq!(GoldilocksField::zero() - GoldilocksField::one(), max);
+        assert_eq!(-GoldilocksField::from(5), GoldilocksField::from(-5));
+        assert_eq!(-GoldilocksField::zero(), GoldilocksField::zero());
+        assert_eq!(GoldilocksField::from(-5).to_string(), "-5");
+    }
+
+    #[test]
+    fn division() {
+        let a = GoldilocksField::from(0x1234_5678_9abc_def0u64);
+        let b = GoldilocksField::from(0xfedc_ba98_7654_3210u64);
+        assert_eq!(a / b * b, a);
+        assert_eq!(
+            GoldilocksField::from(8) / GoldilocksField::from(2),
+            4.into()
+        );
+    }
+
+    #[test]
+    fn signed() {
+        for value in [0, 1, -4, (P / 2) as i64, -((P / 2) as i64)] {
+            let x = GoldilocksField::from_i64(value);
+            assert_eq!(x.to_signed_i64(), Some(value));
+            assert_eq!(x.to_string(), value.to_string());
+        }
+        assert_eq!(
+            GoldilocksField::from(P / 2 + 1).to_signed_i64(),
+            Some(-((P / 2) as i64))
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::AddAssign,
+};
+
+use crate::{
+    format::format_element, macros::impl_ref_ops, AbstractNumberType, ConversionError, FieldElement,
+};
+
+/// The modulus 2**64 - 2**32 + 1.
+const P: u64 = 0xffff_ffff_0000_0001;
+
+/// 2**64 mod P, i.e. 2**32 - 1.
+const EPSILON: u64 = 0xffff_ffff;
+
+/// R**2 mod P for the Montgomery radix R = 2**64, used to convert into Montgomery form.
+const R2: u64 = ((EPSILON as u128 * EPSILON as u128) % P as u128) as u64;
+
+/// An element of the Goldilocks field in Montgomery form.
+///
+/// The value `x` is stored as `x * 2**64 mod P`. Reduction is lazy:
+/// The stored value can be any 64 bit integer congruent to that, it is only
+/// brought into canonical form when the element is compared, hashed or converted.
+/// This is an alternative to `GoldilocksField` and produces the same results.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GoldilocksMontgomeryField {
+    value: u64,
+}
+
+impl GoldilocksMontgomeryField {
+    /// Computes `x * 2**-64 mod P`. The result is smaller than 2**64
+    /// but not necessarily canonical.
+    fn montgomery_reduce(x: u128) -> u64 {
+        let low = x as u64;
+        let high = (x >> 64) as u64;
+        // P**-1 = 2**32 + 1 (mod 2**64)
+        let m = low.wrapping_add(low << 32);
+        // The low halves of x and m * P are equal, so (x - m * P) / 2**64 = high - (m * P) / 2**64.
+        let m_times_p_high = ((m as u128 * P as u128) >> 64) as u64;
+        let (result, borrow) = high.overflowing_sub(m_times_p_high);
+        if borrow {
+            result.wrapping_add(P)
+        } else {
+            result
+        }
+    }
+
+    fn from_canonical(value: u64) -> Self {
+        Self {
+            value: Self::montgomery_reduce(value as u128 * R2 as u128),
+        }
+    }
+
+    /// Returns the canonical representative.
+    fn canonical(&self) -> u64 {
+        let value = Self::montgomery_reduce(self.value as u128);
+        if value >= P {
+            value - P
+        } else {
+            value
+        }
+    }
+
+    fn pow_u64(self, mut exponent: u64) -> Self {
+        let mut base = self;
+        let mut result = Self::one();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exponent >>= 1;
+        }
+        result
+    }
+
+    fn inverse(self) -> Self {
+        assert!(!self.is_zero(), "Division by zero");
+        self.pow_u64(P - 2)
+    }
+}
+
+impl FieldElement for GoldilocksMontgomeryField {
+    fn try_to_u64(&self) -> Result<u64, ConversionError> {
+        Ok(self.canonical())
+    }
+
+    fn to_signed_i64(&self) -> Option<i64> {
+        let value = self.canonical();
+        Some(if value > (P - 1) / 2 {
+            -((P - value) as i64)
+        } else {
+            value as i64
+        })
+    }
+
+    fn to_integer(&self) -> AbstractNumberType {
+        self.canonical().into()
+    }
+
+    fn modulus() -> AbstractNumberType {
+        P.into()
+    }
+
+    fn zero() -> Self {
+        Self { value: 0 }
+    }
+
+    fn one() -> Self {
+        Self::from_canonical(1)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.canonical() == 0
+    }
+
+    fn pow(self, exponent: AbstractNumberType) -> Self {
+        // x**(a + b * 2**64) = x**a * (x**(2**64))**b
+        let mut base = self;
+        let mut result = Self::one();
+        for digit in exponent.to_u64_digits() {
+            result = result * base.pow_u64(digit);
+            base = (0..64).fold(base, |b, _| b * b);
+        }
+        result
+    }
+
+    fn integer_div(self, other: Self) -> Self {
+        Self::from_canonical(self.canonical() / other.canonical())
+    }
+
+    fn to_bytes_le(&self) -> Vec<u8> {
+        self.canonical().to_le_bytes().to_vec()
+    }
+}
+
+impl From<AbstractNumberType> for GoldilocksMontgomeryField {
+    fn from(value: AbstractNumberType) -> Self {
+        Self::from_canonical((value % P).to_u64_digits().first().copied().unwrap_or(0))
+    }
+}
+
+impl From<u64> for GoldilocksMontgomeryField {
+    fn from(value: u64) -> Self {
+        // Montgomery reduction does not require a canonical input.
+        Self::from_canonical(value)
+    }
+}
+
+impl From<u32> for GoldilocksMontgomeryField {
+    fn from(value: u32) -> Self {
+        Self::from_canonical(value as u64)
+    }
+}
+
+impl From<i32> for GoldilocksMontgomeryField {
+    fn from(value: i32) -> Self {
+        let abs = Self::from_canonical(value.unsigned_abs() as u64);
+        if value < 0 {
+            -abs
+        } else {
+            abs
+        }
+    }
+}
+
+impl From<bool> for GoldilocksMontgomeryField {
+    fn from(value: bool) -> Self {
+        Self::from_canonical(value as u64)
+    }
+}
+
+impl PartialEq for GoldilocksMontgomeryField {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical() == other.canonical()
+    }
+}
+
+impl Eq for GoldilocksMontgomeryField {}
+
+impl PartialOrd for GoldilocksMontgomeryField {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for GoldilocksMontgomeryField {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.canonical().cmp(&other.canonical())
+    }
+}
+
+impl Hash for GoldilocksMontgomeryField {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical().hash(state);
+    }
+}
+
+impl std::ops::Add for GoldilocksMontgomeryField {
+    type Output = GoldilocksMontgomeryField;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        // An overflow drops 2**64, which is EPSILON modulo P.
+        // The second addition can only overflow if the first did, in which case
+        // the sum is small enough to absorb another EPSILON.
+        let (sum, carry) = self.value.overflowing_add(rhs.value);
+        let (sum, carry) = sum.overflowing_add(EPSILON * carry as u64);
+        Self {
+            value: sum + EPSILON * carry as u64,
+        }
+    }
+}
+
+impl AddAssign for GoldilocksMontgomeryField {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl std::ops::Sub for GoldilocksMontgomeryField {
+    type Output = GoldilocksMontgomeryField;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        let (difference, borrow) = self.value.overflowing_sub(rhs.value);
+        let (difference, borrow) = difference.overflowing_sub(EPSILON * borrow as u64);
+        Self {
+            value: difference - EPSILON * borrow as u64,
+        }
+    }
+}
+
+impl std::ops::Mul for GoldilocksMontgomeryField {
+    type Output = GoldilocksMontgomeryField;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self {
+            value: Self::montgomery_reduce(self.value as u128 * rhs.value as u128),
+        }
+    }
+}
+
+impl std::ops::Div for GoldilocksMontgomeryField {
+    type Output = GoldilocksMontgomeryField;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: Self) -> Self::Output {
+        self * rhs.inverse()
+    }
+}
+
+impl_ref_ops!(GoldilocksMontgomeryField);
+
+impl std::ops::Neg for GoldilocksMontgomeryField {
+    type Output = GoldilocksMontgomeryField;
+
+    fn neg(self) -> Self::Output {
+        Self::zero() - self
+    }
+}
+
+impl fmt::Display for GoldilocksMontgomeryField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        format_element(self, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::GoldilocksField;
+
+    use super::*;
+
+    /// Values close to the boundaries of the lazy representation and some pseudo-random ones.
+    fn values() -> Vec<u64> {
+        let mut values = vec![0, 1, 2, EPSILON, P - 1, P, P + 1, u64::MAX - 1, u64::MAX];
+        let mut x: u64 = 0x1234_5678_9abc_def0;
+        for _ in 0..20 {
+            x = x
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            values.push(x);
+        }
+        values
+    }
+
+    #[test]
+    fn agrees_with_goldilocks() {
+        for a in values() {
+            for b in values() {
+                let (ma, mb) = (
+                    GoldilocksMontgomeryField::from(a),
+                    GoldilocksMontgomeryField::from(b),
+                );
+                let (ga, gb) = (GoldilocksField::from(a), GoldilocksField::from(b));
+                assert_eq!((ma + mb).to_integer(), (ga + gb).to_integer());
+                assert_eq!((ma - mb).to_integer(), (ga - gb).to_integer());
+                assert_eq!((ma * mb).to_integer(), (ga * gb).to_integer());
+                assert_eq!((-ma).to_integer(), (-ga).to_integer());
+                if b % P != 0 {
+                    assert_eq!((ma / mb).to_integer(), (ga / gb).to_integer());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn non_canonical_values() {
+        // Both are representations of zero in Montgomery form.
+        let zero = GoldilocksMontgomeryField { value: 0 };
+        let also_zero = GoldilocksMontgomeryField { value: P };
+        assert_eq!(zero, also_zero);
+        assert!(also_zero.is_zero());
+        assert_eq!(
+            also_zero + GoldilocksMontgomeryField::one(),
+            GoldilocksMontgomeryField::one()
+        );
+        assert_eq!(GoldilocksMontgomeryField::from(-3).to_string(), "-3");
+    }
+
+    #[test]
+    #[should_panic]
+    fn div_by_zero() {
+        let _ = GoldilocksMontgomeryField::from(1) / GoldilocksMontgomeryField::from(0);
+    }
+}
@@ -0,0 +1,242 @@
+/// Implements the by-reference variants of the arithmetic operators
+/// in terms of the by-value ones, which every field has to provide.
+macro_rules! impl_ref_ops {
+    ($name:ident) => {
+        $crate::macros::impl_ref_ops!($name, Add, add);
+        $crate::macros::impl_ref_ops!($name, Sub, sub);
+        $crate::macros::impl_ref_ops!($name, Mul, mul);
+        $crate::macros::impl_ref_ops!($name, Div, div);
+    };
+    ($name:ident, $op:ident, $method:ident) => {
+        impl<'a> std::ops::$op<&'a $name> for $name {
+            type Output = $name;
+
+            fn $method(self, rhs: &'a $name) -> Self::Output {
+                std::ops::$op::$method(self, *rhs)
+            }
+        }
+
+        impl std::ops::$op for &$name {
+            type Output = $name;
+
+            fn $method(self, rhs: Self) -> Self::Output {
+                std::ops::$op::$method(*self, *rhs)
+            }
+        }
+
+        impl std::ops::$op<$name> for &$name {
+            type Output = $name;
+
+            fn $method(self, rhs: $name) -> Self::Output {
+                std::ops::$op::$method(*self, rhs)
+            }
+        }
+    };
+}
+
+/// Implements a field with a modulus below 2**31 on top of a `u32`.
+/// The type has to be a struct with a single field `value: u32` holding
+/// the canonical representative and an associated function
+/// `reduce(value: u64) -> u32` that reduces the product of two canonical values.
+macro_rules! impl_small_field {
+    ($name:ident, $modulus:expr) => {
+        impl $name {
+            const P: u32 = $modulus;
+
+            const fn from_canonical(value: u32) -> Self {
+                Self { value }
+            }
+
+            fn pow_u64(self, mut exponent: u64) -> Self {
+                let mut base = self;
+                let mut result = <Self as $crate::FieldElement>::one();
+                while exponent > 0 {
+                    if exponent & 1 == 1 {
+                        result = result * base;
+                    }
+                    base = base * base;
+                    exponent >>= 1;
+                }
+                result
+            }
+
+            fn inverse(self) -> Self {
+                assert!(self.value != 0, "Division by zero");
+                self.pow_u64((Self::P - 2) as u64)
+            }
+        }
+
+        impl $crate::FieldElement for $name {
+            fn try_to_u64(&self) -> Result<u64, $crate::ConversionError> {
+                Ok(self.value as u64)
+            }
+
+            fn try_to_u32(&self) -> Result<u32, $crate::ConversionError> {
+                Ok(self.value)
+            }
+
+            fn to_signed_i64(&self) -> Option<i64> {
+                Some(if self.value > (Self::P - 1) / 2 {
+                    -((Self::P - self.value) as i64)
+                } else {
+                    self.value as i64
+                })
+            }
+
+            fn to_integer(&self) -> $crate::AbstractNumberType {
+                self.value.into()
+            }
+
+            fn modulus() -> $crate::AbstractNumberType {
+                Self::P.into()
+            }
+
+            fn zero() -> Self {
+                Self::from_canonical(0)
+            }
+
+            fn one() -> Self {
+                Self::from_canonical(1)
+            }
+
+            fn is_zero(&self) -> bool {
+                self.value == 0
+            }
+
+            fn pow(self, exponent: $crate::AbstractNumberType) -> Self {
+                use num_traits::{ToPrimitive, Zero};
+                if exponent.is_zero() {
+                    Self::one()
+                } else if self.is_zero() {
+                    self
+                } else {
+                    // The multiplicative group has order P - 1.
+                    let exponent = (exponent % (Self::P - 1)).to_u64().unwrap();
+                    self.pow_u64(exponent)
+                }
+            }
+
+            fn random<R: rand::Rng + ?Sized>(rng: &mut R) -> Self {
+                loop {
+                    // The modulus is below 2**31.
+                    let value = rng.next_u32() >> 1;
+                    if value < Self::P {
+                        return Self::from_canonical(value);
+                    }
+                }
+            }
+
+            fn integer_div(self, other: Self) -> Self {
+                Self::from_canonical(self.value / other.value)
+            }
+
+            fn to_bytes_le(&self) -> Vec<u8> {
+                self.value.to_le_bytes().to_vec()
+            }
+        }
+
+        impl From<$crate::AbstractNumberType> for $name {
+            fn from(value: $crate::AbstractNumberType) -> Self {
+                use num_traits::ToPrimitive;
+                Self::from_canonical((value % Self::P).to_u32().unwrap())
+            }
+        }
+
+        impl From<u64> for $name {
+            fn from(value: u64) -> Self {
+                Self::from_canonical((value % Self::P as u64) as u32)
+            }
+        }
+
+        impl From<u32> for $name {
+            fn from(value: u32) -> Self {
+                Self::from_canonical(value % Self::P)
+            }
+        }
+
+        impl From<i32> for $name {
+            fn from(value: i32) -> Self {
+                let abs = Self::from(value.unsigned_abs());
+                if value < 0 {
+                    -abs
+                } else {
+                    abs
+                }
+            }
+        }
+
+        impl From<bool> for $name {
+            fn from(value: bool) -> Self {
+                Self::from_canonical(value as u32)
+            }
+        }
+
+        impl std::ops::Add for $name {
+            type Output = $name;
+
+            fn add(self, rhs: Self) -> Self::Output {
+                // Both summands are below 2**31, so the sum fits.
+                let sum = self.value + rhs.value;
+                Self::from_canonical(if sum >= Self::P { sum - Self::P } else { sum })
+            }
+        }
+
+        impl std::ops::AddAssign for $name {
+            fn add_assign(&mut self, rhs: Self) {
+                *self = *self + rhs;
+            }
+        }
+
+        impl std::ops::Sub for $name {
+            type Output = $name;
+
+            fn sub(self, rhs: Self) -> Self::Output {
+                if self.value >= rhs.value {
+                    Self::from_canonical(self.value - rhs.value)
+                } else {
+                    Self::from_canonical(self.value + (Self::P - rhs.value))
+                }
+            }
+        }
+
+        impl std::ops::Mul for $name {
+            type Output = $name;
+
+            fn mul(self, rhs: Self) -> Self::Output {
+                Self::from_canonical(Self::reduce(self.value as u64 * rhs.value as u64))
+            }
+        }
+
+        impl std::ops::Div for $name {
+            type Output = $name;
+
+            #[allow(clippy::suspicious_arithmetic_impl)]
+            fn div(self, rhs: Self) -> Self::Output {
+                self * rhs.inverse()
+            }
+        }
+
+        $crate::macros::impl_ref_ops!($name);
+
+        impl std::ops::Neg for $name {
+            type Output = $name;
+
+            fn neg(self) -> Self::Output {
+                if self.value == 0 {
+                    self
+                } else {
+                    Self::from_canonical(Self::P - self.value)
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                $crate::format::format_element(self, f)
+            }
+        }
+    };
+}
+
+pub(crate) use impl_ref_ops;
+pub(crate) use impl_small_field;
@@ -0,0 +1,55 @@
+use crate::macros::impl_small_field;
+
+/// An element of the Mersenne-31 field with modulus 2**31 - 1.
+///
+/// Note that 32 bit machine words do not fit into this field and have
+/// to be split into smaller limbs.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, PartialOrd, Ord, Hash)]
+pub struct Mersenne31Field {
+    value: u32,
+}
+
+impl Mersenne31Field {
+    /// Reduces a value below 2**62 using 2**31 = 1 (mod P).
+    fn reduce(value: u64) -> u32 {
+        let p = Self::P as u64;
+        let folded = (value & p) + (value >> 31);
+        let folded = (folded & p) + (folded >> 31);
+        (if folded >= p { folded - p } else { folded }) as u32
+    }
+}
+
+impl_small_field!(Mersenne31Field, 0x7fff_ffff);
+
+#[cfg(test)]
+mod test {
+    use crate::FieldElement;
+
+    use super::*;
+
+    #[test]
+    fn reduction() {
+        let values = [0u32, 1, 2, 0x4000_0000, 0x7fff_fffe, 0x1234_5678];
+        for a in values {
+            for b in values {
+                let product = Mersenne31Field::from(a) * Mersenne31Field::from(b);
+                assert_eq!(product.to_degree(), a as u64 * b as u64 % 0x7fff_ffff);
+            }
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        let max = Mersenne31Field::from(-1);
+        assert_eq!(max.to_degree(), 0x7fff_fffe);
+        assert_eq!(max + Mersenne31Field::one(), Mersenne31Field::zero());
+        assert_eq!(Mersenne31Field::from(1u64 << 31), Mersenne31Field::one());
+        assert_eq!(Mersenne31Field::from(-5).to_string(), "-5");
+        let a = Mersenne31Field::from(987654321);
+        assert_eq!(a / a, Mersenne31Field::one());
+        assert_eq!(
+            Mersenne31Field::from(2).pow(31u32.into()),
+            Mersenne31Field::one()
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! Number theoretic transforms and related polynomial operations.
+//!
+//! Polynomials are given by their coefficients, starting with the constant one.
+//! The evaluation domain of size `n` (a power of two) consists of the powers
+//! `1, w, w**2, ...` of a primitive `n`-th root of unity `w`, in this order.
+
+use num_traits::One;
+
+use crate::{
+    batch, AbstractNumberType, BabyBearField, Bn254Field, FieldElement, GoldilocksField,
+    GoldilocksMontgomeryField,
+};
+
+/// A field with large power-of-two roots of unity.
+pub trait NttField: FieldElement {
+    /// The largest `k` such that `2**k` divides `modulus - 1`.
+    const TWO_ADICITY: u32;
+
+    /// A generator of the multiplicative group.
+    fn multiplicative_generator() -> Self;
+
+    /// Returns a primitive `2**log_n`-th root of unity.
+    fn root_of_unity(log_n: u32) -> Self {
+        assert!(
+            log_n <= Self::TWO_ADICITY,
+            "The field has no roots of unity of order 2**{log_n}."
+        );
+        let order = Self::modulus() - AbstractNumberType::one();
+        Self::multiplicative_generator().pow(order >> log_n)
+    }
+}
+
+impl NttField for GoldilocksField {
+    const TWO_ADICITY: u32 = 32;
+
+    fn multiplicative_generator() -> Self {
+        7.into()
+    }
+}
+
+impl NttField for GoldilocksMontgomeryField {
+    const TWO_ADICITY: u32 = 32;
+
+    fn multiplicative_generator() -> Self {
+        7.into()
+    }
+}
+
+impl NttField for BabyBearField {
+    const TWO_ADICITY: u32 = 27;
+
+    fn multiplicative_generator() -> Self {
+        31.into()
+    }
+}
+
+impl NttField for Bn254Field {
+    const TWO_ADICITY: u32 = 28;
+
+    fn multiplicative_generator() -> Self {
+        5.into()
+    }
+}
+
+/// Replaces the coefficients of a polynomial by its evaluations on the domain
+/// of size `values.len()`, which has to be a power of two.
+pub fn ntt<T: NttField>(values: &mut [T]) {
+    transform(values, root_of_unity_for_len(values.len()));
+}
+
+/// Inverse of `ntt`: Replaces evaluations on the domain by the coefficients
+/// of the unique polynomial of degree less than `values.len()` that has them.
+pub fn intt<T: NttField>(values: &mut [T]) {
+    if values.is_empty() {
+        return;
+    }
+    transform(values, T::one() / root_of_unity_for_len(values.len()));
+    let n_inverse = T::one() / T::from(values.len() as u64);
+    batch::mul_scalar_assign(values, n_inverse);
+}
+
+/// Like `ntt`, but evaluates on the coset `g * domain`, where `g` is
+/// the multiplicative generator. Cosets are disjoint from the domain,
+/// which is needed e.g. to evaluate quotient polynomials.
+pub fn coset_ntt<T: NttField>(values: &mut [T]) {
+    scale_by_powers(values, T::multiplicative_generator());
+    ntt(values);
+}
+
+/// Inverse of `coset_ntt`.
+pub fn coset_intt<T: NttField>(values: &mut [T]) {
+    intt(values);
+    scale_by_powers(values, T::one() / T::multiplicative_generator());
+}
+
+/// Evaluates the polynomial with the given coefficients at `x`.
+pub fn evaluate<T: FieldElement>(coefficients: &[T], x: T) -> T {
+    coefficients
+        .iter()
+        .rev()
+        .fold(T::zero(), |acc, c| acc * x + *c)
+}
+
+/// Returns the coefficients of the unique polynomial of degree less than
+/// `points.len()` that passes through the given points, using Lagrange
+/// interpolation. The x coordinates have to be distinct.
+/// This takes quadratic time, use `intt` if the points are a domain.
+pub fn interpolate<T: FieldElement>(points: &[(T, T)]) -> Vec<T> {
+    let n = points.len();
+    // The coefficients of prod_j (X - x_j).
+    let mut vanishing = vec![T::one()];
+    for (x, _) in points {
+        vanishing = multiply_by_linear(&vanishing, *x);
+    }
+    // The denominators prod_{j != i} (x_i - x_j) of the Lagrange basis polynomials.
+    let mut denominators = points
+        .iter()
+        .enumerate()
+        .map(|(i, (x_i, _))| {
+            points
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .fold(T::one(), |acc, (_, (x_j, _))| acc * (*x_i - *x_j))
+        })
+        .collect::<Vec<_>>();
+    batch::invert_many(&mut denominators);
+
+    let mut result = vec![T::zero(); n];
+    for ((x_i, y_i), denominator_inverse) in points.iter().zip(denominators) {
+        let basis = divide_by_linear(&vanishing, *x_i);
+        let factor = *y_i * denominator_inverse;
+        for (r, b) in result.iter_mut().zip(basis) {
+            *r += factor * b;
+        }
+    }
+    result
+}
+
+fn root_of_unity_for_len<T: NttField>(len: usize) -> T {
+    assert!(
+        len.is_power_of_two(),
+        "The length {len} is not a power of two."
+    );
+    T::root_of_unity(len.trailing_zeros())
+}
+
+/// Iterative radix-2 Cooley-Tukey transform: Evaluates the polynomial
+/// at the powers of `root`, which has to have order `values.len()`.
+fn transform<T: FieldElement>(values: &mut [T], root: T) {
+    let n = values.len();
+    if n <= 1 {
+        return;
+    }
+    let log_n = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - log_n);
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+    // The twiddle factors of the last round, the earlier rounds use every k-th.
+    let mut twiddles = Vec::with_capacity(n / 2);
+    let mut power = T::one();
+    for _ in 0..n / 2 {
+        twiddles.push(power);
+        power = power * root;
+    }
+    let mut len = 2;
+    while len <= n {
+        let half = len / 2;
+        let stride = n / len;
+        for chunk in values.chunks_exact_mut(len) {
+            let (low, high) = chunk.split_at_mut(half);
+            for (k, (a, b)) in low.iter_mut().zip(high).enumerate() {
+                let t = *b * twiddles[k * stride];
+                *b = *a - t;
+                *a += t;
+            }
+        }
+        len *= 2;
+    }
+}
+
+/// Multiplies the i-th element by `factor**i`.
+fn scale_by_powers<T: FieldElement>(values: &mut [T], factor: T) {
+    let mut power = T::one();
+    for v in values {
+        *v = *v * power;
+        power = power * factor;
+    }
+}
+
+/// Multiplies a polynomial by `X - a`.
+fn multiply_by_linear<T: FieldElement>(coefficients: &[T], a: T) -> Vec<T> {
+    let mut result = vec![T::zero(); coefficients.len() + 1];
+    for (i, c) in coefficients.iter().enumerate() {
+        result[i + 1] += *c;
+        result[i] += -a * *c;
+    }
+    result
+}
+
+/// Divides a polynomial by `X - a`, which has to divide it.
+fn divide_by_linear<T: FieldElement>(coefficients: &[T], a: T) -> Vec<T> {
+    // Synthetic division, from the leading coefficient down.
+    let mut result = vec![T::zero(); coefficients.len() - 1];
+    let mut carry = T::zero();
+    for i in (1..coefficients.len()).rev() {
+        carry = coefficients[i] + carry * a;
+        result[i - 1] = carry;
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::random::seeded_rng;
+
+    use super::*;
+
+    fn random_values<T: FieldElement>(len: usize) -> Vec<T> {
+        let mut rng = seeded_rng(len as u64);
+        (0..len).map(|_| T::random(&mut rng)).collect()
+    }
+
+    fn check_ntt<T: NttField>() {
+        let root = T::root_of_unity(T::TWO_ADICITY);
+        assert_eq!(
+            root.pow(AbstractNumberType::one() << T::TWO_ADICITY),
+            T::one()
+        );
+        assert_ne!(
+            root.pow(AbstractNumberType::one() << (T::TWO_ADICITY - 1)),
+            T::one()
+        );
+
+        for len in [1, 2, 8, 32] {
+            let coefficients = random_values::<T>(len);
+            let w = T::root_of_unity(len.trailing_zeros());
+            let g = T::multiplicative_generator();
+
+            let mut evaluations = coefficients.clone();
+            ntt(&mut evaluations);
+            let mut x = T::one();
+            for e in &evaluations {
+                assert_eq!(*e, evaluate(&coefficients, x));
+                x = x * w;
+            }
+            intt(&mut evaluations);
+            assert_eq!(evaluations, coefficients);
+
+            let mut coset_evaluations = coefficients.clone();
+            coset_ntt(&mut coset_evaluations);
+            let mut x = g;
+            for e in &coset_evaluations {
+                assert_eq!(*e, evaluate(&coefficients, x));
+                x = x * w;
+            }
+            coset_intt(&mut coset_evaluations);
+            assert_eq!(coset_evaluations, coefficients);
+        }
+    }
+
+    #[test]
+    fn ntt_roundtrip() {
+        check_ntt::<GoldilocksField>();
+        check_ntt::<GoldilocksMontgomeryField>();
+        check_ntt::<BabyBearField>();
+        check_ntt::<Bn254Field>();
+    }
+
+    #[test]
+    fn lagrange_interpolation() {
+        let coefficients = random_values::<GoldilocksField>(7);
+        let points = random_values::<GoldilocksField>(7)
+            .into_iter()
+            .map(|x| (x, evaluate(&coefficients, x)))
+            .collect::<Vec<_>>();
+        assert_eq!(interpolate(&points), coefficients);
+        assert_eq!(interpolate::<GoldilocksField>(&[]), vec![]);
+    }
+
+    #[test]
+    #[should_panic = "not a power of two"]
+    fn non_power_of_two() {
+        ntt(&mut [GoldilocksField::from(1); 3]);
+    }
+}
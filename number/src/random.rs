@@ -0,0 +1,41 @@
+use rand::SeedableRng;
+
+/// A fast random number generator whose output only depends on its seed.
+pub type SeededRng = rand_chacha::ChaCha8Rng;
+
+/// Returns a generator for reproducible random field elements,
+/// e.g. in property tests: `T::random(&mut seeded_rng(42))`.
+pub fn seeded_rng(seed: u64) -> SeededRng {
+    SeededRng::seed_from_u64(seed)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{BabyBearField, Bn254Field, FieldElement, GoldilocksField, Mersenne31Field};
+
+    use super::*;
+
+    fn check_random<T: FieldElement>() {
+        let values = |seed| {
+            let mut rng = seeded_rng(seed);
+            (0..100).map(|_| T::random(&mut rng)).collect::<Vec<_>>()
+        };
+        let first = values(7);
+        assert_eq!(first, values(7));
+        assert_ne!(first, values(8));
+        assert!(first.iter().all(|v| v.to_integer() < T::modulus()));
+        // All 100 values being equal or all being small is virtually impossible.
+        assert!(first.iter().any(|v| *v != first[0]));
+        assert!(first
+            .iter()
+            .any(|v| v.to_integer() > T::modulus() / 1024u32));
+    }
+
+    #[test]
+    fn reproducible() {
+        check_random::<GoldilocksField>();
+        check_random::<BabyBearField>();
+        check_random::<Mersenne31Field>();
+        check_random::<Bn254Field>();
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    fmt,
+    hash::Hash,
+    ops::{Add, AddAssign, Div, Mul, Neg, Sub},
+};
+
+use num_bigint::{BigInt, RandBigInt};
+use num_traits::{Num, ToPrimitive};
+use rand::Rng;
+
+use crate::{AbstractNumberType, ConversionError, DecodeError, DegreeType};
+
+/// A field element.
+///
+/// All parts of the pipeline are generic over the field they compute in,
+/// the concrete field is chosen by the caller, e.g. the command line interface.
+pub trait FieldElement:
+    'static
+    + Send
+    + Sync
+    + Default
+    + Copy
+    + PartialEq
+    + Eq
+    + PartialOrd
+    + Ord
+    + Hash
+    + Add<Output = Self>
+    + AddAssign
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + fmt::Display
+    + fmt::Debug
+    + From<AbstractNumberType>
+    + From<u64>
+    + From<u32>
+    + From<i32>
+    + From<bool>
+{
+    /// Converts the canonical representative to a degree. Only use this for values
+    /// that are known to be small, it panics otherwise. Prefer `try_to_u64`.
+    fn to_degree(&self) -> DegreeType {
+        self.try_to_u64().unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    fn try_to_u64(&self) -> Result<u64, ConversionError> {
+        let value = self.to_integer();
+        value
+            .to_u64()
+            .ok_or_else(|| ConversionError::new(value, "u64"))
+    }
+
+    fn try_to_u32(&self) -> Result<u32, ConversionError> {
+        let value = self.to_integer();
+        value
+            .to_u32()
+            .ok_or_else(|| ConversionError::new(value, "u32"))
+    }
+
+    /// Returns the canonical representative of this element in `[0, modulus)`.
+    fn to_integer(&self) -> AbstractNumberType;
+
+    fn modulus() -> AbstractNumberType;
+
+    /// Returns the representative in `(-modulus / 2, modulus / 2]`,
+    /// i.e. values above half the modulus are treated as negative.
+    /// This is also how field elements are displayed.
+    fn to_signed_integer(&self) -> BigInt {
+        let value = self.to_integer();
+        let modulus = Self::modulus();
+        if value > (&modulus - 1u32) / 2u32 {
+            -BigInt::from(modulus - value)
+        } else {
+            value.into()
+        }
+    }
+
+    /// Returns the signed representative (see `to_signed_integer`)
+    /// if it fits into an `i64`.
+    fn to_signed_i64(&self) -> Option<i64> {
+        self.to_signed_integer().to_i64()
+    }
+
+    /// Converts a signed integer, mapping negative values to `modulus - |value|`.
+    fn from_i64(value: i64) -> Self {
+        let abs = Self::from(value.unsigned_abs());
+        if value < 0 {
+            -abs
+        } else {
+            abs
+        }
+    }
+
+    fn zero() -> Self;
+
+    fn one() -> Self;
+
+    fn is_zero(&self) -> bool;
+
+    fn pow(self, exponent: AbstractNumberType) -> Self;
+
+    /// Returns a uniformly distributed element.
+    /// Use `random::seeded_rng` for reproducible results.
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        rng.gen_biguint_below(&Self::modulus()).into()
+    }
+
+    /// Integer division of the canonical representatives.
+    fn integer_div(self, other: Self) -> Self;
+
+    // The following operations act on the canonical representatives.
+    // They avoid arbitrary precision integers if both operands fit into 64 bits.
+
+    /// Remainder of the integer division of the canonical representatives.
+    fn integer_mod(self, other: Self) -> Self {
+        match (self.try_to_u64(), other.try_to_u64()) {
+            (Ok(a), Ok(b)) => (a % b).into(),
+            _ => (self.to_integer() % other.to_integer()).into(),
+        }
+    }
+
+    fn bitand(self, other: Self) -> Self {
+        match (self.try_to_u64(), other.try_to_u64()) {
+            (Ok(a), Ok(b)) => (a & b).into(),
+            _ => (self.to_integer() & other.to_integer()).into(),
+        }
+    }
+
+    fn bitor(self, other: Self) -> Self {
+        match (self.try_to_u64(), other.try_to_u64()) {
+            (Ok(a), Ok(b)) => (a | b).into(),
+            _ => (self.to_integer() | other.to_integer()).into(),
+        }
+    }
+
+    fn bitxor(self, other: Self) -> Self {
+        match (self.try_to_u64(), other.try_to_u64()) {
+            (Ok(a), Ok(b)) => (a ^ b).into(),
+            _ => (self.to_integer() ^ other.to_integer()).into(),
+        }
+    }
+
+    /// Shifts the canonical representative to the left, reducing the result.
+    fn shl(self, amount: u64) -> Self {
+        match self.try_to_u64() {
+            Ok(a) if amount < 64 && a.leading_zeros() as u64 >= amount => (a << amount).into(),
+            _ => (self.to_integer() << amount).into(),
+        }
+    }
+
+    fn shr(self, amount: u64) -> Self {
+        match self.try_to_u64() {
+            Ok(a) => a
+                .checked_shr(amount.try_into().unwrap_or(u32::MAX))
+                .unwrap_or(0)
+                .into(),
+            Err(_) => (self.to_integer() >> amount).into(),
+        }
+    }
+
+    /// Shifts to the left by the canonical representative of `amount`,
+    /// which has to fit into 64 bits.
+    fn try_shl(self, amount: Self) -> Result<Self, ConversionError> {
+        let amount = amount
+            .try_to_u64()
+            .map_err(|e| e.with_context("shift amount"))?;
+        Ok(self.shl(amount))
+    }
+
+    /// Shifts to the right by the canonical representative of `amount`,
+    /// which has to fit into 64 bits.
+    fn try_shr(self, amount: Self) -> Result<Self, ConversionError> {
+        let amount = amount
+            .try_to_u64()
+            .map_err(|e| e.with_context("shift amount"))?;
+        Ok(self.shr(amount))
+    }
+
+    /// Returns the canonical encoding: The canonical representative
+    /// in little endian, padded to `byte_len()` bytes.
+    fn to_bytes_le(&self) -> Vec<u8>;
+
+    /// The number of bytes needed to encode any element.
+    fn byte_len() -> usize {
+        (Self::modulus().bits() as usize).div_ceil(8)
+    }
+
+    /// Decodes the canonical encoding produced by `to_bytes_le`,
+    /// rejecting inputs of the wrong length and values not below the modulus.
+    fn from_bytes_le(bytes: &[u8]) -> Result<Self, DecodeError> {
+        if bytes.len() != Self::byte_len() {
+            return Err(DecodeError::Length {
+                expected: Self::byte_len(),
+                actual: bytes.len(),
+            });
+        }
+        let value = AbstractNumberType::from_bytes_le(bytes);
+        if value >= Self::modulus() {
+            return Err(DecodeError::NotCanonical(value));
+        }
+        Ok(value.into())
+    }
+
+    /// Returns the canonical encoding as a `0x`-prefixed big endian hex string
+    /// of `2 * byte_len()` digits.
+    fn to_hex(&self) -> String {
+        format!(
+            "0x{:0width$x}",
+            self.to_integer(),
+            width = 2 * Self::byte_len()
+        )
+    }
+
+    /// Decodes the encoding produced by `to_hex`, the prefix is optional.
+    fn from_hex(input: &str) -> Result<Self, DecodeError> {
+        let digits = input.strip_prefix("0x").unwrap_or(input);
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(DecodeError::InvalidHex(input.to_string()));
+        }
+        if digits.len() != 2 * Self::byte_len() {
+            return Err(DecodeError::Length {
+                expected: Self::byte_len(),
+                actual: digits.len().div_ceil(2),
+            });
+        }
+        let value = AbstractNumberType::from_str_radix(digits, 16).unwrap();
+        if value >= Self::modulus() {
+            return Err(DecodeError::NotCanonical(value));
+        }
+        Ok(value.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Bn254Field, GoldilocksField};
+
+    use super::*;
+
+    fn check_integer_operations<T: FieldElement>() {
+        let values = [0u64, 1, 3, 0xff, 0x1234_5678_9abc_def0, u64::MAX]
+            .into_iter()
+            .map(T::from)
+            .chain([T::from(-1), T::from(-2)]);
+        for a in values.clone() {
+            let x = a.to_integer();
+            for b in values.clone() {
+                let y = b.to_integer();
+                if !b.is_zero() {
+                    assert_eq!(a.integer_mod(b), T::from(&x % &y));
+                }
+                assert_eq!(a.bitand(b), T::from(&x & &y));
+                assert_eq!(a.bitor(b), T::from(&x | &y));
+                assert_eq!(a.bitxor(b), T::from(&x ^ &y));
+            }
+            for amount in [0, 1, 8, 63, 64, 65, 200] {
+                assert_eq!(a.shl(amount), T::from(&x << amount));
+                assert_eq!(a.shr(amount), T::from(&x >> amount));
+            }
+        }
+    }
+
+    #[test]
+    fn integer_operations() {
+        check_integer_operations::<GoldilocksField>();
+        check_integer_operations::<Bn254Field>();
+    }
+}
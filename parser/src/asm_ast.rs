@@ -1,9 +1,9 @@
 use number::AbstractNumberType;
 
-use super::ast::{Expression, SelectedExpressions, Statement};
+use super::ast::{Expression, PolynomialReference, SelectedExpressions, Statement};
 
 #[derive(Debug, PartialEq, Eq)]
-pub struct ASMFile(pub Vec<ASMStatement>);
+pub struct ASMFile<T>(pub Vec<ASMStatement<T>>);
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct InstructionParamList {
@@ -29,25 +29,130 @@ impl InstructionParams {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub enum ASMStatement {
+pub enum ASMStatement<T> {
     Degree(usize, AbstractNumberType),
     RegisterDeclaration(usize, String, Option<RegisterFlag>),
     InstructionDeclaration(
         usize,
         String,
         InstructionParams,
-        Vec<InstructionBodyElement>,
+        Vec<InstructionBodyElement<T>>,
     ),
-    InlinePil(usize, Vec<Statement>),
-    Assignment(usize, Vec<String>, Option<String>, Box<Expression>),
-    Instruction(usize, String, Vec<Expression>),
+    /// `jump_table name X: l0, l1, l2;`: declares the instruction `name X`
+    /// that jumps to the label at the position given by the value of the
+    /// assignment register `X` in the list.
+    JumpTable(usize, String, String, Vec<String>),
+    /// `pil { ... }`: PIL that can use the registers by name and the flags of
+    /// the instructions as `instr.name`.
+    InlinePil(usize, Vec<Statement<T>>),
+    /// `machine Name(params) { ... }`, PIL that is placed in the namespace of
+    /// each instance of the machine, with the parameters replaced.
+    MachineDefinition(usize, String, Vec<String>, Vec<Statement<T>>),
+    /// `name: Machine(args);`, an instance of a machine in the namespace `name`.
+    MachineInstance(usize, String, String, Vec<Expression<T>>),
+    /// `A, B <=X, Y= value;`: writes to registers through assignment registers,
+    /// one for each output if the value is an instruction call.
+    Assignment(usize, Vec<String>, Option<Vec<String>>, Box<Expression<T>>),
+    Instruction(usize, String, Vec<Expression<T>>),
+    /// `call l;`: jumps to the label and stores the return address.
+    Call(usize, String),
+    /// `return;`: jumps to the stored return address.
+    Return(usize),
+    /// `halt;`: ends the program, what happens in the remaining rows is
+    /// declared by `on_halt`.
+    Halt(usize),
+    /// `on_halt loop;`, `on_halt jump label;` or `on_halt padding;`.
+    OnHalt(usize, HaltBehavior),
+    /// `export label;`: makes the label an entry point of the program, the
+    /// entry points are numbered in the order of their declaration.
+    Export(usize, String),
+    /// `assert_eq left, right, "message";`: constrains the values to be equal
+    /// and reports the message if they are not during witness generation.
+    Assert(usize, Expression<T>, Expression<T>, String),
+    /// A label, local to the code after the previous non-local label if the
+    /// name starts with a dot.
     Label(usize, String),
+    DebugDirective(usize, DebugDirective),
+    /// `%macro name(params) { ... }`, the code is inserted by `name args;`
+    /// with the parameters replaced by the arguments.
+    MacroDefinition(usize, String, Vec<String>, Vec<ASMStatement<T>>),
+    /// `%repeat i in start..end { ... }`, the code is inserted once for each
+    /// value of `i` from `start` (inclusive) to `end` (exclusive).
+    Repeat(
+        usize,
+        String,
+        AbstractNumberType,
+        AbstractNumberType,
+        Vec<ASMStatement<T>>,
+    ),
+    /// `#if NAME ... #else ... #endif`, like `Statement::Conditional`.
+    Conditional(usize, String, Vec<ASMStatement<T>>, Vec<ASMStatement<T>>),
+}
+
+impl<T: Clone> ASMStatement<T> {
+    /// Creates an instruction call, or `Call`, `Return`, `Halt` and `Assert`
+    /// for the built-in `call`, `return`, `halt` and `assert_eq` with a
+    /// message, which are not keywords so they can still be used as names in
+    /// PIL.
+    pub fn instruction(start: usize, name: String, args: Vec<Expression<T>>) -> Self {
+        match (name.as_str(), &args[..]) {
+            (
+                "call",
+                [Expression::PolynomialReference(PolynomialReference {
+                    namespace: None,
+                    name: label,
+                    index: None,
+                    next: false,
+                })],
+            ) => ASMStatement::Call(start, label.clone()),
+            ("return", []) => ASMStatement::Return(start),
+            ("halt", []) => ASMStatement::Halt(start),
+            ("assert_eq", [left, right, Expression::String(message)]) => {
+                ASMStatement::Assert(start, left.clone(), right.clone(), message.clone())
+            }
+            _ => ASMStatement::Instruction(start, name, args),
+        }
+    }
+}
+
+/// What happens in the rows after the program executed `halt`.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub enum HaltBehavior {
+    /// The pc stays at the `halt`.
+    #[default]
+    Loop,
+    /// The pc jumps to the label.
+    Jump(String),
+    /// The pc stays at the `halt` and the witness column `is_padding` is one
+    /// in the rows after it, so that constraints can be disabled there.
+    Padding,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DebugDirective {
+    /// Declares a source file: id, directory, file name.
+    File(usize, String, String),
+    /// Source location of the following code: file id, line, column.
+    Loc(usize, usize, usize),
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum RegisterFlag {
     IsPC,
     IsAssignment,
+    /// The register `call` stores the return address in.
+    IsReturnAddress,
+    /// A register that is zero in the next row if it is not written.
+    /// Registers without flag keep their value instead.
+    ResetToZero,
+    /// A register that is unconstrained in the next row if it is not written.
+    Unconstrained,
+    /// A register that keeps its initial value (zero if not given) and
+    /// cannot be written.
+    ReadOnly(Option<AbstractNumberType>),
+    /// A register that keeps its value if it is not written, like registers
+    /// without flag, and that functions have to restore before they return.
+    CalleeSaved,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -57,9 +162,13 @@ pub struct InstructionParam {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub enum InstructionBodyElement {
-    Expression(Expression),
-    PlookupIdentity(SelectedExpressions, PlookupOperator, SelectedExpressions),
+pub enum InstructionBodyElement<T> {
+    Expression(Expression<T>),
+    PlookupIdentity(
+        SelectedExpressions<T>,
+        PlookupOperator,
+        SelectedExpressions<T>,
+    ),
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
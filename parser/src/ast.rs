@@ -1,67 +1,112 @@
 use number::{DegreeType, FieldElement};
 
 #[derive(Debug, PartialEq, Eq)]
-pub struct PILFile(pub Vec<Statement>);
+pub struct PILFile<T>(pub Vec<Statement<T>>);
 
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub enum Statement {
+pub enum Statement<T> {
     /// File name
     Include(usize, String),
     /// Name of namespace and polynomial degree (constant)
-    Namespace(usize, String, Expression),
-    PolynomialDefinition(usize, String, Expression),
-    PublicDeclaration(usize, String, PolynomialReference, Expression),
-    PolynomialConstantDeclaration(usize, Vec<PolynomialName>),
-    PolynomialConstantDefinition(usize, String, FunctionDefinition),
-    PolynomialCommitDeclaration(usize, Vec<PolynomialName>, Option<FunctionDefinition>),
-    PolynomialIdentity(usize, Expression),
-    PlookupIdentity(usize, SelectedExpressions, SelectedExpressions),
-    PermutationIdentity(usize, SelectedExpressions, SelectedExpressions),
-    ConnectIdentity(usize, Vec<Expression>, Vec<Expression>),
-    ConstantDefinition(usize, String, Expression),
+    Namespace(usize, String, Expression<T>),
+    PolynomialDefinition(usize, String, Expression<T>),
+    PublicDeclaration(usize, String, PolynomialReference<T>, Expression<T>),
+    PolynomialConstantDeclaration(usize, Vec<PolynomialName<T>>),
+    PolynomialConstantDefinition(usize, String, FunctionDefinition<T>),
+    PolynomialCommitDeclaration(usize, Vec<PolynomialName<T>>, Option<FunctionDefinition<T>>),
+    PolynomialIdentity(usize, Expression<T>),
+    PlookupIdentity(usize, SelectedExpressions<T>, SelectedExpressions<T>),
+    /// A lookup proven with logarithmic derivatives, the last element is
+    /// the witness column holding the multiplicities of the right hand side.
+    LogUpIdentity(
+        usize,
+        SelectedExpressions<T>,
+        SelectedExpressions<T>,
+        Expression<T>,
+    ),
+    PermutationIdentity(usize, SelectedExpressions<T>, SelectedExpressions<T>),
+    ConnectIdentity(usize, Vec<Expression<T>>, Vec<Expression<T>>),
+    ConstantDefinition(usize, String, Expression<T>),
     MacroDefinition(
         usize,
         String,
         Vec<String>,
-        Vec<Statement>,
-        Option<Expression>,
+        Vec<Statement<T>>,
+        Option<Expression<T>>,
     ),
-    FunctionCall(usize, String, Vec<Expression>),
+    FunctionCall(usize, String, Vec<Expression<T>>),
+    /// `#if NAME ... #else ... #endif`, the statements of the first branch
+    /// are used if `NAME` is defined at analysis time, otherwise the ones of
+    /// the second branch.
+    Conditional(usize, String, Vec<Statement<T>>, Vec<Statement<T>>),
+    /// `#[test] name(degree) { ... }`, the statements are only used if the
+    /// test `name` is run, with the degree of all namespaces replaced.
+    /// With `#[should_fail]` after `#[test]`, the test passes if the witness
+    /// cannot be generated or violates the identities.
+    Test(usize, String, Expression<T>, bool, Vec<Statement<T>>),
+}
+
+impl<T> Statement<T> {
+    /// The offset of the statement in its file.
+    pub fn start(&self) -> usize {
+        match self {
+            Statement::Include(start, ..)
+            | Statement::Namespace(start, ..)
+            | Statement::PolynomialDefinition(start, ..)
+            | Statement::PublicDeclaration(start, ..)
+            | Statement::PolynomialConstantDeclaration(start, ..)
+            | Statement::PolynomialConstantDefinition(start, ..)
+            | Statement::PolynomialCommitDeclaration(start, ..)
+            | Statement::PolynomialIdentity(start, ..)
+            | Statement::PlookupIdentity(start, ..)
+            | Statement::LogUpIdentity(start, ..)
+            | Statement::PermutationIdentity(start, ..)
+            | Statement::ConnectIdentity(start, ..)
+            | Statement::ConstantDefinition(start, ..)
+            | Statement::MacroDefinition(start, ..)
+            | Statement::FunctionCall(start, ..)
+            | Statement::Conditional(start, ..)
+            | Statement::Test(start, ..) => *start,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub struct SelectedExpressions {
-    pub selector: Option<Expression>,
-    pub expressions: Vec<Expression>,
+pub struct SelectedExpressions<T> {
+    pub selector: Option<Expression<T>>,
+    pub expressions: Vec<Expression<T>>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub enum Expression {
+pub enum Expression<T> {
     /// Reference to a constant, "%ConstantName"
     Constant(String),
-    PolynomialReference(PolynomialReference),
+    PolynomialReference(PolynomialReference<T>),
     PublicReference(String),
-    Number(FieldElement),
+    Number(T),
     String(String),
-    Tuple(Vec<Expression>),
-    BinaryOperation(Box<Expression>, BinaryOperator, Box<Expression>),
-    UnaryOperation(UnaryOperator, Box<Expression>),
-    FunctionCall(String, Vec<Expression>),
-    FreeInput(Box<Expression>),
-    MatchExpression(Box<Expression>, Vec<(Option<Expression>, Expression)>),
+    Tuple(Vec<Expression<T>>),
+    BinaryOperation(Box<Expression<T>>, BinaryOperator, Box<Expression<T>>),
+    UnaryOperation(UnaryOperator, Box<Expression<T>>),
+    FunctionCall(String, Vec<Expression<T>>),
+    FreeInput(Box<Expression<T>>),
+    MatchExpression(
+        Box<Expression<T>>,
+        Vec<(Option<Expression<T>>, Expression<T>)>,
+    ),
 }
 
 #[derive(Debug, PartialEq, Eq, Default, Clone)]
-pub struct PolynomialName {
+pub struct PolynomialName<T> {
     pub name: String,
-    pub array_size: Option<Expression>,
+    pub array_size: Option<Expression<T>>,
 }
 
 #[derive(Debug, PartialEq, Eq, Default, Clone)]
-pub struct PolynomialReference {
+pub struct PolynomialReference<T> {
     pub namespace: Option<String>,
     pub name: String,
-    pub index: Option<Box<Expression>>,
+    pub index: Option<Box<Expression<T>>>,
     pub next: bool,
 }
 
@@ -89,28 +134,28 @@ pub enum BinaryOperator {
 /// The definition of a function (excluding its name):
 /// Either a param-value mapping or an array expression.
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub enum FunctionDefinition {
+pub enum FunctionDefinition<T> {
     /// Parameter-value-mapping.
-    Mapping(Vec<String>, Expression),
+    Mapping(Vec<String>, Expression<T>),
     /// Array expression.
-    Array(ArrayExpression),
+    Array(ArrayExpression<T>),
     /// Prover query.
-    Query(Vec<String>, Expression),
+    Query(Vec<String>, Expression<T>),
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub enum ArrayExpression {
-    Value(Vec<Expression>),
-    RepeatedValue(Vec<Expression>),
-    Concat(Box<ArrayExpression>, Box<ArrayExpression>),
+pub enum ArrayExpression<T> {
+    Value(Vec<Expression<T>>),
+    RepeatedValue(Vec<Expression<T>>),
+    Concat(Box<ArrayExpression<T>>, Box<ArrayExpression<T>>),
 }
 
-impl ArrayExpression {
-    pub fn value(v: Vec<Expression>) -> Self {
+impl<T: FieldElement> ArrayExpression<T> {
+    pub fn value(v: Vec<Expression<T>>) -> Self {
         Self::Value(v)
     }
 
-    pub fn repeated_value(v: Vec<Expression>) -> Self {
+    pub fn repeated_value(v: Vec<Expression<T>>) -> Self {
         Self::RepeatedValue(v)
     }
 
@@ -118,7 +163,7 @@ impl ArrayExpression {
         Self::Concat(Box::new(self), Box::new(other))
     }
 
-    fn pad_with(self, pad: Expression) -> Self {
+    fn pad_with(self, pad: Expression<T>) -> Self {
         Self::concat(self, Self::repeated_value(vec![pad]))
     }
 
@@ -126,7 +171,7 @@ impl ArrayExpression {
         self.pad_with(Expression::Number(0.into()))
     }
 
-    fn last(&self) -> Option<&Expression> {
+    fn last(&self) -> Option<&Expression<T>> {
         match self {
             ArrayExpression::Value(v) => v.last(),
             ArrayExpression::RepeatedValue(v) => v.last(),
@@ -140,40 +185,42 @@ impl ArrayExpression {
     }
 }
 
-impl ArrayExpression {
+impl<T> ArrayExpression<T> {
     /// solve for `*`
-    pub fn solve(&self, degree: DegreeType) -> Option<DegreeType> {
-        assert!(degree > 0, "Degree cannot be zero.");
+    pub fn solve(&self, degree: DegreeType) -> Result<Option<DegreeType>, String> {
+        if degree == 0 {
+            return Err("Degree cannot be zero.".to_string());
+        }
         // the length of this expression is `a + b*x`
-        let (a, b) = self.len();
+        let (a, b) = self.len()?;
         // it must match `degree`, and we solve for `x`
         if b == 0 {
-            None
-        } else {
-            assert!(
-                a <= degree,
+            Ok(None)
+        } else if a > degree {
+            Err(format!(
                 "Array literal is too large ({a}) for degree ({degree})."
-            );
-            assert_eq!((degree - a) % b, 0, "Cannot find a suitable value for `*`");
-            Some((degree - a) / b)
+            ))
+        } else if !(degree - a).is_multiple_of(b) {
+            Err("Cannot find a suitable value for `*`".to_string())
+        } else {
+            Ok(Some((degree - a) / b))
         }
     }
 
     /// find the total length of an array expression as an affine expression: `a + b*x`
-    fn len(&self) -> (DegreeType, DegreeType) {
+    fn len(&self) -> Result<(DegreeType, DegreeType), String> {
         match self {
-            ArrayExpression::RepeatedValue(e) => (0, e.len() as DegreeType),
-            ArrayExpression::Value(e) => (e.len() as DegreeType, 0),
+            ArrayExpression::RepeatedValue(e) => Ok((0, e.len() as DegreeType)),
+            ArrayExpression::Value(e) => Ok((e.len() as DegreeType, 0)),
             ArrayExpression::Concat(left, right) => {
-                let (a0, b0) = left.len();
-                let (a1, b1) = right.len();
+                let (a0, b0) = left.len()?;
+                let (a1, b1) = right.len()?;
 
-                assert!(
-                    b0 == 0 || b1 == 0,
-                    "`*` can be used only once in rhs of array definition"
-                );
+                if b0 != 0 && b1 != 0 {
+                    return Err("`*` can be used only once in rhs of array definition".to_string());
+                }
 
-                (a0 + a1, b0 + b1)
+                Ok((a0 + a1, b0 + b1))
             }
         }
     }
@@ -1,12 +1,14 @@
 use std::fmt::{Display, Formatter, Result};
 
+use number::FieldElement;
 use parser_util::quote;
 
+use super::asm_ast::*;
 use super::ast::*;
 
 // TODO indentation
 
-impl Display for PILFile {
+impl<T: FieldElement> Display for PILFile<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         for s in &self.0 {
             writeln!(f, "{s}")?;
@@ -15,7 +17,7 @@ impl Display for PILFile {
     }
 }
 
-impl Display for Statement {
+impl<T: FieldElement> Display for Statement<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         match self {
             Statement::Include(_, path) => write!(f, "include {};", quote(path)),
@@ -50,6 +52,9 @@ impl Display for Statement {
                 }
             }
             Statement::PlookupIdentity(_, left, right) => write!(f, "{left} in {right};"),
+            Statement::LogUpIdentity(_, left, right, multiplicity) => {
+                write!(f, "{left} in {right} multiplicity {multiplicity};")
+            }
             Statement::PermutationIdentity(_, left, right) => write!(f, "{left} is {right};"),
             Statement::ConnectIdentity(_, left, right) => write!(
                 f,
@@ -76,11 +81,22 @@ impl Display for Statement {
             Statement::FunctionCall(_, name, args) => {
                 write!(f, "{name}({});", format_expressions(args))
             }
+            Statement::Conditional(_, name, then, otherwise) => {
+                write!(f, "{}", format_conditional(name, then, otherwise))
+            }
+            Statement::Test(_, name, degree, should_fail, body) => {
+                let should_fail = if *should_fail { " #[should_fail]" } else { "" };
+                write!(
+                    f,
+                    "#[test]{should_fail} {name}({degree}) {{\n{}}}",
+                    indent_statements(body)
+                )
+            }
         }
     }
 }
 
-fn format_names(names: &[PolynomialName]) -> String {
+fn format_names<T: FieldElement>(names: &[PolynomialName<T>]) -> String {
     names
         .iter()
         .map(|n| format!("{n}"))
@@ -88,7 +104,7 @@ fn format_names(names: &[PolynomialName]) -> String {
         .join(", ")
 }
 
-impl Display for ArrayExpression {
+impl<T: FieldElement> Display for ArrayExpression<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         match self {
             ArrayExpression::Value(expressions) => {
@@ -102,7 +118,7 @@ impl Display for ArrayExpression {
     }
 }
 
-impl Display for FunctionDefinition {
+impl<T: FieldElement> Display for FunctionDefinition<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         match self {
             FunctionDefinition::Mapping(params, body) => {
@@ -118,7 +134,7 @@ impl Display for FunctionDefinition {
     }
 }
 
-impl Display for SelectedExpressions {
+impl<T: FieldElement> Display for SelectedExpressions<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         write!(
             f,
@@ -132,7 +148,7 @@ impl Display for SelectedExpressions {
     }
 }
 
-fn format_expressions(expressions: &[Expression]) -> String {
+fn format_expressions<T: FieldElement>(expressions: &[Expression<T>]) -> String {
     expressions
         .iter()
         .map(|e| format!("{e}"))
@@ -140,13 +156,14 @@ fn format_expressions(expressions: &[Expression]) -> String {
         .join(", ")
 }
 
-impl Display for Expression {
+impl<T: FieldElement> Display for Expression<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         match self {
             Expression::Constant(name) => write!(f, "{name}"),
             Expression::PolynomialReference(reference) => write!(f, "{reference}"),
             Expression::PublicReference(name) => write!(f, "{name}"),
-            Expression::Number(value) => write!(f, "{value}"),
+            // Independent of the configured display format, so that the output can be parsed again.
+            Expression::Number(value) => write!(f, "{}", value.to_signed_integer()),
             Expression::String(value) => write!(f, "\"{value}\""), // TODO quote?
             Expression::Tuple(items) => write!(f, "({})", format_expressions(items)),
             Expression::BinaryOperation(left, op, right) => write!(f, "({left} {op} {right})"),
@@ -170,7 +187,7 @@ impl Display for Expression {
     }
 }
 
-impl Display for PolynomialName {
+impl<T: FieldElement> Display for PolynomialName<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         write!(
             f,
@@ -184,7 +201,7 @@ impl Display for PolynomialName {
     }
 }
 
-impl Display for PolynomialReference {
+impl<T: FieldElement> Display for PolynomialReference<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         write!(
             f,
@@ -238,8 +255,219 @@ impl Display for UnaryOperator {
     }
 }
 
+impl<T: FieldElement> Display for ASMFile<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        for s in &self.0 {
+            writeln!(f, "{s}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: FieldElement> Display for ASMStatement<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            ASMStatement::Degree(_, degree) => write!(f, "degree {degree};"),
+            ASMStatement::RegisterDeclaration(_, name, flag) => write!(
+                f,
+                "reg {name}{};",
+                flag.as_ref()
+                    .map(|flag| format!("[{flag}]"))
+                    .unwrap_or_default()
+            ),
+            ASMStatement::InstructionDeclaration(_, name, params, body) => write!(
+                f,
+                "instr {name}{params} {{ {} }}",
+                body.iter()
+                    .map(|e| format!("{e}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            ASMStatement::JumpTable(_, name, register, labels) => {
+                write!(f, "jump_table {name} {register}: {};", labels.join(", "))
+            }
+            ASMStatement::InlinePil(_, statements) => {
+                write!(f, "pil {{\n{}}}", indent_statements(statements))
+            }
+            ASMStatement::MachineDefinition(_, name, params, body) => write!(
+                f,
+                "machine {name}({}) {{\n{}}}",
+                params.join(", "),
+                indent_statements(body)
+            ),
+            ASMStatement::MachineInstance(_, name, machine, args) => {
+                write!(f, "{name}: {machine}({});", format_expressions(args))
+            }
+            ASMStatement::Assignment(_, write_regs, assignment_reg, value) => write!(
+                f,
+                "{} <={}= {value};",
+                write_regs.join(", "),
+                assignment_reg
+                    .as_ref()
+                    .map(|regs| regs.join(", "))
+                    .unwrap_or_default()
+            ),
+            ASMStatement::Instruction(_, name, args) if args.is_empty() => write!(f, "{name};"),
+            ASMStatement::Instruction(_, name, args) => {
+                write!(f, "{name} {};", format_expressions(args))
+            }
+            ASMStatement::Call(_, label) => write!(f, "call {label};"),
+            ASMStatement::Return(_) => write!(f, "return;"),
+            ASMStatement::Halt(_) => write!(f, "halt;"),
+            ASMStatement::OnHalt(_, behavior) => write!(f, "on_halt {behavior};"),
+            ASMStatement::Export(_, label) => write!(f, "export {label};"),
+            ASMStatement::Assert(_, left, right, message) => {
+                write!(f, "assert_eq {left}, {right}, \"{message}\";")
+            }
+            ASMStatement::Label(_, name) => write!(f, "{name}::"),
+            ASMStatement::DebugDirective(_, directive) => write!(f, "{directive}"),
+            ASMStatement::MacroDefinition(_, name, params, body) => write!(
+                f,
+                "%macro {name}({}) {{\n{}}}",
+                params.join(", "),
+                indent_statements(body)
+            ),
+            ASMStatement::Repeat(_, var, start, end, body) => write!(
+                f,
+                "%repeat {var} in {start}..{end} {{\n{}}}",
+                indent_statements(body)
+            ),
+            ASMStatement::Conditional(_, name, then, otherwise) => {
+                write!(f, "{}", format_conditional(name, then, otherwise))
+            }
+        }
+    }
+}
+
+fn format_conditional<S: Display>(name: &str, then: &[S], otherwise: &[S]) -> String {
+    let otherwise = if otherwise.is_empty() {
+        String::new()
+    } else {
+        format!("#else\n{}", indent_statements(otherwise))
+    };
+    format!("#if {name}\n{}{otherwise}#endif", indent_statements(then))
+}
+
+fn indent_statements<S: Display>(statements: &[S]) -> String {
+    statements
+        .iter()
+        .flat_map(|s| {
+            s.to_string()
+                .lines()
+                .map(|line| format!("    {line}\n"))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+impl Display for HaltBehavior {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            HaltBehavior::Loop => write!(f, "loop"),
+            HaltBehavior::Jump(label) => write!(f, "jump {label}"),
+            HaltBehavior::Padding => write!(f, "padding"),
+        }
+    }
+}
+
+impl Display for RegisterFlag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            RegisterFlag::IsPC => write!(f, "@pc"),
+            RegisterFlag::IsAssignment => write!(f, "<="),
+            RegisterFlag::IsReturnAddress => write!(f, "@ra"),
+            RegisterFlag::ResetToZero => write!(f, "@reset"),
+            RegisterFlag::Unconstrained => write!(f, "@free"),
+            RegisterFlag::ReadOnly(None) => write!(f, "@readonly"),
+            RegisterFlag::ReadOnly(Some(value)) => write!(f, "@readonly = {value}"),
+            RegisterFlag::CalleeSaved => write!(f, "@saved"),
+        }
+    }
+}
+
+/// Includes the leading space if there are parameters.
+impl Display for InstructionParams {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        if !self.inputs.params.is_empty() {
+            write!(f, " {}", self.inputs)?;
+        }
+        if let Some(outputs) = &self.outputs {
+            write!(f, " -> {outputs}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for InstructionParamList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(
+            f,
+            "{}",
+            self.params
+                .iter()
+                .map(|p| format!("{p}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+impl Display for InstructionParam {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(
+            f,
+            "{}{}",
+            self.name,
+            self.ty
+                .as_ref()
+                .map(|ty| format!(": {ty}"))
+                .unwrap_or_default()
+        )
+    }
+}
+
+impl<T: FieldElement> Display for InstructionBodyElement<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            InstructionBodyElement::Expression(Expression::BinaryOperation(
+                left,
+                BinaryOperator::Sub,
+                right,
+            )) => write!(f, "{left} = {right}"),
+            InstructionBodyElement::Expression(expression) => write!(f, "{expression} = 0"),
+            InstructionBodyElement::PlookupIdentity(left, operator, right) => {
+                write!(f, "{left} {operator} {right}")
+            }
+        }
+    }
+}
+
+impl Display for PlookupOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            PlookupOperator::In => write!(f, "in"),
+            PlookupOperator::Is => write!(f, "is"),
+        }
+    }
+}
+
+impl Display for DebugDirective {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            DebugDirective::File(nr, directory, file) => {
+                write!(f, ".debug file {nr} {} {};", quote(directory), quote(file))
+            }
+            DebugDirective::Loc(file, line, column) => {
+                write!(f, ".debug loc {file} {line} {column};")
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use number::GoldilocksField;
+
     use crate::parse;
 
     #[test]
@@ -265,21 +493,56 @@ macro on_regular_row(cond) { ((1 - ISLAST) * cond) = 0; };
 on_regular_row(constrain_equal_expr(x', y));
 on_regular_row(constrain_equal_expr(y', (x + y)));
 public out = y(%last_row);"#;
-        let printed = format!("{}", parse(Some("input"), input).unwrap());
+        let printed = format!(
+            "{}",
+            parse::<GoldilocksField>(Some("input"), input).unwrap()
+        );
         assert_eq!(input.trim(), printed.trim());
     }
 
     #[test]
     fn reparse_witness_query() {
         let input = r#"pol commit wit(i) query (x(i), y(i));"#;
-        let printed = format!("{}", parse(Some("input"), input).unwrap());
+        let printed = format!(
+            "{}",
+            parse::<GoldilocksField>(Some("input"), input).unwrap()
+        );
         assert_eq!(input.trim(), printed.trim());
     }
 
     #[test]
     fn reparse_strings_and_tuples() {
         let input = r#"constant %N = ("abc", 3);"#;
-        let printed = format!("{}", parse(Some("input"), input).unwrap());
+        let printed = format!(
+            "{}",
+            parse::<GoldilocksField>(Some("input"), input).unwrap()
+        );
+        assert_eq!(input.trim(), printed.trim());
+    }
+
+    #[test]
+    fn reparse_asm() {
+        let input = r#"degree 8;
+reg pc[@pc];
+reg X[<=];
+reg A;
+pil {
+    pol commit XInv;
+    (X * XInv) = 1;
+}
+instr jmp l: label { pc' = l }
+instr mload -> X { { A, X } in { m_addr, m_value } }
+instr assert_zero X { X = 0 }
+A <=X= (A + ${ ("input", 0) });
+start::
+.debug file 1 "dir" "file.rs";
+.debug loc 1 2 3;
+assert_zero A;
+jmp start;"#;
+        let printed = format!(
+            "{}",
+            crate::parse_asm::<GoldilocksField>(Some("input"), input).unwrap()
+        );
         assert_eq!(input.trim(), printed.trim());
     }
 }
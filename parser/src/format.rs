@@ -0,0 +1,1009 @@
+//! Formatter for PIL and powdr assembly files.
+//!
+//! The file is parsed and printed again with normalized whitespace, minimal
+//! parentheses and consistent indentation: Statements are indented inside
+//! namespaces, `pil` blocks, multi-line macros and after the first label of
+//! an assembly program. The `=` of consecutive polynomial identities is
+//! aligned. Comments, blank lines between statements and the spelling of
+//! number literals are kept. Comments inside of a statement are moved
+//! after it.
+//!
+//! As a safety net, the output is parsed again and has to be equal to the
+//! input when printed with the `Display` implementations.
+
+use std::collections::VecDeque;
+
+use number::{AbstractNumberType, FieldElement};
+use parser_util::{quote, ParseError};
+
+use crate::asm_ast::*;
+use crate::ast::*;
+
+const INDENT: &str = "    ";
+
+/// Formats the contents of a PIL file.
+pub fn format_pil<'a, T: FieldElement>(
+    file_name: Option<&str>,
+    input: &'a str,
+) -> Result<String, ParseError<'a>> {
+    let ast = crate::parse::<T>(file_name, input)?;
+    let mut formatter = Formatter::new(input);
+    formatter.pil_statements(&ast.0, 0, true);
+    let output = formatter.finish();
+    let reparsed = crate::parse::<T>(file_name, &output)
+        .unwrap_or_else(|err| panic!("Formatted output does not parse: {}", err.message()));
+    check_unchanged(input, &output, format!("{ast}"), format!("{reparsed}"));
+    Ok(output)
+}
+
+/// Formats the contents of a powdr assembly file.
+pub fn format_asm<'a, T: FieldElement>(
+    file_name: Option<&str>,
+    input: &'a str,
+) -> Result<String, ParseError<'a>> {
+    let ast = crate::parse_asm::<T>(file_name, input)?;
+    let mut formatter = Formatter::new(input);
+    formatter.asm_statements(&ast.0, 0, usize::MAX);
+    let output = formatter.finish();
+    let reparsed = crate::parse_asm::<T>(file_name, &output)
+        .unwrap_or_else(|err| panic!("Formatted output does not parse: {}", err.message()));
+    check_unchanged(input, &output, format!("{ast}"), format!("{reparsed}"));
+    Ok(output)
+}
+
+fn check_unchanged(input: &str, output: &str, printed: String, reprinted: String) {
+    assert_eq!(
+        printed, reprinted,
+        "Formatting changed the meaning of the file."
+    );
+    assert_eq!(
+        scan(input).0.len(),
+        scan(output).0.len(),
+        "Formatting lost comments."
+    );
+}
+
+struct Comment {
+    start: usize,
+    end: usize,
+    /// True if there is only whitespace before the comment on its line.
+    own_line: bool,
+}
+
+/// Returns the comments and the number literals of the input, in the
+/// order they appear. Follows the tokens of the lexer, so that comment
+/// markers inside of strings are not mistaken for comments.
+fn scan(input: &str) -> (Vec<Comment>, VecDeque<&str>) {
+    let bytes = input.as_bytes();
+    let mut comments = vec![];
+    let mut literals = VecDeque::new();
+    let mut i = 0;
+    let is_identifier = |b: u8| b.is_ascii_alphanumeric() || b"$_@".contains(&b);
+    while i < bytes.len() {
+        let start = i;
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') || bytes.get(i + 1) == Some(&b'*') => {
+                i = if bytes[i + 1] == b'/' {
+                    input[i..].find('\n').map(|n| i + n).unwrap_or(input.len())
+                } else {
+                    input[i + 2..]
+                        .find("*/")
+                        .map(|n| i + 2 + n + 2)
+                        .unwrap_or(input.len())
+                };
+                let line_start = input[..start].rfind('\n').map(|n| n + 1).unwrap_or(0);
+                comments.push(Comment {
+                    start,
+                    end: i,
+                    own_line: input[line_start..start].trim().is_empty(),
+                });
+            }
+            b'"' => {
+                i = input[i + 1..]
+                    .find('"')
+                    .map(|n| i + 1 + n + 1)
+                    .unwrap_or(input.len())
+            }
+            b'0'..=b'9' => {
+                i += 1;
+                while i < bytes.len() && (bytes[i].is_ascii_hexdigit() || b"_x".contains(&bytes[i]))
+                {
+                    i += 1;
+                }
+                literals.push_back(&input[start..i]);
+            }
+            b if b.is_ascii_alphabetic() || b == b'_' || b == b'%' => {
+                i += 1;
+                while i < bytes.len() && is_identifier(bytes[i]) {
+                    i += 1;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    (comments, literals)
+}
+
+fn parse_literal(literal: &str) -> Option<AbstractNumberType> {
+    let literal = literal.replace('_', "");
+    match literal.strip_prefix("0x") {
+        Some(hex) => AbstractNumberType::parse_bytes(hex.as_bytes(), 16),
+        None => AbstractNumberType::parse_bytes(literal.as_bytes(), 10),
+    }
+}
+
+/// The operator precedence levels of the grammar, higher binds stronger.
+const UNARY: u8 = 8;
+const TERM: u8 = 9;
+
+fn level<T>(e: &Expression<T>) -> u8 {
+    match e {
+        Expression::BinaryOperation(_, op, _) => operator_level(op),
+        Expression::UnaryOperation(..) => UNARY,
+        _ => TERM,
+    }
+}
+
+fn operator_level(op: &BinaryOperator) -> u8 {
+    match op {
+        BinaryOperator::BinaryOr => 1,
+        BinaryOperator::BinaryXor => 2,
+        BinaryOperator::BinaryAnd => 3,
+        BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => 4,
+        BinaryOperator::Add | BinaryOperator::Sub => 5,
+        BinaryOperator::Mul | BinaryOperator::Div | BinaryOperator::Mod => 6,
+        BinaryOperator::Pow => 7,
+    }
+}
+
+struct Formatter<'a> {
+    input: &'a str,
+    comments: Vec<Comment>,
+    next_comment: usize,
+    /// The spellings of the number literals not printed yet.
+    literals: VecDeque<&'a str>,
+    lines: Vec<String>,
+    /// Lines with polynomial identities that are aligned at their `=`,
+    /// by line index and the position of the `=`.
+    aligned: Vec<(usize, usize)>,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(input: &'a str) -> Self {
+        let (comments, literals) = scan(input);
+        Formatter {
+            input,
+            comments,
+            next_comment: 0,
+            literals,
+            lines: vec![],
+            aligned: vec![],
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.comments_before(self.input.len(), 0);
+        self.align();
+        self.lines
+            .iter()
+            .map(|line| format!("{}\n", line.trim_end()))
+            .collect()
+    }
+
+    fn push_line(&mut self, level: usize, text: String) {
+        self.align();
+        self.lines.push(format!("{}{text}", INDENT.repeat(level)));
+    }
+
+    fn push_identity(&mut self, level: usize, left: String, right: String) {
+        let line = format!("{}{left} = {right};", INDENT.repeat(level));
+        self.aligned
+            .push((self.lines.len(), INDENT.len() * level + left.len()));
+        self.lines.push(line);
+    }
+
+    /// Pads the current run of polynomial identities so that their `=` align.
+    fn align(&mut self) {
+        let width = self.aligned.iter().map(|(_, pos)| *pos).max();
+        for (line, pos) in std::mem::take(&mut self.aligned) {
+            let padding = " ".repeat(width.unwrap() - pos);
+            self.lines[line].insert_str(pos, &padding);
+        }
+    }
+
+    fn blank_line(&mut self) {
+        if self.lines.last().map(|l| !l.is_empty()).unwrap_or(false) {
+            self.align();
+            self.lines.push(String::new());
+        }
+    }
+
+    /// Adds a blank line if there is one before `offset` in the input.
+    fn blank_line_before(&mut self, offset: usize) {
+        let before = &self.input[..offset];
+        let whitespace = &before[before.trim_end().len()..];
+        if whitespace.matches('\n').count() >= 2 {
+            self.blank_line();
+        }
+    }
+
+    /// Prints the comments that start before `offset`.
+    fn comments_before(&mut self, offset: usize, level: usize) {
+        while self.next_comment < self.comments.len()
+            && self.comments[self.next_comment].start < offset
+        {
+            self.print_next_comment(level);
+        }
+    }
+
+    /// Prints the comments before the closing brace of the current block.
+    fn comments_in_block(&mut self, level: usize) {
+        while self.next_comment < self.comments.len() && self.closes_block(self.next_comment) {
+            self.print_next_comment(level);
+        }
+    }
+
+    /// Prints the comments before the closing brace of the current block if
+    /// they start before `end`, so that they are not taken from later blocks.
+    fn comments_in_block_before(&mut self, level: usize, end: usize) {
+        while self.next_comment < self.comments.len()
+            && self.comments[self.next_comment].start < end
+            && self.closes_block(self.next_comment)
+        {
+            self.print_next_comment(level);
+        }
+    }
+
+    /// Returns true if the next token after the comment is a closing brace,
+    /// `#else` or `#endif`.
+    fn closes_block(&self, comment: usize) -> bool {
+        let mut rest = &self.input[self.comments[comment].end..];
+        for next in &self.comments[comment + 1..] {
+            let skipped = &self.input[self.input.len() - rest.len()..next.start];
+            if !skipped.trim().is_empty() {
+                break;
+            }
+            rest = &self.input[next.end..];
+        }
+        let rest = rest.trim_start();
+        rest.starts_with('}') || rest.starts_with("#else") || rest.starts_with("#endif")
+    }
+
+    fn print_next_comment(&mut self, level: usize) {
+        let comment = &self.comments[self.next_comment];
+        let (start, own_line) = (comment.start, comment.own_line);
+        let text = self.input[comment.start..comment.end]
+            .trim_end()
+            .to_string();
+        self.next_comment += 1;
+        match self.lines.last_mut() {
+            Some(last) if !own_line && !last.is_empty() => {
+                last.push(' ');
+                last.push_str(&text);
+            }
+            _ => {
+                self.blank_line_before(start);
+                self.push_line(level, text);
+            }
+        }
+    }
+
+    /// Returns the first words of the statement at `start`, so that
+    /// `pol` and `col`, `commit` and `witness` and so on are kept as written.
+    fn keywords(&self, start: usize, count: usize) -> String {
+        self.input[start..]
+            .split_whitespace()
+            .take(count)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Returns the literal as written in the input if the next literal
+    /// has the given value, otherwise the value in decimal.
+    fn literal(
+        &mut self,
+        value: &AbstractNumberType,
+        modulus: Option<AbstractNumberType>,
+    ) -> String {
+        let matches = |literal: &&str| {
+            parse_literal(literal)
+                .map(|v| match &modulus {
+                    Some(modulus) => v % modulus == *value,
+                    None => v == *value,
+                })
+                .unwrap_or(false)
+        };
+        match self.literals.front() {
+            Some(literal) if matches(literal) => self.literals.pop_front().unwrap().to_string(),
+            _ => value.to_string(),
+        }
+    }
+
+    fn pil_statements<T: FieldElement>(
+        &mut self,
+        statements: &[Statement<T>],
+        level: usize,
+        top_level: bool,
+    ) {
+        let mut in_namespace = false;
+        for statement in statements {
+            let is_namespace = matches!(statement, Statement::Namespace(..));
+            let level = if top_level && in_namespace && !is_namespace {
+                level + 1
+            } else {
+                level
+            };
+            let start = pil_statement_start(statement);
+            let comments_before = self.next_comment;
+            self.comments_before(start, level);
+            if top_level && is_namespace && comments_before == self.next_comment {
+                self.blank_line();
+            } else {
+                self.blank_line_before(start);
+            }
+            in_namespace |= is_namespace;
+            self.pil_statement(statement, level);
+        }
+    }
+
+    fn pil_statement<T: FieldElement>(&mut self, statement: &Statement<T>, level: usize) {
+        match statement {
+            Statement::PolynomialIdentity(
+                _,
+                Expression::BinaryOperation(left, BinaryOperator::Sub, right),
+            ) => {
+                let left = self.expression(left);
+                let right = self.expression(right);
+                self.push_identity(level, left, right);
+            }
+            Statement::MacroDefinition(_, name, params, statements, expression)
+                if statements.len() + expression.iter().len() > 1
+                    || statements
+                        .iter()
+                        .any(|s| matches!(s, Statement::MacroDefinition(..))) =>
+            {
+                self.push_line(level, format!("macro {name}({}) {{", params.join(", ")));
+                self.pil_statements(statements, level + 1, false);
+                if let Some(expression) = expression {
+                    let expression = self.expression(expression);
+                    self.push_line(level + 1, expression);
+                }
+                self.comments_in_block(level + 1);
+                self.push_line(level, "};".to_string());
+            }
+            Statement::Conditional(_, name, then, otherwise) => {
+                self.push_line(level, format!("#if {name}"));
+                self.pil_statements(then, level + 1, false);
+                let else_start = otherwise.first().map_or(usize::MAX, pil_statement_start);
+                self.comments_in_block_before(level + 1, else_start);
+                if !otherwise.is_empty() {
+                    self.push_line(level, "#else".to_string());
+                    self.pil_statements(otherwise, level + 1, false);
+                    self.comments_in_block(level + 1);
+                }
+                self.push_line(level, "#endif".to_string());
+            }
+            Statement::Test(_, name, degree, should_fail, body) => {
+                let should_fail = if *should_fail { " #[should_fail]" } else { "" };
+                let degree = self.expression(degree);
+                self.push_line(level, format!("#[test]{should_fail} {name}({degree}) {{"));
+                self.pil_statements(body, level + 1, false);
+                self.comments_in_block(level + 1);
+                self.push_line(level, "}".to_string());
+            }
+            _ => {
+                let text = self.pil_statement_text(statement);
+                self.push_line(level, text);
+            }
+        }
+    }
+
+    /// Formats a statement that fits on a single line.
+    fn pil_statement_text<T: FieldElement>(&mut self, statement: &Statement<T>) -> String {
+        match statement {
+            Statement::Include(_, path) => format!("include {};", quote(path)),
+            Statement::Namespace(_, name, degree) => {
+                format!("namespace {name}({});", self.expression(degree))
+            }
+            Statement::PolynomialDefinition(start, name, value) => {
+                let keyword = self.keywords(*start, 1);
+                format!("{keyword} {name} = {};", self.expression(value))
+            }
+            Statement::PublicDeclaration(_, name, poly, index) => {
+                let poly = self.polynomial_reference(poly);
+                format!("public {name} = {poly}({});", self.expression(index))
+            }
+            Statement::PolynomialConstantDeclaration(start, names) => {
+                let keywords = self.keywords(*start, 2);
+                format!("{keywords} {};", self.polynomial_names(names))
+            }
+            Statement::PolynomialConstantDefinition(start, name, definition) => {
+                let keywords = self.keywords(*start, 2);
+                format!("{keywords} {name}{};", self.function_definition(definition))
+            }
+            Statement::PolynomialCommitDeclaration(start, names, value) => {
+                let keywords = self.keywords(*start, 2);
+                let names = self.polynomial_names(names);
+                let value = value
+                    .as_ref()
+                    .map(|v| self.function_definition(v))
+                    .unwrap_or_default();
+                format!("{keywords} {names}{value};")
+            }
+            Statement::PolynomialIdentity(
+                _,
+                Expression::BinaryOperation(left, BinaryOperator::Sub, right),
+            ) => {
+                let left = self.expression(left);
+                format!("{left} = {};", self.expression(right))
+            }
+            Statement::PolynomialIdentity(_, expression) => {
+                format!("{} = 0;", self.expression(expression))
+            }
+            Statement::PlookupIdentity(_, left, right) => {
+                let left = self.selected_expressions(left);
+                format!("{left} in {};", self.selected_expressions(right))
+            }
+            Statement::LogUpIdentity(_, left, right, multiplicity) => {
+                let left = self.selected_expressions(left);
+                let right = self.selected_expressions(right);
+                format!(
+                    "{left} in {right} multiplicity {};",
+                    self.expression(multiplicity)
+                )
+            }
+            Statement::PermutationIdentity(_, left, right) => {
+                let left = self.selected_expressions(left);
+                format!("{left} is {};", self.selected_expressions(right))
+            }
+            Statement::ConnectIdentity(_, left, right) => {
+                let left = self.expressions(left);
+                format!("{{ {left} }} connect {{ {} }};", self.expressions(right))
+            }
+            Statement::ConstantDefinition(_, name, value) => {
+                format!("constant {name} = {};", self.expression(value))
+            }
+            Statement::MacroDefinition(_, name, params, statements, expression) => {
+                let mut body = statements
+                    .iter()
+                    .map(|s| self.pil_statement_text(s))
+                    .collect::<String>();
+                if let Some(expression) = expression {
+                    body += &self.expression(expression);
+                }
+                let body = if body.is_empty() {
+                    " ".to_string()
+                } else {
+                    format!(" {body} ")
+                };
+                format!("macro {name}({}) {{{body}}};", params.join(", "))
+            }
+            Statement::FunctionCall(_, name, args) => {
+                format!("{name}({});", self.expressions(args))
+            }
+            Statement::Conditional(..) | Statement::Test(..) => {
+                unreachable!("Conditionals and tests are formatted on several lines.")
+            }
+        }
+    }
+
+    /// Formats the statements of a block whose closing brace is before `end`.
+    fn asm_statements<T: FieldElement>(
+        &mut self,
+        statements: &[ASMStatement<T>],
+        level: usize,
+        end: usize,
+    ) {
+        let mut after_label = false;
+        for (i, statement) in statements.iter().enumerate() {
+            let is_code = matches!(
+                statement,
+                ASMStatement::Assignment(..)
+                    | ASMStatement::Instruction(..)
+                    | ASMStatement::Call(..)
+                    | ASMStatement::Return(..)
+                    | ASMStatement::Halt(..)
+                    | ASMStatement::Assert(..)
+                    | ASMStatement::DebugDirective(..)
+                    | ASMStatement::Repeat(..)
+                    | ASMStatement::Conditional(..)
+            );
+            let level = level + usize::from(after_label && is_code);
+            let start = asm_statement_start(statement);
+            self.comments_before(start, level);
+            self.blank_line_before(start);
+            after_label |= matches!(statement, ASMStatement::Label(..));
+            let end = statements.get(i + 1).map_or(end, asm_statement_start);
+            self.asm_statement(statement, level, end);
+        }
+    }
+
+    /// Formats the statement, which ends before `end`.
+    fn asm_statement<T: FieldElement>(
+        &mut self,
+        statement: &ASMStatement<T>,
+        level: usize,
+        end: usize,
+    ) {
+        let text = match statement {
+            ASMStatement::Degree(_, degree) => format!("degree {};", self.literal(degree, None)),
+            ASMStatement::RegisterDeclaration(_, name, flag) => format!(
+                "reg {name}{};",
+                flag.as_ref()
+                    .map(|flag| format!("[{flag}]"))
+                    .unwrap_or_default()
+            ),
+            ASMStatement::JumpTable(_, name, register, labels) => {
+                format!("jump_table {name} {register}: {};", labels.join(", "))
+            }
+            ASMStatement::InstructionDeclaration(_, name, params, body) => {
+                let body = body
+                    .iter()
+                    .map(|element| match element {
+                        InstructionBodyElement::Expression(Expression::BinaryOperation(
+                            left,
+                            BinaryOperator::Sub,
+                            right,
+                        )) => {
+                            let left = self.expression(left);
+                            format!("{left} = {}", self.expression(right))
+                        }
+                        InstructionBodyElement::Expression(e) => {
+                            format!("{} = 0", self.expression(e))
+                        }
+                        InstructionBodyElement::PlookupIdentity(left, operator, right) => {
+                            let left = self.selected_expressions(left);
+                            format!("{left} {operator} {}", self.selected_expressions(right))
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                format!("instr {name}{params} {{ {} }}", body.join(", "))
+            }
+            ASMStatement::InlinePil(_, statements) => {
+                self.push_line(level, "pil {".to_string());
+                self.pil_statements(statements, level + 1, false);
+                self.comments_in_block(level + 1);
+                "}".to_string()
+            }
+            ASMStatement::MachineDefinition(_, name, params, body) => {
+                self.push_line(level, format!("machine {name}({}) {{", params.join(", ")));
+                self.pil_statements(body, level + 1, false);
+                self.comments_in_block_before(level + 1, end);
+                "}".to_string()
+            }
+            ASMStatement::MachineInstance(_, name, machine, args) => {
+                format!("{name}: {machine}({});", self.expressions(args))
+            }
+            ASMStatement::Assignment(_, write_regs, assignment_reg, value) => format!(
+                "{} <={}= {};",
+                write_regs.join(", "),
+                assignment_reg
+                    .as_ref()
+                    .map(|regs| regs.join(", "))
+                    .unwrap_or_default(),
+                self.expression(value)
+            ),
+            ASMStatement::Instruction(_, name, args) if args.is_empty() => format!("{name};"),
+            ASMStatement::Instruction(_, name, args) => {
+                format!("{name} {};", self.expressions(args))
+            }
+            ASMStatement::Call(_, label) => format!("call {label};"),
+            ASMStatement::Return(_) => "return;".to_string(),
+            ASMStatement::Halt(_) => "halt;".to_string(),
+            ASMStatement::OnHalt(_, behavior) => format!("on_halt {behavior};"),
+            ASMStatement::Export(_, label) => format!("export {label};"),
+            ASMStatement::Assert(_, left, right, message) => {
+                let left = self.expression(left);
+                format!(
+                    "assert_eq {left}, {}, \"{message}\";",
+                    self.expression(right)
+                )
+            }
+            ASMStatement::Label(_, name) => format!("{name}::"),
+            ASMStatement::DebugDirective(_, DebugDirective::File(nr, directory, file)) => {
+                format!(
+                    ".debug file {} {} {};",
+                    self.literal(&(*nr).into(), None),
+                    quote(directory),
+                    quote(file)
+                )
+            }
+            ASMStatement::MacroDefinition(_, name, params, body) => {
+                self.push_line(level, format!("%macro {name}({}) {{", params.join(", ")));
+                self.asm_statements(body, level + 1, end);
+                self.comments_in_block_before(level + 1, end);
+                "}".to_string()
+            }
+            ASMStatement::Repeat(_, var, from, to, body) => {
+                let from = self.literal(from, None);
+                let to = self.literal(to, None);
+                self.push_line(level, format!("%repeat {var} in {from}..{to} {{"));
+                self.asm_statements(body, level + 1, end);
+                self.comments_in_block_before(level + 1, end);
+                "}".to_string()
+            }
+            ASMStatement::Conditional(_, name, then, otherwise) => {
+                self.push_line(level, format!("#if {name}"));
+                let else_start = otherwise.first().map_or(end, asm_statement_start);
+                self.asm_statements(then, level + 1, else_start);
+                self.comments_in_block_before(level + 1, else_start);
+                if !otherwise.is_empty() {
+                    self.push_line(level, "#else".to_string());
+                    self.asm_statements(otherwise, level + 1, end);
+                    self.comments_in_block_before(level + 1, end);
+                }
+                "#endif".to_string()
+            }
+            ASMStatement::DebugDirective(_, DebugDirective::Loc(file, line, column)) => {
+                let file = self.literal(&(*file).into(), None);
+                let line = self.literal(&(*line).into(), None);
+                format!(
+                    ".debug loc {file} {line} {};",
+                    self.literal(&(*column).into(), None)
+                )
+            }
+        };
+        self.push_line(level, text);
+    }
+
+    fn expression<T: FieldElement>(&mut self, e: &Expression<T>) -> String {
+        match e {
+            Expression::Constant(name) => name.clone(),
+            Expression::PolynomialReference(reference) => self.polynomial_reference(reference),
+            Expression::PublicReference(name) => format!(":{name}"),
+            Expression::Number(value) => self.literal(&value.to_integer(), Some(T::modulus())),
+            Expression::String(value) => format!("\"{value}\""),
+            Expression::Tuple(items) => format!("({})", self.expressions(items)),
+            Expression::BinaryOperation(left, op, right) => {
+                let (left_level, right_level) = match operator_level(op) {
+                    // The grammar only allows `&` and stronger operators
+                    // as right operands of `|` and `^`.
+                    level @ (1 | 2) => (level, 3),
+                    level if *op == BinaryOperator::Pow => (level, TERM),
+                    level => (level, level + 1),
+                };
+                let left = self.operand(left, left_level);
+                format!("{left} {op} {}", self.operand(right, right_level))
+            }
+            Expression::UnaryOperation(op, e) => format!("{op}{}", self.operand(e, TERM)),
+            Expression::FunctionCall(name, args) => format!("{name}({})", self.expressions(args)),
+            Expression::FreeInput(input) => format!("${{ {} }}", self.expression(input)),
+            Expression::MatchExpression(scrutinee, arms) => {
+                let scrutinee = self.expression(scrutinee);
+                let arms = arms
+                    .iter()
+                    .map(|(n, e)| {
+                        let n = n
+                            .as_ref()
+                            .map(|n| self.expression(n))
+                            .unwrap_or_else(|| "_".to_string());
+                        format!("{n} => {}", self.expression(e))
+                    })
+                    .collect::<Vec<_>>();
+                format!("match {scrutinee} {{ {} }}", arms.join(", "))
+            }
+        }
+    }
+
+    /// Formats `e` with parentheses if it binds weaker than `min_level`.
+    fn operand<T: FieldElement>(&mut self, e: &Expression<T>, min_level: u8) -> String {
+        let formatted = self.expression(e);
+        if level(e) < min_level {
+            format!("({formatted})")
+        } else {
+            formatted
+        }
+    }
+
+    fn expressions<T: FieldElement>(&mut self, expressions: &[Expression<T>]) -> String {
+        expressions
+            .iter()
+            .map(|e| self.expression(e))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn selected_expressions<T: FieldElement>(
+        &mut self,
+        selected: &SelectedExpressions<T>,
+    ) -> String {
+        let selector = selected
+            .selector
+            .as_ref()
+            .map(|s| format!("{} ", self.operand(s, TERM)))
+            .unwrap_or_default();
+        format!(
+            "{selector}{{ {} }}",
+            self.expressions(&selected.expressions)
+        )
+    }
+
+    fn polynomial_reference<T: FieldElement>(
+        &mut self,
+        reference: &PolynomialReference<T>,
+    ) -> String {
+        format!(
+            "{}{}{}{}",
+            reference
+                .namespace
+                .as_ref()
+                .map(|n| format!("{n}."))
+                .unwrap_or_default(),
+            reference.name,
+            reference
+                .index
+                .as_ref()
+                .map(|i| format!("[{}]", self.expression(i)))
+                .unwrap_or_default(),
+            if reference.next { "'" } else { "" }
+        )
+    }
+
+    fn polynomial_names<T: FieldElement>(&mut self, names: &[PolynomialName<T>]) -> String {
+        names
+            .iter()
+            .map(|n| {
+                let size = n
+                    .array_size
+                    .as_ref()
+                    .map(|s| format!("[{}]", self.expression(s)))
+                    .unwrap_or_default();
+                format!("{}{size}", n.name)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn function_definition<T: FieldElement>(
+        &mut self,
+        definition: &FunctionDefinition<T>,
+    ) -> String {
+        match definition {
+            FunctionDefinition::Mapping(params, body) => {
+                format!("({}) {{ {} }}", params.join(", "), self.expression(body))
+            }
+            FunctionDefinition::Array(array) => format!(" = {}", self.array_expression(array)),
+            FunctionDefinition::Query(params, value) => {
+                format!("({}) query {}", params.join(", "), self.expression(value))
+            }
+        }
+    }
+
+    fn array_expression<T: FieldElement>(&mut self, array: &ArrayExpression<T>) -> String {
+        match array {
+            ArrayExpression::Value(items) => format!("[{}]", self.expressions(items)),
+            ArrayExpression::RepeatedValue(items) => format!("[{}]*", self.expressions(items)),
+            ArrayExpression::Concat(left, right) => {
+                let left = self.array_expression(left);
+                format!("{left} + {}", self.array_expression(right))
+            }
+        }
+    }
+}
+
+fn pil_statement_start<T>(statement: &Statement<T>) -> usize {
+    match statement {
+        Statement::Include(start, _)
+        | Statement::Namespace(start, _, _)
+        | Statement::PolynomialDefinition(start, _, _)
+        | Statement::PublicDeclaration(start, _, _, _)
+        | Statement::PolynomialConstantDeclaration(start, _)
+        | Statement::PolynomialConstantDefinition(start, _, _)
+        | Statement::PolynomialCommitDeclaration(start, _, _)
+        | Statement::PolynomialIdentity(start, _)
+        | Statement::PlookupIdentity(start, _, _)
+        | Statement::LogUpIdentity(start, _, _, _)
+        | Statement::PermutationIdentity(start, _, _)
+        | Statement::ConnectIdentity(start, _, _)
+        | Statement::ConstantDefinition(start, _, _)
+        | Statement::MacroDefinition(start, _, _, _, _)
+        | Statement::FunctionCall(start, _, _)
+        | Statement::Conditional(start, _, _, _)
+        | Statement::Test(start, _, _, _, _) => *start,
+    }
+}
+
+fn asm_statement_start<T>(statement: &ASMStatement<T>) -> usize {
+    match statement {
+        ASMStatement::Degree(start, _)
+        | ASMStatement::RegisterDeclaration(start, _, _)
+        | ASMStatement::InstructionDeclaration(start, _, _, _)
+        | ASMStatement::JumpTable(start, _, _, _)
+        | ASMStatement::InlinePil(start, _)
+        | ASMStatement::MachineDefinition(start, _, _, _)
+        | ASMStatement::MachineInstance(start, _, _, _)
+        | ASMStatement::Assignment(start, _, _, _)
+        | ASMStatement::Instruction(start, _, _)
+        | ASMStatement::Call(start, _)
+        | ASMStatement::Return(start)
+        | ASMStatement::Halt(start)
+        | ASMStatement::OnHalt(start, _)
+        | ASMStatement::Export(start, _)
+        | ASMStatement::Assert(start, _, _, _)
+        | ASMStatement::Label(start, _)
+        | ASMStatement::DebugDirective(start, _)
+        | ASMStatement::MacroDefinition(start, _, _, _)
+        | ASMStatement::Repeat(start, _, _, _, _)
+        | ASMStatement::Conditional(start, _, _, _) => *start,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+
+    use super::*;
+
+    fn format_pil_string(input: &str) -> String {
+        format_pil::<GoldilocksField>(None, input).unwrap()
+    }
+
+    #[test]
+    fn pil_layout() {
+        let input = r#"constant %N = 16;
+// The fibonacci sequence.
+namespace Fibonacci(%N);
+col fixed ISLAST(i) { match i { %N - 1 => 1, _ => 0, } };
+    col witness x,y;  // two columns
+
+
+    ISLAST*(y'-1)=0;
+    (1-ISLAST) * (x' - y) = 0;
+  public out = y(%N-1);
+namespace Other(0x10);
+  macro bool(X) { X * (1 - X) = 0; };
+  macro two(X) {
+    /* inside */
+    bool(X); X * 2
+  };
+"#;
+        let expected = r#"constant %N = 16;
+// The fibonacci sequence.
+namespace Fibonacci(%N);
+    col fixed ISLAST(i) { match i { %N - 1 => 1, _ => 0 } };
+    col witness x, y; // two columns
+
+    ISLAST * (y' - 1)       = 0;
+    (1 - ISLAST) * (x' - y) = 0;
+    public out = y(%N - 1);
+
+namespace Other(0x10);
+    macro bool(X) { X * (1 - X) = 0; };
+    macro two(X) {
+        /* inside */
+        bool(X);
+        X * 2
+    };
+"#;
+        let formatted = format_pil_string(input);
+        assert_eq!(formatted, expected);
+        assert_eq!(format_pil_string(&formatted), formatted);
+    }
+
+    #[test]
+    fn minimal_parentheses() {
+        let input =
+            "pol x = ((a + b) * c) - (d - e) + -(f ** 2) + (g ** h) ** (-i) | (j ^ k) | (l & m);";
+        assert_eq!(
+            format_pil_string(input),
+            "pol x = (a + b) * c - (d - e) + -(f ** 2) + g ** h ** (-i) | (j ^ k) | l & m;\n"
+        );
+    }
+
+    #[test]
+    fn asm_layout() {
+        let input = r#"reg pc[@pc]; // the program counter
+reg X[<=];
+pil{
+    col witness XInv;
+    XInv * X = 1;
+    // last
+}
+instr jmp l: label { pc' = l }
+start::
+jmp start;
+    A <=X= A+0xff;
+"#;
+        let expected = r#"reg pc[@pc]; // the program counter
+reg X[<=];
+pil {
+    col witness XInv;
+    XInv * X = 1;
+    // last
+}
+instr jmp l: label { pc' = l }
+start::
+    jmp start;
+    A <=X= A + 0xff;
+"#;
+        let formatted = format_asm::<GoldilocksField>(None, input).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(
+            format_asm::<GoldilocksField>(None, &formatted).unwrap(),
+            formatted
+        );
+    }
+
+    #[test]
+    fn asm_macros() {
+        let input = r#"%macro add(R,v) {
+R <=X= R+v; // add
+    end::
+}
+start::
+%repeat i in 0..0x4 {
+add A, i;
+// last
+}
+"#;
+        let expected = r#"%macro add(R, v) {
+    R <=X= R + v; // add
+    end::
+}
+start::
+    %repeat i in 0..0x4 {
+        add A, i;
+        // last
+    }
+"#;
+        let formatted = format_asm::<GoldilocksField>(None, input).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(
+            format_asm::<GoldilocksField>(None, &formatted).unwrap(),
+            formatted
+        );
+    }
+
+    #[test]
+    fn conditionals() {
+        let input = r#"namespace Main(4);
+#if DEBUG
+col witness d;
+  d = 0; // debug only
+#else
+col witness r;
+// release
+#endif
+"#;
+        let expected = r#"namespace Main(4);
+    #if DEBUG
+        col witness d;
+        d = 0; // debug only
+    #else
+        col witness r;
+        // release
+    #endif
+"#;
+        let formatted = format_pil_string(input);
+        assert_eq!(formatted, expected);
+        assert_eq!(format_pil_string(&formatted), formatted);
+
+        let input = "start::\n#if DEBUG\nassert_zero A;\n#endif\nloop;\n";
+        let expected = "start::\n    #if DEBUG\n        assert_zero A;\n    #endif\n    loop;\n";
+        assert_eq!(
+            format_asm::<GoldilocksField>(None, input).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn tests() {
+        let input = r#"namespace Main(%N);
+col witness x;
+#[test] #[should_fail] too_large(4) {
+col fixed X = [300]*;
+  x = X; // not a byte
+}
+"#;
+        let expected = r#"namespace Main(%N);
+    col witness x;
+    #[test] #[should_fail] too_large(4) {
+        col fixed X = [300]*;
+        x = X; // not a byte
+    }
+"#;
+        let formatted = format_pil_string(input);
+        assert_eq!(formatted, expected);
+        assert_eq!(format_pil_string(&formatted), formatted);
+    }
+}
@@ -2,11 +2,16 @@
 
 use lalrpop_util::*;
 
-use parser_util::{handle_parse_error, ParseError};
+use number::FieldElement;
+use parser_util::{handle_parse_errors, ActionError, ParseError};
 
 pub mod asm_ast;
 pub mod ast;
 pub mod display;
+pub mod format;
+mod literal;
+
+pub use literal::{literal_overflow_policy, set_literal_overflow_policy, LiteralOverflowPolicy};
 
 lalrpop_mod!(
     #[allow(clippy::all)]
@@ -14,36 +19,84 @@ lalrpop_mod!(
     "/powdr.rs"
 );
 
-pub fn parse<'a>(file_name: Option<&str>, input: &'a str) -> Result<ast::PILFile, ParseError<'a>> {
-    powdr::PILFileParser::new()
-        .parse(input)
-        .map_err(|err| handle_parse_error(err, file_name, input))
+pub fn parse<'a, T: FieldElement>(
+    file_name: Option<&str>,
+    input: &'a str,
+) -> Result<ast::PILFile<T>, ParseError<'a>> {
+    parse_recovering(file_name, input, |errors| {
+        powdr::PILFileParser::new().parse(errors, input)
+    })
+}
+
+pub fn parse_asm<'a, T: FieldElement>(
+    file_name: Option<&str>,
+    input: &'a str,
+) -> Result<asm_ast::ASMFile<T>, ParseError<'a>> {
+    parse_recovering(file_name, input, |errors| {
+        powdr::ASMFileParser::new().parse(errors, input)
+    })
 }
 
-pub fn parse_asm<'a>(
+/// Parses a single PIL expression, like the right hand side of an identity.
+pub fn parse_expression<'a, T: FieldElement>(
     file_name: Option<&str>,
     input: &'a str,
-) -> Result<asm_ast::ASMFile, ParseError<'a>> {
-    powdr::ASMFileParser::new()
-        .parse(input)
-        .map_err(|err| handle_parse_error(err, file_name, input))
+) -> Result<ast::Expression<T>, ParseError<'a>> {
+    parse_recovering(file_name, input, |errors| {
+        powdr::ExpressionParser::new().parse(errors, input)
+    })
+}
+
+type Recovered<'input> = Vec<ErrorRecovery<usize, lexer::Token<'input>, ActionError>>;
+
+/// Runs the parser, which skips statements with syntax errors, and reports
+/// all syntax errors of the input.
+fn parse_recovering<'a, R>(
+    file_name: Option<&str>,
+    input: &'a str,
+    parse: impl FnOnce(
+        &mut Recovered<'a>,
+    ) -> Result<R, lalrpop_util::ParseError<usize, lexer::Token<'a>, ActionError>>,
+) -> Result<R, ParseError<'a>> {
+    let mut recovered = vec![];
+    let result = parse(&mut recovered);
+    let mut errors = recovered
+        .into_iter()
+        .map(|recovery| recovery.error)
+        .collect::<Vec<_>>();
+    match result {
+        Ok(result) if errors.is_empty() => Ok(result),
+        Ok(_) => Err(handle_parse_errors(errors, file_name, input)),
+        Err(err) => {
+            errors.push(err);
+            Err(handle_parse_errors(errors, file_name, input))
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use std::fs;
 
-    use super::{asm_ast::ASMFile, *};
+    use super::{
+        asm_ast::{ASMFile, ASMStatement, HaltBehavior, RegisterFlag},
+        *,
+    };
     use ast::*;
+    use number::GoldilocksField;
 
     #[test]
     fn empty() {
-        assert!(powdr::PILFileParser::new().parse("").is_ok());
+        assert!(powdr::PILFileParser::new()
+            .parse::<GoldilocksField>(&mut vec![], "")
+            .is_ok());
     }
 
     #[test]
     fn simple_include() {
-        let parsed = powdr::PILFileParser::new().parse("include \"x\";").unwrap();
+        let parsed = powdr::PILFileParser::new()
+            .parse::<GoldilocksField>(&mut vec![], "include \"x\";")
+            .unwrap();
         assert_eq!(
             parsed,
             PILFile(vec![Statement::Include(0, "x".to_string())])
@@ -53,7 +106,7 @@ mod test {
     #[test]
     fn start_offsets() {
         let parsed = powdr::PILFileParser::new()
-            .parse("include \"x\"; pol commit t;")
+            .parse::<GoldilocksField>(&mut vec![], "include \"x\"; pol commit t;")
             .unwrap();
         assert_eq!(
             parsed,
@@ -73,7 +126,9 @@ mod test {
 
     #[test]
     fn simple_plookup() {
-        let parsed = powdr::PILFileParser::new().parse("f in g;").unwrap();
+        let parsed = powdr::PILFileParser::new()
+            .parse::<GoldilocksField>(&mut vec![], "f in g;")
+            .unwrap();
         assert_eq!(
             parsed,
             PILFile(vec![Statement::PlookupIdentity(
@@ -96,7 +151,37 @@ mod test {
         );
     }
 
-    fn parse_file(name: &str) -> PILFile {
+    #[test]
+    fn log_up() {
+        let input = "s { f } in { g } multiplicity m;";
+        let parsed = powdr::PILFileParser::new()
+            .parse::<GoldilocksField>(&mut vec![], input)
+            .unwrap();
+        let reference = |name: &str| {
+            Expression::PolynomialReference(PolynomialReference {
+                name: name.to_string(),
+                ..Default::default()
+            })
+        };
+        assert_eq!(
+            parsed,
+            PILFile(vec![Statement::LogUpIdentity(
+                0,
+                SelectedExpressions {
+                    selector: Some(reference("s")),
+                    expressions: vec![reference("f")]
+                },
+                SelectedExpressions {
+                    selector: None,
+                    expressions: vec![reference("g")]
+                },
+                reference("m")
+            )])
+        );
+        assert_eq!(parsed.to_string(), format!("{input}\n"));
+    }
+
+    fn parse_file(name: &str) -> PILFile<GoldilocksField> {
         let file = std::path::PathBuf::from("../test_data/").join(name);
 
         let input = fs::read_to_string(file).unwrap();
@@ -107,7 +192,7 @@ mod test {
         })
     }
 
-    fn parse_asm_file(name: &str) -> ASMFile {
+    fn parse_asm_file(name: &str) -> ASMFile<GoldilocksField> {
         let file = std::path::PathBuf::from("../test_data/").join(name);
 
         let input = fs::read_to_string(file).unwrap();
@@ -141,7 +226,7 @@ mod test {
     #[test]
     fn simple_macro() {
         let parsed = powdr::PILFileParser::new()
-            .parse("macro f(x) { x in g; x + 1 };")
+            .parse::<GoldilocksField>(&mut vec![], "macro f(x) { x in g; x + 1 };")
             .unwrap();
         assert_eq!(
             parsed,
@@ -180,8 +265,223 @@ mod test {
         );
     }
 
+    #[test]
+    fn literal_overflow() {
+        // The policy is process-wide, so all policies are checked in one test.
+        let input = "pol x = 0xffffffff00000002;";
+        let err = parse::<GoldilocksField>(None, input).unwrap_err();
+        assert_eq!(err.span(), (8, 26));
+        assert!(err.message().contains(
+            "Literal 0xffffffff00000002 has the value 18446744069414584322, \
+             which is not smaller than the field modulus 18446744069414584321"
+        ));
+        for policy in [LiteralOverflowPolicy::Wrap, LiteralOverflowPolicy::Warn] {
+            set_literal_overflow_policy(policy);
+            let parsed = parse::<GoldilocksField>(None, input).unwrap();
+            assert_eq!(
+                parsed,
+                PILFile(vec![Statement::PolynomialDefinition(
+                    0,
+                    "x".to_string(),
+                    Expression::Number(1.into())
+                )])
+            );
+        }
+        set_literal_overflow_policy(LiteralOverflowPolicy::default());
+        assert!(parse::<GoldilocksField>(None, "pol x = 0xffffffff00000000;").is_ok());
+    }
+
     #[test]
     fn parse_example_asm_files() {
         parse_asm_file("asm/simple_sum.asm");
+        parse_asm_file("asm/call_return.asm");
+        parse_asm_file("asm/macros.asm");
+        parse_asm_file("asm/machines.asm");
+        parse_asm_file("asm/multi_return.asm");
+        parse_asm_file("asm/register_defaults.asm");
+        parse_asm_file("asm/local_labels.asm");
+        parse_asm_file("asm/inline_pil_flags.asm");
+        parse_asm_file("asm/conditional.asm");
+        parse_asm_file("asm/assert_eq.asm");
+        parse_asm_file("asm/constant_propagation.asm");
+        parse_asm_file("asm/read_only_registers.asm");
+        parse_asm_file("asm/jump_table.asm");
+        parse_asm_file("asm/halt.asm");
+        parse_asm_file("asm/entry_points.asm");
+    }
+
+    #[test]
+    fn export() {
+        let input = "export f;\nexport .g;\n";
+        let parsed = parse_asm::<GoldilocksField>(None, input).unwrap();
+        assert_eq!(parsed.0[1], ASMStatement::Export(10, ".g".to_string()));
+        assert_eq!(parsed.to_string(), input);
+    }
+
+    #[test]
+    fn halt() {
+        let input = "on_halt jump .start;\nhalt;\n";
+        let parsed = parse_asm::<GoldilocksField>(None, input).unwrap();
+        assert_eq!(
+            parsed.0,
+            [
+                ASMStatement::OnHalt(0, HaltBehavior::Jump(".start".to_string())),
+                ASMStatement::Halt(21)
+            ]
+        );
+        assert_eq!(parsed.to_string(), input);
+        let err = parse_asm::<GoldilocksField>(None, "on_halt stop;").unwrap_err();
+        assert_eq!(err.span(), (8, 12));
+        assert_eq!(
+            err.message(),
+            "Expected `loop`, `jump label` or `padding` after `on_halt`."
+        );
+    }
+
+    #[test]
+    fn jump_table() {
+        let input = "jump_table dispatch X: a, .b, c;\n";
+        let parsed = parse_asm::<GoldilocksField>(None, input).unwrap();
+        assert_eq!(
+            parsed.0[0],
+            ASMStatement::JumpTable(
+                0,
+                "dispatch".to_string(),
+                "X".to_string(),
+                vec!["a".to_string(), ".b".to_string(), "c".to_string()]
+            )
+        );
+        assert_eq!(parsed.to_string(), input);
+    }
+
+    #[test]
+    fn register_flags() {
+        let input = "reg ZERO[@readonly];\nreg SIZE[@readonly = 16];\nreg S[@saved];\n";
+        let parsed = parse_asm::<GoldilocksField>(None, input).unwrap();
+        assert_eq!(
+            parsed.0[1],
+            ASMStatement::RegisterDeclaration(
+                21,
+                "SIZE".to_string(),
+                Some(RegisterFlag::ReadOnly(Some(16u32.into())))
+            )
+        );
+        assert_eq!(
+            parsed.0[2],
+            ASMStatement::RegisterDeclaration(47, "S".to_string(), Some(RegisterFlag::CalleeSaved))
+        );
+        assert_eq!(parsed.to_string(), input);
+    }
+
+    #[test]
+    fn assert_eq() {
+        let input = "assert_eq A, (B + 1), \"A is not B + 1\";\nassert_eq A, B;\n";
+        let parsed = parse_asm::<GoldilocksField>(None, input).unwrap();
+        assert!(
+            matches!(&parsed.0[0], ASMStatement::Assert(0, _, _, message) if message == "A is not B + 1")
+        );
+        assert!(
+            matches!(&parsed.0[1], ASMStatement::Instruction(_, name, args) if name == "assert_eq" && args.len() == 2)
+        );
+        assert_eq!(parsed.to_string(), input);
+    }
+
+    #[test]
+    fn conditionals() {
+        let input = "#if DEBUG\n    A <=X= 1;\n#else\n    #if FAST\n        B <=X= 2;\n    #endif\n#endif\npil {\n    #if DEBUG\n        pol commit D;\n    #endif\n}\n";
+        let parsed = parse_asm::<GoldilocksField>(None, input).unwrap();
+        assert!(
+            matches!(&parsed.0[0], ASMStatement::Conditional(0, name, then, otherwise) if name == "DEBUG" && then.len() == 1 && otherwise.len() == 1)
+        );
+        assert!(
+            matches!(&parsed.0[1], ASMStatement::InlinePil(_, statements) if matches!(&statements[..], [Statement::Conditional(_, name, then, otherwise)] if name == "DEBUG" && then.len() == 1 && otherwise.is_empty()))
+        );
+        assert_eq!(parsed.to_string(), input);
+    }
+
+    #[test]
+    fn macros_and_repeat() {
+        let input = "%macro add(R, v) {\n    R <=X= (R + v);\n    l::\n}\n%repeat i in 0..3 {\n    add A, i;\n}\n";
+        let parsed = parse_asm::<GoldilocksField>(None, input).unwrap();
+        assert!(
+            matches!(&parsed.0[0], ASMStatement::MacroDefinition(0, name, params, body) if name == "add" && params == &["R", "v"] && body.len() == 2)
+        );
+        assert!(
+            matches!(&parsed.0[1], ASMStatement::Repeat(_, var, start, end, body) if var == "i" && *start == 0u32.into() && *end == 3u32.into() && body.len() == 1)
+        );
+        assert_eq!(parsed.to_string(), input);
+    }
+
+    #[test]
+    fn call_and_return() {
+        let input = "reg ra[@ra];\ncall f;\nf::\nreturn;\ncall f, g;\n";
+        let parsed = parse_asm::<GoldilocksField>(None, input).unwrap();
+        assert_eq!(parsed.0[1], ASMStatement::Call(13, "f".to_string()));
+        assert_eq!(parsed.0[3], ASMStatement::Return(25));
+        // Only `call` with a single label is the built-in instruction.
+        assert!(
+            matches!(&parsed.0[4], ASMStatement::Instruction(_, name, args) if name == "call" && args.len() == 2)
+        );
+        assert_eq!(parsed.to_string(), input);
+    }
+
+    #[test]
+    fn machines() {
+        let input = "machine Range(max) {\n    pol constant VALUE(i) { (i % max) };\n}\nbyte: Range(256);\nempty: Empty();\n";
+        let parsed = parse_asm::<GoldilocksField>(None, input).unwrap();
+        assert!(
+            matches!(&parsed.0[0], ASMStatement::MachineDefinition(0, name, params, body) if name == "Range" && params == &["max"] && body.len() == 1)
+        );
+        assert_eq!(
+            parsed.0[1],
+            ASMStatement::MachineInstance(
+                64,
+                "byte".to_string(),
+                "Range".to_string(),
+                vec![Expression::Number(256.into())]
+            )
+        );
+        assert_eq!(parsed.to_string(), input);
+    }
+
+    #[test]
+    fn multiple_assignment_registers() {
+        let input = "Q, R <=Z, W= divrem(A, B);\nQ, R <== divrem(A, B);\n";
+        let parsed = parse_asm::<GoldilocksField>(None, input).unwrap();
+        assert!(
+            matches!(&parsed.0[0], ASMStatement::Assignment(_, write_regs, Some(assign_regs), _) if write_regs == &["Q", "R"] && assign_regs == &["Z", "W"])
+        );
+        assert!(matches!(
+            &parsed.0[1],
+            ASMStatement::Assignment(_, _, None, _)
+        ));
+        assert_eq!(parsed.to_string(), input);
+    }
+
+    #[test]
+    fn local_labels() {
+        let input = "f::\n.loop::\njmp .loop;\nA <=X= load_label(.loop);\ncall .loop;\n";
+        let parsed = parse_asm::<GoldilocksField>(None, input).unwrap();
+        assert_eq!(parsed.0[1], ASMStatement::Label(4, ".loop".to_string()));
+        assert_eq!(parsed.0[4], ASMStatement::Call(49, ".loop".to_string()));
+        assert_eq!(parsed.to_string(), input);
+    }
+
+    #[test]
+    fn all_syntax_errors() {
+        let input = "pol commit x;\npol witness y = ;\nx = 1;\nx + = y;\npol commit z;\n";
+        let err = parse::<GoldilocksField>(None, input).unwrap_err();
+        assert_eq!(err.messages().count(), 2);
+        assert_eq!(err.span(), (28, 29));
+
+        let input = "reg A;\nA <=X= ;\nA <=X= A + 1;\nB <== ;\n";
+        let err = parse_asm::<GoldilocksField>(None, input).unwrap_err();
+        assert_eq!(err.messages().count(), 2);
+    }
+
+    #[test]
+    fn error_at_end() {
+        let err = parse::<GoldilocksField>(None, "pol witness y = ;\npol commit").unwrap_err();
+        assert_eq!(err.messages().count(), 2);
     }
 }
@@ -0,0 +1,94 @@
+use std::{
+    fmt,
+    str::FromStr,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use lalrpop_util::lexer::Token;
+use number::{AbstractNumberType, FieldElement};
+use parser_util::ActionError;
+
+/// What to do with number literals that are not smaller than the field modulus.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum LiteralOverflowPolicy {
+    /// Reject the input with a parse error.
+    #[default]
+    Error,
+    /// Reduce the literal modulo the field modulus and log a warning.
+    Warn,
+    /// Silently reduce the literal modulo the field modulus.
+    Wrap,
+}
+
+static POLICY: AtomicU8 = AtomicU8::new(LiteralOverflowPolicy::Error as u8);
+
+/// Sets the policy for all subsequent parser invocations in this process.
+pub fn set_literal_overflow_policy(policy: LiteralOverflowPolicy) {
+    POLICY.store(policy as u8, Ordering::Relaxed);
+}
+
+pub fn literal_overflow_policy() -> LiteralOverflowPolicy {
+    match POLICY.load(Ordering::Relaxed) {
+        0 => LiteralOverflowPolicy::Error,
+        1 => LiteralOverflowPolicy::Warn,
+        2 => LiteralOverflowPolicy::Wrap,
+        _ => unreachable!(),
+    }
+}
+
+impl FromStr for LiteralOverflowPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(LiteralOverflowPolicy::Error),
+            "warn" => Ok(LiteralOverflowPolicy::Warn),
+            "wrap" => Ok(LiteralOverflowPolicy::Wrap),
+            _ => Err(format!(
+                "Unknown literal overflow policy {s}, expected one of error, warn, wrap."
+            )),
+        }
+    }
+}
+
+impl fmt::Display for LiteralOverflowPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LiteralOverflowPolicy::Error => "error",
+            LiteralOverflowPolicy::Warn => "warn",
+            LiteralOverflowPolicy::Wrap => "wrap",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Converts the value of the literal `literal` at `start..end` to a field element,
+/// applying the overflow policy if it does not fit.
+pub(crate) fn to_field_element<'input, T: FieldElement>(
+    literal: &str,
+    start: usize,
+    end: usize,
+    value: AbstractNumberType,
+) -> Result<T, lalrpop_util::ParseError<usize, Token<'input>, ActionError>> {
+    let modulus = T::modulus();
+    if value < modulus {
+        return Ok(value.into());
+    }
+    let message =
+        format!("Literal {literal} has the value {value}, which is not smaller than the field modulus {modulus}");
+    match literal_overflow_policy() {
+        LiteralOverflowPolicy::Error => Err(lalrpop_util::ParseError::User {
+            error: ActionError {
+                start,
+                end,
+                message: format!("{message}."),
+            },
+        }),
+        LiteralOverflowPolicy::Warn => {
+            let reduced = T::from(value);
+            log::warn!("{message}, using {} instead.", reduced.to_integer());
+            Ok(reduced)
+        }
+        LiteralOverflowPolicy::Wrap => Ok(value.into()),
+    }
+}
@@ -0,0 +1,250 @@
+//! Errors that show the source they refer to, with the offending lines
+//! underlined, secondary labels and notes.
+//!
+//! Most errors only know the file name and line of a statement, so the
+//! contents of the files are given separately with `with_source` by whoever
+//! has them. Labels in files without contents are shown as notes.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use codespan_reporting::diagnostic::{self, LabelStyle};
+use codespan_reporting::files::SimpleFiles;
+use codespan_reporting::term;
+use codespan_reporting::term::termcolor::{ColorChoice, NoColor, StandardStream, WriteColor};
+
+use crate::lines::{compute_line_starts, offset_to_line};
+
+/// A line and a column, both starting at 1.
+pub type LineColumn = (usize, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Location {
+    /// The start and end offsets in the file.
+    Span(usize, usize),
+    /// A line, starting at 1, which is underlined without its indentation.
+    Line(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Label {
+    primary: bool,
+    file_name: String,
+    location: Location,
+    message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    message: String,
+    labels: Vec<Label>,
+    notes: Vec<String>,
+    /// Contents of the files the labels refer to.
+    sources: BTreeMap<String, Arc<str>>,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>) -> Self {
+        Diagnostic {
+            message: message.into(),
+            labels: vec![],
+            notes: vec![],
+            sources: BTreeMap::new(),
+        }
+    }
+
+    /// Underlines the input between the offsets as the cause of the error.
+    pub fn with_span(mut self, file_name: &str, start: usize, end: usize, message: &str) -> Self {
+        self.add_label(true, file_name, Location::Span(start, end), message);
+        self
+    }
+
+    /// Underlines the line as the cause of the error.
+    pub fn with_line(mut self, file_name: &str, line: usize, message: &str) -> Self {
+        self.add_label(true, file_name, Location::Line(line), message);
+        self
+    }
+
+    /// Adds a line that is related to the error, like the earlier declaration
+    /// of a name that is declared twice.
+    pub fn with_secondary_line(mut self, file_name: &str, line: usize, message: &str) -> Self {
+        self.add_label(false, file_name, Location::Line(line), message);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Uses the contents for the labels in the file, which are shown as notes
+    /// otherwise.
+    pub fn with_source(mut self, file_name: &str, contents: impl Into<Arc<str>>) -> Self {
+        self.sources.insert(file_name.to_string(), contents.into());
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns true if the error refers to a part of a file.
+    pub fn has_labels(&self) -> bool {
+        !self.labels.is_empty()
+    }
+
+    /// Returns the file name, the start and the (exclusive) end of the first
+    /// primary label as lines and columns starting at 1, if the contents of
+    /// its file are known.
+    pub fn primary_position(&self) -> Option<(&str, LineColumn, LineColumn)> {
+        let label = self.labels.iter().find(|label| label.primary)?;
+        let contents = self.sources.get(&label.file_name)?;
+        let range = location_range(contents, label.location)?;
+        let line_starts = compute_line_starts(contents);
+        let position = |offset: usize| {
+            let line = offset_to_line(offset, &line_starts);
+            let column = contents[line_starts[line - 1]..offset].chars().count() + 1;
+            (line, column)
+        };
+        Some((&label.file_name, position(range.start), position(range.end)))
+    }
+
+    pub fn output_to_stderr(&self) {
+        self.emit(&mut StandardStream::stderr(ColorChoice::Always));
+    }
+
+    fn add_label(&mut self, primary: bool, file_name: &str, location: Location, message: &str) {
+        self.labels.push(Label {
+            primary,
+            file_name: file_name.to_string(),
+            location,
+            message: message.to_string(),
+        });
+    }
+
+    fn emit(&self, writer: &mut dyn WriteColor) {
+        let mut files = SimpleFiles::new();
+        let mut file_ids = BTreeMap::new();
+        let mut labels = vec![];
+        let mut notes = vec![];
+        for label in &self.labels {
+            let contents = self.sources.get(&label.file_name).cloned();
+            let range = contents
+                .as_ref()
+                .and_then(|contents| location_range(contents, label.location));
+            match (contents, range) {
+                (Some(contents), Some(range)) => {
+                    let file_id = *file_ids
+                        .entry(label.file_name.clone())
+                        .or_insert_with(|| files.add(label.file_name.clone(), contents));
+                    let style = if label.primary {
+                        LabelStyle::Primary
+                    } else {
+                        LabelStyle::Secondary
+                    };
+                    labels.push(
+                        diagnostic::Label::new(style, file_id, range).with_message(&label.message),
+                    );
+                }
+                _ => notes.push(match (label.location, label.message.is_empty()) {
+                    (Location::Line(line), true) => format!("at {}:{line}", label.file_name),
+                    (Location::Line(line), false) => {
+                        format!("{} at {}:{line}", label.message, label.file_name)
+                    }
+                    (Location::Span(start, _), _) => {
+                        format!("{} at offset {start} of {}", label.message, label.file_name)
+                    }
+                }),
+            }
+        }
+        notes.extend(self.notes.iter().cloned());
+        let diagnostic = diagnostic::Diagnostic::error()
+            .with_message(&self.message)
+            .with_labels(labels)
+            .with_notes(notes);
+        term::emit(writer, &term::Config::default(), &files, &diagnostic).unwrap();
+    }
+}
+
+/// Renders the diagnostic without colors.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut writer = NoColor::new(vec![]);
+        self.emit(&mut writer);
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+        write!(f, "{}", output.trim_end())
+    }
+}
+
+/// The offsets of the location, which is clamped to the contents.
+fn location_range(contents: &str, location: Location) -> Option<std::ops::Range<usize>> {
+    match location {
+        Location::Span(start, end) => {
+            let end = end.min(contents.len());
+            Some(start.min(end)..end)
+        }
+        Location::Line(line) => {
+            let line_starts = compute_line_starts(contents);
+            let start = *line_starts.get(line.checked_sub(1)?)?;
+            let end = line_starts
+                .get(line)
+                .map(|next| next - 1)
+                .unwrap_or(contents.len());
+            let text = &contents[start..end];
+            let indentation = text.len() - text.trim_start().len();
+            Some(start + indentation..start + text.trim_end().len().max(indentation))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn line_labels() {
+        let contents = "namespace Main(4);\n    col witness x;\n    col witness x;\n";
+        let diagnostic = Diagnostic::error("Main.x is declared twice.")
+            .with_line("main.pil", 3, "declared again here")
+            .with_secondary_line("main.pil", 2, "first declared here")
+            .with_note("Column names have to be unique in a namespace.")
+            .with_source("main.pil", contents);
+        assert_eq!(
+            diagnostic.to_string(),
+            r"error: Main.x is declared twice.
+  ┌─ main.pil:3:5
+  │
+2 │     col witness x;
+  │     -------------- first declared here
+3 │     col witness x;
+  │     ^^^^^^^^^^^^^^ declared again here
+  │
+  = Column names have to be unique in a namespace."
+        );
+    }
+
+    #[test]
+    fn primary_position() {
+        let diagnostic = Diagnostic::error("Failed.")
+            .with_secondary_line("main.pil", 1, "")
+            .with_line("main.pil", 2, "")
+            .with_source("main.pil", "x = 1;\n  y = 2;\n");
+        assert_eq!(
+            diagnostic.primary_position(),
+            Some(("main.pil", (2, 3), (2, 9)))
+        );
+        assert_eq!(Diagnostic::error("Failed.").primary_position(), None);
+    }
+
+    #[test]
+    fn unknown_file() {
+        let diagnostic = Diagnostic::error("Failed.")
+            .with_line("unknown.pil", 7, "this identity")
+            .with_span("unknown.pil", 3, 4, "this token");
+        assert_eq!(
+            diagnostic.to_string(),
+            "error: Failed.\n = this identity at unknown.pil:7\n = this token at offset 3 of unknown.pil"
+        );
+    }
+}
@@ -1,63 +1,138 @@
 //! Utils used with different lalrpop parsers
 
+use std::fmt;
+
+pub mod diagnostics;
 pub mod lines;
 
+use diagnostics::Diagnostic;
+
 pub fn quote(input: &str) -> String {
     format!("\"{}\"", input.replace('\\', "\\\\").replace('"', "\\\""))
 }
 
+/// The syntax errors of an input, at least one.
 #[derive(Debug)]
 pub struct ParseError<'a> {
-    start: usize,
-    end: usize,
     file_name: String,
     contents: &'a str,
-    message: String,
+    /// The errors in the order they were found, with the start and end
+    /// offsets of the offending input.
+    errors: Vec<(usize, usize, String)>,
 }
 
 impl<'a> ParseError<'a> {
+    /// The message of the first error.
+    pub fn message(&self) -> &str {
+        &self.errors[0].2
+    }
+
+    /// The start and end offsets of the input of the first error.
+    pub fn span(&self) -> (usize, usize) {
+        (self.errors[0].0, self.errors[0].1)
+    }
+
+    /// The messages of all errors.
+    pub fn messages(&self) -> impl Iterator<Item = &str> {
+        self.errors.iter().map(|(_, _, message)| message.as_str())
+    }
+
+    /// One diagnostic for each error.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.errors
+            .iter()
+            .map(|(start, end, message)| {
+                Diagnostic::error(message)
+                    .with_span(&self.file_name, *start, *end, "")
+                    .with_source(&self.file_name, self.contents)
+            })
+            .collect()
+    }
+
     pub fn output_to_stderr(&self) {
-        use codespan_reporting::diagnostic::{Diagnostic, Label};
-        use codespan_reporting::files::SimpleFiles;
-        use codespan_reporting::term;
-        use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
+        for diagnostic in self.diagnostics() {
+            diagnostic.output_to_stderr();
+        }
+    }
+}
+
+/// An error raised by a grammar action, located at the input that caused it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActionError {
+    pub start: usize,
+    pub end: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// The error type of grammar actions.
+pub trait UserError: fmt::Display {
+    /// The start and end offsets of the input that caused the error, if known.
+    fn span(&self) -> Option<(usize, usize)>;
+}
 
-        let config = term::Config::default();
-        let mut files = SimpleFiles::new();
-        let file_id = files.add(&self.file_name, self.contents);
-        let diagnostic = Diagnostic::error()
-            .with_message(&self.message)
-            .with_labels(vec![Label::primary(file_id, self.start..self.end)]);
-        let mut writer = StandardStream::stderr(ColorChoice::Always);
-        term::emit(&mut writer, &config, &files, &diagnostic).unwrap()
+impl UserError for &str {
+    fn span(&self) -> Option<(usize, usize)> {
+        None
     }
 }
 
-pub fn handle_parse_error<'a>(
-    err: lalrpop_util::ParseError<usize, lalrpop_util::lexer::Token, &str>,
+impl UserError for ActionError {
+    fn span(&self) -> Option<(usize, usize)> {
+        Some((self.start, self.end))
+    }
+}
+
+pub fn handle_parse_error<'a, E: UserError>(
+    err: lalrpop_util::ParseError<usize, lalrpop_util::lexer::Token, E>,
+    file_name: Option<&str>,
+    input: &'a str,
+) -> ParseError<'a> {
+    handle_parse_errors(vec![err], file_name, input)
+}
+
+/// Combines the errors of a parser that recovers from syntax errors, which
+/// must not be empty.
+pub fn handle_parse_errors<'a, E: UserError>(
+    errors: Vec<lalrpop_util::ParseError<usize, lalrpop_util::lexer::Token, E>>,
     file_name: Option<&str>,
     input: &'a str,
 ) -> ParseError<'a> {
-    let (&start, &end) = match &err {
-        lalrpop_util::ParseError::InvalidToken { location } => (location, location),
+    assert!(!errors.is_empty());
+    ParseError {
+        file_name: file_name.unwrap_or("input").to_string(),
+        contents: input,
+        errors: errors
+            .into_iter()
+            .map(|err| {
+                let (start, end) = error_span(&err);
+                (start, end, format!("{err}"))
+            })
+            .collect(),
+    }
+}
+
+fn error_span<E: UserError>(
+    err: &lalrpop_util::ParseError<usize, lalrpop_util::lexer::Token, E>,
+) -> (usize, usize) {
+    match err {
+        lalrpop_util::ParseError::InvalidToken { location } => (*location, *location),
         lalrpop_util::ParseError::UnrecognizedEOF {
             location,
             expected: _,
-        } => (location, location),
+        } => (*location, *location),
         lalrpop_util::ParseError::UnrecognizedToken {
             token: (start, _, end),
             expected: _,
-        } => (start, end),
+        } => (*start, *end),
         lalrpop_util::ParseError::ExtraToken {
             token: (start, _, end),
-        } => (start, end),
-        lalrpop_util::ParseError::User { error: _ } => (&0, &0),
-    };
-    ParseError {
-        start,
-        end,
-        file_name: file_name.unwrap_or("input").to_string(),
-        contents: input,
-        message: format!("{err}"),
+        } => (*start, *end),
+        lalrpop_util::ParseError::User { error } => error.span().unwrap_or((0, 0)),
     }
 }
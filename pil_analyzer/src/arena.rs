@@ -0,0 +1,103 @@
+//! The arena of the sub-expressions of an analyzed PIL file: Operands of
+//! operations are stored once in the arena of the `Analyzed` file and
+//! referenced by index, so that expressions are small, cheap to clone and
+//! allocated together. Expressions that are cloned, for example the
+//! arguments of macros, share their operands.
+//!
+//! Only the file that created an expression (or a clone of its arena) can
+//! resolve its operands, which is why formatting needs the arena, see
+//! `ExpressionArena::display`.
+//!
+//! The arena is append-only: Because operands are shared, an operand cannot
+//! be modified in place, and expressions that are no longer referenced are
+//! only freed with the whole arena. Rewriting an expression adds the new
+//! operands to the arena.
+
+use std::ops::Index;
+
+use crate::Expression;
+
+/// The index of an expression in an `ExpressionArena`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ExpressionId(u32);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpressionArena<T> {
+    expressions: Vec<Expression<T>>,
+}
+
+impl<T> Default for ExpressionArena<T> {
+    fn default() -> Self {
+        ExpressionArena {
+            expressions: vec![],
+        }
+    }
+}
+
+impl<T> ExpressionArena<T> {
+    /// Moves the expression into the arena and returns its index.
+    pub fn add(&mut self, expression: Expression<T>) -> ExpressionId {
+        let id = ExpressionId(
+            self.expressions
+                .len()
+                .try_into()
+                .expect("Too many expressions."),
+        );
+        self.expressions.push(expression);
+        id
+    }
+
+    pub fn len(&self) -> usize {
+        self.expressions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expressions.is_empty()
+    }
+}
+
+impl<T> Index<ExpressionId> for ExpressionArena<T> {
+    type Output = Expression<T>;
+
+    fn index(&self, id: ExpressionId) -> &Expression<T> {
+        &self.expressions[id.0 as usize]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+
+    use super::*;
+    use crate::{analyze_string, BinaryOperator};
+
+    #[test]
+    fn operands_in_arena() {
+        let analyzed = analyze_string::<GoldilocksField>(
+            "namespace Main(4);\ncol witness x;\nx * (1 - x) = 0;",
+        );
+        let selector = analyzed.identities[0].left.selector.as_ref().unwrap();
+        let Expression::BinaryOperation(product, BinaryOperator::Sub, zero) = selector else {
+            panic!()
+        };
+        assert_eq!(analyzed.expressions[*zero], Expression::Number(0.into()));
+        let Expression::BinaryOperation(left, BinaryOperator::Mul, right) =
+            &analyzed.expressions[*product]
+        else {
+            panic!()
+        };
+        assert!(matches!(
+            &analyzed.expressions[*left],
+            Expression::PolynomialReference(reference) if reference.name == "Main.x"
+        ));
+        assert!(matches!(
+            &analyzed.expressions[*right],
+            Expression::BinaryOperation(_, BinaryOperator::Sub, _)
+        ));
+        assert_eq!(analyzed.expressions.len(), 6);
+        assert_eq!(
+            analyzed.expressions.display(selector).to_string(),
+            "((Main.x * (1 - Main.x)) - 0)"
+        );
+    }
+}
@@ -0,0 +1,404 @@
+//! Constructs an analyzed PIL file from Rust code instead of from PIL
+//! source, so that other compilers and DSLs can use the executor and the
+//! backends as a library.
+
+use std::collections::HashMap;
+
+use number::{DegreeType, FieldElement};
+
+use crate::{
+    Analyzed, BinaryOperator, Expression, FunctionValueDefinition, Identity, IdentityKind,
+    Polynomial, PolynomialReference, PolynomialType, PublicDeclaration, SelectedExpressions,
+    SourceRef, StatementIdentifier, Symbol, UnaryOperator,
+};
+
+/// Adds columns, identities and public declarations in source order, like
+/// the statements of a PIL file. Columns are added to the current namespace
+/// and everything is attributed to the current source, see `namespace` and
+/// `source`. The column methods return a reference to the new column to be
+/// used in expressions, definitions of fixed and query columns are functions
+/// of the row, which is `Expression::LocalVariableReference(0)`.
+/// Operations are created with `binary_operation` and `unary_operation`,
+/// which move the operands into the arena of the analyzed file.
+///
+/// Like the analyzer, the builder panics if a name is defined twice.
+pub struct AnalyzedBuilder<T> {
+    analyzed: Analyzed<T>,
+    namespace: String,
+    degree: DegreeType,
+    source: SourceRef,
+    /// The next IDs of the witness, fixed and intermediate columns.
+    poly_counters: [u64; 3],
+    identity_counter: HashMap<IdentityKind, u64>,
+}
+
+impl<T: FieldElement> Default for AnalyzedBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: FieldElement> AnalyzedBuilder<T> {
+    pub fn new() -> Self {
+        AnalyzedBuilder {
+            analyzed: Analyzed {
+                constants: HashMap::new(),
+                definitions: HashMap::new(),
+                public_declarations: HashMap::new(),
+                identities: vec![],
+                source_order: vec![],
+                expressions: Default::default(),
+                sources: Default::default(),
+                pc_locations: None,
+            },
+            namespace: "Global".to_string(),
+            degree: 0,
+            source: SourceRef {
+                file: String::new(),
+                line: 0,
+            },
+            poly_counters: [0; 3],
+            identity_counter: HashMap::new(),
+        }
+    }
+
+    /// Starts a namespace (a machine) with the given degree, like
+    /// `namespace Main(1024);`.
+    pub fn namespace(&mut self, name: &str, degree: DegreeType) -> &mut Self {
+        self.namespace = name.to_string();
+        self.degree = degree;
+        self
+    }
+
+    /// Sets the source that the following columns and identities are
+    /// attributed to, for example in error messages.
+    pub fn source(&mut self, file: &str, line: usize) -> &mut Self {
+        self.source = SourceRef {
+            file: file.to_string(),
+            line,
+        };
+        self
+    }
+
+    /// Defines a constant, like `constant %N = 1024;`. Constants are not
+    /// namespaced.
+    pub fn constant(&mut self, name: &str, value: T) -> &mut Self {
+        let is_new = self
+            .analyzed
+            .constants
+            .insert(name.to_string(), value)
+            .is_none();
+        assert!(is_new, "Constant {name} was defined twice.");
+        self
+    }
+
+    pub fn witness_column(&mut self, name: &str) -> Expression<T> {
+        self.add_column(name, PolynomialType::Committed, None)
+    }
+
+    /// Adds a witness column whose values witness generation asks the prover
+    /// for, `query` is a function of the row.
+    pub fn query_column(&mut self, name: &str, query: Expression<T>) -> Expression<T> {
+        self.add_column(
+            name,
+            PolynomialType::Committed,
+            Some(FunctionValueDefinition::Query(query)),
+        )
+    }
+
+    /// Adds a fixed column defined by a function of the row or by an array
+    /// with as many elements as the degree.
+    pub fn fixed_column(
+        &mut self,
+        name: &str,
+        definition: FunctionValueDefinition<T>,
+    ) -> Expression<T> {
+        if let FunctionValueDefinition::Array(items) = &definition {
+            assert_eq!(
+                items.iter().map(|item| item.size()).sum::<DegreeType>(),
+                self.degree,
+                "The array of {name} does not have the size of the degree."
+            );
+        }
+        self.add_column(name, PolynomialType::Constant, Some(definition))
+    }
+
+    /// Adds an intermediate column, like `col sum = a + b;`.
+    pub fn intermediate_column(&mut self, name: &str, definition: Expression<T>) -> Expression<T> {
+        self.add_column(
+            name,
+            PolynomialType::Intermediate,
+            Some(FunctionValueDefinition::Mapping(definition)),
+        )
+    }
+
+    /// Returns `left op right`, like `x + y`.
+    pub fn binary_operation(
+        &mut self,
+        left: Expression<T>,
+        op: BinaryOperator,
+        right: Expression<T>,
+    ) -> Expression<T> {
+        Expression::BinaryOperation(
+            self.analyzed.expressions.add(left),
+            op,
+            self.analyzed.expressions.add(right),
+        )
+    }
+
+    /// Returns `op value`, like `-x`.
+    pub fn unary_operation(&mut self, op: UnaryOperator, value: Expression<T>) -> Expression<T> {
+        Expression::UnaryOperation(op, self.analyzed.expressions.add(value))
+    }
+
+    /// Adds the polynomial identity `expression = 0`.
+    pub fn polynomial_identity(&mut self, expression: Expression<T>) -> &mut Self {
+        let left = SelectedExpressions {
+            selector: Some(expression),
+            expressions: vec![],
+        };
+        self.add_identity(IdentityKind::Polynomial, left, Default::default(), None)
+    }
+
+    /// Adds the lookup `left in right`.
+    pub fn lookup(
+        &mut self,
+        left: SelectedExpressions<T>,
+        right: SelectedExpressions<T>,
+    ) -> &mut Self {
+        self.add_identity(IdentityKind::Plookup, left, right, None)
+    }
+
+    /// Adds the lookup `left in right` with the witness column that holds how
+    /// often each row of `right` is looked up.
+    pub fn logup(
+        &mut self,
+        left: SelectedExpressions<T>,
+        right: SelectedExpressions<T>,
+        multiplicity: &Expression<T>,
+    ) -> &mut Self {
+        let multiplicity =
+            match multiplicity {
+                Expression::PolynomialReference(reference)
+                    if !reference.next
+                        && reference.index.is_none()
+                        && self.analyzed.definitions.get(&reference.name).is_some_and(
+                            |(poly, _)| poly.poly_type == PolynomialType::Committed,
+                        ) =>
+                {
+                    reference.clone()
+                }
+                e => panic!(
+                    "The multiplicity has to be a witness column, but it is {}.",
+                    self.analyzed.expressions.display(e)
+                ),
+            };
+        self.add_identity(IdentityKind::LogUp, left, right, Some(multiplicity))
+    }
+
+    /// Adds the permutation `left is right`.
+    pub fn permutation(
+        &mut self,
+        left: SelectedExpressions<T>,
+        right: SelectedExpressions<T>,
+    ) -> &mut Self {
+        self.add_identity(IdentityKind::Permutation, left, right, None)
+    }
+
+    /// Adds the connection `{ left } connect { right }`.
+    pub fn connect(&mut self, left: Vec<Expression<T>>, right: Vec<Expression<T>>) -> &mut Self {
+        let selected = |expressions| SelectedExpressions {
+            selector: None,
+            expressions,
+        };
+        self.add_identity(IdentityKind::Connect, selected(left), selected(right), None)
+    }
+
+    /// Declares the public `name` as the value of the column on the row,
+    /// like `public name = column(row);`.
+    pub fn public(&mut self, name: &str, column: &Expression<T>, row: DegreeType) -> &mut Self {
+        let Expression::PolynomialReference(polynomial) = column else {
+            panic!(
+                "Public {name} has to reference a column, but it is {}.",
+                self.analyzed.expressions.display(column)
+            );
+        };
+        let declaration = PublicDeclaration {
+            id: self.analyzed.public_declarations.len() as u64,
+            source: self.source.clone(),
+            name: name.to_string(),
+            polynomial: polynomial.clone(),
+            index: row,
+        };
+        let is_new = self
+            .analyzed
+            .public_declarations
+            .insert(name.to_string(), declaration)
+            .is_none();
+        assert!(is_new, "Public {name} was declared twice.");
+        self.analyzed
+            .source_order
+            .push(StatementIdentifier::PublicDeclaration(name.to_string()));
+        self
+    }
+
+    pub fn build(self) -> Analyzed<T> {
+        self.analyzed
+    }
+
+    fn add_column(
+        &mut self,
+        name: &str,
+        poly_type: PolynomialType,
+        definition: Option<FunctionValueDefinition<T>>,
+    ) -> Expression<T> {
+        let counter = &mut self.poly_counters[match poly_type {
+            PolynomialType::Committed => 0,
+            PolynomialType::Constant => 1,
+            PolynomialType::Intermediate => 2,
+        }];
+        let absolute_name = Symbol::intern(&format!("{}.{name}", self.namespace));
+        let poly = Polynomial {
+            id: *counter,
+            source: self.source.clone(),
+            absolute_name,
+            poly_type,
+            degree: self.degree,
+            length: None,
+        };
+        *counter += 1;
+        let is_new = self
+            .analyzed
+            .definitions
+            .insert(absolute_name, (poly, definition))
+            .is_none();
+        assert!(is_new, "Column {absolute_name} was defined twice.");
+        self.analyzed
+            .source_order
+            .push(StatementIdentifier::Definition(absolute_name));
+        Expression::PolynomialReference(PolynomialReference {
+            name: absolute_name,
+            index: None,
+            next: false,
+        })
+    }
+
+    fn add_identity(
+        &mut self,
+        kind: IdentityKind,
+        left: SelectedExpressions<T>,
+        right: SelectedExpressions<T>,
+        multiplicity: Option<PolynomialReference>,
+    ) -> &mut Self {
+        let counter = self.identity_counter.entry(kind).or_default();
+        let identity = Identity {
+            id: *counter,
+            kind,
+            source: self.source.clone(),
+            left,
+            right,
+            multiplicity,
+            origin: None,
+        };
+        *counter += 1;
+        self.analyzed
+            .source_order
+            .push(StatementIdentifier::Identity(
+                self.analyzed.identities.len(),
+            ));
+        self.analyzed.identities.push(identity);
+        self
+    }
+}
+
+/// Returns the reference to the next row of the column, like `x'`.
+pub fn next<T>(column: &Expression<T>) -> Expression<T> {
+    match column {
+        Expression::PolynomialReference(reference) if !reference.next => {
+            Expression::PolynomialReference(PolynomialReference {
+                next: true,
+                ..reference.clone()
+            })
+        }
+        _ => panic!("Only references to the current row have a next row."),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+
+    use super::*;
+    use crate::{analyze_string, RepeatedArray};
+
+    #[test]
+    fn same_as_analyzed_source() {
+        let source = r#"namespace F(4);
+    col fixed ISLAST = [0, 0, 0, 1];
+    col witness x;
+    col witness y;
+    col sum = x + y;
+    (1 - ISLAST) * y' = (1 - ISLAST) * sum;
+    { x } in { ISLAST };
+    public out = y(3);
+"#;
+        let one = || Expression::Number(1.into());
+        let mut builder = AnalyzedBuilder::<GoldilocksField>::new();
+        builder.namespace("F", 4).source("input", 2);
+        let is_last = builder.fixed_column(
+            "ISLAST",
+            FunctionValueDefinition::Array(vec![RepeatedArray {
+                values: [0, 0, 0, 1].map(|v| Expression::Number(v.into())).to_vec(),
+                repetitions: 1,
+            }]),
+        );
+        builder.source("input", 3);
+        let x = builder.witness_column("x");
+        builder.source("input", 4);
+        let y = builder.witness_column("y");
+        builder.source("input", 5);
+        let x_plus_y = builder.binary_operation(x.clone(), BinaryOperator::Add, y.clone());
+        let sum = builder.intermediate_column("sum", x_plus_y);
+        let not_last = builder.binary_operation(one(), BinaryOperator::Sub, is_last.clone());
+        let left = builder.binary_operation(not_last, BinaryOperator::Mul, next(&y));
+        let not_last = builder.binary_operation(one(), BinaryOperator::Sub, is_last.clone());
+        let right = builder.binary_operation(not_last, BinaryOperator::Mul, sum);
+        let identity = builder.binary_operation(left, BinaryOperator::Sub, right);
+        builder.source("input", 6).polynomial_identity(identity);
+        builder
+            .source("input", 7)
+            .lookup(
+                SelectedExpressions {
+                    selector: None,
+                    expressions: vec![x],
+                },
+                SelectedExpressions {
+                    selector: None,
+                    expressions: vec![is_last],
+                },
+            )
+            .source("input", 8)
+            .public("out", &y, 3);
+        let built = builder.build();
+        let analyzed = analyze_string::<GoldilocksField>(source);
+
+        assert_eq!(built.to_string(), analyzed.to_string());
+        assert_eq!(built.identities, analyzed.identities);
+        for (name, (poly, _)) in &analyzed.definitions {
+            let (built_poly, _) = &built.definitions[name];
+            assert_eq!(
+                (built_poly.id, &built_poly.source, built_poly.degree),
+                (poly.id, &poly.source, poly.degree)
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic = "Column F.x was defined twice."]
+    fn defined_twice() {
+        let mut builder = AnalyzedBuilder::<GoldilocksField>::new();
+        builder.namespace("F", 4);
+        builder.witness_column("x");
+        builder.witness_column("x");
+    }
+}
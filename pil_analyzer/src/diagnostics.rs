@@ -0,0 +1,303 @@
+//! Checks a PIL file without generating any columns and reports the problems
+//! as diagnostics, for editors and CI.
+//!
+//! The analyzer stops at the first error, so there is at most one error
+//! diagnostic. Its span is the statement that caused it, in the file that
+//! contains the statement.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use json::{object, JsonValue};
+use number::FieldElement;
+use parser_util::lines::{compute_line_starts, offset_to_line};
+
+use crate::util::expr_any;
+use crate::{Analyzed, Expression, FunctionValueDefinition, PolynomialType, Symbol};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A line and column in a file, both starting at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The part of a file a diagnostic refers to, the end is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub file: String,
+    pub span: Option<Span>,
+    pub severity: Severity,
+    /// A short identifier of the kind of problem, like `parse-error`.
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn to_json(&self) -> JsonValue {
+        let position = |p: Position| object! { line: p.line, column: p.column };
+        object! {
+            file: self.file.as_str(),
+            span: self.span.map(|s| object! { start: position(s.start), end: position(s.end) }),
+            severity: self.severity.to_string(),
+            code: self.code,
+            message: self.message.as_str(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let Some(span) = self.span {
+            write!(f, ":{}:{}", span.start.line, span.start.column)?;
+        }
+        write!(f, ": {}[{}]: {}", self.severity, self.code, self.message)
+    }
+}
+
+/// Returns the diagnostics as a JSON array.
+pub fn to_json(diagnostics: &[Diagnostic]) -> String {
+    JsonValue::from(
+        diagnostics
+            .iter()
+            .map(Diagnostic::to_json)
+            .collect::<Vec<_>>(),
+    )
+    .pretty(2)
+}
+
+/// Parses and analyzes the PIL file and returns all problems found.
+pub fn check<T: FieldElement>(path: &Path) -> Vec<Diagnostic> {
+    let file = path.to_str().unwrap().to_string();
+    let error = |code, span, message| Diagnostic {
+        file: file.clone(),
+        span,
+        severity: Severity::Error,
+        code,
+        message,
+    };
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => return vec![error("io-error", None, format!("Could not read file: {e}"))],
+    };
+    if let Err(err) = parser::parse::<T>(Some(&file), &contents) {
+        let line_starts = compute_line_starts(&contents);
+        let position = |offset: usize| {
+            let line = offset_to_line(offset, &line_starts);
+            Position {
+                line,
+                column: contents[line_starts[line - 1]..offset].chars().count() + 1,
+            }
+        };
+        let (start, end) = err.span();
+        let span = Span {
+            start: position(start),
+            end: position(end),
+        };
+        return vec![error("parse-error", Some(span), err.message().to_string())];
+    }
+
+    match crate::try_analyze::<T>(path) {
+        Ok(analyzed) => unconstrained_witness_columns(&analyzed, &file),
+        Err(e) => {
+            let file_name = Path::new(&file).file_name().unwrap().to_str().unwrap();
+            let mut diagnostic = error("analysis-error", None, e.message().to_string());
+            if let Some((label_file, start, end)) = e.primary_position() {
+                // The source references of the analyzer only have the file name.
+                if label_file != file_name {
+                    diagnostic.file = label_file.to_string();
+                }
+                let position = |(line, column)| Position { line, column };
+                diagnostic.span = Some(Span {
+                    start: position(start),
+                    end: position(end),
+                });
+            }
+            vec![diagnostic]
+        }
+    }
+}
+
+/// Warns about witness columns that are not referenced by any identity,
+/// directly or through intermediate columns, since they can take any value.
+fn unconstrained_witness_columns<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    file: &str,
+) -> Vec<Diagnostic> {
+    let mut referenced = HashSet::new();
+    let mut visit = |e: &Expression<T>| {
+        expr_any(&analyzed.expressions, e, |e| {
+            if let Expression::PolynomialReference(reference) = e {
+                referenced.insert(reference.name);
+            }
+            false
+        });
+    };
+    for identity in &analyzed.identities {
+        for selected in [&identity.left, &identity.right] {
+            selected.selector.iter().for_each(&mut visit);
+            selected.expressions.iter().for_each(&mut visit);
+        }
+    }
+    for (poly, definition) in analyzed.definitions.values() {
+        if let (PolynomialType::Intermediate, Some(FunctionValueDefinition::Mapping(e))) =
+            (poly.poly_type, definition)
+        {
+            visit(e);
+        }
+    }
+    referenced.extend(
+        analyzed
+            .identities
+            .iter()
+            .filter_map(|identity| identity.multiplicity.as_ref())
+            .chain(analyzed.public_declarations.values().map(|p| &p.polynomial))
+            .map(|reference| reference.name),
+    );
+
+    let file_name = Path::new(file).file_name().unwrap().to_str().unwrap();
+    analyzed
+        .committed_polys_in_source_order()
+        .into_iter()
+        .map(|(poly, _)| poly)
+        .filter(|poly| !referenced.contains(&Symbol::intern(&poly.absolute_name)))
+        .map(|poly| Diagnostic {
+            file: if poly.source.file == file_name {
+                file.to_string()
+            } else {
+                poly.source.file.clone()
+            },
+            span: Some(Span {
+                start: Position {
+                    line: poly.source.line,
+                    column: 1,
+                },
+                end: Position {
+                    line: poly.source.line + 1,
+                    column: 1,
+                },
+            }),
+            severity: Severity::Warning,
+            code: "unconstrained-witness-column",
+            message: format!(
+                "The witness column {} is not used in any identity.",
+                poly.absolute_name
+            ),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use number::GoldilocksField;
+
+    use super::*;
+
+    fn check_string(contents: &str) -> Vec<Diagnostic> {
+        let dir = mktemp::Temp::new_dir().unwrap();
+        let path = dir.join("input.pil");
+        fs::File::create(&path)
+            .unwrap()
+            .write_all(contents.as_bytes())
+            .unwrap();
+        check::<GoldilocksField>(&path)
+            .into_iter()
+            .map(|d| Diagnostic {
+                file: Path::new(&d.file)
+                    .file_name()
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .to_string(),
+                ..d
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_error() {
+        let diagnostics = check_string("namespace N(4);\n    col witness x;\n    x = ;\n");
+        assert_eq!(diagnostics.len(), 1);
+        let diagnostic = &diagnostics[0];
+        assert_eq!(
+            (diagnostic.severity, diagnostic.code),
+            (Severity::Error, "parse-error")
+        );
+        assert_eq!(
+            diagnostic.span.unwrap().start,
+            Position { line: 3, column: 9 }
+        );
+        assert_eq!(
+            diagnostic.to_json()["span"]["start"]["line"].as_usize(),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn analysis_error() {
+        let diagnostics = check_string("namespace N(4);\n    col witness x;\n    x = %N;\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "analysis-error");
+        assert_eq!(diagnostics[0].message, "Constant %N not found.");
+        assert_eq!(
+            diagnostics[0].span,
+            Some(Span {
+                start: Position { line: 3, column: 5 },
+                end: Position {
+                    line: 3,
+                    column: 12
+                },
+            })
+        );
+        assert_eq!(
+            diagnostics[0].to_json()["span"]["start"]["column"].as_usize(),
+            Some(5)
+        );
+    }
+
+    #[test]
+    fn rendered_analysis_error() {
+        let diagnostics = check_string("namespace N(4);\n    col witness x;\n    col witness x;\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "Column N.x was defined twice.");
+        assert_eq!(diagnostics[0].span.unwrap().start.line, 3);
+    }
+
+    #[test]
+    fn unconstrained_witness_column() {
+        let diagnostics = check_string(
+            "namespace N(4);\n    col witness x;\n    col witness y, z;\n    col w = z * 2;\n    x = w;\n",
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "input.pil:3:1: warning[unconstrained-witness-column]: The witness column N.y is not used in any identity."
+        );
+    }
+}
@@ -9,10 +9,10 @@ use itertools::Itertools;
 
 use super::*;
 
-impl Display for Analyzed {
+impl<T: FieldElement> Display for Analyzed<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        for (name, value) in &self.constants {
-            writeln!(f, "constant {name} = {value};")?;
+        for (name, value) in self.constants.iter().sorted_by_key(|(name, _)| *name) {
+            writeln!(f, "constant {name} = {};", value.to_signed_integer())?;
         }
 
         let mut namespace = "Global".to_string();
@@ -40,7 +40,7 @@ impl Display for Analyzed {
                     };
                     write!(f, "    col {kind}{name}")?;
                     if let Some(value) = definition {
-                        writeln!(f, "{value};")?
+                        writeln!(f, "{};", self.expressions.display(value))?
                     } else {
                         writeln!(f, ";")?
                     }
@@ -55,7 +55,9 @@ impl Display for Analyzed {
                         decl.polynomial, decl.index
                     )?;
                 }
-                StatementIdentifier::Identity(i) => writeln!(f, "    {}", &self.identities[*i])?,
+                StatementIdentifier::Identity(i) => {
+                    writeln!(f, "    {}", self.expressions.display(&self.identities[*i]))?
+                }
             }
         }
 
@@ -63,79 +65,128 @@ impl Display for Analyzed {
     }
 }
 
-impl Display for FunctionValueDefinition {
+/// An expression, identity, selected expressions or definition together with
+/// the arena of its operands, to format it, see `ExpressionArena::display`.
+pub struct WithArena<'a, T, I: ?Sized> {
+    expressions: &'a ExpressionArena<T>,
+    item: &'a I,
+}
+
+impl<T> ExpressionArena<T> {
+    /// Returns the item for formatting, its operands have to be in this arena.
+    pub fn display<'a, I: ?Sized>(&'a self, item: &'a I) -> WithArena<'a, T, I> {
+        WithArena {
+            expressions: self,
+            item,
+        }
+    }
+}
+
+impl<'a, T: FieldElement> Display for WithArena<'a, T, FunctionValueDefinition<T>> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        match self {
-            FunctionValueDefinition::Mapping(e) => write!(f, "(i) {{ {e} }}"),
+        let expressions = self.expressions;
+        match self.item {
+            FunctionValueDefinition::Mapping(e) => {
+                write!(f, "(i) {{ {} }}", expressions.display(e))
+            }
             FunctionValueDefinition::Array(items) => {
-                write!(f, " = {}", items.iter().map(|i| i.to_string()).join(" + "))
+                write!(
+                    f,
+                    " = {}",
+                    items
+                        .iter()
+                        .map(|i| expressions.display(i).to_string())
+                        .join(" + ")
+                )
             }
-            FunctionValueDefinition::Query(e) => write!(f, "(i) query {e}"),
+            FunctionValueDefinition::Query(e) => write!(f, "(i) query {}", expressions.display(e)),
         }
     }
 }
 
-impl Display for RepeatedArray {
+impl<'a, T: FieldElement> Display for WithArena<'a, T, RepeatedArray<T>> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        if self.repetitions == 0 {
+        if self.item.repetitions == 0 {
             return Ok(());
         }
         write!(
             f,
             "[{}]",
-            self.values.iter().map(|i| i.to_string()).join(", ")
+            format_expressions(self.expressions, &self.item.values)
         )?;
-        if self.repetitions > 1 {
+        if self.item.repetitions > 1 {
             write!(f, "*")?;
         }
         Ok(())
     }
 }
 
-impl Display for Identity {
+impl<'a, T: FieldElement> Display for WithArena<'a, T, Identity<T>> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        match self.kind {
+        let expressions = self.expressions;
+        let identity = self.item;
+        let left = expressions.display(&identity.left);
+        let right = expressions.display(&identity.right);
+        match identity.kind {
             IdentityKind::Polynomial => {
-                let expression = self.left.selector.as_ref().unwrap();
+                let expression = identity.left.selector.as_ref().unwrap();
                 if let Expression::BinaryOperation(left, BinaryOperator::Sub, right) = expression {
-                    write!(f, "{left} = {right};")
+                    write!(
+                        f,
+                        "{} = {};",
+                        expressions.display(&expressions[*left]),
+                        expressions.display(&expressions[*right])
+                    )
                 } else {
-                    write!(f, "{expression} = 0;")
+                    write!(f, "{} = 0;", expressions.display(expression))
                 }
             }
-            IdentityKind::Plookup => write!(f, "{} in {};", self.left, self.right),
-            IdentityKind::Permutation => write!(f, "{} is {};", self.left, self.right),
-            IdentityKind::Connect => write!(f, "{} connect {};", self.left, self.right),
+            IdentityKind::Plookup => write!(f, "{left} in {right};"),
+            IdentityKind::LogUp => write!(
+                f,
+                "{left} in {right} multiplicity {};",
+                identity.multiplicity.as_ref().unwrap()
+            ),
+            IdentityKind::Permutation => write!(f, "{left} is {right};"),
+            IdentityKind::Connect => write!(f, "{left} connect {right};"),
         }
     }
 }
 
-impl Display for SelectedExpressions {
+impl<'a, T: FieldElement> Display for WithArena<'a, T, SelectedExpressions<T>> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         write!(
             f,
             "{}{{ {} }}",
-            self.selector
+            self.item
+                .selector
                 .as_ref()
-                .map(|s| format!("{s} "))
+                .map(|s| format!("{} ", self.expressions.display(s)))
                 .unwrap_or_default(),
-            format_expressions(&self.expressions)
+            format_expressions(self.expressions, &self.item.expressions)
         )
     }
 }
 
-impl Display for Expression {
+impl<'a, T: FieldElement> Display for WithArena<'a, T, Expression<T>> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        match self {
+        let expressions = self.expressions;
+        let operand = |id: &ExpressionId| expressions.display(&expressions[*id]);
+        match self.item {
             Expression::Constant(name) => write!(f, "{name}"),
             Expression::PolynomialReference(reference) => write!(f, "{reference}"),
             Expression::PublicReference(name) => write!(f, ":{name}"),
-            Expression::Number(value) => write!(f, "{value}"),
+            // Independent of the configured display format, so that the output can be parsed again.
+            Expression::Number(value) => write!(f, "{}", value.to_signed_integer()),
             Expression::String(value) => write!(f, "\"{value}\""), // TODO quote?
-            Expression::Tuple(items) => write!(f, "({})", format_expressions(items)),
-            Expression::BinaryOperation(left, op, right) => write!(f, "({left} {op} {right})"),
-            Expression::UnaryOperation(op, exp) => write!(f, "{op}{exp}"),
-            Expression::FunctionCall(fun, args) => write!(f, "{fun}({})", format_expressions(args)),
+            Expression::Tuple(items) => write!(f, "({})", format_expressions(expressions, items)),
+            Expression::BinaryOperation(left, op, right) => {
+                write!(f, "({} {op} {})", operand(left), operand(right))
+            }
+            Expression::UnaryOperation(op, exp) => write!(f, "{op}{}", operand(exp)),
+            Expression::FunctionCall(fun, args) => {
+                write!(f, "{fun}({})", format_expressions(expressions, args))
+            }
             Expression::LocalVariableReference(index) => {
                 // TODO this is not really reproducing the input, but
                 // if we want to do that, we would need the names of the local variables somehow.
@@ -147,13 +198,15 @@ impl Display for Expression {
             }
             Expression::MatchExpression(scrutinee, arms) => write!(
                 f,
-                "match {scrutinee} {{ {} }}",
+                "match {} {{ {} }}",
+                operand(scrutinee),
                 arms.iter()
                     .map(|(n, e)| format!(
-                        "{} => {e},",
+                        "{} => {},",
                         n.as_ref()
                             .map(|n| n.to_string())
-                            .unwrap_or_else(|| "_".to_string())
+                            .unwrap_or_else(|| "_".to_string()),
+                        expressions.display(e)
                     ))
                     .collect::<Vec<_>>()
                     .join(" ")
@@ -162,10 +215,13 @@ impl Display for Expression {
     }
 }
 
-fn format_expressions(expressions: &[Expression]) -> String {
+fn format_expressions<T: FieldElement>(
+    arena: &ExpressionArena<T>,
+    expressions: &[Expression<T>],
+) -> String {
     expressions
         .iter()
-        .map(|e| format!("{e}"))
+        .map(|e| arena.display(e).to_string())
         .collect::<Vec<_>>()
         .join(", ")
 }
@@ -187,7 +243,9 @@ impl Display for PolynomialReference {
 
 #[cfg(test)]
 mod test {
-    use crate::pil_analyzer::process_pil_file_contents;
+    use number::GoldilocksField;
+
+    use crate::analyze_string;
 
     #[test]
     fn parse_print_analyzed() {
@@ -231,7 +289,7 @@ namespace T(65536);
     col fixed p_reg_write_X_CNT = [1, 0, 0, 0, 0, 0, 0, 0, 0] + [0]*;
     { T.pc, T.reg_write_X_A, T.reg_write_X_CNT } in (1 - T.first_step) { T.line, T.p_reg_write_X_A, T.p_reg_write_X_CNT };
 "#;
-        let formatted = process_pil_file_contents(input).to_string();
+        let formatted = analyze_string::<GoldilocksField>(input).to_string();
         if input != formatted {
             for (i, f) in input.split('\n').zip(formatted.split('\n')) {
                 assert_eq!(i, f);
@@ -239,4 +297,41 @@ namespace T(65536);
         }
         assert_eq!(input, formatted);
     }
+
+    #[test]
+    fn print_log_up() {
+        let input = r#"namespace Main(4);
+    col fixed A;
+    col witness x;
+    col witness m;
+    { Main.x } in { Main.A } multiplicity Main.m;
+"#;
+        let analyzed = analyze_string::<GoldilocksField>(input);
+        assert_eq!(format!("{analyzed}"), input);
+    }
+
+    #[test]
+    fn fingerprint() {
+        let pil = r#"
+constant %N = 4;
+constant %M = 2;
+namespace Main(%N);
+    col fixed A = [1, 2, 3, 4];
+    col witness x;
+    x = A * %M;
+"#;
+        let analyzed = analyze_string::<GoldilocksField>(pil);
+        assert_eq!(
+            analyzed.fingerprint(),
+            analyze_string::<GoldilocksField>(pil).fingerprint()
+        );
+        assert_ne!(
+            analyzed.fingerprint(),
+            analyze_string::<GoldilocksField>(&pil.replace("%M = 2", "%M = 3")).fingerprint()
+        );
+        assert_ne!(
+            analyzed.fingerprint(),
+            analyze_string::<number::Bn254Field>(pil).fingerprint()
+        );
+    }
 }
@@ -0,0 +1,120 @@
+//! Exports the machines (namespaces) of an analyzed PIL file and the
+//! lookups, permutations and connections between them as a Graphviz graph.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use number::FieldElement;
+
+use crate::util::{namespace, referenced_namespaces};
+use crate::{Analyzed, IdentityKind, PolynomialType};
+
+/// Returns the graph in the DOT language, with a node for every namespace and
+/// an edge from the namespace of the left hand side of an identity to the
+/// namespace of the right hand side, labeled with the number of identities.
+/// The left hand side belongs to the namespaces of its witness columns, if
+/// there are any, so that shared fixed columns (like `Global.L1`) do not
+/// create edges. Identities within a single namespace are not shown.
+pub fn export<T: FieldElement>(analyzed: &Analyzed<T>) -> String {
+    // Column counts and degree by namespace.
+    let mut machines = BTreeMap::<&str, (usize, usize, u64)>::new();
+    for (poly, _) in analyzed.definitions.values() {
+        let (witness, fixed, degree) = machines.entry(namespace(&poly.absolute_name)).or_default();
+        let count = poly.length.unwrap_or(1) as usize;
+        match poly.poly_type {
+            PolynomialType::Committed => *witness += count,
+            PolynomialType::Constant => *fixed += count,
+            PolynomialType::Intermediate => {}
+        }
+        *degree = poly.degree;
+    }
+
+    let mut links = BTreeMap::<_, usize>::new();
+    for identity in &analyzed.identities {
+        let kind = match identity.kind {
+            IdentityKind::Polynomial => continue,
+            IdentityKind::Plookup | IdentityKind::LogUp => "lookup",
+            IdentityKind::Permutation => "permutation",
+            IdentityKind::Connect => "connect",
+        };
+        let from = Some(referenced_namespaces(analyzed, &identity.left, true))
+            .filter(|from| !from.is_empty())
+            .unwrap_or_else(|| referenced_namespaces(analyzed, &identity.left, false));
+        let to = referenced_namespaces(analyzed, &identity.right, false);
+        for from in &from {
+            for to in to.iter().filter(|to| *to != from) {
+                *links.entry((*from, *to, kind)).or_default() += 1;
+            }
+        }
+    }
+
+    let mut dot = String::from("digraph machines {\n    node [shape=box];\n");
+    for (name, (witness, fixed, degree)) in &machines {
+        writeln!(
+            dot,
+            "    \"{name}\" [label=\"{name}\\ndegree {degree}\\n{witness} witness, {fixed} fixed columns\"];"
+        )
+        .unwrap();
+    }
+    for ((from, to, kind), count) in &links {
+        let style = match *kind {
+            "permutation" => "dashed",
+            "connect" => "dotted",
+            _ => "solid",
+        };
+        let plural = if *count == 1 { "" } else { "s" };
+        writeln!(
+            dot,
+            "    \"{from}\" -> \"{to}\" [label=\"{count} {kind}{plural}\", style={style}];"
+        )
+        .unwrap();
+    }
+    dot += "}\n";
+    dot
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use number::GoldilocksField;
+
+    use super::*;
+
+    #[test]
+    fn block_lookup() {
+        let analyzed =
+            crate::analyze::<GoldilocksField>(Path::new("../test_data/pil/block_lookup_or.pil"));
+        assert_eq!(
+            export(&analyzed),
+            r#"digraph machines {
+    node [shape=box];
+    "Main" [label="Main\ndegree 65536\n1 witness, 3 fixed columns"];
+    "Or" [label="Or\ndegree 65536\n6 witness, 5 fixed columns"];
+    "Main" -> "Or" [label="1 lookup", style=solid];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn links_between_namespaces() {
+        let analyzed = crate::analyze_string::<GoldilocksField>(
+            "namespace A(4);
+    col witness x, y;
+    x in B.t;
+    y in B.t;
+    {x} is B.s {B.u};
+    x in x;
+namespace B(4);
+    col fixed t = [0, 1, 2, 3];
+    col fixed s = [1, 0, 0, 0];
+    col witness u;
+",
+        );
+        let dot = export(&analyzed);
+        assert!(dot.contains("\"A\" -> \"B\" [label=\"2 lookups\", style=solid];"));
+        assert!(dot.contains("\"A\" -> \"B\" [label=\"1 permutation\", style=dashed];"));
+        assert!(!dot.contains("\"A\" -> \"A\""));
+    }
+}
@@ -2,18 +2,24 @@ use std::cmp;
 use std::collections::{BTreeMap, HashMap};
 
 use json::{object, JsonValue};
+use number::FieldElement;
 
 use crate::{
     Analyzed, BinaryOperator, Expression, FunctionValueDefinition, IdentityKind,
-    PolynomialReference, PolynomialType, StatementIdentifier, UnaryOperator,
+    PolynomialReference, PolynomialType, StatementIdentifier, Symbol, UnaryOperator,
 };
 
 use self::expression_counter::compute_intermediate_expression_ids;
 
 mod expression_counter;
 
-struct Exporter<'a> {
-    analyzed: &'a Analyzed,
+/// The version of the JSON format, in the `version` field, increased on
+/// incompatible changes. The other fields are those of pilcom, which does not
+/// write a version.
+pub const VERSION: u32 = 1;
+
+struct Exporter<'a, T> {
+    analyzed: &'a Analyzed<T>,
     expressions: Vec<JsonValue>,
     /// Translates from polynomial IDs to expression IDs for intermediate
     /// polynomials.
@@ -21,7 +27,7 @@ struct Exporter<'a> {
     number_q: u64,
 }
 
-pub fn export(analyzed: &Analyzed) -> JsonValue {
+pub fn export<T: FieldElement>(analyzed: &Analyzed<T>) -> JsonValue {
     let mut exporter = Exporter::new(analyzed);
     let mut publics = Vec::new();
     let mut pol_identities = Vec::new();
@@ -77,7 +83,9 @@ pub fn export(analyzed: &Analyzed) -> JsonValue {
                         fileName: file_name,
                         line: line
                     }),
-                    IdentityKind::Plookup => {
+                    // pilcom has no LogUp identities, the multiplicities are only
+                    // needed for the proof, the statement is the same as for a plookup.
+                    IdentityKind::Plookup | IdentityKind::LogUp => {
                         plookup_identities.push(object! {
                             selF: sel_left,
                             f: left,
@@ -110,6 +118,7 @@ pub fn export(analyzed: &Analyzed) -> JsonValue {
         }
     }
     object! {
+        version: VERSION,
         nCommitments: analyzed.commitment_count(),
         nQ: exporter.number_q,
         nIm: analyzed.intermediate_count(),
@@ -124,6 +133,196 @@ pub fn export(analyzed: &Analyzed) -> JsonValue {
     }
 }
 
+/// The JSON Schema document of the output of `export`. It is written by hand
+/// and has to be changed together with `export`, see `crate::json_schema`.
+pub fn schema() -> JsonValue {
+    let id = || object! { type: "integer", minimum: 0 };
+    let ids = || object! { type: "array", items: id() };
+    let optional_id = || object! { type: ["integer", "null"], minimum: 0 };
+    // The identities also have the location of their source.
+    let identity = |mut properties: JsonValue, required: &[&str]| {
+        properties["fileName"] = object! { type: "string" };
+        properties["line"] = id();
+        object! {
+            type: "object",
+            properties: properties,
+            required: [required, &["fileName", "line"]].concat(),
+            additionalProperties: false,
+        }
+    };
+    let selected = || {
+        identity(
+            object! { selF: optional_id(), f: ids(), selT: optional_id(), t: ids() },
+            &["selF", "f", "selT", "t"],
+        )
+    };
+    object! {
+        "$schema": crate::json_schema::DIALECT,
+        title: "powdr PIL JSON",
+        description: "The constraints of an analyzed PIL file, in the format of pilcom with a version.",
+        type: "object",
+        properties: {
+            version: { const: VERSION },
+            nCommitments: id(),
+            nQ: id(),
+            nIm: id(),
+            nConstants: id(),
+            publics: {
+                type: "array",
+                items: {
+                    type: "object",
+                    properties: {
+                        name: { type: "string" },
+                        polId: id(),
+                        polType: { enum: ["cmP", "constP", "imP"] },
+                        idx: id(),
+                        id: id(),
+                    },
+                    required: ["name", "polId", "polType", "idx", "id"],
+                    additionalProperties: false,
+                },
+            },
+            references: {
+                description: "The columns by absolute name.",
+                type: "object",
+                additionalProperties: {
+                    type: "object",
+                    properties: {
+                        type: { enum: ["cmP", "constP", "imP"] },
+                        id: id(),
+                        polDeg: id(),
+                        isArray: { type: "boolean" },
+                        len: id(),
+                    },
+                    required: ["type", "id", "polDeg", "isArray"],
+                    additionalProperties: false,
+                },
+            },
+            expressions: { type: "array", items: { "$ref": "#/$defs/expression" } },
+            polIdentities: {
+                type: "array",
+                items: identity(object! { e: id() }, &["e"]),
+            },
+            plookupIdentities: { type: "array", items: selected() },
+            permutationIdentities: { type: "array", items: selected() },
+            connectionIdentities: {
+                type: "array",
+                items: identity(
+                    object! { pols: ids(), connections: ids() },
+                    &["pols", "connections"],
+                ),
+            },
+        },
+        required: [
+            "version",
+            "nCommitments",
+            "nQ",
+            "nIm",
+            "nConstants",
+            "publics",
+            "references",
+            "expressions",
+            "polIdentities",
+            "plookupIdentities",
+            "permutationIdentities",
+            "connectionIdentities",
+        ],
+        additionalProperties: false,
+        "$defs": {
+            expression: {
+                description: "A node of an expression. References to columns have an id and next, numbers a value and operations the values of their operands.",
+                type: "object",
+                properties: {
+                    op: { enum: ["cm", "const", "exp", "public", "number", "add", "sub", "mul", "pow", "neg"] },
+                    deg: id(),
+                    id: id(),
+                    next: { type: "boolean" },
+                    value: { type: "string" },
+                    values: { type: "array", items: { "$ref": "#/$defs/expression" } },
+                    idQ: id(),
+                    deps: ids(),
+                },
+                required: ["op", "deg"],
+                additionalProperties: false,
+            },
+        },
+    }
+}
+
+/// Removes the version, replaces all idQ values by 99 and converts hex
+/// numbers to decimal, so that the export can be compared to the output of
+/// pilcom: The idQ values are just a counter and pilcom assigns them in a
+/// different order.
+pub fn normalize(v: &mut JsonValue) {
+    v.remove("version");
+    match v {
+        JsonValue::Object(obj) => obj.iter_mut().for_each(|(key, value)| {
+            if key == "idQ" {
+                *value = 99.into();
+            } else if key == "value" {
+                match value.as_str() {
+                    Some(v) if v.starts_with("0x") => {
+                        *value = format!("{}", i64::from_str_radix(&v[2..], 16).unwrap()).into();
+                    }
+                    _ => {}
+                }
+            } else {
+                normalize(value)
+            }
+        }),
+        JsonValue::Array(arr) => arr.iter_mut().for_each(normalize),
+        _ => {}
+    }
+}
+
+/// Returns the paths (like `references.Main.x.id`) at which the two values
+/// differ, with both values.
+pub fn differences(left: &JsonValue, right: &JsonValue) -> Vec<String> {
+    let mut differences = vec![];
+    collect_differences(left, right, "", &mut differences);
+    differences
+}
+
+fn collect_differences(
+    left: &JsonValue,
+    right: &JsonValue,
+    path: &str,
+    differences: &mut Vec<String>,
+) {
+    let child = |key: &dyn std::fmt::Display| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        }
+    };
+    match (left, right) {
+        (JsonValue::Object(l), JsonValue::Object(r)) => {
+            for (key, value) in l.iter() {
+                match r.get(key) {
+                    Some(other) => collect_differences(value, other, &child(&key), differences),
+                    None => differences.push(format!("{}: only in the left value", child(&key))),
+                }
+            }
+            for (key, _) in r.iter().filter(|(key, _)| l.get(key).is_none()) {
+                differences.push(format!("{}: only in the right value", child(&key)));
+            }
+        }
+        (JsonValue::Array(l), JsonValue::Array(r)) if l.len() == r.len() => {
+            for (i, (l, r)) in l.iter().zip(r).enumerate() {
+                collect_differences(l, r, &child(&i), differences);
+            }
+        }
+        (JsonValue::Array(l), JsonValue::Array(r)) => differences.push(format!(
+            "{path}: {} elements and {} elements",
+            l.len(),
+            r.len()
+        )),
+        _ if left != right => differences.push(format!("{path}: {left} and {right}")),
+        _ => {}
+    }
+}
+
 fn polynomial_type_to_json_string(t: PolynomialType) -> &'static str {
     polynomial_reference_type_to_type(polynomial_reference_type_to_json_string(t))
 }
@@ -145,8 +344,8 @@ fn polynomial_reference_type_to_type(t: &str) -> &'static str {
     }
 }
 
-impl<'a> Exporter<'a> {
-    fn new(analyzed: &'a Analyzed) -> Self {
+impl<'a, T: FieldElement> Exporter<'a, T> {
+    fn new(analyzed: &'a Analyzed<T>) -> Self {
         Self {
             analyzed,
             expressions: vec![],
@@ -174,7 +373,7 @@ impl<'a> Exporter<'a> {
                 if poly.is_array() {
                     out["len"] = JsonValue::from(poly.length.unwrap() as i64);
                 }
-                (name.clone(), out)
+                (name.to_string(), out)
             })
             .collect::<BTreeMap<String, JsonValue>>()
             .into()
@@ -182,7 +381,7 @@ impl<'a> Exporter<'a> {
 
     /// Processes the given expression
     /// @returns the expression ID
-    fn extract_expression(&mut self, expr: &Expression, max_degree: u32) -> usize {
+    fn extract_expression(&mut self, expr: &Expression<T>, max_degree: u32) -> usize {
         let id = self.expressions.len();
         let (degree, mut json, dependencies) = self.expression_to_json(expr);
         if degree > max_degree {
@@ -199,21 +398,21 @@ impl<'a> Exporter<'a> {
 
     fn extract_expression_opt(
         &mut self,
-        expr: &Option<Expression>,
+        expr: &Option<Expression<T>>,
         max_degree: u32,
     ) -> Option<usize> {
         expr.as_ref()
             .map(|e| self.extract_expression(e, max_degree))
     }
 
-    fn extract_expression_vec(&mut self, expr: &[Expression], max_degree: u32) -> Vec<usize> {
+    fn extract_expression_vec(&mut self, expr: &[Expression<T>], max_degree: u32) -> Vec<usize> {
         expr.iter()
             .map(|e| self.extract_expression(e, max_degree))
             .collect()
     }
 
     /// returns the degree, the JSON value and the dependencies (intermediate polynomial IDs)
-    fn expression_to_json(&self, expr: &Expression) -> (u32, JsonValue, Vec<u64>) {
+    fn expression_to_json(&self, expr: &Expression<T>) -> (u32, JsonValue, Vec<u64>) {
         match expr {
             Expression::Constant(name) => (
                 1,
@@ -222,7 +421,7 @@ impl<'a> Exporter<'a> {
                     op: "const",
                     deg: 1,
                     // TODO is it declarations or constants?
-                    id: self.analyzed.definitions[name].0.id,
+                    id: self.analyzed.definitions[&Symbol::intern(name)].0.id,
                     next: false
                 },
                 Vec::new(),
@@ -247,13 +446,14 @@ impl<'a> Exporter<'a> {
                 object! {
                     op: "number",
                     deg: 0,
-                    value: format!("{value}"),
+                    value: value.to_signed_integer().to_string(),
                 },
                 Vec::new(),
             ),
             Expression::BinaryOperation(left, op, right) => {
-                let (deg_left, left, deps_left) = self.expression_to_json(left);
-                let (deg_right, right, deps_right) = self.expression_to_json(right);
+                let expressions = &self.analyzed.expressions;
+                let (deg_left, left, deps_left) = self.expression_to_json(&expressions[*left]);
+                let (deg_right, right, deps_right) = self.expression_to_json(&expressions[*right]);
                 let (op, degree) = match op {
                     BinaryOperator::Add => ("add", cmp::max(deg_left, deg_right)),
                     BinaryOperator::Sub => ("sub", cmp::max(deg_left, deg_right)),
@@ -287,7 +487,8 @@ impl<'a> Exporter<'a> {
                 )
             }
             Expression::UnaryOperation(op, value) => {
-                let (deg, value, deps) = self.expression_to_json(value);
+                let (deg, value, deps) =
+                    self.expression_to_json(&self.analyzed.expressions[*value]);
                 match op {
                     UnaryOperator::Plus => (deg, value, deps),
                     UnaryOperator::Minus => (
@@ -343,6 +544,8 @@ mod test {
     use std::fs;
     use std::process::Command;
 
+    use number::GoldilocksField;
+
     use super::*;
 
     fn generate_json_pair(file: &str) -> (JsonValue, JsonValue) {
@@ -351,8 +554,10 @@ mod test {
 
         let file = std::path::PathBuf::from("../test_data/polygon-hermez/").join(file);
 
-        let analyzed = crate::analyze(&file);
-        let json_out = export(&analyzed);
+        let analyzed = crate::analyze::<GoldilocksField>(&file);
+        let mut json_out = export(&analyzed);
+        // pilcom does not write a version.
+        assert_eq!(json_out.remove("version"), VERSION);
 
         let pilcom = std::env::var("PILCOM").expect(
             "Please set the PILCOM environment variable to the path to the pilcom repository.",
@@ -387,37 +592,31 @@ mod test {
         assert_eq!(json_out, pilcom_parsed);
     }
 
-    /// Normalizes the json in that it replaces all idQ values by "99"
-    /// and converts hex numbers to decimal.
-    fn normalize_idq_and_hex(v: &mut JsonValue) {
-        match v {
-            JsonValue::Object(obj) => obj.iter_mut().for_each(|(key, value)| {
-                if key == "idQ" {
-                    *value = 99.into();
-                } else if key == "value" {
-                    match value.as_str() {
-                        Some(v) if v.starts_with("0x") => {
-                            *value =
-                                format!("{}", i64::from_str_radix(&v[2..], 16).unwrap()).into();
-                        }
-                        _ => {}
-                    }
-                } else {
-                    normalize_idq_and_hex(value)
-                }
-            }),
-            JsonValue::Array(arr) => arr.iter_mut().for_each(normalize_idq_and_hex),
-            _ => {}
-        }
-    }
-
     fn compare_export_file_ignore_idq_hex(file: &str) {
         let (mut json_out, mut pilcom_parsed) = generate_json_pair(file);
-        normalize_idq_and_hex(&mut json_out);
-        normalize_idq_and_hex(&mut pilcom_parsed);
+        normalize(&mut json_out);
+        normalize(&mut pilcom_parsed);
         assert_eq!(json_out, pilcom_parsed);
     }
 
+    #[test]
+    fn differences_of_values() {
+        let mut left =
+            json::parse(r#"{"a": [1, {"idQ": 3}], "b": {"value": "0x10"}, "c": 1}"#).unwrap();
+        let right =
+            json::parse(r#"{"a": [2, {"idQ": 99}], "b": {"value": "16"}, "d": 1}"#).unwrap();
+        normalize(&mut left);
+        assert_eq!(
+            differences(&left, &right),
+            [
+                "a.0: 1 and 2",
+                "c: only in the left value",
+                "d: only in the right value"
+            ]
+        );
+        assert!(differences(&right, &right).is_empty());
+    }
+
     #[test]
     fn export_config() {
         compare_export_file("config.pil");
@@ -476,4 +675,22 @@ mod test {
         compare_export_file_ignore_idq_hex("rom.pil");
         compare_export_file_ignore_idq_hex("main.pil");
     }
+
+    #[test]
+    fn export_matches_schema() {
+        let schema = schema();
+        for file in [
+            "../test_data/polygon-hermez/main.pil",
+            "../test_data/pil/fibonacci.pil",
+            "../test_data/pil/conditional.pil",
+        ] {
+            let analyzed = crate::analyze::<GoldilocksField>(std::path::Path::new(file));
+            let json = export(&analyzed);
+            assert_eq!(
+                crate::json_schema::validate(&schema, &json),
+                Ok(()),
+                "{file}"
+            );
+        }
+    }
 }
@@ -0,0 +1,195 @@
+//! Validation of JSON values against the JSON Schema documents of the
+//! exported artifacts, see `json_exporter::schema` and the schemas of the
+//! proof artifacts in the backend.
+//!
+//! The schemas are not derived from Rust types: The exported JSON is built
+//! with `json::object!`, not serialized from types, so each schema is written
+//! next to the code that writes the JSON. The tests of the exporters validate
+//! their output against the schemas, which catches most changes to one that
+//! are not made to the other, but not, for example, a schema that allows
+//! more than the exporter writes.
+//!
+//! This is not a general JSON Schema validator, it is only meant for these
+//! schemas. Only the keywords they use are supported: `type`, `const`,
+//! `enum`, `minimum`, `properties`, `required`, `additionalProperties` (as a
+//! boolean or a schema), `items`, `oneOf` and `$ref` into the `$defs` of the
+//! root schema. Other keywords are ignored, use a complete implementation to
+//! validate against other schemas.
+
+use json::JsonValue;
+
+/// The JSON Schema dialect of the schema documents.
+pub const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Checks the value against the schema.
+/// Fails with the path of the first value that does not match, like
+/// `expressions.3.op`.
+pub fn validate(schema: &JsonValue, value: &JsonValue) -> Result<(), String> {
+    Validator { root: schema }.validate(schema, value, "")
+}
+
+struct Validator<'a> {
+    root: &'a JsonValue,
+}
+
+impl<'a> Validator<'a> {
+    fn validate(&self, schema: &'a JsonValue, value: &JsonValue, path: &str) -> Result<(), String> {
+        let fail = |message: String| {
+            Err(if path.is_empty() {
+                message
+            } else {
+                format!("{path}: {message}")
+            })
+        };
+        if let Some(reference) = schema["$ref"].as_str() {
+            let definition = reference
+                .strip_prefix("#/$defs/")
+                .map(|name| &self.root["$defs"][name])
+                .filter(|definition| !definition.is_null())
+                .ok_or_else(|| format!("Unsupported reference {reference} in schema."))?;
+            self.validate(definition, value, path)?;
+        }
+        if !schema["type"].is_null() {
+            let types = if schema["type"].is_array() {
+                schema["type"].members().collect::<Vec<_>>()
+            } else {
+                vec![&schema["type"]]
+            };
+            if !types
+                .iter()
+                .any(|t| t.as_str().is_some_and(|t| has_type(value, t)))
+            {
+                return fail(format!("expected {}, found {value}", schema["type"]));
+            }
+        }
+        if schema.has_key("const") && schema["const"] != *value {
+            return fail(format!("expected {}, found {value}", schema["const"]));
+        }
+        if schema["enum"].is_array() && !schema["enum"].members().any(|v| v == value) {
+            return fail(format!("expected one of {}, found {value}", schema["enum"]));
+        }
+        if let (Some(minimum), Some(number)) = (schema["minimum"].as_f64(), value.as_f64()) {
+            if number < minimum {
+                return fail(format!("expected at least {minimum}, found {value}"));
+            }
+        }
+        if schema["oneOf"].is_array() {
+            let matching = schema["oneOf"]
+                .members()
+                .filter(|option| self.validate(option, value, path).is_ok())
+                .count();
+            if matching != 1 {
+                return fail(format!(
+                    "expected exactly one matching alternative, found {matching}"
+                ));
+            }
+        }
+        let child = |key: &dyn std::fmt::Display| {
+            if path.is_empty() {
+                key.to_string()
+            } else {
+                format!("{path}.{key}")
+            }
+        };
+        if let JsonValue::Object(object) = value {
+            for required in schema["required"].members() {
+                let key = required.as_str().unwrap();
+                if object.get(key).is_none() {
+                    return fail(format!("missing property {key}"));
+                }
+            }
+            for (key, property) in object.iter() {
+                let property_schema = &schema["properties"][key];
+                if !property_schema.is_null() {
+                    self.validate(property_schema, property, &child(&key))?;
+                } else if schema["additionalProperties"] == false {
+                    return fail(format!("unexpected property {key}"));
+                } else if schema["additionalProperties"].is_object() {
+                    self.validate(&schema["additionalProperties"], property, &child(&key))?;
+                }
+            }
+        }
+        if value.is_array() && schema["items"].is_object() {
+            for (i, item) in value.members().enumerate() {
+                self.validate(&schema["items"], item, &child(&i))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn has_type(value: &JsonValue, t: &str) -> bool {
+    match t {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.as_f64().is_some_and(|v| v.fract() == 0.0),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use json::object;
+
+    use super::*;
+
+    #[test]
+    fn keywords() {
+        let schema = object! {
+            "$defs": {
+                node: {
+                    type: "object",
+                    properties: {
+                        op: { enum: ["add", "number"] },
+                        values: { type: "array", items: { "$ref": "#/$defs/node" } },
+                    },
+                    required: ["op"],
+                    additionalProperties: false,
+                }
+            },
+            type: "object",
+            properties: {
+                version: { const: 1 },
+                root: { "$ref": "#/$defs/node" },
+                count: { type: "integer", minimum: 0 },
+            },
+            required: ["version", "root"],
+        };
+        let valid = object! {
+            version: 1,
+            root: { op: "add", values: [{ op: "number" }, { op: "number" }] },
+            count: 3,
+        };
+        assert_eq!(validate(&schema, &valid), Ok(()));
+
+        let mut wrong_version = valid.clone();
+        wrong_version["version"] = 2.into();
+        assert_eq!(
+            validate(&schema, &wrong_version),
+            Err("version: expected 1, found 2".to_string())
+        );
+        let mut nested = valid.clone();
+        nested["root"]["values"][1]["op"] = "mul".into();
+        assert_eq!(
+            validate(&schema, &nested),
+            Err(r#"root.values.1.op: expected one of ["add","number"], found mul"#.to_string())
+        );
+        let mut extra = valid.clone();
+        extra["root"]["deg"] = 1.into();
+        assert_eq!(
+            validate(&schema, &extra),
+            Err("root: unexpected property deg".to_string())
+        );
+        let mut negative = valid;
+        negative["count"] = (-1).into();
+        assert!(validate(&schema, &negative).is_err());
+        assert_eq!(
+            validate(&schema, &object! { version: 1 }),
+            Err("missing property root".to_string())
+        );
+    }
+}
@@ -1,40 +1,152 @@
+pub mod arena;
+pub mod builder;
+pub mod diagnostics;
 pub mod display;
+pub mod dot_exporter;
 pub mod json_exporter;
+pub mod json_schema;
 pub mod pil_analyzer;
+pub mod r1cs_exporter;
+pub mod row_evaluator;
+pub mod smt_exporter;
+pub mod stats;
+pub mod symbol;
 pub mod util;
 
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+pub use arena::{ExpressionArena, ExpressionId};
 use number::{DegreeType, FieldElement};
 pub use parser::ast::{BinaryOperator, UnaryOperator};
+use parser_util::diagnostics::Diagnostic;
+pub use symbol::Symbol;
 
-pub fn analyze(path: &Path) -> Analyzed {
+/// Analyzes the PIL file and panics with the rendered error if it is invalid,
+/// see `try_analyze` for the error.
+pub fn analyze<T: FieldElement>(path: &Path) -> Analyzed<T> {
+    try_analyze(path).unwrap_or_else(|e| panic!("{e}"))
+}
+
+pub fn try_analyze<T: FieldElement>(path: &Path) -> Result<Analyzed<T>, Diagnostic> {
     pil_analyzer::process_pil_file(path)
 }
 
-pub fn analyze_string(contents: &str) -> Analyzed {
+/// Analyzes the PIL file, using the statements after `#if NAME` if `NAME` is
+/// in `defines` and the ones after `#else` otherwise.
+pub fn analyze_with_defines<T: FieldElement>(
+    path: &Path,
+    defines: BTreeSet<String>,
+) -> Analyzed<T> {
+    try_analyze_with_defines(path, defines).unwrap_or_else(|e| panic!("{e}"))
+}
+
+pub fn try_analyze_with_defines<T: FieldElement>(
+    path: &Path,
+    defines: BTreeSet<String>,
+) -> Result<Analyzed<T>, Diagnostic> {
+    pil_analyzer::process_pil_file_with_defines(path, defines)
+}
+
+pub fn analyze_string<T: FieldElement>(contents: &str) -> Analyzed<T> {
+    try_analyze_string(contents).unwrap_or_else(|e| panic!("{e}"))
+}
+
+pub fn try_analyze_string<T: FieldElement>(contents: &str) -> Result<Analyzed<T>, Diagnostic> {
     pil_analyzer::process_pil_file_contents(contents)
 }
 
+/// Analyzes the PIL file `main` with the files it includes taken from `sources`
+/// (contents by path) instead of the file system, for example in the browser.
+pub fn analyze_sources<T: FieldElement>(
+    main: &Path,
+    sources: HashMap<PathBuf, String>,
+) -> Result<Analyzed<T>, Diagnostic> {
+    pil_analyzer::process_pil_sources(main, sources)
+}
+
+/// Analyzes the PIL file and also returns the state of the analyzer at its end,
+/// to process further expressions in the context of the file.
+pub fn analyze_with_context<T: FieldElement>(
+    path: &Path,
+) -> Result<(Analyzed<T>, pil_analyzer::ExpressionContext<T>), Diagnostic> {
+    pil_analyzer::process_pil_file_with_context(path)
+}
+
+/// Returns the tests declared with `#[test]` in the PIL file and the files it
+/// includes, in the order of their declaration.
+pub fn tests<T: FieldElement>(
+    path: &Path,
+    defines: BTreeSet<String>,
+) -> Result<Vec<PilTest>, Diagnostic> {
+    pil_analyzer::collect_tests::<T>(path, defines)
+}
+
+/// Analyzes the PIL file for running the test: with the statements of the
+/// test and with the degree of the test for all namespaces.
+pub fn analyze_test<T: FieldElement>(
+    path: &Path,
+    defines: BTreeSet<String>,
+    test: &PilTest,
+) -> Result<Analyzed<T>, Diagnostic> {
+    pil_analyzer::process_pil_file_for_test(path, defines, test)
+}
+
+/// A test declared with `#[test] name(degree) { ... }` in a PIL file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PilTest {
+    pub name: String,
+    pub source: SourceRef,
+    /// The degree of all namespaces while the test is run.
+    pub degree: DegreeType,
+    /// If the test is declared with `#[should_fail]`, it passes if the
+    /// witness cannot be generated or violates the identities.
+    pub should_fail: bool,
+}
+
+/// Returns the PIL file and all files it includes, for example to watch them for changes.
+pub fn included_files<T: FieldElement>(path: &Path) -> Vec<PathBuf> {
+    pil_analyzer::included_files::<T>(path)
+}
+
+#[derive(Clone)]
 pub enum StatementIdentifier {
-    Definition(String),
+    Definition(Symbol),
     PublicDeclaration(String),
     Identity(usize),
 }
 
-pub struct Analyzed {
+#[derive(Clone)]
+pub struct Analyzed<T> {
     /// Constants are not namespaced!
-    pub constants: HashMap<String, FieldElement>,
-    pub definitions: HashMap<String, (Polynomial, Option<FunctionValueDefinition>)>,
+    pub constants: HashMap<String, T>,
+    pub definitions: HashMap<Symbol, (Polynomial, Option<FunctionValueDefinition<T>>)>,
     pub public_declarations: HashMap<String, PublicDeclaration>,
-    pub identities: Vec<Identity>,
+    pub identities: Vec<Identity<T>>,
     /// The order in which definitions and identities
     /// appear in the source.
     pub source_order: Vec<StatementIdentifier>,
+    /// The operands of the expressions of the definitions and identities.
+    pub expressions: ExpressionArena<T>,
+    /// The contents of the analyzed files by the file name in the source
+    /// references, to show their lines in diagnostics. Files whose names
+    /// are not unique are left out.
+    pub sources: BTreeMap<String, Arc<str>>,
+    /// The source locations of the values of the pc column, for PIL that was
+    /// generated from a program, see `set_pc_locations`.
+    pub pc_locations: Option<PcLocations>,
+}
+
+/// The source locations of the lines of a program, indexed by the value of
+/// its pc column, to show where in the program a row fails.
+#[derive(Clone)]
+pub struct PcLocations {
+    pub pc: Symbol,
+    pub locations: BTreeMap<DegreeType, String>,
 }
 
-impl Analyzed {
+impl<T: FieldElement> Analyzed<T> {
     /// @returns the number of committed polynomials (with multiplicities for arrays)
     pub fn commitment_count(&self) -> usize {
         self.declaration_type_count(PolynomialType::Committed)
@@ -48,22 +160,34 @@ impl Analyzed {
         self.declaration_type_count(PolynomialType::Constant)
     }
 
+    /// @returns a fingerprint of the field and the printed PIL file, which is
+    /// stable across runs and can be used to cache data that only depends on
+    /// the constraints and fixed columns.
+    pub fn fingerprint(&self) -> u64 {
+        // 64 bit FNV-1a
+        format!("{}\n{self}", T::modulus())
+            .bytes()
+            .fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            })
+    }
+
     pub fn constant_polys_in_source_order(
         &self,
-    ) -> Vec<&(Polynomial, Option<FunctionValueDefinition>)> {
+    ) -> Vec<&(Polynomial, Option<FunctionValueDefinition<T>>)> {
         self.definitions_in_source_order(PolynomialType::Constant)
     }
 
     pub fn committed_polys_in_source_order(
         &self,
-    ) -> Vec<&(Polynomial, Option<FunctionValueDefinition>)> {
+    ) -> Vec<&(Polynomial, Option<FunctionValueDefinition<T>>)> {
         self.definitions_in_source_order(PolynomialType::Committed)
     }
 
     pub fn definitions_in_source_order(
         &self,
         poly_type: PolynomialType,
-    ) -> Vec<&(Polynomial, Option<FunctionValueDefinition>)> {
+    ) -> Vec<&(Polynomial, Option<FunctionValueDefinition<T>>)> {
         self.source_order
             .iter()
             .filter_map(move |statement| {
@@ -78,6 +202,24 @@ impl Analyzed {
             .collect()
     }
 
+    /// Sets the origins of the identities from a map from lines of the
+    /// source to origins, for PIL that was generated from other sources,
+    /// see `Identity::origin`.
+    pub fn set_identity_origins(&mut self, origins: &BTreeMap<usize, String>) {
+        for identity in &mut self.identities {
+            identity.origin = origins.get(&identity.source.line).cloned();
+        }
+    }
+
+    /// Sets the source locations of the values of the pc column `pc`, for
+    /// PIL that was generated from a program, see `pc_locations`.
+    pub fn set_pc_locations(&mut self, pc: &str, locations: BTreeMap<DegreeType, String>) {
+        self.pc_locations = Some(PcLocations {
+            pc: Symbol::intern(pc),
+            locations,
+        });
+    }
+
     fn declaration_type_count(&self, poly_type: PolynomialType) -> usize {
         self.definitions
             .iter()
@@ -92,10 +234,11 @@ impl Analyzed {
     }
 }
 
+#[derive(Clone)]
 pub struct Polynomial {
     pub id: u64,
     pub source: SourceRef,
-    pub absolute_name: String,
+    pub absolute_name: Symbol,
     pub poly_type: PolynomialType,
     pub degree: DegreeType,
     pub length: Option<DegreeType>,
@@ -107,19 +250,21 @@ impl Polynomial {
     }
 }
 
-pub enum FunctionValueDefinition {
-    Mapping(Expression),
-    Array(Vec<RepeatedArray>),
-    Query(Expression),
+#[derive(Clone)]
+pub enum FunctionValueDefinition<T> {
+    Mapping(Expression<T>),
+    Array(Vec<RepeatedArray<T>>),
+    Query(Expression<T>),
 }
 
 /// An array of elements that might be repeated (the whole list is repeated).
-pub struct RepeatedArray {
-    pub values: Vec<Expression>,
+#[derive(Clone)]
+pub struct RepeatedArray<T> {
+    pub values: Vec<Expression<T>>,
     pub repetitions: DegreeType,
 }
 
-impl RepeatedArray {
+impl<T: FieldElement> RepeatedArray<T> {
     /// Returns the number of elements in this array (including repetitions).
     pub fn size(&self) -> DegreeType {
         if self.repetitions == 0 {
@@ -132,6 +277,7 @@ impl RepeatedArray {
     }
 }
 
+#[derive(Clone)]
 pub struct PublicDeclaration {
     pub id: u64,
     pub source: SourceRef,
@@ -142,52 +288,62 @@ pub struct PublicDeclaration {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Identity {
+pub struct Identity<T> {
     /// The ID is specific to the kind.
     pub id: u64,
     pub kind: IdentityKind,
     pub source: SourceRef,
     /// For a simple polynomial identity, the selector contains
     /// the actual expression.
-    pub left: SelectedExpressions,
-    pub right: SelectedExpressions,
+    pub left: SelectedExpressions<T>,
+    pub right: SelectedExpressions<T>,
+    /// For a LogUp identity, the witness column that holds how often
+    /// the row of the right hand side is looked up.
+    pub multiplicity: Option<PolynomialReference>,
+    /// What the identity was generated from if the PIL was generated, for
+    /// example "instruction `mstore` at program.asm:117".
+    pub origin: Option<String>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum IdentityKind {
     Polynomial,
     Plookup,
+    /// A lookup with a multiplicity column, proven with logarithmic derivatives
+    /// by backends that support it and as a plain lookup otherwise.
+    LogUp,
     Permutation,
     Connect,
 }
 
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
-pub struct SelectedExpressions {
-    pub selector: Option<Expression>,
-    pub expressions: Vec<Expression>,
+pub struct SelectedExpressions<T> {
+    pub selector: Option<Expression<T>>,
+    pub expressions: Vec<Expression<T>>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub enum Expression {
+pub enum Expression<T> {
     Constant(String),
     PolynomialReference(PolynomialReference),
     LocalVariableReference(u64),
     PublicReference(String),
-    Number(FieldElement),
+    Number(T),
     String(String),
-    Tuple(Vec<Expression>),
-    BinaryOperation(Box<Expression>, BinaryOperator, Box<Expression>),
-    UnaryOperation(UnaryOperator, Box<Expression>),
+    Tuple(Vec<Expression<T>>),
+    /// The operands are in the `ExpressionArena` of the analyzed file.
+    BinaryOperation(ExpressionId, BinaryOperator, ExpressionId),
+    UnaryOperation(UnaryOperator, ExpressionId),
     /// Call to a non-macro function (like a constant polynomial)
-    FunctionCall(String, Vec<Expression>),
-    MatchExpression(Box<Expression>, Vec<(Option<FieldElement>, Expression)>),
+    FunctionCall(String, Vec<Expression<T>>),
+    MatchExpression(ExpressionId, Vec<(Option<T>, Expression<T>)>),
 }
 
 #[derive(Debug, PartialEq, Eq, Default, Clone)]
 pub struct PolynomialReference {
-    // TODO would be better to use numeric IDs instead of names,
-    // but the IDs as they are overlap. Maybe we can change that.
-    pub name: String,
+    /// The absolute name of the polynomial, interned because references
+    /// are compared and looked up on the hot paths of witness generation.
+    pub name: Symbol,
     pub index: Option<u64>,
     pub next: bool,
 }
@@ -1,39 +1,268 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
 
 use number::DegreeType;
 use parser::ast;
 pub use parser::ast::{BinaryOperator, UnaryOperator};
+use parser_util::diagnostics::Diagnostic;
 
 use super::*;
 
-pub fn process_pil_file(path: &Path) -> Analyzed {
+pub fn process_pil_file<T: FieldElement>(path: &Path) -> Result<Analyzed<T>, Diagnostic> {
     let mut ctx = PILContext::new();
-    ctx.process_file(path);
-    ctx.into()
+    ctx.process_file(path).map_err(|e| ctx.with_sources(e))?;
+    ctx.finish()
 }
 
-pub fn process_pil_file_contents(contents: &str) -> Analyzed {
+/// Processes the PIL file with the branches of `#if NAME` chosen by whether
+/// `NAME` is in `defines`.
+pub fn process_pil_file_with_defines<T: FieldElement>(
+    path: &Path,
+    defines: BTreeSet<String>,
+) -> Result<Analyzed<T>, Diagnostic> {
     let mut ctx = PILContext::new();
-    ctx.process_file_contents(Path::new("input"), contents);
-    ctx.into()
+    ctx.defines = defines;
+    ctx.process_file(path).map_err(|e| ctx.with_sources(e))?;
+    ctx.finish()
+}
+
+/// Processes the PIL file without any test and returns the tests declared
+/// with `#[test]`.
+pub fn collect_tests<T: FieldElement>(
+    path: &Path,
+    defines: BTreeSet<String>,
+) -> Result<Vec<PilTest>, Diagnostic> {
+    let mut ctx = PILContext::<T>::new();
+    ctx.defines = defines;
+    ctx.process_file(path).map_err(|e| ctx.with_sources(e))?;
+    Ok(ctx.tests)
+}
+
+/// Processes the PIL file with the statements of the test and the degree of
+/// all namespaces replaced by the degree of the test.
+pub fn process_pil_file_for_test<T: FieldElement>(
+    path: &Path,
+    defines: BTreeSet<String>,
+    test: &PilTest,
+) -> Result<Analyzed<T>, Diagnostic> {
+    let mut ctx = PILContext::new();
+    ctx.defines = defines;
+    ctx.test = Some(test.clone());
+    ctx.process_file(path).map_err(|e| ctx.with_sources(e))?;
+    ctx.finish()
+}
+
+pub fn process_pil_file_contents<T: FieldElement>(
+    contents: &str,
+) -> Result<Analyzed<T>, Diagnostic> {
+    let mut ctx = PILContext::new();
+    ctx.process_file_contents(Path::new("input"), contents)
+        .map_err(|e| ctx.with_sources(e))?;
+    ctx.finish()
+}
+
+pub fn process_pil_sources<T: FieldElement>(
+    main: &Path,
+    sources: HashMap<PathBuf, String>,
+) -> Result<Analyzed<T>, Diagnostic> {
+    let mut ctx = PILContext::new();
+    ctx.sources = Some(
+        sources
+            .into_iter()
+            .map(|(path, contents)| (normalize(&path), contents))
+            .collect(),
+    );
+    ctx.process_file(main).map_err(|e| ctx.with_sources(e))?;
+    ctx.finish()
+}
+
+pub fn process_pil_file_with_context<T: FieldElement>(
+    path: &Path,
+) -> Result<(Analyzed<T>, ExpressionContext<T>), Diagnostic> {
+    let mut ctx = PILContext::new();
+    ctx.process_file(path).map_err(|e| ctx.with_sources(e))?;
+    ctx.check_multiplicities()
+        .map_err(|e| ctx.with_sources(e))?;
+    // Identities created by macros later on refer to lines of the main file.
+    ctx.current_file = path.to_path_buf();
+    ctx.line_starts = parser_util::lines::compute_line_starts(&fs::read_to_string(path).unwrap());
+    let analyzed = Analyzed {
+        constants: ctx.constants.clone(),
+        definitions: std::mem::take(&mut ctx.definitions),
+        public_declarations: std::mem::take(&mut ctx.public_declarations),
+        identities: std::mem::take(&mut ctx.identities),
+        source_order: std::mem::take(&mut ctx.source_order),
+        // The context keeps the operands as well, the expressions it
+        // processes later refer to them.
+        expressions: ctx.expressions.clone(),
+        sources: ctx.unique_sources(),
+        pc_locations: None,
+    };
+    Ok((analyzed, ExpressionContext(ctx)))
+}
+
+/// A processed expression with the identities the macros in it create.
+pub type Expanded<E, T> = (E, Vec<Identity<T>>);
+
+/// The state of the analyzer at the end of a PIL file, to process further
+/// expressions with the constants, macros and namespace of the file.
+pub struct ExpressionContext<T>(PILContext<T>);
+
+impl<T: FieldElement> ExpressionContext<T> {
+    pub fn namespace(&self) -> &str {
+        &self.0.namespace
+    }
+
+    pub fn set_namespace(&mut self, namespace: &str) {
+        self.0.namespace = namespace.to_string();
+    }
+
+    /// Returns the name prefixed with the current namespace,
+    /// unless it already has a namespace.
+    pub fn namespaced(&self, name: &str) -> String {
+        if name.contains('.') {
+            name.to_string()
+        } else {
+            self.0.namespaced(name)
+        }
+    }
+
+    pub fn macro_definition(&self, name: &str) -> Option<&MacroDefinition<T>> {
+        self.0.macros.get(name)
+    }
+
+    /// The operands of the expressions of the analyzed file and of the
+    /// expressions processed so far.
+    pub fn expressions(&self) -> &ExpressionArena<T> {
+        &self.0.expressions
+    }
+
+    /// Processes the expression and expands the macros in it.
+    /// Also returns the identities the macros create, which are not
+    /// added to the analyzed file.
+    pub fn process_expression(
+        &mut self,
+        expr: &ast::Expression<T>,
+    ) -> Result<Expanded<Expression<T>, T>, Diagnostic> {
+        self.collect_identities(|ctx| ctx.process_expression(expr))
+    }
+
+    /// Expands a call of the macro, which can also be one that
+    /// only creates identities.
+    pub fn expand_macro(
+        &mut self,
+        name: &str,
+        arguments: &[ast::Expression<T>],
+    ) -> Result<Expanded<Option<Expression<T>>, T>, Diagnostic> {
+        self.collect_identities(|ctx| ctx.process_macro_call(name, arguments))
+    }
+
+    fn collect_identities<R>(
+        &mut self,
+        process: impl FnOnce(&mut PILContext<T>) -> Result<R, Diagnostic>,
+    ) -> Result<Expanded<R, T>, Diagnostic> {
+        // Reset the state in case an earlier call failed.
+        self.0.macro_arguments = None;
+        self.0.local_variables.clear();
+        self.0.identities.clear();
+        self.0.source_order.clear();
+        let identity_counter = self.0.identity_counter.clone();
+        let result = process(&mut self.0);
+        self.0.identity_counter = identity_counter;
+        Ok((result?, std::mem::take(&mut self.0.identities)))
+    }
+}
+
+/// Returns the PIL file and all files it includes, directly or indirectly.
+/// Files that cannot be read or parsed are returned without their includes.
+pub fn included_files<T: FieldElement>(path: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(file) = pending.pop() {
+        let file = file.canonicalize().unwrap_or(file);
+        if files.contains(&file) {
+            continue;
+        }
+        if let Ok(contents) = fs::read_to_string(&file) {
+            if let Ok(ast) = parser::parse::<T>(file.to_str(), &contents) {
+                let mut includes = vec![];
+                collect_includes(&ast.0, &mut includes);
+                pending.extend(
+                    includes
+                        .into_iter()
+                        .rev()
+                        .map(|include| file.parent().unwrap().join(include)),
+                );
+            }
+        }
+        files.push(file);
+    }
+    files
+}
+
+/// Collects the included files, in both branches of conditionals and in tests.
+fn collect_includes<'a, T>(statements: &'a [ast::Statement<T>], includes: &mut Vec<&'a str>) {
+    for statement in statements {
+        match statement {
+            ast::Statement::Include(_, include) => includes.push(include),
+            ast::Statement::Conditional(_, _, then, otherwise) => {
+                collect_includes(then, includes);
+                collect_includes(otherwise, includes);
+            }
+            ast::Statement::Test(_, _, _, _, body) => collect_includes(body, includes),
+            _ => {}
+        }
+    }
+}
+
+/// Returns the error with the lines of both definitions of the name.
+fn defined_twice(kind: &str, name: &str, source: &SourceRef, previous: &SourceRef) -> Diagnostic {
+    Diagnostic::error(format!("{kind} {name} was defined twice."))
+        .with_line(&source.file, source.line, "defined again here")
+        .with_secondary_line(&previous.file, previous.line, "first defined here")
+}
+
+fn error<R>(message: impl Into<String>) -> Result<R, Diagnostic> {
+    Err(Diagnostic::error(message))
+}
+
+/// Removes `.` and resolves `..` in the path without accessing the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
 }
 
 #[derive(Default)]
-struct PILContext {
+struct PILContext<T> {
     namespace: String,
     polynomial_degree: DegreeType,
     /// Constants are not namespaced!
-    constants: HashMap<String, FieldElement>,
-    definitions: HashMap<String, (Polynomial, Option<FunctionValueDefinition>)>,
+    constants: HashMap<String, T>,
+    definitions: HashMap<Symbol, (Polynomial, Option<FunctionValueDefinition<T>>)>,
     public_declarations: HashMap<String, PublicDeclaration>,
-    macros: HashMap<String, MacroDefinition>,
-    identities: Vec<Identity>,
+    macros: HashMap<String, MacroDefinition<T>>,
+    identities: Vec<Identity<T>>,
     /// The order in which definitions and identities
     /// appear in the source.
     source_order: Vec<StatementIdentifier>,
+    expressions: ExpressionArena<T>,
     included_files: HashSet<PathBuf>,
+    /// The contents of the files by path, if they are not read from the file system.
+    sources: Option<HashMap<PathBuf, String>>,
+    /// The contents of the processed files by the file name in their source
+    /// references, `None` if several files have the name.
+    file_contents: BTreeMap<String, Option<Arc<str>>>,
     line_starts: Vec<usize>,
     current_file: PathBuf,
     commit_poly_counter: u64,
@@ -42,139 +271,242 @@ struct PILContext {
     identity_counter: HashMap<IdentityKind, u64>,
     local_variables: HashMap<String, u64>,
     /// If we are evaluating a macro, this holds the arguments.
-    macro_arguments: Option<Vec<Expression>>,
+    macro_arguments: Option<Vec<Expression<T>>>,
+    /// The names that are defined for `#if`.
+    defines: BTreeSet<String>,
+    /// The tests declared so far.
+    tests: Vec<PilTest>,
+    /// The test that is run, whose statements are used.
+    test: Option<PilTest>,
 }
 
 #[derive(Debug)]
-pub struct MacroDefinition {
+pub struct MacroDefinition<T> {
     pub source: SourceRef,
     pub absolute_name: String,
     pub parameters: Vec<String>,
-    pub identities: Vec<ast::Statement>,
-    pub expression: Option<ast::Expression>,
+    pub identities: Vec<ast::Statement<T>>,
+    pub expression: Option<ast::Expression<T>>,
 }
 
-impl From<PILContext> for Analyzed {
-    fn from(
-        PILContext {
+impl<T> From<PILContext<T>> for Analyzed<T> {
+    fn from(ctx: PILContext<T>) -> Self {
+        let sources = ctx.unique_sources();
+        let PILContext {
             constants,
             definitions,
             public_declarations,
             identities,
             source_order,
+            expressions,
             ..
-        }: PILContext,
-    ) -> Self {
+        } = ctx;
         Self {
             constants,
             definitions,
             public_declarations,
             identities,
             source_order,
+            expressions,
+            sources,
+            pc_locations: None,
         }
     }
 }
 
-impl PILContext {
-    pub fn new() -> PILContext {
+impl<T> PILContext<T> {
+    fn unique_sources(&self) -> BTreeMap<String, Arc<str>> {
+        self.file_contents
+            .iter()
+            .filter_map(|(file, contents)| Some((file.clone(), contents.clone()?)))
+            .collect()
+    }
+
+    /// Adds the contents of the processed files to the error, to show the
+    /// lines it refers to.
+    fn with_sources(&self, error: Diagnostic) -> Diagnostic {
+        self.unique_sources()
+            .into_iter()
+            .fold(error, |error, (file, contents)| {
+                error.with_source(&file, contents)
+            })
+    }
+}
+
+impl<T: FieldElement> PILContext<T> {
+    pub fn new() -> PILContext<T> {
         PILContext {
             namespace: "Global".to_string(),
             ..Default::default()
         }
     }
 
-    pub fn process_file(&mut self, path: &Path) {
-        let path = path
-            .canonicalize()
-            .unwrap_or_else(|e| panic!("File {path:?} not found: {e}"));
+    fn finish(self) -> Result<Analyzed<T>, Diagnostic> {
+        self.check_multiplicities()
+            .map_err(|e| self.with_sources(e))?;
+        Ok(self.into())
+    }
+
+    /// Checks that the multiplicities of LogUp identities are witness columns,
+    /// which can be declared after the identity.
+    fn check_multiplicities(&self) -> Result<(), Diagnostic> {
+        for identity in &self.identities {
+            let Some(multiplicity) = &identity.multiplicity else {
+                continue;
+            };
+            match self.definitions.get(&multiplicity.name) {
+                Some((poly, _))
+                    if poly.poly_type == PolynomialType::Committed && poly.length.is_none() => {}
+                _ => {
+                    return Err(Diagnostic::error(format!(
+                        "The multiplicity has to be a witness column, but {} is not one.",
+                        multiplicity.name
+                    ))
+                    .with_line(
+                        &identity.source.file,
+                        identity.source.line,
+                        "",
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn process_file(&mut self, path: &Path) -> Result<(), Diagnostic> {
+        let path = match &self.sources {
+            Some(_) => normalize(path),
+            None => path
+                .canonicalize()
+                .or_else(|e| error(format!("File {path:?} not found: {e}")))?,
+        };
         if !self.included_files.insert(path.clone()) {
-            return;
+            return Ok(());
         }
-        let contents = fs::read_to_string(path.clone()).unwrap();
-        self.process_file_contents(&path, &contents);
+        let contents = match &self.sources {
+            Some(sources) => match sources.get(&path) {
+                Some(contents) => contents.clone(),
+                None => return error(format!("File {path:?} not found.")),
+            },
+            None => fs::read_to_string(&path)
+                .or_else(|e| error(format!("Could not read {path:?}: {e}")))?,
+        };
+        self.process_file_contents(&path, &contents)
     }
 
-    pub fn process_file_contents(&mut self, path: &Path, contents: &str) {
+    pub fn process_file_contents(&mut self, path: &Path, contents: &str) -> Result<(), Diagnostic> {
         let old_current_file = std::mem::take(&mut self.current_file);
         let old_line_starts = std::mem::take(&mut self.line_starts);
 
         // TODO make this work for other line endings
         self.line_starts = parser_util::lines::compute_line_starts(contents);
         self.current_file = path.to_path_buf();
-        let pil_file =
-            parser::parse(Some(path.to_str().unwrap()), contents).unwrap_or_else(|err| {
-                eprintln!("Error parsing .pil file:");
-                err.output_to_stderr();
-                panic!();
-            });
-
-        for statement in &pil_file.0 {
-            use ast::Statement;
-            match statement {
-                Statement::Include(_, include) => self.handle_include(include),
-                Statement::Namespace(_, name, degree) => self.handle_namespace(name, degree),
-                Statement::PolynomialDefinition(start, name, value) => {
-                    self.handle_polynomial_definition(
-                        self.to_source_ref(*start),
-                        name,
-                        &None,
-                        PolynomialType::Intermediate,
-                        Some(&ast::FunctionDefinition::Mapping(vec![], value.clone())),
-                    );
-                }
-                Statement::PublicDeclaration(start, name, polynomial, index) => self
-                    .handle_public_declaration(self.to_source_ref(*start), name, polynomial, index),
-                Statement::PolynomialConstantDeclaration(start, polynomials) => self
-                    .handle_polynomial_declarations(
-                        self.to_source_ref(*start),
-                        polynomials,
-                        PolynomialType::Constant,
-                    ),
-                Statement::PolynomialConstantDefinition(start, name, definition) => {
-                    self.handle_polynomial_definition(
-                        self.to_source_ref(*start),
-                        name,
-                        &None,
-                        PolynomialType::Constant,
-                        Some(definition),
-                    );
-                }
-                Statement::PolynomialCommitDeclaration(start, polynomials, None) => self
-                    .handle_polynomial_declarations(
-                        self.to_source_ref(*start),
-                        polynomials,
-                        PolynomialType::Committed,
-                    ),
-                Statement::PolynomialCommitDeclaration(start, polynomials, Some(definition)) => {
-                    assert!(polynomials.len() == 1);
-                    let name = polynomials.first().unwrap();
-                    self.handle_polynomial_definition(
-                        self.to_source_ref(*start),
-                        &name.name,
-                        &name.array_size,
-                        PolynomialType::Committed,
-                        Some(definition),
-                    );
-                }
-                Statement::ConstantDefinition(_, name, value) => {
-                    self.handle_constant_definition(name, value)
-                }
-                Statement::MacroDefinition(start, name, params, statments, expression) => self
-                    .handle_macro_definition(
-                        self.to_source_ref(*start),
-                        name,
-                        params,
-                        statments,
-                        expression,
-                    ),
-                _ => {
-                    self.handle_identity_statement(statement);
-                }
-            }
-        }
+        let file_name = self.to_source_ref(0).file;
+        let is_new = !self.file_contents.contains_key(&file_name);
+        self.file_contents
+            .insert(file_name, is_new.then(|| contents.into()));
+        let result = match parser::parse(Some(path.to_str().unwrap()), contents) {
+            Ok(pil_file) => pil_file
+                .0
+                .iter()
+                .try_for_each(|statement| self.handle_statement(statement)),
+            Err(err) => Err(err.diagnostics().into_iter().next().unwrap()),
+        };
 
         self.current_file = old_current_file;
         self.line_starts = old_line_starts;
+        result
+    }
+
+    /// Handles the statement and underlines it in errors that do not refer
+    /// to a part of a file already.
+    fn handle_statement(&mut self, statement: &ast::Statement<T>) -> Result<(), Diagnostic> {
+        self.handle_statement_inner(statement).map_err(|e| {
+            if e.has_labels() {
+                e
+            } else {
+                let source = self.to_source_ref(statement.start());
+                e.with_line(&source.file, source.line, "")
+            }
+        })
+    }
+
+    fn handle_statement_inner(&mut self, statement: &ast::Statement<T>) -> Result<(), Diagnostic> {
+        use ast::Statement;
+        match statement {
+            Statement::Include(_, include) => self.handle_include(include),
+            Statement::Namespace(_, name, degree) => self.handle_namespace(name, degree),
+            Statement::PolynomialDefinition(start, name, value) => self
+                .handle_polynomial_definition(
+                    self.to_source_ref(*start),
+                    name,
+                    &None,
+                    PolynomialType::Intermediate,
+                    Some(&ast::FunctionDefinition::Mapping(vec![], value.clone())),
+                )
+                .map(|_| ()),
+            Statement::PublicDeclaration(start, name, polynomial, index) => {
+                self.handle_public_declaration(self.to_source_ref(*start), name, polynomial, index)
+            }
+            Statement::PolynomialConstantDeclaration(start, polynomials) => self
+                .handle_polynomial_declarations(
+                    self.to_source_ref(*start),
+                    polynomials,
+                    PolynomialType::Constant,
+                ),
+            Statement::PolynomialConstantDefinition(start, name, definition) => self
+                .handle_polynomial_definition(
+                    self.to_source_ref(*start),
+                    name,
+                    &None,
+                    PolynomialType::Constant,
+                    Some(definition),
+                )
+                .map(|_| ()),
+            Statement::PolynomialCommitDeclaration(start, polynomials, None) => self
+                .handle_polynomial_declarations(
+                    self.to_source_ref(*start),
+                    polynomials,
+                    PolynomialType::Committed,
+                ),
+            Statement::PolynomialCommitDeclaration(start, polynomials, Some(definition)) => {
+                assert!(polynomials.len() == 1);
+                let name = polynomials.first().unwrap();
+                self.handle_polynomial_definition(
+                    self.to_source_ref(*start),
+                    &name.name,
+                    &name.array_size,
+                    PolynomialType::Committed,
+                    Some(definition),
+                )
+                .map(|_| ())
+            }
+            Statement::ConstantDefinition(_, name, value) => {
+                self.handle_constant_definition(name, value)
+            }
+            Statement::MacroDefinition(start, name, params, statments, expression) => self
+                .handle_macro_definition(
+                    self.to_source_ref(*start),
+                    name,
+                    params,
+                    statments,
+                    expression,
+                ),
+            Statement::Conditional(_, name, then, otherwise) => {
+                let statements = if self.defines.contains(name) {
+                    then
+                } else {
+                    otherwise
+                };
+                statements
+                    .iter()
+                    .try_for_each(|statement| self.handle_statement(statement))
+            }
+            Statement::Test(start, name, degree, should_fail, body) => {
+                self.handle_test(*start, name, degree, *should_fail, body)
+            }
+            _ => self.handle_identity_statement(statement),
+        }
     }
 
     fn to_source_ref(&self, start: usize) -> SourceRef {
@@ -185,27 +517,31 @@ impl PILContext {
         }
     }
 
-    fn handle_identity_statement(&mut self, statement: &ast::Statement) {
+    fn handle_identity_statement(
+        &mut self,
+        statement: &ast::Statement<T>,
+    ) -> Result<(), Diagnostic> {
         if let ast::Statement::FunctionCall(_start, name, arguments) = statement {
             if !self.macros.contains_key(name) {
-                panic!(
+                return error(format!(
                     "Macro {name} not found - only macros allowed at this point, no fixed columns."
-                );
+                ));
             }
             // TODO check that it does not contain local variable references.
             // But we also need to do some other well-formedness checks.
-            if self.process_macro_call(name, arguments).is_some() {
-                panic!("Invoked a macro in statement context with non-empty expression.");
+            if self.process_macro_call(name, arguments)?.is_some() {
+                return error("Invoked a macro in statement context with non-empty expression.");
             }
-            return;
+            return Ok(());
         }
 
+        let mut multiplicity = None;
         let (start, kind, left, right) = match statement {
             ast::Statement::PolynomialIdentity(start, expression) => (
                 start,
                 IdentityKind::Polynomial,
                 SelectedExpressions {
-                    selector: Some(self.process_expression(expression)),
+                    selector: Some(self.process_expression(expression)?),
                     expressions: vec![],
                 },
                 SelectedExpressions::default(),
@@ -213,31 +549,50 @@ impl PILContext {
             ast::Statement::PlookupIdentity(start, key, haystack) => (
                 start,
                 IdentityKind::Plookup,
-                self.process_selected_expression(key),
-                self.process_selected_expression(haystack),
+                self.process_selected_expression(key)?,
+                self.process_selected_expression(haystack)?,
             ),
+            ast::Statement::LogUpIdentity(start, key, haystack, column) => {
+                multiplicity = match self.process_expression(column)? {
+                    Expression::PolynomialReference(reference)
+                        if reference.index.is_none() && !reference.next =>
+                    {
+                        Some(reference)
+                    }
+                    e => {
+                        return error(format!(
+                            "The multiplicity has to be a witness column, but it is {}.",
+                            self.expressions.display(&e)
+                        ))
+                    }
+                };
+                (
+                    start,
+                    IdentityKind::LogUp,
+                    self.process_selected_expression(key)?,
+                    self.process_selected_expression(haystack)?,
+                )
+            }
             ast::Statement::PermutationIdentity(start, left, right) => (
                 start,
                 IdentityKind::Permutation,
-                self.process_selected_expression(left),
-                self.process_selected_expression(right),
+                self.process_selected_expression(left)?,
+                self.process_selected_expression(right)?,
             ),
             ast::Statement::ConnectIdentity(start, left, right) => (
                 start,
                 IdentityKind::Connect,
                 SelectedExpressions {
                     selector: None,
-                    expressions: self.process_expressions(left),
+                    expressions: self.process_expressions(left)?,
                 },
                 SelectedExpressions {
                     selector: None,
-                    expressions: self.process_expressions(right),
+                    expressions: self.process_expressions(right)?,
                 },
             ),
             // TODO at some point, these should all be caught by the type checker.
-            _ => {
-                panic!("Only identities allowed at this point.")
-            }
+            _ => return error("Only identities allowed at this point."),
         };
         let id = self.dispense_id(kind);
         let identity = Identity {
@@ -246,30 +601,72 @@ impl PILContext {
             source: self.to_source_ref(*start),
             left,
             right,
+            multiplicity,
+            origin: None,
         };
         let id = self.identities.len();
         self.identities.push(identity);
         self.source_order.push(StatementIdentifier::Identity(id));
+        Ok(())
     }
 
-    fn handle_include(&mut self, path: &str) {
+    fn handle_include(&mut self, path: &str) -> Result<(), Diagnostic> {
         let mut dir = self.current_file.parent().unwrap().to_owned();
         dir.push(path);
-        self.process_file(&dir);
+        self.process_file(&dir)
     }
 
-    fn handle_namespace(&mut self, name: &str, degree: &ast::Expression) {
+    fn handle_namespace(
+        &mut self,
+        name: &str,
+        degree: &ast::Expression<T>,
+    ) -> Result<(), Diagnostic> {
         // TODO: the polynomial degree should be handled without going through a field element. This requires having types in Expression
-        self.polynomial_degree = self.evaluate_expression(degree).unwrap().to_degree();
+        self.polynomial_degree = self.evaluate_to_u64(degree, || format!("degree of {name}"))?;
+        if let Some(test) = &self.test {
+            self.polynomial_degree = test.degree;
+        }
         self.namespace = name.to_owned();
+        Ok(())
+    }
+
+    fn handle_test(
+        &mut self,
+        start: usize,
+        name: &str,
+        degree: &ast::Expression<T>,
+        should_fail: bool,
+        body: &[ast::Statement<T>],
+    ) -> Result<(), Diagnostic> {
+        if let Some(previous) = self.tests.iter().find(|test| test.name == name) {
+            return Err(defined_twice(
+                "Test",
+                name,
+                &self.to_source_ref(start),
+                &previous.source,
+            ));
+        }
+        let degree = self.evaluate_to_u64(degree, || format!("degree of test {name}"))?;
+        self.tests.push(PilTest {
+            name: name.to_string(),
+            source: self.to_source_ref(start),
+            degree,
+            should_fail,
+        });
+        if self.test.as_ref().is_some_and(|test| test.name == name) {
+            for statement in body {
+                self.handle_statement(statement)?;
+            }
+        }
+        Ok(())
     }
 
     fn handle_polynomial_declarations(
         &mut self,
         source: SourceRef,
-        polynomials: &[ast::PolynomialName],
+        polynomials: &[ast::PolynomialName<T>],
         polynomial_type: PolynomialType,
-    ) {
+    ) -> Result<(), Diagnostic> {
         for ast::PolynomialName { name, array_size } in polynomials {
             self.handle_polynomial_definition(
                 source.clone(),
@@ -277,22 +674,23 @@ impl PILContext {
                 array_size,
                 polynomial_type,
                 None,
-            );
+            )?;
         }
+        Ok(())
     }
 
     fn handle_polynomial_definition(
         &mut self,
         source: SourceRef,
         name: &str,
-        array_size: &Option<ast::Expression>,
+        array_size: &Option<ast::Expression<T>>,
         polynomial_type: PolynomialType,
-        value: Option<&ast::FunctionDefinition>,
-    ) -> u64 {
+        value: Option<&ast::FunctionDefinition<T>>,
+    ) -> Result<u64, Diagnostic> {
         let length = array_size
             .as_ref()
-            .map(|l| self.evaluate_expression(l).unwrap())
-            .map(|l| l.to_degree());
+            .map(|l| self.evaluate_to_u64(l, || format!("array length of {name}")))
+            .transpose()?;
         if length.is_some() {
             assert!(value.is_none());
         }
@@ -306,15 +704,26 @@ impl PILContext {
         let poly = Polynomial {
             id,
             source,
-            absolute_name: self.namespaced(name),
+            absolute_name: self.namespaced(name).into(),
             degree: self.polynomial_degree,
             poly_type: polynomial_type,
             length,
         };
-        let name = poly.absolute_name.clone();
-        let value = value.map(|v| match v {
-            ast::FunctionDefinition::Mapping(params, expr)
-            | ast::FunctionDefinition::Query(params, expr) => {
+        let name = poly.absolute_name;
+        if let Some((previous, _)) = self.definitions.get(&name) {
+            return Err(defined_twice(
+                "Column",
+                &name,
+                &poly.source,
+                &previous.source,
+            ));
+        }
+        let value = match value {
+            None => None,
+            Some(
+                v @ (ast::FunctionDefinition::Mapping(params, expr)
+                | ast::FunctionDefinition::Query(params, expr)),
+            ) => {
                 assert!(array_size.is_none());
                 if !params.is_empty() {
                     assert!(
@@ -331,7 +740,8 @@ impl PILContext {
                     .collect();
                 let processed_value = self.process_expression(expr);
                 self.local_variables.clear();
-                match v {
+                let processed_value = processed_value?;
+                Some(match v {
                     ast::FunctionDefinition::Mapping(_, _) => {
                         FunctionValueDefinition::Mapping(processed_value)
                     }
@@ -339,57 +749,67 @@ impl PILContext {
                         FunctionValueDefinition::Query(processed_value)
                     }
                     _ => panic!(),
-                }
+                })
             }
-            ast::FunctionDefinition::Array(value) => {
-                let star_value = value.solve(self.polynomial_degree);
-                let expression = self.process_array_expression(value, star_value);
-                assert_eq!(
-                    expression.iter().map(|e| e.size()).sum::<DegreeType>(),
-                    self.polynomial_degree
-                );
-                FunctionValueDefinition::Array(expression)
+            Some(ast::FunctionDefinition::Array(value)) => {
+                let star_value = value
+                    .solve(self.polynomial_degree)
+                    .map_err(Diagnostic::error)?;
+                let expression = self.process_array_expression(value, star_value)?;
+                let size = expression.iter().map(|e| e.size()).sum::<DegreeType>();
+                if size != self.polynomial_degree {
+                    return error(format!(
+                        "The array has {size} elements, but the degree is {}.",
+                        self.polynomial_degree
+                    ));
+                }
+                Some(FunctionValueDefinition::Array(expression))
             }
-        });
-        let is_new = self
-            .definitions
-            .insert(name.clone(), (poly, value))
-            .is_none();
-        assert!(is_new);
+        };
+        self.definitions.insert(name, (poly, value));
         self.source_order
             .push(StatementIdentifier::Definition(name));
-        id
+        Ok(id)
     }
 
     fn handle_public_declaration(
         &mut self,
         source: SourceRef,
         name: &str,
-        poly: &ast::PolynomialReference,
-        index: &ast::Expression,
-    ) {
+        poly: &ast::PolynomialReference<T>,
+        index: &ast::Expression<T>,
+    ) -> Result<(), Diagnostic> {
         let id = self.public_declarations.len() as u64;
+        let polynomial = self.process_polynomial_reference(poly)?;
+        let index = self.evaluate_to_u64(index, || format!("index of public {name}"))?;
         self.public_declarations.insert(
             name.to_string(),
             PublicDeclaration {
                 id,
                 source,
                 name: name.to_string(),
-                polynomial: self.process_polynomial_reference(poly),
-                index: self.evaluate_expression(index).unwrap().to_degree(),
+                polynomial,
+                index,
             },
         );
         self.source_order
             .push(StatementIdentifier::PublicDeclaration(name.to_string()));
+        Ok(())
     }
 
-    fn handle_constant_definition(&mut self, name: &str, value: &ast::Expression) {
+    fn handle_constant_definition(
+        &mut self,
+        name: &str,
+        value: &ast::Expression<T>,
+    ) -> Result<(), Diagnostic> {
         // TODO does the order matter here?
-        let is_new = self
-            .constants
-            .insert(name.to_string(), self.evaluate_expression(value).unwrap())
-            .is_none();
-        assert!(is_new, "Constant {name} was defined twice.");
+        let Some(value) = self.evaluate_expression(value)? else {
+            return error(format!("The value of the constant {name} is not constant."));
+        };
+        if self.constants.insert(name.to_string(), value).is_some() {
+            return error(format!("Constant {name} was defined twice."));
+        }
+        Ok(())
     }
 
     fn dispense_id(&mut self, kind: IdentityKind) -> u64 {
@@ -404,23 +824,23 @@ impl PILContext {
         source: SourceRef,
         name: &str,
         params: &[String],
-        statements: &[ast::Statement],
-        expression: &Option<ast::Expression>,
-    ) {
-        let is_new = self
-            .macros
-            .insert(
-                name.to_string(),
-                MacroDefinition {
-                    source,
-                    absolute_name: self.namespaced(name),
-                    parameters: params.to_vec(),
-                    identities: statements.to_vec(),
-                    expression: expression.clone(),
-                },
-            )
-            .is_none();
-        assert!(is_new);
+        statements: &[ast::Statement<T>],
+        expression: &Option<ast::Expression<T>>,
+    ) -> Result<(), Diagnostic> {
+        if let Some(previous) = self.macros.get(name) {
+            return Err(defined_twice("Macro", name, &source, &previous.source));
+        }
+        self.macros.insert(
+            name.to_string(),
+            MacroDefinition {
+                source,
+                absolute_name: self.namespaced(name),
+                parameters: params.to_vec(),
+                identities: statements.to_vec(),
+                expression: expression.clone(),
+            },
+        );
+        Ok(())
     }
 
     fn namespaced(&self, name: &str) -> String {
@@ -433,22 +853,26 @@ impl PILContext {
 
     fn process_selected_expression(
         &mut self,
-        expr: &ast::SelectedExpressions,
-    ) -> SelectedExpressions {
-        SelectedExpressions {
-            selector: expr.selector.as_ref().map(|e| self.process_expression(e)),
-            expressions: self.process_expressions(&expr.expressions),
-        }
+        expr: &ast::SelectedExpressions<T>,
+    ) -> Result<SelectedExpressions<T>, Diagnostic> {
+        Ok(SelectedExpressions {
+            selector: expr
+                .selector
+                .as_ref()
+                .map(|e| self.process_expression(e))
+                .transpose()?,
+            expressions: self.process_expressions(&expr.expressions)?,
+        })
     }
 
     fn process_array_expression(
         &mut self,
-        array_expression: &ast::ArrayExpression,
+        array_expression: &ast::ArrayExpression<T>,
         star_value: Option<DegreeType>,
-    ) -> Vec<RepeatedArray> {
-        match array_expression {
+    ) -> Result<Vec<RepeatedArray<T>>, Diagnostic> {
+        Ok(match array_expression {
             ast::ArrayExpression::Value(expressions) => vec![RepeatedArray {
-                values: self.process_expressions(expressions),
+                values: self.process_expressions(expressions)?,
                 repetitions: 1,
             }],
             ast::ArrayExpression::RepeatedValue(expressions) => {
@@ -456,198 +880,473 @@ impl PILContext {
                     vec![]
                 } else {
                     vec![RepeatedArray {
-                        values: self.process_expressions(expressions),
+                        values: self.process_expressions(expressions)?,
                         repetitions: star_value.unwrap(),
                     }]
                 }
             }
-            ast::ArrayExpression::Concat(left, right) => self
-                .process_array_expression(left, star_value)
-                .into_iter()
-                .chain(self.process_array_expression(right, star_value))
-                .collect(),
-        }
+            ast::ArrayExpression::Concat(left, right) => {
+                let mut arrays = self.process_array_expression(left, star_value)?;
+                arrays.extend(self.process_array_expression(right, star_value)?);
+                arrays
+            }
+        })
     }
 
-    fn process_expressions(&mut self, exprs: &[ast::Expression]) -> Vec<Expression> {
+    fn process_expressions(
+        &mut self,
+        exprs: &[ast::Expression<T>],
+    ) -> Result<Vec<Expression<T>>, Diagnostic> {
         exprs.iter().map(|e| self.process_expression(e)).collect()
     }
 
-    fn process_expression(&mut self, expr: &ast::Expression) -> Expression {
-        match expr {
+    fn process_expression(
+        &mut self,
+        expr: &ast::Expression<T>,
+    ) -> Result<Expression<T>, Diagnostic> {
+        Ok(match expr {
             ast::Expression::Constant(name) => Expression::Constant(name.clone()),
             ast::Expression::PolynomialReference(poly) => {
                 if poly.namespace.is_none() && self.local_variables.contains_key(&poly.name) {
                     let id = self.local_variables[&poly.name];
                     // TODO to make this work inside macros, "next" and "index" need to be
                     // their own ast nodes / operators.
-                    assert!(!poly.next);
-                    assert!(poly.index.is_none());
+                    if poly.next || poly.index.is_some() {
+                        return error(format!(
+                            "The parameter {} cannot be shifted or indexed.",
+                            poly.name
+                        ));
+                    }
                     if let Some(arguments) = &self.macro_arguments {
                         arguments[id as usize].clone()
                     } else {
                         Expression::LocalVariableReference(id)
                     }
                 } else {
-                    Expression::PolynomialReference(self.process_polynomial_reference(poly))
+                    Expression::PolynomialReference(self.process_polynomial_reference(poly)?)
                 }
             }
             ast::Expression::PublicReference(name) => Expression::PublicReference(name.clone()),
             ast::Expression::Number(n) => Expression::Number(*n),
             ast::Expression::String(value) => Expression::String(value.clone()),
-            ast::Expression::Tuple(items) => Expression::Tuple(self.process_expressions(items)),
+            ast::Expression::Tuple(items) => Expression::Tuple(self.process_expressions(items)?),
             ast::Expression::BinaryOperation(left, op, right) => {
-                if let Some(value) = self.evaluate_binary_operation(left, op, right) {
+                if let Some(value) = self.evaluate_binary_operation(left, op, right)? {
                     Expression::Number(value)
                 } else {
+                    let left = self.process_expression(left)?;
+                    let right = self.process_expression(right)?;
                     Expression::BinaryOperation(
-                        Box::new(self.process_expression(left)),
+                        self.expressions.add(left),
                         *op,
-                        Box::new(self.process_expression(right)),
+                        self.expressions.add(right),
                     )
                 }
             }
             ast::Expression::UnaryOperation(op, value) => {
-                if let Some(value) = self.evaluate_unary_operation(op, value) {
+                if let Some(value) = self.evaluate_unary_operation(op, value)? {
                     Expression::Number(value)
                 } else {
-                    Expression::UnaryOperation(*op, Box::new(self.process_expression(value)))
+                    let value = self.process_expression(value)?;
+                    Expression::UnaryOperation(*op, self.expressions.add(value))
                 }
             }
             ast::Expression::FunctionCall(name, arguments) if self.macros.contains_key(name) => {
-                self.process_macro_call(name, arguments)
-                    .expect("Invoked a macro in expression context with empty expression.")
-            }
-            ast::Expression::FunctionCall(name, arguments) => {
-                Expression::FunctionCall(self.namespaced(name), self.process_expressions(arguments))
+                match self.process_macro_call(name, arguments)? {
+                    Some(value) => value,
+                    None => {
+                        return error(
+                            "Invoked a macro in expression context with empty expression.",
+                        )
+                    }
+                }
             }
-            ast::Expression::MatchExpression(scrutinee, arms) => Expression::MatchExpression(
-                Box::new(self.process_expression(scrutinee)),
-                arms.iter()
+            ast::Expression::FunctionCall(name, arguments) => Expression::FunctionCall(
+                self.namespaced(name),
+                self.process_expressions(arguments)?,
+            ),
+            ast::Expression::MatchExpression(scrutinee, arms) => {
+                let scrutinee = self.process_expression(scrutinee)?;
+                let scrutinee = self.expressions.add(scrutinee);
+                let arms = arms
+                    .iter()
                     .map(|(n, e)| {
-                        (
-                            n.as_ref().map(|n| {
-                                self.evaluate_expression(n).unwrap_or_else(|| {
-                                    panic!("Left side of match arm must be a constant, found {n}")
-                                })
-                            }),
-                            self.process_expression(e),
-                        )
+                        let n = match n {
+                            Some(n) => match self.evaluate_expression(n)? {
+                                Some(value) => Some(value),
+                                None => {
+                                    return error(format!(
+                                        "Left side of match arm must be a constant, found {n}"
+                                    ))
+                                }
+                            },
+                            None => None,
+                        };
+                        Ok((n, self.process_expression(e)?))
                     })
-                    .collect(),
-            ),
-            ast::Expression::FreeInput(_) => panic!(),
-        }
+                    .collect::<Result<_, _>>()?;
+                Expression::MatchExpression(scrutinee, arms)
+            }
+            ast::Expression::FreeInput(_) => {
+                return error("Free inputs are only allowed in assembly.")
+            }
+        })
     }
 
     fn process_macro_call(
         &mut self,
         name: &str,
-        arguments: &[ast::Expression],
-    ) -> Option<Expression> {
-        let arguments = Some(self.process_expressions(arguments));
-        let old_arguments = std::mem::replace(&mut self.macro_arguments, arguments);
-
-        let old_locals = std::mem::take(&mut self.local_variables);
-
-        let mac = &self
-            .macros
-            .get(name)
-            .unwrap_or_else(|| panic!("Macro {name} not found."));
-        self.local_variables = mac
-            .parameters
-            .iter()
-            .enumerate()
-            .map(|(i, n)| (n.clone(), i as u64))
-            .collect();
+        arguments: &[ast::Expression<T>],
+    ) -> Result<Option<Expression<T>>, Diagnostic> {
+        let Some(mac) = self.macros.get(name) else {
+            return error(format!("Macro {name} not found."));
+        };
         // TODO avoid clones
+        let parameters = mac.parameters.clone();
         let expression = mac.expression.clone();
         let identities = mac.identities.clone();
-        for identity in &identities {
-            self.handle_identity_statement(identity);
-        }
-        let result = expression.map(|expr| self.process_expression(&expr));
+
+        let arguments = Some(self.process_expressions(arguments)?);
+        let old_arguments = std::mem::replace(&mut self.macro_arguments, arguments);
+        let old_locals = std::mem::replace(
+            &mut self.local_variables,
+            parameters
+                .into_iter()
+                .enumerate()
+                .map(|(i, n)| (n, i as u64))
+                .collect(),
+        );
+        let result = identities
+            .iter()
+            .try_for_each(|identity| self.handle_identity_statement(identity))
+            .and_then(|_| {
+                expression
+                    .map(|expr| self.process_expression(&expr))
+                    .transpose()
+            });
         self.macro_arguments = old_arguments;
         self.local_variables = old_locals;
         result
     }
 
-    fn process_polynomial_reference(&self, poly: &ast::PolynomialReference) -> PolynomialReference {
+    fn process_polynomial_reference(
+        &self,
+        poly: &ast::PolynomialReference<T>,
+    ) -> Result<PolynomialReference, Diagnostic> {
         let index = poly
             .index
             .as_ref()
-            .map(|i| self.evaluate_expression(i).unwrap())
-            .map(|i| i.to_degree());
-        PolynomialReference {
-            name: self.namespaced_ref(&poly.namespace, &poly.name),
+            .map(|i| self.evaluate_to_u64(i, || format!("array index into {}", poly.name)))
+            .transpose()?;
+        Ok(PolynomialReference {
+            name: self.namespaced_ref(&poly.namespace, &poly.name).into(),
             index,
             next: poly.next,
+        })
+    }
+
+    /// Evaluates the expression to a number that fits 64 bits, the context
+    /// describes the number in the error.
+    fn evaluate_to_u64(
+        &self,
+        expr: &ast::Expression<T>,
+        context: impl FnOnce() -> String,
+    ) -> Result<u64, Diagnostic> {
+        match self.evaluate_expression(expr)? {
+            Some(value) => value
+                .try_to_u64()
+                .map_err(|e| Diagnostic::error(e.with_context(context()).to_string())),
+            None => error(format!("The {} has to be a constant.", context())),
         }
     }
 
-    fn evaluate_expression(&self, expr: &ast::Expression) -> Option<FieldElement> {
-        match expr {
-            ast::Expression::Constant(name) => Some(
-                *self
-                    .constants
-                    .get(name)
-                    .unwrap_or_else(|| panic!("Constant {name} not found.")),
-            ),
+    fn evaluate_expression(&self, expr: &ast::Expression<T>) -> Result<Option<T>, Diagnostic> {
+        Ok(match expr {
+            ast::Expression::Constant(name) => match self.constants.get(name) {
+                Some(value) => Some(*value),
+                None => return error(format!("Constant {name} not found.")),
+            },
             ast::Expression::PolynomialReference(_) => None,
             ast::Expression::PublicReference(_) => None,
             ast::Expression::Number(n) => Some(*n),
             ast::Expression::String(_) => None,
             ast::Expression::Tuple(_) => None,
             ast::Expression::BinaryOperation(left, op, right) => {
-                self.evaluate_binary_operation(left, op, right)
+                self.evaluate_binary_operation(left, op, right)?
+            }
+            ast::Expression::UnaryOperation(op, value) => {
+                self.evaluate_unary_operation(op, value)?
             }
-            ast::Expression::UnaryOperation(op, value) => self.evaluate_unary_operation(op, value),
             ast::Expression::FunctionCall(_, _) => None,
-            ast::Expression::FreeInput(_) => panic!(),
+            ast::Expression::FreeInput(_) => {
+                return error("Free inputs are only allowed in assembly.")
+            }
             ast::Expression::MatchExpression(_, _) => None,
-        }
+        })
     }
 
     fn evaluate_binary_operation(
         &self,
-        left: &ast::Expression,
+        left: &ast::Expression<T>,
         op: &BinaryOperator,
-        right: &ast::Expression,
-    ) -> Option<FieldElement> {
-        if let (Some(left), Some(right)) = (
-            self.evaluate_expression(left),
-            self.evaluate_expression(right),
-        ) {
-            Some(match op {
-                BinaryOperator::Add => left + right,
-                BinaryOperator::Sub => left - right,
-                BinaryOperator::Mul => left * right,
-                BinaryOperator::Div => left.integer_div(right),
-                BinaryOperator::Pow => {
-                    let right_int = right.to_integer();
-                    assert!(right_int <= u32::MAX.into());
-                    left.pow(right_int)
+        right: &ast::Expression<T>,
+    ) -> Result<Option<T>, Diagnostic> {
+        let (Some(left), Some(right)) = (
+            self.evaluate_expression(left)?,
+            self.evaluate_expression(right)?,
+        ) else {
+            return Ok(None);
+        };
+        let conversion_error = |e: number::ConversionError| Diagnostic::error(e.to_string());
+        Ok(Some(match op {
+            BinaryOperator::Add => left + right,
+            BinaryOperator::Sub => left - right,
+            BinaryOperator::Mul => left * right,
+            BinaryOperator::Div => left.integer_div(right),
+            BinaryOperator::Pow => {
+                let right_int = right.to_integer();
+                if right_int > u32::MAX.into() {
+                    return error(format!("The exponent {right} does not fit 32 bits."));
                 }
-                BinaryOperator::Mod => (left.to_integer() % right.to_integer()).into(),
-                BinaryOperator::BinaryAnd => (left.to_integer() & right.to_integer()).into(),
-                BinaryOperator::BinaryXor => (left.to_integer() ^ right.to_integer()).into(),
-                BinaryOperator::BinaryOr => (left.to_integer() | right.to_integer()).into(),
-                BinaryOperator::ShiftLeft => (left.to_integer() << right.to_integer()).into(),
-                BinaryOperator::ShiftRight => (left.to_integer() >> right.to_integer()).into(),
-            })
-        } else {
-            None
-        }
+                left.pow(right_int)
+            }
+            BinaryOperator::Mod => left.integer_mod(right),
+            BinaryOperator::BinaryAnd => left.bitand(right),
+            BinaryOperator::BinaryXor => left.bitxor(right),
+            BinaryOperator::BinaryOr => left.bitor(right),
+            BinaryOperator::ShiftLeft => left.try_shl(right).map_err(conversion_error)?,
+            BinaryOperator::ShiftRight => left.try_shr(right).map_err(conversion_error)?,
+        }))
     }
 
     fn evaluate_unary_operation(
         &self,
         op: &UnaryOperator,
-        value: &ast::Expression,
-    ) -> Option<FieldElement> {
-        self.evaluate_expression(value).map(|v| match op {
+        value: &ast::Expression<T>,
+    ) -> Result<Option<T>, Diagnostic> {
+        Ok(self.evaluate_expression(value)?.map(|v| match op {
             UnaryOperator::Plus => v,
             UnaryOperator::Minus => -v,
-        })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+
+    use super::*;
+
+    #[test]
+    fn included_files_transitive() {
+        let files =
+            included_files::<GoldilocksField>(Path::new("../test_data/polygon-hermez/binary.pil"));
+        let names = files
+            .iter()
+            .map(|f| f.file_name().unwrap().to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["binary.pil", "global.pil", "config.pil"]);
+        assert!(files.iter().all(|f| f.is_absolute()));
+    }
+
+    #[test]
+    fn in_memory_sources() {
+        let sources = [
+            (
+                "main.pil",
+                "include \"lib/a.pil\";\nnamespace Main(4);\ncol witness x;",
+            ),
+            (
+                "lib/a.pil",
+                "include \"./../lib/b.pil\";\nnamespace A(4);\ncol witness y;",
+            ),
+            (
+                "lib/b.pil",
+                "include \"a.pil\";\nnamespace B(4);\ncol fixed Z = [0]*;",
+            ),
+        ]
+        .into_iter()
+        .map(|(path, contents)| (PathBuf::from(path), contents.to_string()))
+        .collect();
+        let analyzed =
+            process_pil_sources::<GoldilocksField>(Path::new("./main.pil"), sources).unwrap();
+        assert_eq!(analyzed.commitment_count(), 2);
+        assert_eq!(analyzed.constant_count(), 1);
+        assert!(analyzed.definitions.contains_key(&Symbol::intern("B.Z")));
+    }
+
+    #[test]
+    fn conditionals() {
+        let file = Path::new("../test_data/pil/conditional.pil");
+        let analyzed =
+            process_pil_file_with_defines::<GoldilocksField>(file, Default::default()).unwrap();
+        assert!(analyzed
+            .definitions
+            .contains_key(&Symbol::intern("Conditional.unchecked")));
+        assert!(!analyzed
+            .definitions
+            .contains_key(&Symbol::intern("Conditional.BYTE")));
+        assert_eq!(analyzed.identities.len(), 1);
+
+        let defines = ["RANGE_CHECK".to_string()].into_iter().collect();
+        let analyzed = process_pil_file_with_defines::<GoldilocksField>(file, defines).unwrap();
+        assert!(!analyzed
+            .definitions
+            .contains_key(&Symbol::intern("Conditional.unchecked")));
+        assert!(analyzed
+            .definitions
+            .contains_key(&Symbol::intern("Conditional.BYTE")));
+        assert_eq!(analyzed.identities.len(), 2);
+    }
+
+    #[test]
+    fn tests() {
+        let file = Path::new("../test_data/pil/tests.pil");
+        let tests = collect_tests::<GoldilocksField>(file, Default::default()).unwrap();
+        assert_eq!(
+            tests
+                .iter()
+                .map(|t| (t.name.as_str(), t.source.line, t.degree, t.should_fail))
+                .collect::<Vec<_>>(),
+            [
+                ("decomposes", 11, 256, false),
+                ("too_large", 16, 256, true),
+                ("zero_or_one", 26, 4, false),
+                ("two", 31, 4, true)
+            ]
+        );
+        let analyzed =
+            process_pil_file_with_defines::<GoldilocksField>(file, Default::default()).unwrap();
+        assert!(!analyzed
+            .definitions
+            .contains_key(&Symbol::intern("Decompose.VALUE")));
+        assert_eq!(
+            analyzed.definitions[&Symbol::intern("Bool.x")].0.degree,
+            65536
+        );
+
+        let analyzed =
+            process_pil_file_for_test::<GoldilocksField>(file, Default::default(), &tests[1])
+                .unwrap();
+        assert!(analyzed
+            .definitions
+            .contains_key(&Symbol::intern("Decompose.VALUE")));
+        assert!(!analyzed.definitions.contains_key(&Symbol::intern("Bool.X")));
+        assert_eq!(
+            analyzed.definitions[&Symbol::intern("Bool.x")].0.degree,
+            256
+        );
+        assert_eq!(analyzed.identities.len(), 5);
+    }
+
+    #[test]
+    fn identity_origins() {
+        let mut analyzed = process_pil_file_contents::<GoldilocksField>(
+            "namespace T(4);\ncol witness x;\nx * (1 - x) = 0;\nx' = x;",
+        )
+        .unwrap();
+        let origins = [(3, "instruction `bool` at main.asm:7".to_string())]
+            .into_iter()
+            .collect();
+        analyzed.set_identity_origins(&origins);
+        assert_eq!(
+            analyzed.identities[0].origin.as_deref(),
+            Some("instruction `bool` at main.asm:7")
+        );
+        assert_eq!(analyzed.identities[1].origin, None);
+    }
+
+    #[test]
+    fn multiplicity_declared_later() {
+        let analyzed = process_pil_file_contents::<GoldilocksField>(
+            "namespace T(4);\ncol fixed BYTE = [0, 1, 2, 3];\ncol witness x;\n{ x } in { BYTE } multiplicity m;\ncol witness m;",
+        )
+        .unwrap();
+        assert_eq!(
+            analyzed.identities[0].multiplicity.as_ref().unwrap().name,
+            "T.m"
+        );
+    }
+
+    #[test]
+    fn multiplicity_not_witness() {
+        let err = process_pil_file_contents::<GoldilocksField>(
+            "namespace T(4);\ncol fixed BYTE = [0, 1, 2, 3];\ncol fixed m = [0]*;\ncol witness x;\n{ x } in { BYTE } multiplicity m;",
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            err.message(),
+            "The multiplicity has to be a witness column, but T.m is not one."
+        );
+    }
+
+    #[test]
+    fn errors_underline_the_statement() {
+        let err = process_pil_file_contents::<GoldilocksField>(
+            "namespace T(4);\ncol witness x;\n    x = %N;\n",
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            err.to_string(),
+            r"error: Constant %N not found.
+  ┌─ input:3:5
+  │
+3 │     x = %N;
+  │     ^^^^^^^"
+        );
+    }
+
+    #[test]
+    fn degree_not_constant() {
+        let err = process_pil_file_contents::<GoldilocksField>("namespace T(x);")
+            .err()
+            .unwrap();
+        assert_eq!(err.message(), "The degree of T has to be a constant.");
+    }
+
+    #[test]
+    fn defined_twice_shows_both_lines() {
+        let sources = [(
+            PathBuf::from("twice.pil"),
+            "namespace T(4);\ncol witness x;\ncol witness y;\n    col fixed x = [0]*;\n"
+                .to_string(),
+        )]
+        .into_iter()
+        .collect();
+        let err = process_pil_sources::<GoldilocksField>(Path::new("twice.pil"), sources)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            r"error: Column T.x was defined twice.
+  ┌─ twice.pil:4:5
+  │
+2 │ col witness x;
+  │ -------------- first defined here
+3 │ col witness y;
+4 │     col fixed x = [0]*;
+  │     ^^^^^^^^^^^^^^^^^^^ defined again here"
+        );
+    }
+
+    #[test]
+    fn files_with_the_same_name() {
+        let sources = [
+            ("main.pil", "include \"a/x.pil\";\ninclude \"b/x.pil\";"),
+            ("a/x.pil", "namespace A(4);\ncol witness x;"),
+            ("b/x.pil", "namespace B(4);\n    x = %N;"),
+        ]
+        .into_iter()
+        .map(|(path, contents)| (PathBuf::from(path), contents.to_string()))
+        .collect();
+        let err = process_pil_sources::<GoldilocksField>(Path::new("main.pil"), sources)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "error: Constant %N not found.\n = at x.pil:2"
+        );
     }
 }
@@ -0,0 +1,650 @@
+//! Exports an analyzed PIL file together with its fixed and witness columns
+//! as a rank-1 constraint system in the iden3 `.r1cs` format and the witness
+//! in the `.wtns` format, which can be used with snarkjs.
+//!
+//! Every witness cell becomes a wire, fixed columns are inlined. Products of
+//! two non-constant expressions get their own wire. Plookups into fixed
+//! columns are expanded: For every row, one boolean wire per table entry
+//! selects the entry that the left hand side has to match.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, Write};
+
+use number::{DegreeType, FieldElement};
+
+use crate::{
+    Analyzed, BinaryOperator, Expression, FunctionValueDefinition, IdentityKind, PolynomialType,
+    SelectedExpressions, UnaryOperator,
+};
+
+/// A linear combination of wires, wire 0 is the constant one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinearCombination<T>(BTreeMap<usize, T>);
+
+impl<T: FieldElement> LinearCombination<T> {
+    fn constant(value: T) -> Self {
+        Self::wire(0).scale(value)
+    }
+
+    fn wire(wire: usize) -> Self {
+        Self([(wire, T::one())].into_iter().collect())
+    }
+
+    fn as_constant(&self) -> Option<T> {
+        match self.0.len() {
+            0 => Some(T::zero()),
+            1 => self.0.get(&0).copied(),
+            _ => None,
+        }
+    }
+
+    fn add(mut self, other: &Self) -> Self {
+        for (wire, coeff) in &other.0 {
+            let sum = self.0.get(wire).copied().unwrap_or_else(T::zero) + *coeff;
+            if sum.is_zero() {
+                self.0.remove(wire);
+            } else {
+                self.0.insert(*wire, sum);
+            }
+        }
+        self
+    }
+
+    fn scale(self, factor: T) -> Self {
+        if factor.is_zero() {
+            return Self(Default::default());
+        }
+        Self(self.0.into_iter().map(|(w, c)| (w, c * factor)).collect())
+    }
+
+    fn evaluate(&self, wires: &[T]) -> T {
+        self.0
+            .iter()
+            .fold(T::zero(), |acc, (wire, coeff)| acc + *coeff * wires[*wire])
+    }
+}
+
+/// A constraint `A * B = C`.
+pub type Constraint<T> = (
+    LinearCombination<T>,
+    LinearCombination<T>,
+    LinearCombination<T>,
+);
+
+/// A constraint system together with a satisfying assignment.
+pub struct R1cs<T> {
+    /// The values of all wires, starting with the constant one and the publics.
+    pub wires: Vec<T>,
+    pub public_count: usize,
+    pub constraints: Vec<Constraint<T>>,
+}
+
+/// Lowers the constraints of the analyzed PIL file to R1CS.
+/// The columns are given by name, as returned by the constant evaluator and the witness generator.
+/// Fails for permutation and connect identities, for lookups whose right
+/// hand side references witness columns and if the namespaces or the given
+/// columns differ in their number of rows.
+pub fn export<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    fixed: &[(&str, Vec<T>)],
+    witness: &[(&str, Vec<T>)],
+) -> Result<R1cs<T>, String> {
+    let degree = analyzed
+        .definitions
+        .values()
+        .map(|(poly, _)| poly.degree)
+        .max()
+        .unwrap_or_default();
+    if let Some((poly, _)) = analyzed
+        .definitions
+        .values()
+        .find(|(poly, _)| poly.degree != degree)
+    {
+        return Err(format!(
+            "All namespaces need the same degree to be exported to R1CS, {} has degree {} instead of {degree}.",
+            poly.absolute_name, poly.degree
+        ));
+    }
+    for (name, values) in fixed.iter().chain(witness) {
+        if values.len() as DegreeType != degree {
+            return Err(format!(
+                "Column {name} has {} rows instead of {degree}.",
+                values.len()
+            ));
+        }
+    }
+    let mut exporter = Exporter {
+        analyzed,
+        degree,
+        fixed: fixed.iter().map(|(n, v)| (*n, v)).collect(),
+        witness: witness.iter().map(|(n, v)| (*n, v)).collect(),
+        cell_wires: HashMap::new(),
+        wires: vec![T::one()],
+        constraints: vec![],
+    };
+
+    let mut publics = analyzed.public_declarations.values().collect::<Vec<_>>();
+    publics.sort_by_key(|decl| decl.id);
+    // The public wires come right after the constant one.
+    let public_wires = publics
+        .iter()
+        .map(|_| exporter.new_wire(T::zero()))
+        .collect::<Vec<_>>();
+    for (wire, decl) in public_wires.into_iter().zip(&publics) {
+        if decl.index >= degree {
+            return Err(format!(
+                "Public {} refers to row {} of {}, which only has {degree} rows.",
+                decl.name, decl.index, decl.polynomial.name
+            ));
+        }
+        let reference = Expression::PolynomialReference(decl.polynomial.clone());
+        let value = exporter.lower(&reference, decl.index)?;
+        exporter.wires[wire] = value.evaluate(&exporter.wires);
+        exporter.constrain_zero(value.add(&LinearCombination::wire(wire).scale(-T::one())));
+    }
+
+    for identity in &analyzed.identities {
+        match identity.kind {
+            IdentityKind::Polynomial => {
+                let expression = identity.left.selector.as_ref().unwrap();
+                for row in 0..degree {
+                    exporter.polynomial_identity(expression, row)?;
+                }
+            }
+            IdentityKind::Plookup | IdentityKind::LogUp => {
+                exporter.plookup(&identity.left, &identity.right)?
+            }
+            IdentityKind::Permutation | IdentityKind::Connect => {
+                return Err(format!(
+                    "{:?} identities cannot be exported to R1CS ({}:{}).",
+                    identity.kind, identity.source.file, identity.source.line
+                ))
+            }
+        }
+    }
+
+    Ok(R1cs {
+        wires: exporter.wires,
+        public_count: publics.len(),
+        constraints: exporter.constraints,
+    })
+}
+
+impl<T: FieldElement> R1cs<T> {
+    /// Writes the constraint system in the iden3 binary r1cs format.
+    pub fn write_r1cs(&self, writer: &mut impl Write) -> io::Result<()> {
+        let field_size = T::byte_len();
+        let mut header = Vec::new();
+        header.extend((field_size as u32).to_le_bytes());
+        header.extend(modulus_bytes::<T>());
+        header.extend((self.wires.len() as u32).to_le_bytes());
+        header.extend(0u32.to_le_bytes()); // public outputs
+        header.extend((self.public_count as u32).to_le_bytes()); // public inputs
+        header.extend(0u32.to_le_bytes()); // private inputs
+        header.extend((self.wires.len() as u64).to_le_bytes()); // labels
+        header.extend((self.constraints.len() as u32).to_le_bytes());
+
+        let mut constraints = Vec::new();
+        for (a, b, c) in &self.constraints {
+            for lc in [a, b, c] {
+                constraints.extend((lc.0.len() as u32).to_le_bytes());
+                for (wire, coeff) in &lc.0 {
+                    constraints.extend((*wire as u32).to_le_bytes());
+                    constraints.extend(coeff.to_bytes_le());
+                }
+            }
+        }
+
+        let labels = (0..self.wires.len() as u64)
+            .flat_map(|i| i.to_le_bytes())
+            .collect::<Vec<_>>();
+
+        write_sections(
+            writer,
+            b"r1cs",
+            1,
+            &[(1, header), (2, constraints), (3, labels)],
+        )
+    }
+
+    /// Writes the wire values in the iden3 binary wtns format.
+    pub fn write_wtns(&self, writer: &mut impl Write) -> io::Result<()> {
+        let mut header = Vec::new();
+        header.extend((T::byte_len() as u32).to_le_bytes());
+        header.extend(modulus_bytes::<T>());
+        header.extend((self.wires.len() as u32).to_le_bytes());
+        let values = self
+            .wires
+            .iter()
+            .flat_map(|v| v.to_bytes_le())
+            .collect::<Vec<_>>();
+        write_sections(writer, b"wtns", 2, &[(1, header), (2, values)])
+    }
+
+    /// Returns true if the wire values satisfy all constraints.
+    pub fn is_satisfied(&self) -> bool {
+        self.constraints.iter().all(|(a, b, c)| {
+            a.evaluate(&self.wires) * b.evaluate(&self.wires) == c.evaluate(&self.wires)
+        })
+    }
+}
+
+fn modulus_bytes<T: FieldElement>() -> Vec<u8> {
+    let mut bytes = T::modulus().to_bytes_le();
+    bytes.resize(T::byte_len(), 0);
+    bytes
+}
+
+fn write_sections(
+    writer: &mut impl Write,
+    magic: &[u8; 4],
+    version: u32,
+    sections: &[(u32, Vec<u8>)],
+) -> io::Result<()> {
+    writer.write_all(magic)?;
+    writer.write_all(&version.to_le_bytes())?;
+    writer.write_all(&(sections.len() as u32).to_le_bytes())?;
+    for (section_type, content) in sections {
+        writer.write_all(&section_type.to_le_bytes())?;
+        writer.write_all(&(content.len() as u64).to_le_bytes())?;
+        writer.write_all(content)?;
+    }
+    Ok(())
+}
+
+struct Exporter<'a, T> {
+    analyzed: &'a Analyzed<T>,
+    degree: DegreeType,
+    fixed: HashMap<&'a str, &'a Vec<T>>,
+    witness: HashMap<&'a str, &'a Vec<T>>,
+    /// The wire of each witness cell (column name and row) that is used.
+    cell_wires: HashMap<(&'a str, DegreeType), usize>,
+    wires: Vec<T>,
+    constraints: Vec<Constraint<T>>,
+}
+
+impl<'a, T: FieldElement> Exporter<'a, T> {
+    fn new_wire(&mut self, value: T) -> usize {
+        self.wires.push(value);
+        self.wires.len() - 1
+    }
+
+    fn constrain_zero(&mut self, lc: LinearCombination<T>) {
+        if lc.as_constant() != Some(T::zero()) {
+            self.constraints.push((
+                lc,
+                LinearCombination::constant(T::one()),
+                LinearCombination::constant(T::zero()),
+            ));
+        }
+    }
+
+    fn polynomial_identity(
+        &mut self,
+        expression: &Expression<T>,
+        row: DegreeType,
+    ) -> Result<(), String> {
+        // A product at the top level does not need its own wire.
+        if let Expression::BinaryOperation(left, BinaryOperator::Mul, right) = expression {
+            let expressions = &self.analyzed.expressions;
+            let (left, right) = (
+                self.lower(&expressions[*left], row)?,
+                self.lower(&expressions[*right], row)?,
+            );
+            if left.as_constant().is_none() && right.as_constant().is_none() {
+                self.constraints
+                    .push((left, right, LinearCombination::constant(T::zero())));
+                return Ok(());
+            }
+        }
+        let lc = self.lower(expression, row)?;
+        self.constrain_zero(lc);
+        Ok(())
+    }
+
+    fn plookup(
+        &mut self,
+        left: &SelectedExpressions<T>,
+        right: &SelectedExpressions<T>,
+    ) -> Result<(), String> {
+        let table = self.table(right)?;
+        for row in 0..self.degree {
+            let selector = match &left.selector {
+                Some(selector) => self.lower(selector, row)?,
+                None => LinearCombination::constant(T::one()),
+            };
+            if selector.as_constant() == Some(T::zero()) {
+                continue;
+            }
+            let inputs = left
+                .expressions
+                .iter()
+                .map(|e| {
+                    let input = self.lower(e, row)?;
+                    Ok(self.multiply(selector.clone(), input))
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            let input_values = inputs
+                .iter()
+                .map(|i| i.evaluate(&self.wires))
+                .collect::<Vec<_>>();
+            let selector_value = selector.evaluate(&self.wires);
+
+            // The inputs are multiplied by the selector, so this finds the
+            // matching entry if the selector is one.
+            let choices = table
+                .iter()
+                .map(|entry| {
+                    let chosen = !selector_value.is_zero()
+                        && entry
+                            .iter()
+                            .zip(&input_values)
+                            .all(|(t, i)| *t * selector_value == *i);
+                    self.new_wire(chosen.into())
+                })
+                .collect::<Vec<_>>();
+            let mut sum = selector.scale(-T::one());
+            for choice in &choices {
+                let choice_lc = LinearCombination::wire(*choice);
+                self.constraints.push((
+                    choice_lc.clone(),
+                    choice_lc
+                        .clone()
+                        .scale(-T::one())
+                        .add(&LinearCombination::constant(T::one())),
+                    LinearCombination::constant(T::zero()),
+                ));
+                sum = sum.add(&choice_lc);
+            }
+            self.constrain_zero(sum);
+            for (k, input) in inputs.into_iter().enumerate() {
+                let chosen = choices.iter().zip(&table).fold(
+                    input.scale(-T::one()),
+                    |acc, (choice, entry)| {
+                        acc.add(&LinearCombination::wire(*choice).scale(entry[k]))
+                    },
+                );
+                self.constrain_zero(chosen);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the distinct tuples of the right hand side of a lookup on the
+    /// rows where its selector is one.
+    fn table(&mut self, right: &SelectedExpressions<T>) -> Result<Vec<Vec<T>>, String> {
+        let mut seen = HashSet::new();
+        let mut table = vec![];
+        for row in 0..self.degree {
+            let constant = |exporter: &mut Self, e: &Expression<T>| {
+                exporter.lower(e, row)?.as_constant().ok_or_else(|| {
+                    format!(
+                        "Lookups into witness columns cannot be exported to R1CS: {}",
+                        exporter.analyzed.expressions.display(e)
+                    )
+                })
+            };
+            if let Some(selector) = &right.selector {
+                if constant(self, selector)?.is_zero() {
+                    continue;
+                }
+            }
+            let entry = right
+                .expressions
+                .iter()
+                .map(|e| constant(self, e))
+                .collect::<Result<Vec<_>, _>>()?;
+            if seen.insert(entry.clone()) {
+                table.push(entry);
+            }
+        }
+        Ok(table)
+    }
+
+    fn multiply(
+        &mut self,
+        left: LinearCombination<T>,
+        right: LinearCombination<T>,
+    ) -> LinearCombination<T> {
+        if let Some(c) = left.as_constant() {
+            right.scale(c)
+        } else if let Some(c) = right.as_constant() {
+            left.scale(c)
+        } else {
+            let wire = self.new_wire(left.evaluate(&self.wires) * right.evaluate(&self.wires));
+            self.constraints
+                .push((left, right, LinearCombination::wire(wire)));
+            LinearCombination::wire(wire)
+        }
+    }
+
+    fn lower(
+        &mut self,
+        expression: &Expression<T>,
+        row: DegreeType,
+    ) -> Result<LinearCombination<T>, String> {
+        let expressions = &self.analyzed.expressions;
+        let text = || expressions.display(expression);
+        Ok(match expression {
+            Expression::Number(n) => LinearCombination::constant(*n),
+            Expression::Constant(name) => {
+                LinearCombination::constant(self.analyzed.constants[name])
+            }
+            Expression::PolynomialReference(reference) if reference.index.is_none() => {
+                let row = (row + reference.next as DegreeType) % self.degree;
+                let name = reference.name.as_str();
+                let (poly, definition) = &self.analyzed.definitions[&reference.name];
+                match poly.poly_type {
+                    PolynomialType::Constant => {
+                        let values = self
+                            .fixed
+                            .get(name)
+                            .ok_or_else(|| format!("No values for fixed column {name}."))?;
+                        LinearCombination::constant(values[row as usize])
+                    }
+                    PolynomialType::Committed => {
+                        let name = poly.absolute_name.as_str();
+                        let wire = match self.cell_wires.get(&(name, row)) {
+                            Some(wire) => *wire,
+                            None => {
+                                let values = self.witness.get(name).ok_or_else(|| {
+                                    format!("No values for witness column {name}.")
+                                })?;
+                                let wire = self.new_wire(values[row as usize]);
+                                self.cell_wires.insert((name, row), wire);
+                                wire
+                            }
+                        };
+                        LinearCombination::wire(wire)
+                    }
+                    PolynomialType::Intermediate => match definition {
+                        Some(FunctionValueDefinition::Mapping(definition)) => {
+                            self.lower(definition, row)?
+                        }
+                        _ => return Err(format!("Intermediate column {name} has no definition.")),
+                    },
+                }
+            }
+            Expression::BinaryOperation(left, op, right) => {
+                let left = self.lower(&expressions[*left], row)?;
+                match op {
+                    BinaryOperator::Add => left.add(&self.lower(&expressions[*right], row)?),
+                    BinaryOperator::Sub => {
+                        left.add(&self.lower(&expressions[*right], row)?.scale(-T::one()))
+                    }
+                    BinaryOperator::Mul => {
+                        let right = self.lower(&expressions[*right], row)?;
+                        self.multiply(left, right)
+                    }
+                    BinaryOperator::Pow => {
+                        let exponent = self
+                            .lower(&expressions[*right], row)?
+                            .as_constant()
+                            .and_then(|e| e.try_to_u64().ok())
+                            .ok_or_else(|| format!("Non-constant exponent: {}", text()))?;
+                        // Square-and-multiply, which needs a logarithmic
+                        // number of products in the exponent.
+                        let (mut result, mut base, mut exponent) =
+                            (LinearCombination::constant(T::one()), left, exponent);
+                        while exponent > 0 {
+                            if exponent & 1 == 1 {
+                                result = self.multiply(result, base.clone());
+                            }
+                            exponent >>= 1;
+                            if exponent > 0 {
+                                base = self.multiply(base.clone(), base);
+                            }
+                        }
+                        result
+                    }
+                    _ => {
+                        // Other operators are only allowed on constants.
+                        let (l, r) = (
+                            left.as_constant(),
+                            self.lower(&expressions[*right], row)?.as_constant(),
+                        );
+                        let (Some(l), Some(r)) = (l, r) else {
+                            return Err(format!(
+                                "Operator {op} on non-constant values: {}",
+                                text()
+                            ));
+                        };
+                        LinearCombination::constant(match op {
+                            BinaryOperator::Div => l / r,
+                            BinaryOperator::Mod => l.integer_mod(r),
+                            BinaryOperator::BinaryAnd => l.bitand(r),
+                            BinaryOperator::BinaryOr => l.bitor(r),
+                            BinaryOperator::BinaryXor => l.bitxor(r),
+                            _ => return Err(format!("Operator {op} is not supported: {}", text())),
+                        })
+                    }
+                }
+            }
+            Expression::UnaryOperation(UnaryOperator::Minus, inner) => {
+                self.lower(&expressions[*inner], row)?.scale(-T::one())
+            }
+            Expression::UnaryOperation(UnaryOperator::Plus, inner) => {
+                self.lower(&expressions[*inner], row)?
+            }
+            _ => return Err(format!("Expression cannot be exported to R1CS: {}", text())),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use number::Bn254Field;
+
+    use super::*;
+    use crate::analyze_string;
+
+    fn columns<'a>(names: &[&'a str], values: &[&[i32]]) -> Vec<(&'a str, Vec<Bn254Field>)> {
+        names
+            .iter()
+            .zip(values)
+            .map(|(n, v)| (*n, v.iter().map(|x| Bn254Field::from(*x)).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn fibonacci() {
+        let analyzed = analyze_string::<Bn254Field>(
+            r#"
+namespace F(4);
+    col fixed ISLAST = [0, 0, 0, 1];
+    col witness x, y;
+    ISLAST * (y' - 1) = 0;
+    ISLAST * (x' - 1) = 0;
+    (1 - ISLAST) * (x' - y) = 0;
+    (1 - ISLAST) * (y' - (x + y)) = 0;
+    public out = y(3);
+"#,
+        );
+        let fixed = columns(&["F.ISLAST"], &[&[0, 0, 0, 1]]);
+        let mut witness = columns(&["F.x", "F.y"], &[&[1, 1, 2, 3], &[1, 2, 3, 5]]);
+        let r1cs = export(&analyzed, &fixed, &witness).unwrap();
+        assert_eq!(r1cs.wires[..2], [1.into(), 5.into()]);
+        // One wire per cell and the public.
+        assert_eq!(r1cs.wires.len(), 10);
+        assert!(r1cs.is_satisfied());
+
+        let mut file = vec![];
+        r1cs.write_r1cs(&mut file).unwrap();
+        assert_eq!(&file[..4], b"r1cs");
+
+        witness[1].1[2] = 4.into();
+        assert!(!export(&analyzed, &fixed, &witness).unwrap().is_satisfied());
+    }
+
+    #[test]
+    fn lookup() {
+        let analyzed = analyze_string::<Bn254Field>(
+            r#"
+namespace L(4);
+    col fixed A = [1, 2, 3, 4];
+    col fixed B = [2, 4, 6, 8];
+    col fixed SEL = [1, 1, 0, 1];
+    col witness x, y;
+    SEL { x, y } in { A, B };
+"#,
+        );
+        let fixed = columns(
+            &["L.A", "L.B", "L.SEL"],
+            &[&[1, 2, 3, 4], &[2, 4, 6, 8], &[1, 1, 0, 1]],
+        );
+        let mut witness = columns(&["L.x", "L.y"], &[&[3, 1, 7, 4], &[6, 2, 0, 8]]);
+        assert!(export(&analyzed, &fixed, &witness).unwrap().is_satisfied());
+        witness[1].1[3] = 7.into();
+        assert!(!export(&analyzed, &fixed, &witness).unwrap().is_satisfied());
+    }
+
+    #[test]
+    fn power() {
+        let analyzed = analyze_string::<Bn254Field>(
+            r#"
+namespace X(2);
+    col witness x, y;
+    y = x**5;
+"#,
+        );
+        let witness = columns(&["X.x", "X.y"], &[&[2, 3], &[32, 243]]);
+        let r1cs = export(&analyzed, &[], &witness).unwrap();
+        assert!(r1cs.is_satisfied());
+        // x**2, x**4 and x * x**4 and the identity on each row.
+        assert_eq!(r1cs.constraints.len(), 8);
+    }
+
+    #[test]
+    fn rows_out_of_range() {
+        let analyzed = analyze_string::<Bn254Field>(
+            r#"
+namespace R(2);
+    col witness x;
+    public p = x(2);
+"#,
+        );
+        let witness = columns(&["R.x"], &[&[1, 2]]);
+        assert_eq!(
+            export(&analyzed, &[], &witness).err().unwrap(),
+            "Public p refers to row 2 of R.x, which only has 2 rows."
+        );
+        let witness = columns(&["R.x"], &[&[1]]);
+        assert_eq!(
+            export(&analyzed, &[], &witness).err().unwrap(),
+            "Column R.x has 1 rows instead of 2."
+        );
+    }
+
+    #[test]
+    fn permutation() {
+        let analyzed = analyze_string::<Bn254Field>(
+            r#"
+namespace P(2);
+    col witness x, y;
+    { x } is { y };
+"#,
+        );
+        let witness = columns(&["P.x", "P.y"], &[&[1, 2], &[2, 1]]);
+        assert!(export(&analyzed, &[], &witness).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+//! Evaluates expressions of an analyzed PIL file on single rows of a trace.
+
+use std::collections::HashMap;
+
+use number::FieldElement;
+
+use crate::{
+    Analyzed, BinaryOperator, Expression, FunctionValueDefinition, PolynomialType,
+    SelectedExpressions, UnaryOperator,
+};
+
+/// The cells an evaluation references, with their values.
+pub type Cells<T> = Vec<(String, T)>;
+
+/// A row, the values of a tuple on that row and the cells they reference.
+pub type SelectedTuple<T> = (usize, Vec<T>, Cells<T>);
+
+pub struct RowEvaluator<'a, T> {
+    analyzed: &'a Analyzed<T>,
+    columns: HashMap<&'a str, &'a [T]>,
+    pub degree: usize,
+}
+
+impl<'a, T: FieldElement> RowEvaluator<'a, T> {
+    pub fn new(
+        analyzed: &'a Analyzed<T>,
+        fixed: &'a [(&'a str, Vec<T>)],
+        witness: &'a [(&'a str, Vec<T>)],
+    ) -> Self {
+        let columns = fixed
+            .iter()
+            .chain(witness)
+            .map(|(name, values)| (*name, values.as_slice()))
+            .collect();
+        RowEvaluator {
+            analyzed,
+            columns,
+            degree: analyzed
+                .definitions
+                .values()
+                .map(|(poly, _)| poly.degree as usize)
+                .max()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Returns the first fixed or witness column of the PIL file that has no values.
+    pub fn missing_column(&self) -> Option<&'a str> {
+        self.analyzed
+            .definitions
+            .values()
+            .filter(|(poly, _)| poly.poly_type != PolynomialType::Intermediate)
+            .map(|(poly, _)| poly.absolute_name.as_str())
+            .find(|name| !self.columns.contains_key(name))
+    }
+
+    /// Returns the values of a fixed or witness column.
+    pub fn column(&self, name: &str) -> Option<&'a [T]> {
+        self.columns.get(name).copied()
+    }
+
+    /// Returns the rows where the selector is not zero, with the values of the tuple.
+    pub fn selected_tuples(
+        &self,
+        selected: &SelectedExpressions<T>,
+    ) -> Result<Vec<(usize, Vec<T>)>, String> {
+        let mut tuples = vec![];
+        for row in 0..self.degree {
+            if let Some(tuple) = self.selected_tuple(selected, row, None)? {
+                tuples.push((row, tuple));
+            }
+        }
+        Ok(tuples)
+    }
+
+    /// Like `selected_tuples`, but also returns the cells each tuple references.
+    pub fn selected_tuples_with_cells(
+        &self,
+        selected: &SelectedExpressions<T>,
+    ) -> Result<Vec<SelectedTuple<T>>, String> {
+        let mut tuples = vec![];
+        for row in 0..self.degree {
+            let mut cells = vec![];
+            if let Some(tuple) = self.selected_tuple(selected, row, Some(&mut cells))? {
+                tuples.push((row, tuple, cells));
+            }
+        }
+        Ok(tuples)
+    }
+
+    fn selected_tuple(
+        &self,
+        selected: &SelectedExpressions<T>,
+        row: usize,
+        mut cells: Option<&mut Cells<T>>,
+    ) -> Result<Option<Vec<T>>, String> {
+        if let Some(selector) = &selected.selector {
+            if self
+                .evaluate_recording(selector, row, cells.as_deref_mut())?
+                .is_zero()
+            {
+                return Ok(None);
+            }
+        }
+        selected
+            .expressions
+            .iter()
+            .map(|e| self.evaluate_recording(e, row, cells.as_deref_mut()))
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+
+    /// Evaluates the expression on the given row.
+    pub fn evaluate(&self, expr: &Expression<T>, row: usize) -> Result<T, String> {
+        self.evaluate_recording(expr, row, None)
+    }
+
+    /// Evaluates the expression on the given row and adds the values of all
+    /// referenced columns to `cells`, if given.
+    pub fn evaluate_recording(
+        &self,
+        expr: &Expression<T>,
+        row: usize,
+        mut cells: Option<&mut Cells<T>>,
+    ) -> Result<T, String> {
+        Ok(match expr {
+            Expression::Constant(name) => self.analyzed.constants[name],
+            Expression::Number(n) => *n,
+            Expression::PolynomialReference(reference) if reference.index.is_none() => {
+                let name = reference.name.as_str();
+                let row = (row + reference.next as usize) % self.degree;
+                let value = if let Some(values) = self.columns.get(name) {
+                    values[row]
+                } else if let Some((_, Some(FunctionValueDefinition::Mapping(definition)))) =
+                    self.analyzed.definitions.get(&reference.name)
+                {
+                    self.evaluate_recording(definition, row, cells.as_deref_mut())?
+                } else {
+                    return Err(format!("reference to {name}"));
+                };
+                if let Some(cells) = cells {
+                    let cell = format!("{name}{}", if reference.next { "'" } else { "" });
+                    if !cells.iter().any(|(c, _)| *c == cell) {
+                        cells.push((cell, value));
+                    }
+                }
+                value
+            }
+            Expression::BinaryOperation(left, op, right) => {
+                let left = self.evaluate_recording(
+                    &self.analyzed.expressions[*left],
+                    row,
+                    cells.as_deref_mut(),
+                )?;
+                let right =
+                    self.evaluate_recording(&self.analyzed.expressions[*right], row, cells)?;
+                match op {
+                    BinaryOperator::Add => left + right,
+                    BinaryOperator::Sub => left - right,
+                    BinaryOperator::Mul => left * right,
+                    BinaryOperator::Pow => left.pow(right.to_integer()),
+                    _ => return Err(format!("operator {op} in identity")),
+                }
+            }
+            Expression::UnaryOperation(op, inner) => {
+                let value =
+                    self.evaluate_recording(&self.analyzed.expressions[*inner], row, cells)?;
+                match op {
+                    UnaryOperator::Plus => value,
+                    UnaryOperator::Minus => -value,
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "expression {} in identity",
+                    self.analyzed.expressions.display(expr)
+                ))
+            }
+        })
+    }
+}
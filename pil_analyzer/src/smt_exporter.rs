@@ -0,0 +1,426 @@
+//! Exports the identities of an analyzed PIL file on the first rows as an
+//! SMT-LIB script, so that properties of small instances, like the
+//! uniqueness of the witness or that a state cannot be reached, can be
+//! checked with Z3 or cvc5.
+//!
+//! Every witness cell becomes an integer constant `|Main.x@3|` in the range
+//! of the field, fixed and intermediate columns are inlined. The identities
+//! are asserted modulo the field modulus, lookups as a disjunction over the
+//! rows of the table. The script does not end with `(check-sat)`, so that
+//! the properties to check can be appended.
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Write};
+
+use number::{DegreeType, FieldElement};
+
+use crate::util::referenced_namespaces;
+use crate::{
+    Analyzed, BinaryOperator, Expression, FunctionValueDefinition, Identity, IdentityKind,
+    PolynomialType, SelectedExpressions, UnaryOperator,
+};
+
+/// An SMT-LIB term, with the parts that only depend on fixed columns folded
+/// into values.
+#[derive(Clone, PartialEq, Eq)]
+enum Term<T> {
+    Value(T),
+    Smt(String),
+}
+
+impl<T: FieldElement> Term<T> {
+    fn add(self, other: Self) -> Self {
+        match (self, other) {
+            (Term::Value(a), Term::Value(b)) => Term::Value(a + b),
+            (Term::Value(z), t) | (t, Term::Value(z)) if z.is_zero() => t,
+            (a, b) => Term::Smt(format!("(+ {a} {b})")),
+        }
+    }
+
+    fn sub(self, other: Self) -> Self {
+        match (self, other) {
+            (Term::Value(a), Term::Value(b)) => Term::Value(a - b),
+            (t, Term::Value(z)) if z.is_zero() => t,
+            (a, b) => Term::Smt(format!("(- {a} {b})")),
+        }
+    }
+
+    fn mul(self, other: Self) -> Self {
+        match (self, other) {
+            (Term::Value(a), Term::Value(b)) => Term::Value(a * b),
+            (Term::Value(z), _) | (_, Term::Value(z)) if z.is_zero() => Term::Value(z),
+            (Term::Value(o), t) | (t, Term::Value(o)) if o == T::one() => t,
+            (a, b) => Term::Smt(format!("(* {a} {b})")),
+        }
+    }
+
+    /// The formula that the term is zero in the field.
+    fn is_zero(&self) -> String {
+        match self {
+            Term::Value(v) => v.is_zero().to_string(),
+            Term::Smt(t) => format!("(= (mod {t} modulus) 0)"),
+        }
+    }
+}
+
+impl<T: FieldElement> fmt::Display for Term<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::Value(v) => write!(f, "{}", v.to_integer()),
+            Term::Smt(t) => write!(f, "{t}"),
+        }
+    }
+}
+
+/// Returns the SMT-LIB script for the identities that reference the
+/// namespace (all identities if it is None) on the first `rows` rows, with
+/// the values of the fixed columns given by name. An identity is only
+/// asserted on the rows on which it does not reference a witness cell beyond
+/// `rows`, with the next row of the last row being the first row if `rows` is
+/// the degree. Lookups into tables with witness columns are only asserted if
+/// `rows` is the degree, LogUp multiplicities are not checked.
+/// Fails for permutation and connect identities.
+pub fn export<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    fixed: &[(&str, Vec<T>)],
+    namespace: Option<&str>,
+    rows: DegreeType,
+) -> Result<String, String> {
+    let degree = analyzed
+        .definitions
+        .values()
+        .map(|(poly, _)| poly.degree)
+        .max()
+        .unwrap_or_default();
+    let mut exporter = Exporter {
+        analyzed,
+        fixed,
+        degree,
+        rows: rows.min(degree),
+        cells: BTreeSet::new(),
+        pending_cells: vec![],
+        out_of_range: false,
+    };
+    let mut assertions = String::new();
+    for identity in &analyzed.identities {
+        if let Some(namespace) = namespace {
+            if !referenced_namespaces(analyzed, &identity.left, false).contains(namespace) {
+                continue;
+            }
+        }
+        writeln!(
+            assertions,
+            "; {}:{}: {}",
+            identity.source.file,
+            identity.source.line,
+            analyzed.expressions.display(identity)
+        )
+        .unwrap();
+        for formula in exporter.identity(identity)? {
+            writeln!(assertions, "(assert {formula})").unwrap();
+        }
+    }
+
+    let mut smt = format!(
+        "; {} identities on {} of {degree} rows\n(set-logic QF_NIA)\n(define-fun modulus () Int {})\n",
+        namespace.unwrap_or("All"),
+        exporter.rows,
+        T::modulus()
+    );
+    for cell in &exporter.cells {
+        writeln!(smt, "(declare-const {cell} Int)").unwrap();
+        writeln!(smt, "(assert (and (<= 0 {cell}) (< {cell} modulus)))").unwrap();
+    }
+    smt.push_str(&assertions);
+    Ok(smt)
+}
+
+struct Exporter<'a, T> {
+    analyzed: &'a Analyzed<T>,
+    fixed: &'a [(&'a str, Vec<T>)],
+    degree: DegreeType,
+    rows: DegreeType,
+    /// The names of the witness cells that are used.
+    cells: BTreeSet<String>,
+    /// The cells of the formula that is built, they are used if it is
+    /// asserted.
+    pending_cells: Vec<String>,
+    /// Set if an expression references a witness cell beyond `rows`.
+    out_of_range: bool,
+}
+
+impl<'a, T: FieldElement> Exporter<'a, T> {
+    /// The formulas of the identity, one per row.
+    fn identity(&mut self, identity: &Identity<T>) -> Result<Vec<String>, String> {
+        let mut formulas = vec![];
+        for row in 0..self.rows {
+            self.pending_cells.clear();
+            let formula = match identity.kind {
+                IdentityKind::Polynomial => {
+                    let expression = identity.left.selector.as_ref().unwrap();
+                    self.lower_in_range(expression, row)?
+                        .map(|term| term.is_zero())
+                }
+                IdentityKind::Plookup | IdentityKind::LogUp => {
+                    self.lookup(&identity.left, &identity.right, row)?
+                }
+                IdentityKind::Permutation | IdentityKind::Connect => {
+                    return Err(format!(
+                        "{:?} identities cannot be exported to SMT-LIB ({}:{}).",
+                        identity.kind, identity.source.file, identity.source.line
+                    ))
+                }
+            };
+            if let Some(formula) = formula.filter(|formula| formula != "true") {
+                self.cells.extend(self.pending_cells.drain(..));
+                formulas.push(formula);
+            }
+        }
+        Ok(formulas)
+    }
+
+    /// The formula that the left hand side on the row is in the table or not
+    /// selected, or None if the row or the table references a cell beyond
+    /// `rows`.
+    fn lookup(
+        &mut self,
+        left: &SelectedExpressions<T>,
+        right: &SelectedExpressions<T>,
+        row: DegreeType,
+    ) -> Result<Option<String>, String> {
+        let Some(tuple) = self.selected_tuple(left, row)? else {
+            return Ok(None);
+        };
+        let Some((selector, inputs)) = tuple else {
+            return Ok(Some("true".to_string()));
+        };
+        let mut entries = vec![];
+        for table_row in 0..self.degree {
+            let cells = self.pending_cells.len();
+            let Some(entry) = self.selected_tuple(right, table_row)? else {
+                // The table is not complete on the rows.
+                return Ok(None);
+            };
+            let Some((table_selector, values)) = entry else {
+                continue;
+            };
+            let mut conditions = inputs
+                .iter()
+                .zip(values)
+                .map(|(input, value)| input.clone().sub(value).is_zero())
+                .collect::<Vec<_>>();
+            if let Some(table_selector) = table_selector {
+                conditions.push(format!("(not {})", table_selector.is_zero()));
+            }
+            conditions.retain(|c| c != "true");
+            if conditions.iter().any(|c| c == "false") {
+                self.pending_cells.truncate(cells);
+                continue;
+            }
+            let entry = match conditions.len() {
+                0 => "true".to_string(),
+                1 => conditions.pop().unwrap(),
+                _ => format!("(and {})", conditions.join(" ")),
+            };
+            if entry == "true" {
+                return Ok(Some("true".to_string()));
+            }
+            if !entries.contains(&entry) {
+                entries.push(entry);
+            }
+        }
+        let lookup = match entries.len() {
+            0 => "false".to_string(),
+            1 => entries.pop().unwrap(),
+            _ => format!("(or {})", entries.join(" ")),
+        };
+        Ok(Some(match selector {
+            Some(selector) => format!("(or {} {lookup})", selector.is_zero()),
+            None => lookup,
+        }))
+    }
+
+    /// The selector (None if it is one) and the expressions on the row,
+    /// Some(None) if the selector is zero and None if the row references a
+    /// cell beyond `rows`.
+    #[allow(clippy::type_complexity)]
+    fn selected_tuple(
+        &mut self,
+        selected: &SelectedExpressions<T>,
+        row: DegreeType,
+    ) -> Result<Option<Option<(Option<Term<T>>, Vec<Term<T>>)>>, String> {
+        let cells = self.pending_cells.len();
+        let selector = match &selected.selector {
+            Some(selector) => match self.lower_in_range(selector, row)? {
+                None => return Ok(None),
+                Some(Term::Value(v)) if v.is_zero() => return Ok(Some(None)),
+                Some(Term::Value(v)) if v == T::one() => None,
+                Some(selector) => Some(selector),
+            },
+            None => None,
+        };
+        let mut expressions = vec![];
+        for e in &selected.expressions {
+            match self.lower_in_range(e, row)? {
+                Some(term) => expressions.push(term),
+                None => {
+                    self.pending_cells.truncate(cells);
+                    return Ok(None);
+                }
+            }
+        }
+        Ok(Some(Some((selector, expressions))))
+    }
+
+    /// Lowers the expression, or returns None if it references a witness
+    /// cell beyond `rows`.
+    fn lower_in_range(
+        &mut self,
+        expression: &Expression<T>,
+        row: DegreeType,
+    ) -> Result<Option<Term<T>>, String> {
+        let cells = self.pending_cells.len();
+        self.out_of_range = false;
+        let term = self.lower(expression, row)?;
+        if self.out_of_range {
+            self.pending_cells.truncate(cells);
+            return Ok(None);
+        }
+        Ok(Some(term))
+    }
+
+    fn lower(&mut self, expression: &Expression<T>, row: DegreeType) -> Result<Term<T>, String> {
+        let expressions = &self.analyzed.expressions;
+        let text = || expressions.display(expression);
+        Ok(match expression {
+            Expression::Number(n) => Term::Value(*n),
+            Expression::Constant(name) => Term::Value(self.analyzed.constants[name]),
+            Expression::PublicReference(name) => {
+                let declaration = &self.analyzed.public_declarations[name];
+                let reference = Expression::PolynomialReference(declaration.polynomial.clone());
+                self.lower(&reference, declaration.index)?
+            }
+            Expression::PolynomialReference(reference) if reference.index.is_none() => {
+                let row = (row + reference.next as DegreeType) % self.degree;
+                let name = reference.name.as_str();
+                let (poly, definition) = &self.analyzed.definitions[&reference.name];
+                match poly.poly_type {
+                    PolynomialType::Constant => {
+                        let (_, values) = self
+                            .fixed
+                            .iter()
+                            .find(|(n, _)| *n == name)
+                            .ok_or_else(|| format!("No values for fixed column {name}."))?;
+                        Term::Value(values[row as usize])
+                    }
+                    PolynomialType::Committed => {
+                        if row >= self.rows {
+                            self.out_of_range = true;
+                        }
+                        let cell = format!("|{}@{row}|", poly.absolute_name);
+                        self.pending_cells.push(cell.clone());
+                        Term::Smt(cell)
+                    }
+                    PolynomialType::Intermediate => match definition {
+                        Some(FunctionValueDefinition::Mapping(definition)) => {
+                            self.lower(definition, row)?
+                        }
+                        _ => return Err(format!("Intermediate column {name} has no definition.")),
+                    },
+                }
+            }
+            Expression::BinaryOperation(left, op, right) => {
+                let (left, right) = (
+                    self.lower(&expressions[*left], row)?,
+                    self.lower(&expressions[*right], row)?,
+                );
+                match (op, left, right) {
+                    (BinaryOperator::Add, left, right) => left.add(right),
+                    (BinaryOperator::Sub, left, right) => left.sub(right),
+                    (BinaryOperator::Mul, left, right) => left.mul(right),
+                    (BinaryOperator::Pow, left, Term::Value(exponent)) => {
+                        let exponent = exponent
+                            .try_to_u64()
+                            .map_err(|_| format!("Exponent too large: {}", text()))?;
+                        (0..exponent)
+                            .fold(Term::Value(T::one()), |result, _| result.mul(left.clone()))
+                    }
+                    // Other operators are only allowed on fixed values.
+                    (op, Term::Value(l), Term::Value(r)) => Term::Value(match op {
+                        BinaryOperator::Div => l / r,
+                        BinaryOperator::Mod => l.integer_mod(r),
+                        BinaryOperator::BinaryAnd => l.bitand(r),
+                        BinaryOperator::BinaryOr => l.bitor(r),
+                        BinaryOperator::BinaryXor => l.bitxor(r),
+                        _ => return Err(format!("Operator {op} is not supported: {}", text())),
+                    }),
+                    (op, _, _) => {
+                        return Err(format!("Operator {op} on witness columns: {}", text()))
+                    }
+                }
+            }
+            Expression::UnaryOperation(UnaryOperator::Minus, inner) => {
+                Term::Value(T::zero()).sub(self.lower(&expressions[*inner], row)?)
+            }
+            Expression::UnaryOperation(UnaryOperator::Plus, inner) => {
+                self.lower(&expressions[*inner], row)?
+            }
+            _ => {
+                return Err(format!(
+                    "Expression cannot be exported to SMT-LIB: {}",
+                    text()
+                ))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+
+    use super::*;
+    use crate::analyze_string;
+
+    #[test]
+    fn fibonacci() {
+        let analyzed = analyze_string::<GoldilocksField>(
+            r#"
+namespace F(4);
+    col fixed ISLAST = [0, 0, 0, 1];
+    col fixed BIT = [0, 1, 0, 1];
+    col witness x, y;
+    ISLAST * (y' - 1) = 0;
+    (1 - ISLAST) * (x' - y) = 0;
+    { x } in { BIT };
+"#,
+        );
+        let fixed = [
+            ("F.ISLAST", [0, 0, 0, 1].map(GoldilocksField::from).to_vec()),
+            ("F.BIT", [0, 1, 0, 1].map(GoldilocksField::from).to_vec()),
+        ];
+        let smt = export(&analyzed, &fixed, Some("F"), 2).unwrap();
+        assert_eq!(
+            smt,
+            "; F identities on 2 of 4 rows
+(set-logic QF_NIA)
+(define-fun modulus () Int 18446744069414584321)
+(declare-const |F.x@0| Int)
+(assert (and (<= 0 |F.x@0|) (< |F.x@0| modulus)))
+(declare-const |F.x@1| Int)
+(assert (and (<= 0 |F.x@1|) (< |F.x@1| modulus)))
+(declare-const |F.y@0| Int)
+(assert (and (<= 0 |F.y@0|) (< |F.y@0| modulus)))
+; input:6: (F.ISLAST * (F.y' - 1)) = 0;
+; input:7: ((1 - F.ISLAST) * (F.x' - F.y)) = 0;
+(assert (= (mod (- |F.x@1| |F.y@0|) modulus) 0))
+; input:8: { F.x } in { F.BIT };
+(assert (or (= (mod |F.x@0| modulus) 0) (= (mod (- |F.x@0| 1) modulus) 0)))
+(assert (or (= (mod |F.x@1| modulus) 0) (= (mod (- |F.x@1| 1) modulus) 0)))
+"
+        );
+        assert!(export(&analyzed, &fixed, Some("G"), 2)
+            .unwrap()
+            .ends_with("(define-fun modulus () Int 18446744069414584321)\n"));
+    }
+}
@@ -0,0 +1,305 @@
+//! Statistics about the size of an analyzed PIL file: the number of columns
+//! and identities, the maximal degree of the identities and the number of
+//! committed cells, in total and by namespace.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use number::FieldElement;
+
+use crate::util::{namespace, referenced_namespaces};
+use crate::{
+    Analyzed, BinaryOperator, Expression, FunctionValueDefinition, Identity, IdentityKind,
+    PolynomialType, SelectedExpressions,
+};
+
+/// The statistics of a single namespace.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NamespaceStats {
+    pub degree: u64,
+    pub witness_columns: usize,
+    pub fixed_columns: usize,
+    pub intermediate_columns: usize,
+    pub polynomial_identities: usize,
+    pub plookup_identities: usize,
+    pub logup_identities: usize,
+    pub permutation_identities: usize,
+    pub connect_identities: usize,
+    /// The maximal degree of the identities in this namespace.
+    pub max_identity_degree: usize,
+}
+
+impl NamespaceStats {
+    pub fn identities(&self) -> usize {
+        self.polynomial_identities
+            + self.plookup_identities
+            + self.logup_identities
+            + self.permutation_identities
+            + self.connect_identities
+    }
+
+    /// The number of cells in the witness columns, which is an estimate of
+    /// the size of the commitments.
+    pub fn committed_cells(&self) -> u64 {
+        self.witness_columns as u64 * self.degree
+    }
+
+    fn add(&mut self, other: &NamespaceStats) {
+        self.degree = self.degree.max(other.degree);
+        self.witness_columns += other.witness_columns;
+        self.fixed_columns += other.fixed_columns;
+        self.intermediate_columns += other.intermediate_columns;
+        self.polynomial_identities += other.polynomial_identities;
+        self.plookup_identities += other.plookup_identities;
+        self.logup_identities += other.logup_identities;
+        self.permutation_identities += other.permutation_identities;
+        self.connect_identities += other.connect_identities;
+        self.max_identity_degree = self.max_identity_degree.max(other.max_identity_degree);
+    }
+}
+
+/// The statistics of all namespaces of a PIL file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub namespaces: BTreeMap<String, NamespaceStats>,
+}
+
+impl Stats {
+    /// The sums over all namespaces. The degree is the maximal degree.
+    pub fn total(&self) -> NamespaceStats {
+        let mut total = NamespaceStats::default();
+        for stats in self.namespaces.values() {
+            total.add(stats);
+        }
+        total
+    }
+
+    /// The number of committed cells over all namespaces.
+    pub fn committed_cells(&self) -> u64 {
+        self.namespaces
+            .values()
+            .map(NamespaceStats::committed_cells)
+            .sum()
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = [
+            "namespace",
+            "degree",
+            "witness",
+            "fixed",
+            "intermediate",
+            "polynomial",
+            "plookup",
+            "logup",
+            "permutation",
+            "connect",
+            "max degree",
+            "committed cells",
+        ];
+        let row = |name: &str, stats: &NamespaceStats, committed_cells: u64| {
+            [
+                name.to_string(),
+                stats.degree.to_string(),
+                stats.witness_columns.to_string(),
+                stats.fixed_columns.to_string(),
+                stats.intermediate_columns.to_string(),
+                stats.polynomial_identities.to_string(),
+                stats.plookup_identities.to_string(),
+                stats.logup_identities.to_string(),
+                stats.permutation_identities.to_string(),
+                stats.connect_identities.to_string(),
+                stats.max_identity_degree.to_string(),
+                committed_cells.to_string(),
+            ]
+        };
+        let rows = std::iter::once(header.map(str::to_string))
+            .chain(
+                self.namespaces
+                    .iter()
+                    .map(|(name, stats)| row(name, stats, stats.committed_cells())),
+            )
+            .chain(std::iter::once(row(
+                "total",
+                &self.total(),
+                self.committed_cells(),
+            )))
+            .collect::<Vec<_>>();
+        let widths = (0..header.len())
+            .map(|i| rows.iter().map(|row| row[i].len()).max().unwrap())
+            .collect::<Vec<_>>();
+        for row in &rows {
+            let line = row
+                .iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(i, (cell, width))| {
+                    if i == 0 {
+                        format!("{cell:<width$}")
+                    } else {
+                        format!("{cell:>width$}")
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("  ");
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// Computes the statistics of the analyzed PIL file. An identity belongs to
+/// the namespace of the witness columns on its left hand side, or of any
+/// column on its left hand side if there are no witness columns.
+pub fn stats<T: FieldElement>(analyzed: &Analyzed<T>) -> Stats {
+    let mut namespaces = BTreeMap::<String, NamespaceStats>::new();
+    for (poly, _) in analyzed.definitions.values() {
+        let stats = namespaces
+            .entry(namespace(&poly.absolute_name).to_string())
+            .or_default();
+        let count = poly.length.unwrap_or(1) as usize;
+        match poly.poly_type {
+            PolynomialType::Committed => stats.witness_columns += count,
+            PolynomialType::Constant => stats.fixed_columns += count,
+            PolynomialType::Intermediate => stats.intermediate_columns += count,
+        }
+        stats.degree = poly.degree;
+    }
+
+    for identity in &analyzed.identities {
+        let owner = referenced_namespaces(analyzed, &identity.left, true)
+            .into_iter()
+            .chain(referenced_namespaces(analyzed, &identity.left, false))
+            .next()
+            .unwrap_or("");
+        let stats = namespaces.entry(owner.to_string()).or_default();
+        match identity.kind {
+            IdentityKind::Polynomial => stats.polynomial_identities += 1,
+            IdentityKind::Plookup => stats.plookup_identities += 1,
+            IdentityKind::LogUp => stats.logup_identities += 1,
+            IdentityKind::Permutation => stats.permutation_identities += 1,
+            IdentityKind::Connect => stats.connect_identities += 1,
+        }
+        stats.max_identity_degree = stats
+            .max_identity_degree
+            .max(identity_degree(analyzed, identity));
+    }
+    Stats { namespaces }
+}
+
+/// The degree of the identity as a polynomial in the columns. For lookups,
+/// permutations and connections, this is the degree of the selected
+/// expressions on the larger side.
+pub fn identity_degree<T: FieldElement>(analyzed: &Analyzed<T>, identity: &Identity<T>) -> usize {
+    let selected_degree = |selected: &SelectedExpressions<T>| {
+        let selector = selected
+            .selector
+            .as_ref()
+            .map(|selector| expression_degree(analyzed, selector))
+            .unwrap_or_default();
+        let expressions = selected
+            .expressions
+            .iter()
+            .map(|e| expression_degree(analyzed, e))
+            .max()
+            .unwrap_or_default();
+        selector + expressions
+    };
+    match identity.kind {
+        IdentityKind::Polynomial => selected_degree(&identity.left),
+        _ => selected_degree(&identity.left).max(selected_degree(&identity.right)),
+    }
+}
+
+/// The degree of the expression as a polynomial in the columns. References
+/// to intermediate columns have the degree of their definition.
+pub fn expression_degree<T: FieldElement>(analyzed: &Analyzed<T>, expr: &Expression<T>) -> usize {
+    match expr {
+        Expression::PolynomialReference(reference) => {
+            match analyzed.definitions.get(&reference.name) {
+                Some((poly, Some(FunctionValueDefinition::Mapping(definition))))
+                    if poly.poly_type == PolynomialType::Intermediate =>
+                {
+                    expression_degree(analyzed, definition)
+                }
+                _ => 1,
+            }
+        }
+        Expression::BinaryOperation(left, op, right) => {
+            let right = &analyzed.expressions[*right];
+            let left = expression_degree(analyzed, &analyzed.expressions[*left]);
+            let right_degree = expression_degree(analyzed, right);
+            match (op, right) {
+                (BinaryOperator::Add | BinaryOperator::Sub, _) => left.max(right_degree),
+                (BinaryOperator::Pow, Expression::Number(exponent)) => {
+                    left * exponent.to_degree() as usize
+                }
+                _ => left + right_degree,
+            }
+        }
+        Expression::UnaryOperation(_, e) => expression_degree(analyzed, &analyzed.expressions[*e]),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use number::GoldilocksField;
+
+    use super::*;
+
+    #[test]
+    fn block_lookup() {
+        let analyzed =
+            crate::analyze::<GoldilocksField>(Path::new("../test_data/pil/block_lookup_or.pil"));
+        let stats = stats(&analyzed);
+        let main = &stats.namespaces["Main"];
+        assert_eq!(main.witness_columns, 1);
+        assert_eq!(main.fixed_columns, 3);
+        assert_eq!(main.plookup_identities, 1);
+        assert_eq!(stats.total().identities(), analyzed.identities.len());
+        assert_eq!(
+            stats.committed_cells(),
+            analyzed.commitment_count() as u64 * 65536
+        );
+    }
+
+    #[test]
+    fn degrees() {
+        let analyzed = crate::analyze_string::<GoldilocksField>(
+            "namespace A(4);
+    col witness x, y;
+    col fixed ONE = [1]*;
+    col sq = x * x;
+    x' = sq * y + 1;
+    y * (1 - y) = 0;
+    (x - 1)**3 = 0;
+    ONE * sq { x } in { y };
+    x + 2 = x;
+namespace B(8);
+    col witness z[2];
+",
+        );
+        let stats = stats(&analyzed);
+        let a = &stats.namespaces["A"];
+        assert_eq!(a.intermediate_columns, 1);
+        assert_eq!(a.polynomial_identities, 4);
+        assert_eq!(a.plookup_identities, 1);
+        assert_eq!(a.max_identity_degree, 4);
+        let degrees = analyzed
+            .identities
+            .iter()
+            .map(|identity| identity_degree(&analyzed, identity))
+            .collect::<Vec<_>>();
+        assert_eq!(degrees, [3, 2, 3, 4, 1]);
+        assert_eq!(stats.namespaces["B"].committed_cells(), 16);
+        assert_eq!(stats.committed_cells(), 2 * 4 + 16);
+        let table = stats.to_string();
+        assert!(table.starts_with("namespace  degree  witness"));
+        assert!(table.lines().last().unwrap().starts_with("total"));
+    }
+}
@@ -0,0 +1,189 @@
+//! Interned names: A `Symbol` is the address of the only copy of a name in
+//! a global interner, so it is copyable, compared and hashed in constant
+//! time and resolved to its name without accessing the interner. Only
+//! interning a name takes the lock of the interner.
+//!
+//! Interned names are never freed, they live as long as the process.
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::ptr;
+use std::sync::{OnceLock, RwLock};
+
+#[derive(Clone, Copy)]
+pub struct Symbol(&'static str);
+
+fn interner() -> &'static RwLock<HashSet<&'static str>> {
+    static INTERNER: OnceLock<RwLock<HashSet<&'static str>>> = OnceLock::new();
+    INTERNER.get_or_init(Default::default)
+}
+
+impl Symbol {
+    /// Returns the symbol of the name, which is the same for equal names.
+    pub fn intern(name: &str) -> Self {
+        if let Some(symbol) = Symbol::lookup(name) {
+            return symbol;
+        }
+        let mut interner = interner().write().unwrap();
+        // Another thread might have interned the name in the meantime.
+        if let Some(name) = interner.get(name) {
+            return Symbol(name);
+        }
+        let name: &'static str = Box::leak(name.into());
+        interner.insert(name);
+        Symbol(name)
+    }
+
+    /// Returns the symbol of the name if it was interned, without interning
+    /// it. A name that is not interned is not the name of any symbol.
+    pub fn lookup(name: &str) -> Option<Self> {
+        interner()
+            .read()
+            .unwrap()
+            .get(name)
+            .map(|name| Symbol(name))
+    }
+
+    /// Resolves the symbol to its name.
+    pub fn as_str(self) -> &'static str {
+        self.0
+    }
+}
+
+/// There is only one copy of each name, so equal symbols have the same address.
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self.0, other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        ptr::hash(self.0, state)
+    }
+}
+
+impl Default for Symbol {
+    fn default() -> Self {
+        Symbol::intern("")
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Self {
+        Symbol::intern(&name)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for Symbol {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<Symbol> for str {
+    fn eq(&self, other: &Symbol) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<Symbol> for &str {
+    fn eq(&self, other: &Symbol) -> bool {
+        *self == other.as_str()
+    }
+}
+
+impl PartialEq<Symbol> for String {
+    fn eq(&self, other: &Symbol) -> bool {
+        self == other.as_str()
+    }
+}
+
+/// Symbols are ordered by their names, so that the order of maps and sorted
+/// lists does not depend on the addresses of the names. Use hash maps where
+/// the order does not matter.
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self == other {
+            Ordering::Equal
+        } else {
+            self.as_str().cmp(other.as_str())
+        }
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn intern_and_resolve() {
+        let x = Symbol::intern("Main.x");
+        let name = String::from("Main.x");
+        assert_eq!(Symbol::intern(&name), x);
+        assert_ne!(Symbol::intern("Main.y"), x);
+        assert_eq!(x.as_str(), "Main.x");
+        assert_eq!(x, "Main.x");
+        assert_eq!(format!("{x} {x:?}"), "Main.x \"Main.x\"");
+        assert_eq!(Symbol::lookup("Main.x"), Some(x));
+        assert_eq!(Symbol::lookup("not interned anywhere"), None);
+        // Ordered by the names, not by the order of interning.
+        let b = Symbol::intern("Main.b");
+        assert!(Symbol::intern("Main.a") < b);
+    }
+}
@@ -1,10 +1,15 @@
-use std::{iter::once, ops::ControlFlow};
+use std::{collections::BTreeSet, iter::once, ops::ControlFlow};
 
-use crate::Expression;
+use crate::{Analyzed, Expression, ExpressionArena, PolynomialType, SelectedExpressions, Symbol};
 
 /// Visits `expr` and all of its sub-expressions and returns true if `f` returns true on any of them.
-pub fn expr_any(expr: &Expression, mut f: impl FnMut(&Expression) -> bool) -> bool {
-    previsit_expression(expr, &mut |e| {
+/// The operands of `expr` are in `expressions`.
+pub fn expr_any<T>(
+    expressions: &ExpressionArena<T>,
+    expr: &Expression<T>,
+    mut f: impl FnMut(&Expression<T>) -> bool,
+) -> bool {
+    previsit_expression(expressions, expr, &mut |e| {
         if f(e) {
             ControlFlow::Break(())
         } else {
@@ -15,9 +20,14 @@ pub fn expr_any(expr: &Expression, mut f: impl FnMut(&Expression) -> bool) -> bo
 }
 
 /// Traverses the expression tree and calls `f` in pre-order.
-pub fn previsit_expression<'a, F, B>(e: &'a Expression, f: &mut F) -> ControlFlow<B>
+/// The operands of `e` are in `expressions`.
+pub fn previsit_expression<'a, T, F, B>(
+    expressions: &'a ExpressionArena<T>,
+    e: &'a Expression<T>,
+    f: &mut F,
+) -> ControlFlow<B>
 where
-    F: FnMut(&'a Expression) -> ControlFlow<B>,
+    F: FnMut(&'a Expression<T>) -> ControlFlow<B>,
 {
     f(e)?;
 
@@ -29,49 +39,57 @@ where
         | Expression::Number(_)
         | Expression::String(_) => {}
         Expression::BinaryOperation(left, _, right) => {
-            previsit_expression(left, f)?;
-            previsit_expression(right, f)?;
+            previsit_expression(expressions, &expressions[*left], f)?;
+            previsit_expression(expressions, &expressions[*right], f)?;
         }
-        Expression::UnaryOperation(_, e) => previsit_expression(e, f)?,
+        Expression::UnaryOperation(_, e) => previsit_expression(expressions, &expressions[*e], f)?,
         Expression::Tuple(items) | Expression::FunctionCall(_, items) => items
             .iter()
-            .try_for_each(|item| previsit_expression(item, f))?,
+            .try_for_each(|item| previsit_expression(expressions, item, f))?,
         Expression::MatchExpression(scrutinee, arms) => {
-            once(scrutinee.as_ref())
+            once(&expressions[*scrutinee])
                 .chain(arms.iter().map(|(_n, e)| e))
-                .try_for_each(move |item| previsit_expression(item, f))?;
+                .try_for_each(move |item| previsit_expression(expressions, item, f))?;
         }
     };
     ControlFlow::Continue(())
 }
 
-/// Traverses the expression tree and calls `f` in pre-order.
-pub fn previsit_expression_mut<F, B>(e: &mut Expression, f: &mut F) -> ControlFlow<B>
-where
-    F: FnMut(&mut Expression) -> ControlFlow<B>,
-{
-    f(e)?;
+/// Returns the namespace of an absolute name, or the empty string for the
+/// global namespace.
+pub(crate) fn namespace(name: &str) -> &str {
+    name.rsplit_once('.')
+        .map(|(namespace, _)| namespace)
+        .unwrap_or("")
+}
 
-    match e {
-        Expression::PolynomialReference(_)
-        | Expression::Constant(_)
-        | Expression::LocalVariableReference(_)
-        | Expression::PublicReference(_)
-        | Expression::Number(_)
-        | Expression::String(_) => {}
-        Expression::BinaryOperation(left, _, right) => {
-            previsit_expression_mut(left, f)?;
-            previsit_expression_mut(right, f)?;
-        }
-        Expression::UnaryOperation(_, e) => previsit_expression_mut(e.as_mut(), f)?,
-        Expression::Tuple(items) | Expression::FunctionCall(_, items) => items
-            .iter_mut()
-            .try_for_each(|item| previsit_expression_mut(item, f))?,
-        Expression::MatchExpression(scrutinee, arms) => {
-            once(scrutinee.as_mut())
-                .chain(arms.iter_mut().map(|(_n, e)| e))
-                .try_for_each(move |item| previsit_expression_mut(item, f))?;
-        }
+/// Returns the namespaces of the (witness) columns referenced in the
+/// selector and the expressions.
+pub(crate) fn referenced_namespaces<'a, T>(
+    analyzed: &Analyzed<T>,
+    selected: &'a SelectedExpressions<T>,
+    witness_only: bool,
+) -> BTreeSet<&'a str> {
+    let is_witness = |name: &Symbol| {
+        analyzed
+            .definitions
+            .get(name)
+            .map(|(poly, _)| poly.poly_type == PolynomialType::Committed)
+            .unwrap_or_default()
     };
-    ControlFlow::Continue(())
+    let mut namespaces = BTreeSet::new();
+    for expr in selected.selector.iter().chain(&selected.expressions) {
+        let _: ControlFlow<()> = previsit_expression(&analyzed.expressions, expr, &mut |e| {
+            match e {
+                Expression::PolynomialReference(reference)
+                    if !witness_only || is_witness(&reference.name) =>
+                {
+                    namespaces.insert(namespace(reference.name.as_str()));
+                }
+                _ => {}
+            }
+            ControlFlow::Continue(())
+        });
+    }
+    namespaces
 }
@@ -0,0 +1,588 @@
+//! Static checks of powdr assembly that find code that is probably wrong
+//! but still compiles.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+
+use number::FieldElement;
+use parser::asm_ast::*;
+use parser::ast::*;
+use parser_util::lines::{compute_line_starts, offset_to_line};
+
+use crate::extract_update;
+
+/// A problem found in the assembly, at a line of the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Finds labels that are never jumped to, code after unconditional jumps that
+/// has no label, registers that are written but never read and functions
+/// that do not restore the registers declared with `@saved`. The statements
+/// need to be macro-expanded and `input` is the source they were parsed from.
+pub fn analyze<T: FieldElement>(statements: &[ASMStatement<T>], input: &str) -> Vec<Warning> {
+    let line_starts = compute_line_starts(input);
+    let mut warnings = vec![];
+    let mut warn = |start: usize, message: String| {
+        warnings.push(Warning {
+            line: offset_to_line(start, &line_starts),
+            message,
+        })
+    };
+
+    let pc = statements.iter().find_map(|s| match s {
+        ASMStatement::RegisterDeclaration(_, name, Some(RegisterFlag::IsPC)) => Some(name.clone()),
+        _ => None,
+    });
+    let instructions = statements
+        .iter()
+        .filter_map(|s| match s {
+            ASMStatement::InstructionDeclaration(_, name, params, body) => Some((
+                name.as_str(),
+                InstructionInfo::new(params, body, pc.as_deref()),
+            )),
+            ASMStatement::JumpTable(_, name, _, _) => Some((
+                name.as_str(),
+                InstructionInfo {
+                    label_inputs: vec![false],
+                    is_unconditional_jump: true,
+                    writes: BTreeSet::new(),
+                },
+            )),
+            _ => None,
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    // Registers that can be written and read by the code.
+    let registers = statements
+        .iter()
+        .filter_map(|s| match s {
+            ASMStatement::RegisterDeclaration(_, name, flag)
+                if !matches!(
+                    flag,
+                    Some(RegisterFlag::IsPC) | Some(RegisterFlag::IsAssignment)
+                ) =>
+            {
+                Some(name.as_str())
+            }
+            _ => None,
+        })
+        .collect::<BTreeSet<_>>();
+    let mut jump_targets = BTreeSet::new();
+    let mut first_writes = BTreeMap::new();
+    let mut reads = BTreeSet::new();
+    for statement in statements {
+        match statement {
+            ASMStatement::InstructionDeclaration(_, _, _, body) => {
+                for element in body {
+                    match element {
+                        InstructionBodyElement::Expression(e) => collect_reads(e, &mut reads),
+                        InstructionBodyElement::PlookupIdentity(left, _, right) => {
+                            for e in left.expressions.iter().chain(&right.expressions) {
+                                collect_reads(e, &mut reads);
+                            }
+                        }
+                    }
+                }
+            }
+            ASMStatement::InlinePil(_, pil) => {
+                for statement in pil {
+                    match statement {
+                        Statement::PolynomialDefinition(_, _, e)
+                        | Statement::PolynomialIdentity(_, e) => collect_reads(e, &mut reads),
+                        Statement::PlookupIdentity(_, left, right)
+                        | Statement::PermutationIdentity(_, left, right) => {
+                            for e in left.selector.iter().chain(&left.expressions) {
+                                collect_reads(e, &mut reads);
+                            }
+                            for e in right.selector.iter().chain(&right.expressions) {
+                                collect_reads(e, &mut reads);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            ASMStatement::Return(_) => {
+                // `return` reads the return address register.
+                reads.extend(statements.iter().filter_map(|s| match s {
+                    ASMStatement::RegisterDeclaration(
+                        _,
+                        name,
+                        Some(RegisterFlag::IsReturnAddress),
+                    ) => Some(name.clone()),
+                    _ => None,
+                }));
+            }
+            ASMStatement::Assignment(start, write_regs, _, value) => {
+                for reg in write_regs {
+                    first_writes.entry(reg.as_str()).or_insert(*start);
+                }
+                match value.as_ref() {
+                    Expression::FunctionCall(name, args) => {
+                        collect_arguments(&instructions, name, args, &mut reads, &mut jump_targets)
+                    }
+                    value => collect_reads(value, &mut reads),
+                }
+            }
+            ASMStatement::Instruction(_, name, args) => {
+                collect_arguments(&instructions, name, args, &mut reads, &mut jump_targets)
+            }
+            ASMStatement::Call(_, label) => {
+                jump_targets.insert(label.clone());
+            }
+            ASMStatement::JumpTable(_, _, _, labels) => {
+                jump_targets.extend(labels.iter().cloned());
+            }
+            ASMStatement::OnHalt(_, HaltBehavior::Jump(label)) | ASMStatement::Export(_, label) => {
+                jump_targets.insert(label.clone());
+            }
+            ASMStatement::Assert(_, left, right, _) => {
+                collect_reads(left, &mut reads);
+                collect_reads(right, &mut reads);
+            }
+            _ => {}
+        }
+    }
+
+    let mut after_jump = false;
+    for statement in statements {
+        match statement {
+            ASMStatement::Label(start, name) => {
+                if !jump_targets.contains(name) {
+                    warn(*start, format!("Label {name} is never jumped to."));
+                }
+                after_jump = false;
+            }
+            ASMStatement::Assignment(start, ..)
+            | ASMStatement::Instruction(start, ..)
+            | ASMStatement::Call(start, ..)
+            | ASMStatement::Return(start)
+            | ASMStatement::Halt(start)
+            | ASMStatement::Assert(start, ..) => {
+                if after_jump {
+                    warn(
+                        *start,
+                        "Unreachable code after unconditional jump.".to_string(),
+                    );
+                }
+                after_jump |= match statement {
+                    ASMStatement::Instruction(_, name, _) => instructions
+                        .get(name.as_str())
+                        .map(|i| i.is_unconditional_jump)
+                        .unwrap_or_default(),
+                    ASMStatement::Return(_) | ASMStatement::Halt(_) => true,
+                    _ => false,
+                };
+            }
+            _ => {}
+        }
+    }
+
+    for (reg, start) in first_writes {
+        if registers.contains(reg) && !reads.contains(reg) {
+            warn(start, format!("Register {reg} is written but never read."));
+        }
+    }
+
+    check_callee_saved(statements, &instructions, &mut warn);
+
+    warnings.sort_by_key(|w| w.line);
+    warnings
+}
+
+struct InstructionInfo {
+    /// For each input, whether it is a label.
+    label_inputs: Vec<bool>,
+    /// Whether the instruction always sets the pc to something else than the
+    /// next line.
+    is_unconditional_jump: bool,
+    /// The registers other than the pc the instruction assigns in its body.
+    writes: BTreeSet<String>,
+}
+
+impl InstructionInfo {
+    fn new<T: FieldElement>(
+        params: &InstructionParams,
+        body: &[InstructionBodyElement<T>],
+        pc: Option<&str>,
+    ) -> Self {
+        let label_inputs = params
+            .inputs
+            .params
+            .iter()
+            .map(|p| p.ty.as_deref() == Some("label"))
+            .collect();
+        let is_unconditional_jump = body.iter().any(|element| match element {
+            InstructionBodyElement::Expression(e) => match extract_update(e.clone()) {
+                (Some(var), update) => Some(var.as_str()) == pc && !increments(&update, &var),
+                (None, _) => false,
+            },
+            InstructionBodyElement::PlookupIdentity(..) => false,
+        });
+        let writes = body
+            .iter()
+            .filter_map(|element| match element {
+                InstructionBodyElement::Expression(e) => extract_update(e.clone()).0,
+                InstructionBodyElement::PlookupIdentity(..) => None,
+            })
+            .filter(|var| Some(var.as_str()) != pc)
+            .collect();
+        InstructionInfo {
+            label_inputs,
+            is_unconditional_jump,
+            writes,
+        }
+    }
+}
+
+/// For each register declared with `@saved`, the registers that hold the
+/// value it had at the start of the function.
+type SavedValues = BTreeMap<String, BTreeSet<String>>;
+
+/// Warns at each `return` of a function (a label that is called) that can
+/// be reached on a path that does not restore a register declared with
+/// `@saved` to its value at the start of the function. Values are only
+/// tracked through copies between registers, saving a register in memory
+/// is not understood. Called functions are assumed to restore the saved
+/// registers and to change all others.
+fn check_callee_saved<T: FieldElement>(
+    statements: &[ASMStatement<T>],
+    instructions: &BTreeMap<&str, InstructionInfo>,
+    warn: &mut impl FnMut(usize, String),
+) {
+    let saved = statements
+        .iter()
+        .filter_map(|s| match s {
+            ASMStatement::RegisterDeclaration(_, name, Some(RegisterFlag::CalleeSaved)) => {
+                Some(name.clone())
+            }
+            _ => None,
+        })
+        .collect::<BTreeSet<_>>();
+    if saved.is_empty() {
+        return;
+    }
+    let labels = statements
+        .iter()
+        .enumerate()
+        .filter_map(|(i, s)| match s {
+            ASMStatement::Label(_, name) => Some((name.as_str(), i)),
+            _ => None,
+        })
+        .collect::<BTreeMap<_, _>>();
+    let jump_tables = statements
+        .iter()
+        .filter_map(|s| match s {
+            ASMStatement::JumpTable(_, name, _, labels) => Some((name.as_str(), labels)),
+            _ => None,
+        })
+        .collect::<BTreeMap<_, _>>();
+    let functions = statements
+        .iter()
+        .filter_map(|s| match s {
+            ASMStatement::Call(_, label) => labels.get(label.as_str()).map(|i| (label, *i)),
+            _ => None,
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    // The successors of an instruction call and the registers it writes.
+    let instruction_effect = |i: usize, name: &str, args: &[Expression<T>]| {
+        let Some(info) = instructions.get(name) else {
+            return (vec![i + 1], BTreeSet::new());
+        };
+        let mut successors = args
+            .iter()
+            .zip(&info.label_inputs)
+            .filter_map(|(arg, is_label)| match arg {
+                Expression::PolynomialReference(r) if *is_label => labels.get(r.name.as_str()),
+                _ => None,
+            })
+            .chain(
+                jump_tables
+                    .get(name)
+                    .into_iter()
+                    .flat_map(|table| table.iter().filter_map(|l| labels.get(l.as_str()))),
+            )
+            .copied()
+            .collect::<Vec<_>>();
+        if !info.is_unconditional_jump {
+            successors.push(i + 1);
+        }
+        (successors, info.writes.clone())
+    };
+
+    for (function, entry) in functions {
+        let initial = saved
+            .iter()
+            .map(|reg| (reg.clone(), [reg.clone()].into()))
+            .collect::<SavedValues>();
+        let mut states = BTreeMap::from([(entry, initial)]);
+        let mut queue = vec![entry];
+        while let Some(i) = queue.pop() {
+            let mut state = states[&i].clone();
+            let successors = match &statements[i] {
+                ASMStatement::Assignment(_, write_regs, _, value) => match value.as_ref() {
+                    Expression::FunctionCall(name, args) => {
+                        let (successors, writes) = instruction_effect(i, name, args);
+                        for holders in state.values_mut() {
+                            holders.retain(|r| !writes.contains(r) && !write_regs.contains(r));
+                        }
+                        successors
+                    }
+                    value => {
+                        for holders in state.values_mut() {
+                            let copied = matches!(value, Expression::PolynomialReference(r)
+                                if !r.next && r.index.is_none() && holders.contains(&r.name));
+                            holders.retain(|r| !write_regs.contains(r));
+                            if copied {
+                                holders.extend(write_regs.iter().cloned());
+                            }
+                        }
+                        vec![i + 1]
+                    }
+                },
+                ASMStatement::Instruction(_, name, args) => {
+                    let (successors, writes) = instruction_effect(i, name, args);
+                    for holders in state.values_mut() {
+                        holders.retain(|r| !writes.contains(r));
+                    }
+                    successors
+                }
+                ASMStatement::Call(..) => {
+                    for holders in state.values_mut() {
+                        holders.retain(|r| saved.contains(r));
+                    }
+                    vec![i + 1]
+                }
+                ASMStatement::Return(_) | ASMStatement::Halt(_) => vec![],
+                _ => vec![i + 1],
+            };
+            for successor in successors.into_iter().filter(|s| *s < statements.len()) {
+                let merged = match states.get(&successor) {
+                    Some(previous) => previous
+                        .iter()
+                        .map(|(reg, holders)| (reg.clone(), &state[reg] & holders))
+                        .collect(),
+                    None => state.clone(),
+                };
+                if states.get(&successor) != Some(&merged) {
+                    states.insert(successor, merged);
+                    queue.push(successor);
+                }
+            }
+        }
+        for (i, state) in states {
+            if let ASMStatement::Return(start) = &statements[i] {
+                for (reg, holders) in state {
+                    if !holders.contains(&reg) {
+                        warn(
+                            *start,
+                            format!(
+                                "Function {function} can return without restoring register {reg}."
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn collect_arguments<T>(
+    instructions: &BTreeMap<&str, InstructionInfo>,
+    name: &str,
+    args: &[Expression<T>],
+    reads: &mut BTreeSet<String>,
+    jump_targets: &mut BTreeSet<String>,
+) {
+    let label_inputs = instructions
+        .get(name)
+        .map(|i| i.label_inputs.as_slice())
+        .unwrap_or_default();
+    for (i, arg) in args.iter().enumerate() {
+        match (label_inputs.get(i), arg) {
+            (Some(true), Expression::PolynomialReference(r)) => {
+                jump_targets.insert(r.name.clone());
+            }
+            _ => collect_reads(arg, reads),
+        }
+    }
+}
+
+/// Returns true if the expression contains `var + 1`.
+fn increments<T: FieldElement>(e: &Expression<T>, var: &str) -> bool {
+    let is_var = |e: &Expression<T>| matches!(e, Expression::PolynomialReference(r) if r.name == var && !r.next);
+    let is_one = |e: &Expression<T>| matches!(e, Expression::Number(n) if *n == 1.into());
+    match e {
+        Expression::BinaryOperation(left, BinaryOperator::Add, right)
+            if (is_var(left) && is_one(right)) || (is_one(left) && is_var(right)) =>
+        {
+            true
+        }
+        Expression::BinaryOperation(left, _, right) => {
+            increments(left, var) || increments(right, var)
+        }
+        Expression::UnaryOperation(_, e) => increments(e, var),
+        _ => false,
+    }
+}
+
+/// Adds the names of the columns referenced in the current row.
+fn collect_reads<T>(e: &Expression<T>, reads: &mut BTreeSet<String>) {
+    match e {
+        Expression::PolynomialReference(r) => {
+            if !r.next {
+                reads.insert(r.name.clone());
+            }
+            if let Some(index) = &r.index {
+                collect_reads(index, reads);
+            }
+        }
+        Expression::BinaryOperation(left, _, right) => {
+            collect_reads(left, reads);
+            collect_reads(right, reads);
+        }
+        Expression::UnaryOperation(_, e) | Expression::FreeInput(e) => collect_reads(e, reads),
+        Expression::FunctionCall(_, items) | Expression::Tuple(items) => {
+            items.iter().for_each(|e| collect_reads(e, reads))
+        }
+        Expression::MatchExpression(scrutinee, arms) => {
+            collect_reads(scrutinee, reads);
+            for (n, e) in arms {
+                if let Some(n) = n {
+                    collect_reads(n, reads);
+                }
+                collect_reads(e, reads);
+            }
+        }
+        Expression::Constant(_)
+        | Expression::PublicReference(_)
+        | Expression::Number(_)
+        | Expression::String(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+
+    use crate::check;
+
+    fn warnings(input: &str) -> Vec<String> {
+        check::<GoldilocksField>(None, input, &Default::default())
+            .unwrap()
+            .iter()
+            .map(|w| w.to_string())
+            .collect()
+    }
+
+    const HEADER: &str = r#"reg pc[@pc];
+reg X[<=];
+reg A;
+reg B;
+instr jmpz X, l: label { pc' = XIsZero * l + (1 - XIsZero) * (pc + 1) }
+instr jmp l: label { pc' = l }
+instr loop { pc' = pc }
+instr assert_zero X { XIsZero = 1 }
+"#;
+
+    #[test]
+    fn no_warnings() {
+        let code = "A <=X= 1;\nstart::\njmpz A, end;\nA <=X= A - 1;\njmp start;\nend::\nloop;\n";
+        assert!(warnings(&format!("{HEADER}{code}")).is_empty());
+    }
+
+    #[test]
+    fn unused_label_and_unreachable_code() {
+        let code =
+            "start::\nA <=X= 1;\njmp end;\nassert_zero A;\nA <=X= 2;\nend::\nloop;\nB <=X= A;\n";
+        assert_eq!(
+            warnings(&format!("{HEADER}{code}")),
+            [
+                "line 9: Label start is never jumped to.",
+                "line 12: Unreachable code after unconditional jump.",
+                "line 13: Unreachable code after unconditional jump.",
+                "line 16: Unreachable code after unconditional jump.",
+                "line 16: Register B is written but never read.",
+            ]
+        );
+    }
+
+    #[test]
+    fn labels_in_macros() {
+        let code = "%macro skip() {\njmp end;\nA <=X= 1;\nend::\n}\nskip;\nassert_zero A;\nloop;\n";
+        assert_eq!(
+            warnings(&format!("{HEADER}{code}")),
+            ["line 11: Unreachable code after unconditional jump."]
+        );
+    }
+
+    #[test]
+    fn exported_labels() {
+        let code = "export f;\nloop;\nf::\nloop;\ng::\nloop;\n";
+        assert_eq!(
+            warnings(&format!("{HEADER}{code}")),
+            ["line 13: Label g is never jumped to."]
+        );
+    }
+
+    #[test]
+    fn jump_tables() {
+        let code = "jump_table dispatch X: a, b;\ndispatch A;\nassert_zero A;\na::\nb::\nloop;\n";
+        assert_eq!(
+            warnings(&format!("{HEADER}{code}")),
+            ["line 11: Unreachable code after unconditional jump."]
+        );
+    }
+
+    #[test]
+    fn callee_saved_registers() {
+        let header = format!("{HEADER}reg S[@saved];\n");
+        let code = r#"
+call restores;
+call clobbers;
+call clobbers_on_one_path;
+call restores_after_call;
+loop;
+restores::
+B <=X= S;
+S <=X= 1;
+S <=X= B;
+return;
+clobbers::
+S <=X= S + 1;
+return;
+clobbers_on_one_path::
+jmpz A, skip;
+S <=X= 2;
+skip::
+return;
+restores_after_call::
+A <=X= S;
+call restores;
+S <=X= A;
+return;
+"#;
+        let warnings = warnings(&format!("{header}{code}"))
+            .into_iter()
+            .filter(|w| w.contains("restoring"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            warnings,
+            [
+                "line 23: Function clobbers can return without restoring register S.",
+                "line 28: Function clobbers_on_one_path can return without restoring register S.",
+                "line 33: Function restores_after_call can return without restoring register S.",
+            ]
+        );
+    }
+}
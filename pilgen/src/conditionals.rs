@@ -0,0 +1,118 @@
+//! Resolution of `#if NAME ... #else ... #endif` from the defined names.
+
+use std::collections::BTreeSet;
+
+use parser::asm_ast::*;
+use parser::ast::*;
+
+/// Replaces each conditional by the statements of the branch selected by
+/// `defines`, also in macros, repeat blocks, inline PIL and machines.
+pub fn resolve<T>(
+    statements: Vec<ASMStatement<T>>,
+    defines: &BTreeSet<String>,
+) -> Vec<ASMStatement<T>> {
+    let mut resolved = vec![];
+    for statement in statements {
+        match statement {
+            ASMStatement::Conditional(_, name, then, otherwise) => {
+                let branch = if defines.contains(&name) {
+                    then
+                } else {
+                    otherwise
+                };
+                resolved.extend(resolve(branch, defines));
+            }
+            ASMStatement::InlinePil(start, statements) => {
+                resolved.push(ASMStatement::InlinePil(
+                    start,
+                    resolve_pil(statements, defines),
+                ));
+            }
+            ASMStatement::MachineDefinition(start, name, params, body) => {
+                resolved.push(ASMStatement::MachineDefinition(
+                    start,
+                    name,
+                    params,
+                    resolve_pil(body, defines),
+                ));
+            }
+            ASMStatement::MacroDefinition(start, name, params, body) => {
+                resolved.push(ASMStatement::MacroDefinition(
+                    start,
+                    name,
+                    params,
+                    resolve(body, defines),
+                ));
+            }
+            ASMStatement::Repeat(start, var, from, to, body) => {
+                resolved.push(ASMStatement::Repeat(
+                    start,
+                    var,
+                    from,
+                    to,
+                    resolve(body, defines),
+                ));
+            }
+            statement => resolved.push(statement),
+        }
+    }
+    resolved
+}
+
+fn resolve_pil<T>(statements: Vec<Statement<T>>, defines: &BTreeSet<String>) -> Vec<Statement<T>> {
+    let mut resolved = vec![];
+    for statement in statements {
+        match statement {
+            Statement::Conditional(_, name, then, otherwise) => {
+                let branch = if defines.contains(&name) {
+                    then
+                } else {
+                    otherwise
+                };
+                resolved.extend(resolve_pil(branch, defines));
+            }
+            statement => resolved.push(statement),
+        }
+    }
+    resolved
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+
+    use super::*;
+
+    fn resolve_source(input: &str, defines: &[&str]) -> Vec<String> {
+        let ast = parser::parse_asm::<GoldilocksField>(None, input).unwrap();
+        let defines = defines.iter().map(|d| d.to_string()).collect();
+        resolve(ast.0, &defines)
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn branches() {
+        let input = r#"
+#if DEBUG
+    assert_zero A;
+    #if VERBOSE
+        print A;
+    #endif
+#else
+    A <=X= 1;
+#endif
+pil {
+#if DEBUG
+    col witness D;
+#endif
+}
+"#;
+        assert_eq!(resolve_source(input, &[]), ["A <=X= 1;", "pil {\n}"]);
+        assert_eq!(
+            resolve_source(input, &["DEBUG", "VERBOSE"]),
+            ["assert_zero A;", "print A;", "pil {\n    pol commit D;\n}"]
+        );
+    }
+}
@@ -0,0 +1,839 @@
+//! Direct execution of powdr assembly, without generating PIL or a witness.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use number::FieldElement;
+use parser::asm_ast::*;
+use parser::ast::*;
+use parser_util::lines::{compute_line_starts, offset_to_line};
+use parser_util::ParseError;
+
+use crate::{
+    conditionals, declare_return_address_register, direct_reference, extract_update,
+    macro_expander, resolve_local_labels,
+};
+
+/// Computes the outputs of an instruction from the values of its inputs,
+/// for instructions that access other machines through lookups or
+/// permutations, which the interpreter cannot execute.
+pub type InstructionStub<T> = Box<dyn FnMut(&[T]) -> Vec<T>>;
+
+/// The result of `interpret`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Execution<T> {
+    /// The number of executed lines, including the last one.
+    pub steps: usize,
+    /// The values of the registers after the last step, without the
+    /// assignment registers.
+    pub registers: BTreeMap<String, T>,
+    /// Why the execution failed, starting with the location of the line.
+    pub error: Option<String>,
+}
+
+/// Executes the assembly line by line on the inputs, which is much faster
+/// than witness generation, to test programs and the compiler.
+///
+/// The execution ends at `halt`, if the registers do not change from one
+/// line to the next apart from labels in between (like at `loop` or at
+/// `end:: jmp end;`), or with an error after `max_steps` lines.
+/// Like in the PIL, assignment registers are zero on lines that do not
+/// assign them. Registers declared with `@unconstrained` keep their value if
+/// they are not written. The witness columns of inline PIL and the outputs of
+/// instructions are solved from the constraints of the instruction and the
+/// identities of inline PIL that are affine in a single unknown, identities
+/// with next references are ignored. Instructions with lookups or
+/// permutations need a stub in `stubs`, by their name. Programs with
+/// `export` start at the exported label with the number `entry_point`.
+pub fn interpret<'a, T: FieldElement>(
+    file_name: Option<&str>,
+    input: &'a str,
+    defines: &BTreeSet<String>,
+    entry_point: u64,
+    inputs: &[T],
+    stubs: &mut BTreeMap<String, InstructionStub<T>>,
+    max_steps: usize,
+) -> Result<Execution<T>, ParseError<'a>> {
+    parser::parse_asm(file_name, input).map(|ast| {
+        let statements = conditionals::resolve(ast.0, defines);
+        let mut statements = resolve_local_labels(macro_expander::expand(statements));
+        declare_return_address_register(&mut statements);
+        let mut interpreter = Interpreter {
+            file_name: file_name.map(|f| f.to_string()),
+            line_starts: compute_line_starts(input),
+            entry_point,
+            inputs,
+            stubs: Some(stubs),
+            ..Default::default()
+        };
+        interpreter.declare(statements);
+        interpreter.run(max_steps)
+    })
+}
+
+struct InstructionDefinition<T> {
+    inputs: Vec<InstructionParam>,
+    outputs: Vec<String>,
+    body: Vec<InstructionBodyElement<T>>,
+}
+
+#[derive(Default)]
+struct Interpreter<'a, T> {
+    file_name: Option<String>,
+    line_starts: Vec<usize>,
+    entry_point: u64,
+    inputs: &'a [T],
+    stubs: Option<&'a mut BTreeMap<String, InstructionStub<T>>>,
+    pc: Option<String>,
+    return_address: Option<String>,
+    /// The registers except for the assignment registers, with their flags.
+    registers: BTreeMap<String, Option<RegisterFlag>>,
+    assignment_registers: Vec<String>,
+    instructions: BTreeMap<String, InstructionDefinition<T>>,
+    /// The assignment register and the labels of the jump tables.
+    jump_tables: BTreeMap<String, (String, Vec<String>)>,
+    /// The witness columns of inline PIL.
+    witnesses: BTreeSet<String>,
+    /// The intermediate columns of inline PIL.
+    definitions: BTreeMap<String, Expression<T>>,
+    /// The polynomial identities of inline PIL.
+    identities: Vec<(usize, Expression<T>)>,
+    entry_points: Vec<String>,
+    /// The statements that occupy a line of the program, indexed by the pc.
+    code: Vec<ASMStatement<T>>,
+    labels: BTreeMap<String, usize>,
+}
+
+/// The values of a row of the trace that are known, the columns that are
+/// still unknown and the executed instruction.
+struct Row<T> {
+    values: BTreeMap<String, T>,
+    unknowns: BTreeSet<String>,
+    instruction: Option<String>,
+}
+
+impl<'a, T: FieldElement> Interpreter<'a, T> {
+    fn declare(&mut self, statements: Vec<ASMStatement<T>>) {
+        for statement in statements {
+            match statement {
+                ASMStatement::RegisterDeclaration(_, name, Some(RegisterFlag::IsAssignment)) => {
+                    self.assignment_registers.push(name);
+                }
+                ASMStatement::RegisterDeclaration(_, name, flag) => {
+                    match flag {
+                        Some(RegisterFlag::IsPC) => self.pc = Some(name.clone()),
+                        Some(RegisterFlag::IsReturnAddress) => {
+                            self.return_address = Some(name.clone())
+                        }
+                        _ => {}
+                    }
+                    self.registers.insert(name, flag);
+                }
+                ASMStatement::InstructionDeclaration(_, name, params, body) => {
+                    let outputs = params
+                        .outputs
+                        .map(|outputs| outputs.params.into_iter().map(|p| p.name).collect())
+                        .unwrap_or_default();
+                    self.instructions.insert(
+                        name,
+                        InstructionDefinition {
+                            inputs: params.inputs.params,
+                            outputs,
+                            body,
+                        },
+                    );
+                }
+                ASMStatement::JumpTable(_, name, register, labels) => {
+                    self.jump_tables.insert(name, (register, labels));
+                }
+                ASMStatement::InlinePil(_, statements) => {
+                    for statement in statements {
+                        match statement {
+                            Statement::PolynomialCommitDeclaration(_, names, _) => {
+                                self.witnesses.extend(names.into_iter().map(|n| n.name))
+                            }
+                            Statement::PolynomialDefinition(_, name, value) => {
+                                self.definitions.insert(name, value);
+                            }
+                            Statement::PolynomialIdentity(start, identity) => {
+                                self.identities.push((start, identity))
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                ASMStatement::Export(_, label) => self.entry_points.push(label),
+                ASMStatement::Label(_, ref name) => {
+                    self.labels.insert(name.clone(), self.code.len());
+                    self.code.push(statement);
+                }
+                ASMStatement::Assignment(..)
+                | ASMStatement::Instruction(..)
+                | ASMStatement::Call(..)
+                | ASMStatement::Return(..)
+                | ASMStatement::Halt(..)
+                | ASMStatement::Assert(..) => self.code.push(statement),
+                ASMStatement::Degree(..)
+                | ASMStatement::OnHalt(..)
+                | ASMStatement::MachineDefinition(..)
+                | ASMStatement::MachineInstance(..)
+                | ASMStatement::DebugDirective(..) => {}
+                ASMStatement::MacroDefinition(..)
+                | ASMStatement::Repeat(..)
+                | ASMStatement::Conditional(..) => {
+                    unreachable!("Macros and conditionals are resolved before the execution.")
+                }
+            }
+        }
+    }
+
+    fn run(&mut self, max_steps: usize) -> Execution<T> {
+        let mut registers = self
+            .registers
+            .iter()
+            .map(|(name, flag)| {
+                let value = match flag {
+                    Some(RegisterFlag::ReadOnly(Some(value))) => T::from(value.clone()),
+                    _ => 0.into(),
+                };
+                (name.clone(), value)
+            })
+            .collect();
+        let mut steps = 0;
+        let error = self.execute(&mut registers, &mut steps, max_steps).err();
+        Execution {
+            steps,
+            registers,
+            error,
+        }
+    }
+
+    fn execute(
+        &mut self,
+        registers: &mut BTreeMap<String, T>,
+        steps: &mut usize,
+        max_steps: usize,
+    ) -> Result<(), String> {
+        let pc = self.pc.clone().ok_or("The program has no pc register.")?;
+        if !self.entry_points.is_empty() {
+            let number = self.entry_point;
+            let label = self
+                .entry_points
+                .get(number as usize)
+                .ok_or_else(|| format!("There is no entry point {number}."))?;
+            registers.insert(pc.clone(), self.label_position(label)?);
+        }
+        // The registers before the previous line that is not a label.
+        let mut previous = None;
+        loop {
+            if *steps == max_steps {
+                return Err(format!(
+                    "The program did not halt within {max_steps} steps."
+                ));
+            }
+            let line = registers[&pc]
+                .try_to_u64()
+                .ok()
+                .and_then(|line| self.code.get(line as usize))
+                .cloned()
+                .ok_or_else(|| format!("The pc {} is outside of the program.", registers[&pc]))?;
+            if !matches!(line, ASMStatement::Label(..)) {
+                if previous.as_ref() == Some(registers) {
+                    return Ok(());
+                }
+                previous = Some(registers.clone());
+            }
+            *steps += 1;
+            let location = self.location(line_start(&line));
+            match self
+                .step(line, registers)
+                .map_err(|e| format!("{location}: {e}"))?
+            {
+                Some(next) => *registers = next,
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// Executes a line and returns the values of the registers in the next
+    /// row, or `None` if the line halts.
+    fn step(
+        &mut self,
+        line: ASMStatement<T>,
+        registers: &BTreeMap<String, T>,
+    ) -> Result<Option<BTreeMap<String, T>>, String> {
+        let pc = self.pc.clone().unwrap();
+        let mut row = Row {
+            values: registers.clone(),
+            unknowns: self.witnesses.clone(),
+            instruction: None,
+        };
+        for reg in &self.assignment_registers {
+            row.values.insert(reg.clone(), 0.into());
+        }
+        let mut updates = BTreeMap::new();
+        match line {
+            ASMStatement::Label(..) => self.solve(&mut row, &[])?,
+            ASMStatement::Assignment(_, write_regs, assign_regs, value) => match *value {
+                Expression::FunctionCall(name, args) => {
+                    let args = args
+                        .into_iter()
+                        .chain(write_regs.into_iter().map(direct_reference))
+                        .collect::<Vec<_>>();
+                    self.execute_instruction(&name, &args, &mut row, &mut updates)?;
+                }
+                value => {
+                    let value = self.evaluate(&value, &row.values)?;
+                    let assign_reg = assign_regs
+                        .and_then(|regs| regs.first().cloned())
+                        .or_else(|| self.assignment_registers.first().cloned())
+                        .ok_or("There is no assignment register.")?;
+                    row.values.insert(assign_reg, value);
+                    self.solve(&mut row, &[])?;
+                    for reg in write_regs {
+                        updates.insert(reg, value);
+                    }
+                }
+            },
+            ASMStatement::Instruction(_, name, args) => {
+                self.execute_instruction(&name, &args, &mut row, &mut updates)?
+            }
+            ASMStatement::Call(_, label) => {
+                row.instruction = Some("call".to_string());
+                self.solve(&mut row, &[])?;
+                let return_address = self.return_address.clone().unwrap();
+                updates.insert(return_address, registers[&pc] + 1.into());
+                updates.insert(pc, self.label_position(&label)?);
+            }
+            ASMStatement::Return(_) => {
+                row.instruction = Some("return".to_string());
+                self.solve(&mut row, &[])?;
+                let return_address = self.return_address.as_ref().unwrap();
+                updates.insert(pc, registers[return_address]);
+            }
+            ASMStatement::Halt(_) => return Ok(None),
+            ASMStatement::Assert(_, left, right, message) => {
+                let difference =
+                    self.evaluate(&left, &row.values)? - self.evaluate(&right, &row.values)?;
+                if !difference.is_zero() {
+                    return Err(format!(
+                        "Assertion failed: {message} (the difference is {difference})"
+                    ));
+                }
+                row.instruction = Some("assert_eq".to_string());
+                self.solve(&mut row, &[])?;
+            }
+            _ => unreachable!("Only code lines are executed."),
+        }
+        self.next_registers(registers, updates).map(Some)
+    }
+
+    /// Executes an instruction, with the registers written by its outputs
+    /// at the end of the arguments.
+    fn execute_instruction(
+        &mut self,
+        name: &str,
+        args: &[Expression<T>],
+        row: &mut Row<T>,
+        updates: &mut BTreeMap<String, T>,
+    ) -> Result<(), String> {
+        row.instruction = Some(name.to_string());
+        if let Some((register, labels)) = self.jump_tables.get(name) {
+            let [arg] = args else {
+                return Err(format!("Jump table {name} expects one argument."));
+            };
+            let index = self.evaluate(arg, &row.values)?;
+            row.values.insert(register.clone(), index);
+            let label = index
+                .try_to_u64()
+                .ok()
+                .and_then(|i| labels.get(i as usize))
+                .ok_or_else(|| format!("Jump table {name} has no label at index {index}."))?;
+            updates.insert(self.pc.clone().unwrap(), self.label_position(label)?);
+            return self.solve(row, &[]);
+        }
+
+        let instruction = self
+            .instructions
+            .get(name)
+            .ok_or_else(|| format!("Instruction {name} is not declared."))?;
+        let InstructionDefinition {
+            inputs,
+            outputs,
+            body,
+        } = instruction;
+        if args.len() != inputs.len() + outputs.len() {
+            return Err(format!(
+                "Instruction {name} expects {} arguments, but {} are given.",
+                inputs.len() + outputs.len(),
+                args.len()
+            ));
+        }
+        let mut input_values = vec![];
+        for (param, arg) in inputs.iter().zip(args) {
+            let value = match (param.ty.as_deref(), arg) {
+                (Some("label"), Expression::PolynomialReference(r)) => {
+                    self.label_position(&r.name)?
+                }
+                (Some("label"), _) => return Err(format!("Expected a label, found {arg}.")),
+                _ => {
+                    let value = self.evaluate(arg, &row.values)?;
+                    input_values.push(value);
+                    value
+                }
+            };
+            row.values.insert(param.name.clone(), value);
+        }
+
+        let mut constraints = vec![];
+        let mut register_updates = vec![];
+        let mut accesses_machines = false;
+        for element in body {
+            match element {
+                InstructionBodyElement::Expression(e) => match extract_update(e.clone()) {
+                    (Some(register), value) => register_updates.push((register, value)),
+                    (None, constraint) => constraints.push(constraint),
+                },
+                InstructionBodyElement::PlookupIdentity(..) => accesses_machines = true,
+            }
+        }
+        match self.stubs.as_mut().and_then(|stubs| stubs.get_mut(name)) {
+            Some(stub) => {
+                let values = stub(&input_values);
+                if values.len() != outputs.len() {
+                    return Err(format!(
+                        "The stub of instruction {name} returns {} values, but the instruction has {} outputs.",
+                        values.len(),
+                        outputs.len()
+                    ));
+                }
+                row.values.extend(outputs.iter().cloned().zip(values));
+            }
+            None if accesses_machines => {
+                return Err(format!(
+                    "Instruction {name} accesses other machines and needs a stub."
+                ));
+            }
+            None => {
+                for output in outputs {
+                    row.values.remove(output);
+                    row.unknowns.insert(output.clone());
+                }
+            }
+        }
+        self.solve(row, &constraints)?;
+
+        for (register, value) in register_updates {
+            let value = self
+                .affine(&value, row)
+                .and_then(|value| value.value())
+                .ok_or_else(|| {
+                    format!("The update of register {register} by instruction {name} cannot be computed.")
+                })?;
+            updates.insert(register, value);
+        }
+        for (output, arg) in outputs.iter().zip(&args[inputs.len()..]) {
+            let value = row.values.get(output).ok_or_else(|| {
+                format!("The output {output} of instruction {name} cannot be computed, it needs a stub.")
+            })?;
+            let Expression::PolynomialReference(register) = arg else {
+                return Err(format!("Expected a register to write to, found {arg}."));
+            };
+            updates.insert(register.name.clone(), *value);
+        }
+        Ok(())
+    }
+
+    /// Solves the identities of inline PIL and the constraints of the
+    /// instruction for the unknown columns of the row as long as one of them is
+    /// affine in a single unknown, and checks the ones without unknowns.
+    fn solve(&self, row: &mut Row<T>, constraints: &[Expression<T>]) -> Result<(), String> {
+        let identities = self
+            .identities
+            .iter()
+            .map(|(start, e)| (e, Some(*start)))
+            .chain(constraints.iter().map(|c| (c, None)))
+            .collect::<Vec<_>>();
+        let mut progress = true;
+        while progress {
+            progress = false;
+            for (identity, _) in &identities {
+                if let Some((unknown, value)) = self
+                    .affine(identity, row)
+                    .and_then(|a| a.solve_single_unknown())
+                {
+                    row.unknowns.remove(&unknown);
+                    row.values.insert(unknown, value);
+                    progress = true;
+                }
+            }
+        }
+        for (identity, start) in identities {
+            let value = self.affine(identity, row).and_then(|a| a.value());
+            if value.is_some_and(|v| !v.is_zero()) {
+                let origin = match start {
+                    Some(start) => format!("of inline PIL at {}", self.location(start)),
+                    None => format!("of instruction {}", row.instruction.as_ref().unwrap()),
+                };
+                return Err(format!(
+                    "The constraint {identity} = 0 {origin} is not satisfied."
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The value of the expression as an affine expression in the unknown
+    /// columns of the row, if it is one.
+    fn affine(&self, e: &Expression<T>, row: &Row<T>) -> Option<Affine<T>> {
+        match e {
+            Expression::Number(n) => Some(Affine::constant(*n)),
+            Expression::PolynomialReference(r) if r.next || r.index.is_some() => None,
+            Expression::PolynomialReference(r) if r.namespace.as_deref() == Some("instr") => {
+                let flag = row.instruction.as_ref() == Some(&r.name);
+                Some(Affine::constant(flag.into()))
+            }
+            Expression::PolynomialReference(r) if r.namespace.is_none() => {
+                if let Some(value) = row.values.get(&r.name) {
+                    Some(Affine::constant(*value))
+                } else if row.unknowns.contains(&r.name) {
+                    Some(Affine::unknown(&r.name))
+                } else {
+                    self.affine(self.definitions.get(&r.name)?, row)
+                }
+            }
+            Expression::BinaryOperation(left, op, right) => {
+                let left = self.affine(left, row)?;
+                let right = self.affine(right, row)?;
+                match op {
+                    BinaryOperator::Add => Some(left.add(right)),
+                    BinaryOperator::Sub => Some(left.add(right.scale(-T::one()))),
+                    BinaryOperator::Mul => match (left.value(), right.value()) {
+                        (Some(factor), _) => Some(right.scale(factor)),
+                        (_, Some(factor)) => Some(left.scale(factor)),
+                        _ => None,
+                    },
+                    BinaryOperator::Pow => Some(Affine::constant(
+                        left.value()?.pow(right.value()?.to_integer()),
+                    )),
+                    _ => None,
+                }
+            }
+            Expression::UnaryOperation(UnaryOperator::Minus, e) => {
+                Some(self.affine(e, row)?.scale(-T::one()))
+            }
+            Expression::UnaryOperation(UnaryOperator::Plus, e) => self.affine(e, row),
+            _ => None,
+        }
+    }
+
+    /// Evaluates the value of an assignment or an argument of an instruction.
+    fn evaluate(&self, e: &Expression<T>, values: &BTreeMap<String, T>) -> Result<T, String> {
+        match e {
+            Expression::Number(n) => Ok(*n),
+            Expression::PolynomialReference(r)
+                if r.namespace.is_none() && r.index.is_none() && !r.next =>
+            {
+                values
+                    .get(&r.name)
+                    .copied()
+                    .ok_or_else(|| format!("Unknown register {}.", r.name))
+            }
+            Expression::FreeInput(query) => match query.as_ref() {
+                Expression::Tuple(items) => match &items[..] {
+                    [Expression::String(kind), index] if kind == "input" => {
+                        let index = self.evaluate(index, values)?;
+                        index
+                            .try_to_u64()
+                            .ok()
+                            .and_then(|i| self.inputs.get(i as usize))
+                            .copied()
+                            .ok_or_else(|| format!("Input {index} is not given."))
+                    }
+                    _ => Err(format!("Unsupported query {query}.")),
+                },
+                _ => Err(format!("Unsupported query {query}.")),
+            },
+            Expression::BinaryOperation(left, op, right) => {
+                let left = self.evaluate(left, values)?;
+                let right = self.evaluate(right, values)?;
+                match op {
+                    BinaryOperator::Add => Ok(left + right),
+                    BinaryOperator::Sub => Ok(left - right),
+                    BinaryOperator::Mul => Ok(left * right),
+                    BinaryOperator::Pow => Ok(left.pow(right.to_integer())),
+                    _ => Err(format!("Invalid operation in expression {e}.")),
+                }
+            }
+            Expression::UnaryOperation(UnaryOperator::Minus, e) => Ok(-self.evaluate(e, values)?),
+            Expression::UnaryOperation(UnaryOperator::Plus, e) => self.evaluate(e, values),
+            _ => Err(format!("Cannot evaluate {e}.")),
+        }
+    }
+
+    /// The values of the registers in the next row: the updated value, the
+    /// next line for the pc, zero for registers with `@reset` and the
+    /// current value otherwise.
+    fn next_registers(
+        &self,
+        registers: &BTreeMap<String, T>,
+        updates: BTreeMap<String, T>,
+    ) -> Result<BTreeMap<String, T>, String> {
+        let mut next = registers.clone();
+        for (name, value) in next.iter_mut() {
+            if Some(name) == self.pc.as_ref() {
+                *value += 1.into();
+            } else if self.registers[name] == Some(RegisterFlag::ResetToZero) {
+                *value = 0.into();
+            }
+        }
+        for (name, value) in updates {
+            match self.registers.get(&name) {
+                None => return Err(format!("Unknown register {name}.")),
+                Some(Some(RegisterFlag::ReadOnly(_))) => {
+                    return Err(format!("The read-only register {name} is written."))
+                }
+                Some(_) => next.insert(name, value),
+            };
+        }
+        Ok(next)
+    }
+
+    fn label_position(&self, label: &str) -> Result<T, String> {
+        self.labels
+            .get(label)
+            .map(|position| (*position as u64).into())
+            .ok_or_else(|| format!("Label {label} not found."))
+    }
+
+    /// The file and line of an offset in the assembly, for messages.
+    fn location(&self, start: usize) -> String {
+        let line = offset_to_line(start, &self.line_starts);
+        match &self.file_name {
+            Some(file_name) => format!("{file_name}:{line}"),
+            None => format!("line {line}"),
+        }
+    }
+}
+
+fn line_start<T>(line: &ASMStatement<T>) -> usize {
+    match line {
+        ASMStatement::Label(start, _)
+        | ASMStatement::Assignment(start, ..)
+        | ASMStatement::Instruction(start, ..)
+        | ASMStatement::Call(start, _)
+        | ASMStatement::Return(start)
+        | ASMStatement::Halt(start)
+        | ASMStatement::Assert(start, ..) => *start,
+        _ => unreachable!("Only code lines are executed."),
+    }
+}
+
+/// A linear combination of unknown columns plus a constant.
+struct Affine<T> {
+    coefficients: BTreeMap<String, T>,
+    constant: T,
+}
+
+impl<T: FieldElement> Affine<T> {
+    fn constant(constant: T) -> Self {
+        Affine {
+            coefficients: BTreeMap::new(),
+            constant,
+        }
+    }
+
+    fn unknown(name: &str) -> Self {
+        Affine {
+            coefficients: [(name.to_string(), T::one())].into(),
+            constant: T::zero(),
+        }
+    }
+
+    fn add(mut self, other: Self) -> Self {
+        for (name, coefficient) in other.coefficients {
+            *self.coefficients.entry(name).or_default() += coefficient;
+        }
+        self.constant += other.constant;
+        self
+    }
+
+    fn scale(self, factor: T) -> Self {
+        Affine {
+            coefficients: self
+                .coefficients
+                .into_iter()
+                .map(|(name, coefficient)| (name, coefficient * factor))
+                .collect(),
+            constant: self.constant * factor,
+        }
+    }
+
+    /// The value if no unknown has a non-zero coefficient.
+    fn value(&self) -> Option<T> {
+        self.coefficients
+            .values()
+            .all(|c| c.is_zero())
+            .then_some(self.constant)
+    }
+
+    /// The unknown and the value that makes the expression zero if exactly
+    /// one unknown has a non-zero coefficient.
+    fn solve_single_unknown(&self) -> Option<(String, T)> {
+        let mut unknowns = self.coefficients.iter().filter(|(_, c)| !c.is_zero());
+        match (unknowns.next(), unknowns.next()) {
+            (Some((name, coefficient)), None) => {
+                Some((name.clone(), -self.constant / *coefficient))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use number::GoldilocksField;
+
+    use super::*;
+
+    fn run_file(
+        file_name: &str,
+        entry_point: u64,
+        inputs: &[u64],
+        stubs: &mut BTreeMap<String, InstructionStub<GoldilocksField>>,
+    ) -> Execution<GoldilocksField> {
+        let file_name = format!("../test_data/asm/{file_name}");
+        let contents = fs::read_to_string(&file_name).unwrap();
+        let inputs = inputs.iter().map(|&i| i.into()).collect::<Vec<_>>();
+        interpret(
+            Some(&file_name),
+            &contents,
+            &Default::default(),
+            entry_point,
+            &inputs,
+            stubs,
+            1000,
+        )
+        .unwrap()
+    }
+
+    fn run_source(code: &str, inputs: &[u64]) -> Execution<GoldilocksField> {
+        let input = format!(
+            r#"reg pc[@pc];
+reg X[<=];
+reg A;
+pil{{
+    col witness XInv;
+    col witness XIsZero;
+    XIsZero  = 1 - X * XInv;
+    XIsZero * X = 0;
+    XIsZero * (1 - XIsZero) = 0;
+}}
+instr jmpz X, l: label {{ pc' = XIsZero * l + (1 - XIsZero) * (pc + 1) }}
+instr assert_zero X {{ XIsZero = 1 }}
+instr loop {{ pc' = pc }}
+{code}"#
+        );
+        let inputs = inputs.iter().map(|&i| i.into()).collect::<Vec<_>>();
+        interpret(
+            None,
+            &input,
+            &Default::default(),
+            0,
+            &inputs,
+            &mut BTreeMap::new(),
+            100,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn simple_sum() {
+        let execution = run_file(
+            "simple_sum.asm",
+            0,
+            &[10, 4, 1, 2, 3, 4],
+            &mut BTreeMap::new(),
+        );
+        assert_eq!(execution.error, None);
+        assert_eq!(execution.registers["A"], 10.into());
+        assert_eq!(execution.registers["CNT"], 0.into());
+        assert_eq!(execution.steps, 26);
+    }
+
+    #[test]
+    fn calls_jump_tables_and_entry_points() {
+        for (file_name, inputs) in [
+            ("call_return.asm", &[5, 20][..]),
+            ("jump_table.asm", &[2, 30]),
+            ("entry_points.asm", &[5, 6]),
+            ("halt.asm", &[3, 4, 7]),
+            ("read_only_registers.asm", &[5]),
+        ] {
+            let execution = run_file(file_name, 0, inputs, &mut BTreeMap::new());
+            assert_eq!(execution.error, None, "{file_name}");
+        }
+        let execution = run_file("entry_points.asm", 1, &[5, 10], &mut BTreeMap::new());
+        assert_eq!(execution.error, None);
+        let execution = run_file("entry_points.asm", 2, &[5, 10], &mut BTreeMap::new());
+        assert_eq!(execution.error.unwrap(), "There is no entry point 2.");
+        let execution = run_file("jump_table.asm", 0, &[1, 30], &mut BTreeMap::new());
+        assert_eq!(
+            execution.error.unwrap(),
+            "../test_data/asm/jump_table.asm:35: The constraint (XIsZero - 1) = 0 of instruction assert_zero is not satisfied."
+        );
+    }
+
+    #[test]
+    fn failed_assertion() {
+        let execution = run_file("assert_eq.asm", 0, &[3, 4, 8], &mut BTreeMap::new());
+        assert_eq!(
+            execution.error.unwrap(),
+            "../test_data/asm/assert_eq.asm:13: Assertion failed: sum mismatch (the difference is -1)"
+        );
+        assert_eq!(execution.steps, 4);
+        assert_eq!(execution.registers["A"], 7.into());
+    }
+
+    #[test]
+    fn stubs() {
+        let execution = run_file("machines.asm", 0, &[200, 10], &mut BTreeMap::new());
+        assert_eq!(
+            execution.error.unwrap(),
+            "../test_data/asm/machines.asm:21: Instruction assert_byte accesses other machines and needs a stub."
+        );
+        let mut stubs = BTreeMap::<String, InstructionStub<GoldilocksField>>::new();
+        stubs.insert("assert_byte".to_string(), Box::new(|_| vec![]));
+        stubs.insert("assert_nibble".to_string(), Box::new(|_| vec![]));
+        assert_eq!(
+            run_file("machines.asm", 0, &[200, 10], &mut stubs).error,
+            None
+        );
+
+        let mut stubs = BTreeMap::<String, InstructionStub<GoldilocksField>>::new();
+        stubs.insert(
+            "wrap".to_string(),
+            Box::new(|inputs| vec![(inputs[0].to_degree() & 0xffffffff).into()]),
+        );
+        let execution = run_file("functional_instructions.asm", 0, &[20], &mut stubs);
+        assert_eq!(execution.error, None);
+        assert_eq!(execution.registers["A"], 0.into());
+    }
+
+    #[test]
+    fn missing_input_and_endless_loop() {
+        assert_eq!(
+            run_source("A <=X= ${ (\"input\", 1) };\nloop;\n", &[1])
+                .error
+                .unwrap(),
+            "line 14: Input 1 is not given."
+        );
+        let execution = run_source("start::\nA <=X= A + 1;\njmpz 0, start;\n", &[]);
+        assert_eq!(
+            execution.error.unwrap(),
+            "The program did not halt within 100 steps."
+        );
+        assert_eq!(execution.registers["A"], 33.into());
+    }
+}
@@ -1,36 +1,168 @@
 //! Compilation from powdr assembly to PIL
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter};
 
-use number::AbstractNumberType;
 use number::DegreeType;
 use number::FieldElement;
 
 use parser::asm_ast::*;
 use parser::ast::*;
+use parser_util::lines::{compute_line_starts, offset_to_line};
 use parser_util::ParseError;
 
-pub fn compile<'a>(file_name: Option<&str>, input: &'a str) -> Result<PILFile, ParseError<'a>> {
-    parser::parse_asm(file_name, input).map(|ast| ASMPILConverter::new().convert(ast))
+mod analysis;
+mod conditionals;
+mod interpreter;
+mod machines;
+mod macro_expander;
+mod optimizer;
+mod rom;
+
+pub use analysis::Warning;
+pub use interpreter::{interpret, Execution, InstructionStub};
+use machines::MachineInstance;
+
+/// Options of the compilation to PIL.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CompileOptions {
+    /// The names for which the code after `#if NAME` is used, the code
+    /// after `#else` is used for all others.
+    pub defines: BTreeSet<String>,
+    /// If set, the assembly is optimized before it is converted,
+    /// see `optimizer::optimize`.
+    pub optimize: bool,
+    /// If set, the instruction flags are encoded in few fixed columns. By
+    /// default, each instruction flag `instr_name` is matched against its own
+    /// fixed column `p_instr_name`. In the compressed encoding, the flags are
+    /// boolean witness columns whose binary combinations are matched against
+    /// few fixed columns `p_instr_word_i` instead:
+    /// `instr_word_i = instr_a + 2 * instr_b + 4 * instr_c + ...`.
+    pub compress_rom: bool,
+}
+
+pub fn compile<'a, T: FieldElement>(
+    file_name: Option<&str>,
+    input: &'a str,
+    options: &CompileOptions,
+) -> Result<PILFile<T>, ParseError<'a>> {
+    compile_with_source_map(file_name, input, options).map(|(pil, ..)| pil)
+}
+
+/// Source locations of the lines of the program, indexed by the value of the pc.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SourceMap {
+    /// The absolute name of the pc column, if there is a pc register.
+    pub pc: Option<String>,
+    pub locations: BTreeMap<usize, SourceLocation>,
+}
+
+/// What the identities were generated from, indexed by their line in the
+/// printed PIL, for example "instruction `mstore` at program.asm:117".
+pub type IdentityOrigins = BTreeMap<usize, String>;
+
+/// Compiles to PIL and also returns the source locations (from `.debug` directives)
+/// of the lines of the program, indexed by the value of the pc, and what the
+/// identities were generated from, indexed by their line in the printed PIL.
+pub fn compile_with_source_map<'a, T: FieldElement>(
+    file_name: Option<&str>,
+    input: &'a str,
+    options: &CompileOptions,
+) -> Result<(PILFile<T>, SourceMap, IdentityOrigins), ParseError<'a>> {
+    parser::parse_asm(file_name, input).map(|ast| {
+        let mut converter = ASMPILConverter::new(file_name, input, options);
+        let pil = converter.convert(ASMFile(conditionals::resolve(ast.0, &options.defines)));
+        let origins = converter.identity_origins(&pil);
+        (pil, converter.source_map(), origins)
+    })
+}
+
+/// Parses the assembly and returns warnings about labels that are never
+/// jumped to, unreachable code and registers that are written but never read.
+pub fn check<'a, T: FieldElement>(
+    file_name: Option<&str>,
+    input: &'a str,
+    defines: &BTreeSet<String>,
+) -> Result<Vec<Warning>, ParseError<'a>> {
+    parser::parse_asm(file_name, input).map(|ast: ASMFile<T>| {
+        let statements = conditionals::resolve(ast.0, defines);
+        let statements = resolve_local_labels(macro_expander::expand(statements));
+        analysis::analyze(&statements, input)
+    })
+}
+
+/// A location in the source code the assembly was generated from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
 }
 
 #[derive(Default)]
-struct ASMPILConverter {
+struct ASMPILConverter<T> {
     degree: DegreeType,
-    pil: Vec<Statement>,
+    pil: Vec<Statement<T>>,
     pc_name: Option<String>,
-    registers: BTreeMap<String, Register>,
+    /// The register `call` stores the return address in.
+    return_address_name: Option<String>,
+    registers: BTreeMap<String, Register<T>>,
     instructions: BTreeMap<String, Instruction>,
-    code_lines: Vec<CodeLine>,
+    code_lines: Vec<CodeLine<T>>,
+    /// Instructions whose flags are referenced in inline PIL.
+    inline_pil_instructions: BTreeSet<String>,
+    /// Machines declared with `machine`: their parameters and PIL.
+    machines: BTreeMap<String, (Vec<String>, Vec<Statement<T>>)>,
+    /// Instances of machines, in declaration order.
+    machine_instances: Vec<MachineInstance<T>>,
     /// Pairs of columns that are used in the connecting plookup
     line_lookup: Vec<(String, String)>,
     /// Names of fixed columns that contain the program.
     program_constant_names: Vec<String>,
+    /// Source files declared through `.debug file`, by id.
+    debug_files: BTreeMap<usize, String>,
+    /// The source location set by the most recent `.debug loc`.
+    source_location: Option<SourceLocation>,
+    /// The name of the assembly file and the offsets its lines start at,
+    /// for the locations in the messages of assertions.
+    file_name: Option<String>,
+    line_starts: Vec<usize>,
+    /// If set, the assembly is optimized before it is converted.
+    optimize: bool,
+    /// If set, the instruction flags are encoded in words, see `rom`.
+    compress_rom: bool,
+    /// What the identities were generated from, by their index in `pil`.
+    origins: BTreeMap<usize, String>,
+    /// If set, some instruction flags are batched with this number of bits,
+    /// see `rom::choose_flag_batches`.
+    flag_batch_bits: Option<usize>,
+    flag_batches: rom::FlagBatches,
+    /// The instruction flags in declaration order.
+    instruction_flags: Vec<String>,
+    /// The labels of the jump tables, by the name of their instruction.
+    jump_tables: BTreeMap<String, Vec<String>>,
+    /// What happens after `halt`, set when the instruction is defined.
+    halt_behavior: Option<HaltBehavior>,
+    /// The exported labels. If there are any, the program starts at the one
+    /// selected by the `"entry_point"` query instead of the first line.
+    entry_points: Vec<String>,
 }
 
-impl ASMPILConverter {
-    fn new() -> Self {
-        Default::default()
+impl<T: FieldElement> ASMPILConverter<T> {
+    fn new(file_name: Option<&str>, input: &str, options: &CompileOptions) -> Self {
+        ASMPILConverter {
+            optimize: options.optimize,
+            file_name: file_name.map(|f| f.to_string()),
+            line_starts: compute_line_starts(input),
+            compress_rom: options.compress_rom,
+            ..Default::default()
+        }
     }
 
     fn set_degree(&mut self, degree: DegreeType) {
@@ -42,13 +174,21 @@ impl ASMPILConverter {
         self.degree = degree;
     }
 
-    fn convert(&mut self, input: ASMFile) -> PILFile {
+    fn convert(&mut self, input: ASMFile<T>) -> PILFile<T> {
         self.set_degree(1024);
 
-        let mut statements = input.0.into_iter().peekable();
+        let mut statements = resolve_local_labels(macro_expander::expand(input.0));
+        declare_return_address_register(&mut statements);
+        if self.optimize {
+            statements = optimizer::optimize(statements);
+        }
+        let mut statements = statements.into_iter().peekable();
 
         if let Some(ASMStatement::Degree(_, degree)) = statements.peek() {
-            self.set_degree(*degree as DegreeType);
+            self.set_degree(
+                DegreeType::try_from(degree)
+                    .unwrap_or_else(|_| panic!("Degree too large: {degree}")),
+            );
             statements.next();
         }
 
@@ -74,59 +214,172 @@ impl ASMPILConverter {
                     self.handle_register_declaration(flags, &name, start);
                 }
                 ASMStatement::InstructionDeclaration(start, name, params, body) => {
+                    assert!(
+                        name != "call" && name != "return" && name != "halt",
+                        "The instruction {name} is built in and cannot be declared."
+                    );
                     self.handle_instruction_def(start, body, name, params);
                 }
-                ASMStatement::InlinePil(_start, statements) => self.pil.extend(statements.clone()),
-                ASMStatement::Assignment(start, write_regs, assign_reg, value) => match *value {
-                    Expression::FunctionCall(function_name, args) => {
-                        self.handle_functional_instruction(
-                            write_regs,
-                            assign_reg.unwrap(),
-                            function_name,
-                            args,
-                        );
+                ASMStatement::JumpTable(start, name, register, labels) => {
+                    self.handle_jump_table(start, name, register, labels);
+                }
+                ASMStatement::InlinePil(_start, statements) => {
+                    for statement in statements {
+                        let statement = self.resolve_instruction_flags(statement);
+                        match &statement {
+                            Statement::PolynomialIdentity(start, ..)
+                            | Statement::PlookupIdentity(start, ..)
+                            | Statement::LogUpIdentity(start, ..)
+                            | Statement::PermutationIdentity(start, ..)
+                            | Statement::ConnectIdentity(start, ..) => {
+                                let origin = format!("inline PIL at {}", self.location(*start));
+                                self.push_identity(statement, origin);
+                            }
+                            _ => self.pil.push(statement),
+                        }
                     }
-                    _ => {
-                        self.handle_assignment(start, write_regs, assign_reg, *value);
+                }
+                ASMStatement::MachineDefinition(_start, name, params, body) => {
+                    assert!(
+                        self.machines.insert(name.clone(), (params, body)).is_none(),
+                        "Machine {name} is defined twice."
+                    );
+                }
+                ASMStatement::MachineInstance(_start, name, machine, args) => {
+                    self.handle_machine_instance(name, machine, args)
+                }
+                ASMStatement::Assignment(start, write_regs, assign_reg, value) => {
+                    self.check_writable(start, &write_regs);
+                    match *value {
+                        Expression::FunctionCall(function_name, args) => {
+                            self.handle_functional_instruction(
+                                write_regs,
+                                assign_reg,
+                                function_name,
+                                args,
+                            );
+                        }
+                        _ => {
+                            self.handle_assignment(start, write_regs, assign_reg, *value);
+                        }
                     }
-                },
+                }
                 ASMStatement::Instruction(_start, instr_name, args) => {
                     self.handle_instruction(instr_name, args)
                 }
-                ASMStatement::Label(_start, name) => self.code_lines.push(CodeLine {
+                ASMStatement::Call(_start, label) => {
+                    self.define_call_instructions();
+                    self.handle_instruction("call".to_string(), vec![direct_reference(label)])
+                }
+                ASMStatement::Return(_start) => {
+                    self.define_call_instructions();
+                    self.handle_instruction("return".to_string(), vec![])
+                }
+                ASMStatement::Halt(_start) => {
+                    let behavior = self.define_halt_instruction(HaltBehavior::Loop);
+                    let args = match behavior {
+                        HaltBehavior::Jump(label) => vec![direct_reference(label)],
+                        HaltBehavior::Loop | HaltBehavior::Padding => vec![],
+                    };
+                    self.handle_instruction("halt".to_string(), args)
+                }
+                ASMStatement::Export(_start, label) => {
+                    assert!(
+                        !self.entry_points.contains(&label),
+                        "Label {label} is exported twice."
+                    );
+                    self.entry_points.push(label);
+                }
+                ASMStatement::OnHalt(_start, behavior) => {
+                    assert!(
+                        !self.instructions.contains_key("halt"),
+                        "on_halt has to come before the first halt."
+                    );
+                    self.define_halt_instruction(behavior);
+                }
+                ASMStatement::Assert(start, left, right, message) => {
+                    self.handle_assertion(start, left, right, message)
+                }
+                ASMStatement::Label(_start, name) => self.push_code_line(CodeLine {
                     label: Some(name.clone()),
                     ..Default::default()
                 }),
+                ASMStatement::DebugDirective(_start, directive) => {
+                    self.handle_debug_directive(directive)
+                }
+                ASMStatement::MacroDefinition(..) | ASMStatement::Repeat(..) => {
+                    unreachable!("Macros are expanded before the conversion.")
+                }
+                ASMStatement::Conditional(..) => {
+                    unreachable!("Conditionals are resolved before the conversion.")
+                }
             }
         }
+        for name in &self.inline_pil_instructions {
+            assert!(
+                self.instructions.contains_key(name),
+                "Instruction {name} used in inline PIL as instr.{name} is not declared."
+            );
+        }
         let assignment_registers = self.assignment_registers().cloned().collect::<Vec<_>>();
         for reg in assignment_registers {
             self.create_constraints_for_assignment_reg(reg);
         }
 
-        self.pil.extend(
-            self.registers
-                .iter()
-                .filter_map(|(name, reg)| {
-                    reg.update_expression().map(|mut update| {
-                        if Some(name) == self.pc_name.as_ref() {
-                            // Force pc to zero on first row.
-                            update = build_mul(
-                                build_sub(build_number(1u64), next_reference("first_step")),
-                                update,
-                            )
-                        }
+        let updates = self
+            .registers
+            .iter()
+            .filter_map(|(name, reg)| {
+                reg.update_expression().map(|update| {
+                    let not_first = build_sub(build_number(1u64), next_reference("first_step"));
+                    let identity = if Some(name) != self.pc_name.as_ref() {
+                        build_sub(next_reference(name), update)
+                    } else if self.entry_points.is_empty() {
+                        // Force pc to zero on first row.
+                        build_sub(next_reference(name), build_mul(not_first, update))
+                    } else {
+                        // The pc on the first row is an entry point, see
+                        // `declare_entry_points`.
+                        build_mul(not_first, build_sub(next_reference(name), update))
+                    };
+                    (
+                        Statement::PolynomialIdentity(0, identity),
+                        format!("the update of register `{name}`"),
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+        for (identity, origin) in updates {
+            self.push_identity(identity, origin);
+        }
 
-                        (name, update)
-                    })
+        let updates = self
+            .registers
+            .iter()
+            .filter(|(_, reg)| reg.is_unconstrained)
+            .flat_map(|(name, reg)| {
+                reg.conditioned_updates.iter().map(move |(cond, value)| {
+                    (
+                        Statement::PolynomialIdentity(
+                            0,
+                            build_mul(cond.clone(), build_sub(next_reference(name), value.clone())),
+                        ),
+                        format!("the update of register `{name}`"),
+                    )
                 })
-                .map(|(name, update)| {
-                    Statement::PolynomialIdentity(0, build_sub(next_reference(name), update))
-                }),
-        );
+            })
+            .collect::<Vec<_>>();
+        for (identity, origin) in updates {
+            self.push_identity(identity, origin);
+        }
 
+        if !self.compress_rom {
+            self.batch_flags();
+        }
         self.translate_code_lines();
 
+        self.origins
+            .insert(self.pil.len(), "the lookup into the program".to_string());
         self.pil.push(Statement::PlookupIdentity(
             0,
             SelectedExpressions {
@@ -147,9 +400,226 @@ impl ASMPILConverter {
             },
         ));
 
+        if let Some(bits) = self.flag_batch_bits {
+            self.decode_batched_flags(bits);
+        }
+
+        for instance in std::mem::take(&mut self.machine_instances) {
+            let origin = format!("machine instance `{}`", instance.name);
+            for statement in instance.into_pil(self.degree) {
+                self.push_identity(statement, origin.clone());
+            }
+        }
+
         PILFile(std::mem::take(&mut self.pil))
     }
 
+    /// Adds a statement and records what it was generated from, which is
+    /// only used if the statement is an identity.
+    fn push_identity(&mut self, statement: Statement<T>, origin: String) {
+        self.origins.insert(self.pil.len(), origin);
+        self.pil.push(statement);
+    }
+
+    /// Returns the origins of the identities in `pil`, which is the result of
+    /// `convert`, by the line the identities are printed at.
+    fn identity_origins(&self, pil: &PILFile<T>) -> IdentityOrigins {
+        let mut line = 1;
+        let mut origins = BTreeMap::new();
+        for (i, statement) in pil.0.iter().enumerate() {
+            if let Some(origin) = self.origins.get(&i) {
+                origins.insert(line, origin.clone());
+            }
+            line += statement.to_string().lines().count().max(1);
+        }
+        origins
+    }
+
+    /// The file and line of an offset in the assembly, for messages.
+    fn location(&self, start: usize) -> String {
+        let line = offset_to_line(start, &self.line_starts);
+        match &self.file_name {
+            Some(file_name) => format!("{file_name}:{line}"),
+            None => format!("line {line}"),
+        }
+    }
+
+    /// Replaces the references `instr.name` in the constraints of inline PIL
+    /// by the flag of the instruction.
+    fn resolve_instruction_flags(&mut self, statement: Statement<T>) -> Statement<T> {
+        map_statement_references(statement, &mut |r| match r.namespace.as_deref() {
+            Some("instr") => {
+                assert!(
+                    r.index.is_none(),
+                    "Instruction flags cannot be indexed: instr.{}",
+                    r.name
+                );
+                let flag = format!("instr_{}", r.name);
+                self.inline_pil_instructions.insert(r.name);
+                if r.next {
+                    next_reference(&flag)
+                } else {
+                    direct_reference(flag)
+                }
+            }
+            _ => Expression::PolynomialReference(r),
+        })
+    }
+
+    /// Replaces the references to the batched instruction flags in the
+    /// constraints by the expressions that decode them from the bits.
+    /// Chooses the instruction flags to batch, see `rom::choose_flag_batches`,
+    /// and replaces their committed columns by the bits of the batches,
+    /// which are declared in place of the first flag of each batch.
+    fn batch_flags(&mut self) {
+        let Some((bits, batches)) = rom::choose_flag_batches(&self.pil, &self.instruction_flags)
+        else {
+            return;
+        };
+        let mut pil = vec![];
+        let mut origins = BTreeMap::new();
+        for (i, statement) in std::mem::take(&mut self.pil).into_iter().enumerate() {
+            let batched = match &statement {
+                Statement::PolynomialCommitDeclaration(start, names, None) if names.len() == 1 => {
+                    batches
+                        .get(&names[0].name)
+                        .map(|position| (*start, *position))
+                }
+                _ => None,
+            };
+            match batched {
+                Some((start, (batch, 1))) => {
+                    for bit in 0..bits {
+                        pil.push(witness_column(start, rom::batch_bit_name(batch, bit), None));
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    if let Some(origin) = self.origins.remove(&i) {
+                        origins.insert(pil.len(), origin);
+                    }
+                    pil.push(statement);
+                }
+            }
+        }
+        self.pil = pil;
+        self.origins = origins;
+        self.line_lookup
+            .retain(|(name, _)| !batches.contains_key(name));
+        self.program_constant_names
+            .retain(|name| !batches.contains_key(&name["p_".len()..]));
+        let batch_count = batches.values().map(|(batch, _)| batch + 1).max().unwrap();
+        for batch in 0..batch_count {
+            for bit in 0..bits {
+                let name = rom::batch_bit_name(batch, bit);
+                self.line_lookup.push((name.clone(), format!("p_{name}")));
+                self.program_constant_names.push(format!("p_{name}"));
+            }
+        }
+        self.flag_batch_bits = Some(bits);
+        self.flag_batches = batches;
+    }
+
+    /// Replaces the references to batched flags by the products that decode
+    /// them from the bits of their batches.
+    fn decode_batched_flags(&mut self, bits: usize) {
+        let flags = &self.flag_batches;
+        self.pil = std::mem::take(&mut self.pil)
+            .into_iter()
+            .map(|statement| {
+                map_statement_references(statement, &mut |r| match flags.get(&r.name) {
+                    Some((batch, number)) if r.namespace.is_none() => {
+                        rom::decode_flag(*batch, *number, bits, r.next)
+                    }
+                    _ => Expression::PolynomialReference(r),
+                })
+            })
+            .collect();
+    }
+
+    fn handle_machine_instance(&mut self, name: String, machine: String, args: Vec<Expression<T>>) {
+        assert!(
+            self.machine_instances.iter().all(|i| i.name != name),
+            "Machine instance {name} is declared twice."
+        );
+        let (params, body) = self
+            .machines
+            .get(&machine)
+            .unwrap_or_else(|| panic!("Machine {machine} is not defined."));
+        let instance = MachineInstance::new(name, &machine, params, body, args);
+        self.machine_instances.push(instance);
+    }
+
+    /// Adds a selector in the machine instance to the right side of a
+    /// permutation from an instruction if it has none and only refers to
+    /// columns of that instance.
+    fn select_in_instance(
+        &mut self,
+        instruction_flag: &str,
+        mut right: SelectedExpressions<T>,
+    ) -> SelectedExpressions<T> {
+        if right.selector.is_some() {
+            return right;
+        }
+        let mut namespaces = right.expressions.iter().map(|e| match e {
+            Expression::PolynomialReference(r) => r.namespace.as_deref(),
+            _ => None,
+        });
+        let Some(Some(namespace)) = namespaces.next() else {
+            return right;
+        };
+        if namespaces.all(|n| n == Some(namespace)) {
+            if let Some(instance) = self
+                .machine_instances
+                .iter_mut()
+                .find(|i| i.name == namespace)
+            {
+                right.selector = Some(instance.add_selector(instruction_flag));
+            }
+        }
+        right
+    }
+
+    fn handle_debug_directive(&mut self, directive: DebugDirective) {
+        match directive {
+            DebugDirective::File(id, directory, file) => {
+                let path = if directory.is_empty() {
+                    file
+                } else {
+                    format!("{directory}/{file}")
+                };
+                self.debug_files.insert(id, path);
+            }
+            DebugDirective::Loc(file, line, column) => {
+                let file = self
+                    .debug_files
+                    .get(&file)
+                    .unwrap_or_else(|| panic!("Undeclared debug file id: {file}"))
+                    .clone();
+                self.source_location = Some(SourceLocation { file, line, column });
+            }
+        }
+    }
+
+    fn push_code_line(&mut self, line: CodeLine<T>) {
+        self.code_lines.push(CodeLine {
+            source_location: self.source_location.clone(),
+            ..line
+        });
+    }
+
+    fn source_map(&self) -> SourceMap {
+        SourceMap {
+            pc: self.pc_name.as_ref().map(|pc| format!("Assembly.{pc}")),
+            locations: self
+                .code_lines
+                .iter()
+                .enumerate()
+                .filter_map(|(i, line)| line.source_location.clone().map(|loc| (i, loc)))
+                .collect(),
+        }
+    }
+
     fn handle_register_declaration(
         &mut self,
         flags: Option<RegisterFlag>,
@@ -169,13 +639,45 @@ impl ASMPILConverter {
             Some(RegisterFlag::IsAssignment) => {
                 // no updates
             }
-            None => {
+            Some(RegisterFlag::ReadOnly(ref value)) => {
+                let initial = match value {
+                    Some(value) => build_sub(direct_reference(name), build_number(value.clone())),
+                    None => direct_reference(name),
+                };
+                let value = value.clone().map(T::from).unwrap_or_default();
+                let origin = format!("register `{name}` at {}", self.location(start));
+                self.push_identity(
+                    Statement::PolynomialIdentity(
+                        start,
+                        build_mul(direct_reference("first_step"), initial),
+                    ),
+                    origin,
+                );
+                // There are no writes, so the register keeps its value
+                // until it is set to the initial value again in the
+                // first row after the wrap-around.
+                conditioned_updates = vec![(next_reference("first_step"), build_number(value))];
+                default_update = Some(direct_reference(name));
+            }
+            None
+            | Some(RegisterFlag::IsReturnAddress)
+            | Some(RegisterFlag::ResetToZero)
+            | Some(RegisterFlag::Unconstrained)
+            | Some(RegisterFlag::CalleeSaved) => {
+                if flags == Some(RegisterFlag::IsReturnAddress) {
+                    assert_eq!(self.return_address_name, None);
+                    self.return_address_name = Some(name.to_string());
+                }
                 // This might be superfluous but makes it easier to determine that the register needs to
                 // be zero in the first row.
-                self.pil.push(Statement::PolynomialIdentity(
-                    start,
-                    build_mul(direct_reference("first_step"), direct_reference(name)),
-                ));
+                let origin = format!("register `{name}` at {}", self.location(start));
+                self.push_identity(
+                    Statement::PolynomialIdentity(
+                        start,
+                        build_mul(direct_reference("first_step"), direct_reference(name)),
+                    ),
+                    origin,
+                );
                 conditioned_updates = vec![
                     // The value here is actually irrelevant, it is only important
                     // that "first_step'" is included to compute the "default condition"
@@ -189,7 +691,11 @@ impl ASMPILConverter {
                     conditioned_updates
                         .push((direct_reference(&write_flag), direct_reference(&reg)));
                 }
-                default_update = Some(direct_reference(name));
+                default_update = match flags {
+                    Some(RegisterFlag::ResetToZero) => Some(build_number(0u64)),
+                    Some(RegisterFlag::Unconstrained) => None,
+                    _ => Some(direct_reference(name)),
+                };
             }
         };
         self.registers.insert(
@@ -198,6 +704,8 @@ impl ASMPILConverter {
                 conditioned_updates,
                 default_update,
                 is_assignment: flags == Some(RegisterFlag::IsAssignment),
+                is_unconstrained: flags == Some(RegisterFlag::Unconstrained),
+                is_read_only: matches!(flags, Some(RegisterFlag::ReadOnly(_))),
             },
         );
         self.pil.push(witness_column(start, name, None));
@@ -206,14 +714,37 @@ impl ASMPILConverter {
     fn handle_instruction_def(
         &mut self,
         start: usize,
-        body: Vec<InstructionBodyElement>,
+        body: Vec<InstructionBodyElement<T>>,
         name: String,
         params: InstructionParams,
     ) {
         let instruction_flag = format!("instr_{name}");
-        self.create_witness_fixed_pair(start, &instruction_flag);
-        // it's part of the lookup!
-        //self.pil.push(constrain_zero_one(&col_name));
+        let origin =
+            if start == 0 && ["call", "return", "assert_eq", "halt"].contains(&name.as_str()) {
+                format!("built-in instruction `{name}`")
+            } else {
+                format!("instruction `{name}` at {}", self.location(start))
+            };
+        if self.compress_rom {
+            // The flag is decoded from the words in the lookup, which
+            // requires it to be boolean.
+            self.pil
+                .push(witness_column(start, &instruction_flag, None));
+            self.push_identity(
+                Statement::PolynomialIdentity(
+                    start,
+                    build_mul(
+                        direct_reference(&instruction_flag),
+                        build_sub(build_number(1u64), direct_reference(&instruction_flag)),
+                    ),
+                ),
+                origin.clone(),
+            );
+        } else {
+            // The flag might be replaced by a batch later, see `batch_flags`.
+            self.create_witness_fixed_pair(start, &instruction_flag);
+        }
+        self.instruction_flags.push(instruction_flag.clone());
 
         let inputs: Vec<_> = params
             .inputs
@@ -226,7 +757,7 @@ impl ASMPILConverter {
             })
             .collect();
 
-        let outputs = params
+        let outputs: Vec<String> = params
             .outputs
             .map(|outputs| {
                 outputs
@@ -239,6 +770,12 @@ impl ASMPILConverter {
                     .collect()
             })
             .unwrap_or_default();
+        for (i, output) in outputs.iter().enumerate() {
+            assert!(
+                !outputs[..i].contains(output),
+                "Output {output} of {name} is used twice, each output needs its own assignment register."
+            );
+        }
 
         let instr = Instruction { inputs, outputs };
 
@@ -258,16 +795,22 @@ impl ASMPILConverter {
                     let expr = substitute(expr, &substitutions);
                     match extract_update(expr) {
                         (Some(var), expr) => {
-                            self.registers
-                                .get_mut(&var)
-                                .unwrap()
+                            let register = self.registers.get_mut(&var).unwrap();
+                            assert!(
+                                !register.is_read_only,
+                                "Instruction {name} writes the read-only register {var}."
+                            );
+                            register
                                 .conditioned_updates
                                 .push((direct_reference(&instruction_flag), expr));
                         }
-                        (None, expr) => self.pil.push(Statement::PolynomialIdentity(
-                            0,
-                            build_mul(direct_reference(&instruction_flag), expr.clone()),
-                        )),
+                        (None, expr) => self.push_identity(
+                            Statement::PolynomialIdentity(
+                                0,
+                                build_mul(direct_reference(&instruction_flag), expr.clone()),
+                            ),
+                            origin.clone(),
+                        ),
                     }
                 }
                 InstructionBodyElement::PlookupIdentity(left, op, right) => {
@@ -277,31 +820,231 @@ impl ASMPILConverter {
                         expressions: substitute_vec(left.expressions, &substitutions),
                     };
                     let right = substitute_selected_exprs(right, &substitutions);
-                    self.pil.push(match op {
+                    let identity = match op {
                         PlookupOperator::In => Statement::PlookupIdentity(start, left, right),
-                        PlookupOperator::Is => Statement::PermutationIdentity(start, left, right),
-                    })
+                        PlookupOperator::Is => {
+                            let right = self.select_in_instance(&instruction_flag, right);
+                            Statement::PermutationIdentity(start, left, right)
+                        }
+                    };
+                    self.push_identity(identity, origin.clone());
                 }
             }
         }
         self.instructions.insert(name, instr);
     }
 
+    /// Declares the instruction of a jump table. It looks up the target of
+    /// the jump by the value of the assignment register in a pair of fixed
+    /// columns that are filled in `translate_code_lines`.
+    fn handle_jump_table(
+        &mut self,
+        start: usize,
+        name: String,
+        register: String,
+        labels: Vec<String>,
+    ) {
+        let pc = self
+            .pc_name
+            .clone()
+            .expect("Jump tables need a pc register.");
+        let target = format!("jump_table_{name}_target");
+        self.pil.push(witness_column(start, &target, None));
+        let body = vec![
+            InstructionBodyElement::Expression(build_sub(
+                next_reference(&pc),
+                direct_reference(&target),
+            )),
+            InstructionBodyElement::PlookupIdentity(
+                SelectedExpressions {
+                    selector: None,
+                    expressions: vec![direct_reference(&register), direct_reference(&target)],
+                },
+                PlookupOperator::In,
+                SelectedExpressions {
+                    selector: None,
+                    expressions: vec![
+                        direct_reference(format!("jump_table_{name}_index")),
+                        direct_reference(format!("jump_table_{name}_pc")),
+                    ],
+                },
+            ),
+        ];
+        let params = InstructionParams::new(
+            InstructionParamList::new(vec![InstructionParam {
+                name: register,
+                ty: None,
+            }]),
+            None,
+        );
+        assert!(
+            self.jump_tables.insert(name.clone(), labels).is_none(),
+            "Jump table {name} is declared twice."
+        );
+        self.handle_instruction_def(start, body, name, params);
+    }
+
+    /// Defines the built-in instructions `call` and `return` on their first use.
+    fn define_call_instructions(&mut self) {
+        if self.instructions.contains_key("call") {
+            return;
+        }
+        let pc = self
+            .pc_name
+            .clone()
+            .expect("call and return need a pc register.");
+        let return_address = self.return_address_name.clone().unwrap();
+        let update = |register: &str, value| {
+            InstructionBodyElement::Expression(build_sub(next_reference(register), value))
+        };
+        let label = InstructionParam {
+            name: "l".to_string(),
+            ty: Some("label".to_string()),
+        };
+        self.handle_instruction_def(
+            0,
+            vec![
+                update(&pc, direct_reference("l")),
+                update(
+                    &return_address,
+                    build_add(direct_reference(&pc), build_number(1u64)),
+                ),
+            ],
+            "call".to_string(),
+            InstructionParams::new(InstructionParamList::new(vec![label]), None),
+        );
+        self.handle_instruction_def(
+            0,
+            vec![update(&pc, direct_reference(&return_address))],
+            "return".to_string(),
+            InstructionParams::new(InstructionParamList::new(vec![]), None),
+        );
+    }
+
+    /// Defines the built-in instruction `halt` with the given behavior,
+    /// unless it is already defined, and returns the behavior it was
+    /// defined with.
+    fn define_halt_instruction(&mut self, behavior: HaltBehavior) -> HaltBehavior {
+        if let Some(behavior) = &self.halt_behavior {
+            return behavior.clone();
+        }
+        let pc = self.pc_name.clone().expect("halt needs a pc register.");
+        let (target, params) = match &behavior {
+            HaltBehavior::Jump(_) => (
+                direct_reference("l"),
+                vec![InstructionParam {
+                    name: "l".to_string(),
+                    ty: Some("label".to_string()),
+                }],
+            ),
+            HaltBehavior::Loop | HaltBehavior::Padding => (direct_reference(&pc), vec![]),
+        };
+        self.handle_instruction_def(
+            0,
+            vec![InstructionBodyElement::Expression(build_sub(
+                next_reference(&pc),
+                target,
+            ))],
+            "halt".to_string(),
+            InstructionParams::new(InstructionParamList::new(params), None),
+        );
+        if behavior == HaltBehavior::Padding {
+            // The pc stays at the halt, so all rows after the first halt
+            // up to the end of the trace are padding.
+            self.pil.push(witness_column(0, "is_padding", None));
+            self.push_identity(
+                Statement::PolynomialIdentity(
+                    0,
+                    build_sub(
+                        next_reference("is_padding"),
+                        build_mul(
+                            build_sub(build_number(1u64), next_reference("first_step")),
+                            direct_reference("instr_halt"),
+                        ),
+                    ),
+                ),
+                "built-in instruction `halt`".to_string(),
+            );
+        }
+        self.halt_behavior = Some(behavior.clone());
+        behavior
+    }
+
+    /// Declares the instruction `assert_eq` that constrains the first
+    /// assignment register to zero and returns the register.
+    fn define_assert_instruction(&mut self) -> String {
+        let reg = self
+            .assignment_registers()
+            .next()
+            .cloned()
+            .expect("assert_eq needs an assignment register.");
+        if !self.instructions.contains_key("assert_eq") {
+            let param = InstructionParam {
+                name: reg.clone(),
+                ty: None,
+            };
+            self.handle_instruction_def(
+                0,
+                vec![InstructionBodyElement::Expression(direct_reference(&reg))],
+                "assert_eq".to_string(),
+                InstructionParams::new(InstructionParamList::new(vec![param]), None),
+            );
+        }
+        reg
+    }
+
+    /// Reads the difference of the values into an assignment register that
+    /// is constrained to zero. The message is reported by the query of
+    /// `assert_eq_query` if the difference is not zero.
+    fn handle_assertion(
+        &mut self,
+        start: usize,
+        left: Expression<T>,
+        right: Expression<T>,
+        message: String,
+    ) {
+        let reg = self.define_assert_instruction();
+        let value = self.process_assignment_value(build_sub(left, right));
+        assert!(
+            !value
+                .iter()
+                .any(|(_, item)| matches!(item, AffineExpressionComponent::FreeInput(_))),
+            "assert_eq can only compare registers and constants: {message}"
+        );
+        let location = self.location(start);
+        self.push_code_line(CodeLine {
+            value: [(reg, value)].into_iter().collect(),
+            instruction: Some("assert_eq".to_string()),
+            assertion: Some(format!("{location}: {message}")),
+            ..Default::default()
+        });
+    }
+
+    fn check_writable(&self, start: usize, write_regs: &[String]) {
+        for reg in write_regs {
+            assert!(
+                !self.registers.get(reg).is_some_and(|r| r.is_read_only),
+                "The read-only register {reg} is written at {}.",
+                self.location(start)
+            );
+        }
+    }
+
     fn handle_assignment(
         &mut self,
         _start: usize,
         write_regs: Vec<String>,
-        assign_reg: Option<String>,
-        value: Expression,
+        assign_reg: Option<Vec<String>>,
+        value: Expression<T>,
     ) {
         assert!(write_regs.len() <= 1);
-        assert!(
-            assign_reg.is_some(),
-            "Implicit assign register not yet supported."
-        );
-        let assign_reg = assign_reg.unwrap();
+        let assign_reg = match assign_reg.as_deref() {
+            Some([reg]) => reg.clone(),
+            Some(_) => panic!("Only instruction calls can use multiple assignment registers."),
+            None => panic!("Implicit assign register not yet supported."),
+        };
         let value = self.process_assignment_value(value);
-        self.code_lines.push(CodeLine {
+        self.push_code_line(CodeLine {
             write_regs: [(assign_reg.clone(), write_regs)].into_iter().collect(),
             value: [(assign_reg, value)].into(),
             ..Default::default()
@@ -311,28 +1054,38 @@ impl ASMPILConverter {
     fn handle_functional_instruction(
         &mut self,
         write_regs: Vec<String>,
-        assign_reg: String,
+        assign_regs: Option<Vec<String>>,
         instr_name: String,
-        args: Vec<Expression>,
+        args: Vec<Expression<T>>,
     ) {
-        assert!(write_regs.len() == 1);
         let instr = &self
             .instructions
             .get(&instr_name)
             .unwrap_or_else(|| panic!("Intruction not found: {instr_name}"));
-        assert_eq!(instr.outputs.len(), 1);
-        let output = instr.outputs[0].clone();
-        assert!(
-            output == assign_reg,
-            "{output} vs {assign_reg} in {instr_name}",
+        assert_eq!(
+            write_regs.len(),
+            instr.outputs.len(),
+            "{instr_name} has {} outputs, but {} registers are written.",
+            instr.outputs.len(),
+            write_regs.len()
         );
+        // The assignment registers are the outputs of the instruction and
+        // can be omitted.
+        if let Some(assign_regs) = assign_regs {
+            assert!(
+                assign_regs == instr.outputs,
+                "{} vs {} in {instr_name}",
+                instr.outputs.join(", "),
+                assign_regs.join(", ")
+            );
+        }
 
         let mut args = args;
-        args.push(direct_reference(write_regs.first().unwrap().clone()));
+        args.extend(write_regs.into_iter().map(direct_reference));
         self.handle_instruction(instr_name, args);
     }
 
-    fn handle_instruction(&mut self, instr_name: String, args: Vec<Expression>) {
+    fn handle_instruction(&mut self, instr_name: String, args: Vec<Expression<T>>) {
         let instr = &self
             .instructions
             .get(&instr_name)
@@ -381,7 +1134,7 @@ impl ASMPILConverter {
 
         assert_eq!(write_regs.len(), instr.outputs.len());
 
-        self.code_lines.push(CodeLine {
+        self.push_code_line(CodeLine {
             write_regs,
             instruction: Some(instr_name.to_string()),
             value,
@@ -392,8 +1145,8 @@ impl ASMPILConverter {
 
     fn process_assignment_value(
         &self,
-        value: Expression,
-    ) -> Vec<(FieldElement, AffineExpressionComponent)> {
+        value: Expression<T>,
+    ) -> Vec<(T, AffineExpressionComponent<T>)> {
         match value {
             Expression::Constant(_) => panic!(),
             Expression::PublicReference(_) => panic!(),
@@ -431,12 +1184,12 @@ impl ASMPILConverter {
                         // TODO overflow?
                         right
                             .into_iter()
-                            .map(|(coeff, comp)| (f * coeff, comp))
+                            .map(|(coeff, comp)| (*f * coeff, comp))
                             .collect()
                     } else if let [(f, AffineExpressionComponent::Constant)] = &right[..] {
                         // TODO overflow?
                         left.into_iter()
-                            .map(|(coeff, comp)| (f * coeff, comp))
+                            .map(|(coeff, comp)| (*f * coeff, comp))
                             .collect()
                     } else {
                         panic!("Multiplication by non-constant.");
@@ -478,9 +1231,9 @@ impl ASMPILConverter {
 
     fn add_assignment_value(
         &self,
-        mut left: Vec<(FieldElement, AffineExpressionComponent)>,
-        right: Vec<(FieldElement, AffineExpressionComponent)>,
-    ) -> Vec<(FieldElement, AffineExpressionComponent)> {
+        mut left: Vec<(T, AffineExpressionComponent<T>)>,
+        right: Vec<(T, AffineExpressionComponent<T>)>,
+    ) -> Vec<(T, AffineExpressionComponent<T>)> {
         // TODO combine (or at leats check for) same components.
         left.extend(right);
         left
@@ -488,8 +1241,8 @@ impl ASMPILConverter {
 
     fn negate_assignment_value(
         &self,
-        expr: Vec<(FieldElement, AffineExpressionComponent)>,
-    ) -> Vec<(FieldElement, AffineExpressionComponent)> {
+        expr: Vec<(T, AffineExpressionComponent<T>)>,
+    ) -> Vec<(T, AffineExpressionComponent<T>)> {
         expr.into_iter().map(|(v, c)| (-v, c)).collect()
     }
 
@@ -515,10 +1268,13 @@ impl ASMPILConverter {
                 build_mul(direct_reference(read_free), direct_reference(free_value)),
             ])
             .reduce(build_add);
-        self.pil.push(Statement::PolynomialIdentity(
-            0,
-            build_sub(direct_reference(register), assign_constraint.unwrap()),
-        ));
+        self.push_identity(
+            Statement::PolynomialIdentity(
+                0,
+                build_sub(direct_reference(&register), assign_constraint.unwrap()),
+            ),
+            format!("assignment register `{register}`"),
+        );
     }
 
     /// Translates the code lines to fixed column but also fills
@@ -531,23 +1287,27 @@ impl ASMPILConverter {
             FunctionDefinition::Array(
                 ArrayExpression::Value(
                     (0..self.code_lines.len())
-                        .map(|i| build_number(i as AbstractNumberType))
+                        .map(|i| build_number(i as u64))
                         .collect(),
                 )
                 .pad_with_last()
                 .unwrap_or_else(|| ArrayExpression::RepeatedValue(vec![build_number(0)])),
             ),
         ));
+        if self.compress_rom {
+            self.declare_instruction_words();
+        }
         // TODO check that all of them are matched against execution trace witnesses.
         let mut program_constants = self
             .program_constant_names
             .iter()
-            .map(|n| (n, vec![FieldElement::from(0); self.code_lines.len()]))
+            .map(|n| (n, vec![T::from(0); self.code_lines.len()]))
             .collect::<BTreeMap<_, _>>();
         let mut free_value_query_arms = self
             .assignment_registers()
             .map(|r| (r.clone(), vec![]))
             .collect::<BTreeMap<_, _>>();
+        let mut assertion_query_arms = vec![];
 
         let label_positions = self.compute_label_positions();
         for (i, line) in self.code_lines.iter().enumerate() {
@@ -581,14 +1341,25 @@ impl ASMPILConverter {
                             program_constants
                                 .get_mut(&format!("p_{assign_reg}_read_free"))
                                 .unwrap()[i] = *coeff;
-                            free_value_query_arms.get_mut(assign_reg).unwrap().push((
-                                Some(build_number(FieldElement::from(i as u64))),
-                                expr.clone(),
-                            ));
+                            free_value_query_arms
+                                .get_mut(assign_reg)
+                                .unwrap()
+                                .push((Some(build_number(T::from(i as u64))), expr.clone()));
                         }
                     }
                 }
             }
+            if let Some(message) = &line.assertion {
+                let difference = line.value.values().next().unwrap();
+                assertion_query_arms.push((
+                    Some(build_number(i as u64)),
+                    Expression::Tuple(vec![
+                        Expression::String("assert".to_string()),
+                        affine_expression(difference),
+                        Expression::String(message.clone()),
+                    ]),
+                ));
+            }
             if let Some(instr) = &line.instruction {
                 for (reg, writes) in &line.write_regs {
                     if !writes.is_empty() {
@@ -602,9 +1373,28 @@ impl ASMPILConverter {
                             .unwrap()[i] = 1.into();
                     }
                 }
-                program_constants
-                    .get_mut(&format!("p_instr_{instr}"))
-                    .unwrap()[i] = 1.into();
+                if let Some(&(batch, number)) = self.flag_batches.get(&format!("instr_{instr}")) {
+                    let bits = self.flag_batch_bits.unwrap();
+                    for bit in (0..bits).filter(|bit| number & (1 << bit) != 0) {
+                        program_constants
+                            .get_mut(&format!("p_{}", rom::batch_bit_name(batch, bit)))
+                            .unwrap()[i] = 1.into();
+                    }
+                } else if self.compress_rom {
+                    let index = self
+                        .instruction_flags
+                        .iter()
+                        .position(|flag| *flag == format!("instr_{instr}"))
+                        .unwrap();
+                    let (word, bit) = rom::flag_position::<T>(index);
+                    program_constants
+                        .get_mut(&format!("p_{}", rom::word_name(word)))
+                        .unwrap()[i] = T::from(2u32).pow(bit.into());
+                } else {
+                    program_constants
+                        .get_mut(&format!("p_instr_{instr}"))
+                        .unwrap()[i] = 1.into();
+                }
                 for (arg, param) in line
                     .instruction_literal_args
                     .iter()
@@ -612,7 +1402,7 @@ impl ASMPILConverter {
                 {
                     program_constants
                         .get_mut(&format!("p_instr_{instr}_param_{}", param.clone()))
-                        .unwrap()[i] = (label_positions[arg] as i64).into();
+                        .unwrap()[i] = (label_positions[arg] as u64).into();
                 }
             } else {
                 assert!(line.instruction_literal_args.is_empty());
@@ -637,6 +1427,45 @@ impl ASMPILConverter {
             })
             .collect::<Vec<_>>();
         self.pil.extend(free_value_pil);
+        if !assertion_query_arms.is_empty() {
+            self.pil.push(witness_column(
+                0,
+                "assert_eq_query",
+                Some(FunctionDefinition::Query(
+                    vec!["i".to_string()],
+                    Expression::MatchExpression(
+                        Box::new(direct_reference(pc_name.as_ref().unwrap())),
+                        assertion_query_arms,
+                    ),
+                )),
+            ));
+        }
+        for (name, labels) in &self.jump_tables {
+            let targets = labels
+                .iter()
+                .map(|label| {
+                    let position = label_positions
+                        .get(label)
+                        .unwrap_or_else(|| panic!("Label {label} of jump table {name} not found."));
+                    build_number(*position as u64)
+                })
+                .collect();
+            for (column, values) in [
+                (
+                    format!("jump_table_{name}_index"),
+                    (0..labels.len()).map(|i| build_number(i as u64)).collect(),
+                ),
+                (format!("jump_table_{name}_pc"), targets),
+            ] {
+                self.pil.push(Statement::PolynomialConstantDefinition(
+                    0,
+                    column,
+                    FunctionDefinition::Array(
+                        ArrayExpression::value(values).pad_with_last().unwrap(),
+                    ),
+                ));
+            }
+        }
         for (name, values) in program_constants {
             self.pil.push(Statement::PolynomialConstantDefinition(
                 0,
@@ -648,6 +1477,117 @@ impl ASMPILConverter {
                 ),
             ));
         }
+        if !self.entry_points.is_empty() {
+            self.declare_entry_points(&label_positions);
+        }
+    }
+
+    /// Declares the column `entry_point` with the number of the entry point
+    /// the program starts at, which is the answer to the `"entry_point"`
+    /// query (and not an input, so that the inputs are the same for all
+    /// entry points) and exposed as the public `entry`, and constrains the
+    /// pc on the first row to the position of its label.
+    fn declare_entry_points(&mut self, label_positions: &HashMap<String, usize>) {
+        let pc = self
+            .pc_name
+            .clone()
+            .expect("Entry points need a pc register.");
+        let positions = self
+            .entry_points
+            .iter()
+            .map(|label| {
+                let position = label_positions
+                    .get(label)
+                    .unwrap_or_else(|| panic!("Exported label {label} not found."));
+                build_number(*position as u64)
+            })
+            .collect();
+        let numbers = (0..self.entry_points.len())
+            .map(|i| build_number(i as u64))
+            .collect();
+        for (name, values) in [
+            ("entry_point_number", numbers),
+            ("entry_point_pc", positions),
+        ] {
+            self.pil.push(Statement::PolynomialConstantDefinition(
+                0,
+                name.to_string(),
+                FunctionDefinition::Array(ArrayExpression::value(values).pad_with_last().unwrap()),
+            ));
+        }
+        self.pil.push(witness_column(
+            0,
+            "entry_point",
+            Some(FunctionDefinition::Query(
+                vec!["i".to_string()],
+                Expression::String("entry_point".to_string()),
+            )),
+        ));
+        let origin = "the entry points".to_string();
+        self.push_identity(
+            Statement::PolynomialIdentity(
+                0,
+                build_sub(
+                    next_reference("entry_point"),
+                    direct_reference("entry_point"),
+                ),
+            ),
+            origin.clone(),
+        );
+        self.push_identity(
+            Statement::PlookupIdentity(
+                0,
+                SelectedExpressions {
+                    selector: Some(direct_reference("first_step")),
+                    expressions: vec![direct_reference("entry_point"), direct_reference(&pc)],
+                },
+                SelectedExpressions {
+                    selector: None,
+                    expressions: vec![
+                        direct_reference("entry_point_number"),
+                        direct_reference("entry_point_pc"),
+                    ],
+                },
+            ),
+            origin,
+        );
+        self.pil.push(Statement::PublicDeclaration(
+            0,
+            "entry".to_string(),
+            PolynomialReference {
+                namespace: None,
+                name: "entry_point".to_string(),
+                index: None,
+                next: false,
+            },
+            build_number(0u64),
+        ));
+    }
+
+    /// Declares the words the instruction flags are encoded in and
+    /// constrains them to the binary combinations of the flags.
+    fn declare_instruction_words(&mut self) {
+        let flags = std::mem::take(&mut self.instruction_flags);
+        for (word, flags_in_word) in flags.chunks(rom::flags_per_word::<T>()).enumerate() {
+            let name = rom::word_name(word);
+            self.create_witness_fixed_pair(0, &name);
+            let combination = flags_in_word
+                .iter()
+                .enumerate()
+                .map(|(bit, flag)| {
+                    build_mul(
+                        build_number(T::from(2u32).pow(bit.into())),
+                        direct_reference(flag),
+                    )
+                })
+                .reduce(build_add)
+                .unwrap();
+            self.push_identity(
+                Statement::PolynomialIdentity(0, build_sub(direct_reference(&name), combination)),
+                "the encoding of instruction flags".to_string(),
+            );
+        }
+        self.instruction_flags = flags;
     }
 
     fn compute_label_positions(&self) -> HashMap<String, usize> {
@@ -684,18 +1624,26 @@ impl ASMPILConverter {
     }
 }
 
-struct Register {
+struct Register<T> {
     /// Constraints to update this register, first item being the
     /// condition, second item the value.
     /// TODO check that condition is bool
-    conditioned_updates: Vec<(Expression, Expression)>,
-    default_update: Option<Expression>,
+    conditioned_updates: Vec<(Expression<T>, Expression<T>)>,
+    default_update: Option<Expression<T>>,
     is_assignment: bool,
+    /// If set, the register is only constrained by the conditioned updates
+    /// whose condition is true and there is no default update.
+    is_unconstrained: bool,
+    /// If set, the register keeps its initial value and cannot be written.
+    is_read_only: bool,
 }
 
-impl Register {
+impl<T: FieldElement> Register<T> {
     /// Returns the expression assigned to this register in the next row.
-    pub fn update_expression(&self) -> Option<Expression> {
+    pub fn update_expression(&self) -> Option<Expression<T>> {
+        if self.is_unconstrained {
+            return None;
+        }
         // TODO conditions need to be all boolean
         let updates = self
             .conditioned_updates
@@ -748,29 +1696,140 @@ impl Instruction {
 // TODO turn this into an enum, split into
 // label, assignment, instruction.
 #[derive(Default)]
-struct CodeLine {
+struct CodeLine<T> {
     /// Which regular registers to assign to, from which assignment register
     /// Maps assignment register to a vector of regular registers.
     write_regs: BTreeMap<String, Vec<String>>,
     /// The value on the right-hand-side, per assignment register
-    value: BTreeMap<String, Vec<(FieldElement, AffineExpressionComponent)>>,
+    value: BTreeMap<String, Vec<(T, AffineExpressionComponent<T>)>>,
     label: Option<String>,
     instruction: Option<String>,
     // TODO we only support labels for now.
     instruction_literal_args: Vec<String>,
+    source_location: Option<SourceLocation>,
+    /// The location and message of `assert_eq`.
+    assertion: Option<String>,
 }
 
-enum AffineExpressionComponent {
+enum AffineExpressionComponent<T> {
     Register(String),
     Constant,
-    FreeInput(Expression),
+    FreeInput(Expression<T>),
 }
 
-fn witness_column<S: Into<String>>(
+/// Prefixes the local labels (starting with a dot) and the references to them
+/// with the previous non-local label, so that the same local label can be used
+/// after different non-local labels.
+fn resolve_local_labels<T: FieldElement>(statements: Vec<ASMStatement<T>>) -> Vec<ASMStatement<T>> {
+    let mut scope = String::new();
+    let mut resolved = vec![];
+    for statement in statements {
+        let resolve = |name: String| {
+            if name.starts_with('.') {
+                format!("{scope}{name}")
+            } else {
+                name
+            }
+        };
+        let resolve_args = |args: Vec<Expression<T>>| {
+            args.into_iter()
+                .map(|arg| match arg {
+                    Expression::PolynomialReference(r) => {
+                        Expression::PolynomialReference(PolynomialReference {
+                            name: resolve(r.name),
+                            ..r
+                        })
+                    }
+                    arg => arg,
+                })
+                .collect()
+        };
+        let statement = match statement {
+            ASMStatement::Label(start, name) if !name.starts_with('.') => {
+                scope = name.clone();
+                ASMStatement::Label(start, name)
+            }
+            ASMStatement::Label(start, name) => ASMStatement::Label(start, resolve(name)),
+            ASMStatement::Call(start, label) => ASMStatement::Call(start, resolve(label)),
+            ASMStatement::OnHalt(start, HaltBehavior::Jump(label)) => {
+                ASMStatement::OnHalt(start, HaltBehavior::Jump(resolve(label)))
+            }
+            ASMStatement::Export(start, label) => ASMStatement::Export(start, resolve(label)),
+            ASMStatement::JumpTable(start, name, register, labels) => ASMStatement::JumpTable(
+                start,
+                name,
+                register,
+                labels.into_iter().map(resolve).collect(),
+            ),
+            ASMStatement::Instruction(start, name, args) => {
+                ASMStatement::Instruction(start, name, resolve_args(args))
+            }
+            ASMStatement::Assignment(start, write_regs, assign_regs, value) => {
+                let value = match *value {
+                    Expression::FunctionCall(name, args) => {
+                        Expression::FunctionCall(name, resolve_args(args))
+                    }
+                    value => value,
+                };
+                ASMStatement::Assignment(start, write_regs, assign_regs, Box::new(value))
+            }
+            statement => statement,
+        };
+        resolved.push(statement);
+    }
+    resolved
+}
+
+/// Declares the register `return_address` for `call` and `return` after the
+/// other registers if they are used but no register is declared with `@ra`.
+fn declare_return_address_register<T>(statements: &mut Vec<ASMStatement<T>>) {
+    let uses_calls = statements
+        .iter()
+        .any(|s| matches!(s, ASMStatement::Call(..) | ASMStatement::Return(..)));
+    let declared = statements.iter().any(|s| {
+        matches!(
+            s,
+            ASMStatement::RegisterDeclaration(_, _, Some(RegisterFlag::IsReturnAddress))
+        )
+    });
+    if uses_calls && !declared {
+        let position = statements
+            .iter()
+            .rposition(|s| matches!(s, ASMStatement::RegisterDeclaration(..)))
+            .map_or(0, |i| i + 1);
+        statements.insert(
+            position,
+            ASMStatement::RegisterDeclaration(
+                0,
+                "return_address".to_string(),
+                Some(RegisterFlag::IsReturnAddress),
+            ),
+        );
+    }
+}
+
+/// Builds the expression `c1 * r1 + c2 * r2 + ... + c` from its components.
+fn affine_expression<T: FieldElement>(
+    value: &[(T, AffineExpressionComponent<T>)],
+) -> Expression<T> {
+    value
+        .iter()
+        .map(|(coeff, item)| match item {
+            AffineExpressionComponent::Register(reg) => {
+                build_mul(build_number(*coeff), direct_reference(reg))
+            }
+            AffineExpressionComponent::Constant => build_number(*coeff),
+            AffineExpressionComponent::FreeInput(_) => unreachable!(),
+        })
+        .reduce(build_add)
+        .unwrap_or_else(|| build_number(0u64))
+}
+
+fn witness_column<T: FieldElement, S: Into<String>>(
     start: usize,
     name: S,
-    def: Option<FunctionDefinition>,
-) -> Statement {
+    def: Option<FunctionDefinition<T>>,
+) -> Statement<T> {
     Statement::PolynomialCommitDeclaration(
         start,
         vec![PolynomialName {
@@ -781,7 +1840,7 @@ fn witness_column<S: Into<String>>(
     )
 }
 
-fn direct_reference<S: Into<String>>(name: S) -> Expression {
+fn direct_reference<T: FieldElement, S: Into<String>>(name: S) -> Expression<T> {
     Expression::PolynomialReference(PolynomialReference {
         namespace: None,
         name: name.into(),
@@ -790,7 +1849,7 @@ fn direct_reference<S: Into<String>>(name: S) -> Expression {
     })
 }
 
-fn next_reference(name: &str) -> Expression {
+fn next_reference<T: FieldElement>(name: &str) -> Expression<T> {
     Expression::PolynomialReference(PolynomialReference {
         namespace: None,
         name: name.to_owned(),
@@ -799,31 +1858,35 @@ fn next_reference(name: &str) -> Expression {
     })
 }
 
-fn build_mul(left: Expression, right: Expression) -> Expression {
+fn build_mul<T: FieldElement>(left: Expression<T>, right: Expression<T>) -> Expression<T> {
     build_binary_expr(left, BinaryOperator::Mul, right)
 }
 
-fn build_sub(left: Expression, right: Expression) -> Expression {
+fn build_sub<T: FieldElement>(left: Expression<T>, right: Expression<T>) -> Expression<T> {
     build_binary_expr(left, BinaryOperator::Sub, right)
 }
 
-fn build_add(left: Expression, right: Expression) -> Expression {
+fn build_add<T: FieldElement>(left: Expression<T>, right: Expression<T>) -> Expression<T> {
     build_binary_expr(left, BinaryOperator::Add, right)
 }
 
-fn build_binary_expr(left: Expression, op: BinaryOperator, right: Expression) -> Expression {
+fn build_binary_expr<T: FieldElement>(
+    left: Expression<T>,
+    op: BinaryOperator,
+    right: Expression<T>,
+) -> Expression<T> {
     Expression::BinaryOperation(Box::new(left), op, Box::new(right))
 }
 
-fn build_unary_expr(op: UnaryOperator, exp: Expression) -> Expression {
+fn build_unary_expr<T: FieldElement>(op: UnaryOperator, exp: Expression<T>) -> Expression<T> {
     Expression::UnaryOperation(op, Box::new(exp))
 }
 
-fn build_number<V: Into<FieldElement>>(value: V) -> Expression {
+fn build_number<T: FieldElement, V: Into<T>>(value: V) -> Expression<T> {
     Expression::Number(value.into())
 }
 
-fn extract_update(expr: Expression) -> (Option<String>, Expression) {
+fn extract_update<T: FieldElement>(expr: Expression<T>) -> (Option<String>, Expression<T>) {
     // TODO check that there are no other "next" references in the expression
     if let Expression::BinaryOperation(left, BinaryOperator::Sub, right) = expr {
         if let Expression::PolynomialReference(PolynomialReference {
@@ -844,7 +1907,100 @@ fn extract_update(expr: Expression) -> (Option<String>, Expression) {
     }
 }
 
-fn substitute(input: Expression, substitution: &HashMap<String, String>) -> Expression {
+/// Replaces each polynomial reference in the expression by the result of `f`.
+fn map_references<T: FieldElement>(
+    input: Expression<T>,
+    f: &mut impl FnMut(PolynomialReference<T>) -> Expression<T>,
+) -> Expression<T> {
+    let mut map_boxed = |e: Box<Expression<T>>| Box::new(map_references(*e, f));
+    match input {
+        Expression::PolynomialReference(r) => f(r),
+        Expression::BinaryOperation(left, op, right) => {
+            let left = map_boxed(left);
+            Expression::BinaryOperation(left, op, map_boxed(right))
+        }
+        Expression::UnaryOperation(op, exp) => Expression::UnaryOperation(op, map_boxed(exp)),
+        Expression::FunctionCall(name, args) => Expression::FunctionCall(
+            name,
+            args.into_iter().map(|e| map_references(e, f)).collect(),
+        ),
+        Expression::Tuple(items) => {
+            Expression::Tuple(items.into_iter().map(|e| map_references(e, f)).collect())
+        }
+        Expression::FreeInput(expr) => Expression::FreeInput(map_boxed(expr)),
+        Expression::MatchExpression(scrutinee, arms) => {
+            let scrutinee = map_boxed(scrutinee);
+            Expression::MatchExpression(
+                scrutinee,
+                arms.into_iter()
+                    .map(|(n, e)| (n, map_references(e, f)))
+                    .collect(),
+            )
+        }
+        Expression::Constant(_)
+        | Expression::PublicReference(_)
+        | Expression::Number(_)
+        | Expression::String(_) => input,
+    }
+}
+
+/// Replaces each polynomial reference in the expressions of the definition
+/// or identity by the result of `f`.
+fn map_statement_references<T: FieldElement>(
+    statement: Statement<T>,
+    f: &mut impl FnMut(PolynomialReference<T>) -> Expression<T>,
+) -> Statement<T> {
+    fn map_selected<T: FieldElement>(
+        selected: SelectedExpressions<T>,
+        f: &mut impl FnMut(PolynomialReference<T>) -> Expression<T>,
+    ) -> SelectedExpressions<T> {
+        SelectedExpressions {
+            selector: selected.selector.map(|e| map_references(e, f)),
+            expressions: selected
+                .expressions
+                .into_iter()
+                .map(|e| map_references(e, f))
+                .collect(),
+        }
+    }
+    match statement {
+        Statement::PolynomialDefinition(start, name, value) => {
+            Statement::PolynomialDefinition(start, name, map_references(value, f))
+        }
+        Statement::PolynomialIdentity(start, expr) => {
+            Statement::PolynomialIdentity(start, map_references(expr, f))
+        }
+        Statement::PlookupIdentity(start, left, right) => {
+            let left = map_selected(left, f);
+            Statement::PlookupIdentity(start, left, map_selected(right, f))
+        }
+        Statement::LogUpIdentity(start, left, right, multiplicities) => {
+            let left = map_selected(left, f);
+            let right = map_selected(right, f);
+            Statement::LogUpIdentity(start, left, right, map_references(multiplicities, f))
+        }
+        Statement::PermutationIdentity(start, left, right) => {
+            let left = map_selected(left, f);
+            Statement::PermutationIdentity(start, left, map_selected(right, f))
+        }
+        Statement::ConnectIdentity(start, left, right) => {
+            let mut map_all = |expressions: Vec<Expression<T>>| {
+                expressions
+                    .into_iter()
+                    .map(|e| map_references(e, f))
+                    .collect()
+            };
+            let left = map_all(left);
+            Statement::ConnectIdentity(start, left, map_all(right))
+        }
+        statement => statement,
+    }
+}
+
+fn substitute<T: FieldElement>(
+    input: Expression<T>,
+    substitution: &HashMap<String, String>,
+) -> Expression<T> {
     match input {
         // TODO namespace
         Expression::PolynomialReference(r) => {
@@ -885,20 +2041,20 @@ fn substitute(input: Expression, substitution: &HashMap<String, String>) -> Expr
     }
 }
 
-fn substitute_selected_exprs(
-    input: SelectedExpressions,
+fn substitute_selected_exprs<T: FieldElement>(
+    input: SelectedExpressions<T>,
     substitution: &HashMap<String, String>,
-) -> SelectedExpressions {
+) -> SelectedExpressions<T> {
     SelectedExpressions {
         selector: input.selector.map(|s| substitute(s, substitution)),
         expressions: substitute_vec(input.expressions, substitution),
     }
 }
 
-fn substitute_vec(
-    input: Vec<Expression>,
+fn substitute_vec<T: FieldElement>(
+    input: Vec<Expression<T>>,
     substitution: &HashMap<String, String>,
-) -> Vec<Expression> {
+) -> Vec<Expression<T>> {
     input
         .into_iter()
         .map(|e| substitute(e, substitution))
@@ -916,7 +2072,11 @@ fn substitute_string(input: &str, substitution: &HashMap<String, String>) -> Str
 mod test {
     use std::fs;
 
-    use super::compile;
+    use number::GoldilocksField;
+
+    use super::{
+        compile, compile_with_source_map, resolve_local_labels, ASMPILConverter, CompileOptions,
+    };
 
     #[test]
     pub fn compile_simple_sum() {
@@ -972,7 +2132,461 @@ pol constant p_reg_write_X_CNT = [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0] + [0]*;
 "#;
         let file_name = "../test_data/asm/simple_sum.asm";
         let contents = fs::read_to_string(file_name).unwrap();
-        let pil = compile(Some(file_name), &contents).unwrap();
+        let pil =
+            compile::<GoldilocksField>(Some(file_name), &contents, &Default::default()).unwrap();
         assert_eq!(format!("{pil}").trim(), expectation.trim());
     }
+
+    #[test]
+    pub fn source_map() {
+        let input = r#"
+reg pc[@pc];
+reg X[<=];
+reg A;
+instr loop { pc' = pc }
+.debug file 1 "src" "main.rs";
+A <=X= 1;
+.debug loc 1 4 5;
+A <=X= 2;
+end::
+loop;
+"#;
+        let (_, source_map, _) =
+            compile_with_source_map::<GoldilocksField>(None, input, &Default::default()).unwrap();
+        assert_eq!(source_map.pc.as_deref(), Some("Assembly.pc"));
+        assert_eq!(
+            source_map
+                .locations
+                .iter()
+                .map(|(pc, loc)| format!("{pc} {loc}"))
+                .collect::<Vec<_>>(),
+            vec![
+                "1 src/main.rs:4:5",
+                "2 src/main.rs:4:5",
+                "3 src/main.rs:4:5"
+            ]
+        );
+    }
+
+    #[test]
+    pub fn identity_origins() {
+        let input = r#"
+reg pc[@pc];
+reg X[<=];
+reg A;
+instr loop { pc' = pc }
+instr nonzero X { XInv * X = 1 }
+pil {
+    col witness XInv;
+}
+A <=X= 1;
+loop;
+"#;
+        let (pil, _, origins) =
+            compile_with_source_map::<GoldilocksField>(None, input, &Default::default()).unwrap();
+        let pil = pil.to_string();
+        let lines = pil.lines().collect::<Vec<_>>();
+        let origin_of = |line: &str| {
+            let index = lines.iter().position(|l| *l == line).unwrap();
+            origins.get(&(index + 1)).map(|o| o.as_str())
+        };
+        assert_eq!(
+            origin_of("(instr_nonzero * ((XInv * X) - 1)) = 0;"),
+            Some("instruction `nonzero` at line 6")
+        );
+        assert_eq!(origin_of("pol commit XInv;"), None);
+        assert!(origins.values().any(|o| o == "the lookup into the program"));
+    }
+
+    #[test]
+    pub fn call_and_return() {
+        let input = r#"
+reg pc[@pc];
+reg X[<=];
+reg A;
+call f;
+f::
+A <=X= 1;
+return;
+"#;
+        let pil = compile::<GoldilocksField>(None, input, &Default::default())
+            .unwrap()
+            .to_string();
+        let lines = pil.lines().collect::<Vec<_>>();
+        assert!(lines.contains(&"pol commit return_address;"));
+        assert!(lines.contains(&"pc' = ((1 - first_step') * (((instr_call * instr_call_param_l) + (instr_return * return_address)) + ((1 - (instr_call + instr_return)) * (pc + 1))));"));
+        assert!(lines.contains(&"return_address' = ((((first_step' * 0) + (reg_write_X_return_address * X)) + (instr_call * (pc + 1))) + ((1 - ((first_step' + reg_write_X_return_address) + instr_call)) * return_address));"));
+        assert!(lines.contains(&"pol constant p_instr_call_param_l = [1, 0, 0, 0] + [0]*;"));
+
+        let with_register = input.replace("reg A;", "reg A;\nreg ra[@ra];");
+        let pil = compile::<GoldilocksField>(None, &with_register, &Default::default())
+            .unwrap()
+            .to_string();
+        assert!(pil.contains(
+            "pc' = ((1 - first_step') * (((instr_call * instr_call_param_l) + (instr_return * ra))"
+        ));
+        assert!(!pil.contains("return_address"));
+    }
+
+    #[test]
+    pub fn machine_instances() {
+        let input = r#"
+reg pc[@pc];
+reg X[<=];
+reg Y[<=];
+reg A;
+machine Square(offset) {
+    col witness x, y;
+    y = x * x + offset;
+}
+sq: Square(7);
+instr square X -> Y { { X, Y } is { sq.x, sq.y } }
+A <=Y= square(3);
+"#;
+        let pil = compile::<GoldilocksField>(None, input, &Default::default())
+            .unwrap()
+            .to_string();
+        let sq = pil.split_once("namespace sq(1024);\n").unwrap().1;
+        assert_eq!(
+            sq,
+            "pol commit instr_square;
+(instr_square * (1 - instr_square)) = 0;
+pol commit x, y;
+y = ((x * x) + 7);
+"
+        );
+        assert!(pil.contains("instr_square { X, Y } is sq.instr_square { sq.x, sq.y };"));
+    }
+
+    #[test]
+    #[should_panic = "Machine Square takes 1 arguments, but 0 were given for sq."]
+    pub fn machine_instance_arguments() {
+        let input = "machine Square(offset) { col witness x; }\nsq: Square();";
+        compile::<GoldilocksField>(None, input, &Default::default()).unwrap();
+    }
+
+    #[test]
+    pub fn multiple_outputs() {
+        let input = r#"
+reg pc[@pc];
+reg X[<=];
+reg Y[<=];
+reg Z[<=];
+reg A;
+reg B;
+instr divrem X -> Y, Z { X = 2 * Y + Z }
+A, B <=Y, Z= divrem(7);
+B, A <== divrem(A);
+"#;
+        let pil = compile::<GoldilocksField>(None, input, &Default::default())
+            .unwrap()
+            .to_string();
+        assert!(pil.contains("pol constant p_reg_write_Y_A = [1, 0] + [0]*;"));
+        assert!(pil.contains("pol constant p_reg_write_Z_B = [1, 0] + [0]*;"));
+        assert!(pil.contains("pol constant p_reg_write_Y_B = [0, 1] + [1]*;"));
+        assert!(pil.contains("pol constant p_reg_write_Z_A = [0, 1] + [1]*;"));
+    }
+
+    #[test]
+    #[should_panic = "divrem has 2 outputs, but 1 registers are written."]
+    pub fn missing_output() {
+        let input = "reg X[<=];\nreg Y[<=];\nreg Z[<=];\nreg A;\ninstr divrem X -> Y, Z { X = 2 * Y + Z }\nA <=Y= divrem(7);";
+        compile::<GoldilocksField>(None, input, &Default::default()).unwrap();
+    }
+
+    #[test]
+    pub fn register_defaults() {
+        let input = r#"
+reg pc[@pc];
+reg X[<=];
+reg A;
+reg R[@reset];
+reg F[@free];
+R <=X= A;
+F <=X= A;
+"#;
+        let pil = compile::<GoldilocksField>(None, input, &Default::default())
+            .unwrap()
+            .to_string();
+        let lines = pil.lines().collect::<Vec<_>>();
+        assert!(lines.contains(&"A' = (((first_step' * 0) + (reg_write_X_A * X)) + ((1 - (first_step' + reg_write_X_A)) * A));"));
+        assert!(lines.contains(&"R' = (((first_step' * 0) + (reg_write_X_R * X)) + ((1 - (first_step' + reg_write_X_R)) * 0));"));
+        assert!(lines.contains(&"(first_step' * (F' - 0)) = 0;"));
+        assert!(lines.contains(&"(reg_write_X_F * (F' - X)) = 0;"));
+        assert!(!lines.iter().any(|l| l.starts_with("F' = ")));
+    }
+
+    #[test]
+    pub fn jump_table() {
+        let input = r#"
+reg pc[@pc];
+reg X[<=];
+reg A;
+instr loop { pc' = pc }
+f::
+jump_table dispatch X: .b, a;
+dispatch A;
+.b::
+a::
+loop;
+"#;
+        let pil = compile::<GoldilocksField>(None, input, &Default::default())
+            .unwrap()
+            .to_string();
+        let lines = pil.lines().collect::<Vec<_>>();
+        assert!(lines.contains(&"instr_dispatch { X, jump_table_dispatch_target } in { jump_table_dispatch_index, jump_table_dispatch_pc };"));
+        assert!(lines.contains(&"pol constant jump_table_dispatch_index = [0, 1] + [1]*;"));
+        assert!(lines.contains(&"pol constant jump_table_dispatch_pc = [2, 3] + [3]*;"));
+        assert!(lines.contains(&"pc' = ((1 - first_step') * (((instr_loop * pc) + (instr_dispatch * jump_table_dispatch_target)) + ((1 - (instr_loop + instr_dispatch)) * (pc + 1))));"));
+    }
+
+    #[test]
+    #[should_panic = "Label c of jump table dispatch not found."]
+    pub fn jump_table_missing_label() {
+        let input = "reg pc[@pc];\nreg X[<=];\njump_table dispatch X: c;\n";
+        compile::<GoldilocksField>(None, input, &Default::default()).unwrap();
+    }
+
+    #[test]
+    pub fn halt() {
+        let compile_halt = |on_halt: &str| {
+            let input =
+                format!("reg pc[@pc];\nreg X[<=];\nreg A;\n{on_halt}start::\nA <=X= 1;\nhalt;\n");
+            compile::<GoldilocksField>(None, &input, &Default::default())
+                .unwrap()
+                .to_string()
+        };
+        let pil = compile_halt("");
+        assert!(pil.contains(
+            "pc' = ((1 - first_step') * ((instr_halt * pc) + ((1 - instr_halt) * (pc + 1))));"
+        ));
+        assert!(!pil.contains("is_padding"));
+        let pil = compile_halt("on_halt jump start;\n");
+        assert!(pil.contains("pc' = ((1 - first_step') * ((instr_halt * instr_halt_param_l) + ((1 - instr_halt) * (pc + 1))));"));
+        assert!(pil.contains("pol constant p_instr_halt_param_l = [0, 0, 0] + [0]*;"));
+        let pil = compile_halt("on_halt padding;\n");
+        assert!(pil.contains(
+            "pc' = ((1 - first_step') * ((instr_halt * pc) + ((1 - instr_halt) * (pc + 1))));"
+        ));
+        assert!(pil.contains("is_padding' = ((1 - first_step') * instr_halt);"));
+    }
+
+    #[test]
+    #[should_panic = "on_halt has to come before the first halt."]
+    pub fn on_halt_after_halt() {
+        let input = "reg pc[@pc];\nhalt;\non_halt padding;\n";
+        compile::<GoldilocksField>(None, input, &Default::default()).unwrap();
+    }
+
+    #[test]
+    pub fn entry_points() {
+        let input = r#"
+reg pc[@pc];
+reg X[<=];
+reg A;
+export g;
+export f;
+f::
+A <=X= 1;
+g::
+halt;
+"#;
+        let pil = compile::<GoldilocksField>(None, input, &Default::default())
+            .unwrap()
+            .to_string();
+        let lines = pil.lines().collect::<Vec<_>>();
+        assert!(lines.contains(&"((1 - first_step') * (pc' - ((instr_halt * pc) + ((1 - instr_halt) * (pc + 1))))) = 0;"));
+        assert!(lines.contains(&"pol constant entry_point_pc = [2, 0] + [0]*;"));
+        assert!(lines.contains(
+            &"first_step { entry_point, pc } in { entry_point_number, entry_point_pc };"
+        ));
+        assert!(lines.contains(&"public entry = entry_point(0);"));
+        assert!(lines.contains(&"pol commit entry_point(i) query \"entry_point\";"));
+    }
+
+    #[test]
+    #[should_panic = "Exported label f not found."]
+    pub fn missing_entry_point() {
+        let input = "reg pc[@pc];\nexport f;\nhalt;\n";
+        compile::<GoldilocksField>(None, input, &Default::default()).unwrap();
+    }
+
+    #[test]
+    pub fn read_only_registers() {
+        let input = r#"
+reg pc[@pc];
+reg X[<=];
+reg A;
+reg ZERO[@readonly];
+reg STEP[@readonly = 3];
+A <=X= ZERO + STEP;
+"#;
+        let pil = compile::<GoldilocksField>(None, input, &Default::default())
+            .unwrap()
+            .to_string();
+        let lines = pil.lines().collect::<Vec<_>>();
+        assert!(lines.contains(&"(first_step * ZERO) = 0;"));
+        assert!(lines.contains(&"(first_step * (STEP - 3)) = 0;"));
+        assert!(lines.contains(&"ZERO' = ((first_step' * 0) + ((1 - first_step') * ZERO));"));
+        assert!(lines.contains(&"STEP' = ((first_step' * 3) + ((1 - first_step') * STEP));"));
+        assert!(!pil.contains("reg_write_X_ZERO"));
+    }
+
+    #[test]
+    #[should_panic = "The read-only register ZERO is written at line 5."]
+    pub fn read_only_register_assignment() {
+        let input = "reg pc[@pc];\nreg X[<=];\nreg ZERO[@readonly];\n\nZERO <=X= 1;\n";
+        compile::<GoldilocksField>(None, input, &Default::default()).unwrap();
+    }
+
+    #[test]
+    #[should_panic = "Instruction clear writes the read-only register ZERO."]
+    pub fn read_only_register_instruction() {
+        let input = "reg pc[@pc];\nreg ZERO[@readonly];\ninstr clear { ZERO' = 0 }\n";
+        compile::<GoldilocksField>(None, input, &Default::default()).unwrap();
+    }
+
+    #[test]
+    pub fn local_labels() {
+        let input = r#"
+.start::
+f::
+.loop::
+jmp .loop;
+g::
+.loop::
+jmp .loop;
+call .loop;
+"#;
+        let ast = parser::parse_asm::<GoldilocksField>(None, input).unwrap();
+        let labels = resolve_local_labels(ast.0)
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            [
+                ".start::",
+                "f::",
+                "f.loop::",
+                "jmp f.loop;",
+                "g::",
+                "g.loop::",
+                "jmp g.loop;",
+                "call g.loop;"
+            ]
+        );
+    }
+
+    #[test]
+    pub fn inline_pil_flags() {
+        let input = r#"
+reg pc[@pc];
+reg X[<=];
+reg A;
+instr jmp l: label { pc' = l }
+instr store X { A' = X }
+pil {
+    instr.store * (1 - instr.jmp') * A = 0;
+    instr.store { X } in { A };
+}
+"#;
+        let pil = compile::<GoldilocksField>(None, input, &Default::default())
+            .unwrap()
+            .to_string();
+        let lines = pil.lines().collect::<Vec<_>>();
+        assert!(lines.contains(&"((instr_store * (1 - instr_jmp')) * A) = 0;"));
+        assert!(lines.contains(&"instr_store { X } in { A };"));
+    }
+
+    #[test]
+    #[should_panic = "Instruction store used in inline PIL as instr.store is not declared."]
+    pub fn inline_pil_undeclared_instruction() {
+        let input = "reg pc[@pc];\nreg A;\npil { instr.store * A = 0; }\n";
+        compile::<GoldilocksField>(None, input, &Default::default()).unwrap();
+    }
+
+    #[test]
+    pub fn assert_eq() {
+        let input = r#"
+reg pc[@pc];
+reg X[<=];
+reg A;
+reg B;
+A <=X= 3;
+assert_eq A, B + 1, "A is not B + 1";
+"#;
+        let pil = compile::<GoldilocksField>(None, input, &Default::default())
+            .unwrap()
+            .to_string();
+        let lines = pil.lines().collect::<Vec<_>>();
+        assert!(lines.contains(&"(instr_assert_eq * X) = 0;"));
+        assert!(lines.contains(&r#"pol commit assert_eq_query(i) query match pc { 1 => ("assert", (((1 * A) + (-1 * B)) + -1), "line 7: A is not B + 1"), };"#));
+    }
+
+    #[test]
+    #[should_panic = "assert_eq can only compare registers and constants: not free"]
+    pub fn assert_eq_free_input() {
+        let input =
+            "reg pc[@pc];\nreg X[<=];\nreg A;\nassert_eq A, ${ (\"input\", 0) }, \"not free\";\n";
+        compile::<GoldilocksField>(None, input, &Default::default()).unwrap();
+    }
+
+    fn compile_compressed(input: &str) -> Vec<String> {
+        let options = CompileOptions {
+            compress_rom: true,
+            ..Default::default()
+        };
+        compile::<GoldilocksField>(None, input, &options)
+            .unwrap()
+            .to_string()
+            .lines()
+            .map(|l| l.to_string())
+            .collect()
+    }
+
+    #[test]
+    pub fn compressed_rom() {
+        let input = "reg pc[@pc];\nreg X[<=];\nreg A;\ninstr inc { A' = A + 1 }\ninstr loop { pc' = pc }\ninc;\nloop;\n";
+        let lines = compile_compressed(input);
+        assert!(lines.contains(&"(instr_inc * (1 - instr_inc)) = 0;".to_string()));
+        assert!(lines.contains(&"instr_word_0 = ((1 * instr_inc) + (2 * instr_loop));".to_string()));
+        assert!(lines.contains(&"pol constant p_instr_word_0 = [1, 2] + [2]*;".to_string()));
+        assert!(!lines
+            .iter()
+            .any(|l| l.starts_with("pol constant p_instr_inc")));
+    }
+
+    #[test]
+    pub fn compressed_rom_multiple_words() {
+        let instructions = (0..70)
+            .map(|i| format!("instr i{i} {{ A' = A + {i} }}\ni{i};\n"))
+            .collect::<String>();
+        let lines = compile_compressed(&format!("reg pc[@pc];\nreg A;\n{instructions}"));
+        let words = lines
+            .iter()
+            .filter(|l| l.starts_with("pol constant p_instr_word_"))
+            .collect::<Vec<_>>();
+        assert_eq!(words.len(), 2);
+        assert!(words[1].starts_with(&format!(
+            "pol constant p_instr_word_1 = [{}1, 2, 4, 8, 16, 32, 64]",
+            "0, ".repeat(63)
+        )));
+    }
+
+    #[test]
+    pub fn batched_flags() {
+        let input = "reg pc[@pc];\nreg X[<=];\nreg A;\ninstr inc { A' = A + 1 }\ninstr dec { A' = A - 1 }\ninstr dbl { A' = 2 * A }\ninstr loop { pc' = pc }\ninc;\ndec;\ndbl;\nloop;\n";
+        let mut converter = ASMPILConverter::new(None, input, &Default::default());
+        let ast = parser::parse_asm::<GoldilocksField>(None, input).unwrap();
+        let pil = converter.convert(ast).to_string();
+        let lines = pil.lines().collect::<Vec<_>>();
+        assert!(!pil.contains("pol commit instr_inc;"));
+        assert!(lines.contains(&"pol constant p_instr_batch_0_bit_0 = [1, 0, 1, 0] + [0]*;"));
+        assert!(lines.contains(&"pol constant p_instr_batch_0_bit_1 = [0, 1, 1, 0] + [0]*;"));
+        assert!(pil.contains("(((1 - instr_batch_0_bit_0) * instr_batch_0_bit_1) * (A - 1))"));
+        // Decoding the flag of `loop` would increase the degree of the
+        // update of the pc beyond the maximum.
+        assert!(lines.contains(&"pol commit instr_loop;"));
+    }
 }
@@ -0,0 +1,247 @@
+//! Instances of machines declared with `machine`, placed in their own namespaces.
+
+use std::collections::HashMap;
+
+use number::{DegreeType, FieldElement};
+use parser::ast::*;
+
+use crate::macro_expander::substitute;
+use crate::{build_mul, build_number, build_sub, direct_reference};
+
+/// A machine instantiated with `name: Machine(args);`.
+pub struct MachineInstance<T> {
+    pub name: String,
+    /// The PIL of the machine with the parameters replaced by the arguments.
+    statements: Vec<Statement<T>>,
+    /// Selectors of the permutations from instructions into this instance.
+    selectors: Vec<String>,
+}
+
+impl<T: FieldElement> MachineInstance<T> {
+    pub fn new(
+        name: String,
+        machine: &str,
+        params: &[String],
+        body: &[Statement<T>],
+        args: Vec<Expression<T>>,
+    ) -> Self {
+        assert_eq!(
+            params.len(),
+            args.len(),
+            "Machine {machine} takes {} arguments, but {} were given for {name}.",
+            params.len(),
+            args.len()
+        );
+        let substitution = params.iter().cloned().zip(args).collect();
+        MachineInstance {
+            name,
+            statements: body
+                .iter()
+                .map(|s| substitute_statement(s.clone(), &substitution))
+                .collect(),
+            selectors: vec![],
+        }
+    }
+
+    /// Adds a witness column that selects the rows of this instance that take
+    /// part in a permutation and returns a reference to it.
+    pub fn add_selector(&mut self, name: &str) -> Expression<T> {
+        self.selectors.push(name.to_string());
+        Expression::PolynomialReference(PolynomialReference {
+            namespace: Some(self.name.clone()),
+            name: name.to_string(),
+            index: None,
+            next: false,
+        })
+    }
+
+    /// Returns the PIL of the instance, including its namespace.
+    pub fn into_pil(self, degree: DegreeType) -> Vec<Statement<T>> {
+        let mut pil = vec![Statement::Namespace(
+            0,
+            self.name,
+            Expression::Number(degree.into()),
+        )];
+        if !self.selectors.is_empty() {
+            pil.push(Statement::PolynomialCommitDeclaration(
+                0,
+                self.selectors
+                    .iter()
+                    .map(|name| PolynomialName {
+                        name: name.clone(),
+                        array_size: None,
+                    })
+                    .collect(),
+                None,
+            ));
+        }
+        pil.extend(self.selectors.iter().map(|name| {
+            let selector = direct_reference(name);
+            Statement::PolynomialIdentity(
+                0,
+                build_mul(selector.clone(), build_sub(build_number(1u64), selector)),
+            )
+        }));
+        pil.extend(self.statements);
+        pil
+    }
+}
+
+fn substitute_statement<T: FieldElement>(
+    statement: Statement<T>,
+    substitution: &HashMap<String, Expression<T>>,
+) -> Statement<T> {
+    let substitute_names = |names: Vec<PolynomialName<T>>| {
+        names
+            .into_iter()
+            .map(|name| PolynomialName {
+                array_size: name.array_size.map(|size| substitute(size, substitution)),
+                ..name
+            })
+            .collect()
+    };
+    match statement {
+        Statement::Include(..) | Statement::Namespace(..) => {
+            panic!("Includes and namespaces are not allowed in machines, found {statement}.")
+        }
+        Statement::PolynomialDefinition(start, name, value) => {
+            Statement::PolynomialDefinition(start, name, substitute(value, substitution))
+        }
+        Statement::PublicDeclaration(start, name, poly, index) => {
+            Statement::PublicDeclaration(start, name, poly, substitute(index, substitution))
+        }
+        Statement::PolynomialConstantDeclaration(start, names) => {
+            Statement::PolynomialConstantDeclaration(start, substitute_names(names))
+        }
+        Statement::PolynomialConstantDefinition(start, name, definition) => {
+            Statement::PolynomialConstantDefinition(
+                start,
+                name,
+                substitute_function_definition(definition, substitution),
+            )
+        }
+        Statement::PolynomialCommitDeclaration(start, names, definition) => {
+            Statement::PolynomialCommitDeclaration(
+                start,
+                substitute_names(names),
+                definition.map(|d| substitute_function_definition(d, substitution)),
+            )
+        }
+        Statement::PolynomialIdentity(start, expr) => {
+            Statement::PolynomialIdentity(start, substitute(expr, substitution))
+        }
+        Statement::PlookupIdentity(start, left, right) => Statement::PlookupIdentity(
+            start,
+            substitute_selected(left, substitution),
+            substitute_selected(right, substitution),
+        ),
+        Statement::LogUpIdentity(start, left, right, multiplicities) => Statement::LogUpIdentity(
+            start,
+            substitute_selected(left, substitution),
+            substitute_selected(right, substitution),
+            substitute(multiplicities, substitution),
+        ),
+        Statement::PermutationIdentity(start, left, right) => Statement::PermutationIdentity(
+            start,
+            substitute_selected(left, substitution),
+            substitute_selected(right, substitution),
+        ),
+        Statement::ConnectIdentity(start, left, right) => Statement::ConnectIdentity(
+            start,
+            substitute_vec(left, substitution),
+            substitute_vec(right, substitution),
+        ),
+        Statement::ConstantDefinition(start, name, value) => {
+            Statement::ConstantDefinition(start, name, substitute(value, substitution))
+        }
+        Statement::MacroDefinition(start, name, params, statements, expr) => {
+            let substitution = without(substitution, &params);
+            Statement::MacroDefinition(
+                start,
+                name,
+                params,
+                statements
+                    .into_iter()
+                    .map(|s| substitute_statement(s, &substitution))
+                    .collect(),
+                expr.map(|e| substitute(e, &substitution)),
+            )
+        }
+        Statement::FunctionCall(start, name, args) => {
+            Statement::FunctionCall(start, name, substitute_vec(args, substitution))
+        }
+        Statement::Conditional(..) => {
+            unreachable!("Conditionals are resolved before machines are instantiated.")
+        }
+        Statement::Test(..) => panic!("Tests cannot be declared in machines."),
+    }
+}
+
+fn substitute_function_definition<T: FieldElement>(
+    definition: FunctionDefinition<T>,
+    substitution: &HashMap<String, Expression<T>>,
+) -> FunctionDefinition<T> {
+    match definition {
+        FunctionDefinition::Mapping(params, value) => {
+            let value = substitute(value, &without(substitution, &params));
+            FunctionDefinition::Mapping(params, value)
+        }
+        FunctionDefinition::Query(params, value) => {
+            let value = substitute(value, &without(substitution, &params));
+            FunctionDefinition::Query(params, value)
+        }
+        FunctionDefinition::Array(array) => {
+            FunctionDefinition::Array(substitute_array(array, substitution))
+        }
+    }
+}
+
+fn substitute_array<T: FieldElement>(
+    array: ArrayExpression<T>,
+    substitution: &HashMap<String, Expression<T>>,
+) -> ArrayExpression<T> {
+    match array {
+        ArrayExpression::Value(items) => {
+            ArrayExpression::Value(substitute_vec(items, substitution))
+        }
+        ArrayExpression::RepeatedValue(items) => {
+            ArrayExpression::RepeatedValue(substitute_vec(items, substitution))
+        }
+        ArrayExpression::Concat(left, right) => ArrayExpression::Concat(
+            Box::new(substitute_array(*left, substitution)),
+            Box::new(substitute_array(*right, substitution)),
+        ),
+    }
+}
+
+fn substitute_selected<T: FieldElement>(
+    selected: SelectedExpressions<T>,
+    substitution: &HashMap<String, Expression<T>>,
+) -> SelectedExpressions<T> {
+    SelectedExpressions {
+        selector: selected.selector.map(|s| substitute(s, substitution)),
+        expressions: substitute_vec(selected.expressions, substitution),
+    }
+}
+
+fn substitute_vec<T: FieldElement>(
+    expressions: Vec<Expression<T>>,
+    substitution: &HashMap<String, Expression<T>>,
+) -> Vec<Expression<T>> {
+    expressions
+        .into_iter()
+        .map(|e| substitute(e, substitution))
+        .collect()
+}
+
+/// Returns the substitution without the names that are shadowed by `params`.
+fn without<T: Clone>(
+    substitution: &HashMap<String, Expression<T>>,
+    params: &[String],
+) -> HashMap<String, Expression<T>> {
+    substitution
+        .iter()
+        .filter(|(name, _)| !params.contains(name))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect()
+}
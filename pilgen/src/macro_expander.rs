@@ -0,0 +1,333 @@
+//! Expansion of `%macro` and `%repeat` at compile time.
+
+use std::collections::{BTreeSet, HashMap};
+
+use number::FieldElement;
+use parser::asm_ast::*;
+use parser::ast::*;
+
+/// Replaces macro invocations and repeat blocks by the code they stand for
+/// and removes the macro definitions.
+///
+/// Labels defined inside a macro or repeat body get a unique name in each
+/// expansion, so a macro can be invoked several times.
+pub fn expand<T: FieldElement>(statements: Vec<ASMStatement<T>>) -> Vec<ASMStatement<T>> {
+    let mut expander = MacroExpander::default();
+    for statement in &statements {
+        match statement {
+            ASMStatement::MacroDefinition(_, name, params, body) => {
+                let previous = expander
+                    .macros
+                    .insert(name.clone(), (params.clone(), body.clone()));
+                assert!(previous.is_none(), "Macro {name} is defined twice.");
+            }
+            ASMStatement::InstructionDeclaration(_, name, _, _) => {
+                expander.instructions.insert(name.clone());
+            }
+            _ => {}
+        }
+    }
+    if let Some(name) = expander
+        .macros
+        .keys()
+        .find(|name| expander.instructions.contains(*name))
+    {
+        panic!("{name} is declared both as an instruction and as a macro.");
+    }
+    let mut output = vec![];
+    for statement in statements {
+        match statement {
+            ASMStatement::MacroDefinition(..) => {}
+            statement => expander.expand_statement(statement, &mut output),
+        }
+    }
+    output
+}
+
+#[derive(Default)]
+struct MacroExpander<T> {
+    macros: HashMap<String, (Vec<String>, Vec<ASMStatement<T>>)>,
+    instructions: BTreeSet<String>,
+    /// The macros currently being expanded, to detect recursion.
+    active: Vec<String>,
+    /// The number of bodies expanded so far, used to make labels unique.
+    expansion_count: usize,
+}
+
+impl<T: FieldElement> MacroExpander<T> {
+    fn expand_statement(&mut self, statement: ASMStatement<T>, output: &mut Vec<ASMStatement<T>>) {
+        match statement {
+            ASMStatement::Instruction(_, name, args) if self.macros.contains_key(&name) => {
+                assert!(
+                    !self.active.contains(&name),
+                    "Macro {name} is invoked recursively."
+                );
+                let (params, body) = self.macros[&name].clone();
+                assert_eq!(
+                    params.len(),
+                    args.len(),
+                    "Macro {name} takes {} arguments, but {} were given.",
+                    params.len(),
+                    args.len()
+                );
+                let substitution = params.into_iter().zip(args).collect();
+                self.active.push(name);
+                self.expand_body(body, substitution, output);
+                self.active.pop();
+            }
+            ASMStatement::Repeat(_, var, start, end, body) => {
+                let mut i = start;
+                while i < end {
+                    let substitution = [(var.clone(), Expression::Number(i.clone().into()))]
+                        .into_iter()
+                        .collect();
+                    self.expand_body(body.clone(), substitution, output);
+                    i += 1u32;
+                }
+            }
+            statement => output.push(statement),
+        }
+    }
+
+    /// Expands the body with the names in `substitution` replaced and the
+    /// labels defined in the body renamed.
+    fn expand_body(
+        &mut self,
+        body: Vec<ASMStatement<T>>,
+        mut substitution: HashMap<String, Expression<T>>,
+        output: &mut Vec<ASMStatement<T>>,
+    ) {
+        self.expansion_count += 1;
+        for statement in &body {
+            if let ASMStatement::Label(_, name) = statement {
+                substitution.insert(
+                    name.clone(),
+                    direct_reference(format!("{name}@{}", self.expansion_count)),
+                );
+            }
+        }
+        for statement in body {
+            let statement = substitute_statement(statement, &substitution);
+            self.expand_statement(statement, output);
+        }
+    }
+}
+
+fn substitute_statement<T: FieldElement>(
+    statement: ASMStatement<T>,
+    substitution: &HashMap<String, Expression<T>>,
+) -> ASMStatement<T> {
+    match statement {
+        ASMStatement::Assignment(start, write_regs, assign_reg, value) => ASMStatement::Assignment(
+            start,
+            write_regs
+                .into_iter()
+                .map(|r| substitute_name(r, substitution))
+                .collect(),
+            assign_reg.map(|regs| {
+                regs.into_iter()
+                    .map(|r| substitute_name(r, substitution))
+                    .collect()
+            }),
+            Box::new(substitute(*value, substitution)),
+        ),
+        ASMStatement::Instruction(start, name, args) => ASMStatement::Instruction(
+            start,
+            name,
+            args.into_iter()
+                .map(|a| substitute(a, substitution))
+                .collect(),
+        ),
+        ASMStatement::Call(start, label) => {
+            ASMStatement::Call(start, substitute_name(label, substitution))
+        }
+        ASMStatement::Assert(start, left, right, message) => ASMStatement::Assert(
+            start,
+            substitute(left, substitution),
+            substitute(right, substitution),
+            message,
+        ),
+        ASMStatement::Label(start, name) => {
+            ASMStatement::Label(start, substitute_name(name, substitution))
+        }
+        ASMStatement::Repeat(start, var, from, to, body) => {
+            let mut substitution = substitution.clone();
+            substitution.remove(&var);
+            ASMStatement::Repeat(
+                start,
+                var,
+                from,
+                to,
+                body.into_iter()
+                    .map(|s| substitute_statement(s, &substitution))
+                    .collect(),
+            )
+        }
+        ASMStatement::Return(_) | ASMStatement::Halt(_) | ASMStatement::DebugDirective(..) => {
+            statement
+        }
+        ASMStatement::Degree(..)
+        | ASMStatement::RegisterDeclaration(..)
+        | ASMStatement::InstructionDeclaration(..)
+        | ASMStatement::JumpTable(..)
+        | ASMStatement::OnHalt(..)
+        | ASMStatement::Export(..)
+        | ASMStatement::InlinePil(..)
+        | ASMStatement::MachineDefinition(..)
+        | ASMStatement::MachineInstance(..)
+        | ASMStatement::MacroDefinition(..)
+        | ASMStatement::Conditional(..) => {
+            panic!("Only code is allowed in macros and repeat blocks, found {statement}.")
+        }
+    }
+}
+
+/// Substitutes a name that has to stay a name, like a register or a label.
+fn substitute_name<T: FieldElement>(
+    name: String,
+    substitution: &HashMap<String, Expression<T>>,
+) -> String {
+    match substitution.get(&name) {
+        None => name,
+        Some(Expression::PolynomialReference(PolynomialReference {
+            namespace: None,
+            name,
+            index: None,
+            next: false,
+        })) => name.clone(),
+        Some(e) => panic!("Expected a register or label name to substitute {name}, found {e}."),
+    }
+}
+
+pub fn substitute<T: FieldElement>(
+    input: Expression<T>,
+    substitution: &HashMap<String, Expression<T>>,
+) -> Expression<T> {
+    let substitute_boxed = |e: Box<Expression<T>>| Box::new(substitute(*e, substitution));
+    match input {
+        Expression::PolynomialReference(PolynomialReference {
+            namespace: None,
+            ref name,
+            index: None,
+            next: false,
+        }) => substitution.get(name).cloned().unwrap_or(input),
+        Expression::PolynomialReference(r) => {
+            Expression::PolynomialReference(PolynomialReference {
+                index: r.index.map(substitute_boxed),
+                ..r
+            })
+        }
+        Expression::BinaryOperation(left, op, right) => {
+            Expression::BinaryOperation(substitute_boxed(left), op, substitute_boxed(right))
+        }
+        Expression::UnaryOperation(op, exp) => {
+            Expression::UnaryOperation(op, substitute_boxed(exp))
+        }
+        Expression::FunctionCall(name, args) => Expression::FunctionCall(
+            name,
+            args.into_iter()
+                .map(|e| substitute(e, substitution))
+                .collect(),
+        ),
+        Expression::Tuple(items) => Expression::Tuple(
+            items
+                .into_iter()
+                .map(|e| substitute(e, substitution))
+                .collect(),
+        ),
+        Expression::FreeInput(expr) => Expression::FreeInput(substitute_boxed(expr)),
+        Expression::MatchExpression(scrutinee, arms) => Expression::MatchExpression(
+            substitute_boxed(scrutinee),
+            arms.into_iter()
+                .map(|(n, e)| (n, substitute(e, substitution)))
+                .collect(),
+        ),
+        Expression::Constant(_)
+        | Expression::PublicReference(_)
+        | Expression::Number(_)
+        | Expression::String(_) => input,
+    }
+}
+
+fn direct_reference<T>(name: String) -> Expression<T> {
+    Expression::PolynomialReference(PolynomialReference {
+        namespace: None,
+        name,
+        index: None,
+        next: false,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+
+    use super::*;
+
+    fn expand_source(input: &str) -> String {
+        let ast = parser::parse_asm::<GoldilocksField>(None, input).unwrap();
+        expand(ast.0)
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn macro_with_labels() {
+        let input = r#"
+%macro add_twice(R, v) {
+    R <=X= R + v;
+    jmpz R, done;
+    R <=X= R + v;
+    done::
+}
+add_twice A, 2;
+add_twice B, C;
+"#;
+        assert_eq!(
+            expand_source(input),
+            r#"A <=X= (A + 2);
+jmpz A, done@1;
+A <=X= (A + 2);
+done@1::
+B <=X= (B + C);
+jmpz B, done@2;
+B <=X= (B + C);
+done@2::"#
+        );
+    }
+
+    #[test]
+    fn repeat() {
+        let input = r#"
+%macro push(v) {
+    %repeat i in 0..2 {
+        A <=X= A + v * i;
+    }
+}
+%repeat j in 1..3 {
+    push j;
+}
+"#;
+        assert_eq!(
+            expand_source(input),
+            r#"A <=X= (A + (1 * 0));
+A <=X= (A + (1 * 1));
+A <=X= (A + (2 * 0));
+A <=X= (A + (2 * 1));"#
+        );
+    }
+
+    #[test]
+    #[should_panic = "Macro f is invoked recursively."]
+    fn recursion() {
+        expand_source("%macro f() { f; }\nf;");
+    }
+
+    #[test]
+    #[should_panic = "Macro f takes 1 arguments, but 0 were given."]
+    fn wrong_argument_count() {
+        expand_source("%macro f(x) { A <=X= x; }\nf;");
+    }
+}
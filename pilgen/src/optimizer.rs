@@ -0,0 +1,280 @@
+//! Optimization of powdr assembly before it is converted to PIL.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use number::FieldElement;
+use parser::asm_ast::*;
+use parser::ast::*;
+
+use crate::map_references;
+
+/// Propagates the values of registers that are known to be constant inside
+/// basic blocks, folds arithmetic on constants and removes assignments that
+/// do not change the value of the register or are overwritten before the
+/// register is read. The statements need to be macro-expanded.
+///
+/// Basic blocks end at labels, writes to the pc and everything else that is
+/// not an assignment of a value or an assertion, because instructions can
+/// read and write registers in their bodies. Only the values of registers
+/// without flags or with `@saved` are tracked, and registers referenced by
+/// inline PIL are never treated as unread.
+pub fn optimize<T: FieldElement>(statements: Vec<ASMStatement<T>>) -> Vec<ASMStatement<T>> {
+    let registers = statements
+        .iter()
+        .filter_map(|s| match s {
+            ASMStatement::RegisterDeclaration(_, name, None | Some(RegisterFlag::CalleeSaved)) => {
+                Some(name.clone())
+            }
+            _ => None,
+        })
+        .collect::<BTreeSet<_>>();
+    let pc = statements.iter().find_map(|s| match s {
+        ASMStatement::RegisterDeclaration(_, name, Some(RegisterFlag::IsPC)) => Some(name.clone()),
+        _ => None,
+    });
+    let mut read_by_pil = BTreeSet::new();
+    for statement in &statements {
+        if let ASMStatement::InlinePil(_, pil) = statement {
+            for statement in pil {
+                collect_pil_references(statement, &mut read_by_pil);
+            }
+        }
+    }
+
+    let mut optimized: Vec<Option<ASMStatement<T>>> = vec![];
+    let mut known = BTreeMap::<String, T>::new();
+    // Registers whose last write can be removed if they are written again
+    // before they are read, with the index of the writing statement.
+    let mut unread_writes = BTreeMap::<String, usize>::new();
+    for statement in statements {
+        match statement {
+            ASMStatement::Assignment(start, write_regs, assign_reg, value)
+                if !matches!(*value, Expression::FunctionCall(..))
+                    && !write_regs.iter().any(|r| Some(r) == pc.as_ref()) =>
+            {
+                let value = fold(propagate(*value, &known));
+                let mut reads = BTreeSet::new();
+                collect_references(&value, &mut reads);
+                for reg in &reads {
+                    unread_writes.remove(reg);
+                }
+                if let Expression::Number(n) = value {
+                    if !write_regs.is_empty() && write_regs.iter().all(|r| known.get(r) == Some(&n))
+                    {
+                        continue;
+                    }
+                }
+                for reg in &write_regs {
+                    if let Some(index) = unread_writes.remove(reg) {
+                        optimized[index] = None;
+                    }
+                    match value {
+                        Expression::Number(n) if registers.contains(reg) => {
+                            known.insert(reg.clone(), n)
+                        }
+                        _ => known.remove(reg),
+                    };
+                }
+                if let ([reg], false) = (&write_regs[..], has_free_input(&value)) {
+                    if registers.contains(reg) && !read_by_pil.contains(reg) {
+                        unread_writes.insert(reg.clone(), optimized.len());
+                    }
+                }
+                optimized.push(Some(ASMStatement::Assignment(
+                    start,
+                    write_regs,
+                    assign_reg,
+                    Box::new(value),
+                )));
+            }
+            ASMStatement::Assert(start, left, right, message) => {
+                let left = fold(propagate(left, &known));
+                let right = fold(propagate(right, &known));
+                match (&left, &right) {
+                    (Expression::Number(l), Expression::Number(r)) if l == r => continue,
+                    _ => {}
+                }
+                let mut reads = BTreeSet::new();
+                collect_references(&left, &mut reads);
+                collect_references(&right, &mut reads);
+                for reg in &reads {
+                    unread_writes.remove(reg);
+                }
+                optimized.push(Some(ASMStatement::Assert(start, left, right, message)));
+            }
+            ASMStatement::RegisterDeclaration(..)
+            | ASMStatement::InstructionDeclaration(..)
+            | ASMStatement::JumpTable(..)
+            | ASMStatement::Export(..)
+            | ASMStatement::InlinePil(..)
+            | ASMStatement::MachineDefinition(..)
+            | ASMStatement::MachineInstance(..)
+            | ASMStatement::DebugDirective(..) => optimized.push(Some(statement)),
+            statement => {
+                known.clear();
+                unread_writes.clear();
+                optimized.push(Some(statement));
+            }
+        }
+    }
+    optimized.into_iter().flatten().collect()
+}
+
+/// Replaces the references to registers with known values by the values.
+fn propagate<T: FieldElement>(e: Expression<T>, known: &BTreeMap<String, T>) -> Expression<T> {
+    map_references(e, &mut |r| match known.get(&r.name) {
+        Some(value) if r.namespace.is_none() && r.index.is_none() && !r.next => {
+            Expression::Number(*value)
+        }
+        _ => Expression::PolynomialReference(r),
+    })
+}
+
+/// Evaluates additions, subtractions, multiplications and negations of
+/// numbers. Free inputs are not changed.
+fn fold<T: FieldElement>(e: Expression<T>) -> Expression<T> {
+    match e {
+        Expression::BinaryOperation(left, op, right) => match (fold(*left), op, fold(*right)) {
+            (Expression::Number(l), BinaryOperator::Add, Expression::Number(r)) => {
+                Expression::Number(l + r)
+            }
+            (Expression::Number(l), BinaryOperator::Sub, Expression::Number(r)) => {
+                Expression::Number(l - r)
+            }
+            (Expression::Number(l), BinaryOperator::Mul, Expression::Number(r)) => {
+                Expression::Number(l * r)
+            }
+            (left, op, right) => Expression::BinaryOperation(Box::new(left), op, Box::new(right)),
+        },
+        Expression::UnaryOperation(op, e) => match (op, fold(*e)) {
+            (UnaryOperator::Minus, Expression::Number(n)) => Expression::Number(-n),
+            (UnaryOperator::Plus, Expression::Number(n)) => Expression::Number(n),
+            (op, e) => Expression::UnaryOperation(op, Box::new(e)),
+        },
+        e => e,
+    }
+}
+
+fn has_free_input<T>(e: &Expression<T>) -> bool {
+    match e {
+        Expression::FreeInput(_) => true,
+        Expression::BinaryOperation(left, _, right) => {
+            has_free_input(left) || has_free_input(right)
+        }
+        Expression::UnaryOperation(_, e) => has_free_input(e),
+        Expression::FunctionCall(_, items) | Expression::Tuple(items) => {
+            items.iter().any(has_free_input)
+        }
+        _ => false,
+    }
+}
+
+/// Adds the names of all referenced columns, also in free inputs.
+fn collect_references<T>(e: &Expression<T>, names: &mut BTreeSet<String>) {
+    match e {
+        Expression::PolynomialReference(r) => {
+            names.insert(r.name.clone());
+            if let Some(index) = &r.index {
+                collect_references(index, names);
+            }
+        }
+        Expression::BinaryOperation(left, _, right) => {
+            collect_references(left, names);
+            collect_references(right, names);
+        }
+        Expression::UnaryOperation(_, e) | Expression::FreeInput(e) => collect_references(e, names),
+        Expression::FunctionCall(_, items) | Expression::Tuple(items) => {
+            items.iter().for_each(|e| collect_references(e, names))
+        }
+        Expression::MatchExpression(scrutinee, arms) => {
+            collect_references(scrutinee, names);
+            for (n, e) in arms {
+                if let Some(n) = n {
+                    collect_references(n, names);
+                }
+                collect_references(e, names);
+            }
+        }
+        Expression::Constant(_)
+        | Expression::PublicReference(_)
+        | Expression::Number(_)
+        | Expression::String(_) => {}
+    }
+}
+
+fn collect_pil_references<T>(statement: &Statement<T>, names: &mut BTreeSet<String>) {
+    match statement {
+        Statement::PolynomialDefinition(_, _, e) | Statement::PolynomialIdentity(_, e) => {
+            collect_references(e, names)
+        }
+        Statement::PlookupIdentity(_, left, right)
+        | Statement::PermutationIdentity(_, left, right) => {
+            for selected in [left, right] {
+                for e in selected.selector.iter().chain(&selected.expressions) {
+                    collect_references(e, names);
+                }
+            }
+        }
+        Statement::Conditional(_, _, then, otherwise) => {
+            for statement in then.iter().chain(otherwise) {
+                collect_pil_references(statement, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use number::GoldilocksField;
+
+    use super::*;
+
+    fn optimize_source(code: &str) -> Vec<String> {
+        let input = format!(
+            "reg pc[@pc];\nreg X[<=];\nreg A;\nreg B;\nreg R[@reset];\ninstr loop {{ pc' = pc }}\n{code}"
+        );
+        let ast = parser::parse_asm::<GoldilocksField>(None, &input).unwrap();
+        optimize(ast.0)
+            .iter()
+            .skip(6)
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn propagation_and_folding() {
+        assert_eq!(
+            optimize_source("A <=X= 2 * 3;\nB <=X= A + 1;\nassert_eq B, 7, \"B\";\nloop;\n"),
+            ["A <=X= 6;", "B <=X= 7;", "loop;"]
+        );
+    }
+
+    #[test]
+    fn overwritten_and_unchanged_writes() {
+        assert_eq!(
+            optimize_source("A <=X= 1;\nB <=X= 2;\nA <=X= 3;\nB <=X= B + 0;\nA <=X= 3;\nloop;\n"),
+            ["B <=X= 2;", "A <=X= 3;", "loop;"]
+        );
+    }
+
+    #[test]
+    fn basic_blocks() {
+        assert_eq!(
+            optimize_source(
+                "A <=X= 1;\nloop;\nA <=X= 1;\nl::\nB <=X= A;\nR <=X= 1;\nR <=X= R;\nA <=X= ${ (\"input\", 0) };\nA <=X= 2;\n"
+            ),
+            [
+                "A <=X= 1;",
+                "loop;",
+                "A <=X= 1;",
+                "l::",
+                "B <=X= A;",
+                "R <=X= 1;",
+                "R <=X= R;",
+                "A <=X= ${ (\"input\", 0) };",
+                "A <=X= 2;"
+            ]
+        );
+    }
+}
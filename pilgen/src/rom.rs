@@ -0,0 +1,235 @@
+//! Compressed encodings of the instruction flags in the program ROM.
+
+use std::collections::{HashMap, HashSet};
+
+use number::FieldElement;
+use parser::ast::{
+    BinaryOperator, Expression, PolynomialReference, SelectedExpressions, Statement,
+};
+
+use crate::{
+    build_mul, build_number, build_sub, direct_reference, map_statement_references, next_reference,
+};
+
+/// The number of flags packed into one word, so that all of them can be
+/// decoded through bit constraints without exceeding the field modulus.
+pub fn flags_per_word<T: FieldElement>() -> usize {
+    (T::modulus().bits() - 1) as usize
+}
+
+/// The word and the bit the flag with the given index is encoded in.
+pub fn flag_position<T: FieldElement>(index: usize) -> (usize, usize) {
+    (index / flags_per_word::<T>(), index % flags_per_word::<T>())
+}
+
+pub fn word_name(word: usize) -> String {
+    format!("instr_word_{word}")
+}
+
+/// The largest number of bits per batch of instruction flags that is tried.
+const MAX_FLAG_BATCH_BITS: usize = 8;
+
+/// The batch and the number in the batch of each batched instruction flag.
+pub type FlagBatches = HashMap<String, (usize, usize)>;
+
+/// Chooses the instruction flags that are batched and the number of bits
+/// per batch, or returns `None` if batching saves no committed column.
+///
+/// The flags of instructions are mutually exclusive, because each line of
+/// the program executes at most one instruction. With batching, up to
+/// `2**bits - 1` flags share `bits` committed columns `instr_batch_i_bit_j`
+/// that hold the number of the executed instruction in the batch (starting
+/// at one) in binary and are matched against the program. The flags are
+/// not committed, each reference to a flag is replaced by the product that
+/// decodes it, which increases the degree of the identities by `bits - 1`.
+/// Flags are only batched if the identities they appear in do not exceed
+/// the maximum degree of the identities in `pil` then, so that the backends
+/// do not need a larger degree, and the number of bits is the one that saves
+/// the most committed columns.
+pub fn choose_flag_batches<T: FieldElement>(
+    pil: &[Statement<T>],
+    flags: &[String],
+) -> Option<(usize, FlagBatches)> {
+    // Flags in intermediate columns are not batched, so that the degrees
+    // of the intermediate columns do not change.
+    let mut intermediate_degrees = HashMap::new();
+    let mut candidates = flags.iter().map(|f| f.as_str()).collect::<HashSet<_>>();
+    for statement in pil {
+        if let Statement::PolynomialDefinition(_, name, value) = statement {
+            let degree = expression_degree(value, &|r| {
+                intermediate_degrees.get(&r.name).copied().unwrap_or(1)
+            });
+            intermediate_degrees.insert(name.clone(), degree);
+            for name in referenced_names(statement) {
+                candidates.remove(name.as_str());
+            }
+        }
+    }
+    let identities = pil
+        .iter()
+        .filter(|s| identity_degree(s, &|_| 1).is_some())
+        .map(|s| (s, referenced_names(s)))
+        .collect::<Vec<_>>();
+    let degree = |statement, batched: &HashSet<&str>, bits| {
+        identity_degree(statement, &|r| {
+            if r.namespace.is_none() && batched.contains(r.name.as_str()) {
+                bits
+            } else {
+                intermediate_degrees.get(&r.name).copied().unwrap_or(1)
+            }
+        })
+        .unwrap()
+    };
+    let max_degree = identities
+        .iter()
+        .map(|(s, _)| degree(s, &HashSet::new(), 1))
+        .max()?;
+
+    let mut best: Option<(usize, usize, Vec<&String>)> = None;
+    for bits in 2..=MAX_FLAG_BATCH_BITS {
+        let mut batched = candidates.clone();
+        loop {
+            let too_large = identities
+                .iter()
+                .filter(|(s, _)| degree(s, &batched, bits) > max_degree)
+                .flat_map(|(_, names)| names)
+                .filter(|name| batched.contains(name.as_str()))
+                .collect::<Vec<_>>();
+            if too_large.is_empty() {
+                break;
+            }
+            for name in too_large {
+                batched.remove(name.as_str());
+            }
+        }
+        let mut batched = flags
+            .iter()
+            .filter(|f| batched.contains(f.as_str()))
+            .collect::<Vec<_>>();
+        // A last batch with at most `bits` flags saves nothing.
+        let flags_per_batch = (1 << bits) - 1;
+        let remainder = batched.len() % flags_per_batch;
+        if remainder <= bits {
+            batched.truncate(batched.len() - remainder);
+        }
+        let saved = batched.len() - batched.len().div_ceil(flags_per_batch) * bits;
+        if saved > 0 && best.as_ref().is_none_or(|(best, ..)| saved > *best) {
+            best = Some((saved, bits, batched));
+        }
+    }
+    best.map(|(_, bits, batched)| {
+        let batches = batched
+            .into_iter()
+            .enumerate()
+            .map(|(index, flag)| (flag.clone(), batch_position(index, bits)))
+            .collect();
+        (bits, batches)
+    })
+}
+
+/// The batch of the flag with the given index and its number in the batch.
+pub fn batch_position(index: usize, bits: usize) -> (usize, usize) {
+    let flags_per_batch = (1 << bits) - 1;
+    (index / flags_per_batch, index % flags_per_batch + 1)
+}
+
+pub fn batch_bit_name(batch: usize, bit: usize) -> String {
+    format!("instr_batch_{batch}_bit_{bit}")
+}
+
+/// The expression that is one if the bits of the batch hold `number` and
+/// zero otherwise.
+pub fn decode_flag<T: FieldElement>(
+    batch: usize,
+    number: usize,
+    bits: usize,
+    next: bool,
+) -> Expression<T> {
+    (0..bits)
+        .map(|bit| {
+            let name = batch_bit_name(batch, bit);
+            let value = if next {
+                next_reference(&name)
+            } else {
+                direct_reference(name)
+            };
+            if number & (1 << bit) != 0 {
+                value
+            } else {
+                build_sub(build_number(1u64), value)
+            }
+        })
+        .reduce(build_mul)
+        .unwrap()
+}
+
+/// The names of the columns the statement refers to in its own namespace.
+fn referenced_names<T: FieldElement>(statement: &Statement<T>) -> HashSet<String> {
+    let mut names = HashSet::new();
+    map_statement_references(statement.clone(), &mut |r| {
+        if r.namespace.is_none() {
+            names.insert(r.name.clone());
+        }
+        Expression::PolynomialReference(r)
+    });
+    names
+}
+
+/// The degree of the identity as a polynomial in the columns, like in the
+/// statistics of the analyzer, or `None` if the statement is no identity.
+fn identity_degree<T: FieldElement>(
+    statement: &Statement<T>,
+    reference_degree: &dyn Fn(&PolynomialReference<T>) -> usize,
+) -> Option<usize> {
+    let selected_degree = |selected: &SelectedExpressions<T>| {
+        let selector = selected
+            .selector
+            .as_ref()
+            .map(|selector| expression_degree(selector, reference_degree))
+            .unwrap_or_default();
+        let expressions = selected
+            .expressions
+            .iter()
+            .map(|e| expression_degree(e, reference_degree))
+            .max()
+            .unwrap_or_default();
+        selector + expressions
+    };
+    match statement {
+        Statement::PolynomialIdentity(_, expr) => Some(expression_degree(expr, reference_degree)),
+        Statement::PlookupIdentity(_, left, right)
+        | Statement::LogUpIdentity(_, left, right, _)
+        | Statement::PermutationIdentity(_, left, right) => {
+            Some(selected_degree(left).max(selected_degree(right)))
+        }
+        Statement::ConnectIdentity(_, left, right) => left
+            .iter()
+            .chain(right)
+            .map(|e| expression_degree(e, reference_degree))
+            .max()
+            .or(Some(0)),
+        _ => None,
+    }
+}
+
+fn expression_degree<T: FieldElement>(
+    expr: &Expression<T>,
+    reference_degree: &dyn Fn(&PolynomialReference<T>) -> usize,
+) -> usize {
+    match expr {
+        Expression::PolynomialReference(reference) => reference_degree(reference),
+        Expression::BinaryOperation(left, op, right) => {
+            let left_degree = expression_degree(left, reference_degree);
+            let right_degree = expression_degree(right, reference_degree);
+            match (op, right.as_ref()) {
+                (BinaryOperator::Add | BinaryOperator::Sub, _) => left_degree.max(right_degree),
+                (BinaryOperator::Pow, Expression::Number(exponent)) => {
+                    left_degree * exponent.to_degree() as usize
+                }
+                _ => left_degree + right_degree,
+            }
+        }
+        Expression::UnaryOperation(_, e) => expression_degree(e, reference_degree),
+        _ => 0,
+    }
+}
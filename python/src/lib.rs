@@ -0,0 +1,265 @@
+//! Python bindings for the PIL analyzer and the executor, to prototype
+//! constraint systems in notebooks. Build the `powdr` package with
+//! `maturin develop` in this directory, then:
+//!
+//! ```python
+//! import powdr
+//! pil = powdr.analyze("fibonacci.pil", field="gl")
+//! fixed = pil.fixed_columns()
+//! witness = pil.witness(query=lambda q: 7 if q == '"input", 0' else None)
+//! ```
+//!
+//! Field elements are Python integers. Errors in the PIL files are raised
+//! as `PanicException`.
+
+use std::path::Path;
+
+use number::{AbstractNumberType, BabyBearField, Bn254Field, FieldElement, GoldilocksField};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// Analyzed PIL in one of the supported fields.
+enum AnyAnalyzed {
+    Goldilocks(pil_analyzer::Analyzed<GoldilocksField>),
+    Bn254(pil_analyzer::Analyzed<Bn254Field>),
+    BabyBear(pil_analyzer::Analyzed<BabyBearField>),
+}
+
+/// Runs the body with `$analyzed` bound to the analyzed PIL in its field.
+macro_rules! with_analyzed {
+    ($any:expr, $analyzed:ident => $body:expr) => {
+        match $any {
+            AnyAnalyzed::Goldilocks($analyzed) => $body,
+            AnyAnalyzed::Bn254($analyzed) => $body,
+            AnyAnalyzed::BabyBear($analyzed) => $body,
+        }
+    };
+}
+
+impl AnyAnalyzed {
+    fn new(field: &str, analyze: impl AnalyzeFn) -> PyResult<Self> {
+        match field {
+            "gl" => Ok(AnyAnalyzed::Goldilocks(analyze.analyze()?)),
+            "bn254" => Ok(AnyAnalyzed::Bn254(analyze.analyze()?)),
+            "bb" => Ok(AnyAnalyzed::BabyBear(analyze.analyze()?)),
+            field => Err(PyValueError::new_err(format!(
+                "Unknown field {field}, expected gl, bn254 or bb."
+            ))),
+        }
+    }
+}
+
+/// Analyzes PIL in any field.
+trait AnalyzeFn {
+    fn analyze<T: FieldElement>(self) -> PyResult<pil_analyzer::Analyzed<T>>;
+}
+
+struct File<'a>(&'a str);
+
+impl AnalyzeFn for File<'_> {
+    fn analyze<T: FieldElement>(self) -> PyResult<pil_analyzer::Analyzed<T>> {
+        pil_analyzer::try_analyze(Path::new(self.0))
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+}
+
+struct Contents<'a>(&'a str);
+
+impl AnalyzeFn for Contents<'_> {
+    fn analyze<T: FieldElement>(self) -> PyResult<pil_analyzer::Analyzed<T>> {
+        pil_analyzer::try_analyze_string(self.0).map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+}
+
+/// Analyzed PIL, returned by `analyze` and `analyze_string`.
+#[pyclass(name = "Analyzed", unsendable)]
+pub struct PyAnalyzed {
+    analyzed: AnyAnalyzed,
+}
+
+#[pymethods]
+impl PyAnalyzed {
+    /// The degree of the polynomials, if all of them have the same one.
+    #[getter]
+    fn degree(&self) -> Option<u64> {
+        with_analyzed!(&self.analyzed, analyzed => {
+            let degrees = analyzed
+                .definitions
+                .values()
+                .map(|(poly, _)| poly.degree)
+                .collect::<std::collections::BTreeSet<_>>();
+            (degrees.len() == 1).then(|| *degrees.iter().next().unwrap())
+        })
+    }
+
+    /// Returns the values of the fixed columns by name, in source order.
+    fn fixed_columns(&self, py: Python) -> PyResult<Py<PyDict>> {
+        with_analyzed!(&self.analyzed, analyzed => {
+            let (fixed, _) = executor::constant_evaluator::generate(analyzed)
+                .map_err(PyValueError::new_err)?;
+            columns(py, &fixed)
+        })
+    }
+
+    /// Generates the witness columns and returns their values by name, in
+    /// source order. Prover queries are answered by `query`, which is called
+    /// with the query string and returns an integer or `None`, or from the
+    /// list of `inputs` for `("input", i)` queries.
+    #[pyo3(signature = (query = None, inputs = None))]
+    fn witness(
+        &self,
+        py: Python,
+        query: Option<PyObject>,
+        inputs: Option<Vec<AbstractNumberType>>,
+    ) -> PyResult<Py<PyDict>> {
+        with_analyzed!(&self.analyzed, analyzed => {
+            let (fixed, degree) = executor::constant_evaluator::generate(analyzed)
+                .map_err(PyValueError::new_err)?;
+            let witness = match (query, inputs) {
+                (Some(_), Some(_)) => {
+                    return Err(PyValueError::new_err(
+                        "Only one of query and inputs can be given.",
+                    ))
+                }
+                (Some(query), None) => {
+                    let mut error = None;
+                    let callback = |q: &str| match query
+                        .call1(py, (q,))
+                        .and_then(|value| value.extract::<Option<AbstractNumberType>>(py))
+                    {
+                        Ok(value) => value.map(Into::into),
+                        Err(e) => {
+                            error.get_or_insert(e);
+                            None
+                        }
+                    };
+                    let witness = executor::witgen::generate(analyzed, degree, &fixed, Some(callback));
+                    if let Some(error) = error {
+                        return Err(error);
+                    }
+                    witness
+                }
+                (None, inputs) => executor::witgen::generate(
+                    analyzed,
+                    degree,
+                    &fixed,
+                    Some(executor::witgen::inputs_query_callback(
+                        inputs.unwrap_or_default().into_iter().map(Into::into).collect(),
+                        0,
+                    )),
+                ),
+            };
+            columns(py, &witness)
+        })
+    }
+
+    /// Returns the analyzed PIL in the JSON format of pilcom.
+    fn to_json(&self) -> String {
+        with_analyzed!(&self.analyzed, analyzed => {
+            pil_analyzer::json_exporter::export(analyzed).dump()
+        })
+    }
+
+    /// Returns the dependency graph of the columns in the dot format.
+    fn to_dot(&self) -> String {
+        with_analyzed!(&self.analyzed, analyzed => pil_analyzer::dot_exporter::export(analyzed))
+    }
+
+    fn __str__(&self) -> String {
+        with_analyzed!(&self.analyzed, analyzed => analyzed.to_string())
+    }
+}
+
+/// Returns the columns as a dict from name to list of integers.
+fn columns<T: FieldElement>(py: Python, columns: &[(&str, Vec<T>)]) -> PyResult<Py<PyDict>> {
+    let dict = PyDict::new(py);
+    for (name, values) in columns {
+        dict.set_item(
+            name,
+            values.iter().map(|v| v.to_integer()).collect::<Vec<_>>(),
+        )?;
+    }
+    Ok(dict.into())
+}
+
+/// Analyzes the PIL file and the files it includes.
+#[pyfunction]
+#[pyo3(signature = (file, field = "gl"))]
+fn analyze(file: &str, field: &str) -> PyResult<PyAnalyzed> {
+    Ok(PyAnalyzed {
+        analyzed: AnyAnalyzed::new(field, File(file))?,
+    })
+}
+
+/// Analyzes PIL source code without includes.
+#[pyfunction]
+#[pyo3(signature = (contents, field = "gl"))]
+fn analyze_string(contents: &str, field: &str) -> PyResult<PyAnalyzed> {
+    Ok(PyAnalyzed {
+        analyzed: AnyAnalyzed::new(field, Contents(contents))?,
+    })
+}
+
+#[pymodule]
+fn powdr(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyAnalyzed>()?;
+    m.add_function(wrap_pyfunction!(analyze, m)?)?;
+    m.add_function(wrap_pyfunction!(analyze_string, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FIBONACCI: &str = "namespace Fib(4);
+col fixed ISLAST(i) { match i { 3 => 1, _ => 0 } };
+col witness x, y, z;
+(1 - ISLAST) * (x' - y) = 0;
+(1 - ISLAST) * (y' - (x + y)) = 0;
+ISLAST * (x' - 1) = 0;
+ISLAST * (y' - 1) = 0;
+col witness input(i) query (\"input\", i);
+z = input;";
+
+    fn column(dict: &PyDict, name: &str) -> Vec<u64> {
+        dict.get_item(name).unwrap().extract().unwrap()
+    }
+
+    #[test]
+    fn fixed_and_witness() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let pil = analyze_string(FIBONACCI, "gl").unwrap();
+            assert_eq!(pil.degree(), Some(4));
+            let fixed = pil.fixed_columns(py).unwrap();
+            assert_eq!(column(fixed.as_ref(py), "Fib.ISLAST"), [0, 0, 0, 1]);
+
+            let query = py.eval("lambda q: 7", None, None).unwrap().into();
+            let witness = pil.witness(py, Some(query), None).unwrap();
+            assert_eq!(column(witness.as_ref(py), "Fib.y"), [1, 2, 3, 5]);
+            assert_eq!(column(witness.as_ref(py), "Fib.z"), [7, 7, 7, 7]);
+
+            let inputs = vec![1u32, 2, 3, 4].into_iter().map(Into::into).collect();
+            let witness = pil.witness(py, None, Some(inputs)).unwrap();
+            assert_eq!(column(witness.as_ref(py), "Fib.z"), [1, 2, 3, 4]);
+        });
+    }
+
+    #[test]
+    fn errors() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let error = analyze_string(FIBONACCI, "m61").err().unwrap();
+            assert_eq!(
+                error.value(py).to_string(),
+                "Unknown field m61, expected gl, bn254 or bb."
+            );
+            let pil = analyze_string(FIBONACCI, "bb").unwrap();
+            let query = py.eval("lambda q: 1 // 0", None, None).unwrap().into();
+            let error = pil.witness(py, Some(query), None).unwrap_err();
+            assert!(error.is_instance_of::<pyo3::exceptions::PyZeroDivisionError>(py));
+        });
+    }
+}
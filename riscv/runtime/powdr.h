@@ -0,0 +1,41 @@
+// Runtime glue for C/C++ guests compiled to powdr via clang.
+// This header is written next to the sources by `compile_c_to_riscv_asm`,
+// so guests can just `#include "powdr.h"`.
+#ifndef POWDR_H
+#define POWDR_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+// Returns the free input at position `index` provided by the prover.
+static inline uint32_t get_prover_input(uint32_t index)
+{
+    register uint32_t a0 asm("a0") = index;
+    asm volatile("ecall" : "+r"(a0));
+    return a0;
+}
+
+// Makes the proof fail if the condition does not hold.
+#define powdr_assert(cond)    \
+    do                        \
+    {                         \
+        if (!(cond))          \
+            __builtin_trap(); \
+    } while (0)
+
+// Halts execution (the program counter stays in place).
+static inline void __attribute__((noreturn)) powdr_halt(void)
+{
+    for (;;)
+    {
+    }
+}
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -8,23 +8,35 @@ use crate::{disambiguator, reachability};
 
 use super::parser::Constant;
 
+/// The symbol the linker defines as the value of the global pointer register.
+pub const GLOBAL_POINTER: &str = "__global_pointer$";
+
 /// Compiles riscv assembly to POWDR assembly. Adds required library routines.
 pub fn compile_riscv_asm(mut assemblies: BTreeMap<String, String>) -> String {
     // stack grows towards zero
     let stack_start = 0x10000;
     // data grows away from zero
     let data_start = 0x20000;
+    // Like the linker, we let the global pointer point into the data section
+    // such that the first 4 KiB of data can be accessed relative to it.
+    let global_pointer = data_start + 0x800;
 
     assert!(assemblies
         .insert("__runtime".to_string(), runtime().to_string())
         .is_none());
 
+    let mut source_files = vec![];
     let mut statements = disambiguator::disambiguate(
         assemblies
             .into_iter()
-            .map(|(name, contents)| (name, parser::parse_asm(&contents)))
+            .map(|(name, contents)| {
+                let statements = parser::parse_asm(&contents);
+                (name, resolve_source_files(statements, &mut source_files))
+            })
             .collect(),
     );
+    reject_hardware_float(&statements);
+    replace_global_pointer_references(&mut statements, global_pointer);
     let mut objects = data_parser::extract_data_objects(&statements);
 
     // Reduce to the code that is actually reachable from main
@@ -37,14 +49,20 @@ pub fn compile_riscv_asm(mut assemblies: BTreeMap<String, String>) -> String {
     let (data_code, data_positions) = store_data_objects(&objects, data_start);
 
     preamble()
+        + &source_files
+            .iter()
+            .enumerate()
+            .map(|(i, (dir, file))| format!(".debug file {i} \"{dir}\" \"{file}\";\n"))
+            .collect::<String>()
         + &data_code
             .into_iter()
             .chain([
                 format!("// Set stack pointer\nx2 <=X= {stack_start};"),
+                format!("// Set global pointer\nx3 <=X= {global_pointer};"),
                 "jump main;".to_string(),
             ])
             .chain(
-                insert_data_positions(statements, &data_positions)
+                insert_data_positions(statements, &data_positions, global_pointer)
                     .into_iter()
                     .flat_map(process_statement),
             )
@@ -59,6 +77,82 @@ pub fn compile_riscv_asm(mut assemblies: BTreeMap<String, String>) -> String {
             .join("\n")
 }
 
+/// Removes the `.file` directives with file ids, which are local to each
+/// assembly file, collects the files in `source_files` and changes the file ids
+/// in `.loc` directives to the index in `source_files`. `.loc` directives with
+/// undeclared file ids are removed.
+fn resolve_source_files(
+    statements: Vec<Statement>,
+    source_files: &mut Vec<(String, String)>,
+) -> Vec<Statement> {
+    let mut file_ids = BTreeMap::new();
+    statements
+        .into_iter()
+        .filter_map(|s| match &s {
+            Statement::Directive(dir, args) if dir == ".file" => {
+                if let [Argument::Constant(Constant::Number(id)), Argument::StringLiteral(dir), Argument::StringLiteral(file)] =
+                    &args[..]
+                {
+                    let file = (
+                        String::from_utf8_lossy(dir).to_string(),
+                        String::from_utf8_lossy(file).to_string(),
+                    );
+                    let index = source_files
+                        .iter()
+                        .position(|f| *f == file)
+                        .unwrap_or_else(|| {
+                            source_files.push(file);
+                            source_files.len() - 1
+                        });
+                    file_ids.insert(*id, index as i64);
+                    None
+                } else {
+                    Some(s)
+                }
+            }
+            Statement::Directive(dir, args) if dir == ".loc" => {
+                let [Argument::Constant(Constant::Number(id)), line, column] = &args[..] else {
+                    panic!("Invalid .loc directive: {s}");
+                };
+                let index = *file_ids.get(id)?;
+                Some(Statement::Directive(
+                    dir.clone(),
+                    vec![
+                        Argument::Constant(Constant::Number(index)),
+                        line.clone(),
+                        column.clone(),
+                    ],
+                ))
+            }
+            _ => Some(s),
+        })
+        .collect()
+}
+
+const FLOAT_MNEMONICS: [&str; 32] = [
+    "flw", "fsw", "fld", "fsd", "fadd", "fsub", "fmul", "fdiv", "fsqrt", "fmin", "fmax", "fmadd",
+    "fmsub", "fnmadd", "fnmsub", "fsgnj", "fsgnjn", "fsgnjx", "fneg", "fabs", "fmv", "fcvt", "feq",
+    "flt", "fle", "fclass", "frcsr", "fscsr", "frrm", "fsrm", "frflags", "fsflags",
+];
+
+/// Floating point operations are only supported via soft-float routines
+/// from compiler_builtins, which only use integer instructions.
+fn reject_hardware_float(statements: &[Statement]) {
+    for s in statements {
+        if let Statement::Instruction(instr, _) = s {
+            let mnemonic = instr.split('.').next().unwrap();
+            if FLOAT_MNEMONICS.contains(&mnemonic) {
+                panic!(
+                    "Hardware floating point instruction \"{instr}\" is not supported.\n\
+                    Please compile for a target without the F/D extensions (e.g. riscv32imc \
+                    with the ilp32 ABI) so that floating point operations are lowered to \
+                    calls into the soft-float routines of compiler_builtins."
+                );
+            }
+        }
+    }
+}
+
 /// Replace certain patterns of references to code labels by
 /// special instructions. We ignore any references to data objects
 /// because they will be handled differently.
@@ -191,13 +285,16 @@ fn next_multiple_of_four(x: usize) -> usize {
 fn insert_data_positions(
     mut statements: Vec<Statement>,
     data_positions: &BTreeMap<String, u32>,
+    global_pointer: u32,
 ) -> Vec<Statement> {
     for s in &mut statements {
         let Statement::Instruction(_name, args) = s else { continue; };
         for arg in args {
             match arg {
-                Argument::RegOffset(_, offset) => replace_data_reference(offset, data_positions),
-                Argument::Constant(c) => replace_data_reference(c, data_positions),
+                Argument::RegOffset(_, offset) => {
+                    replace_data_reference(offset, data_positions, global_pointer)
+                }
+                Argument::Constant(c) => replace_data_reference(c, data_positions, global_pointer),
                 _ => {}
             }
         }
@@ -205,7 +302,11 @@ fn insert_data_positions(
     statements
 }
 
-fn replace_data_reference(constant: &mut Constant, data_positions: &BTreeMap<String, u32>) {
+fn replace_data_reference(
+    constant: &mut Constant,
+    data_positions: &BTreeMap<String, u32>,
+    global_pointer: u32,
+) {
     match constant {
         Constant::Number(_) => {}
         Constant::HiDataRef(data) => {
@@ -220,6 +321,37 @@ fn replace_data_reference(constant: &mut Constant, data_positions: &BTreeMap<Str
             }
             // Otherwise, it references a code label
         }
+        Constant::GpRelDataRef(data) => {
+            let pos = data_positions
+                .get(data)
+                .unwrap_or_else(|| panic!("gp-relative reference to non-data symbol {data}"));
+            *constant = Constant::Number(*pos as i64 - global_pointer as i64)
+        }
+    }
+}
+
+/// Replaces `%hi(__global_pointer$)` and `%lo(__global_pointer$)`, which are
+/// used to initialize the global pointer, by the actual value.
+fn replace_global_pointer_references(statements: &mut [Statement], global_pointer: u32) {
+    for s in statements {
+        let Statement::Instruction(_, args) = s else {
+            continue;
+        };
+        for arg in args {
+            let (Argument::Constant(c) | Argument::RegOffset(_, c)) = arg else {
+                continue;
+            };
+            match c {
+                Constant::HiDataRef(sym) if sym == GLOBAL_POINTER => {
+                    *c = Constant::Number((global_pointer >> 12) as i64)
+                }
+                Constant::LoDataRef(sym) if sym == GLOBAL_POINTER => {
+                    *c = Constant::Number((global_pointer & 0xfff) as i64)
+                }
+                Constant::GpRelDataRef(sym) if sym == GLOBAL_POINTER => *c = Constant::Number(0),
+                _ => {}
+            }
+        }
     }
 }
 
@@ -236,7 +368,8 @@ reg tmp3;
 "#
     .to_string()
         + &(0..32)
-            .map(|i| format!("reg x{i};\n"))
+            // x1 is the return address register of `call` and `return`.
+            .map(|i| format!("reg x{i}{};\n", if i == 1 { "[@ra]" } else { "" }))
             .collect::<Vec<_>>()
             .concat()
         + r#"
@@ -312,10 +445,8 @@ instr jump l: label { pc' = l }
 instr load_label l: label -> X { X = l }
 instr jump_dyn X { pc' = X }
 instr jump_and_link_dyn X { pc' = X, x1' = pc + 1 }
-instr call l: label { pc' = l, x1' = pc + 1 }
 // TODO x6 actually stores some relative address, but only part of it.
 instr tail l: label { pc' = l, x6' = l }
-instr ret { pc' = x1 }
 
 instr branch_if_nonzero X, l: label { pc' = (1 - XIsZero) * l + XIsZero * (pc + 1) }
 instr branch_if_zero X, l: label { pc' = XIsZero * l + (1 - XIsZero) * (pc + 1) }
@@ -652,6 +783,12 @@ memcmp@plt:
 fn process_statement(s: Statement) -> Vec<String> {
     match &s {
         Statement::Label(l) => vec![format!("{}::", escape_label(l))],
+        Statement::Directive(dir, args) if dir == ".loc" => {
+            let [file, line, column] = &args[..] else {
+                panic!("Invalid .loc directive: {s}");
+            };
+            vec![format!("  .debug loc {file} {line} {column};")]
+        }
         Statement::Directive(_, _) => panic!(""),
         Statement::Instruction(instr, args) => process_instruction(instr, args)
             .into_iter()
@@ -676,7 +813,7 @@ fn argument_to_number(x: &Argument) -> u32 {
 fn constant_to_number(c: &Constant) -> u32 {
     match c {
         Constant::Number(n) => *n as u32,
-        Constant::HiDataRef(n) | Constant::LoDataRef(n) => {
+        Constant::HiDataRef(n) | Constant::LoDataRef(n) | Constant::GpRelDataRef(n) => {
             panic!("Data reference was not erased during preprocessing: {n}");
         }
     }
@@ -849,6 +986,31 @@ fn process_instruction(instr: &str, args: &[Argument]) -> Vec<String> {
                 format!("{rd} <=X= shr({r1}, tmp1);"),
             ]
         }
+        "srai" => {
+            // arithmetic shift right
+            // If the value is negative, we invert it, shift and invert again.
+            let (rd, rs, amount) = rri(args);
+            assert!(amount <= 31);
+            vec![
+                format!("tmp1 <=Y= is_positive({rs} - 2**31 + 1);"),
+                "tmp1 <=X= tmp1 * 0xffffffff;".to_string(),
+                format!("tmp2 <=X= xor({rs}, tmp1);"),
+                format!("tmp2 <=X= shr(tmp2, {amount});"),
+                format!("{rd} <=X= xor(tmp2, tmp1);"),
+            ]
+        }
+        "sra" => {
+            // arithmetic shift right
+            let (rd, r1, r2) = rrr(args);
+            vec![
+                format!("tmp3 <=X= and({r2}, 0x1f);"),
+                format!("tmp1 <=Y= is_positive({r1} - 2**31 + 1);"),
+                "tmp1 <=X= tmp1 * 0xffffffff;".to_string(),
+                format!("tmp2 <=X= xor({r1}, tmp1);"),
+                "tmp2 <=X= shr(tmp2, tmp3);".to_string(),
+                format!("{rd} <=X= xor(tmp2, tmp1);"),
+            ]
+        }
 
         // comparison
         "seqz" => {
@@ -866,6 +1028,26 @@ fn process_instruction(instr: &str, args: &[Argument]) -> Vec<String> {
                 format!("{rd} <=Y= is_positive({imm} - tmp1);"),
             ]
         }
+        "slt" => {
+            let (rd, r1, r2) = rrr(args);
+            vec![
+                format!("tmp1 <=X= to_signed({r1});"),
+                format!("tmp2 <=X= to_signed({r2});"),
+                format!("{rd} <=Y= is_positive(tmp2 - tmp1);"),
+            ]
+        }
+        "sltz" => {
+            // set if 2**31 <= rs < 2**32
+            let (rd, rs) = rr(args);
+            vec![format!("{rd} <=Y= is_positive({rs} - 2**31 + 1);")]
+        }
+        "sgtz" => {
+            let (rd, rs) = rr(args);
+            vec![
+                format!("tmp1 <=X= to_signed({rs});"),
+                format!("{rd} <=Y= is_positive(tmp1);"),
+            ]
+        }
         "sltiu" => {
             let (rd, rs, imm) = rri(args);
             vec![format!("{rd} <=Y= is_positive({imm} - {rs});")]
@@ -896,6 +1078,14 @@ fn process_instruction(instr: &str, args: &[Argument]) -> Vec<String> {
                 format!("branch_if_positive {r1} + 1, {label};"),
             ]
         }
+        "bleu" => {
+            let (r1, r2, label) = rrl(args);
+            vec![format!("branch_if_positive {r2} - {r1} + 1, {label};")]
+        }
+        "bgtu" => {
+            let (r1, r2, label) = rrl(args);
+            vec![format!("branch_if_positive {r1} - {r2}, {label};")]
+        }
         "bltu" => {
             let (r1, r2, label) = rrl(args);
             vec![format!("branch_if_positive {r2} - {r1}, {label};")]
@@ -920,6 +1110,24 @@ fn process_instruction(instr: &str, args: &[Argument]) -> Vec<String> {
                 format!("branch_if_positive tmp1 - tmp2 + 1, {label};"),
             ]
         }
+        "bgt" => {
+            let (r1, r2, label) = rrl(args);
+            // Branch if r1 > r2 (signed).
+            vec![
+                format!("tmp1 <=X= to_signed({r1});"),
+                format!("tmp2 <=X= to_signed({r2});"),
+                format!("branch_if_positive tmp1 - tmp2, {label};"),
+            ]
+        }
+        "ble" => {
+            let (r1, r2, label) = rrl(args);
+            // Branch if r1 <= r2 (signed).
+            vec![
+                format!("tmp1 <=X= to_signed({r1});"),
+                format!("tmp2 <=X= to_signed({r2});"),
+                format!("branch_if_positive tmp2 - tmp1 + 1, {label};"),
+            ]
+        }
         "bltz" => {
             // branch if 2**31 <= r1 < 2**32
             let (r1, label) = rl(args);
@@ -992,7 +1200,7 @@ fn process_instruction(instr: &str, args: &[Argument]) -> Vec<String> {
         }
         "ret" => {
             assert!(args.is_empty());
-            vec!["ret;".to_string()]
+            vec!["return;".to_string()]
         }
 
         // memory access
@@ -1073,6 +1281,7 @@ fn process_instruction(instr: &str, args: &[Argument]) -> Vec<String> {
             ]
         }
         "unimp" => vec!["fail;".to_string()],
+        "nop" => vec![],
 
         // Special instruction that is inserted to allow dynamic label references
         "load_dynamic" => {
@@ -1085,3 +1294,52 @@ fn process_instruction(instr: &str, args: &[Argument]) -> Vec<String> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn gp_relative_references() {
+        let mut statements = parser::parse_asm(
+            r#"
+  lui gp, %hi(__global_pointer$)
+  addi gp, gp, %lo(__global_pointer$)
+  addi a0, gp, %gprel(x)
+  lw a1, %gprel(y)(gp)
+"#,
+        );
+        let global_pointer = 0x20800;
+        replace_global_pointer_references(&mut statements, global_pointer);
+        let data_positions = [("x".to_string(), 0x20000), ("y".to_string(), 0x20ff0)]
+            .into_iter()
+            .collect();
+        let code = insert_data_positions(statements, &data_positions, global_pointer)
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<String>();
+        assert_eq!(
+            code,
+            "  lui x3, 32\n  addi x3, x3, 2048\n  addi x10, x3, -2048\n  lw x11, 2032(x3)\n"
+        );
+    }
+
+    #[test]
+    fn source_files() {
+        let statements = parser::parse_asm(
+            r#"
+  .file 1 "/src" "main.rs" md5 0x0123456789abcdef0123456789abcdef
+  .loc 1 7 3 prologue_end
+  .loc 2 8 1
+  li a0, 1
+"#,
+        );
+        let mut source_files = vec![];
+        let code = resolve_source_files(statements, &mut source_files)
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<String>();
+        assert_eq!(source_files, [("/src".to_string(), "main.rs".to_string())]);
+        assert_eq!(code, "  .loc 0, 7, 3\n  li x10, 1\n");
+    }
+}
@@ -2,6 +2,7 @@ use std::collections::HashSet;
 
 use itertools::Itertools;
 
+use crate::compiler::GLOBAL_POINTER;
 use crate::parser::{Argument, Constant, Statement};
 
 pub fn disambiguate(assemblies: Vec<(String, Vec<Statement>)>) -> Vec<Statement> {
@@ -109,11 +110,14 @@ fn disambiguate_constant_if_needed(
         Constant::LoDataRef(s) => {
             Constant::LoDataRef(disambiguate_symbol_if_needed(s, prefix, globals))
         }
+        Constant::GpRelDataRef(s) => {
+            Constant::GpRelDataRef(disambiguate_symbol_if_needed(s, prefix, globals))
+        }
     }
 }
 
 fn disambiguate_symbol_if_needed(s: String, prefix: &str, globals: &HashSet<String>) -> String {
-    if globals.contains(s.as_str()) || s.starts_with('@') {
+    if globals.contains(s.as_str()) || s.starts_with('@') || s == GLOBAL_POINTER {
         s
     } else {
         format!("{prefix}__{s}")
@@ -0,0 +1,277 @@
+//! Builds guest crates for the RISC-V target and translates them to powdr
+//! assembly, with a cache of the translations by crate fingerprint.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use ::compiler::is_reproducible;
+use mktemp::Temp;
+use walkdir::WalkDir;
+
+use crate::reproducible_path_prefixes;
+
+/// The Rust target guest crates are built for.
+pub const TARGET: &str = "riscv32imc-unknown-none-elf";
+
+/// A guest crate, or a package of a workspace, and how to build it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GuestCrate {
+    /// The Cargo.toml of the crate or of the workspace.
+    pub manifest_path: PathBuf,
+    /// The package to build, required for workspaces with several packages.
+    pub package: Option<String>,
+    pub features: Vec<String>,
+    pub no_default_features: bool,
+    /// The directory the powdr assembly is cached in, nothing is cached if
+    /// this is not set.
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl GuestCrate {
+    /// The crate (or workspace) with the given Cargo.toml or directory,
+    /// built with the default features.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let manifest_path = if path.is_dir() {
+            path.join("Cargo.toml")
+        } else {
+            path.to_path_buf()
+        };
+        GuestCrate {
+            manifest_path,
+            ..Default::default()
+        }
+    }
+
+    /// The name of the package, or of the directory of the manifest.
+    pub fn name(&self) -> String {
+        self.package.clone().unwrap_or_else(|| {
+            let dir = self.manifest_path.parent().unwrap_or(Path::new(""));
+            fs::canonicalize(dir)
+                .unwrap_or_else(|_| dir.to_path_buf())
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| "guest".to_string())
+        })
+    }
+
+    /// Returns the powdr assembly of the crate, from the cache if the crate
+    /// has not changed since it was cached.
+    pub fn build(&self) -> Result<String, String> {
+        let cache_file = match &self.cache_dir {
+            Some(cache_dir) => {
+                Some(cache_dir.join(format!("{}_{:016x}.asm", self.name(), self.fingerprint()?)))
+            }
+            None => None,
+        };
+        if let Some(cache_file) = &cache_file {
+            if let Ok(powdr_asm) = fs::read_to_string(cache_file) {
+                log::info!("Using powdr assembly from {}.", cache_file.display());
+                return Ok(powdr_asm);
+            }
+        }
+        let powdr_asm = crate::compiler::compile_riscv_asm(self.riscv_asm()?);
+        if let Some(cache_file) = &cache_file {
+            fs::create_dir_all(cache_file.parent().unwrap())
+                .and_then(|_| fs::write(cache_file, &powdr_asm))
+                .map_err(|e| format!("Could not write {}: {e}", cache_file.display()))?;
+            log::info!("Wrote {}.", cache_file.display());
+        }
+        Ok(powdr_asm)
+    }
+
+    /// Builds the crate and returns the RISC-V assembly of the crate and its
+    /// dependencies by file name.
+    pub fn riscv_asm(&self) -> Result<BTreeMap<String, String>, String> {
+        let temp_dir = Temp::new_dir().unwrap();
+
+        let mut rustflags = "--emit=asm".to_string();
+        if is_reproducible() {
+            // The last matching prefix is replaced, so the home directory comes first.
+            let prefixes = std::env::var("HOME")
+                .map(|home| (home, "~"))
+                .into_iter()
+                .chain(reproducible_path_prefixes(
+                    &self.workspace_root()?,
+                    &temp_dir,
+                ));
+            for (from, to) in prefixes {
+                rustflags += &format!(" --remap-path-prefix={from}={to}");
+            }
+        }
+        let mut command = Command::new("cargo");
+        command.env("RUSTFLAGS", rustflags).args([
+            "+nightly",
+            "build",
+            "--release",
+            "-Z",
+            "build-std=core",
+            "--target",
+            TARGET,
+            "--lib",
+        ]);
+        command.arg("--target-dir").arg(&*temp_dir);
+        command.arg("--manifest-path").arg(&self.manifest_path);
+        if let Some(package) = &self.package {
+            command.args(["--package", package]);
+        }
+        if !self.features.is_empty() {
+            command.args(["--features", &self.features.join(",")]);
+        }
+        if self.no_default_features {
+            command.arg("--no-default-features");
+        }
+        let status = command
+            .status()
+            .map_err(|e| format!("Could not run cargo: {e}"))?;
+        if !status.success() {
+            return Err(format!("Building {} failed.", self.manifest_path.display()));
+        }
+
+        let mut assemblies = BTreeMap::new();
+        for entry in WalkDir::new(&temp_dir).sort_by_file_name() {
+            let entry = entry.unwrap();
+            // TODO search only in certain subdir?
+            let file_name = entry.file_name().to_str().unwrap();
+            if let Some(name) = file_name.strip_suffix(".s") {
+                let contents = fs::read_to_string(entry.path()).unwrap();
+                if assemblies.insert(name.to_string(), contents).is_some() {
+                    return Err(format!("Duplicate assembly file name: {name}"));
+                }
+            }
+        }
+        Ok(assemblies)
+    }
+
+    /// Returns a fingerprint of the sources of the workspace, the build
+    /// options and the version of powdr. Path dependencies outside of the
+    /// workspace and the toolchain are not included.
+    pub fn fingerprint(&self) -> Result<u64, String> {
+        let root = self.workspace_root()?;
+        // 64 bit FNV-1a
+        let mut hash = 0xcbf29ce484222325u64;
+        let mut add = |bytes: &[u8]| {
+            // Separates the items, so that their boundaries matter.
+            for byte in bytes.iter().chain(&[0xff]) {
+                hash = (hash ^ *byte as u64).wrapping_mul(0x100000001b3);
+            }
+        };
+        add(env!("CARGO_PKG_VERSION").as_bytes());
+        add(format!("{:?}", self.package).as_bytes());
+        add(self.features.join(",").as_bytes());
+        add(&[self.no_default_features as u8, is_reproducible() as u8]);
+        // The cache may be inside of the workspace.
+        let cache_dir = self
+            .cache_dir
+            .as_ref()
+            .and_then(|dir| fs::canonicalize(dir).ok());
+        let entries = WalkDir::new(&root)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| {
+                let name = entry.file_name().to_string_lossy();
+                entry.depth() == 0
+                    || !(name.starts_with('.')
+                        || name == "target"
+                        || (entry.file_type().is_dir()
+                            && fs::canonicalize(entry.path()).ok() == cache_dir))
+            });
+        for entry in entries {
+            let entry = entry.map_err(|e| e.to_string())?;
+            if entry.file_type().is_file() {
+                let contents = fs::read(entry.path())
+                    .map_err(|e| format!("Could not read {}: {e}", entry.path().display()))?;
+                add(entry
+                    .path()
+                    .strip_prefix(&root)
+                    .unwrap()
+                    .as_os_str()
+                    .as_encoded_bytes());
+                add(&contents);
+            }
+        }
+        Ok(hash)
+    }
+
+    /// The directory of the workspace the crate belongs to.
+    fn workspace_root(&self) -> Result<PathBuf, String> {
+        let output = Command::new("cargo")
+            .args(["locate-project", "--workspace", "--message-format", "plain"])
+            .arg("--manifest-path")
+            .arg(&self.manifest_path)
+            .output()
+            .map_err(|e| format!("Could not run cargo: {e}"))?;
+        if !output.status.success() {
+            return Err(format!(
+                "Could not find the workspace of {}: {}",
+                self.manifest_path.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let manifest = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+        Ok(manifest.parent().unwrap().to_path_buf())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fingerprint() {
+        let dir = Temp::new_dir().unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"guest\"\nversion = \"0.1.0\"\n\n[features]\nfast = []\n",
+        )
+        .unwrap();
+        fs::create_dir(dir.join("src")).unwrap();
+        fs::write(dir.join("src/lib.rs"), "#![no_std]\n").unwrap();
+        let guest = GuestCrate::new(&*dir);
+        assert_eq!(guest.manifest_path, dir.join("Cargo.toml"));
+        let fingerprint = guest.fingerprint().unwrap();
+
+        // Build outputs are ignored.
+        fs::create_dir(dir.join("target")).unwrap();
+        fs::write(dir.join("target/lib.s"), "").unwrap();
+        assert_eq!(guest.fingerprint().unwrap(), fingerprint);
+
+        let with_feature = GuestCrate {
+            features: vec!["fast".to_string()],
+            ..guest.clone()
+        };
+        assert_ne!(with_feature.fingerprint().unwrap(), fingerprint);
+
+        fs::write(dir.join("src/lib.rs"), "#![no_std]\npub fn f() {}\n").unwrap();
+        assert_ne!(guest.fingerprint().unwrap(), fingerprint);
+    }
+
+    #[test]
+    fn cached_build() {
+        let temp_dir = Temp::new_dir().unwrap();
+        let dir = temp_dir.join("guest");
+        fs::create_dir(&dir).unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"guest\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        fs::create_dir(dir.join("src")).unwrap();
+        fs::write(dir.join("src/lib.rs"), "#![no_std]\n").unwrap();
+        let guest = GuestCrate {
+            cache_dir: Some(dir.join("cache")),
+            ..GuestCrate::new(&dir)
+        };
+        assert_eq!(guest.name(), "guest");
+        // A cached translation is used without building the crate.
+        let cache_file = dir.join(format!(
+            "cache/guest_{:016x}.asm",
+            guest.fingerprint().unwrap()
+        ));
+        fs::create_dir(dir.join("cache")).unwrap();
+        fs::write(&cache_file, "machine Main { }").unwrap();
+        assert_eq!(guest.build().unwrap(), "machine Main { }");
+    }
+}
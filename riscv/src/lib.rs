@@ -1,35 +1,116 @@
 //! A RISC-V frontend for powdr
 
-use std::{collections::BTreeMap, path::Path, process::Command};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
-use ::compiler::compile_asm_string;
+use ::compiler::{
+    compile_asm_string, is_reproducible, write_artifact, CompileOptions, OutputLayout,
+};
 use mktemp::Temp;
 use std::fs;
 use walkdir::WalkDir;
 
 use number::FieldElement;
 
+pub use guest::GuestCrate;
+
 pub mod compiler;
 mod data_parser;
 mod disambiguator;
+pub mod guest;
 pub mod parser;
 mod reachability;
 
 /// Compiles a rust file all the way down to PIL and generates
 /// fixed and witness columns.
-pub fn compile_rust(
+pub fn compile_rust<T: FieldElement>(
     file_name: &str,
-    inputs: Vec<FieldElement>,
+    options: &CompileOptions,
+    inputs: Vec<T>,
     output_dir: &Path,
     force_overwrite: bool,
 ) {
-    let riscv_asm = if file_name.ends_with("Cargo.toml") {
-        compile_rust_crate_to_riscv_asm(file_name)
-    } else if fs::metadata(file_name).unwrap().is_dir() {
-        compile_rust_crate_to_riscv_asm(&format!("{file_name}/Cargo.toml"))
+    if file_name.ends_with("Cargo.toml") || fs::metadata(file_name).unwrap().is_dir() {
+        compile_guest(
+            &GuestCrate::new(file_name),
+            options,
+            inputs,
+            output_dir,
+            force_overwrite,
+        )
     } else {
-        compile_rust_to_riscv_asm(file_name)
+        let riscv_asm = compile_rust_to_riscv_asm(file_name);
+        write_riscv_asm_and_compile(
+            file_name,
+            riscv_asm,
+            options,
+            inputs,
+            output_dir,
+            force_overwrite,
+        )
+    }
+}
+
+/// Builds a guest crate (using the cache of the guest, if any) all the way
+/// down to PIL and generates fixed and witness columns. The powdr assembly
+/// file is named after the package.
+pub fn compile_guest<T: FieldElement>(
+    guest: &GuestCrate,
+    options: &CompileOptions,
+    inputs: Vec<T>,
+    output_dir: &Path,
+    force_overwrite: bool,
+) {
+    if !output_dir.exists() {
+        fs::create_dir_all(output_dir).unwrap()
+    }
+    let powdr_asm_file_name = match powdr_asm_file_name(&guest.name(), output_dir, force_overwrite)
+    {
+        Some(file_name) => file_name,
+        None => return,
     };
+    let powdr_asm = guest.build().unwrap_or_else(|e| panic!("{e}"));
+    write_powdr_asm_and_compile(
+        &powdr_asm_file_name,
+        &powdr_asm,
+        options,
+        inputs,
+        output_dir,
+        force_overwrite,
+    )
+}
+
+/// Compiles a C or C++ file (or a directory of such files) all the way down
+/// to PIL and generates fixed and witness columns.
+pub fn compile_c<T: FieldElement>(
+    file_name: &str,
+    options: &CompileOptions,
+    inputs: Vec<T>,
+    output_dir: &Path,
+    force_overwrite: bool,
+) {
+    let riscv_asm = compile_c_to_riscv_asm(file_name);
+    write_riscv_asm_and_compile(
+        file_name,
+        riscv_asm,
+        options,
+        inputs,
+        output_dir,
+        force_overwrite,
+    )
+}
+
+fn write_riscv_asm_and_compile<T: FieldElement>(
+    file_name: &str,
+    riscv_asm: BTreeMap<String, String>,
+    options: &CompileOptions,
+    inputs: Vec<T>,
+    output_dir: &Path,
+    force_overwrite: bool,
+) {
     if !output_dir.exists() {
         fs::create_dir_all(output_dir).unwrap()
     }
@@ -46,20 +127,53 @@ pub fn compile_rust(
             return;
         }
 
-        fs::write(riscv_asm_file_name.clone(), contents).unwrap();
+        write_artifact(&riscv_asm_file_name, contents.as_bytes()).unwrap();
         log::info!("Wrote {}", riscv_asm_file_name.to_str().unwrap());
     }
 
-    compile_riscv_asm_bundle(file_name, riscv_asm, inputs, output_dir, force_overwrite)
+    compile_riscv_asm_bundle(
+        file_name,
+        riscv_asm,
+        options,
+        inputs,
+        output_dir,
+        force_overwrite,
+    )
 }
 
-pub fn compile_riscv_asm_bundle(
+pub fn compile_riscv_asm_bundle<T: FieldElement>(
     original_file_name: &str,
     riscv_asm_files: BTreeMap<String, String>,
-    inputs: Vec<FieldElement>,
+    options: &CompileOptions,
+    inputs: Vec<T>,
     output_dir: &Path,
     force_overwrite: bool,
 ) {
+    let powdr_asm_file_name =
+        match powdr_asm_file_name(original_file_name, output_dir, force_overwrite) {
+            Some(file_name) => file_name,
+            None => return,
+        };
+
+    let powdr_asm = compiler::compile_riscv_asm(riscv_asm_files);
+
+    write_powdr_asm_and_compile(
+        &powdr_asm_file_name,
+        &powdr_asm,
+        options,
+        inputs,
+        output_dir,
+        force_overwrite,
+    )
+}
+
+/// Returns the name of the powdr assembly file for the original file, or
+/// `None` if it already exists and must not be overwritten.
+fn powdr_asm_file_name(
+    original_file_name: &str,
+    output_dir: &Path,
+    force_overwrite: bool,
+) -> Option<PathBuf> {
     let powdr_asm_file_name = output_dir.join(format!(
         "{}.asm",
         Path::new(original_file_name)
@@ -73,29 +187,41 @@ pub fn compile_riscv_asm_bundle(
             "Target file {} already exists. Not overwriting.",
             powdr_asm_file_name.to_str().unwrap()
         );
-        return;
+        return None;
     }
+    Some(powdr_asm_file_name)
+}
 
-    let powdr_asm = compiler::compile_riscv_asm(riscv_asm_files);
-
-    fs::write(powdr_asm_file_name.clone(), &powdr_asm).unwrap();
+fn write_powdr_asm_and_compile<T: FieldElement>(
+    powdr_asm_file_name: &Path,
+    powdr_asm: &str,
+    options: &CompileOptions,
+    inputs: Vec<T>,
+    output_dir: &Path,
+    force_overwrite: bool,
+) {
+    write_artifact(powdr_asm_file_name, powdr_asm.as_bytes()).unwrap();
     log::info!("Wrote {}", powdr_asm_file_name.to_str().unwrap());
 
     compile_asm_string(
         powdr_asm_file_name.to_str().unwrap(),
-        &powdr_asm,
+        powdr_asm,
+        options,
         inputs,
-        output_dir,
+        0,
+        &OutputLayout::new(output_dir),
         force_overwrite,
-    )
+        None,
+    );
 }
 
 /// Compiles a riscv asm file all the way down to PIL and generates
 /// fixed and witness columns.
-pub fn compile_riscv_asm(
+pub fn compile_riscv_asm<T: FieldElement>(
     original_file_name: &str,
     file_name: &str,
-    inputs: Vec<FieldElement>,
+    options: &CompileOptions,
+    inputs: Vec<T>,
     output_dir: &Path,
     force_overwrite: bool,
 ) {
@@ -105,6 +231,7 @@ pub fn compile_riscv_asm(
         vec![(file_name.to_string(), contents)]
             .into_iter()
             .collect(),
+        options,
         inputs,
         output_dir,
         force_overwrite,
@@ -139,42 +266,100 @@ edition = "2021"
     compile_rust_crate_to_riscv_asm(cargo_file.to_str().unwrap())
 }
 
+/// Builds the crate with the given Cargo.toml for the RISC-V target and
+/// returns the RISC-V assembly by file name, see `GuestCrate::riscv_asm`.
 pub fn compile_rust_crate_to_riscv_asm(input_dir: &str) -> BTreeMap<String, String> {
-    let temp_dir = Temp::new_dir().unwrap();
+    GuestCrate::new(input_dir)
+        .riscv_asm()
+        .unwrap_or_else(|e| panic!("{e}"))
+}
 
-    let cargo_status = Command::new("cargo")
-        .env("RUSTFLAGS", "--emit=asm")
-        .args([
-            "+nightly",
-            "build",
-            "--release",
-            "-Z",
-            "build-std=core",
-            "--target",
-            "riscv32imc-unknown-none-elf",
-            "--lib",
-            "--target-dir",
-            temp_dir.to_str().unwrap(),
-            "--manifest-path",
-            input_dir,
-        ])
-        .status()
-        .unwrap();
-    assert!(cargo_status.success());
+/// Compiles a C/C++ source file or all C/C++ source files in a directory
+/// to riscv assembly using clang. The `powdr.h` runtime header is available
+/// to the sources.
+pub fn compile_c_to_riscv_asm(input: &str) -> BTreeMap<String, String> {
+    let sources: Vec<_> = if fs::metadata(input).unwrap().is_dir() {
+        WalkDir::new(input)
+            .sort_by_file_name()
+            .into_iter()
+            .map(|e| e.unwrap().into_path())
+            .filter(|p| is_c_source(p))
+            .collect()
+    } else {
+        vec![Path::new(input).to_path_buf()]
+    };
+    assert!(
+        !sources.is_empty(),
+        "No C/C++ source files found in {input}"
+    );
+
+    let temp_dir = Temp::new_dir().unwrap();
+    fs::write(temp_dir.join("powdr.h"), include_str!("../runtime/powdr.h")).unwrap();
 
     let mut assemblies = BTreeMap::new();
-    for entry in WalkDir::new(&temp_dir) {
-        let entry = entry.unwrap();
-        // TODO search only in certain subdir?
-        let file_name = entry.file_name().to_str().unwrap();
-        if let Some(name) = file_name.strip_suffix(".s") {
-            assert!(
-                assemblies
-                    .insert(name.to_string(), fs::read_to_string(entry.path()).unwrap())
-                    .is_none(),
-                "Duplicate assembly file name: {name}"
-            );
-        }
+    for source in sources {
+        let name = source.file_stem().unwrap().to_str().unwrap().to_string();
+        let output = temp_dir.join(format!("{name}.s"));
+        let source_dir = source.parent().unwrap();
+        let prefix_maps = if is_reproducible() {
+            reproducible_path_prefixes(source_dir, &temp_dir)
+                .into_iter()
+                .map(|(from, to)| format!("-ffile-prefix-map={from}={to}"))
+                .collect()
+        } else {
+            vec![]
+        };
+        let clang_status = Command::new("clang")
+            .args(prefix_maps)
+            .args([
+                "--target=riscv32-unknown-none-elf",
+                "-march=rv32imc",
+                "-mabi=ilp32",
+                "-O2",
+                "-ffreestanding",
+                "-nostdlib",
+                "-fno-pic",
+                "-S",
+                "-I",
+                temp_dir.to_str().unwrap(),
+                "-o",
+                output.to_str().unwrap(),
+                source.to_str().unwrap(),
+            ])
+            .status()
+            .unwrap();
+        assert!(clang_status.success());
+        assert!(
+            assemblies
+                .insert(name.clone(), fs::read_to_string(&output).unwrap())
+                .is_none(),
+            "Duplicate assembly file name: {name}"
+        );
     }
     assemblies
 }
+
+/// Returns the directories whose paths differ between runs or machines and
+/// the fixed paths that replace them in the debug information (and thus in
+/// the `.file` directives) in reproducible mode.
+fn reproducible_path_prefixes(source_dir: &Path, temp_dir: &Path) -> Vec<(String, &'static str)> {
+    let absolute = |dir: &Path| {
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        dir.canonicalize()
+            .unwrap_or_else(|_| dir.to_path_buf())
+            .to_string_lossy()
+            .to_string()
+    };
+    vec![(absolute(source_dir), "/src"), (absolute(temp_dir), "/tmp")]
+}
+
+fn is_c_source(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("c" | "cc" | "cpp" | "cxx")
+    )
+}
@@ -35,6 +35,8 @@ pub enum Constant {
     Number(i64),
     HiDataRef(String),
     LoDataRef(String),
+    /// Offset of the symbol relative to the global pointer.
+    GpRelDataRef(String),
 }
 
 impl Display for Statement {
@@ -66,6 +68,7 @@ impl Display for Constant {
             Constant::Number(n) => write!(f, "{n}"),
             Constant::HiDataRef(sym) => write!(f, "%hi({sym})"),
             Constant::LoDataRef(sym) => write!(f, "%lo({sym})"),
+            Constant::GpRelDataRef(sym) => write!(f, "%gprel({sym})"),
         }
     }
 }
@@ -85,20 +88,26 @@ impl Display for Register {
 
 pub fn parse_asm(input: &str) -> Vec<Statement> {
     let parser = riscv_asm::MaybeStatementParser::new();
-    input
-        .split('\n')
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty())
-        .flat_map(|line| {
-            parser
-                .parse(line)
-                .map_err(|err| {
-                    handle_parse_error(err, None, line).output_to_stderr();
-                    panic!("RISCV assembly parse error");
-                })
-                .unwrap()
-        })
-        .collect()
+    let mut statements = vec![];
+    let mut line_start = 0;
+    for line in input.split('\n') {
+        // The offset of the trimmed line, so that errors point into the input.
+        let offset = line_start + line.len() - line.trim_start().len();
+        line_start += line.len() + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match parser.parse(line) {
+            Ok(statement) => statements.extend(statement),
+            Err(err) => {
+                let err = err.map_location(|location| location + offset);
+                handle_parse_error(err, None, input).output_to_stderr();
+                panic!("RISCV assembly parse error");
+            }
+        }
+    }
+    statements
 }
 
 /// Parse an escaped string - used in the grammar.
@@ -8,6 +8,8 @@ pub fn filter_reachable_from(
     statements: &mut Vec<Statement>,
     objects: &mut BTreeMap<String, Vec<DataValue>>,
 ) {
+    let instruction_count = count_instructions(statements);
+    let object_count = objects.len();
     let replacements = extract_replacements(statements);
     let label_offsets = extract_label_offsets(statements);
     let mut queued_labels: BTreeSet<&str> = vec![label].into_iter().collect();
@@ -75,6 +77,18 @@ pub fn filter_reachable_from(
         apply_replacement_to_object(value, &replacements)
     }
     *statements = code;
+    log::info!(
+        "Removed {} of {instruction_count} instructions and {} of {object_count} data objects not reachable from {label}.",
+        instruction_count - count_instructions(statements),
+        object_count - objects.len()
+    );
+}
+
+fn count_instructions(statements: &[Statement]) -> usize {
+    statements
+        .iter()
+        .filter(|s| matches!(s, Statement::Instruction(_, _)))
+        .count()
 }
 
 fn extract_replacements(statements: &[Statement]) -> BTreeMap<&str, &str> {
@@ -124,7 +138,9 @@ pub fn references_in_statement(statement: &Statement) -> BTreeSet<&str> {
                 Argument::Symbol(s) => Some(s.as_str()),
                 Argument::RegOffset(_, c) | Argument::Constant(c) => match c {
                     Constant::Number(_) => None,
-                    Constant::HiDataRef(s) | Constant::LoDataRef(s) => Some(s.as_str()),
+                    Constant::HiDataRef(s) | Constant::LoDataRef(s) | Constant::GpRelDataRef(s) => {
+                        Some(s.as_str())
+                    }
                 },
                 Argument::Difference(_, _) => todo!(),
             })
@@ -148,8 +164,11 @@ fn basic_block_references_starting_from(statements: &[Statement]) -> (Vec<&str>,
 fn basic_block_code_starting_from(statements: &[Statement]) -> Vec<Statement> {
     let mut code = vec![];
     iterate_basic_block(statements, |s| {
-        if let Statement::Directive(_, _) = s {
-            panic!("Included directive in code block: {s}");
+        match s {
+            // Source locations are kept to annotate the generated code.
+            Statement::Directive(dir, _) if dir == ".loc" => {}
+            Statement::Directive(_, _) => panic!("Included directive in code block: {s}"),
+            _ => {}
         }
         code.push(s.clone());
     });
@@ -172,9 +191,9 @@ fn ends_control_flow(s: &Statement) -> bool {
             | "xori" | "and" | "andi" | "or" | "ori" | "not" | "slli" | "sll" | "srli" | "srl"
             | "seqz" | "snez" | "slti" | "sltu" | "sltiu" | "beq" | "beqz" | "bgeu" | "bltu"
             | "blt" | "bge" | "bltz" | "blez" | "bgtz" | "bgez" | "bne" | "bnez" | "jal"
-            | "jalr" | "call" | "ecall" | "ebreak" | "lw" | "lb" | "lbu" | "sw" | "sh" | "sb" => {
-                false
-            }
+            | "jalr" | "call" | "ecall" | "ebreak" | "lw" | "lb" | "lbu" | "sw" | "sh" | "sb"
+            | "nop" | "srai" | "sra" | "slt" | "sltz" | "sgtz" | "bleu" | "bgtu" | "bgt"
+            | "ble" => false,
             "j" | "jr" | "tail" | "ret" | "unimp" => true,
             _ => {
                 panic!("Unknown instruction: {instruction}");
@@ -189,7 +208,7 @@ fn apply_replacement_to_instruction(
     replacements: &BTreeMap<&str, &str>,
 ) -> Statement {
     match statement {
-        Statement::Label(_) => statement,
+        Statement::Label(_) | Statement::Directive(_, _) => statement,
         Statement::Instruction(instr, args) => Statement::Instruction(
             instr,
             args.into_iter()
@@ -208,7 +227,6 @@ fn apply_replacement_to_instruction(
                 })
                 .collect(),
         ),
-        _ => panic!("Expected instruction but got: {statement}"),
     }
 }
 
@@ -217,6 +235,7 @@ fn apply_replacement_to_constant(c: Constant, replacements: &BTreeMap<&str, &str
         Constant::Number(_) => c,
         Constant::HiDataRef(s) => Constant::HiDataRef(replace(s, replacements)),
         Constant::LoDataRef(s) => Constant::LoDataRef(replace(s, replacements)),
+        Constant::GpRelDataRef(s) => Constant::GpRelDataRef(replace(s, replacements)),
     }
 }
 
@@ -236,3 +255,47 @@ fn replace(s: String, replacements: &BTreeMap<&str, &str>) -> String {
         None => s,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{data_parser::extract_data_objects, parser::parse_asm};
+
+    #[test]
+    fn removes_unreachable_code_and_data() {
+        let input = r#"
+  .type used,@object
+used:
+  .word other
+  .size used, 4
+  .type other,@object
+other:
+  .word 7
+  .size other, 4
+  .type unused,@object
+unused:
+  .word 8
+  .size unused, 4
+main:
+  lui a0, %hi(used)
+  call f
+  ret
+f:
+  li a0, 1
+  ret
+g:
+  lui a0, %hi(unused)
+  call f
+  ret
+"#;
+        let mut statements = parse_asm(input);
+        let mut objects = extract_data_objects(&statements);
+        filter_reachable_from("main", &mut statements, &mut objects);
+        assert_eq!(objects.keys().collect::<Vec<_>>(), vec!["other", "used"]);
+        let code = statements.iter().map(|s| s.to_string()).collect::<String>();
+        assert_eq!(
+            code,
+            "f:\n  li x10, 1\n  ret \nmain:\n  lui x10, %hi(used)\n  call f\n  ret \n"
+        );
+    }
+}
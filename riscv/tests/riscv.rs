@@ -1,5 +1,5 @@
 use compiler::compile_asm_string_temp;
-use number::FieldElement;
+use number::GoldilocksField;
 
 #[test]
 #[ignore = "Too slow"]
@@ -50,6 +50,13 @@ fn test_double_word() {
     );
 }
 
+#[test]
+#[ignore = "Too slow"]
+fn test_float() {
+    let case = "float.rs";
+    verify_file(case, [10, 5, 10].iter().map(|&x| x.into()).collect());
+}
+
 #[test]
 #[ignore = "Too slow"]
 fn test_keccak() {
@@ -57,7 +64,24 @@ fn test_keccak() {
     verify_crate(case, vec![]);
 }
 
-fn verify_file(case: &str, inputs: Vec<FieldElement>) {
+#[test]
+#[ignore = "Too slow"]
+fn test_sum_c() {
+    let case = "sum.c";
+    let riscv_asm = riscv::compile_c_to_riscv_asm(&format!("tests/riscv_data/{case}"));
+    let powdr_asm = riscv::compiler::compile_riscv_asm(riscv_asm);
+
+    compile_asm_string_temp(
+        &format!("{case}.asm"),
+        &powdr_asm,
+        [16, 4, 1, 2, 8, 5]
+            .iter()
+            .map(|&x| x.into())
+            .collect::<Vec<GoldilocksField>>(),
+    );
+}
+
+fn verify_file(case: &str, inputs: Vec<GoldilocksField>) {
     let riscv_asm = riscv::compile_rust_to_riscv_asm(&format!("tests/riscv_data/{case}"));
     let powdr_asm = riscv::compiler::compile_riscv_asm(riscv_asm);
 
@@ -65,7 +89,7 @@ fn verify_file(case: &str, inputs: Vec<FieldElement>) {
     // TODO eventually, also verify the PIL again with pilcom.
 }
 
-fn verify_crate(case: &str, inputs: Vec<FieldElement>) {
+fn verify_crate(case: &str, inputs: Vec<GoldilocksField>) {
     let riscv_asm =
         riscv::compile_rust_crate_to_riscv_asm(&format!("tests/riscv_data/{case}/Cargo.toml"));
     let powdr_asm = riscv::compiler::compile_riscv_asm(riscv_asm);
@@ -0,0 +1,21 @@
+#![no_std]
+
+use core::arch::asm;
+
+#[no_mangle]
+pub extern "C" fn main() -> ! {
+    let a = get_prover_input(0) as f32;
+    let b = get_prover_input(1) as f32;
+    let c = (a * 1.5 + b) / 2.0;
+    assert!(c as u32 == get_prover_input(2));
+    loop {}
+}
+
+#[inline]
+fn get_prover_input(index: u32) -> u32 {
+    let mut value: u32;
+    unsafe {
+        asm!("ecall", lateout("a0") value, in("a0") index);
+    }
+    value
+}
@@ -0,0 +1,20 @@
+#include "powdr.h"
+
+int main(void)
+{
+    uint32_t buffer[100];
+    uint32_t proposed_sum = get_prover_input(0);
+    uint32_t len = get_prover_input(1);
+    powdr_assert(len > 0 && len < 100);
+    for (uint32_t i = 0; i < len; i++)
+    {
+        buffer[i] = get_prover_input(2 + i);
+    }
+    uint32_t sum = 0;
+    for (uint32_t i = 0; i < len; i++)
+    {
+        sum += buffer[i];
+    }
+    powdr_assert(sum == proposed_sum);
+    powdr_halt();
+}
@@ -0,0 +1,304 @@
+//! Conformance tests using the official riscv-tests ISA suite.
+//!
+//! Set the `RISCV_TESTS` environment variable to the path of a checkout of
+//! https://github.com/riscv-software-src/riscv-tests and run
+//! `cargo test --test riscv_tests -- --ignored`.
+//!
+//! Each test is preprocessed with our own environment header
+//! (`tests/riscv_tests_env/riscv_test.h`), translated to powdr assembly
+//! and witness generation is run on it. A test that jumps to `fail` executes
+//! `unimp`, which makes witness generation fail.
+
+use std::{collections::BTreeMap, path::Path, process::Command};
+
+use compiler::compile_asm_string_temp;
+use number::GoldilocksField;
+
+/// The tests from the rv32ui and rv32um suites that only use
+/// instructions supported by the frontend.
+const SUPPORTED_TESTS: [&str; 26] = [
+    "rv32ui/add",
+    "rv32ui/addi",
+    "rv32ui/and",
+    "rv32ui/andi",
+    "rv32ui/beq",
+    "rv32ui/bge",
+    "rv32ui/bgeu",
+    "rv32ui/blt",
+    "rv32ui/bltu",
+    "rv32ui/bne",
+    "rv32ui/lui",
+    "rv32ui/or",
+    "rv32ui/ori",
+    "rv32ui/sll",
+    "rv32ui/slli",
+    "rv32ui/slti",
+    "rv32ui/sltiu",
+    "rv32ui/sltu",
+    "rv32ui/srl",
+    "rv32ui/srli",
+    "rv32ui/sub",
+    "rv32ui/xor",
+    "rv32ui/xori",
+    "rv32ui/simple",
+    "rv32um/mul",
+    "rv32um/mulhu",
+];
+
+#[test]
+#[ignore = "Needs RISCV_TESTS"]
+fn riscv_tests_conformance() {
+    let suite = std::env::var("RISCV_TESTS").expect(
+        "Please set the RISCV_TESTS environment variable to the path to the riscv-tests repository.",
+    );
+    let failures = SUPPORTED_TESTS
+        .iter()
+        .filter(|name| {
+            let path = format!("{suite}/isa/{name}.S");
+            let result = std::panic::catch_unwind(|| run_test(name, &suite, &path));
+            if result.is_err() {
+                eprintln!("riscv-tests: {name} FAILED");
+            }
+            result.is_err()
+        })
+        .collect::<Vec<_>>();
+    assert!(
+        failures.is_empty(),
+        "{} of {} riscv-tests failed: {failures:?}",
+        failures.len(),
+        SUPPORTED_TESTS.len()
+    );
+}
+
+fn run_test(name: &str, suite: &str, path: &str) {
+    let riscv_asm = normalize(&preprocess(suite, path));
+    let file_name = Path::new(name).file_name().unwrap().to_str().unwrap();
+    let powdr_asm =
+        riscv::compiler::compile_riscv_asm(BTreeMap::from([(file_name.to_string(), riscv_asm)]));
+    compile_asm_string_temp(
+        &format!("{file_name}.asm"),
+        &powdr_asm,
+        Vec::<GoldilocksField>::new(),
+    );
+}
+
+/// Runs the C preprocessor on a test file using our environment header.
+fn preprocess(suite: &str, path: &str) -> String {
+    let output = Command::new("cpp")
+        .args([
+            "-P",
+            "-x",
+            "assembler-with-cpp",
+            "-D__riscv_xlen=32",
+            "-I",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/riscv_tests_env"),
+            "-I",
+            &format!("{suite}/isa/macros/scalar"),
+            path,
+        ])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Turns the preprocessed test into something the riscv parser understands:
+/// One statement per line, unique names for numeric local labels and
+/// constant expressions folded into numbers.
+fn normalize(asm: &str) -> String {
+    let lines = asm
+        .lines()
+        .flat_map(|l| l.split(';'))
+        .flat_map(split_label)
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>();
+
+    // Numeric labels can be defined multiple times, `1b` refers to the closest
+    // previous definition of `1` and `1f` to the closest following one.
+    let local_label_name = |n: &str, i: usize| format!("__local_{n}_{i}");
+    let definitions = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, l)| {
+            let n = l.strip_suffix(':')?;
+            n.chars()
+                .all(|c| c.is_ascii_digit())
+                .then(|| (n.to_string(), i))
+        })
+        .collect::<Vec<_>>();
+
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, l)| {
+            if let Some((n, _)) = definitions.iter().find(|(_, j)| *j == i) {
+                return format!("{}:", local_label_name(n, i));
+            }
+            let Some((instr, args)) = l.split_once(char::is_whitespace) else {
+                return l.clone();
+            };
+            let args = args
+                .split(',')
+                .map(|arg| {
+                    let arg = arg.trim();
+                    if let Some(n) = arg.strip_suffix('b').filter(|n| is_number(n)) {
+                        let (_, j) = definitions
+                            .iter()
+                            .rev()
+                            .find(|(m, j)| m == n && *j < i)
+                            .unwrap();
+                        local_label_name(n, *j)
+                    } else if let Some(n) = arg.strip_suffix('f').filter(|n| is_number(n)) {
+                        let (_, j) = definitions.iter().find(|(m, j)| m == n && *j > i).unwrap();
+                        local_label_name(n, *j)
+                    } else if let Some(value) = evaluate(arg) {
+                        value.to_string()
+                    } else {
+                        arg.to_string()
+                    }
+                })
+                .collect::<Vec<_>>();
+            format!("{instr} {}", args.join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn is_number(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Splits `label: statement` into two lines.
+fn split_label(line: &str) -> Vec<&str> {
+    match line.split_once(':') {
+        Some((label, rest))
+            if !label.trim().is_empty()
+                && label
+                    .trim()
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') =>
+        {
+            vec![&line[..label.len() + 1], rest]
+        }
+        _ => vec![line],
+    }
+}
+
+/// Evaluates a constant expression as produced by the macros in the test suite.
+/// Returns None if the argument is not a constant expression.
+fn evaluate(arg: &str) -> Option<i64> {
+    let tokens = tokenize(arg)?;
+    let (value, rest) = parse_binary(&tokens, 0)?;
+    rest.is_empty().then_some(value)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Token {
+    Number(i64),
+    Operator(&'static str),
+    Open,
+    Close,
+}
+
+fn tokenize(mut s: &str) -> Option<Vec<Token>> {
+    const OPERATORS: [&str; 8] = ["<<", ">>", "+", "-", "&", "|", "^", "~"];
+    let mut tokens = vec![];
+    loop {
+        s = s.trim_start();
+        let Some(c) = s.chars().next() else {
+            return Some(tokens);
+        };
+        if c == '(' || c == ')' {
+            tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            s = &s[1..];
+        } else if let Some(op) = OPERATORS.iter().find(|op| s.starts_with(*op)) {
+            tokens.push(Token::Operator(op));
+            s = &s[op.len()..];
+        } else if c.is_ascii_digit() {
+            let len = s
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(s.len());
+            let literal = &s[..len];
+            tokens.push(Token::Number(match literal.strip_prefix("0x") {
+                Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+                None => literal.parse().ok()?,
+            }));
+            s = &s[len..];
+        } else {
+            return None;
+        }
+    }
+}
+
+fn binding_power(op: &str) -> u8 {
+    match op {
+        "|" => 1,
+        "^" => 2,
+        "&" => 3,
+        "<<" | ">>" => 4,
+        "+" | "-" => 5,
+        _ => unreachable!(),
+    }
+}
+
+fn parse_binary(tokens: &[Token], min_power: u8) -> Option<(i64, &[Token])> {
+    let (mut left, mut rest) = parse_unary(tokens)?;
+    while let Some(Token::Operator(op)) = rest.first() {
+        let power = binding_power(op);
+        if power <= min_power {
+            break;
+        }
+        let (right, r) = parse_binary(&rest[1..], power)?;
+        left = match *op {
+            "|" => left | right,
+            "^" => left ^ right,
+            "&" => left & right,
+            "<<" => left.checked_shl(right as u32)?,
+            ">>" => left.checked_shr(right as u32)?,
+            "+" => left.wrapping_add(right),
+            "-" => left.wrapping_sub(right),
+            _ => unreachable!(),
+        };
+        rest = r;
+    }
+    Some((left, rest))
+}
+
+fn parse_unary(tokens: &[Token]) -> Option<(i64, &[Token])> {
+    match tokens.first()? {
+        Token::Number(n) => Some((*n, &tokens[1..])),
+        Token::Operator("-") => parse_unary(&tokens[1..]).map(|(v, r)| (v.wrapping_neg(), r)),
+        Token::Operator("~") => parse_unary(&tokens[1..]).map(|(v, r)| (!v, r)),
+        Token::Open => {
+            let (value, rest) = parse_binary(&tokens[1..], 0)?;
+            match rest.first()? {
+                Token::Close => Some((value, &rest[1..])),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[test]
+fn normalize_test_macros() {
+    let input = "test_2: li gp, 2; li x1, ((0x00000001) & ((1 << (32 - 1) << 1) - 1)); 1: addi x4, x4, 1; bne x4, x5, 1b; lw x1, 4(x2)\nbne x0, gp, pass; fail: unimp; pass: j pass";
+    let expected = r#"test_2:
+li gp, 2
+li x1, 1
+__local_1_3:
+addi x4, x4, 1
+bne x4, x5, __local_1_3
+lw x1, 4(x2)
+bne x0, gp, pass
+fail:
+unimp
+pass:
+j pass"#;
+    assert_eq!(normalize(input), expected);
+}
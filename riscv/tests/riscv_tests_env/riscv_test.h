@@ -0,0 +1,36 @@
+// Minimal test environment for running the official riscv-tests ISA suite
+// (https://github.com/riscv-software-src/riscv-tests) through powdr.
+// It replaces the suite's `env/p/riscv_test.h`: there are no traps or CSRs,
+// a passing test ends in an infinite loop and a failing test executes
+// `unimp`, which makes witness generation fail.
+#ifndef _ENV_POWDR_H
+#define _ENV_POWDR_H
+
+#define RVTEST_RV64U
+#define RVTEST_RV32U
+#define RVTEST_RV64M
+#define RVTEST_RV32M
+
+#define TESTNUM gp
+
+#define RVTEST_CODE_BEGIN \
+    .text;                \
+    .globl main;          \
+    main:
+
+#define RVTEST_CODE_END
+
+#define RVTEST_PASS \
+    j pass
+
+#define RVTEST_FAIL \
+    unimp
+
+#define EXTRA_DATA
+
+#define RVTEST_DATA_BEGIN \
+    .data;
+
+#define RVTEST_DATA_END
+
+#endif
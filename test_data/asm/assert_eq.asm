@@ -0,0 +1,14 @@
+// Sums up two inputs and asserts that the result equals a third input.
+
+reg pc[@pc];
+reg X[<=];
+reg A;
+reg B;
+
+instr loop { pc' = pc }
+
+A <=X= ${ ("input", 0) };
+A <=X= A + ${ ("input", 1) };
+B <=X= ${ ("input", 2) };
+assert_eq A, B, "sum mismatch";
+loop;
@@ -0,0 +1,38 @@
+// Adds the input B to A four times through function calls and checks the result.
+// Input: B, 4 * B
+
+reg pc[@pc];
+reg X[<=];
+reg A;
+reg B;
+reg C;
+
+pil{
+    col witness XInv;
+    col witness XIsZero;
+    XIsZero  = 1 - X * XInv;
+    XIsZero * X = 0;
+    XIsZero * (1 - XIsZero) = 0;
+}
+
+instr assert_zero X { XIsZero = 1 }
+instr loop { pc' = pc }
+
+B <=X= ${ ("input", 0) };
+call add_b_twice;
+call add_b_twice;
+A <=X= A - ${ ("input", 1) };
+assert_zero A;
+loop;
+
+add_b::
+A <=X= A + B;
+return;
+
+// Calls add_b twice, the return address has to be saved for the nested calls.
+add_b_twice::
+C <=X= return_address;
+call add_b;
+call add_b;
+return_address <=X= C;
+return;
@@ -0,0 +1,26 @@
+// Sums up two inputs. With DEBUG defined, the result is also checked
+// against a third input.
+
+reg pc[@pc];
+reg X[<=];
+reg A;
+reg B;
+
+pil{
+    col witness XInv;
+    col witness XIsZero;
+    XIsZero  = 1 - X * XInv;
+    XIsZero * X = 0;
+    XIsZero * (1 - XIsZero) = 0;
+}
+
+instr assert_zero X { XIsZero = 1 }
+instr loop { pc' = pc }
+
+A <=X= ${ ("input", 0) };
+A <=X= A + ${ ("input", 1) };
+#if DEBUG
+    B <=X= A - ${ ("input", 2) };
+    assert_zero B;
+#endif
+loop;
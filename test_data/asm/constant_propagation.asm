@@ -0,0 +1,20 @@
+// Computes (input + 6) * 2 with constants that `--optimize` can fold and
+// writes that it can remove.
+
+reg pc[@pc];
+reg X[<=];
+reg A;
+reg B;
+
+instr loop { pc' = pc }
+
+A <=X= 1;
+B <=X= 2 * 3;
+A <=X= B + 0;
+B <=X= ${ ("input", 0) };
+B <=X= B + A;
+A <=X= 2;
+B <=X= B * 2;
+A <=X= ${ ("input", 1) };
+assert_eq B, A, "wrong result";
+loop;
@@ -0,0 +1,30 @@
+// Exports two functions, the entry point (0 or 1) selects the one that is run.
+// Input: x, expected result (x + 1 or 2 * x)
+
+reg pc[@pc];
+reg X[<=];
+reg A;
+
+pil{
+    col witness XInv;
+    col witness XIsZero;
+    XIsZero  = 1 - X * XInv;
+    XIsZero * X = 0;
+    XIsZero * (1 - XIsZero) = 0;
+}
+
+instr jmp l: label { pc' = l }
+instr assert_zero X { XIsZero = 1 }
+
+export increment;
+export double;
+
+increment::
+A <=X= ${ ("input", 0) } + 1;
+jmp check;
+double::
+A <=X= 2 * ${ ("input", 0) };
+check::
+A <=X= A - ${ ("input", 1) };
+assert_zero A;
+halt;
@@ -0,0 +1,27 @@
+// Adds the inputs and halts, the rows after the halt are padding.
+// Input: a, b, a + b
+
+reg pc[@pc];
+reg X[<=];
+reg A;
+
+on_halt padding;
+
+pil{
+    col witness XInv;
+    col witness XIsZero;
+    XIsZero  = 1 - X * XInv;
+    XIsZero * X = 0;
+    XIsZero * (1 - XIsZero) = 0;
+    // Counts the rows before the padding.
+    col witness steps;
+    steps' = (1 - first_step') * (steps + 1 - is_padding');
+}
+
+instr assert_zero X { XIsZero = 1 }
+
+A <=X= ${ ("input", 0) };
+A <=X= A + ${ ("input", 1) };
+A <=X= A - ${ ("input", 2) };
+assert_zero A;
+halt;
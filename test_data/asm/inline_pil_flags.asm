@@ -0,0 +1,29 @@
+// Adds a range check to an instruction through its flag in inline PIL.
+// Input: a byte
+
+reg pc[@pc];
+reg X[<=];
+reg A;
+
+pil{
+    col witness XInv;
+    col witness XIsZero;
+    XIsZero  = 1 - X * XInv;
+    XIsZero * X = 0;
+    XIsZero * (1 - XIsZero) = 0;
+}
+
+instr store_byte X { A' = X }
+instr assert_zero X { XIsZero = 1 }
+instr loop { pc' = pc }
+
+pil{
+    col fixed BYTE(i) { i & 0xff };
+    // The value stored by store_byte has to be a byte.
+    instr.store_byte { X } in { BYTE };
+}
+
+store_byte ${ ("input", 0) };
+A <=X= A - ${ ("input", 0) };
+assert_zero A;
+loop;
@@ -0,0 +1,36 @@
+// Dispatches on the input through a jump table.
+// Input: x (0, 1 or 2), y (the expected result)
+
+reg pc[@pc];
+reg X[<=];
+reg A;
+reg B;
+
+pil{
+    col witness XInv;
+    col witness XIsZero;
+    XIsZero  = 1 - X * XInv;
+    XIsZero * X = 0;
+    XIsZero * (1 - XIsZero) = 0;
+}
+
+instr jmp l: label { pc' = l }
+instr assert_zero X { XIsZero = 1 }
+instr loop { pc' = pc }
+
+jump_table dispatch X: case_zero, case_one, case_two;
+
+A <=X= ${ ("input", 0) };
+dispatch A;
+case_zero::
+B <=X= 10;
+jmp end;
+case_one::
+B <=X= 20;
+jmp end;
+case_two::
+B <=X= 30;
+end::
+A <=X= B - ${ ("input", 1) };
+assert_zero A;
+loop;
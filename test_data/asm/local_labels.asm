@@ -0,0 +1,56 @@
+// Two functions that use the same local labels.
+// Input: x, y, x * y, x * x
+
+reg pc[@pc];
+reg X[<=];
+reg A;
+reg B;
+reg C;
+reg D;
+
+pil{
+    col witness XInv;
+    col witness XIsZero;
+    XIsZero  = 1 - X * XInv;
+    XIsZero * X = 0;
+    XIsZero * (1 - XIsZero) = 0;
+}
+
+instr jmpz X, l: label { pc' = XIsZero * l + (1 - XIsZero) * (pc + 1) }
+instr jmp l: label { pc' = l }
+instr assert_zero X { XIsZero = 1 }
+instr loop { pc' = pc }
+
+A <=X= ${ ("input", 0) };
+B <=X= ${ ("input", 1) };
+call mul;
+C <=X= C - ${ ("input", 2) };
+assert_zero C;
+call square;
+D <=X= D - ${ ("input", 3) };
+assert_zero D;
+loop;
+
+// Computes C = A * B by repeated addition.
+mul::
+C <=X= 0;
+D <=X= B;
+.loop::
+jmpz D, .end;
+C <=X= C + A;
+D <=X= D - 1;
+jmp .loop;
+.end::
+return;
+
+// Computes D = A * A by repeated addition.
+square::
+D <=X= 0;
+C <=X= A;
+.loop::
+jmpz C, .end;
+D <=X= D + A;
+C <=X= C - 1;
+jmp .loop;
+.end::
+return;
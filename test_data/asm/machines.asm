@@ -0,0 +1,25 @@
+// Checks the ranges of the inputs with lookups into two instances of a machine.
+// Input: a byte, a nibble
+
+reg pc[@pc];
+reg X[<=];
+reg A;
+
+// The values from 0 to max - 1, repeated.
+machine Range(max) {
+    col fixed VALUE(i) { i % max };
+}
+
+byte: Range(256);
+nibble: Range(16);
+
+instr assert_byte X { { X } in { byte.VALUE } }
+instr assert_nibble X { { X } in { nibble.VALUE } }
+instr loop { pc' = pc }
+
+A <=X= ${ ("input", 0) };
+assert_byte A;
+A <=X= ${ ("input", 1) };
+assert_nibble A;
+assert_byte A;
+loop;
@@ -0,0 +1,54 @@
+// Computes 2^n with a loop inside a macro and sums four inputs with a repeat block.
+// Input: n, 2^n, x0, x1, x2, x3, x0 + x1 + x2 + x3
+
+reg pc[@pc];
+reg X[<=];
+reg A;
+reg B;
+reg CNT;
+
+pil{
+    col witness XInv;
+    col witness XIsZero;
+    XIsZero  = 1 - X * XInv;
+    XIsZero * X = 0;
+    XIsZero * (1 - XIsZero) = 0;
+}
+
+instr jmpz X, l: label { pc' = XIsZero * l + (1 - XIsZero) * (pc + 1) }
+instr jmp l: label { pc' = l }
+instr assert_zero X { XIsZero = 1 }
+instr loop { pc' = pc }
+
+// Multiplies R by 2 `count` times, the label is unique in each expansion.
+%macro double_n(R, count) {
+    CNT <=X= count;
+    start::
+    jmpz CNT, end;
+    R <=X= 2 * R;
+    CNT <=X= CNT - 1;
+    jmp start;
+    end::
+}
+
+%macro assert_eq(R, value) {
+    R <=X= R - value;
+    assert_zero R;
+}
+
+A <=X= 1;
+double_n A, ${ ("input", 0) };
+assert_eq A, ${ ("input", 1) };
+
+A <=X= 0;
+%repeat i in 2..6 {
+    B <=X= ${ ("input", i) };
+    A <=X= A + B;
+}
+assert_eq A, ${ ("input", 6) };
+
+B <=X= 3;
+double_n B, 2;
+assert_eq B, 12;
+
+loop;
@@ -0,0 +1,47 @@
+// Uses instructions with two outputs, which are written in a single row.
+// Input: x, y, x + y, x - y, the low and high byte of x
+
+reg pc[@pc];
+reg X[<=];
+reg Y[<=];
+reg Z[<=];
+reg W[<=];
+reg A;
+reg B;
+
+pil{
+    col witness XInv;
+    col witness XIsZero;
+    XIsZero  = 1 - X * XInv;
+    XIsZero * X = 0;
+    XIsZero * (1 - XIsZero) = 0;
+}
+
+instr sum_diff X, Y -> Z, W { Z = X + Y, W = X - Y }
+// Splits a 16 bit value into its low and high byte.
+instr split X -> Z, W { X = Z + 0x100 * W, Z = LOW, W = HIGH }
+pil{
+    col fixed BYTE(i) { i & 0xff };
+    col witness LOW;
+    col witness HIGH;
+    { LOW } in { BYTE };
+    { HIGH } in { BYTE };
+}
+instr assert_zero X { XIsZero = 1 }
+instr loop { pc' = pc }
+
+A <=X= ${ ("input", 0) };
+B <=X= ${ ("input", 1) };
+A, B <=Z, W= sum_diff(A, B);
+A <=X= A - ${ ("input", 2) };
+assert_zero A;
+B <=X= B - ${ ("input", 3) };
+assert_zero B;
+
+A <=X= ${ ("input", 0) };
+A, B <== split(A);
+A <=X= A - ${ ("input", 4) };
+assert_zero A;
+B <=X= B - ${ ("input", 5) };
+assert_zero B;
+loop;
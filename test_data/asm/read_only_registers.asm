@@ -0,0 +1,29 @@
+// Uses a zero register and a configuration register that cannot be written.
+// Input: x
+
+reg pc[@pc];
+reg X[<=];
+reg A;
+reg ZERO[@readonly];
+reg STEP[@readonly = 3];
+
+pil{
+    col witness XInv;
+    col witness XIsZero;
+    XIsZero  = 1 - X * XInv;
+    XIsZero * X = 0;
+    XIsZero * (1 - XIsZero) = 0;
+}
+
+instr assert_zero X { XIsZero = 1 }
+instr loop { pc' = pc }
+
+A <=X= ${ ("input", 0) };
+A <=X= A + STEP;
+A <=X= A - 3 + ZERO;
+A <=X= A - ${ ("input", 0) };
+assert_zero A;
+assert_zero ZERO;
+A <=X= STEP - 3;
+assert_zero A;
+loop;
@@ -0,0 +1,31 @@
+// Uses registers that are reset to zero or unconstrained when they are not written.
+// Input: x
+
+reg pc[@pc];
+reg X[<=];
+reg A;
+reg R[@reset];
+reg F[@free];
+
+pil{
+    col witness XInv;
+    col witness XIsZero;
+    XIsZero  = 1 - X * XInv;
+    XIsZero * X = 0;
+    XIsZero * (1 - XIsZero) = 0;
+}
+
+instr assert_zero X { XIsZero = 1 }
+instr loop { pc' = pc }
+
+A <=X= ${ ("input", 0) };
+R <=X= A;
+// R still holds the value in this row, A keeps its value.
+A <=X= R - A;
+assert_zero A;
+// R is zero now.
+assert_zero R;
+F <=X= 7;
+A <=X= F - 7;
+assert_zero A;
+loop;
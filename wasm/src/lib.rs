@@ -0,0 +1,183 @@
+//! A JavaScript API for the PIL analyzer and the executor, to analyze PIL and
+//! generate witnesses in the browser. Build it with
+//! `cargo build -p wasm --release --target wasm32-unknown-unknown` and
+//! generate the bindings with `wasm-bindgen`.
+//!
+//! The files are passed in as `Sources` instead of being read from the file
+//! system. Field elements are returned as decimal strings, since they do not
+//! fit into JavaScript numbers. Errors in the PIL files abort the call with
+//! an exception.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use json::JsonValue;
+use number::FieldElement;
+use pil_analyzer::Analyzed;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// Runs the body with `$T` set to the field with the given name.
+macro_rules! with_field {
+    ($field:expr, $T:ident => $body:expr) => {
+        match $field {
+            "gl" => {
+                type $T = ::number::GoldilocksField;
+                Ok($body)
+            }
+            "bn254" => {
+                type $T = ::number::Bn254Field;
+                Ok($body)
+            }
+            "bb" => {
+                type $T = ::number::BabyBearField;
+                Ok($body)
+            }
+            field => Err(format!("Unknown field {field}, expected gl, bn254 or bb.")),
+        }
+    };
+}
+
+/// The PIL files by path. Includes are resolved relative to the including file.
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct Sources {
+    files: HashMap<PathBuf, String>,
+}
+
+#[wasm_bindgen]
+impl Sources {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Sources {
+        Default::default()
+    }
+
+    /// Adds or replaces the file at the path.
+    pub fn add(&mut self, path: &str, contents: &str) {
+        self.files.insert(PathBuf::from(path), contents.to_string());
+    }
+}
+
+/// Analyzes the main file and returns the analyzed PIL.
+#[wasm_bindgen]
+pub fn analyze(field: &str, main: &str, sources: &Sources) -> Result<String, String> {
+    with_field!(field, T => analyze_sources::<T>(main, sources)?.to_string())
+}
+
+/// Returns the statistics of the main file as a table, see `pil_analyzer::stats`.
+#[wasm_bindgen]
+pub fn stats(field: &str, main: &str, sources: &Sources) -> Result<String, String> {
+    with_field!(field, T => {
+        let analyzed = analyze_sources::<T>(main, sources)?;
+        pil_analyzer::stats::stats(&analyzed).to_string()
+    })
+}
+
+/// Generates the fixed and witness columns of the main file with the given
+/// prover inputs and returns them as JSON:
+/// `{"degree": 4, "fixed": {"Main.FIRST": ["1", "0", ...]}, "witness": {...}}`.
+#[wasm_bindgen]
+pub fn generate(
+    field: &str,
+    main: &str,
+    sources: &Sources,
+    inputs: Vec<u64>,
+) -> Result<String, String> {
+    with_field!(field, T => generate_in_field::<T>(main, sources, inputs)?)
+}
+
+fn analyze_sources<T: FieldElement>(main: &str, sources: &Sources) -> Result<Analyzed<T>, String> {
+    let main = Path::new(main);
+    if !sources.files.contains_key(main) {
+        return Err(format!("File {} not found.", main.display()));
+    }
+    pil_analyzer::analyze_sources(main, sources.files.clone()).map_err(|e| e.to_string())
+}
+
+fn generate_in_field<T: FieldElement>(
+    main: &str,
+    sources: &Sources,
+    inputs: Vec<u64>,
+) -> Result<String, String> {
+    let analyzed = analyze_sources::<T>(main, sources)?;
+    let (fixed, degree) = executor::constant_evaluator::generate(&analyzed)?;
+    let witness = executor::witgen::generate(
+        &analyzed,
+        degree,
+        &fixed,
+        Some(executor::witgen::inputs_query_callback(
+            inputs.into_iter().map(T::from).collect(),
+            0,
+        )),
+    );
+    let columns = |columns: &[(&str, Vec<T>)]| {
+        let mut object = JsonValue::new_object();
+        for (name, values) in columns {
+            object[*name] = values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .into();
+        }
+        object
+    };
+    let mut result = JsonValue::new_object();
+    result["degree"] = degree.into();
+    result["fixed"] = columns(&fixed);
+    result["witness"] = columns(&witness);
+    Ok(result.dump())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sources() -> Sources {
+        let mut sources = Sources::new();
+        sources.add(
+            "main.pil",
+            "include \"fib.pil\";\nnamespace Main(4);\ncol witness x;\nx = 7;",
+        );
+        sources.add(
+            "fib.pil",
+            "namespace Fib(4);
+col fixed ISLAST(i) { match i { 3 => 1, _ => 0 } };
+col witness x, y;
+(1 - ISLAST) * (x' - y) = 0;
+(1 - ISLAST) * (y' - (x + y)) = 0;
+ISLAST * (x' - 1) = 0;
+ISLAST * (y' - 1) = 0;",
+        );
+        sources
+    }
+
+    #[test]
+    fn generate_fibonacci() {
+        let result = generate("gl", "main.pil", &sources(), vec![]).unwrap();
+        let result = json::parse(&result).unwrap();
+        assert_eq!(result["degree"], 4);
+        assert_eq!(
+            result["fixed"]["Fib.ISLAST"],
+            json::array!["0", "0", "0", "1"]
+        );
+        assert_eq!(result["witness"]["Fib.y"], json::array!["1", "2", "3", "5"]);
+        assert_eq!(
+            result["witness"]["Main.x"],
+            json::array!["7", "7", "7", "7"]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            analyze("m61", "main.pil", &sources()).unwrap_err(),
+            "Unknown field m61, expected gl, bn254 or bb."
+        );
+        assert_eq!(
+            stats("gl", "other.pil", &sources()).unwrap_err(),
+            "File other.pil not found."
+        );
+        assert!(stats("bb", "main.pil", &sources())
+            .unwrap()
+            .starts_with("namespace"));
+    }
+}